@@ -21,12 +21,17 @@ fn natives<M: Measurement + 'static>(c: &mut Criterion<M>) {
     bench(c, "natives");
 }
 
+fn fields<M: Measurement + 'static>(c: &mut Criterion<M>) {
+    bench(c, "fields");
+}
+
 criterion_group!(
     name = vm_benches;
     config = cpu_time_measurement();
     targets = arith,
     call,
-    natives
+    natives,
+    fields
 );
 
 criterion_main!(vm_benches);
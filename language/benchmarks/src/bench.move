@@ -109,4 +109,33 @@ module 0x1::Bench {
             i = i + 1;
         }
     }
+
+    //
+    // `fields` benchmark
+    //
+    struct Point has copy, drop { x: u64, y: u64 }
+
+    struct Segment has copy, drop { from: Point, to: Point }
+
+    fun length(s: &Segment): u64 {
+        (s.to.x - s.from.x) + (s.to.y - s.from.y)
+    }
+
+    fun stretch(s: &mut Segment, d: u64) {
+        s.to.x = s.to.x + d;
+        s.to.y = s.to.y + d;
+    }
+
+    public fun fields() {
+        let s = Segment { from: Point { x: 0, y: 0 }, to: Point { x: 1, y: 1 } };
+        let i = 0;
+        // 3000 is the number of loops to make the benchmark run for a couple of minutes, which is an eternity.
+        // Adjust according to your needs, it's just a reference
+        while (i < 3000) {
+            stretch(&mut s, 1);
+            check(length(&s) == 2 * (i + 2), 300);
+            check(s.from.x == 0, 301);
+            i = i + 1;
+        };
+    }
 }
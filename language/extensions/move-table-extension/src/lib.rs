@@ -18,6 +18,7 @@ use move_core_types::{
     vm_status::StatusCode,
 };
 use move_vm_runtime::{
+    native_extensions::SavepointExtension,
     native_functions,
     native_functions::{NativeContext, NativeFunction, NativeFunctionTable},
 };
@@ -105,6 +106,9 @@ pub struct NativeTableContext<'a> {
     table_data: RefCell<TableData>,
 }
 
+/// The saved state of a NativeTableContext, see `SavepointExtension`.
+pub struct TableSavepoint(TableData);
+
 // See stdlib/Error.move
 const _ECATEGORY_INVALID_STATE: u8 = 0;
 const ECATEGORY_INVALID_ARGUMENT: u8 = 7;
@@ -199,7 +203,33 @@ impl<'a> NativeTableContext<'a> {
     }
 }
 
+impl<'a> SavepointExtension<'a> for NativeTableContext<'a> {
+    type Savepoint = TableSavepoint;
+
+    fn savepoint(&self) -> PartialVMResult<TableSavepoint> {
+        Ok(TableSavepoint(self.table_data.borrow().copy()?))
+    }
+
+    fn rollback_to(&mut self, savepoint: TableSavepoint) {
+        *self.table_data.get_mut() = savepoint.0;
+    }
+}
+
 impl TableData {
+    /// Returns a deep copy of the table data.
+    fn copy(&self) -> PartialVMResult<Self> {
+        let tables = self
+            .tables
+            .iter()
+            .map(|(handle, table)| Ok((*handle, table.copy()?)))
+            .collect::<PartialVMResult<_>>()?;
+        Ok(Self {
+            new_tables: self.new_tables.clone(),
+            removed_tables: self.removed_tables.clone(),
+            tables,
+        })
+    }
+
     /// Gets or creates a new table in the TableData. This initializes information about
     /// the table, like the type layout for keys and values.
     fn get_or_create_table(
@@ -227,6 +257,20 @@ impl TableData {
 }
 
 impl Table {
+    fn copy(&self) -> PartialVMResult<Self> {
+        let content = self
+            .content
+            .iter()
+            .map(|(key, gv)| Ok((key.clone(), gv.copy_value()?)))
+            .collect::<PartialVMResult<_>>()?;
+        Ok(Self {
+            handle: self.handle,
+            key_layout: self.key_layout.clone(),
+            value_layout: self.value_layout.clone(),
+            content,
+        })
+    }
+
     fn get_or_create_global_value(
         &mut self,
         context: &NativeTableContext,
@@ -24,6 +24,11 @@ export class Configuration {
         return JSON.stringify(this.configuration);
     }
 
+    /** Whether to run the Move Prover on a file whenever it is saved. */
+    get proverOnSave(): boolean {
+        return this.configuration.get<boolean>('prover.onSave', false);
+    }
+
     /** The path to the move-analyzer executable. */
     get serverPath(): string {
         const defaultName = 'move-analyzer';
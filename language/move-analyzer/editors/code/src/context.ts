@@ -123,6 +123,9 @@ export class Context {
         const clientOptions: lc.LanguageClientOptions = {
             documentSelector: [{ scheme: 'file', language: 'move' }],
             traceOutputChannel,
+            initializationOptions: {
+                proveOnSave: this.configuration.proverOnSave,
+            },
         };
 
         const client = new lc.LanguageClient(
@@ -33,6 +33,22 @@ async function serverVersion(context: Readonly<Context>): Promise<void> {
     }
 }
 
+/**
+ * An extension command that runs the Move Prover on the file in the active editor.
+ *
+ * The language server provides the `move-analyzer.verify` command, which the language client
+ * registers with VS Code. The server reports the results as diagnostics and code lenses.
+ */
+async function verifyFile(): Promise<void> {
+    const editor = vscode.window.activeTextEditor;
+    if (editor === undefined || editor.document.languageId !== 'move') {
+        await vscode.window.showErrorMessage('Open a Move file to verify it.');
+        return;
+    }
+    await editor.document.save();
+    await vscode.commands.executeCommand('move-analyzer.verify', editor.document.uri.toString());
+}
+
 /**
  * The entry point to this VS Code extension.
  *
@@ -76,4 +92,5 @@ export async function activate(extensionContext: Readonly<vscode.ExtensionContex
     context.registerCommand('textDocumentDocumentSymbol', commands.textDocumentDocumentSymbol);
     context.registerCommand('textDocumentHover', commands.textDocumentHover);
     context.registerCommand('textDocumentCompletion', commands.textDocumentCompletion);
+    context.registerCommand('verifyFile', verifyFile);
 }
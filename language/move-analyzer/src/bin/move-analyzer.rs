@@ -7,9 +7,10 @@ use clap::Parser;
 use crossbeam::channel::{bounded, select};
 use lsp_server::{Connection, Message, Notification, Request, Response};
 use lsp_types::{
-    notification::Notification as _, request::Request as _, CompletionOptions, Diagnostic,
-    HoverProviderCapability, OneOf, SaveOptions, TextDocumentSyncCapability, TextDocumentSyncKind,
-    TextDocumentSyncOptions, TypeDefinitionProviderCapability, WorkDoneProgressOptions,
+    notification::Notification as _, request::Request as _, CodeLensOptions, CompletionOptions,
+    Diagnostic, ExecuteCommandOptions, HoverProviderCapability, OneOf, SaveOptions,
+    TextDocumentSyncCapability, TextDocumentSyncKind, TextDocumentSyncOptions,
+    TypeDefinitionProviderCapability, WorkDoneProgressOptions,
 };
 use std::{
     collections::BTreeMap,
@@ -21,7 +22,7 @@ use std::{
 use move_analyzer::{
     completion::on_completion_request,
     context::Context,
-    symbols,
+    prover, symbols,
     vfs::{on_text_document_sync_notification, VirtualFileSystem},
 };
 use move_symbol_pool::Symbol;
@@ -50,10 +51,12 @@ fn main() {
 
     let (connection, io_threads) = Connection::stdio();
     let symbols = Arc::new(Mutex::new(symbols::Symbolicator::empty_symbols()));
+    let prover_results = Arc::new(Mutex::new(prover::ProverResults::default()));
     let mut context = Context {
         connection,
         files: VirtualFileSystem::default(),
         symbols: symbols.clone(),
+        prover_results: prover_results.clone(),
     };
 
     let (id, client_response) = context
@@ -107,17 +110,45 @@ fn main() {
         )),
         references_provider: Some(OneOf::Left(symbols::DEFS_AND_REFS_SUPPORT)),
         document_symbol_provider: Some(OneOf::Left(true)),
+        // The server shows the outcome of verifying each function as a code lens, which runs the
+        // prover's verify command when clicked.
+        code_lens_provider: Some(CodeLensOptions {
+            resolve_provider: Some(false),
+        }),
+        execute_command_provider: Some(ExecuteCommandOptions {
+            commands: vec![prover::VERIFY_COMMAND.to_string()],
+            work_done_progress_options: WorkDoneProgressOptions {
+                work_done_progress: None,
+            },
+        }),
         ..Default::default()
     })
     .expect("could not serialize server capabilities");
 
+    let initialize_params: lsp_types::InitializeParams =
+        serde_json::from_value(client_response).expect("could not deserialize client capabilities");
+
+    // The prover runs on request, and in addition whenever a file is saved if the client sets the
+    // `proveOnSave` initialization option.
+    let prove_on_save = initialize_params
+        .initialization_options
+        .as_ref()
+        .and_then(|options| options.get("proveOnSave"))
+        .and_then(|on_save| on_save.as_bool())
+        .unwrap_or(false);
+    let code_lens_refresh = initialize_params
+        .capabilities
+        .workspace
+        .as_ref()
+        .and_then(|workspace| workspace.code_lens.as_ref())
+        .and_then(|code_lens| code_lens.refresh_support)
+        .unwrap_or(false);
+    let (prover_sender, prover_receiver) = bounded::<Result<BTreeMap<Symbol, Vec<Diagnostic>>>>(0);
+    let prover_runner = prover::ProverRunner::new(prover_results, prover_sender, prove_on_save);
+
     let (diag_sender, diag_receiver) = bounded::<Result<BTreeMap<Symbol, Vec<Diagnostic>>>>(0);
     let mut symbolicator_runner = symbols::SymbolicatorRunner::idle();
     if symbols::DEFS_AND_REFS_SUPPORT {
-        let initialize_params: lsp_types::InitializeParams =
-            serde_json::from_value(client_response)
-                .expect("could not deserialize client capabilities");
-
         symbolicator_runner = symbols::SymbolicatorRunner::new(symbols.clone(), diag_sender);
 
         // If initialization information from the client contains a path to the directory being
@@ -156,6 +187,11 @@ fn main() {
         )
         .expect("could not finish connection initialization");
 
+    // Diagnostics are published per file, replacing previously published ones, so the latest
+    // diagnostics of the compiler and the prover are kept to publish them together.
+    let mut compiler_diags = BTreeMap::new();
+    let mut prover_diags = BTreeMap::new();
+    let mut code_lens_refreshes = 0;
     loop {
         select! {
             recv(diag_receiver) -> message => {
@@ -164,15 +200,8 @@ fn main() {
                         match result {
                             Ok(diags) => {
                                 for (k, v) in diags {
-                                    let url = Url::from_file_path(Path::new(&k.to_string())).unwrap();
-                                    let params = lsp_types::PublishDiagnosticsParams::new(url, v, None);
-                                    let notification = Notification::new(lsp_types::notification::PublishDiagnostics::METHOD.to_string(), params);
-                                    if let Err(err) = context
-                                        .connection
-                                        .sender
-                                        .send(lsp_server::Message::Notification(notification)) {
-                                            eprintln!("could not send diagnostics response: {:?}", err);
-                                        };
+                                    compiler_diags.insert(k, v);
+                                    publish_diagnostics(&context, k, &compiler_diags, &prover_diags);
                                 }
                             },
                             Err(err) => {
@@ -195,9 +224,47 @@ fn main() {
                     Err(error) => eprintln!("symbolicator message error: {:?}", error),
                 }
             },
+            recv(prover_receiver) -> message => {
+                match message {
+                    Ok(Ok(diags)) => {
+                        for (k, v) in diags {
+                            prover_diags.insert(k, v);
+                            publish_diagnostics(&context, k, &compiler_diags, &prover_diags);
+                        }
+                        if code_lens_refresh {
+                            // ask the client to fetch the code lenses showing the new results
+                            code_lens_refreshes += 1;
+                            let request = Request::new(
+                                format!("code-lens-refresh-{}", code_lens_refreshes).into(),
+                                lsp_types::request::CodeLensRefresh::METHOD.to_string(),
+                                serde_json::Value::Null,
+                            );
+                            if let Err(err) = context
+                                .connection
+                                .sender
+                                .send(lsp_server::Message::Request(request)) {
+                                    eprintln!("could not send code lens refresh request: {:?}", err);
+                                };
+                        }
+                    },
+                    Ok(Err(err)) => {
+                        let typ = lsp_types::MessageType::Error;
+                        let message = format!("move-prover: {err}");
+                        let params = lsp_types::ShowMessageParams { typ, message };
+                        let notification = Notification::new(lsp_types::notification::ShowMessage::METHOD.to_string(), params);
+                        if let Err(err) = context
+                            .connection
+                            .sender
+                            .send(lsp_server::Message::Notification(notification)) {
+                                eprintln!("could not send prover error response: {:?}", err);
+                            };
+                    },
+                    Err(error) => eprintln!("prover message error: {:?}", error),
+                }
+            },
             recv(context.connection.receiver) -> message => {
                 match message {
-                    Ok(Message::Request(request)) => on_request(&context, &request),
+                    Ok(Message::Request(request)) => on_request(&context, &prover_runner, &request),
                     Ok(Message::Response(response)) => on_response(&context, &response),
                     Ok(Message::Notification(notification)) => {
                         match notification.method.as_str() {
@@ -207,7 +274,7 @@ fn main() {
                                 // It ought to, especially once it begins processing requests that may
                                 // take a long time to respond to.
                             }
-                            _ => on_notification(&mut context, &symbolicator_runner, &notification),
+                            _ => on_notification(&mut context, &symbolicator_runner, &prover_runner, &notification),
                         }
                     }
                     Err(error) => eprintln!("IDE message error: {:?}", error),
@@ -218,10 +285,40 @@ fn main() {
 
     io_threads.join().expect("I/O threads could not finish");
     symbolicator_runner.quit();
+    prover_runner.quit();
     eprintln!("Shut down language server '{}'.", exe);
 }
 
-fn on_request(context: &Context, request: &Request) {
+/// Publishes the diagnostics of the compiler and the prover for the given file.
+fn publish_diagnostics(
+    context: &Context,
+    file: Symbol,
+    compiler_diags: &BTreeMap<Symbol, Vec<Diagnostic>>,
+    prover_diags: &BTreeMap<Symbol, Vec<Diagnostic>>,
+) {
+    let diags = compiler_diags
+        .get(&file)
+        .into_iter()
+        .chain(prover_diags.get(&file))
+        .flatten()
+        .cloned()
+        .collect();
+    let url = Url::from_file_path(Path::new(&file.to_string())).unwrap();
+    let params = lsp_types::PublishDiagnosticsParams::new(url, diags, None);
+    let notification = Notification::new(
+        lsp_types::notification::PublishDiagnostics::METHOD.to_string(),
+        params,
+    );
+    if let Err(err) = context
+        .connection
+        .sender
+        .send(lsp_server::Message::Notification(notification))
+    {
+        eprintln!("could not send diagnostics response: {:?}", err);
+    };
+}
+
+fn on_request(context: &Context, prover_runner: &prover::ProverRunner, request: &Request) {
     match request.method.as_str() {
         lsp_types::request::Completion::METHOD => {
             on_completion_request(context, request, &context.symbols.lock().unwrap())
@@ -241,6 +338,12 @@ fn on_request(context: &Context, request: &Request) {
         lsp_types::request::DocumentSymbolRequest::METHOD => {
             symbols::on_document_symbol_request(context, request, &context.symbols.lock().unwrap());
         }
+        lsp_types::request::CodeLensRequest::METHOD => {
+            prover::on_code_lens_request(context, request, &context.prover_results.lock().unwrap());
+        }
+        lsp_types::request::ExecuteCommand::METHOD => {
+            prover::on_execute_command_request(context, request, prover_runner);
+        }
         _ => eprintln!("handle request '{}' from client", request.method),
     }
 }
@@ -252,6 +355,7 @@ fn on_response(_context: &Context, _response: &Response) {
 fn on_notification(
     context: &mut Context,
     symbolicator_runner: &symbols::SymbolicatorRunner,
+    prover_runner: &prover::ProverRunner,
     notification: &Notification,
 ) {
     match notification.method.as_str() {
@@ -263,7 +367,16 @@ fn on_notification(
                 &mut context.files,
                 symbolicator_runner,
                 notification,
-            )
+            );
+            if notification.method == lsp_types::notification::DidSaveTextDocument::METHOD
+                && prover_runner.on_save()
+            {
+                let parameters = serde_json::from_value::<lsp_types::DidSaveTextDocumentParams>(
+                    notification.params.clone(),
+                )
+                .expect("could not deserialize notification");
+                prover_runner.run(parameters.text_document.uri.to_file_path().unwrap(), None);
+            }
         }
         _ => eprintln!("handle notification '{}' from client", notification.method),
     }
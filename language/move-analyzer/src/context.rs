@@ -2,7 +2,7 @@
 // Copyright (c) The Move Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::{symbols::Symbols, vfs::VirtualFileSystem};
+use crate::{prover::ProverResults, symbols::Symbols, vfs::VirtualFileSystem};
 use lsp_server::Connection;
 use std::sync::{Arc, Mutex};
 
@@ -14,4 +14,6 @@ pub struct Context {
     pub files: VirtualFileSystem,
     /// Symbolication information
     pub symbols: Arc<Mutex<Symbols>>,
+    /// Results of the latest prover runs
+    pub prover_results: Arc<Mutex<ProverResults>>,
 }
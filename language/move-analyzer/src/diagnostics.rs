@@ -1,13 +1,23 @@
 // Copyright (c) The Move Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::utils::get_loc;
-use codespan_reporting::{diagnostic::Severity, files::SimpleFiles};
-use lsp_types::{Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location, Range};
+use crate::utils::{get_loc, get_model_range};
+use codespan::FileId;
+use codespan_reporting::{
+    diagnostic::{Label, LabelStyle, Severity},
+    files::SimpleFiles,
+};
+use lsp_types::{
+    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location, Position, Range,
+};
 use move_command_line_common::files::FileHash;
 use move_ir_types::location::Loc;
+use move_model::model::{GlobalEnv, Loc as ModelLoc};
 use move_symbol_pool::Symbol;
-use std::collections::{BTreeMap, HashMap};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+};
 use url::Url;
 
 /// Converts diagnostics from the codespan format to the format understood by the language server.
@@ -87,6 +97,120 @@ pub fn lsp_empty_diagnostics(
     lsp_diagnostics
 }
 
+/// Converts a diagnostic reported by the move model (e.g., by the prover) to the format understood
+/// by the language server. Returns it together with the path of the file it belongs to and the
+/// location of its primary label. Secondary labels become related information, and so do the
+/// steps of a counterexample's execution trace, which the prover reports as notes.
+pub fn lsp_model_diagnostic(
+    env: &GlobalEnv,
+    diag: &codespan_reporting::diagnostic::Diagnostic<FileId>,
+) -> Option<(PathBuf, ModelLoc, Diagnostic)> {
+    let label_loc = |label: &Label<FileId>| {
+        ModelLoc::new(
+            label.file_id,
+            (label.range.start as u32..label.range.end as u32).into(),
+        )
+    };
+    let loc = label_loc(
+        diag.labels
+            .iter()
+            .find(|label| label.style == LabelStyle::Primary)?,
+    );
+    let (path, range) = get_model_range(env, &loc)?;
+    let mut related_info: Vec<_> = diag
+        .labels
+        .iter()
+        .filter(|label| label.style == LabelStyle::Secondary)
+        .filter_map(|label| {
+            let (lpath, lrange) = get_model_range(env, &label_loc(label))?;
+            Some(DiagnosticRelatedInformation {
+                location: Location::new(Url::from_file_path(lpath).ok()?, lrange),
+                message: label.message.clone(),
+            })
+        })
+        .collect();
+    let (preamble, trace) = lsp_trace(&diag.notes);
+    related_info.extend(trace);
+    let mut message = diag.message.clone();
+    for line in preamble {
+        message.push('\n');
+        message.push_str(&line);
+    }
+    let diagnostic = Diagnostic::new(
+        range,
+        Some(severity(diag.severity)),
+        None,
+        None,
+        message,
+        if related_info.is_empty() {
+            None
+        } else {
+            Some(related_info)
+        },
+        None,
+    );
+    Some((path, loc, diagnostic))
+}
+
+/// Splits the notes of a prover diagnostic into the lines preceding the execution trace (e.g., the
+/// bindings related to the failure) and the steps of the trace. Each step starts with a line of the
+/// form `at <file>:<line>: <function>`, followed by indented lines with the values observed there.
+fn lsp_trace(notes: &[String]) -> (Vec<String>, Vec<DiagnosticRelatedInformation>) {
+    let mut preamble = vec![];
+    let mut steps: Vec<(Location, String, Vec<String>)> = vec![];
+    for line in notes.iter().flat_map(|note| note.lines()) {
+        let entry = line.trim();
+        if entry.is_empty() || entry == "Execution Trace:" {
+            continue;
+        }
+        if let Some((location, function)) = entry.strip_prefix("at ").and_then(trace_location) {
+            steps.push((location, function.to_string(), vec![]));
+        } else if let Some((_, _, values)) = steps.last_mut() {
+            // values are indented by 8 spaces, deeper indentation continues the previous value
+            match values.last_mut() {
+                Some(value) if line.starts_with("         ") => {
+                    value.push(' ');
+                    value.push_str(entry);
+                }
+                _ => values.push(entry.to_string()),
+            }
+        } else {
+            preamble.push(entry.to_string());
+        }
+    }
+    let trace = steps
+        .into_iter()
+        .map(|(location, function, values)| {
+            let message = if values.is_empty() {
+                function
+            } else if function.is_empty() {
+                values.join(", ")
+            } else {
+                format!("{}: {}", function, values.join(", "))
+            };
+            DiagnosticRelatedInformation { location, message }
+        })
+        .collect();
+    (preamble, trace)
+}
+
+/// Parses the `<file>:<line>[: <function>]` part of a trace step.
+fn trace_location(step: &str) -> Option<(Location, &str)> {
+    let (file_line, function) = match step.find(": ") {
+        Some(i) => (&step[..i], &step[i + 2..]),
+        None => (step, ""),
+    };
+    let (file, line) = file_line.rsplit_once(':')?;
+    // lines in the trace are 1-based
+    let line = line.parse::<u32>().ok()?.checked_sub(1)?;
+    let path = dunce::canonicalize(file).ok()?;
+    let pos = Position::new(line, 0);
+    Some((
+        Location::new(Url::from_file_path(path).ok()?, Range::new(pos, pos)),
+        function,
+    ))
+}
+
 /// Converts diagnostic severity level from the codespan format to the format understood by the
 /// language server.
 fn severity(s: Severity) -> DiagnosticSeverity {
@@ -98,3 +222,33 @@ fn severity(s: Severity) -> DiagnosticSeverity {
         Severity::Help => DiagnosticSeverity::Hint,
     }
 }
+
+#[test]
+/// Tests if the execution trace of a prover diagnostic is turned into related information.
+fn trace_test() {
+    let mut path = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("tests/symbols/sources/M1.move");
+    let fpath = dunce::canonicalize(&path).unwrap();
+    let fname = fpath.to_string_lossy();
+
+    let notes = vec![
+        "Related Bindings: ".to_string(),
+        "        `x` = 1".to_string(),
+        "Execution Trace:".to_string(),
+        format!("    at {}:12: f", fname),
+        "        x = 1".to_string(),
+        "        v =".to_string(),
+        "          vector{1, 2}".to_string(),
+        format!("    at {}:14: f (spec)", fname),
+        "        ABORTED".to_string(),
+    ];
+    let (preamble, trace) = lsp_trace(&notes);
+    assert_eq!(preamble, vec!["Related Bindings:", "`x` = 1"]);
+    assert_eq!(trace.len(), 2);
+    let url = Url::from_file_path(&fpath).unwrap();
+    assert_eq!(trace[0].location.uri, url);
+    assert_eq!(trace[0].location.range.start, Position::new(11, 0));
+    assert_eq!(trace[0].message, "f: x = 1, v = vector{1, 2}");
+    assert_eq!(trace[1].location.range.start, Position::new(13, 0));
+    assert_eq!(trace[1].message, "f (spec): ABORTED");
+}
@@ -0,0 +1,666 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module runs the Move Prover on behalf of the language server. Verification happens in a
+//! background thread, either whenever a file is saved (if the client enables it with the
+//! `proveOnSave` initialization option) or when the client executes the `move-analyzer.verify`
+//! command for a file or a single function in it.
+//!
+//! Verification errors are reported as diagnostics, with the execution trace of the counterexample
+//! attached as related information. The outcome for each function verified in a file (verified,
+//! failed or timeout) is shown as a code lens above the function, and executing the lens
+//! re-verifies this function only. The results of the latest runs are kept per file and per
+//! function, so that re-verifying a single function only replaces the outcome for this function.
+
+use crate::{context::Context, diagnostics::lsp_model_diagnostic, symbols, utils::get_model_range};
+use anyhow::Result;
+use codespan_reporting::{diagnostic::Severity, term::termcolor::NoColor};
+use crossbeam::channel::Sender;
+use lsp_server::Request;
+use lsp_types::{CodeLens, CodeLensParams, Command, Diagnostic, ExecuteCommandParams, Range};
+use move_model::model::{GlobalEnv, VerificationScope};
+use move_package::{BuildConfig, ModelConfig};
+use move_symbol_pool::Symbol;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    sync::{Arc, Condvar, Mutex},
+    thread,
+};
+use tempfile::tempdir;
+use url::Url;
+
+/// The command verifying a file, or a single function in it. Its arguments are the URI of the
+/// file and, optionally, the name of the function in `Module::function` form.
+pub const VERIFY_COMMAND: &str = "move-analyzer.verify";
+
+/// The source set for diagnostics reported by the prover.
+const PROVER_SOURCE: &str = "move-prover";
+
+/// The outcome of verifying a function.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum VerificationStatus {
+    Verified,
+    Failed,
+    Timeout,
+}
+
+/// The result of verifying a single function.
+#[derive(Debug, Clone)]
+struct FunctionResult {
+    /// Range of the function declaration, above which the code lens is shown
+    range: Range,
+    status: VerificationStatus,
+    /// Diagnostics reported for the function, with the files they belong to
+    diagnostics: Vec<(PathBuf, Diagnostic)>,
+}
+
+/// Results of the latest verification runs, per verified file.
+#[derive(Debug, Default)]
+pub struct ProverResults {
+    /// Results for the functions of each file, keyed by `Module::function` names
+    functions: BTreeMap<PathBuf, BTreeMap<String, FunctionResult>>,
+    /// Diagnostics of each file's runs which do not belong to any verified function (e.g., errors
+    /// in global invariants)
+    other_diagnostics: BTreeMap<PathBuf, Vec<(PathBuf, Diagnostic)>>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct ProverRequest {
+    /// The (canonical) path of the file to be verified
+    file: PathBuf,
+    /// The function to be verified, or all functions of the file if none
+    function: Option<String>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum RunnerState {
+    Run(ProverRequest),
+    Wait,
+    Quit,
+}
+
+/// Data used to run the prover in the background
+pub struct ProverRunner {
+    mtx_cvar: Arc<(Mutex<RunnerState>, Condvar)>,
+    /// Whether to verify files whenever they are saved
+    on_save: bool,
+}
+
+impl ProverResults {
+    /// Records the results of verifying `file`, or only `function` in it.
+    fn update(
+        &mut self,
+        file: PathBuf,
+        function: Option<&str>,
+        functions: BTreeMap<String, FunctionResult>,
+        other_diagnostics: Vec<(PathBuf, Diagnostic)>,
+    ) {
+        if function.is_some() {
+            self.functions
+                .entry(file.clone())
+                .or_insert_with(BTreeMap::new)
+                .extend(functions);
+            let known = self.other_diagnostics.entry(file).or_insert_with(Vec::new);
+            for diag in other_diagnostics {
+                if !known.contains(&diag) {
+                    known.push(diag);
+                }
+            }
+        } else {
+            self.functions.insert(file.clone(), functions);
+            self.other_diagnostics.insert(file, other_diagnostics);
+        }
+    }
+
+    /// Returns all prover diagnostics, grouped by the files they belong to.
+    fn diagnostics(&self) -> BTreeMap<PathBuf, Vec<Diagnostic>> {
+        let mut diagnostics = BTreeMap::new();
+        let function_diags = self
+            .functions
+            .values()
+            .flat_map(|results| results.values())
+            .flat_map(|result| result.diagnostics.iter());
+        for (path, diag) in function_diags.chain(self.other_diagnostics.values().flatten()) {
+            diagnostics
+                .entry(path.clone())
+                .or_insert_with(Vec::new)
+                .push(diag.clone());
+        }
+        diagnostics
+    }
+
+    /// Returns code lenses showing the verification status of the functions in the given file.
+    pub fn code_lenses(&self, uri: &Url) -> Vec<CodeLens> {
+        let fpath = uri.to_file_path().unwrap();
+        let fpath = dunce::canonicalize(&fpath).unwrap_or(fpath);
+        let results = match self.functions.get(&fpath) {
+            Some(results) => results,
+            None => return vec![],
+        };
+        results
+            .iter()
+            .map(|(name, result)| {
+                let title = match result.status {
+                    VerificationStatus::Verified => "verified",
+                    VerificationStatus::Failed => "failed",
+                    VerificationStatus::Timeout => "timeout",
+                };
+                CodeLens {
+                    range: result.range,
+                    command: Some(Command::new(
+                        title.to_string(),
+                        VERIFY_COMMAND.to_string(),
+                        Some(vec![
+                            serde_json::to_value(uri).unwrap(),
+                            serde_json::to_value(name).unwrap(),
+                        ]),
+                    )),
+                    data: None,
+                }
+            })
+            .collect()
+    }
+}
+
+impl ProverRunner {
+    /// Create a new runner, which reports the diagnostics of all files affected by a run via
+    /// `sender` once the run finished.
+    pub fn new(
+        results: Arc<Mutex<ProverResults>>,
+        sender: Sender<Result<BTreeMap<Symbol, Vec<Diagnostic>>>>,
+        on_save: bool,
+    ) -> Self {
+        let mtx_cvar = Arc::new((Mutex::new(RunnerState::Wait), Condvar::new()));
+        let thread_mtx_cvar = mtx_cvar.clone();
+        let runner = ProverRunner { mtx_cvar, on_save };
+
+        thread::Builder::new()
+            .stack_size(symbols::STACK_SIZE_BYTES)
+            .spawn(move || {
+                let (mtx, cvar) = &*thread_mtx_cvar;
+                // Files for which prover diagnostics have been published, which need to be reset
+                // once a later run no longer reports diagnostics for them
+                let mut published: BTreeSet<PathBuf> = BTreeSet::new();
+                eprintln!("starting prover runner loop");
+                loop {
+                    let request_opt = {
+                        // hold the lock only as long as it takes to get the request, rather than
+                        // through the whole verification process
+                        let mut state = mtx.lock().unwrap();
+                        if *state == RunnerState::Wait {
+                            state = cvar.wait(state).unwrap();
+                        }
+                        match state.clone() {
+                            RunnerState::Quit => break,
+                            RunnerState::Run(request) => {
+                                *state = RunnerState::Wait;
+                                Some(request)
+                            }
+                            RunnerState::Wait => None,
+                        }
+                    };
+                    let request = match request_opt {
+                        Some(request) => request,
+                        None => continue,
+                    };
+                    // files outside of a package are reported by the symbolicator already
+                    let pkg_path = match symbols::SymbolicatorRunner::root_dir(&request.file) {
+                        Some(pkg_path) => pkg_path,
+                        None => continue,
+                    };
+                    eprintln!("verification started");
+                    let diagnostics = match verify(&pkg_path, &request) {
+                        Ok(None) => {
+                            eprintln!("verification skipped");
+                            continue;
+                        }
+                        Ok(Some((functions, other_diagnostics))) => {
+                            eprintln!("verification finished");
+                            let mut results = results.lock().unwrap();
+                            results.update(
+                                request.file,
+                                request.function.as_deref(),
+                                functions,
+                                other_diagnostics,
+                            );
+                            results.diagnostics()
+                        }
+                        Err(err) => {
+                            eprintln!("verification failed: {:?}", err);
+                            if let Err(err) = sender.send(Err(err)) {
+                                eprintln!("could not pass prover error: {:?}", err);
+                            }
+                            continue;
+                        }
+                    };
+                    let lsp_diagnostics = publish_diagnostics(&mut published, diagnostics);
+                    if let Err(err) = sender.send(Ok(lsp_diagnostics)) {
+                        eprintln!("could not pass prover diagnostics: {:?}", err);
+                    }
+                }
+            })
+            .unwrap();
+
+        runner
+    }
+
+    /// Whether files should be verified whenever they are saved.
+    pub fn on_save(&self) -> bool {
+        self.on_save
+    }
+
+    /// Schedules verification of the functions in the given file, or of a single function in it.
+    pub fn run(&self, file: PathBuf, function: Option<String>) {
+        eprintln!("scheduling verification of {:?}", file);
+        let file = dunce::canonicalize(&file).unwrap_or(file);
+        let (mtx, cvar) = &*self.mtx_cvar;
+        let mut state = mtx.lock().unwrap();
+        *state = RunnerState::Run(ProverRequest { file, function });
+        cvar.notify_one();
+    }
+
+    pub fn quit(&self) {
+        let (mtx, cvar) = &*self.mtx_cvar;
+        let mut state = mtx.lock().unwrap();
+        *state = RunnerState::Quit;
+        cvar.notify_one();
+    }
+}
+
+/// Verifies the modules in the requested file of the package at `pkg_path`. Returns the results
+/// for the verified functions, keyed by their `Module::function` names, and the diagnostics which
+/// do not belong to any of them, or `None` if the package does not compile.
+#[allow(clippy::type_complexity)]
+fn verify(
+    pkg_path: &Path,
+    request: &ProverRequest,
+) -> Result<Option<(BTreeMap<String, FunctionResult>, Vec<(PathBuf, Diagnostic)>)>> {
+    // the prover runs in dev mode, so addresses get default assignments
+    let build_config = BuildConfig {
+        dev_mode: true,
+        install_dir: Some(tempdir()?.path().to_path_buf()),
+        ..Default::default()
+    };
+    // only modules in the requested file are verification targets
+    let env = build_config.move_model_for_package(
+        pkg_path,
+        ModelConfig {
+            all_files_as_targets: false,
+            target_filter: Some(request.file.to_string_lossy().to_string()),
+        },
+    )?;
+    if env.get_module_count() == 0 {
+        // compilation errors are reported by the symbolicator already
+        return Ok(None);
+    }
+
+    let mut args = vec!["package".to_string()];
+    let prover_toml = pkg_path.join("Prover.toml");
+    if prover_toml.exists() {
+        args.push(format!("--config={}", prover_toml.to_string_lossy()));
+    }
+    let mut options = move_prover::cli::Options::create_from_args(&args)?;
+    let output_dir = tempdir()?;
+    options.output_path = output_dir
+        .path()
+        .join("output.bpl")
+        .to_string_lossy()
+        .to_string();
+    if let Some(function) = &request.function {
+        options.prover.verify_scope = VerificationScope::Only(function.clone());
+    }
+    let scope = options.prover.verify_scope.clone();
+
+    // the prover accumulates diagnostics in the environment, so its textual output is not needed;
+    // if it fails without any, it could not run at all (e.g., because a backend tool is missing)
+    let res = move_prover::run_move_prover_with_model(
+        &env,
+        &mut NoColor::new(std::io::sink()),
+        options,
+        None,
+    );
+    if let Err(err) = res {
+        if !env.has_errors() {
+            return Err(err);
+        }
+    }
+    Ok(Some(verification_results(&env, &scope)))
+}
+
+/// Collects the results of verification from the diagnostics accumulated in the environment.
+fn verification_results(
+    env: &GlobalEnv,
+    scope: &VerificationScope,
+) -> (BTreeMap<String, FunctionResult>, Vec<(PathBuf, Diagnostic)>) {
+    let mut functions = BTreeMap::new();
+    for module_env in env.get_modules().filter(|m| m.is_target()) {
+        for fun_env in module_env.get_functions() {
+            if fun_env.is_native() || !fun_env.should_verify(scope) {
+                continue;
+            }
+            if let Some((_, range)) = get_model_range(env, &fun_env.get_loc()) {
+                functions.insert(
+                    fun_env.get_name_string().to_string(),
+                    FunctionResult {
+                        // show the code lens above the first line of the function only
+                        range: Range::new(range.start, range.start),
+                        status: VerificationStatus::Verified,
+                        diagnostics: vec![],
+                    },
+                );
+            }
+        }
+    }
+
+    // warnings come from the compiler, and are reported by the symbolicator already
+    let mut other_diagnostics = vec![];
+    for diag in env.get_diags(Severity::Error) {
+        let (path, loc, mut lsp_diag) = match lsp_model_diagnostic(env, &diag) {
+            Some(converted) => converted,
+            None => continue,
+        };
+        lsp_diag.source = Some(PROVER_SOURCE.to_string());
+        let result = env
+            .get_enclosing_function(&loc)
+            .and_then(|fun_env| functions.get_mut(&*fun_env.get_name_string()));
+        match result {
+            Some(result) => {
+                let timeout = diag.message.starts_with("verification out of resources")
+                    || diag.message.starts_with("verification inconclusive");
+                result.status = match result.status {
+                    VerificationStatus::Verified | VerificationStatus::Timeout if timeout => {
+                        VerificationStatus::Timeout
+                    }
+                    _ => VerificationStatus::Failed,
+                };
+                result.diagnostics.push((path, lsp_diag));
+            }
+            None => other_diagnostics.push((path, lsp_diag)),
+        }
+    }
+    (functions, other_diagnostics)
+}
+
+/// Returns the diagnostics to publish for the given prover diagnostics, which also resets the
+/// diagnostics of files in `published` that no longer have any, and adds the files to it.
+fn publish_diagnostics(
+    published: &mut BTreeSet<PathBuf>,
+    diagnostics: BTreeMap<PathBuf, Vec<Diagnostic>>,
+) -> BTreeMap<Symbol, Vec<Diagnostic>> {
+    let mut lsp_diagnostics = BTreeMap::new();
+    for path in published.iter() {
+        lsp_diagnostics.insert(path_symbol(path), vec![]);
+    }
+    for (path, diags) in diagnostics {
+        lsp_diagnostics.insert(path_symbol(&path), diags);
+        published.insert(path);
+    }
+    lsp_diagnostics
+}
+
+fn path_symbol(path: &Path) -> Symbol {
+    Symbol::from(path.to_string_lossy().as_ref())
+}
+
+/// Handles code lens request of the language server
+pub fn on_code_lens_request(context: &Context, request: &Request, results: &ProverResults) {
+    let parameters = serde_json::from_value::<CodeLensParams>(request.params.clone())
+        .expect("could not deserialize code lens request");
+
+    let lenses = results.code_lenses(&parameters.text_document.uri);
+    let response = lsp_server::Response::new_ok(request.id.clone(), lenses);
+    if let Err(err) = context
+        .connection
+        .sender
+        .send(lsp_server::Message::Response(response))
+    {
+        eprintln!("could not send code lens response: {:?}", err);
+    }
+}
+
+/// Handles execute command request of the language server, scheduling verification for the
+/// `move-analyzer.verify` command.
+pub fn on_execute_command_request(context: &Context, request: &Request, runner: &ProverRunner) {
+    let parameters = serde_json::from_value::<ExecuteCommandParams>(request.params.clone())
+        .expect("could not deserialize execute command request");
+
+    if parameters.command == VERIFY_COMMAND {
+        let mut args = parameters.arguments.into_iter();
+        let file = args
+            .next()
+            .and_then(|arg| serde_json::from_value::<Url>(arg).ok())
+            .and_then(|uri| uri.to_file_path().ok());
+        let function = args
+            .next()
+            .and_then(|arg| serde_json::from_value::<String>(arg).ok());
+        match file {
+            Some(file) => runner.run(file, function),
+            None => eprintln!("missing file to verify"),
+        }
+    } else {
+        eprintln!("unknown command '{}'", parameters.command);
+    }
+    let response = lsp_server::Response::new_ok(request.id.clone(), serde_json::Value::Null);
+    if let Err(err) = context
+        .connection
+        .sender
+        .send(lsp_server::Message::Response(response))
+    {
+        eprintln!("could not send execute command response: {:?}", err);
+    }
+}
+
+#[cfg(test)]
+fn test_result(
+    status: VerificationStatus,
+    diagnostics: Vec<(PathBuf, Diagnostic)>,
+) -> FunctionResult {
+    FunctionResult {
+        range: Range::new(
+            lsp_types::Position::new(1, 4),
+            lsp_types::Position::new(1, 4),
+        ),
+        status,
+        diagnostics,
+    }
+}
+
+#[cfg(test)]
+fn test_diagnostic(path: &Path, message: &str) -> (PathBuf, Diagnostic) {
+    (
+        path.to_path_buf(),
+        Diagnostic {
+            message: message.to_string(),
+            ..Default::default()
+        },
+    )
+}
+
+#[test]
+/// Tests if re-verifying a single function only replaces the results for this function.
+fn update_test() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("tests/symbols/sources/M1.move");
+    let fpath = dunce::canonicalize(&path).unwrap();
+    let messages = |results: &ProverResults| {
+        let mut messages: Vec<_> = results
+            .diagnostics()
+            .remove(&fpath)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|diag| diag.message)
+            .collect();
+        messages.sort();
+        messages
+    };
+
+    let mut results = ProverResults::default();
+    results.update(
+        fpath.clone(),
+        None,
+        BTreeMap::from([
+            (
+                "M1::f".to_string(),
+                test_result(
+                    VerificationStatus::Failed,
+                    vec![test_diagnostic(&fpath, "f fails")],
+                ),
+            ),
+            (
+                "M1::g".to_string(),
+                test_result(
+                    VerificationStatus::Timeout,
+                    vec![test_diagnostic(&fpath, "g times out")],
+                ),
+            ),
+        ]),
+        vec![test_diagnostic(&fpath, "invariant fails")],
+    );
+    assert_eq!(
+        messages(&results),
+        vec!["f fails", "g times out", "invariant fails"]
+    );
+
+    results.update(
+        fpath.clone(),
+        Some("M1::f"),
+        BTreeMap::from([(
+            "M1::f".to_string(),
+            test_result(VerificationStatus::Verified, vec![]),
+        )]),
+        vec![
+            test_diagnostic(&fpath, "invariant fails"),
+            test_diagnostic(&fpath, "other invariant fails"),
+        ],
+    );
+    assert_eq!(
+        messages(&results),
+        vec!["g times out", "invariant fails", "other invariant fails"]
+    );
+
+    let uri = Url::from_file_path(&fpath).unwrap();
+    let lenses: Vec<_> = results
+        .code_lenses(&uri)
+        .into_iter()
+        .map(|lens| {
+            let command = lens.command.unwrap();
+            (command.title, command.command, command.arguments.unwrap())
+        })
+        .collect();
+    let args = |name: &str| {
+        vec![
+            serde_json::to_value(&uri).unwrap(),
+            serde_json::to_value(name).unwrap(),
+        ]
+    };
+    assert_eq!(
+        lenses,
+        vec![
+            (
+                "verified".to_string(),
+                VERIFY_COMMAND.to_string(),
+                args("M1::f")
+            ),
+            (
+                "timeout".to_string(),
+                VERIFY_COMMAND.to_string(),
+                args("M1::g")
+            ),
+        ]
+    );
+
+    // verifying the whole file replaces all of its results
+    results.update(fpath.clone(), None, BTreeMap::new(), vec![]);
+    assert!(messages(&results).is_empty());
+    assert!(results.code_lenses(&uri).is_empty());
+}
+
+#[test]
+/// Tests if diagnostics are cleared for files which no longer have any.
+fn publish_diagnostics_test() {
+    let a = PathBuf::from("/a.move");
+    let b = PathBuf::from("/b.move");
+    let diag = test_diagnostic(&a, "fails").1;
+    let mut published = BTreeSet::new();
+
+    let lsp_diagnostics = publish_diagnostics(
+        &mut published,
+        BTreeMap::from([
+            (a.clone(), vec![diag.clone()]),
+            (b.clone(), vec![diag.clone()]),
+        ]),
+    );
+    assert_eq!(lsp_diagnostics.len(), 2);
+    assert_eq!(lsp_diagnostics[&path_symbol(&a)], vec![diag.clone()]);
+
+    let lsp_diagnostics = publish_diagnostics(
+        &mut published,
+        BTreeMap::from([(b.clone(), vec![diag.clone()])]),
+    );
+    assert_eq!(
+        lsp_diagnostics,
+        BTreeMap::from([(path_symbol(&a), vec![]), (path_symbol(&b), vec![diag])])
+    );
+}
+
+#[test]
+/// Tests if functions with timeouts and with other errors are told apart.
+fn verification_results_test() {
+    use move_compiler::shared::PackagePaths;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("M.move");
+    std::fs::write(
+        &path,
+        "module 0x42::M {\n    public fun f() {}\n    public fun g() {}\n    public fun h() {}\n    fun i() {}\n}\n",
+    )
+    .unwrap();
+    let env = move_model::run_model_builder(
+        vec![PackagePaths {
+            name: None,
+            paths: vec![path.to_string_lossy().to_string()],
+            named_address_map: BTreeMap::<String, _>::new(),
+        }],
+        vec![],
+    )
+    .unwrap();
+    assert!(!env.has_errors());
+    let module_env = env
+        .find_module_by_name(env.symbol_pool().make("M"))
+        .unwrap();
+    let fun_loc = |name: &str| {
+        module_env
+            .find_function(env.symbol_pool().make(name))
+            .unwrap()
+            .get_loc()
+    };
+    let timeout = "verification out of resources/timeout (global timeout set to 40s)";
+    env.error(&fun_loc("f"), timeout);
+    env.error(&fun_loc("f"), "verification inconclusive");
+    env.error(&fun_loc("g"), timeout);
+    env.error(&fun_loc("g"), "post-condition does not hold");
+    env.error(
+        &module_env.get_loc(),
+        "global memory invariant does not hold",
+    );
+
+    let (functions, other_diagnostics) = verification_results(&env, &VerificationScope::Public);
+    let statuses: Vec<_> = functions
+        .iter()
+        .map(|(name, result)| (name.as_str(), result.status, result.diagnostics.len()))
+        .collect();
+    assert_eq!(
+        statuses,
+        vec![
+            ("M::f", VerificationStatus::Timeout, 2),
+            ("M::g", VerificationStatus::Failed, 2),
+            ("M::h", VerificationStatus::Verified, 0),
+        ]
+    );
+    assert_eq!(other_diagnostics.len(), 1);
+    assert_eq!(
+        other_diagnostics[0].1.source.as_deref(),
+        Some(PROVER_SOURCE)
+    );
+}
@@ -71,7 +71,7 @@ use std::{
     sync::{Arc, Condvar, Mutex},
     thread,
 };
-use tempfile::tempdir;
+use tempfile::{tempdir, TempDir};
 use url::Url;
 
 use move_command_line_common::files::FileHash;
@@ -87,7 +87,7 @@ use move_compiler::{
     PASS_TYPING,
 };
 use move_ir_types::location::*;
-use move_package::compilation::build_plan::BuildPlan;
+use move_package::compilation::{build_plan::BuildPlan, module_cache::TypedAstCache};
 use move_symbol_pool::Symbol;
 
 /// Enabling/disabling the language server reporting readiness to support go-to-def and
@@ -239,6 +239,14 @@ enum RunnerState {
     Quit,
 }
 
+/// Build state kept across symbolications, so that only the files affected by an edit are
+/// compiled again
+#[derive(Default)]
+pub struct SymbolicatorCache {
+    /// For each package, the directory it is built in and the typed ASTs of its files
+    packages: BTreeMap<PathBuf, (TempDir, TypedAstCache)>,
+}
+
 /// Data used during symbolication running and symbolication info updating
 pub struct SymbolicatorRunner {
     mtx_cvar: Arc<(Mutex<RunnerState>, Condvar)>,
@@ -386,6 +394,7 @@ impl SymbolicatorRunner {
                 let (mtx, cvar) = &*thread_mtx_cvar;
                 // Locations opened in the IDE (files or directories) for which manifest file is missing
                 let mut missing_manifests = BTreeSet::new();
+                let mut cache = SymbolicatorCache::default();
                 // infinite loop to wait for symbolication requests
                 eprintln!("starting symbolicator runner loop");
                 loop {
@@ -432,7 +441,10 @@ impl SymbolicatorRunner {
                             continue;
                         }
                         eprintln!("symbolication started");
-                        match Symbolicator::get_symbols(root_dir.unwrap().as_path()) {
+                        match Symbolicator::get_symbols_with_cache(
+                            root_dir.unwrap().as_path(),
+                            &mut cache,
+                        ) {
                             Ok((symbols_opt, lsp_diagnostics)) => {
                                 eprintln!("symbolication finished");
                                 if let Some(new_symbols) = symbols_opt {
@@ -616,9 +628,25 @@ impl Symbolicator {
     pub fn get_symbols(
         pkg_path: &Path,
     ) -> Result<(Option<Symbols>, BTreeMap<Symbol, Vec<Diagnostic>>)> {
+        Self::get_symbols_with_cache(pkg_path, &mut SymbolicatorCache::default())
+    }
+
+    /// Same as `get_symbols`, but builds the package incrementally, reusing the bytecode and typed
+    /// ASTs of the files that were not affected by changes since the last build in `cache`.
+    pub fn get_symbols_with_cache(
+        pkg_path: &Path,
+        cache: &mut SymbolicatorCache,
+    ) -> Result<(Option<Symbols>, BTreeMap<Symbol, Vec<Diagnostic>>)> {
+        if !cache.packages.contains_key(pkg_path) {
+            cache
+                .packages
+                .insert(pkg_path.to_path_buf(), (tempdir()?, TypedAstCache::new()));
+        }
+        let (install_dir, typed_ast_cache) = cache.packages.get_mut(pkg_path).unwrap();
         let build_config = move_package::BuildConfig {
             test_mode: true,
-            install_dir: Some(tempdir().unwrap().path().to_path_buf()),
+            install_dir: Some(install_dir.path().to_path_buf()),
+            incremental: true,
             ..Default::default()
         };
 
@@ -647,40 +675,55 @@ impl Symbolicator {
         let build_plan = BuildPlan::create(resolution_graph)?;
         let mut typed_ast = None;
         let mut diagnostics = None;
-        build_plan.compile_with_driver(&mut std::io::sink(), None, |compiler| {
-            let (files, compilation_result) = compiler.run::<PASS_TYPING>()?;
-            let (_, compiler) = match compilation_result {
-                Ok(v) => v,
-                Err(diags) => {
-                    let failure = true;
-                    diagnostics = Some((diags, failure));
-                    eprintln!("typed AST compilation failed");
-                    return Ok((files, vec![]));
-                }
-            };
-            eprintln!("compiled to typed AST");
-            let (compiler, typed_program) = compiler.into_ast();
-            typed_ast = Some(typed_program.clone());
-            eprintln!("compiling to bytecode");
-            let compilation_result = compiler.at_typing(typed_program).build();
-            let (units, diags) = match compilation_result {
-                Ok(v) => v,
-                Err(diags) => {
+        // failed compilations return an error rather than no units, so that the files compiled are
+        // not cached for the next incremental build
+        let compiled = build_plan.compile_with_typed_ast_driver(
+            &mut std::io::sink(),
+            None,
+            typed_ast_cache,
+            |compiler| {
+                let (files, compilation_result) = compiler.run::<PASS_TYPING>()?;
+                let (_, compiler) = match compilation_result {
+                    Ok(v) => v,
+                    Err(diags) => {
+                        let failure = true;
+                        diagnostics = Some((diags, failure));
+                        eprintln!("typed AST compilation failed");
+                        return Err(anyhow!("typed AST compilation failed"));
+                    }
+                };
+                eprintln!("compiled to typed AST");
+                let (compiler, typed_program) = compiler.into_ast();
+                eprintln!("compiling to bytecode");
+                let compilation_result = compiler.at_typing(typed_program.clone()).build();
+                let (units, diags) = match compilation_result {
+                    Ok(v) => v,
+                    Err(diags) => {
+                        let failure = false;
+                        diagnostics = Some((diags, failure));
+                        // only covers the files compiled, but still worth symbolicating
+                        typed_ast = Some(typed_program);
+                        eprintln!("bytecode compilation failed");
+                        return Err(anyhow!("bytecode compilation failed"));
+                    }
+                };
+                // warning diagnostics (if any) since compilation succeeded
+                if !diags.is_empty() {
+                    // assign only if non-empty, otherwise return None to reset previous diagnostics
                     let failure = false;
                     diagnostics = Some((diags, failure));
-                    eprintln!("bytecode compilation failed");
-                    return Ok((files, vec![]));
                 }
-            };
-            // warning diagnostics (if any) since compilation succeeded
-            if !diags.is_empty() {
-                // assign only if non-empty, otherwise return None to reset previous diagnostics
-                let failure = false;
-                diagnostics = Some((diags, failure));
-            }
-            eprintln!("compiled to bytecode");
-            Ok((files, units))
-        })?;
+                eprintln!("compiled to bytecode");
+                Ok((files, units, typed_program))
+            },
+        );
+        match compiled {
+            // the typed AST of the whole package, including the files reused from the cache
+            Ok((_, program)) => typed_ast = Some(program),
+            // errors other than compilation failures reported as diagnostics
+            Err(err) if diagnostics.is_none() => return Err(err),
+            Err(_) => (),
+        }
 
         let mut ide_diagnostics = lsp_empty_diagnostics(&file_name_mapping);
         if let Some((compiler_diagnostics, failure)) = diagnostics {
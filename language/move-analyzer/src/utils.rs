@@ -2,11 +2,12 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use codespan_reporting::files::{Files, SimpleFiles};
-use lsp_types::Position;
+use lsp_types::{Position, Range};
 use move_command_line_common::files::FileHash;
 use move_ir_types::location::*;
+use move_model::model::{GlobalEnv, Loc as ModelLoc};
 use move_symbol_pool::Symbol;
-use std::collections::HashMap;
+use std::{collections::HashMap, path::PathBuf};
 
 /// Converts a location from the byte index format to the line/character (Position) format, where
 /// line/character are 0-based.
@@ -29,3 +30,19 @@ pub fn get_loc(
         Err(_) => None,
     }
 }
+
+/// Converts a location in the move model into the (canonical) path of its file and its
+/// line/character range, where line/character are 0-based.
+pub fn get_model_range(env: &GlobalEnv, loc: &ModelLoc) -> Option<(PathBuf, Range)> {
+    let path = dunce::canonicalize(env.get_file(loc.file_id())).ok()?;
+    let start = env.get_location(loc)?;
+    // the location at the end covers the last character of the original one
+    let end = env.get_location(&loc.at_end())?;
+    Some((
+        path,
+        Range::new(
+            Position::new(start.line.0, start.column.0),
+            Position::new(end.line.0, end.column.0 + 1),
+        ),
+    ))
+}
@@ -28,17 +28,33 @@ pub enum Location {
 #[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
 pub struct ExecutionState {
     stack_trace: Vec<(Option<ModuleId>, FunctionDefinitionIndex, CodeOffset)>,
+    /// The rendered values of the locals of each frame of the stack trace, in the same order,
+    /// with `None` for a local holding no value. Empty if the locals were not captured.
+    locals: Vec<Vec<Option<String>>>,
     // we may consider adding more state if necessary
 }
 
 impl ExecutionState {
     pub fn new(stack_trace: Vec<(Option<ModuleId>, FunctionDefinitionIndex, CodeOffset)>) -> Self {
-        Self { stack_trace }
+        Self {
+            stack_trace,
+            locals: vec![],
+        }
+    }
+
+    pub fn with_locals(mut self, locals: Vec<Vec<Option<String>>>) -> Self {
+        debug_assert_eq!(locals.len(), self.stack_trace.len());
+        self.locals = locals;
+        self
     }
 
     pub fn stack_trace(&self) -> &Vec<(Option<ModuleId>, FunctionDefinitionIndex, CodeOffset)> {
         &self.stack_trace
     }
+
+    pub fn locals(&self) -> &[Vec<Option<String>>] {
+        &self.locals
+    }
 }
 
 #[derive(Clone, Eq, Ord, PartialEq, PartialOrd)]
@@ -141,6 +157,11 @@ impl VMError {
         self.0.exec_state.as_ref()
     }
 
+    pub fn with_exec_state(mut self, exec_state: ExecutionState) -> Self {
+        self.0.exec_state = Some(exec_state);
+        self
+    }
+
     pub fn remove_exec_state(&mut self) {
         self.0.exec_state = None;
     }
@@ -429,6 +429,15 @@ impl<Loc: Copy, Lbl: Clone + Ord> BorrowGraph<Loc, Lbl> {
         self.0.keys().cloned().collect()
     }
 
+    /// Returns the number of references plus the number of borrow edges in the graph, which
+    /// bounds the cost of operations on it
+    pub fn graph_size(&self) -> usize {
+        self.0
+            .values()
+            .map(|r| 1 + r.borrowed_by.0.values().map(|e| e.len()).sum::<usize>())
+            .sum()
+    }
+
     /// Prints out a view of the borrow graph
     #[allow(dead_code)]
     pub fn display(&self)
@@ -0,0 +1,131 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use move_binary_format::{file_format::*, IndexKind};
+use move_bytecode_verifier::{
+    meter::{BoundMeter, Scope},
+    verify_module_with_config, verify_module_with_config_metered, VerifierConfig,
+};
+use move_core_types::{identifier::Identifier, vm_status::StatusCode};
+
+/// Creates a module with `functions` functions, each of which borrows `n` locals in a loop, such
+/// that the abstract state of the reference safety analysis grows with `n`.
+fn borrow_loop_module(functions: usize, n: u8) -> CompiledModule {
+    let mut m = empty_module();
+    let locals_idx = SignatureIndex(m.signatures.len() as TableIndex);
+    m.signatures.push(Signature(
+        (0..n)
+            .map(|_| SignatureToken::U64)
+            .chain((0..n).map(|_| SignatureToken::Reference(Box::new(SignatureToken::U64))))
+            .collect(),
+    ));
+
+    let mut code = vec![];
+    for i in 0..n {
+        code.push(Bytecode::LdU64(0));
+        code.push(Bytecode::StLoc(i));
+    }
+    let loop_start = code.len() as CodeOffset;
+    let end = loop_start + 4 * n as CodeOffset + 3;
+    code.push(Bytecode::LdTrue);
+    code.push(Bytecode::BrFalse(end));
+    for i in 0..n {
+        code.push(Bytecode::ImmBorrowLoc(i));
+        code.push(Bytecode::StLoc(n + i));
+        code.push(Bytecode::CopyLoc(n + i));
+        code.push(Bytecode::Pop);
+    }
+    code.push(Bytecode::Branch(loop_start));
+    code.push(Bytecode::Ret);
+    assert_eq!(code.len() as CodeOffset, end + 1);
+
+    for f in 0..functions {
+        m.function_handles.push(FunctionHandle {
+            module: ModuleHandleIndex(0),
+            name: IdentifierIndex(m.identifiers.len() as TableIndex),
+            parameters: SignatureIndex(0),
+            return_: SignatureIndex(0),
+            type_parameters: vec![],
+        });
+        m.identifiers
+            .push(Identifier::new(format!("f{}", f)).unwrap());
+        m.function_defs.push(FunctionDefinition {
+            function: FunctionHandleIndex(f as TableIndex),
+            visibility: Visibility::Private,
+            is_entry: false,
+            acquires_global_resources: vec![],
+            code: Some(CodeUnit {
+                locals: locals_idx,
+                code: code.clone(),
+            }),
+        });
+    }
+    m
+}
+
+fn function_units(module: &CompiledModule) -> u128 {
+    let mut meter = BoundMeter::new(&VerifierConfig::default());
+    verify_module_with_config_metered(&VerifierConfig::default(), module, &mut meter).unwrap();
+    meter.get_usage(Scope::Function)
+}
+
+#[test]
+fn test_unbounded_by_default() {
+    let module = borrow_loop_module(2, 32);
+    let mut meter = BoundMeter::new(&VerifierConfig::default());
+    assert_eq!(meter.get_limit(Scope::Function), None);
+    assert_eq!(meter.get_limit(Scope::Module), None);
+    verify_module_with_config_metered(&VerifierConfig::default(), &module, &mut meter).unwrap();
+    assert!(meter.get_usage(Scope::Function) > 0);
+    assert!(meter.get_usage(Scope::Module) >= 2 * meter.get_usage(Scope::Function));
+}
+
+#[test]
+fn test_cost_grows_with_borrow_graph() {
+    let small = function_units(&borrow_loop_module(1, 4));
+    let large = function_units(&borrow_loop_module(1, 16));
+    // the cost of each instruction grows with the state, so four times the code costs more than
+    // four times as much
+    assert!(large > 4 * small, "{} vs {}", large, small);
+}
+
+#[test]
+fn test_function_budget() {
+    let module = borrow_loop_module(1, 16);
+    let units = function_units(&module);
+    let verify = |max_per_fun_meter_units| {
+        verify_module_with_config(
+            &VerifierConfig {
+                max_per_fun_meter_units,
+                ..Default::default()
+            },
+            &module,
+        )
+    };
+    assert!(verify(Some(units)).is_ok());
+    assert_eq!(
+        verify(Some(units - 1)).unwrap_err().major_status(),
+        StatusCode::PROGRAM_TOO_COMPLEX
+    );
+}
+
+#[test]
+fn test_module_budget() {
+    let units = function_units(&borrow_loop_module(1, 16));
+    let module = borrow_loop_module(3, 16);
+    let verify = |max_per_mod_meter_units| {
+        verify_module_with_config(
+            &VerifierConfig {
+                max_per_fun_meter_units: Some(units),
+                max_per_mod_meter_units,
+                ..Default::default()
+            },
+            &module,
+        )
+    };
+    assert!(verify(Some(3 * units)).is_ok());
+    let err = verify(Some(2 * units)).unwrap_err();
+    assert_eq!(err.major_status(), StatusCode::PROGRAM_TOO_COMPLEX);
+    // the budget is exhausted in the last function
+    assert_eq!(err.indices(), &vec![(IndexKind::FunctionDefinition, 2)]);
+}
@@ -13,6 +13,7 @@ pub mod duplication_tests;
 pub mod generic_ops_tests;
 pub mod limit_tests;
 pub mod loop_summary_tests;
+pub mod metering_tests;
 pub mod multi_pass_tests;
 pub mod negative_stack_size_tests;
 pub mod signature_tests;
@@ -0,0 +1,143 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Searches for code units whose verification is slow although the verifier meter accepts them,
+//! i.e. inputs for which the meter undercharges the work of the abstract interpreters.
+//!
+//! The budgets and the time limit can be set through the environment:
+//! - `MOVE_VERIFIER_FUN_UNITS`: per-function budget, default 8_000_000
+//! - `MOVE_VERIFIER_MOD_UNITS`: per-module budget, default 8_000_000
+//! - `MOVE_VERIFIER_MAX_MILLIS`: time a metered verification may take, default 100
+
+#![no_main]
+use move_binary_format::file_format::{
+    empty_module, AbilitySet, CodeUnit, Constant, FieldDefinition, FunctionDefinition,
+    FunctionHandle, FunctionHandleIndex, IdentifierIndex, ModuleHandleIndex, Signature,
+    SignatureIndex,
+    SignatureToken::{Address, Bool, MutableReference, Reference, U128, U64},
+    StructDefinition, StructFieldInformation, StructHandle, StructHandleIndex, TypeSignature,
+    Visibility,
+};
+use move_bytecode_verifier::{
+    meter::{BoundMeter, Scope},
+    verify_module_with_config_metered, VerifierConfig,
+};
+use move_core_types::{
+    account_address::AccountAddress, identifier::Identifier, vm_status::StatusCode,
+};
+use std::{
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use libfuzzer_sys::fuzz_target;
+
+fn env_or(name: &str, default: u128) -> u128 {
+    std::env::var(name)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(default)
+}
+
+fuzz_target!(|code_unit: CodeUnit| {
+    let mut module = empty_module();
+    module.version = 5;
+
+    module.struct_handles.push(StructHandle {
+        module: ModuleHandleIndex(0),
+        name: IdentifierIndex(1),
+        abilities: AbilitySet::ALL,
+        type_parameters: vec![],
+    });
+
+    let fun_handle = FunctionHandle {
+        module: ModuleHandleIndex(0),
+        name: IdentifierIndex(2),
+        parameters: SignatureIndex(0),
+        return_: SignatureIndex(1),
+        type_parameters: vec![],
+    };
+
+    module.function_handles.push(fun_handle);
+
+    // Parameters include references, so that the reference safety analysis starts with a
+    // non-trivial borrow graph.
+    module.signatures.pop();
+    module.signatures.push(Signature(vec![
+        Address,
+        U64,
+        Reference(Box::new(U64)),
+        MutableReference(Box::new(U64)),
+        U128,
+        MutableReference(Box::new(U128)),
+        U64,
+        U64,
+        U64,
+    ]));
+    module.signatures.push(Signature(vec![]));
+    module.signatures.push(Signature(vec![
+        Address,
+        Bool,
+        Reference(Box::new(Address)),
+        MutableReference(Box::new(Address)),
+    ]));
+
+    module.identifiers.extend(
+        vec![
+            Identifier::from_str("zf_hello_world").unwrap(),
+            Identifier::from_str("awldFnU18mlDKQfh6qNfBGx8X").unwrap(),
+            Identifier::from_str("aQPwJNHyAHpvJ").unwrap(),
+            Identifier::from_str("aT7ZphKTrKcYCwCebJySrmrKlckmnL5").unwrap(),
+            Identifier::from_str("arYpsFa2fvrpPJ").unwrap(),
+        ]
+        .into_iter(),
+    );
+    module.address_identifiers.push(AccountAddress::random());
+
+    module.constant_pool.push(Constant {
+        type_: Address,
+        data: AccountAddress::ZERO.into_bytes().to_vec(),
+    });
+
+    module.struct_defs.push(StructDefinition {
+        struct_handle: StructHandleIndex(0),
+        field_information: StructFieldInformation::Declared(vec![FieldDefinition {
+            name: IdentifierIndex::new(3),
+            signature: TypeSignature(Address),
+        }]),
+    });
+
+    let fun_def = FunctionDefinition {
+        code: Some(code_unit),
+        function: FunctionHandleIndex(0),
+        visibility: Visibility::Public,
+        is_entry: false,
+        acquires_global_resources: vec![],
+    };
+
+    module.function_defs.push(fun_def);
+
+    let config = VerifierConfig {
+        max_per_fun_meter_units: Some(env_or("MOVE_VERIFIER_FUN_UNITS", 8_000_000)),
+        max_per_mod_meter_units: Some(env_or("MOVE_VERIFIER_MOD_UNITS", 8_000_000)),
+        ..Default::default()
+    };
+    let max_time = Duration::from_millis(env_or("MOVE_VERIFIER_MAX_MILLIS", 100) as u64);
+    let mut meter = BoundMeter::new(&config);
+    let start = Instant::now();
+    let result = verify_module_with_config_metered(&config, &module, &mut meter);
+    let elapsed = start.elapsed();
+
+    let out_of_budget = matches!(
+        &result,
+        Err(e) if e.major_status() == StatusCode::PROGRAM_TOO_COMPLEX
+    );
+    if elapsed > max_time && !out_of_budget {
+        panic!(
+            "verification took {:?} with {} units, exceeding {:?} within budget",
+            elapsed,
+            meter.get_usage(Scope::Function),
+            max_time
+        );
+    }
+});
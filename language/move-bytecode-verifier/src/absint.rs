@@ -2,17 +2,26 @@
 // Copyright (c) The Move Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::meter::{Meter, Scope};
 use move_binary_format::{
     binary_views::FunctionView,
     control_flow_graph::{BlockId, ControlFlowGraph},
+    errors::PartialVMResult,
     file_format::{Bytecode, CodeOffset},
 };
 use std::collections::BTreeMap;
 
+/// Costs of the fixpoint iteration itself, on top of the costs charged by the transfer functions
+/// and joins of the domain.
+const ANALYZE_FUNCTION_BASE_COST: u128 = 10;
+const EXECUTE_BLOCK_BASE_COST: u128 = 10;
+const PER_BACKEDGE_COST: u128 = 10;
+const PER_SUCCESSOR_COST: u128 = 10;
+
 /// Trait for finite-height abstract domains. Infinite height domains would require a more complex
 /// trait with widening and a partial order.
 pub trait AbstractDomain: Clone + Sized {
-    fn join(&mut self, other: &Self) -> JoinResult;
+    fn join(&mut self, other: &Self, meter: &mut impl Meter) -> PartialVMResult<JoinResult>;
 }
 
 #[derive(Debug)]
@@ -36,13 +45,12 @@ pub type InvariantMap<State> = BTreeMap<BlockId, BlockInvariant<State>>;
 /// Auxiliary data can be stored in self.
 pub trait TransferFunctions {
     type State: AbstractDomain;
-    type Error;
 
     /// Execute local@instr found at index local@index in the current basic block from pre-state
     /// local@pre.
-    /// Should return an AnalysisError if executing the instruction is unsuccessful, and () if
+    /// Should return an error if executing the instruction is unsuccessful, and () if
     /// the effects of successfully executing local@instr have been reflected by mutatating
-    /// local@pre.
+    /// local@pre. The cost of executing the instruction is charged to local@meter.
     /// Auxilary data from the analysis that is not part of the abstract state can be collected by
     /// mutating local@self.
     /// The last instruction index in the current block is local@last_index. Knowing this
@@ -54,7 +62,8 @@ pub trait TransferFunctions {
         instr: &Bytecode,
         index: CodeOffset,
         last_index: CodeOffset,
-    ) -> Result<(), Self::Error>;
+        meter: &mut impl Meter,
+    ) -> PartialVMResult<()>;
 }
 
 pub trait AbstractInterpreter: TransferFunctions {
@@ -63,7 +72,9 @@ pub trait AbstractInterpreter: TransferFunctions {
         &mut self,
         initial_state: Self::State,
         function_view: &FunctionView,
-    ) -> Result<(), Self::Error> {
+        meter: &mut impl Meter,
+    ) -> PartialVMResult<()> {
+        meter.add(Scope::Function, ANALYZE_FUNCTION_BASE_COST)?;
         let mut inv_map = InvariantMap::new();
         let entry_block_id = function_view.cfg().entry_block_id();
         let mut next_block = Some(entry_block_id);
@@ -83,16 +94,17 @@ pub trait AbstractInterpreter: TransferFunctions {
             let pre_state = &block_invariant.pre;
             // Note: this will stop analysis after the first error occurs, to avoid the risk of
             // subsequent crashes
-            let post_state = self.execute_block(block_id, pre_state, function_view)?;
+            let post_state = self.execute_block(block_id, pre_state, function_view, meter)?;
 
             let mut next_block_candidate = function_view.cfg().next_block(block_id);
             // propagate postcondition of this block to successor blocks
             for successor_block_id in function_view.cfg().successors(block_id) {
+                meter.add(Scope::Function, PER_SUCCESSOR_COST)?;
                 match inv_map.get_mut(successor_block_id) {
                     Some(next_block_invariant) => {
                         let join_result = {
                             let old_pre = &mut next_block_invariant.pre;
-                            old_pre.join(&post_state)
+                            old_pre.join(&post_state, meter)?
                         };
                         match join_result {
                             JoinResult::Unchanged => {
@@ -106,6 +118,7 @@ pub trait AbstractInterpreter: TransferFunctions {
                                     .cfg()
                                     .is_back_edge(block_id, *successor_block_id)
                                 {
+                                    meter.add(Scope::Function, PER_BACKEDGE_COST)?;
                                     next_block_candidate = Some(*successor_block_id);
                                 }
                             }
@@ -133,12 +146,14 @@ pub trait AbstractInterpreter: TransferFunctions {
         block_id: BlockId,
         pre_state: &Self::State,
         function_view: &FunctionView,
-    ) -> Result<Self::State, Self::Error> {
+        meter: &mut impl Meter,
+    ) -> PartialVMResult<Self::State> {
+        meter.add(Scope::Function, EXECUTE_BLOCK_BASE_COST)?;
         let mut state_acc = pre_state.clone();
         let block_end = function_view.cfg().block_end(block_id);
         for offset in function_view.cfg().instr_indexes(block_id) {
             let instr = &function_view.code().code[offset as usize];
-            self.execute(&mut state_acc, instr, offset, block_end)?
+            self.execute(&mut state_acc, instr, offset, block_end, meter)?
         }
         Ok(state_acc)
     }
@@ -6,8 +6,13 @@
 //! The overall verification is split between stack_usage_verifier.rs and
 //! abstract_interpreter.rs. CodeUnitVerifier simply orchestrates calls into these two files.
 use crate::{
-    acquires_list_verifier::AcquiresVerifier, control_flow, locals_safety, reference_safety,
-    stack_usage_verifier::StackUsageVerifier, type_safety, verifier::VerifierConfig,
+    acquires_list_verifier::AcquiresVerifier,
+    control_flow, locals_safety,
+    meter::{BoundMeter, Meter, Scope},
+    reference_safety,
+    stack_usage_verifier::StackUsageVerifier,
+    type_safety,
+    verifier::VerifierConfig,
 };
 use move_binary_format::{
     access::ModuleAccess,
@@ -34,14 +39,29 @@ impl<'a> CodeUnitVerifier<'a> {
         verifier_config: &VerifierConfig,
         module: &'a CompiledModule,
     ) -> VMResult<()> {
-        Self::verify_module_impl(verifier_config, module)
+        Self::verify_module_with_meter(
+            verifier_config,
+            module,
+            &mut BoundMeter::new(verifier_config),
+        )
+    }
+
+    /// Verifies the module, charging the work done to `meter`.
+    pub fn verify_module_with_meter(
+        verifier_config: &VerifierConfig,
+        module: &'a CompiledModule,
+        meter: &mut impl Meter,
+    ) -> VMResult<()> {
+        Self::verify_module_impl(verifier_config, module, meter)
             .map_err(|e| e.finish(Location::Module(module.self_id())))
     }
 
     fn verify_module_impl(
         verifier_config: &VerifierConfig,
         module: &CompiledModule,
+        meter: &mut impl Meter,
     ) -> PartialVMResult<()> {
+        meter.enter_scope(module.self_id().name().as_str(), Scope::Module);
         let mut name_def_map = HashMap::new();
         for (idx, func_def) in module.function_defs().iter().enumerate() {
             let fh = module.function_handle_at(func_def.function);
@@ -55,6 +75,7 @@ impl<'a> CodeUnitVerifier<'a> {
                 function_definition,
                 module,
                 &name_def_map,
+                meter,
             )
             .map_err(|err| err.at_index(IndexKind::FunctionDefinition, index.0))?
         }
@@ -65,13 +86,32 @@ impl<'a> CodeUnitVerifier<'a> {
         verifier_config: &VerifierConfig,
         module: &'a CompiledScript,
     ) -> VMResult<()> {
-        Self::verify_script_impl(verifier_config, module).map_err(|e| e.finish(Location::Script))
+        Self::verify_script_with_meter(
+            verifier_config,
+            module,
+            &mut BoundMeter::new(verifier_config),
+        )
+    }
+
+    /// Verifies the script, charging the work done to `meter`.
+    pub fn verify_script_with_meter(
+        verifier_config: &VerifierConfig,
+        module: &'a CompiledScript,
+        meter: &mut impl Meter,
+    ) -> VMResult<()> {
+        Self::verify_script_impl(verifier_config, module, meter)
+            .map_err(|e| e.finish(Location::Script))
     }
 
     fn verify_script_impl(
         verifier_config: &VerifierConfig,
         script: &'a CompiledScript,
+        meter: &mut impl Meter,
     ) -> PartialVMResult<()> {
+        // a script is metered as a module with a single function
+        meter.enter_scope("script", Scope::Module);
+        meter.enter_scope("main", Scope::Function);
+
         // create `FunctionView` and `BinaryIndexedView`
         let function_view = control_flow::verify_script(verifier_config, script)?;
         let resolver = BinaryIndexedView::Script(script);
@@ -82,7 +122,7 @@ impl<'a> CodeUnitVerifier<'a> {
             function_view,
             name_def_map: &name_def_map,
         };
-        code_unit_verifier.verify_common(verifier_config)
+        code_unit_verifier.verify_common(verifier_config, meter)
     }
 
     fn verify_function(
@@ -91,7 +131,14 @@ impl<'a> CodeUnitVerifier<'a> {
         function_definition: &FunctionDefinition,
         module: &CompiledModule,
         name_def_map: &HashMap<IdentifierIndex, FunctionDefinitionIndex>,
+        meter: &mut impl Meter,
     ) -> PartialVMResult<()> {
+        meter.enter_scope(
+            module
+                .identifier_at(module.function_handle_at(function_definition.function).name)
+                .as_str(),
+            Scope::Function,
+        );
         // nothing to verify for native function
         let code = match &function_definition.code {
             Some(code) => code,
@@ -122,14 +169,23 @@ impl<'a> CodeUnitVerifier<'a> {
             function_view,
             name_def_map,
         };
-        code_unit_verifier.verify_common(verifier_config)?;
+        code_unit_verifier.verify_common(verifier_config, meter)?;
         AcquiresVerifier::verify(module, index, function_definition)
     }
 
-    fn verify_common(&self, verifier_config: &VerifierConfig) -> PartialVMResult<()> {
+    fn verify_common(
+        &self,
+        verifier_config: &VerifierConfig,
+        meter: &mut impl Meter,
+    ) -> PartialVMResult<()> {
         StackUsageVerifier::verify(verifier_config, &self.resolver, &self.function_view)?;
-        type_safety::verify(&self.resolver, &self.function_view)?;
-        locals_safety::verify(&self.resolver, &self.function_view)?;
-        reference_safety::verify(&self.resolver, &self.function_view, self.name_def_map)
+        type_safety::verify(&self.resolver, &self.function_view, meter)?;
+        locals_safety::verify(&self.resolver, &self.function_view, meter)?;
+        reference_safety::verify(
+            &self.resolver,
+            &self.function_view,
+            self.name_def_map,
+            meter,
+        )
     }
 }
@@ -21,6 +21,7 @@ pub mod instantiation_loops;
 pub mod instruction_consistency;
 pub mod limits;
 pub mod loop_summary;
+pub mod meter;
 pub mod script_signature;
 pub mod signature;
 pub mod struct_defs;
@@ -35,8 +36,8 @@ pub use script_signature::{
 pub use signature::SignatureChecker;
 pub use struct_defs::RecursiveStructDefChecker;
 pub use verifier::{
-    verify_module, verify_module_with_config, verify_script, verify_script_with_config,
-    VerifierConfig,
+    verify_module, verify_module_with_config, verify_module_with_config_metered, verify_script,
+    verify_script_with_config, verify_script_with_config_metered, VerifierConfig, VERIFIER_VERSION,
 };
 
 mod acquires_list_verifier;
@@ -4,7 +4,10 @@
 
 //! This module defines the abstract state for the local safety analysis.
 
-use crate::absint::{AbstractDomain, JoinResult};
+use crate::{
+    absint::{AbstractDomain, JoinResult},
+    meter::{Meter, Scope},
+};
 use move_binary_format::{
     binary_views::{BinaryIndexedView, FunctionView},
     errors::{PartialVMError, PartialVMResult},
@@ -12,6 +15,10 @@ use move_binary_format::{
 };
 use move_core_types::vm_status::StatusCode;
 
+// Costs for metering the join of two states, which is linear in their locals
+const JOIN_BASE_COST: u128 = 10;
+const JOIN_PER_LOCAL_COST: u128 = 5;
+
 /// LocalState represents the current assignment state of a local
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub(crate) enum LocalState {
@@ -130,7 +137,17 @@ impl AbstractState {
 
 impl AbstractDomain for AbstractState {
     /// attempts to join state to self and returns the result
-    fn join(&mut self, state: &AbstractState) -> JoinResult {
+    fn join(
+        &mut self,
+        state: &AbstractState,
+        meter: &mut impl Meter,
+    ) -> PartialVMResult<JoinResult> {
+        meter.add(Scope::Function, JOIN_BASE_COST)?;
+        meter.add_items(
+            Scope::Function,
+            JOIN_PER_LOCAL_COST,
+            self.local_states.len(),
+        )?;
         let joined = Self::join_(self, state);
         assert!(self.local_states.len() == joined.local_states.len());
         let locals_unchanged = self
@@ -139,10 +156,10 @@ impl AbstractDomain for AbstractState {
             .zip(&joined.local_states)
             .all(|(self_state, other_state)| self_state == other_state);
         if locals_unchanged {
-            JoinResult::Unchanged
+            Ok(JoinResult::Unchanged)
         } else {
             *self = joined;
-            JoinResult::Changed
+            Ok(JoinResult::Changed)
         }
     }
 }
@@ -8,21 +8,29 @@
 
 mod abstract_state;
 
-use crate::absint::{AbstractInterpreter, TransferFunctions};
+use crate::{
+    absint::{AbstractInterpreter, TransferFunctions},
+    meter::{Meter, Scope},
+};
 use abstract_state::{AbstractState, LocalState};
 use move_binary_format::{
     binary_views::{BinaryIndexedView, FunctionView},
-    errors::{PartialVMError, PartialVMResult},
+    errors::PartialVMResult,
     file_format::{Bytecode, CodeOffset},
 };
 use move_core_types::vm_status::StatusCode;
 
+// Costs for metering the execution of an instruction. Only returns inspect all locals.
+const STEP_BASE_COST: u128 = 1;
+const RET_PER_LOCAL_COST: u128 = 5;
+
 pub(crate) fn verify<'a>(
     resolver: &BinaryIndexedView,
     function_view: &'a FunctionView<'a>,
+    meter: &mut impl Meter,
 ) -> PartialVMResult<()> {
     let initial_state = AbstractState::new(resolver, function_view)?;
-    LocalsSafetyAnalysis().analyze_function(initial_state, function_view)
+    LocalsSafetyAnalysis().analyze_function(initial_state, function_view, meter)
 }
 
 fn execute_inner(
@@ -160,7 +168,6 @@ struct LocalsSafetyAnalysis();
 
 impl TransferFunctions for LocalsSafetyAnalysis {
     type State = AbstractState;
-    type Error = PartialVMError;
 
     fn execute(
         &mut self,
@@ -168,7 +175,16 @@ impl TransferFunctions for LocalsSafetyAnalysis {
         bytecode: &Bytecode,
         index: CodeOffset,
         _last_index: CodeOffset,
+        meter: &mut impl Meter,
     ) -> PartialVMResult<()> {
+        meter.add(Scope::Function, STEP_BASE_COST)?;
+        if matches!(bytecode, Bytecode::Ret) {
+            meter.add_items(
+                Scope::Function,
+                RET_PER_LOCAL_COST,
+                state.local_states().len(),
+            )?;
+        }
         execute_inner(state, bytecode, index)
     }
 }
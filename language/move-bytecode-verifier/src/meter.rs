@@ -0,0 +1,137 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Metering of the bytecode verifier.
+//!
+//! The abstract interpreters of the code unit verifier charge a meter for each step, join, and
+//! borrow graph operation they perform, in proportion to the size of the abstract state they
+//! operate on. A `BoundMeter` aborts verification with `PROGRAM_TOO_COMPLEX` once the units
+//! spent on a function or a module exceed the budgets in the `VerifierConfig`, bounding the cost
+//! of verifying adversarial modules.
+
+use crate::VerifierConfig;
+use move_binary_format::errors::{PartialVMError, PartialVMResult};
+use move_core_types::vm_status::StatusCode;
+
+/// The scope units are charged to. Units charged to a function are also charged to its module.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scope {
+    Module,
+    Function,
+}
+
+/// A meter for the work done by the verifier.
+pub trait Meter {
+    /// Indicates the begin of a new scope with the given name, which resets the units of the
+    /// scope.
+    fn enter_scope(&mut self, name: &str, scope: Scope);
+
+    /// Adds the number of units to the scope, failing if its budget is exceeded.
+    fn add(&mut self, scope: Scope, units: u128) -> PartialVMResult<()>;
+
+    /// Adds the number of units per item times the number of items to the scope.
+    fn add_items(
+        &mut self,
+        scope: Scope,
+        units_per_item: u128,
+        items: usize,
+    ) -> PartialVMResult<()> {
+        if items == 0 {
+            return Ok(());
+        }
+        self.add(scope, units_per_item.saturating_mul(items as u128))
+    }
+}
+
+/// A meter which enforces the budgets of a `VerifierConfig`.
+pub struct BoundMeter {
+    mod_bounds: Bounds,
+    fun_bounds: Bounds,
+}
+
+struct Bounds {
+    name: String,
+    units: u128,
+    max: Option<u128>,
+}
+
+impl Bounds {
+    fn new(max: Option<u128>) -> Self {
+        Self {
+            name: String::new(),
+            units: 0,
+            max,
+        }
+    }
+
+    fn add(&mut self, units: u128) -> PartialVMResult<()> {
+        let new_units = self.units.saturating_add(units);
+        if let Some(max) = self.max {
+            if new_units > max {
+                return Err(
+                    PartialVMError::new(StatusCode::PROGRAM_TOO_COMPLEX).with_message(format!(
+                        "program too complex (in `{}` with `{} current + {} new > {} max`)",
+                        self.name, self.units, units, max
+                    )),
+                );
+            }
+        }
+        self.units = new_units;
+        Ok(())
+    }
+}
+
+impl BoundMeter {
+    pub fn new(config: &VerifierConfig) -> Self {
+        Self {
+            mod_bounds: Bounds::new(config.max_per_mod_meter_units),
+            fun_bounds: Bounds::new(config.max_per_fun_meter_units),
+        }
+    }
+
+    /// Returns the units spent in the current scope of the given kind.
+    pub fn get_usage(&self, scope: Scope) -> u128 {
+        self.bounds(scope).units
+    }
+
+    /// Returns the budget of scopes of the given kind, if any.
+    pub fn get_limit(&self, scope: Scope) -> Option<u128> {
+        self.bounds(scope).max
+    }
+
+    fn bounds(&self, scope: Scope) -> &Bounds {
+        match scope {
+            Scope::Module => &self.mod_bounds,
+            Scope::Function => &self.fun_bounds,
+        }
+    }
+}
+
+impl Meter for BoundMeter {
+    fn enter_scope(&mut self, name: &str, scope: Scope) {
+        let bounds = match scope {
+            Scope::Module => &mut self.mod_bounds,
+            Scope::Function => &mut self.fun_bounds,
+        };
+        bounds.name = name.to_string();
+        bounds.units = 0;
+    }
+
+    fn add(&mut self, scope: Scope, units: u128) -> PartialVMResult<()> {
+        if scope == Scope::Function {
+            self.fun_bounds.add(units)?;
+        }
+        self.mod_bounds.add(units)
+    }
+}
+
+/// A meter which does not count anything.
+pub struct DummyMeter;
+
+impl Meter for DummyMeter {
+    fn enter_scope(&mut self, _name: &str, _scope: Scope) {}
+
+    fn add(&mut self, _scope: Scope, _units: u128) -> PartialVMResult<()> {
+        Ok(())
+    }
+}
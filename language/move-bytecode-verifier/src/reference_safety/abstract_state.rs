@@ -3,7 +3,10 @@
 // SPDX-License-Identifier: Apache-2.0
 
 //! This module defines the abstract state for the type and memory safety analysis.
-use crate::absint::{AbstractDomain, JoinResult};
+use crate::{
+    absint::{AbstractDomain, JoinResult},
+    meter::{Meter, Scope},
+};
 use move_binary_format::{
     binary_views::FunctionView,
     errors::{PartialVMError, PartialVMResult},
@@ -19,6 +22,11 @@ use std::collections::{BTreeMap, BTreeSet};
 
 type BorrowGraph = move_borrow_graph::graph::BorrowGraph<(), Label>;
 
+// Costs for metering the join of two states, which is linear in their locals and borrow graphs
+const JOIN_BASE_COST: u128 = 100;
+const JOIN_PER_LOCAL_COST: u128 = 10;
+const JOIN_PER_GRAPH_ITEM_COST: u128 = 50;
+
 /// AbstractValue represents a reference or a non reference value, both on the stack and stored
 /// in a local
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -119,6 +127,16 @@ impl AbstractState {
         )
     }
 
+    /// returns the number of locals, for metering
+    pub fn local_count(&self) -> usize {
+        self.locals.len()
+    }
+
+    /// returns the size of the borrow graph, for metering
+    pub fn graph_size(&self) -> usize {
+        self.borrow_graph.graph_size()
+    }
+
     //**********************************************************************************************
     // Core API
     //**********************************************************************************************
@@ -669,7 +687,18 @@ impl AbstractState {
 
 impl AbstractDomain for AbstractState {
     /// attempts to join state to self and returns the result
-    fn join(&mut self, state: &AbstractState) -> JoinResult {
+    fn join(
+        &mut self,
+        state: &AbstractState,
+        meter: &mut impl Meter,
+    ) -> PartialVMResult<JoinResult> {
+        meter.add(Scope::Function, JOIN_BASE_COST)?;
+        meter.add_items(Scope::Function, JOIN_PER_LOCAL_COST, self.locals.len())?;
+        meter.add_items(
+            Scope::Function,
+            JOIN_PER_GRAPH_ITEM_COST,
+            self.graph_size() + state.graph_size(),
+        )?;
         let joined = Self::join_(self, state);
         assert!(joined.is_canonical());
         assert!(self.locals.len() == joined.locals.len());
@@ -681,10 +710,10 @@ impl AbstractDomain for AbstractState {
         // locals unchanged and borrow graph covered, return unchanged
         // else mark as changed and update the state
         if locals_unchanged && self.borrow_graph.leq(&joined.borrow_graph) {
-            JoinResult::Unchanged
+            Ok(JoinResult::Unchanged)
         } else {
             *self = joined;
-            JoinResult::Changed
+            Ok(JoinResult::Changed)
         }
     }
 }
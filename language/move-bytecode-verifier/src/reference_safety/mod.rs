@@ -10,7 +10,10 @@
 
 mod abstract_state;
 
-use crate::absint::{AbstractInterpreter, TransferFunctions};
+use crate::{
+    absint::{AbstractInterpreter, TransferFunctions},
+    meter::{Meter, Scope},
+};
 use abstract_state::{AbstractState, AbstractValue};
 use move_binary_format::{
     binary_views::{BinaryIndexedView, FunctionView},
@@ -24,6 +27,12 @@ use move_binary_format::{
 use move_core_types::vm_status::StatusCode;
 use std::collections::{BTreeSet, HashMap};
 
+// Costs for metering the execution of an instruction, which works on the locals and the borrow
+// graph of the state
+const STEP_BASE_COST: u128 = 10;
+const STEP_PER_LOCAL_COST: u128 = 20;
+const STEP_PER_GRAPH_ITEM_COST: u128 = 50;
+
 struct ReferenceSafetyAnalysis<'a> {
     resolver: &'a BinaryIndexedView<'a>,
     function_view: &'a FunctionView<'a>,
@@ -50,11 +59,12 @@ pub(crate) fn verify<'a>(
     resolver: &'a BinaryIndexedView<'a>,
     function_view: &FunctionView,
     name_def_map: &'a HashMap<IdentifierIndex, FunctionDefinitionIndex>,
+    meter: &mut impl Meter,
 ) -> PartialVMResult<()> {
     let initial_state = AbstractState::new(function_view);
 
     let mut verifier = ReferenceSafetyAnalysis::new(resolver, function_view, name_def_map);
-    verifier.analyze_function(initial_state, function_view)
+    verifier.analyze_function(initial_state, function_view, meter)
 }
 
 fn call(
@@ -409,7 +419,6 @@ fn execute_inner(
 
 impl<'a> TransferFunctions for ReferenceSafetyAnalysis<'a> {
     type State = AbstractState;
-    type Error = PartialVMError;
 
     fn execute(
         &mut self,
@@ -417,7 +426,15 @@ impl<'a> TransferFunctions for ReferenceSafetyAnalysis<'a> {
         bytecode: &Bytecode,
         index: CodeOffset,
         last_index: CodeOffset,
+        meter: &mut impl Meter,
     ) -> PartialVMResult<()> {
+        meter.add(Scope::Function, STEP_BASE_COST)?;
+        meter.add_items(Scope::Function, STEP_PER_LOCAL_COST, state.local_count())?;
+        meter.add_items(
+            Scope::Function,
+            STEP_PER_GRAPH_ITEM_COST,
+            state.graph_size(),
+        )?;
         execute_inner(self, state, bytecode, index)?;
         if index == last_index {
             safe_assert!(self.stack.is_empty());
@@ -4,11 +4,17 @@
 
 //! This module contains the public APIs supported by the bytecode verifier.
 use crate::{
-    ability_field_requirements, check_duplication::DuplicationChecker,
-    code_unit_verifier::CodeUnitVerifier, constants, friends,
-    instantiation_loops::InstantiationLoopChecker, instruction_consistency::InstructionConsistency,
-    limits::LimitsVerifier, script_signature,
-    script_signature::no_additional_script_signature_checks, signature::SignatureChecker,
+    ability_field_requirements,
+    check_duplication::DuplicationChecker,
+    code_unit_verifier::CodeUnitVerifier,
+    constants, friends,
+    instantiation_loops::InstantiationLoopChecker,
+    instruction_consistency::InstructionConsistency,
+    limits::LimitsVerifier,
+    meter::{BoundMeter, Meter},
+    script_signature,
+    script_signature::no_additional_script_signature_checks,
+    signature::SignatureChecker,
     struct_defs::RecursiveStructDefChecker,
 };
 use move_binary_format::{
@@ -17,6 +23,12 @@ use move_binary_format::{
     file_format::{CompiledModule, CompiledScript},
 };
 use move_core_types::{state::VMState, vm_status::StatusCode};
+use std::panic::AssertUnwindSafe;
+
+/// The version of the checks performed by the verifier. It must be bumped whenever the verifier
+/// accepts or rejects different modules, so that records of modules which passed an older
+/// verifier, such as persisted verified module caches, are not trusted by newer ones.
+pub const VERIFIER_VERSION: u32 = 1;
 
 #[derive(Debug, Clone)]
 pub struct VerifierConfig {
@@ -31,6 +43,8 @@ pub struct VerifierConfig {
     pub max_struct_definitions: Option<usize>,
     pub max_fields_in_struct: Option<usize>,
     pub max_function_definitions: Option<usize>,
+    pub max_per_fun_meter_units: Option<u128>,
+    pub max_per_mod_meter_units: Option<u128>,
 }
 
 /// Helper for a "canonical" verification of a module.
@@ -48,8 +62,18 @@ pub fn verify_module(module: &CompiledModule) -> VMResult<()> {
 }
 
 pub fn verify_module_with_config(config: &VerifierConfig, module: &CompiledModule) -> VMResult<()> {
+    verify_module_with_config_metered(config, module, &mut BoundMeter::new(config))
+}
+
+/// Like `verify_module_with_config`, but charges the work done by the code unit verifier to
+/// `meter` instead of a `BoundMeter` for the budgets of the config.
+pub fn verify_module_with_config_metered(
+    config: &VerifierConfig,
+    module: &CompiledModule,
+    meter: &mut impl Meter,
+) -> VMResult<()> {
     let prev_state = move_core_types::state::set_state(VMState::VERIFIER);
-    let result = std::panic::catch_unwind(|| {
+    let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
         BoundsChecker::verify_module(module).map_err(|e| {
             // We can't point the error at the module, because if bounds-checking
             // failed, we cannot safely index into module's handle to itself.
@@ -64,13 +88,13 @@ pub fn verify_module_with_config(config: &VerifierConfig, module: &CompiledModul
         ability_field_requirements::verify_module(module)?;
         RecursiveStructDefChecker::verify_module(module)?;
         InstantiationLoopChecker::verify_module(module)?;
-        CodeUnitVerifier::verify_module(config, module)?;
+        CodeUnitVerifier::verify_module_with_meter(config, module, meter)?;
 
         // Add the failpoint injection to test the catch_unwind behavior.
         fail::fail_point!("verifier-failpoint-panic");
 
         script_signature::verify_module(module, no_additional_script_signature_checks)
-    })
+    }))
     .unwrap_or_else(|_| {
         Err(
             PartialVMError::new(StatusCode::VERIFIER_INVARIANT_VIOLATION)
@@ -97,17 +121,27 @@ pub fn verify_script(script: &CompiledScript) -> VMResult<()> {
 }
 
 pub fn verify_script_with_config(config: &VerifierConfig, script: &CompiledScript) -> VMResult<()> {
+    verify_script_with_config_metered(config, script, &mut BoundMeter::new(config))
+}
+
+/// Like `verify_script_with_config`, but charges the work done by the code unit verifier to
+/// `meter` instead of a `BoundMeter` for the budgets of the config.
+pub fn verify_script_with_config_metered(
+    config: &VerifierConfig,
+    script: &CompiledScript,
+    meter: &mut impl Meter,
+) -> VMResult<()> {
     let prev_state = move_core_types::state::set_state(VMState::VERIFIER);
-    let result = std::panic::catch_unwind(|| {
+    let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
         BoundsChecker::verify_script(script).map_err(|e| e.finish(Location::Script))?;
         LimitsVerifier::verify_script(config, script)?;
         DuplicationChecker::verify_script(script)?;
         SignatureChecker::verify_script(script)?;
         InstructionConsistency::verify_script(script)?;
         constants::verify_script(script)?;
-        CodeUnitVerifier::verify_script(config, script)?;
+        CodeUnitVerifier::verify_script_with_meter(config, script, meter)?;
         script_signature::verify_script(script, no_additional_script_signature_checks)
-    })
+    }))
     .unwrap_or_else(|_| {
         Err(
             PartialVMError::new(StatusCode::VERIFIER_INVARIANT_VIOLATION)
@@ -139,6 +173,10 @@ impl Default for VerifierConfig {
             max_fields_in_struct: None,
             // Max count of functions in a module
             max_function_definitions: None,
+            // Max units of work the code unit verifier may spend on a function, see `meter`
+            max_per_fun_meter_units: None,
+            // Max units of work the code unit verifier may spend on a module, see `meter`
+            max_per_mod_meter_units: None,
         }
     }
 }
@@ -0,0 +1,468 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Arguments of transactions, typed by the parameters they are passed to.
+//!
+//! An argument is written either in the syntax of `ParsedValue`, or as JSON. It is converted into
+//! a `MoveValue` that follows the `MoveTypeLayout` of its parameter, so that it can be BCS-encoded
+//! with `MoveValue::simple_serialize`. Struct layouts must be built with their types, as values of
+//! `std::string::String`, `std::ascii::String` and `std::option::Option` are written differently
+//! from other structs.
+
+use crate::values::{ParsableValue, ParsedValue};
+use anyhow::{anyhow, bail, Result};
+use move_core_types::{
+    account_address::AccountAddress,
+    language_storage::{StructTag, TypeTag},
+    u256::U256,
+    value::{MoveFieldLayout, MoveStruct, MoveStructLayout, MoveTypeLayout, MoveValue},
+};
+use std::{collections::BTreeMap, convert::TryInto};
+
+/// An argument, written either as a `ParsedValue` or as JSON.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ArgumentValue {
+    Parsed(ParsedValue),
+    /// A number written in hex without a suffix, e.g. `0x1`, which is an integer or an address.
+    HexNum(U256),
+    Json(serde_json::Value),
+}
+
+impl ArgumentValue {
+    /// Parse `s` as a JSON string, array, object or `null`, and otherwise as a `ParsedValue`, or
+    /// as JSON if it is not one. JSON numbers are only used for values which are not
+    /// `ParsedValue`s, as they lose precision beyond `u64`. A hex number without a suffix is an
+    /// address if its parameter is one, as in earlier versions of the CLI.
+    pub fn parse(s: &str) -> Result<Self> {
+        if let Ok(value) = serde_json::from_str::<serde_json::Value>(s) {
+            if !matches!(
+                value,
+                serde_json::Value::Bool(_) | serde_json::Value::Number(_)
+            ) {
+                return Ok(Self::Json(value));
+            }
+        }
+        match ParsedValue::parse(s) {
+            Ok(ParsedValue::InferredNum(n)) if s.trim_start().starts_with("0x") => {
+                Ok(Self::HexNum(n))
+            }
+            Ok(value) => Ok(Self::Parsed(value)),
+            Err(parse_error) => match serde_json::from_str(s) {
+                Ok(value) => Ok(Self::Json(value)),
+                Err(json_error) => bail!(
+                    "Invalid argument '{}': it is neither a value ({}) nor JSON ({})",
+                    s,
+                    parse_error,
+                    json_error
+                ),
+            },
+        }
+    }
+
+    /// Convert the argument into a value of the type described by `layout`.
+    pub fn into_typed_value(
+        self,
+        layout: &MoveTypeLayout,
+        mapping: &impl Fn(&str) -> Option<AccountAddress>,
+    ) -> Result<MoveValue> {
+        match self {
+            Self::Parsed(value) => value.into_typed_value(layout, mapping),
+            Self::HexNum(n) => match layout {
+                MoveTypeLayout::Address => Ok(MoveValue::Address(number_into_address(n)?)),
+                _ => number_into_typed_value(n, layout),
+            },
+            Self::Json(value) => json_into_typed_value(value, layout, mapping),
+        }
+    }
+}
+
+impl<Extra: ParsableValue> ParsedValue<Extra> {
+    /// Convert the value into a value of the type described by `layout`. Numbers without a suffix
+    /// take the integer type of the layout, addresses are written with `@`, and byte strings
+    /// are accepted as strings. An `Option` is written as a vector of at most one element.
+    /// Custom values have no known type, and are rejected.
+    pub fn into_typed_value(
+        self,
+        layout: &MoveTypeLayout,
+        mapping: &impl Fn(&str) -> Option<AccountAddress>,
+    ) -> Result<MoveValue> {
+        use MoveTypeLayout as L;
+        Ok(match (layout, self) {
+            (L::Bool, ParsedValue::Bool(b)) => MoveValue::Bool(b),
+            (_, ParsedValue::InferredNum(n)) => number_into_typed_value(n, layout)?,
+            (L::U8, ParsedValue::U8(n)) => MoveValue::U8(n),
+            (L::U16, ParsedValue::U16(n)) => MoveValue::U16(n),
+            (L::U32, ParsedValue::U32(n)) => MoveValue::U32(n),
+            (L::U64, ParsedValue::U64(n)) => MoveValue::U64(n),
+            (L::U128, ParsedValue::U128(n)) => MoveValue::U128(n),
+            (L::U256, ParsedValue::U256(n)) => MoveValue::U256(n),
+            (L::Address, ParsedValue::Address(a)) => {
+                MoveValue::Address(a.into_account_address(mapping)?)
+            }
+            (L::Vector(elem), ParsedValue::Vector(elems)) => MoveValue::Vector(
+                elems
+                    .into_iter()
+                    .map(|e| e.into_typed_value(elem, mapping))
+                    .collect::<Result<_>>()?,
+            ),
+            (L::Struct(s), value) => {
+                let (tag, fields) = struct_layout(s)?;
+                match value {
+                    ParsedValue::Vector(elems) if is_string(tag) => {
+                        let bytes = elems
+                            .into_iter()
+                            .map(|e| e.into_typed_value(&L::U8, mapping))
+                            .collect::<Result<_>>()?;
+                        string_value(tag, MoveValue::vec_to_vec_u8(bytes)?)?
+                    }
+                    ParsedValue::Vector(elems) if is_option(tag) => {
+                        if elems.len() > 1 {
+                            bail!(
+                                "Expected a value of type {}, got a vector of {} elements",
+                                type_name(layout),
+                                elems.len()
+                            )
+                        }
+                        MoveValue::Struct(MoveStruct::Runtime(vec![ParsedValue::Vector(elems)
+                            .into_typed_value(&fields[0].layout, mapping)?]))
+                    }
+                    ParsedValue::Struct(address, module, name, values) => {
+                        if address.into_account_address(mapping)? != tag.address
+                            || module != tag.module.as_str()
+                            || name != tag.name.as_str()
+                        {
+                            bail!(
+                                "Expected a value of type {}, got a value of struct {}::{}",
+                                type_name(layout),
+                                module,
+                                name
+                            )
+                        }
+                        struct_value(layout, fields, values, |value, layout| {
+                            value.into_typed_value(layout, mapping)
+                        })?
+                    }
+                    _ => bail!("Expected a value of type {}", type_name(layout)),
+                }
+            }
+            _ => bail!("Expected a value of type {}", type_name(layout)),
+        })
+    }
+}
+
+/// Convert a JSON value into a value of the type described by `layout`. Integers and addresses
+/// can also be written as strings, in the syntax of `ParsedValue`, which is needed for integers
+/// that do not fit a JSON number. An address is a string, which is a named address, a hex
+/// literal, or an `@`-address, and never a number. A string is accepted for a `vector<u8>`,
+/// which is its UTF-8 encoding, or the bytes it gives in hex if it starts with `0x`. An `Option`
+/// is `null`, or the value it holds.
+pub fn json_into_typed_value(
+    value: serde_json::Value,
+    layout: &MoveTypeLayout,
+    mapping: &impl Fn(&str) -> Option<AccountAddress>,
+) -> Result<MoveValue> {
+    use serde_json::Value as J;
+    use MoveTypeLayout as L;
+    Ok(match (layout, value) {
+        (L::Bool, J::Bool(b)) => MoveValue::Bool(b),
+        (L::U8 | L::U16 | L::U32 | L::U64 | L::U128 | L::U256 | L::Address, J::Number(n)) => {
+            match n.as_u64() {
+                Some(n) => number_into_typed_value(U256::from(n), layout)?,
+                None => bail!(
+                    "Expected a value of type {}, got the number {}",
+                    type_name(layout),
+                    n
+                ),
+            }
+        }
+        (L::Address, J::String(s)) if mapping(&s).is_some() => {
+            MoveValue::Address(mapping(&s).unwrap())
+        }
+        (L::Address, J::String(s)) if s.starts_with("0x") => {
+            MoveValue::Address(AccountAddress::from_hex_literal(&s)?)
+        }
+        (L::U8 | L::U16 | L::U32 | L::U64 | L::U128 | L::U256 | L::Address, J::String(s)) => {
+            ParsedValue::<()>::parse(&s)?.into_typed_value(layout, mapping)?
+        }
+        (L::Vector(elem), J::String(s)) if matches!(**elem, L::U8) => {
+            MoveValue::vector_u8(string_bytes(s)?)
+        }
+        (L::Vector(elem), J::Array(elems)) => MoveValue::Vector(
+            elems
+                .into_iter()
+                .map(|e| json_into_typed_value(e, elem, mapping))
+                .collect::<Result<_>>()?,
+        ),
+        (L::Struct(s), value) => {
+            let (tag, fields) = struct_layout(s)?;
+            match value {
+                J::String(s) if is_string(tag) => string_value(tag, s.into_bytes())?,
+                value if is_option(tag) => {
+                    let elem = match &fields[0].layout {
+                        L::Vector(elem) => elem,
+                        _ => bail!("Unexpected layout for type {}", type_name(layout)),
+                    };
+                    let elems = match value {
+                        J::Null => vec![],
+                        value => vec![json_into_typed_value(value, elem, mapping)?],
+                    };
+                    MoveValue::Struct(MoveStruct::Runtime(vec![MoveValue::Vector(elems)]))
+                }
+                J::Object(values) => struct_value(
+                    layout,
+                    fields,
+                    values.into_iter().collect(),
+                    |value, layout| json_into_typed_value(value, layout, mapping),
+                )?,
+                _ => bail!("Expected a value of type {}", type_name(layout)),
+            }
+        }
+        (_, value) => bail!(
+            "Expected a value of type {}, got {}",
+            type_name(layout),
+            value
+        ),
+    })
+}
+
+fn number_into_typed_value(n: U256, layout: &MoveTypeLayout) -> Result<MoveValue> {
+    use MoveTypeLayout as L;
+    let out_of_range = || anyhow!("{} is out of range for type {}", n, type_name(layout));
+    Ok(match layout {
+        L::U8 => MoveValue::U8(n.try_into().map_err(|_| out_of_range())?),
+        L::U16 => MoveValue::U16(n.try_into().map_err(|_| out_of_range())?),
+        L::U32 => MoveValue::U32(n.try_into().map_err(|_| out_of_range())?),
+        L::U64 => MoveValue::U64(n.try_into().map_err(|_| out_of_range())?),
+        L::U128 => MoveValue::U128(n.try_into().map_err(|_| out_of_range())?),
+        L::U256 => MoveValue::U256(n),
+        // A decimal number is not taken as an address, so that arguments passed in the wrong
+        // order are not silently accepted.
+        L::Address => bail!(
+            "Expected a value of type address, got the number {}. Addresses are written with \
+             `@`, e.g. `@0x{:x}`",
+            n,
+            n
+        ),
+        _ => bail!(
+            "Expected a value of type {}, got a number",
+            type_name(layout)
+        ),
+    })
+}
+
+/// The address `n`, written as a number.
+fn number_into_address(n: U256) -> Result<AccountAddress> {
+    // The bytes of an address are big endian.
+    let bytes = n.to_le_bytes();
+    let (low, high) = bytes.split_at(AccountAddress::LENGTH);
+    if high.iter().any(|b| *b != 0) {
+        bail!("{} is out of range for type address", n)
+    }
+    let mut bytes = low.to_vec();
+    bytes.reverse();
+    Ok(AccountAddress::from_bytes(bytes)?)
+}
+
+/// The values of the fields of a struct of type `layout`, in the order of their declaration.
+fn struct_value<V>(
+    layout: &MoveTypeLayout,
+    fields: &[MoveFieldLayout],
+    mut values: BTreeMap<String, V>,
+    into_typed_value: impl Fn(V, &MoveTypeLayout) -> Result<MoveValue>,
+) -> Result<MoveValue> {
+    let values_in_order = fields
+        .iter()
+        .map(|field| match values.remove(field.name.as_str()) {
+            Some(value) => into_typed_value(value, &field.layout),
+            None => bail!(
+                "Missing field '{}' in value of type {}",
+                field.name,
+                type_name(layout)
+            ),
+        })
+        .collect::<Result<_>>()?;
+    if let Some(name) = values.keys().next() {
+        bail!(
+            "Unknown field '{}' in value of type {}",
+            name,
+            type_name(layout)
+        )
+    }
+    Ok(MoveValue::Struct(MoveStruct::Runtime(values_in_order)))
+}
+
+fn struct_layout(layout: &MoveStructLayout) -> Result<(&StructTag, &[MoveFieldLayout])> {
+    match layout {
+        MoveStructLayout::WithTypes { type_, fields } => Ok((type_, fields)),
+        _ => bail!("The layouts of struct arguments must have their types"),
+    }
+}
+
+fn is_string(tag: &StructTag) -> bool {
+    tag.address == AccountAddress::ONE
+        && matches!(tag.module.as_str(), "string" | "ascii")
+        && tag.name.as_str() == "String"
+}
+
+fn is_option(tag: &StructTag) -> bool {
+    tag.address == AccountAddress::ONE
+        && tag.module.as_str() == "option"
+        && tag.name.as_str() == "Option"
+}
+
+/// The value of a string of type `tag`, which holds `bytes`.
+fn string_value(tag: &StructTag, bytes: Vec<u8>) -> Result<MoveValue> {
+    if tag.module.as_str() == "ascii" {
+        if !bytes.is_ascii() {
+            bail!("Expected an ASCII string for type {}", tag)
+        }
+    } else if std::str::from_utf8(&bytes).is_err() {
+        bail!("Expected a UTF-8 string for type {}", tag)
+    }
+    Ok(MoveValue::Struct(MoveStruct::Runtime(vec![
+        MoveValue::vector_u8(bytes),
+    ])))
+}
+
+/// The bytes of a JSON string passed for a `vector<u8>`.
+fn string_bytes(s: String) -> Result<Vec<u8>> {
+    match s.strip_prefix("0x") {
+        Some(digits) => Ok(hex::decode(digits)?),
+        None => Ok(s.into_bytes()),
+    }
+}
+
+fn type_name(layout: &MoveTypeLayout) -> String {
+    match TryInto::<TypeTag>::try_into(layout) {
+        Ok(tag) => tag.to_string(),
+        Err(_) => layout.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ArgumentValue;
+    use move_core_types::{
+        account_address::AccountAddress,
+        identifier::Identifier,
+        language_storage::StructTag,
+        value::{MoveFieldLayout, MoveStructLayout, MoveTypeLayout as L},
+    };
+
+    fn std_struct(module: &str, name: &str, type_params: Vec<L>, fields: Vec<(&str, L)>) -> L {
+        L::Struct(MoveStructLayout::WithTypes {
+            type_: StructTag {
+                address: AccountAddress::ONE,
+                module: Identifier::new(module).unwrap(),
+                name: Identifier::new(name).unwrap(),
+                type_params: type_params.iter().map(|l| l.try_into().unwrap()).collect(),
+            },
+            fields: fields
+                .into_iter()
+                .map(|(name, layout)| MoveFieldLayout::new(Identifier::new(name).unwrap(), layout))
+                .collect(),
+        })
+    }
+
+    fn string() -> L {
+        std_struct("string", "String", vec![], vec![("bytes", vector(L::U8))])
+    }
+
+    fn option(elem: L) -> L {
+        std_struct(
+            "option",
+            "Option",
+            vec![elem.clone()],
+            vec![("vec", vector(elem))],
+        )
+    }
+
+    fn vector(elem: L) -> L {
+        L::Vector(Box::new(elem))
+    }
+
+    fn encode(arg: &str, layout: &L) -> anyhow::Result<Vec<u8>> {
+        let mapping = |name: &str| (name == "std").then_some(AccountAddress::ONE);
+        Ok(ArgumentValue::parse(arg)?
+            .into_typed_value(layout, &mapping)?
+            .simple_serialize()
+            .unwrap())
+    }
+
+    #[test]
+    fn tests_typed_arguments_positive() {
+        let point = std_struct("m", "Point", vec![], vec![("y", L::U8), ("x", L::U64)]);
+        let optional = std_struct("m", "Optional", vec![], vec![("o", option(L::U64))]);
+        let cases: &[(&str, L, &[u8])] = &[
+            ("7", L::U8, &[7]),
+            ("7u8", L::U8, &[7]),
+            ("7", L::U16, &[7, 0]),
+            ("true", L::Bool, &[1]),
+            ("vector[1, 2]", vector(L::U8), &[2, 1, 2]),
+            ("[1, 2]", vector(L::U16), &[2, 1, 0, 2, 0]),
+            ("[[1], []]", vector(vector(L::U8)), &[2, 1, 1, 0]),
+            ("b\"hi\"", vector(L::U8), &[2, b'h', b'i']),
+            ("\"hi\"", string(), &[2, b'h', b'i']),
+            ("\"0x0102\"", vector(L::U8), &[2, 1, 2]),
+            ("\"3\"", L::U32, &[3, 0, 0, 0]),
+            ("vector[]", option(L::U64), &[0]),
+            ("vector[3]", option(L::U8), &[1, 3]),
+            ("null", option(L::U8), &[0]),
+            (
+                "{\"x\": 1, \"y\": 2}",
+                point.clone(),
+                &[2, 1, 0, 0, 0, 0, 0, 0, 0],
+            ),
+            (
+                "0x1::m::Point { x: 1, y: 2 }",
+                point,
+                &[2, 1, 0, 0, 0, 0, 0, 0, 0],
+            ),
+            ("{\"o\": 5}", optional.clone(), &[1, 5, 0, 0, 0, 0, 0, 0, 0]),
+            ("{\"o\": null}", optional, &[0]),
+            ("@0x1", L::Address, &AccountAddress::ONE.to_vec()),
+            ("0x1", L::Address, &AccountAddress::ONE.to_vec()),
+            ("0x10", L::U8, &[16]),
+            ("\"0x1\"", L::Address, &AccountAddress::ONE.to_vec()),
+            ("[\"0x2\", \"std\"]", vector(L::Address), &{
+                let mut bytes = vec![2];
+                bytes.extend(AccountAddress::from_hex_literal("0x2").unwrap().to_vec());
+                bytes.extend(AccountAddress::ONE.to_vec());
+                bytes
+            }),
+        ];
+        for (arg, layout, expected) in cases {
+            assert_eq!(&encode(arg, layout).unwrap(), expected, "{}", arg)
+        }
+    }
+
+    #[test]
+    fn tests_typed_arguments_negative() {
+        let cases: &[(&str, L)] = &[
+            ("256", L::U8),
+            ("7u16", L::U8),
+            ("vector[1, 2]", option(L::U8)),
+            ("[1.5]", vector(L::U64)),
+            (
+                "\"\\u00ff\"",
+                std_struct("ascii", "String", vec![], vec![("bytes", vector(L::U8))]),
+            ),
+            (
+                "{\"x\": 1}",
+                std_struct("m", "Point", vec![], vec![("x", L::U8), ("y", L::U8)]),
+            ),
+            (
+                "{\"x\": 1, \"z\": 2}",
+                std_struct("m", "Point", vec![], vec![("x", L::U8)]),
+            ),
+            ("{", L::U8),
+            ("3", L::Address),
+            ("vector[0x3]", vector(L::Address)),
+            ("vector[@0x1, 2]", vector(L::Address)),
+            ("[\"0x1\", 2]", vector(L::Address)),
+        ];
+        for (arg, layout) in cases {
+            assert!(encode(arg, layout).is_err(), "{}", arg)
+        }
+    }
+}
@@ -43,6 +43,8 @@ pub const MOVE_EXTENSION: &str = "move";
 pub const MOVE_IR_EXTENSION: &str = "mvir";
 /// Extension for Move bytecode files
 pub const MOVE_COMPILED_EXTENSION: &str = "mv";
+/// Extension for the lossless assembly form of Move bytecode files
+pub const MOVE_ASSEMBLY_EXTENSION: &str = "masm";
 /// Extension for Move source map files (mappings from source to bytecode)
 pub const SOURCE_MAP_EXTENSION: &str = "mvsm";
 /// Extension for error description map for compiled releases
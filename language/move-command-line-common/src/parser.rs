@@ -296,7 +296,7 @@ impl<'a, I: Iterator<Item = (ValueToken, &'a str)>> Parser<'a, ValueToken, I> {
                         Ok((field, value))
                     },
                     ValueToken::Comma,
-                    ValueToken::RBracket,
+                    ValueToken::RBrace,
                     true,
                 )?;
                 self.advance(ValueToken::RBrace)?;
@@ -8,6 +8,11 @@ use crate::{
 };
 use std::fmt::Debug;
 
+/// The version of a module or resource as reported by a storage backend which tracks versions,
+/// e.g. a multi-version view used to execute transactions in parallel. Versions are opaque to
+/// the VM, which only records them along with the data read.
+pub type Version = u64;
+
 /// Traits for resolving Move modules and resources from persistent storage
 
 /// A persistent storage backend that can resolve modules by address + name.
@@ -23,6 +28,15 @@ pub trait ModuleResolver {
     type Error: Debug;
 
     fn get_module(&self, id: &ModuleId) -> Result<Option<Vec<u8>>, Self::Error>;
+
+    /// Like `get_module`, but also returns the version of the module read, if the backend
+    /// tracks versions.
+    fn get_module_with_version(
+        &self,
+        id: &ModuleId,
+    ) -> Result<(Option<Vec<u8>>, Option<Version>), Self::Error> {
+        Ok((self.get_module(id)?, None))
+    }
 }
 
 /// A persistent storage backend that can resolve resources by address + type
@@ -42,6 +56,16 @@ pub trait ResourceResolver {
         address: &AccountAddress,
         typ: &StructTag,
     ) -> Result<Option<Vec<u8>>, Self::Error>;
+
+    /// Like `get_resource`, but also returns the version of the resource read, if the backend
+    /// tracks versions.
+    fn get_resource_with_version(
+        &self,
+        address: &AccountAddress,
+        typ: &StructTag,
+    ) -> Result<(Option<Vec<u8>>, Option<Version>), Self::Error> {
+        Ok((self.get_resource(address, typ)?, None))
+    }
 }
 
 /// A persistent storage implementation that can resolve both resources and modules
@@ -67,6 +91,14 @@ impl<T: ResourceResolver + ?Sized> ResourceResolver for &T {
     ) -> Result<Option<Vec<u8>>, Self::Error> {
         (**self).get_resource(address, tag)
     }
+
+    fn get_resource_with_version(
+        &self,
+        address: &AccountAddress,
+        tag: &StructTag,
+    ) -> Result<(Option<Vec<u8>>, Option<Version>), Self::Error> {
+        (**self).get_resource_with_version(address, tag)
+    }
 }
 
 impl<T: ModuleResolver + ?Sized> ModuleResolver for &T {
@@ -74,4 +106,11 @@ impl<T: ModuleResolver + ?Sized> ModuleResolver for &T {
     fn get_module(&self, module_id: &ModuleId) -> Result<Option<Vec<u8>>, Self::Error> {
         (**self).get_module(module_id)
     }
+
+    fn get_module_with_version(
+        &self,
+        module_id: &ModuleId,
+    ) -> Result<(Option<Vec<u8>>, Option<Version>), Self::Error> {
+        (**self).get_module_with_version(module_id)
+    }
 }
@@ -608,6 +608,8 @@ pub enum StatusCode {
     MAX_FUNCTION_DEFINITIONS_REACHED = 1119,
     MAX_STRUCT_DEFINITIONS_REACHED = 1120,
     MAX_FIELD_DEFINITIONS_REACHED = 1121,
+    // Reached the verifier's metering budget for a function or module
+    PROGRAM_TOO_COMPLEX = 1122,
 
     // These are errors that the VM might raise if a violation of internal
     // invariants takes place.
@@ -679,6 +681,14 @@ pub enum StatusCode {
     STORAGE_WRITE_LIMIT_REACHED = 4027,
     MEMORY_LIMIT_EXCEEDED = 4028,
     VM_MAX_TYPE_NODES_REACHED = 4029,
+    // A vector would grow beyond the maximal length configured for the VM.
+    VM_MAX_VECTOR_LENGTH_REACHED = 4030,
+    // The values produced by a session exceed the maximal number of bytes configured for the VM.
+    VM_MAX_VALUE_BYTES_REACHED = 4031,
+    // A session emits more events than configured for the VM.
+    VM_MAX_EVENTS_REACHED = 4032,
+    // A generic type or function instantiation has more type nodes than configured for the VM.
+    VM_MAX_TYPE_INSTANTIATION_NODES_REACHED = 4033,
 
     // A reserved status to represent an unknown vm status.
     // this is std::u64::MAX, but we can't pattern match on that, so put the hardcoded value in
@@ -0,0 +1,149 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Rendering of the stack trace of a failed execution as a Move-level backtrace, with function
+//! names and source lines resolved through source maps.
+
+use crate::source_map::SourceMap;
+use move_binary_format::{
+    access::ModuleAccess,
+    errors::ExecutionState,
+    file_format::{CodeOffset, CompiledModule, FunctionDefinitionIndex},
+};
+use move_command_line_common::files::FileHash;
+use move_core_types::language_storage::ModuleId;
+use move_ir_types::location::Loc;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+};
+
+/// Renders backtraces of the code it knows about. Frames of unknown code, or of code without a
+/// source map, are rendered with their bytecode offsets.
+#[derive(Default)]
+pub struct BacktraceRenderer<'a> {
+    modules: BTreeMap<ModuleId, (&'a CompiledModule, Option<&'a SourceMap>)>,
+    script: Option<(&'a str, &'a SourceMap)>,
+    files: HashMap<FileHash, (&'a str, &'a str)>,
+}
+
+impl<'a> BacktraceRenderer<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_module(&mut self, module: &'a CompiledModule, source_map: Option<&'a SourceMap>) {
+        self.modules.insert(module.self_id(), (module, source_map));
+    }
+
+    /// Sets the script the frames without a module belong to.
+    pub fn set_script(&mut self, name: &'a str, source_map: &'a SourceMap) {
+        self.script = Some((name, source_map));
+    }
+
+    pub fn add_source_file(&mut self, file_hash: FileHash, file_name: &'a str, contents: &'a str) {
+        self.files.insert(file_hash, (file_name, contents));
+    }
+
+    /// Renders the stack trace of the execution state, innermost frame first, one frame per line.
+    /// The values of the arguments and locals of each frame follow it, if the execution state
+    /// carries them.
+    pub fn render(&self, exec_state: &ExecutionState) -> String {
+        let mut buf = String::new();
+        for (idx, (module_id, fdef_idx, offset)) in exec_state.stack_trace().iter().enumerate() {
+            let source_map = self.render_frame(&mut buf, module_id.as_ref(), *fdef_idx, *offset);
+            if let Some(locals) = exec_state.locals().get(idx) {
+                Self::render_locals(&mut buf, source_map, *fdef_idx, locals);
+            }
+        }
+        buf
+    }
+
+    /// Renders the location of a frame and returns the source map of its code, if any.
+    fn render_frame(
+        &self,
+        buf: &mut String,
+        module_id: Option<&ModuleId>,
+        fdef_idx: FunctionDefinitionIndex,
+        offset: CodeOffset,
+    ) -> Option<&'a SourceMap> {
+        let (name, source_map) = match module_id {
+            Some(module_id) => match self.modules.get(module_id) {
+                Some((module, source_map)) => {
+                    let handle =
+                        module.function_handle_at(module.function_def_at(fdef_idx).function);
+                    (
+                        format!(
+                            "{}::{}",
+                            module_id.name(),
+                            module.identifier_at(handle.name)
+                        ),
+                        *source_map,
+                    )
+                }
+                None => (format!("{}::<function #{}>", module_id, fdef_idx), None),
+            },
+            None => match self.script {
+                Some((name, source_map)) => (name.to_string(), Some(source_map)),
+                None => ("<script>".to_string(), None),
+            },
+        };
+        let loc = source_map
+            .and_then(|source_map| source_map.get_function_source_map(fdef_idx).ok())
+            .and_then(|function_source_map| function_source_map.get_code_location(offset));
+        let location = match loc.and_then(|loc| self.source_location(&loc)) {
+            Some(location) => location,
+            None => format!("code offset {}", offset),
+        };
+        writeln!(buf, "\t{}({})", name, location).unwrap();
+        source_map
+    }
+
+    fn render_locals(
+        buf: &mut String,
+        source_map: Option<&SourceMap>,
+        fdef_idx: FunctionDefinitionIndex,
+        locals: &[Option<String>],
+    ) {
+        let function_source_map =
+            source_map.and_then(|source_map| source_map.get_function_source_map(fdef_idx).ok());
+        for (idx, value) in locals.iter().enumerate() {
+            let value = match value {
+                Some(value) => value,
+                None => continue,
+            };
+            let name = match function_source_map {
+                Some(function_source_map) => {
+                    match function_source_map.get_parameter_or_local_name(idx as u64) {
+                        // skip the temporaries introduced by the compiler
+                        Some((name, _)) if name.starts_with('%') => continue,
+                        // strip the suffix the compiler adds to make names unique
+                        Some((name, _)) => name.split('#').next().unwrap_or_default().to_string(),
+                        None => format!("[{}]", idx),
+                    }
+                }
+                None => format!("[{}]", idx),
+            };
+            writeln!(buf, "\t\t{} = {}", name, value).unwrap();
+        }
+    }
+
+    /// Returns `file:line` for the location, or `None` if the file is unknown.
+    fn source_location(&self, loc: &Loc) -> Option<String> {
+        let (file_name, contents) = self.files.get(&loc.file_hash())?;
+        let line_at = |offset: u32| {
+            let offset = (offset as usize).min(contents.len());
+            contents.as_bytes()[..offset]
+                .iter()
+                .filter(|b| **b == b'\n')
+                .count()
+                + 1
+        };
+        let (start, end) = (line_at(loc.start()), line_at(loc.end()));
+        Some(if start == end {
+            format!("{}:{}", file_name, start)
+        } else {
+            format!("{}:{}-{}", file_name, start, end)
+        })
+    }
+}
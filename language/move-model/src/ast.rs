@@ -130,6 +130,7 @@ impl ConditionKind {
                 | AbortsIf
                 | AbortsWith
                 | SucceedsIf
+                | Decreases
                 | Emits
                 | Ensures
                 | Modifies
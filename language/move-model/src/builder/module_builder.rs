@@ -1715,10 +1715,6 @@ impl<'env, 'translator> ModuleBuilder<'env, 'translator> {
         exp: &EA::Exp,
         additional_exps: &[EA::Exp],
     ) {
-        if matches!(kind, ConditionKind::Decreases | ConditionKind::SucceedsIf) {
-            self.parent.error(loc, "condition kind is not supported");
-            return;
-        }
         let expected_type = self.expected_type_for_condition(&kind);
         let mut et = self.exp_translator_for_context(loc, context, &kind);
         let (translated, translated_additional) = match kind {
@@ -1798,8 +1794,11 @@ impl<'env, 'translator> ModuleBuilder<'env, 'translator> {
     }
 
     /// Compute the expected type for the expression in a condition.
-    fn expected_type_for_condition(&mut self, _kind: &ConditionKind) -> Type {
-        BOOL_TYPE.clone()
+    fn expected_type_for_condition(&mut self, kind: &ConditionKind) -> Type {
+        match kind {
+            ConditionKind::Decreases => Type::Primitive(PrimitiveType::Num),
+            _ => BOOL_TYPE.clone(),
+        }
     }
 
     /// Convert a condition kind from AST into the ConditionKind known by the move model.
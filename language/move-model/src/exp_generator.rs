@@ -3,7 +3,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use itertools::Itertools;
-use num::BigUint;
+use num::{BigInt, BigUint};
 
 use crate::{
     ast::{Exp, ExpData, LocalVarDecl, Operation, QuantKind, TempIndex, Value},
@@ -64,6 +64,12 @@ pub trait ExpGenerator<'env> {
         ExpData::Value(node_id, Value::Bool(value)).into_exp()
     }
 
+    /// Make a number constant.
+    fn mk_num_const(&self, value: BigInt) -> Exp {
+        let node_id = self.new_node(NUM_TYPE.clone(), None);
+        ExpData::Value(node_id, Value::Number(value)).into_exp()
+    }
+
     /// Make an address constant.
     fn mk_address_const(&self, value: BigUint) -> Exp {
         let node_id = self.new_node(Type::Primitive(PrimitiveType::Address), None);
@@ -930,6 +930,17 @@ impl GlobalEnv {
             .count()
     }
 
+    /// Returns the diagnostics of given or higher severity, whether or not they have been
+    /// reported already.
+    pub fn get_diags(&self, min_severity: Severity) -> Vec<Diagnostic<FileId>> {
+        self.diags
+            .borrow()
+            .iter()
+            .filter(|(d, _)| d.severity >= min_severity)
+            .map(|(d, _)| d.clone())
+            .collect()
+    }
+
     /// Returns the number of errors.
     pub fn error_count(&self) -> usize {
         self.diag_count(Severity::Error)
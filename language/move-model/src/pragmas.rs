@@ -23,6 +23,10 @@ pub const TIMEOUT_PRAGMA: &str = "timeout";
 /// Pragma defining a random seed.
 pub const SEED_PRAGMA: &str = "seed";
 
+/// Pragma defining an upper bound on the gas a function may use, which is verified against the
+/// statically computed gas bound.
+pub const MAX_GAS_PRAGMA: &str = "max_gas";
+
 /// Pragma indicating an estimate how long verification takes. Verification
 /// is skipped if the timeout is smaller than this.
 pub const VERIFY_DURATION_ESTIMATE_PRAGMA: &str = "verify_duration_estimate";
@@ -205,6 +209,7 @@ pub fn is_pragma_valid_for_block(
             VERIFY_PRAGMA
                 | TIMEOUT_PRAGMA
                 | SEED_PRAGMA
+                | MAX_GAS_PRAGMA
                 | VERIFY_DURATION_ESTIMATE_PRAGMA
                 | INTRINSIC_PRAGMA
                 | OPAQUE_PRAGMA
@@ -64,6 +64,8 @@ pub struct TranslatedSpec {
     pub post: Vec<(Loc, Exp)>,
     pub aborts: Vec<(Loc, Exp, Option<Exp>)>,
     pub aborts_with: Vec<(Loc, Vec<Exp>)>,
+    pub succeeds: Vec<(Loc, Exp)>,
+    pub decreases: Vec<(Loc, Exp)>,
     pub emits: Vec<(Loc, Exp, Exp, Option<Exp>)>,
     pub modifies: Vec<(Loc, Exp)>,
     pub invariants: Vec<(Loc, GlobalId, Exp)>,
@@ -123,6 +125,39 @@ impl TranslatedSpec {
         )
     }
 
+    /// Creates a boolean expression which describes the overall success condition. This is
+    /// a disjunction of the individual `succeeds_if` conditions.
+    pub fn succeeds_condition<'a, T: ExpGenerator<'a>>(&self, builder: &T) -> Option<Exp> {
+        builder.mk_join_bool(Operation::Or, self.succeeds.iter().map(|(_, e)| e.clone()))
+    }
+
+    /// Creates a boolean expression which states that the `decreases` measures `new` are
+    /// smaller than the measures `old`, in the lexicographic order over the measures both have.
+    /// Each measure in `old` must not be negative, which makes the order well-founded. Returns
+    /// `None` if either has no measure.
+    pub fn decreases_condition<'a, T: ExpGenerator<'a>>(
+        builder: &T,
+        old: &[Exp],
+        new: &[Exp],
+    ) -> Option<Exp> {
+        let zero = builder.mk_num_const(0.into());
+        old.iter().zip(new).rev().fold(None, |cond, (old, new)| {
+            // old >= 0 && (new < old || new == old && cond)
+            let smaller = builder.mk_bool_call(Operation::Lt, vec![new.clone(), old.clone()]);
+            let decreases = match cond {
+                None => smaller,
+                Some(cond) => builder.mk_or(
+                    smaller,
+                    builder.mk_and(builder.mk_eq(new.clone(), old.clone()), cond),
+                ),
+            };
+            Some(builder.mk_and(
+                builder.mk_bool_call(Operation::Ge, vec![old.clone(), zero.clone()]),
+                decreases,
+            ))
+        })
+    }
+
     /// Returns true if there are any specs about the abort code.
     pub fn has_aborts_code_specs(&self) -> bool {
         !self.aborts_with.is_empty() || self.aborts.iter().any(|(_, _, c)| c.is_some())
@@ -401,6 +436,26 @@ impl<'a, 'b, T: ExpGenerator<'a>> SpecTranslator<'a, 'b, T> {
             self.result.aborts_with.push((cond.loc.clone(), codes));
         }
 
+        // Success conditions are the complement of abort conditions, and are translated in the
+        // same state.
+        for cond in spec
+            .filter_kind(ConditionKind::SucceedsIf)
+            .filter(is_applicable)
+        {
+            let exp =
+                self.translate_exp(&self.auto_trace(&cond.loc, &cond.exp), self.in_post_state);
+            self.result.succeeds.push((cond.loc.clone(), exp));
+        }
+
+        // Termination measures refer to the parameters on entry, like abort conditions.
+        for cond in spec
+            .filter_kind(ConditionKind::Decreases)
+            .filter(is_applicable)
+        {
+            let exp = self.translate_exp(&cond.exp, self.in_post_state);
+            self.result.decreases.push((cond.loc.clone(), exp));
+        }
+
         // If there are no aborts_if and aborts_with, and the pragma `aborts_if_is_strict` is set,
         // add an implicit aborts_if false.
         if self.result.aborts.is_empty()
@@ -8,6 +8,9 @@
 //! declarations. It can be extended on the go.
 
 pub const VECTOR_BORROW_MUT: &str = "vector::borrow_mut";
+pub const VECTOR_EMPTY: &str = "vector::empty";
+pub const VECTOR_LENGTH: &str = "vector::length";
+pub const VECTOR_PUSH_BACK: &str = "vector::push_back";
 pub const EVENT_EMIT_EVENT: &str = "event::emit_event";
 
 pub const TYPE_NAME_MOVE: &str = "type_info::type_name";
@@ -9,6 +9,8 @@ use std::{
     fs,
     num::ParseIntError,
     option::Option::None,
+    path::Path,
+    time::Duration,
 };
 
 use anyhow::anyhow;
@@ -25,16 +27,23 @@ use move_binary_format::file_format::FunctionDefinitionIndex;
 use move_model::{
     ast::TempIndex,
     code_writer::CodeWriter,
-    model::{FunId, GlobalEnv, Loc, ModuleId, NodeId, QualifiedId, StructEnv},
+    emitln,
+    model::{
+        FunId, FunctionEnv, GlobalEnv, Loc, ModuleId, NodeId, Parameter, QualifiedId, StructEnv,
+    },
     pragmas::INTRINSIC_TYPE_MAP,
     ty::{PrimitiveType, Type},
 };
-use move_stackless_bytecode::function_target_pipeline::{FunctionTargetsHolder, FunctionVariant};
+use move_stackless_bytecode::{
+    function_target_pipeline::{FunctionTargetsHolder, FunctionVariant},
+    inconsistency_check::UNCOVERED_ABORT_MESSAGE,
+    spec_instrumentation::{ABORTS_IF_FAILS_MESSAGE, ENSURES_FAILS_MESSAGE},
+};
 
 // DEBUG
 // use backtrace::Backtrace;
 use crate::{
-    boogie_helpers::{boogie_inst_suffix, boogie_struct_name},
+    boogie_helpers::{boogie_function_name, boogie_inst_suffix, boogie_struct_name},
     options::{BoogieOptions, VectorTheory},
     prover_task_runner::{ProverTaskRunner, RunBoogieWithSeeds},
 };
@@ -59,10 +68,27 @@ pub struct BoogieOutput {
     /// All errors which could be parsed from the output.
     pub errors: Vec<BoogieError>,
 
+    /// Per procedure statistics, if `BoogieOptions::collect_stats` is set.
+    pub stats: Vec<VerificationStats>,
+
     /// Full output as a string.
     pub all_output: String,
 }
 
+/// Statistics about the verification of one Boogie procedure, i.e. one verification variant
+/// of a function.
+#[derive(Debug, Clone)]
+pub struct VerificationStats {
+    /// The function which has been verified.
+    pub fun_id: QualifiedId<FunId>,
+    /// The Boogie procedure, which determines the variant and instantiation.
+    pub procedure: String,
+    /// The time the solver took.
+    pub duration: Duration,
+    /// The number of proof obligations (verification conditions).
+    pub vc_count: usize,
+}
+
 /// Kind of boogie error.
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum BoogieErrorKind {
@@ -70,6 +96,10 @@ pub enum BoogieErrorKind {
     Inconclusive,
     Inconsistency,
     Internal,
+    /// An `ensures` which also holds if the function body is havocked.
+    VacuousEnsures,
+    /// An abort of a function which has no `aborts_if`.
+    UncoveredAbort,
 }
 
 impl BoogieErrorKind {
@@ -77,6 +107,13 @@ impl BoogieErrorKind {
         use BoogieErrorKind::*;
         matches!(self, Assertion)
     }
+
+    /// Returns true if this is a finding of the specification coverage check, which is
+    /// reported in the coverage summary instead of as a diagnostic.
+    pub fn is_spec_coverage(self) -> bool {
+        use BoogieErrorKind::*;
+        matches!(self, VacuousEnsures | UncoveredAbort)
+    }
 }
 
 /// A boogie error.
@@ -88,6 +125,17 @@ pub struct BoogieError {
     pub model: Option<Model>,
 }
 
+impl BoogieError {
+    /// Returns the function whose verification produced this error, which is the first one
+    /// whose locals appear in the execution trace.
+    pub fn verified_function(&self) -> Option<QualifiedId<FunId>> {
+        self.execution_trace.iter().find_map(|entry| match entry {
+            TraceEntry::Temporary(fun_id, ..) => Some(*fun_id),
+            _ => None,
+        })
+    }
+}
+
 /// A trace entry.
 pub enum TraceEntry {
     AtLocation(Loc),
@@ -112,6 +160,18 @@ static INCONCLUSIVE_DIAG_STARTS: Lazy<Regex> = Lazy::new(|| {
 static INCONSISTENCY_DIAG_STARTS: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"(?m)^inconsistency_detected\((?P<args>[^)]*)\)").unwrap());
 
+static VACUITY_DIAG_STARTS: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?m)^vacuous_ensures\((?P<args>[^)]*)\)").unwrap());
+
+// Output of `/trace`
+static TRACE_VERIFYING: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^Verifying (?:Implementation )?(?P<proc>\S+) \.\.\.").unwrap());
+
+static TRACE_RESULT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\s*\[(?P<secs>[0-9.]+) s, (?:solver resource count: \d+, )?(?P<vcs>\d+) proof obligations?\]")
+        .unwrap()
+});
+
 impl<'env> BoogieWrapper<'env> {
     /// Calls boogie on the given file. On success, returns a struct representing the analyzed
     /// output of boogie.
@@ -149,6 +209,7 @@ impl<'env> BoogieWrapper<'env> {
                     };
                     return Ok(BoogieOutput {
                         errors: vec![err],
+                        stats: vec![],
                         all_output: "".to_string(),
                     });
                 } else {
@@ -213,33 +274,52 @@ impl<'env> BoogieWrapper<'env> {
         let mut errors = self.extract_verification_errors(&out);
         errors.extend(self.extract_inconclusive_errors(&out));
         errors.extend(self.extract_inconsistency_errors(&out));
+        errors.extend(self.extract_vacuity_errors(&out));
+        let stats = if self.options.collect_stats {
+            self.extract_stats(&out)
+        } else {
+            vec![]
+        };
         Ok(BoogieOutput {
             errors,
+            stats,
             all_output: out,
         })
     }
 
-    /// Calls boogie and analyzes output.
-    pub fn call_boogie_and_verify_output(&self, boogie_file: &str) -> anyhow::Result<()> {
-        let BoogieOutput { errors, all_output } = self.call_boogie(boogie_file)?;
+    /// Calls boogie and analyzes output. Returns the errors which have been reported, together
+    /// with the statistics of the verified procedures.
+    pub fn call_boogie_and_verify_output(
+        &self,
+        boogie_file: &str,
+    ) -> anyhow::Result<(Vec<BoogieError>, Vec<VerificationStats>)> {
+        let BoogieOutput {
+            errors,
+            stats,
+            all_output,
+        } = self.call_boogie(boogie_file)?;
         let boogie_log_file = self.options.get_boogie_log_file(boogie_file);
         let log_file_existed = std::path::Path::new(&boogie_log_file).exists();
         debug!("writing boogie log to {}", boogie_log_file);
         fs::write(&boogie_log_file, &all_output)?;
 
-        for error in &errors {
+        for error in errors.iter().filter(|e| !e.kind.is_spec_coverage()) {
             self.add_error(error);
         }
 
+        if let Some(dir) = &self.options.counterexample_tests_dir {
+            self.write_counterexample_tests(&errors, dir)?;
+        }
+
         if !log_file_existed && !self.options.keep_artifacts {
             std::fs::remove_file(boogie_log_file).unwrap_or_default();
         }
 
-        Ok(())
+        Ok((errors, stats))
     }
 
     /// Helper to add a boogie error as a codespan Diagnostic.
-    fn add_error(&self, error: &BoogieError) {
+    pub fn add_error(&self, error: &BoogieError) {
         // Create the error
         let label = Label::primary(error.loc.file_id(), error.loc.span());
         let mut diag = Diagnostic::error()
@@ -513,6 +593,7 @@ impl<'env> BoogieWrapper<'env> {
             if !inbetween.is_empty()
                 && !INCONCLUSIVE_DIAG_STARTS.is_match(inbetween)
                 && !INCONSISTENCY_DIAG_STARTS.is_match(inbetween)
+                && !VACUITY_DIAG_STARTS.is_match(inbetween)
             {
                 // This is unexpected text and we report it as an internal error
                 errors.push(BoogieError {
@@ -545,7 +626,11 @@ impl<'env> BoogieWrapper<'env> {
             if msg != "expected to fail" {
                 // Only add this if it is not a negative test. We still needed to parse it.
                 errors.push(BoogieError {
-                    kind: BoogieErrorKind::Assertion,
+                    kind: if msg == UNCOVERED_ABORT_MESSAGE {
+                        BoogieErrorKind::UncoveredAbort
+                    } else {
+                        BoogieErrorKind::Assertion
+                    },
                     loc,
                     message: msg.to_string(),
                     execution_trace,
@@ -760,15 +845,71 @@ impl<'env> BoogieWrapper<'env> {
         }
     }
 
+    /// Extracts the time and number of proof obligations of each procedure from the output
+    /// of `/trace`. A `Verifying` line is followed by its result line; with multiple cores
+    /// those may interleave, in which case they are paired in order.
+    fn extract_stats(&self, out: &str) -> Vec<VerificationStats> {
+        let fun_names: BTreeMap<String, QualifiedId<FunId>> = self
+            .targets
+            .get_funs()
+            .map(|fun_id| {
+                let fun_env = self.env.get_function(fun_id);
+                (boogie_function_name(&fun_env, &[]), fun_id)
+            })
+            .collect();
+        let mut pending = std::collections::VecDeque::new();
+        let mut stats = vec![];
+        for line in out.lines() {
+            if let Some(cap) = TRACE_VERIFYING.captures(line) {
+                pending.push_back(cap.name("proc").unwrap().as_str().to_string());
+            } else if let Some(cap) = TRACE_RESULT.captures(line) {
+                let procedure = match pending.pop_front() {
+                    Some(procedure) => procedure,
+                    None => continue,
+                };
+                // Strip the verification suffix and the type instantiation.
+                let name = match procedure.rfind("$verify") {
+                    Some(pos) => &procedure[..pos],
+                    None => continue,
+                };
+                let name = name.split('\'').next().unwrap_or(name);
+                if let Some(fun_id) = fun_names.get(name) {
+                    stats.push(VerificationStats {
+                        fun_id: *fun_id,
+                        procedure,
+                        duration: Duration::from_secs_f64(
+                            cap.name("secs")
+                                .unwrap()
+                                .as_str()
+                                .parse()
+                                .unwrap_or_default(),
+                        ),
+                        vc_count: cap
+                            .name("vcs")
+                            .unwrap()
+                            .as_str()
+                            .parse()
+                            .unwrap_or_default(),
+                    });
+                }
+            }
+        }
+        stats
+    }
+
     /// Extracts inconclusive (timeout) errors.
     fn extract_inconclusive_errors(&self, out: &str) -> Vec<BoogieError> {
         INCONCLUSIVE_DIAG_STARTS
             .captures_iter(out)
             .filter_map(|cap| {
                 let str = cap.name("str").unwrap().as_str();
-                if str.contains("$verify_inconsistency") {
+                if str.contains("$verify_inconsistency")
+                    || str.contains("$verify_vacuity")
+                    || str.contains("$verify_uncovered_abort")
+                {
                     // Masks the timeout from the negative test for the inconsistency checking, and
-                    // skips the error report of this instance.
+                    // skips the error report of this instance. Same for the specification coverage
+                    // checks, for which a timeout means nothing was found.
                     None
                 } else {
                     let line = cap.name("line").unwrap().as_str();
@@ -815,6 +956,24 @@ impl<'env> BoogieWrapper<'env> {
             .collect_vec()
     }
 
+    /// Extracts vacuous `ensures` found by the specification coverage check.
+    fn extract_vacuity_errors(&self, out: &str) -> Vec<BoogieError> {
+        VACUITY_DIAG_STARTS
+            .captures_iter(out)
+            .map(|cap| {
+                let args = cap.name("args").unwrap().as_str();
+                let loc = self.report_error(self.extract_loc(args), self.env.unknown_loc());
+                BoogieError {
+                    kind: BoogieErrorKind::VacuousEnsures,
+                    loc,
+                    message: "post-condition holds for any function body".to_string(),
+                    execution_trace: vec![],
+                    model: None,
+                }
+            })
+            .collect_vec()
+    }
+
     /// Gets the code byte index and source location (if available) from a target line/column
     /// position.
     fn get_loc_from_pos(&self, pos: Location) -> Option<Loc> {
@@ -826,6 +985,323 @@ impl<'env> BoogieWrapper<'env> {
     }
 }
 
+// -----------------------------------------------
+// # Counterexample Tests
+
+/// Vectors in a counterexample with more elements than this are not rendered into a test.
+const MAX_COUNTEREXAMPLE_VECTOR_SIZE: usize = 1024;
+
+impl<'env> BoogieWrapper<'env> {
+    /// Writes, for each module with verification errors, a file with Move unit tests which
+    /// replay the counterexamples of those errors. The tests are meant to be added to the
+    /// module, and each of them fails until the error it replays is fixed.
+    fn write_counterexample_tests(&self, errors: &[BoogieError], dir: &str) -> anyhow::Result<()> {
+        let mut tests_by_module: BTreeMap<ModuleId, Vec<String>> = BTreeMap::new();
+        let mut count_by_fun: BTreeMap<QualifiedId<FunId>, usize> = BTreeMap::new();
+        for error in errors.iter().filter(|e| e.kind.is_from_verification()) {
+            if let Some(fun_id) = error.verified_function() {
+                let fun_env = self.env.get_function(fun_id);
+                let count = count_by_fun.entry(fun_id).or_default();
+                let test_name = format!(
+                    "counterexample_{}_{}",
+                    fun_env.get_name().display(self.env.symbol_pool()),
+                    count
+                );
+                *count += 1;
+                tests_by_module
+                    .entry(fun_id.module_id)
+                    .or_default()
+                    .push(self.make_counterexample_test(error, &fun_env, &test_name));
+            }
+        }
+        if tests_by_module.is_empty() {
+            return Ok(());
+        }
+        fs::create_dir_all(dir)?;
+        for (module_id, tests) in tests_by_module {
+            let module_name = self.env.get_module(module_id).get_full_name_str();
+            let file = Path::new(dir).join(format!("{}.move", module_name.replace("::", "_")));
+            let header = format!(
+                "// Counterexample tests for module `{}`, generated by the Move prover.\n\
+                 // Add them to the module to replay the verification errors with `move test`.\n",
+                module_name
+            );
+            fs::write(&file, header + &tests.join(""))?;
+            info!(
+                "wrote counterexample tests for `{}` to `{}`",
+                module_name,
+                file.display()
+            );
+        }
+        Ok(())
+    }
+
+    /// Creates a unit test from the counterexample of the given error, which was reported
+    /// for the given function. The test publishes the initial global memory of the
+    /// counterexample, calls the function with the arguments of the counterexample, and
+    /// checks the violated condition where it can be expressed in Move.
+    fn make_counterexample_test(
+        &self,
+        error: &BoogieError,
+        fun_env: &FunctionEnv<'_>,
+        test_name: &str,
+    ) -> String {
+        let env = self.env;
+        let pool = env.symbol_pool();
+        let writer = CodeWriter::new(env.internal_loc());
+        emitln!(writer);
+        emitln!(
+            writer,
+            "// {} {}",
+            error.message,
+            error.loc.display_line_only(env)
+        );
+        if let Err(reason) = self.emit_counterexample_test(&writer, error, fun_env, test_name) {
+            emitln!(
+                writer,
+                "// no test generated for `{}`: {}",
+                fun_env.get_name().display(pool),
+                reason
+            );
+        }
+        writer.extract_result()
+    }
+
+    /// Emits the test for `make_counterexample_test`, or returns why none can be generated.
+    fn emit_counterexample_test(
+        &self,
+        writer: &CodeWriter,
+        error: &BoogieError,
+        fun_env: &FunctionEnv<'_>,
+        test_name: &str,
+    ) -> Result<(), String> {
+        let env = self.env;
+        let pool = env.symbol_pool();
+        let module_id = fun_env.module_env.get_id();
+        let fun_id = fun_env.get_qualified_id();
+        if fun_env.get_type_parameter_count() > 0 {
+            return Err("generic functions are not supported".to_string());
+        }
+        let default_model = Model::new(self);
+        let model = error.model.as_ref().unwrap_or(&default_model);
+
+        // Collect the initial values of parameters and memory. Both are traced on entry of
+        // the function, so the first value found in the trace is the initial one.
+        let mut param_values = BTreeMap::new();
+        let mut mem_values = BTreeMap::new();
+        let mut abort_code = None;
+        for entry in &error.execution_trace {
+            match entry {
+                TraceEntry::Temporary(fun, idx, value) if *fun == fun_id => {
+                    param_values.entry(*idx).or_insert(value);
+                }
+                TraceEntry::GlobalMem(node_id, ModelValue::List(elems)) if elems.len() == 3 => {
+                    mem_values
+                        .entry(env.get_node_type(*node_id))
+                        .or_insert((&elems[1], &elems[2]));
+                }
+                TraceEntry::Abort(_, value) if abort_code.is_none() => {
+                    abort_code = Some(value.extract_i128().unwrap_or(-1));
+                }
+                _ => {}
+            }
+        }
+
+        // Signers become parameters of the test, all other arguments local variables.
+        let mut signers: Vec<(BigInt, String)> = vec![];
+        let mut lets = vec![];
+        let mut args = vec![];
+        let mut signer_params = BTreeSet::new();
+        for (idx, Parameter(name, ty)) in fun_env.get_parameters().into_iter().enumerate() {
+            let name = name.display(pool).to_string();
+            let value = param_values
+                .get(&idx)
+                .ok_or_else(|| format!("no value for parameter `{}`", name))?;
+            let (is_ref, is_mut) = match &ty {
+                Type::Reference(is_mut, _) => (true, *is_mut),
+                _ => (false, false),
+            };
+            let arg = if ty.skip_reference().is_signer() {
+                let addr = value
+                    .extract_list("$signer")
+                    .and_then(|l| l.first())
+                    .and_then(|v| BigInt::parse_bytes(v.extract_literal()?.as_bytes(), 10))
+                    .ok_or_else(|| format!("no address for signer `{}`", name))?;
+                signers.push((addr, name.clone()));
+                signer_params.insert(name.clone());
+                name
+            } else {
+                let rep = value
+                    .move_value(self, model, module_id, ty.skip_reference())
+                    .ok_or_else(|| format!("cannot express value of parameter `{}`", name))?;
+                lets.push(format!("let {} = {};", name, rep));
+                name
+            };
+            args.push(match (is_ref, is_mut) {
+                (true, true) => format!("&mut {}", arg),
+                (true, false) => format!("&{}", arg),
+                _ => arg,
+            });
+        }
+
+        // Publish the initial memory, creating test signers for addresses which are not
+        // given by a signer parameter.
+        let mut publishes = vec![];
+        let domain_info = extract_domain(model);
+        for (ty, (domain, mem)) in mem_values {
+            let values = mem
+                .extract_mem_vector(model, domain, &domain_info)
+                .map(|v| v.values)
+                .unwrap_or_default();
+            for (addr, value) in values {
+                let addr = BigInt::from(addr);
+                let rep = value
+                    .move_value(self, model, module_id, &ty)
+                    .ok_or_else(|| {
+                        format!(
+                            "cannot publish `{}` at 0x{} from this module",
+                            ty.display(&env.get_type_display_ctx()),
+                            addr.to_str_radix(16)
+                        )
+                    })?;
+                let signer = match signers.iter().find(|(a, _)| a == &addr) {
+                    Some((_, name)) => name.clone(),
+                    None => {
+                        let name = format!("account_{}", addr.to_str_radix(16));
+                        signers.push((addr, name.clone()));
+                        name
+                    }
+                };
+                publishes.push(format!("move_to(&{}, {});", signer, rep));
+            }
+        }
+
+        // Bind the results if the violated post-condition can be checked in Move.
+        let check = if error.message == ENSURES_FAILS_MESSAGE {
+            env.get_source(&error.loc)
+                .ok()
+                .and_then(|src| move_condition(fun_env, src, &signer_params))
+        } else {
+            None
+        };
+        let bind_results = check.as_ref().map_or(false, |cond| cond.contains("result"));
+        let results = match fun_env.get_return_count() {
+            0 => "".to_string(),
+            1 if bind_results => "let result = ".to_string(),
+            1 => "let _ = ".to_string(),
+            n if bind_results => format!(
+                "let ({}) = ",
+                (1..=n).map(|i| format!("result_{}", i)).join(", ")
+            ),
+            n => format!("let ({}) = ", vec!["_"; n].join(", ")),
+        };
+
+        if signers.is_empty() {
+            emitln!(writer, "#[test]");
+        } else {
+            emitln!(
+                writer,
+                "#[test({})]",
+                signers
+                    .iter()
+                    .map(|(addr, name)| format!("{} = @0x{}", name, addr.to_str_radix(16)))
+                    .join(", ")
+            );
+        }
+        if error.message == ABORTS_IF_FAILS_MESSAGE {
+            emitln!(writer, "#[expected_failure]");
+        }
+        let acquires = fun_env
+            .get_acquires_global_resources()
+            .into_iter()
+            .map(|struct_id| {
+                fun_env
+                    .module_env
+                    .get_struct(struct_id)
+                    .get_name()
+                    .display(pool)
+                    .to_string()
+            })
+            .collect_vec();
+        emitln!(
+            writer,
+            "fun {}({}){} {{",
+            test_name,
+            signers
+                .iter()
+                .map(|(_, name)| format!("{}: signer", name))
+                .join(", "),
+            if acquires.is_empty() {
+                "".to_string()
+            } else {
+                format!(" acquires {}", acquires.join(", "))
+            }
+        );
+        writer.indent();
+        for line in publishes.iter().chain(lets.iter()) {
+            emitln!(writer, "{}", line);
+        }
+        match abort_code {
+            Some(-1) => emitln!(writer, "// aborts with an execution failure"),
+            Some(code) => emitln!(writer, "// aborts with code 0x{:X}", code),
+            None => {}
+        }
+        emitln!(
+            writer,
+            "{}{}({});",
+            results,
+            fun_env.get_name().display(pool),
+            args.join(", ")
+        );
+        if let Some(cond) = check {
+            emitln!(writer, "assert!({}, 0);", cond);
+        }
+        writer.unindent();
+        emitln!(writer, "}");
+        Ok(())
+    }
+}
+
+/// Returns the post-condition at the given source as a Move expression, if it can be
+/// evaluated in a test after calling the function. This is the case if it only uses
+/// parameters, results, and Move functions of the module.
+fn move_condition(
+    fun_env: &FunctionEnv<'_>,
+    src: &str,
+    signers: &BTreeSet<String>,
+) -> Option<String> {
+    static IDENT: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"(?P<name>[a-zA-Z_][a-zA-Z0-9_]*)\s*(?P<call>\()?").unwrap());
+    let cond = src
+        .trim()
+        .strip_prefix("ensures")?
+        .trim()
+        .trim_end_matches(';')
+        .trim();
+    if ["==>", "..", "::", "[", "{"]
+        .iter()
+        .any(|op| cond.contains(op))
+    {
+        return None;
+    }
+    let pool = fun_env.symbol_pool();
+    for cap in IDENT.captures_iter(cond) {
+        let name = cap.name("name").unwrap().as_str();
+        if signers.contains(name)
+            || name.starts_with("MAX_U")
+            || [
+                "old", "global", "exists", "forall", "with", "update", "len", "TRACE",
+            ]
+            .contains(&name)
+            || cap.name("call").is_some()
+                && fun_env.module_env.find_function(pool.make(name)).is_none()
+        {
+            return None;
+        }
+    }
+    Some(cond.to_string())
+}
+
 /// Creates a position (line/column pair) from strings which are known to consist only of digits.
 fn make_position(line_str: &str, col_str: &str) -> Location {
     // This will crash on overflow.
@@ -971,6 +1447,15 @@ impl Model {
         }
     }
 
+    /// Create a new model without variables, for values which are fully described by their
+    /// term, as returned by an SMT solver's `get-value`.
+    pub fn with_vector_theory(vector_theory: VectorTheory) -> Self {
+        Model {
+            vars: Default::default(),
+            vector_theory,
+        }
+    }
+
     fn is_empty(&self) -> bool {
         self.vars.is_empty()
     }
@@ -1029,6 +1514,11 @@ impl ModelValue {
         ModelValue::Literal(s.to_string())
     }
 
+    /// Parses a model value from an s-expression, as printed by an SMT solver.
+    pub fn parse(input: &str) -> Option<ModelValue> {
+        ModelParser { input, at: 0 }.parse_value().ok()
+    }
+
     // Makes an error value.
     fn error() -> ModelValue {
         ModelValue::List(vec![ModelValue::literal("Error")])
@@ -1626,6 +2116,96 @@ impl ModelValue {
         entries.push(PrettyDoc::text("Default: ").append(default));
         Some(Self::pretty_vec_or_struct_body(entries))
     }
+
+    /// Renders the given model value which has given type as a Move expression which can be
+    /// used in the given module.
+    fn move_value(
+        &self,
+        wrapper: &BoogieWrapper,
+        model: &Model,
+        module_id: ModuleId,
+        ty: &Type,
+    ) -> Option<String> {
+        use PrimitiveType::*;
+        if self.extract_list("Error").is_some() {
+            // This is an undefined value
+            return None;
+        }
+        match ty {
+            Type::Primitive(Bool) => {
+                Some(self.extract_literal()?.parse::<bool>().ok()?.to_string())
+            }
+            Type::Primitive(Address) => {
+                let addr = BigInt::parse_bytes(self.extract_literal()?.as_bytes(), 10)?;
+                Some(format!("@0x{}", addr.to_str_radix(16)))
+            }
+            Type::Primitive(prim @ (U8 | U16 | U32 | U64 | U128 | U256)) => {
+                let n = BigInt::parse_bytes(self.extract_literal()?.as_bytes(), 10)?;
+                let suffix = match prim {
+                    U8 => "u8",
+                    U16 => "u16",
+                    U32 => "u32",
+                    U128 => "u128",
+                    U256 => "u256",
+                    _ => "",
+                };
+                Some(format!("{}{}", n, suffix))
+            }
+            Type::Vector(elem_ty) => {
+                let values = self.extract_vector(model, elem_ty)?;
+                if values.size > MAX_COUNTEREXAMPLE_VECTOR_SIZE {
+                    return None;
+                }
+                let elems = (0..values.size)
+                    .map(|idx| {
+                        values
+                            .values
+                            .get(&idx)
+                            .unwrap_or(&values.default)
+                            .extract_box()
+                            .move_value(wrapper, model, module_id, elem_ty)
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                Some(format!("vector[{}]", elems.join(", ")))
+            }
+            Type::Struct(struct_module_id, struct_id, inst) => {
+                // Structs can only be packed in their defining module.
+                let struct_env = wrapper
+                    .env
+                    .get_struct_qid(struct_module_id.qualified(*struct_id));
+                if *struct_module_id != module_id || struct_env.is_native_or_intrinsic() {
+                    return None;
+                }
+                let pool = struct_env.symbol_pool();
+                let struct_name = &boogie_struct_name(&struct_env, inst);
+                let values = self
+                    .extract_list(struct_name)
+                    .or_else(|| self.extract_list(&format!("|{}|", struct_name)))?;
+                let fields = struct_env
+                    .get_fields()
+                    .enumerate()
+                    .filter(|(_, f)| {
+                        // The compiler adds this field to structs without fields.
+                        struct_env.get_field_count() > 1 || f.get_name() != pool.make("dummy_field")
+                    })
+                    .map(|(i, f)| {
+                        let ty = f.get_type().instantiate(inst);
+                        Some(format!(
+                            "{}: {}",
+                            f.get_name().display(pool),
+                            values.get(i)?.move_value(wrapper, model, module_id, &ty)?
+                        ))
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                Some(format!(
+                    "{} {{ {} }}",
+                    struct_env.get_name().display(pool),
+                    fields.join(", ")
+                ))
+            }
+            _ => None,
+        }
+    }
 }
 
 /// Represents an expression descriptor.
@@ -1773,3 +2353,226 @@ fn index_range_check(max: usize) -> impl FnOnce(usize) -> Result<usize, ModelPar
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use move_compiler::shared::PackagePaths;
+    use move_model::{parse_addresses_from_options, run_model_builder};
+    use tempfile::TempDir;
+
+    const SOURCE: &str = r#"
+module 0x42::M {
+    struct S has copy, drop { x: u64, b: bool }
+    fun f(x: u64, _s: &signer): u64 { x }
+    fun g(x: u64): u64 { x }
+    spec module {
+        fun h(x: u64): u64 { x }
+    }
+}
+module 0x43::N {
+    struct T has copy, drop { x: u64 }
+}
+"#;
+
+    fn build_env() -> GlobalEnv {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("M.move");
+        fs::write(&path, SOURCE).unwrap();
+        let env = run_model_builder(
+            vec![PackagePaths {
+                name: None,
+                paths: vec![path.to_string_lossy().to_string()],
+                named_address_map: parse_addresses_from_options(vec![]).unwrap(),
+            }],
+            vec![],
+        )
+        .unwrap();
+        assert!(!env.has_errors());
+        env
+    }
+
+    #[test]
+    fn test_move_condition() {
+        let env = build_env();
+        let module_env = env
+            .find_module_by_name(env.symbol_pool().make("M"))
+            .unwrap();
+        let fun_env = module_env
+            .find_function(env.symbol_pool().make("f"))
+            .unwrap();
+        let signers = BTreeSet::from(["_s".to_string()]);
+        let cond = |src: &str| move_condition(&fun_env, src, &signers);
+
+        assert_eq!(
+            cond("ensures result == x + 1;").as_deref(),
+            Some("result == x + 1")
+        );
+        assert_eq!(
+            cond("ensures  result != g(x) ;").as_deref(),
+            Some("result != g(x)")
+        );
+        for rejected in [
+            "aborts_if x == 0;",
+            "ensures x > 0 ==> result > 0;",
+            "ensures result == old(x);",
+            "ensures result == h(x);",
+            "ensures result == 0x42::M::g(x);",
+            "ensures result <= MAX_U64;",
+            "ensures _s == _s;",
+            "ensures exists<S>(@0x42);",
+            "ensures S { x, b: true }.x == x;",
+        ] {
+            assert_eq!(cond(rejected), None, "{}", rejected);
+        }
+    }
+
+    #[test]
+    fn test_move_value() {
+        let env = build_env();
+        let targets = FunctionTargetsHolder::default();
+        let writer = CodeWriter::new(env.internal_loc());
+        let options = BoogieOptions::default();
+        let wrapper = BoogieWrapper {
+            env: &env,
+            targets: &targets,
+            writer: &writer,
+            options: &options,
+        };
+        let model = Model::with_vector_theory(VectorTheory::SmtSeq);
+        let module_env = env
+            .find_module_by_name(env.symbol_pool().make("M"))
+            .unwrap();
+        let module_id = module_env.get_id();
+        let struct_type = |module_name: &str, struct_name: &str| {
+            let module_env = env
+                .find_module_by_name(env.symbol_pool().make(module_name))
+                .unwrap();
+            let struct_env = module_env
+                .find_struct(env.symbol_pool().make(struct_name))
+                .unwrap();
+            let value = format!("({} 1 true)", boogie_struct_name(&struct_env, &[]));
+            (
+                Type::Struct(module_env.get_id(), struct_env.get_id(), vec![]),
+                value,
+            )
+        };
+        let move_value = |value: &str, ty: &Type| {
+            ModelValue::parse(value)
+                .unwrap()
+                .move_value(&wrapper, &model, module_id, ty)
+        };
+
+        use PrimitiveType::*;
+        let (s, s_value) = struct_type("M", "S");
+        let (t, t_value) = struct_type("N", "T");
+        let u64_vector = Type::Vector(Box::new(Type::Primitive(U64)));
+        for (value, ty, expected) in [
+            ("true", Type::Primitive(Bool), Some("true")),
+            ("66", Type::Primitive(Address), Some("@0x42")),
+            ("7", Type::Primitive(U8), Some("7u8")),
+            ("7", Type::Primitive(U16), Some("7u16")),
+            ("7", Type::Primitive(U32), Some("7u32")),
+            ("7", Type::Primitive(U64), Some("7")),
+            ("7", Type::Primitive(U128), Some("7u128")),
+            ("7", Type::Primitive(U256), Some("7u256")),
+            (
+                "(seq.++ (seq.unit 1) (seq.unit 2))",
+                u64_vector.clone(),
+                Some("vector[1, 2]"),
+            ),
+            ("(seq.unit 3)", u64_vector.clone(), Some("vector[3]")),
+            ("(as seq.empty (Seq Int))", u64_vector, Some("vector[]")),
+            (s_value.as_str(), s, Some("S { x: 1, b: true }")),
+            // Structs of other modules cannot be packed in the test.
+            (t_value.as_str(), t, None),
+            ("(Error)", Type::Primitive(U64), None),
+            ("1", Type::Primitive(Signer), None),
+        ] {
+            assert_eq!(
+                move_value(value, &ty).as_deref(),
+                expected,
+                "{} as {:?}",
+                value,
+                ty
+            );
+        }
+    }
+
+    #[test]
+    fn test_extract_stats() {
+        let env = build_env();
+        let module_env = env
+            .find_module_by_name(env.symbol_pool().make("M"))
+            .unwrap();
+        let mut targets = FunctionTargetsHolder::default();
+        for fun_env in module_env.get_functions() {
+            targets.add_target(&fun_env);
+        }
+        let fun_id = |name: &str| {
+            module_env
+                .find_function(env.symbol_pool().make(name))
+                .unwrap()
+                .get_qualified_id()
+        };
+        let writer = CodeWriter::new(env.internal_loc());
+        let options = BoogieOptions::default();
+        let wrapper = BoogieWrapper {
+            env: &env,
+            targets: &targets,
+            writer: &writer,
+            options: &options,
+        };
+
+        let out = r#"Parsing output.bpl
+Verifying $42_M_f$verify ...
+  [0.125 s, 3 proof obligations]  verified
+Verifying Implementation $42_M_g'u64'$verify_inconsistency ...
+  [1.5 s, solver resource count: 1234, 1 proof obligation]  error
+Verifying $42_M_f$verify_vacuity_0 ...
+Verifying $42_M_g$verify ...
+  [2 s, 5 proof obligations]  verified
+  [0.25 s, 2 proof obligations]  verified
+Verifying $42_N_h$verify ...
+  [1 s, 1 proof obligation]  verified
+Verifying $42_M_f_helper ...
+  [1 s, 1 proof obligation]  verified
+  [1 s, 1 proof obligation]  verified
+"#;
+        let stats = wrapper
+            .extract_stats(out)
+            .into_iter()
+            .map(|s| (s.fun_id, s.procedure, s.duration, s.vc_count))
+            .collect_vec();
+        assert_eq!(
+            stats,
+            vec![
+                (
+                    fun_id("f"),
+                    "$42_M_f$verify".to_string(),
+                    Duration::from_millis(125),
+                    3
+                ),
+                (
+                    fun_id("g"),
+                    "$42_M_g'u64'$verify_inconsistency".to_string(),
+                    Duration::from_millis(1500),
+                    1
+                ),
+                // With interleaved output, results are paired with procedures in order.
+                (
+                    fun_id("f"),
+                    "$42_M_f$verify_vacuity_0".to_string(),
+                    Duration::from_secs(2),
+                    5
+                ),
+                (
+                    fun_id("g"),
+                    "$42_M_g$verify".to_string(),
+                    Duration::from_millis(250),
+                    2
+                ),
+            ]
+        );
+    }
+}
@@ -626,6 +626,25 @@ impl<'env> FunctionTranslator<'env> {
                         ));
                         format!("$verify_{}", flavor)
                     }
+                    VerificationFlavor::Vacuity(_) => {
+                        // The only assertion left in this variant is the checked `ensures`.
+                        let ensures_loc = fun_target
+                            .get_bytecode()
+                            .iter()
+                            .find_map(|bc| match bc {
+                                Bytecode::Prop(id, PropKind::Assert, _) => {
+                                    Some(fun_target.get_bytecode_loc(*id))
+                                }
+                                _ => None,
+                            })
+                            .unwrap_or_else(|| fun_target.get_loc());
+                        attribs.push(format!(
+                            "{{:msg_if_verifies \"vacuous_ensures{}\"}} ",
+                            self.loc_str(&ensures_loc)
+                        ));
+                        format!("$verify_{}", flavor)
+                    }
+                    VerificationFlavor::UncoveredAbort => format!("$verify_{}", flavor),
                 };
                 (suffix, attribs.join(""))
             }
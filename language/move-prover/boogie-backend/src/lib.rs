@@ -51,7 +51,7 @@ const TABLE_ARRAY_THEORY: &[u8] = include_bytes!("prelude/table-array-theory.bpl
 const BCS_MODULE: &str = "0x1::bcs";
 const EVENT_MODULE: &str = "0x1::event";
 
-mod boogie_helpers;
+pub mod boogie_helpers;
 pub mod boogie_wrapper;
 pub mod bytecode_translator;
 pub mod options;
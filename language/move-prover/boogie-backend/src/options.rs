@@ -139,12 +139,17 @@ pub struct BoogieOptions {
     pub vector_theory: VectorTheory,
     /// Whether to generate a z3 trace file and where to put it.
     pub z3_trace_file: Option<String>,
+    /// Whether to let boogie trace the time and number of proof obligations of each procedure.
+    pub collect_stats: bool,
     /// Options to define user-custom native funs.
     pub custom_natives: Option<CustomNativeOptions>,
     /// Number of iterations to unroll loops.
     pub loop_unroll: Option<u64>,
     /// Optional aggregate function names for native methods implementing mutable borrow semantics
     pub borrow_aggregates: Vec<BorrowAggregate>,
+    /// If set, the directory into which Move unit tests replaying the counterexamples of
+    /// verification errors are written.
+    pub counterexample_tests_dir: Option<String>,
 }
 
 impl Default for BoogieOptions {
@@ -179,9 +184,11 @@ impl Default for BoogieOptions {
             hard_timeout_secs: 0,
             vector_theory: VectorTheory::BoogieArray,
             z3_trace_file: None,
+            collect_stats: false,
             custom_natives: None,
             loop_unroll: None,
             borrow_aggregates: vec![],
+            counterexample_tests_dir: None,
         }
     }
 }
@@ -261,6 +268,9 @@ impl BoogieOptions {
                 &format!("-proverOpt:O:trace_file_name={}", file),
             ]);
         }
+        if self.collect_stats {
+            add(&["-trace"]);
+        }
         if self.generate_smt {
             add(&["-proverLog:@PROC@.smt"]);
         }
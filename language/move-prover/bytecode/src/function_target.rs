@@ -73,6 +73,9 @@ pub struct FunctionData {
     pub locations: BTreeMap<AttrId, Loc>,
     /// The set of asserts that represent loop invariants
     pub loop_invariants: BTreeSet<AttrId>,
+    /// The set of asserts that represent the `decreases` measures of loops. Unlike other
+    /// asserts, their expressions are numbers rather than booleans.
+    pub loop_measures: BTreeSet<AttrId>,
     /// A map from byte code attribute to comments associated with this bytecode.
     /// These comments are generated by transformations and are intended for internal
     /// debugging when the bytecode is dumped.
@@ -408,6 +411,7 @@ impl FunctionData {
         locations: BTreeMap<AttrId, Loc>,
         acquires_global_resources: Vec<StructId>,
         loop_invariants: BTreeSet<AttrId>,
+        loop_measures: BTreeSet<AttrId>,
     ) -> Self {
         let name_to_index = (0..func_env.get_local_count())
             .map(|idx| (func_env.get_local_name(idx), idx))
@@ -422,6 +426,7 @@ impl FunctionData {
             acquires_global_resources,
             locations,
             loop_invariants,
+            loop_measures,
             debug_comments: Default::default(),
             vc_infos: Default::default(),
             annotations: Default::default(),
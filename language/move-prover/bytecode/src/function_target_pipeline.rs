@@ -39,6 +39,10 @@ pub enum VerificationFlavor {
     Regular,
     Instantiated(usize),
     Inconsistency(Box<VerificationFlavor>),
+    /// Checks whether the n-th `ensures` of the function holds for a havocked body.
+    Vacuity(usize),
+    /// Checks whether the function can abort although it has no `aborts_if`.
+    UncoveredAbort,
 }
 
 impl std::fmt::Display for VerificationFlavor {
@@ -49,6 +53,8 @@ impl std::fmt::Display for VerificationFlavor {
                 write!(f, "instantiated_{}", index)
             }
             VerificationFlavor::Inconsistency(flavor) => write!(f, "inconsistency_{}", flavor),
+            VerificationFlavor::Vacuity(index) => write!(f, "vacuity_{}", index),
+            VerificationFlavor::UncoveredAbort => write!(f, "uncovered_abort"),
         }
     }
 }
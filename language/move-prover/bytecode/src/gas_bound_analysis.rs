@@ -0,0 +1,1289 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Static analysis computing symbolic upper bounds of the gas used by functions.
+//!
+//! The analysis runs on the initial stackless bytecode of each function and charges every
+//! instruction with the cost of the Move instruction it stems from, according to a gas schedule
+//! shaped like the `CostTable` of the VM. Charges which depend on the size of values use the
+//! abstract memory size of their type. Bounds are polynomials over symbols with non-negative
+//! values:
+//!
+//! - `n`, the value of an integer parameter which is never modified,
+//! - `len(v)`, the length of a vector parameter which is never modified,
+//! - `len(vector<T>)`, the maximal length of any vector of the given type involved otherwise,
+//! - `size(T)`, the maximal abstract size of values of a type parameter, and
+//! - `gas(m::f)`, the cost of a native function which has no cost in the gas schedule.
+//!
+//! Loops are bounded by loop invariants of the form `i <= e` or `i < e`, where `i` is a
+//! counter which is incremented on every iteration and never decremented, and `e` is built
+//! from the symbols above. The bound of a function without such an invariant for one of its
+//! loops, or with recursion, is unbounded.
+//!
+//! If `ProverOptions::check_max_gas` is set, functions with a `pragma max_gas = N` are checked
+//! against their bound: constant bounds are compared right away, bounds in terms of parameters
+//! are asserted at the entry of the function, like an inline `spec { assert bound <= N; }`, and
+//! verified by the prover under the function's preconditions.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, fs,
+};
+
+use itertools::Itertools;
+use log::warn;
+use num::BigInt;
+use serde::{Deserialize, Serialize};
+
+use move_binary_format::file_format_common::Opcodes;
+use move_model::{
+    ast::{Exp, ExpData, Operation as SpecOperation, TempIndex, Value},
+    exp_generator::ExpGenerator,
+    model::{FunId, FunctionEnv, GlobalEnv, QualifiedId},
+    pragmas::MAX_GAS_PRAGMA,
+    ty::{PrimitiveType, Type, TypeDisplayContext, NUM_TYPE},
+};
+use move_vm_test_utils::gas_schedule::INITIAL_COST_SCHEDULE;
+
+use crate::{
+    function_data_builder::FunctionDataBuilder,
+    function_target::{FunctionData, FunctionTarget},
+    function_target_pipeline::{FunctionTargetProcessor, FunctionTargetsHolder, FunctionVariant},
+    graph::Graph,
+    options::ProverOptions,
+    stackless_bytecode::{AssignKind, Bytecode, Constant, Operation, PropKind},
+    stackless_control_flow_graph::{BlockContent, BlockId, StacklessControlFlowGraph},
+};
+
+/// The message of the assertion that the gas bound does not exceed `pragma max_gas`.
+pub const MAX_GAS_FAILED: &str = "gas bound may exceed `pragma max_gas`";
+
+/// The abstract memory size of primitive values.
+const CONST_SIZE: u64 = 16;
+/// The abstract memory size of addresses.
+const ADDRESS_SIZE: u64 = 32;
+/// The abstract memory size of references.
+const REFERENCE_SIZE: u64 = 8;
+/// The abstract memory size of the header of structs and vectors.
+const STRUCT_SIZE: u64 = 2;
+/// The size charged for an `exists` on data which does not exist.
+const MIN_EXISTS_DATA_SIZE: u64 = 100;
+
+// =================================================================================================
+// Gas Schedule
+
+/// The cost of an instruction, as in the VM's gas schedule.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GasCost {
+    pub instruction_gas: u64,
+    pub memory_gas: u64,
+}
+
+/// The gas schedule the bounds are computed with. It has the shape of the VM's `CostTable`, so
+/// a serialized cost table can be used as is, extended by the costs of native functions.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GasSchedule {
+    /// The costs of instructions, indexed by their opcode minus one.
+    pub instruction_table: Vec<GasCost>,
+    /// The costs of native functions, by their name, e.g. `hash::sha3_256`.
+    #[serde(default)]
+    pub native_table: BTreeMap<String, u64>,
+}
+
+impl Default for GasSchedule {
+    /// Returns the initial cost schedule of the VM, without costs for native functions.
+    fn default() -> Self {
+        Self {
+            instruction_table: INITIAL_COST_SCHEDULE
+                .instruction_table
+                .iter()
+                .map(|cost| GasCost {
+                    instruction_gas: cost.instruction_gas,
+                    memory_gas: cost.memory_gas,
+                })
+                .collect(),
+            native_table: BTreeMap::new(),
+        }
+    }
+}
+
+impl GasSchedule {
+    /// Loads a gas schedule from a JSON file.
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Returns the gas schedule configured in the options, or the default one.
+    pub fn from_options(options: &ProverOptions) -> Self {
+        match &options.gas_schedule {
+            Some(path) => Self::load(path).unwrap_or_else(|e| {
+                warn!("cannot load gas schedule `{}`: {}", path, e);
+                Self::default()
+            }),
+            None => Self::default(),
+        }
+    }
+
+    /// Returns the cost of an instruction.
+    pub fn instruction_cost(&self, opcode: Opcodes) -> u64 {
+        self.instruction_table
+            .get(opcode as usize - 1)
+            .map(|cost| cost.instruction_gas.saturating_add(cost.memory_gas))
+            .unwrap_or_default()
+    }
+}
+
+// =================================================================================================
+// Gas Bounds
+
+/// A symbol of a gas bound, which stands for a non-negative number.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GasSymbol {
+    /// The value of an integer parameter.
+    Param(TempIndex),
+    /// The length of a vector parameter, or a reference to one.
+    ParamLen(TempIndex),
+    /// The maximal length of vectors of the given vector type.
+    Len(Type),
+    /// The maximal abstract size of values of the type parameter with the given index.
+    Size(u16),
+    /// The cost of a native function which has no cost in the gas schedule.
+    Native(QualifiedId<FunId>),
+}
+
+/// A symbolic upper bound of gas: a polynomial with non-negative coefficients over
+/// `GasSymbol`s, represented as a map from monomials (sorted products of symbols) to their
+/// coefficients, or unbounded, with the reason why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GasBound {
+    Bounded(BTreeMap<Vec<GasSymbol>, u64>),
+    Unbounded(String),
+}
+
+impl GasBound {
+    pub fn zero() -> Self {
+        GasBound::Bounded(BTreeMap::new())
+    }
+
+    pub fn constant(value: u64) -> Self {
+        GasBound::Bounded(BTreeMap::from([(vec![], value)]))
+    }
+
+    pub fn symbol(symbol: GasSymbol) -> Self {
+        GasBound::Bounded(BTreeMap::from([(vec![symbol], 1)]))
+    }
+
+    pub fn unbounded(reason: impl Into<String>) -> Self {
+        GasBound::Unbounded(reason.into())
+    }
+
+    /// Returns the value of the bound if it is a constant.
+    pub fn as_constant(&self) -> Option<u64> {
+        match self {
+            GasBound::Bounded(terms) => terms.iter().try_fold(0, |acc: u64, (mono, coeff)| {
+                mono.is_empty().then(|| acc.saturating_add(*coeff))
+            }),
+            GasBound::Unbounded(_) => None,
+        }
+    }
+
+    pub fn add(&self, other: &GasBound) -> GasBound {
+        self.combine(other, |terms, mono, coeff| {
+            let entry = terms.entry(mono).or_default();
+            *entry = entry.saturating_add(coeff);
+        })
+    }
+
+    /// Returns a bound which is at least as large as both bounds. As all symbols are
+    /// non-negative, the coefficient-wise maximum is such a bound.
+    pub fn join(&self, other: &GasBound) -> GasBound {
+        self.combine(other, |terms, mono, coeff| {
+            let entry = terms.entry(mono).or_default();
+            *entry = (*entry).max(coeff);
+        })
+    }
+
+    pub fn mul(&self, other: &GasBound) -> GasBound {
+        match (self, other) {
+            (GasBound::Unbounded(reason), _) | (_, GasBound::Unbounded(reason)) => {
+                GasBound::Unbounded(reason.clone())
+            }
+            (GasBound::Bounded(terms1), GasBound::Bounded(terms2)) => {
+                let mut terms = BTreeMap::new();
+                for ((mono1, coeff1), (mono2, coeff2)) in
+                    terms1.iter().cartesian_product(terms2.iter())
+                {
+                    let mono = mono1.iter().chain(mono2.iter()).cloned().sorted().collect();
+                    let entry: &mut u64 = terms.entry(mono).or_default();
+                    *entry = entry.saturating_add(coeff1.saturating_mul(*coeff2));
+                }
+                GasBound::Bounded(terms)
+            }
+        }
+    }
+
+    /// Replaces each symbol by the given bound.
+    pub fn substitute(&self, f: &mut dyn FnMut(&GasSymbol) -> GasBound) -> GasBound {
+        match self {
+            GasBound::Unbounded(_) => self.clone(),
+            GasBound::Bounded(terms) => {
+                terms.iter().fold(GasBound::zero(), |acc, (mono, coeff)| {
+                    let term = mono
+                        .iter()
+                        .fold(GasBound::constant(*coeff), |term, sym| term.mul(&f(sym)));
+                    acc.add(&term)
+                })
+            }
+        }
+    }
+
+    /// Returns the symbols the bound depends on.
+    pub fn symbols(&self) -> BTreeSet<&GasSymbol> {
+        match self {
+            GasBound::Bounded(terms) => terms.keys().flatten().collect(),
+            GasBound::Unbounded(_) => BTreeSet::new(),
+        }
+    }
+
+    fn combine(
+        &self,
+        other: &GasBound,
+        f: impl Fn(&mut BTreeMap<Vec<GasSymbol>, u64>, Vec<GasSymbol>, u64),
+    ) -> GasBound {
+        match (self, other) {
+            (GasBound::Unbounded(reason), _) | (_, GasBound::Unbounded(reason)) => {
+                GasBound::Unbounded(reason.clone())
+            }
+            (GasBound::Bounded(terms1), GasBound::Bounded(terms2)) => {
+                let mut terms = terms1.clone();
+                for (mono, coeff) in terms2 {
+                    f(&mut terms, mono.clone(), *coeff);
+                }
+                GasBound::Bounded(terms)
+            }
+        }
+    }
+
+    /// Returns a displayable representation of the bound of the given function.
+    pub fn display<'a>(&'a self, fun_env: &'a FunctionEnv<'a>) -> GasBoundDisplay<'a> {
+        GasBoundDisplay {
+            bound: self,
+            fun_env,
+        }
+    }
+}
+
+/// A helper to display a gas bound in the context of its function.
+pub struct GasBoundDisplay<'a> {
+    bound: &'a GasBound,
+    fun_env: &'a FunctionEnv<'a>,
+}
+
+impl<'a> GasBoundDisplay<'a> {
+    fn symbol_str(&self, ctx: &TypeDisplayContext, symbol: &GasSymbol) -> String {
+        let env = self.fun_env.module_env.env;
+        let local_name = |idx: TempIndex| {
+            self.fun_env
+                .get_local_name(idx)
+                .display(self.fun_env.symbol_pool())
+                .to_string()
+        };
+        match symbol {
+            GasSymbol::Param(idx) => local_name(*idx),
+            GasSymbol::ParamLen(idx) => format!("len({})", local_name(*idx)),
+            GasSymbol::Len(ty) => format!("len({})", ty.display(ctx)),
+            GasSymbol::Size(idx) => {
+                format!("size({})", Type::TypeParameter(*idx).display(ctx))
+            }
+            GasSymbol::Native(fun_id) => {
+                format!("gas({})", env.get_function(*fun_id).get_full_name_str())
+            }
+        }
+    }
+}
+
+impl<'a> fmt::Display for GasBoundDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.bound {
+            GasBound::Unbounded(reason) => write!(f, "unbounded ({})", reason),
+            GasBound::Bounded(terms) => {
+                let terms = terms.iter().filter(|(_, coeff)| **coeff > 0).collect_vec();
+                if terms.is_empty() {
+                    return write!(f, "0");
+                }
+                let ctx = self.fun_env.get_type_display_ctx();
+                let term_strs = terms.into_iter().map(|(mono, coeff)| {
+                    let mut syms = mono.iter().map(|sym| self.symbol_str(&ctx, sym));
+                    if mono.is_empty() {
+                        coeff.to_string()
+                    } else if *coeff == 1 {
+                        syms.join("*")
+                    } else {
+                        std::iter::once(coeff.to_string()).chain(syms).join("*")
+                    }
+                });
+                write!(f, "{}", term_strs.format(" + "))
+            }
+        }
+    }
+}
+
+/// The gas bound of a function, as computed by the analysis.
+#[derive(Debug, Clone)]
+pub struct GasBoundAnnotation(pub GasBound);
+
+/// Returns the gas bound of the function target, if it has been analyzed.
+pub fn get_gas_bound<'a>(target: &'a FunctionTarget<'a>) -> Option<&'a GasBound> {
+    target
+        .get_annotations()
+        .get::<GasBoundAnnotation>()
+        .map(|a| &a.0)
+}
+
+// =================================================================================================
+// Analysis
+
+/// The result of tracing a temporary back to where its value comes from.
+enum Origin {
+    /// A parameter which is never modified.
+    Param(TempIndex),
+    Constant(u64),
+    Unknown,
+}
+
+struct GasBoundAnalysis<'a> {
+    env: &'a GlobalEnv,
+    target: FunctionTarget<'a>,
+    targets: &'a FunctionTargetsHolder,
+    schedule: &'a GasSchedule,
+    /// The functions which are mutually recursive with this one.
+    recursive: BTreeSet<QualifiedId<FunId>>,
+    /// The code offset of the definition of each temporary which is defined exactly once.
+    defs: BTreeMap<TempIndex, usize>,
+    /// The parameters which are never modified.
+    stable_params: BTreeSet<TempIndex>,
+}
+
+impl<'a> GasBoundAnalysis<'a> {
+    fn new(
+        target: FunctionTarget<'a>,
+        targets: &'a FunctionTargetsHolder,
+        schedule: &'a GasSchedule,
+        scc_opt: Option<&[FunctionEnv]>,
+    ) -> Self {
+        let code = target.get_bytecode();
+        let mut def_offsets: BTreeMap<TempIndex, Vec<usize>> = BTreeMap::new();
+        let mut mut_borrowed = BTreeSet::new();
+        for (offset, bc) in code.iter().enumerate() {
+            match bc {
+                Bytecode::Assign(_, dest, ..) | Bytecode::Load(_, dest, _) => {
+                    def_offsets.entry(*dest).or_default().push(offset)
+                }
+                Bytecode::Call(_, dests, op, srcs, _) => {
+                    for dest in dests {
+                        def_offsets.entry(*dest).or_default().push(offset)
+                    }
+                    if matches!(op, Operation::BorrowLoc)
+                        && target.get_local_type(dests[0]).is_mutable_reference()
+                    {
+                        mut_borrowed.insert(srcs[0]);
+                    }
+                }
+                _ => {}
+            }
+        }
+        let stable_params = (0..target.get_parameter_count())
+            .filter(|idx| {
+                !def_offsets.contains_key(idx)
+                    && !mut_borrowed.contains(idx)
+                    && !target.get_local_type(*idx).is_mutable_reference()
+            })
+            .collect();
+        let defs = def_offsets
+            .into_iter()
+            .filter_map(|(temp, offsets)| (offsets.len() == 1).then(|| (temp, offsets[0])))
+            .collect();
+        Self {
+            env: target.func_env.module_env.env,
+            target,
+            targets,
+            schedule,
+            recursive: scc_opt
+                .unwrap_or_default()
+                .iter()
+                .map(|fun_env| fun_env.get_qualified_id())
+                .collect(),
+            defs,
+            stable_params,
+        }
+    }
+
+    /// Computes the gas bound of the function: the longest path through the control flow
+    /// graph, where each loop is collapsed into a node whose cost is the cost of its body
+    /// times its bound.
+    fn compute(&self) -> GasBound {
+        let code = self.target.get_bytecode();
+        let cfg = StacklessControlFlowGraph::new_forward(code);
+        let entry = cfg.entry_block();
+        let nodes = cfg.blocks();
+        let edges = nodes
+            .iter()
+            .flat_map(|x| cfg.successors(*x).iter().map(move |y| (*x, *y)))
+            .collect_vec();
+        let graph = Graph::new(entry, nodes.clone(), edges);
+        let natural_loops = match graph.compute_reducible() {
+            Some(loops) => loops,
+            None => return GasBound::unbounded("irreducible control flow"),
+        };
+
+        // Merge the natural loops sharing a header.
+        let mut loops: BTreeMap<BlockId, (BTreeSet<BlockId>, BTreeSet<BlockId>)> = BTreeMap::new();
+        for natural_loop in natural_loops {
+            let (body, latches) = loops.entry(natural_loop.loop_header).or_default();
+            body.extend(natural_loop.loop_body);
+            latches.insert(natural_loop.loop_latch);
+        }
+        let mut loop_bounds = BTreeMap::new();
+        for (header, (body, latches)) in &loops {
+            loop_bounds.insert(*header, self.loop_bound(&cfg, *header, body, latches));
+        }
+
+        // Each block is represented by its outermost loop, if it is in one.
+        let rep = |block: BlockId| {
+            loops
+                .iter()
+                .filter(|(_, (body, _))| body.contains(&block))
+                .max_by_key(|(_, (body, _))| body.len())
+                .map(|(header, _)| *header)
+                .unwrap_or(block)
+        };
+        let mut weights: BTreeMap<BlockId, GasBound> = BTreeMap::new();
+        for block in &nodes {
+            let multiplicity = loops
+                .iter()
+                .filter(|(_, (body, _))| body.contains(block))
+                .fold(GasBound::constant(1), |acc, (header, _)| {
+                    acc.mul(&loop_bounds[header])
+                });
+            let cost = self.block_cost(&cfg, *block).mul(&multiplicity);
+            let weight = weights.entry(rep(*block)).or_insert_with(GasBound::zero);
+            *weight = weight.add(&cost);
+        }
+        let mut successors: BTreeMap<BlockId, BTreeSet<BlockId>> = BTreeMap::new();
+        for block in &nodes {
+            let from = rep(*block);
+            for succ in cfg.successors(*block) {
+                let to = rep(*succ);
+                if from != to {
+                    successors.entry(from).or_default().insert(to);
+                }
+            }
+        }
+        let mut memo = BTreeMap::new();
+        Self::longest_path(
+            rep(entry),
+            &weights,
+            &successors,
+            &mut memo,
+            &mut BTreeSet::new(),
+        )
+    }
+
+    fn longest_path(
+        node: BlockId,
+        weights: &BTreeMap<BlockId, GasBound>,
+        successors: &BTreeMap<BlockId, BTreeSet<BlockId>>,
+        memo: &mut BTreeMap<BlockId, GasBound>,
+        visiting: &mut BTreeSet<BlockId>,
+    ) -> GasBound {
+        if let Some(bound) = memo.get(&node) {
+            return bound.clone();
+        }
+        if !visiting.insert(node) {
+            return GasBound::unbounded("irreducible control flow");
+        }
+        let mut rest = GasBound::zero();
+        for succ in successors.get(&node).into_iter().flatten() {
+            rest = rest.join(&Self::longest_path(
+                *succ, weights, successors, memo, visiting,
+            ));
+        }
+        visiting.remove(&node);
+        let bound = weights
+            .get(&node)
+            .cloned()
+            .unwrap_or_else(GasBound::zero)
+            .add(&rest);
+        memo.insert(node, bound.clone());
+        bound
+    }
+
+    fn block_cost(&self, cfg: &StacklessControlFlowGraph, block: BlockId) -> GasBound {
+        let code = self.target.get_bytecode();
+        match cfg.content(block) {
+            BlockContent::Dummy => GasBound::zero(),
+            BlockContent::Basic { lower, upper } => (*lower..=*upper)
+                .fold(GasBound::zero(), |acc, offset| {
+                    acc.add(&self.instruction_cost(&code[offset as usize]))
+                }),
+        }
+    }
+
+    // ---------------------------------------------------------------------------------------------
+    // Loop Bounds
+
+    /// Computes the maximal number of executions of the loop header per entry of the loop,
+    /// from the loop invariants.
+    fn loop_bound(
+        &self,
+        cfg: &StacklessControlFlowGraph,
+        header: BlockId,
+        body: &BTreeSet<BlockId>,
+        latches: &BTreeSet<BlockId>,
+    ) -> GasBound {
+        let code = self.target.get_bytecode();
+        let invariants = &self.target.data.loop_invariants;
+        let mut bound = None;
+        for offset in cfg.instr_indexes(header).into_iter().flatten().skip(1) {
+            match &code[offset as usize] {
+                Bytecode::Prop(attr_id, PropKind::Assert, exp) if invariants.contains(attr_id) => {
+                    if let Some(b) = self.invariant_bound(cfg, exp, body, latches) {
+                        bound = Some(b);
+                        break;
+                    }
+                }
+                _ => break,
+            }
+        }
+        bound.unwrap_or_else(|| {
+            let loc = self.target.get_bytecode_loc(
+                code[cfg.instr_indexes(header).unwrap().next().unwrap() as usize].get_attr_id(),
+            );
+            GasBound::unbounded(format!(
+                "no loop invariant bounds the loop at line {}",
+                self.env
+                    .get_location(&loc)
+                    .map(|l| l.line.0 + 1)
+                    .unwrap_or_default()
+            ))
+        })
+    }
+
+    /// Derives the number of executions of the loop header from an invariant `i <= e` or
+    /// `i < e`, for a counter `i`: at most `e + 1`.
+    fn invariant_bound(
+        &self,
+        cfg: &StacklessControlFlowGraph,
+        exp: &Exp,
+        body: &BTreeSet<BlockId>,
+        latches: &BTreeSet<BlockId>,
+    ) -> Option<GasBound> {
+        let (counter, limit) = match exp.as_ref() {
+            ExpData::Call(_, SpecOperation::And, args) => {
+                return args
+                    .iter()
+                    .find_map(|arg| self.invariant_bound(cfg, arg, body, latches))
+            }
+            ExpData::Call(_, SpecOperation::Le | SpecOperation::Lt, args) => (&args[0], &args[1]),
+            ExpData::Call(_, SpecOperation::Ge | SpecOperation::Gt, args) => (&args[1], &args[0]),
+            _ => return None,
+        };
+        match counter.as_ref() {
+            ExpData::Temporary(_, temp) if self.is_counter(cfg, *temp, body, latches) => self
+                .spec_bound(limit)
+                .map(|limit| limit.add(&GasBound::constant(1))),
+            _ => None,
+        }
+    }
+
+    /// Translates a specification expression into a gas bound, if it is built from constants,
+    /// stable parameters, and vector lengths.
+    fn spec_bound(&self, exp: &Exp) -> Option<GasBound> {
+        match exp.as_ref() {
+            ExpData::Value(_, Value::Number(n)) => u64::try_from(n).ok().map(GasBound::constant),
+            ExpData::Temporary(_, temp)
+                if self.stable_params.contains(temp)
+                    && self.target.get_local_type(*temp).is_number() =>
+            {
+                Some(GasBound::symbol(GasSymbol::Param(*temp)))
+            }
+            ExpData::Call(_, SpecOperation::Add, args) => {
+                Some(self.spec_bound(&args[0])?.add(&self.spec_bound(&args[1])?))
+            }
+            ExpData::Call(_, SpecOperation::Mul, args) => {
+                Some(self.spec_bound(&args[0])?.mul(&self.spec_bound(&args[1])?))
+            }
+            ExpData::Call(_, SpecOperation::Len, args) => self.spec_len(&args[0]),
+            ExpData::Call(_, SpecOperation::Function(mid, fid, _), args) => {
+                let module_env = self.env.get_module(*mid);
+                let name = module_env.get_spec_fun(*fid).name;
+                if module_env.is_std_vector()
+                    && name.display(self.env.symbol_pool()).to_string() == "length"
+                {
+                    self.spec_len(&args[0])
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn spec_len(&self, exp: &Exp) -> Option<GasBound> {
+        match exp.as_ref() {
+            ExpData::Temporary(_, temp) => Some(self.vector_len(*temp)),
+            _ => None,
+        }
+    }
+
+    /// Checks whether the temporary is a counter of the loop: it is only assigned in the loop by
+    /// incrementing it, never borrowed mutably, and incremented on every path from the header
+    /// to the back edges.
+    fn is_counter(
+        &self,
+        cfg: &StacklessControlFlowGraph,
+        temp: TempIndex,
+        body: &BTreeSet<BlockId>,
+        latches: &BTreeSet<BlockId>,
+    ) -> bool {
+        let code = self.target.get_bytecode();
+        if !self.target.get_local_type(temp).is_number() {
+            return false;
+        }
+        if code.iter().any(|bc| {
+            matches!(bc, Bytecode::Call(_, dests, Operation::BorrowLoc, srcs, _)
+                if srcs[0] == temp && self.target.get_local_type(dests[0]).is_mutable_reference())
+        }) {
+            return false;
+        }
+        let mut incrementing = BTreeSet::new();
+        for block in body {
+            for offset in cfg.instr_indexes(*block).into_iter().flatten() {
+                let bc = &code[offset as usize];
+                let defines = match bc {
+                    Bytecode::Assign(_, dest, ..) | Bytecode::Load(_, dest, _) => *dest == temp,
+                    Bytecode::Call(_, dests, ..) => dests.contains(&temp),
+                    _ => false,
+                };
+                if defines {
+                    if !self.is_increment(bc, temp) {
+                        return false;
+                    }
+                    incrementing.insert(*block);
+                }
+            }
+        }
+        // No back edge is reachable from the header without passing an increment.
+        let header = cfg_header(body, latches, cfg);
+        if incrementing.contains(&header) {
+            return true;
+        }
+        let mut todo = vec![header];
+        let mut visited = BTreeSet::new();
+        while let Some(block) = todo.pop() {
+            if !visited.insert(block) {
+                continue;
+            }
+            if latches.contains(&block) {
+                return false;
+            }
+            for succ in cfg.successors(block) {
+                if body.contains(succ) && *succ != header && !incrementing.contains(succ) {
+                    todo.push(*succ);
+                }
+            }
+        }
+        true
+    }
+
+    /// Checks whether the instruction is `temp := temp + c` with a constant `c >= 1`.
+    fn is_increment(&self, bc: &Bytecode, temp: TempIndex) -> bool {
+        let code = self.target.get_bytecode();
+        let is_add = |bc: &Bytecode| match bc {
+            Bytecode::Call(_, _, Operation::Add, srcs, _) => {
+                let (lhs, rhs) = (self.copied_from(srcs[0]), self.copied_from(srcs[1]));
+                (lhs == temp && matches!(self.origin(rhs), Origin::Constant(c) if c >= 1))
+                    || (rhs == temp && matches!(self.origin(lhs), Origin::Constant(c) if c >= 1))
+            }
+            _ => false,
+        };
+        match bc {
+            Bytecode::Assign(_, _, src, _) => self
+                .defs
+                .get(src)
+                .map(|offset| is_add(&code[*offset]))
+                .unwrap_or(false),
+            Bytecode::Call(..) => is_add(bc),
+            _ => false,
+        }
+    }
+
+    /// Follows copies and moves of a temporary which is defined once.
+    fn copied_from(&self, mut temp: TempIndex) -> TempIndex {
+        let code = self.target.get_bytecode();
+        while let Some(Bytecode::Assign(_, _, src, _)) = self.defs.get(&temp).map(|o| &code[*o]) {
+            temp = *src;
+        }
+        temp
+    }
+
+    /// Traces the value of an integer temporary back to a stable parameter or a constant.
+    fn origin(&self, temp: TempIndex) -> Origin {
+        let code = self.target.get_bytecode();
+        let temp = self.copied_from(temp);
+        if self.stable_params.contains(&temp) {
+            return Origin::Param(temp);
+        }
+        match self.defs.get(&temp).map(|o| &code[*o]) {
+            Some(Bytecode::Load(_, _, constant)) => match constant {
+                Constant::U8(c) => Origin::Constant(*c as u64),
+                Constant::U16(c) => Origin::Constant(*c as u64),
+                Constant::U32(c) => Origin::Constant(*c as u64),
+                Constant::U64(c) => Origin::Constant(*c),
+                Constant::U128(c) => u64::try_from(*c)
+                    .map(Origin::Constant)
+                    .unwrap_or(Origin::Unknown),
+                _ => Origin::Unknown,
+            },
+            _ => Origin::Unknown,
+        }
+    }
+
+    /// Traces a vector, or a reference to one, back to a stable vector parameter.
+    fn vector_param(&self, mut temp: TempIndex) -> Option<TempIndex> {
+        let code = self.target.get_bytecode();
+        loop {
+            if self.stable_params.contains(&temp) {
+                return Some(temp);
+            }
+            temp = match self.defs.get(&temp).map(|o| &code[*o]) {
+                Some(Bytecode::Assign(_, _, src, _)) => *src,
+                Some(Bytecode::Call(
+                    _,
+                    _,
+                    Operation::BorrowLoc | Operation::FreezeRef | Operation::ReadRef,
+                    srcs,
+                    _,
+                )) => srcs[0],
+                _ => return None,
+            }
+        }
+    }
+
+    /// Returns the length of the vector in the temporary, which may be a reference to one.
+    fn vector_len(&self, temp: TempIndex) -> GasBound {
+        match self.vector_param(temp) {
+            Some(param) => GasBound::symbol(GasSymbol::ParamLen(param)),
+            None => GasBound::symbol(GasSymbol::Len(
+                self.target.get_local_type(temp).skip_reference().clone(),
+            )),
+        }
+    }
+
+    // ---------------------------------------------------------------------------------------------
+    // Instruction Costs
+
+    fn charge(&self, opcode: Opcodes) -> GasBound {
+        GasBound::constant(self.schedule.instruction_cost(opcode))
+    }
+
+    /// Charges an instruction per unit of the given size, which is at least one.
+    fn charge_sized(&self, opcode: Opcodes, size: GasBound) -> GasBound {
+        self.charge(opcode).mul(&size.join(&GasBound::constant(1)))
+    }
+
+    /// Returns the abstract memory size of the value in the temporary.
+    fn value_size(&self, temp: TempIndex) -> GasBound {
+        let ty = self.target.get_local_type(temp);
+        match ty {
+            Type::Vector(elem) => match self.vector_param(temp) {
+                Some(param) => {
+                    vector_size(self.env, GasBound::symbol(GasSymbol::ParamLen(param)), elem)
+                }
+                None => type_size(self.env, ty),
+            },
+            _ => type_size(self.env, ty),
+        }
+    }
+
+    /// Returns the abstract memory size of the value the reference in the temporary points to.
+    fn referenced_size(&self, temp: TempIndex) -> GasBound {
+        match self.target.get_local_type(temp).skip_reference() {
+            Type::Vector(elem) => vector_size(self.env, self.vector_len(temp), elem),
+            ty => type_size(self.env, ty),
+        }
+    }
+
+    fn instruction_cost(&self, bc: &Bytecode) -> GasBound {
+        use Bytecode::*;
+        use Opcodes::*;
+        match bc {
+            Assign(_, _, src, kind) => self.charge_sized(
+                match kind {
+                    AssignKind::Copy => COPY_LOC,
+                    AssignKind::Move => MOVE_LOC,
+                    AssignKind::Store => ST_LOC,
+                },
+                self.value_size(*src),
+            ),
+            Load(_, _, constant) => match constant {
+                Constant::Bool(true) => self.charge(LD_TRUE),
+                Constant::Bool(false) => self.charge(LD_FALSE),
+                Constant::U8(_) => self.charge(LD_U8),
+                Constant::U16(_) => self.charge(LD_U16),
+                Constant::U32(_) => self.charge(LD_U32),
+                Constant::U64(_) => self.charge(LD_U64),
+                Constant::U128(_) => self.charge(LD_U128),
+                Constant::U256(_) => self.charge(LD_U256),
+                _ => self.charge_sized(LD_CONST, GasBound::constant(constant_size(constant))),
+            },
+            Ret(..) => self.charge(RET),
+            Abort(..) => self.charge(ABORT),
+            Branch(..) => self.charge(BR_TRUE),
+            Jump(..) => self.charge(BRANCH),
+            Nop(..) => self.charge(NOP),
+            Call(_, dests, op, srcs, _) => self.operation_cost(dests, op, srcs),
+            Label(..) | SaveMem(..) | SaveSpecVar(..) | Prop(..) => GasBound::zero(),
+        }
+    }
+
+    fn operation_cost(&self, dests: &[TempIndex], op: &Operation, srcs: &[TempIndex]) -> GasBound {
+        use Opcodes::*;
+        let sum_sizes = |temps: &[TempIndex]| {
+            temps
+                .iter()
+                .fold(GasBound::constant(temps.len() as u64), |acc, t| {
+                    acc.add(&self.value_size(*t))
+                })
+        };
+        match op {
+            Operation::Function(mid, fid, inst) => self.call_cost(mid.qualified(*fid), inst, srcs),
+            Operation::Pack(_, _, inst) => self.charge_sized(
+                if inst.is_empty() { PACK } else { PACK_GENERIC },
+                sum_sizes(srcs),
+            ),
+            Operation::Unpack(_, _, inst) => self.charge_sized(
+                if inst.is_empty() {
+                    UNPACK
+                } else {
+                    UNPACK_GENERIC
+                },
+                sum_sizes(dests),
+            ),
+            Operation::MoveTo(_, _, inst) => self.charge_sized(
+                if inst.is_empty() {
+                    MOVE_TO
+                } else {
+                    MOVE_TO_GENERIC
+                },
+                self.value_size(srcs[0]),
+            ),
+            Operation::MoveFrom(_, _, inst) => self.charge_sized(
+                if inst.is_empty() {
+                    MOVE_FROM
+                } else {
+                    MOVE_FROM_GENERIC
+                },
+                self.value_size(dests[0]),
+            ),
+            Operation::Exists(_, _, inst) => self.charge_sized(
+                if inst.is_empty() {
+                    EXISTS
+                } else {
+                    EXISTS_GENERIC
+                },
+                GasBound::constant(MIN_EXISTS_DATA_SIZE.max(REFERENCE_SIZE)),
+            ),
+            Operation::BorrowGlobal(_, _, inst) => {
+                let is_mut = self.target.get_local_type(dests[0]).is_mutable_reference();
+                self.charge_sized(
+                    match (is_mut, inst.is_empty()) {
+                        (false, true) => IMM_BORROW_GLOBAL,
+                        (false, false) => IMM_BORROW_GLOBAL_GENERIC,
+                        (true, true) => MUT_BORROW_GLOBAL,
+                        (true, false) => MUT_BORROW_GLOBAL_GENERIC,
+                    },
+                    GasBound::constant(REFERENCE_SIZE),
+                )
+            }
+            Operation::GetGlobal(_, _, inst) => self
+                .charge_sized(
+                    if inst.is_empty() {
+                        IMM_BORROW_GLOBAL
+                    } else {
+                        IMM_BORROW_GLOBAL_GENERIC
+                    },
+                    GasBound::constant(REFERENCE_SIZE),
+                )
+                .add(&self.charge_sized(READ_REF, self.value_size(dests[0]))),
+            Operation::BorrowLoc => {
+                if self.target.get_local_type(dests[0]).is_mutable_reference() {
+                    self.charge(MUT_BORROW_LOC)
+                } else {
+                    self.charge(IMM_BORROW_LOC)
+                }
+            }
+            Operation::BorrowField(_, _, inst, _) => {
+                let is_mut = self.target.get_local_type(dests[0]).is_mutable_reference();
+                self.charge(match (is_mut, inst.is_empty()) {
+                    (false, true) => IMM_BORROW_FIELD,
+                    (false, false) => IMM_BORROW_FIELD_GENERIC,
+                    (true, true) => MUT_BORROW_FIELD,
+                    (true, false) => MUT_BORROW_FIELD_GENERIC,
+                })
+            }
+            Operation::GetField(_, _, inst, _) => self
+                .charge(if inst.is_empty() {
+                    IMM_BORROW_FIELD
+                } else {
+                    IMM_BORROW_FIELD_GENERIC
+                })
+                .add(&self.charge_sized(READ_REF, self.value_size(dests[0]))),
+            Operation::ReadRef => self.charge_sized(READ_REF, self.referenced_size(srcs[0])),
+            Operation::WriteRef => self.charge_sized(WRITE_REF, self.value_size(srcs[1])),
+            Operation::FreezeRef => self.charge(FREEZE_REF),
+            Operation::Destroy => self.charge(POP),
+            Operation::CastU8 => self.charge(CAST_U8),
+            Operation::CastU16 => self.charge(CAST_U16),
+            Operation::CastU32 => self.charge(CAST_U32),
+            Operation::CastU64 => self.charge(CAST_U64),
+            Operation::CastU128 => self.charge(CAST_U128),
+            Operation::CastU256 => self.charge(CAST_U256),
+            Operation::Not => self.charge(NOT),
+            Operation::Add => self.charge(ADD),
+            Operation::Sub => self.charge(SUB),
+            Operation::Mul => self.charge(MUL),
+            Operation::Div => self.charge(DIV),
+            Operation::Mod => self.charge(MOD),
+            Operation::BitOr => self.charge(BIT_OR),
+            Operation::BitAnd => self.charge(BIT_AND),
+            Operation::Xor => self.charge(XOR),
+            Operation::Shl => self.charge(SHL),
+            Operation::Shr => self.charge(SHR),
+            Operation::Lt => self.charge(LT),
+            Operation::Gt => self.charge(GT),
+            Operation::Le => self.charge(LE),
+            Operation::Ge => self.charge(GE),
+            Operation::Or => self.charge(OR),
+            Operation::And => self.charge(AND),
+            Operation::Eq | Operation::Neq => self.charge_sized(
+                if matches!(op, Operation::Eq) { EQ } else { NEQ },
+                self.value_size(srcs[0]).add(&self.value_size(srcs[1])),
+            ),
+            // Operations which are not part of the initial bytecode, or which are for
+            // verification only, are free.
+            _ => GasBound::zero(),
+        }
+    }
+
+    fn call_cost(&self, fun_id: QualifiedId<FunId>, inst: &[Type], srcs: &[TempIndex]) -> GasBound {
+        use Opcodes::*;
+        let callee_env = self.env.get_function(fun_id);
+        let name = callee_env.get_full_name_str();
+        let is_native = callee_env.is_native() || callee_env.is_intrinsic();
+        if is_native && callee_env.module_env.is_std_vector() {
+            // Vector operations are instructions in the VM.
+            let fun_name = callee_env.get_name_str();
+            match fun_name.as_str() {
+                "empty" => return self.charge(VEC_PACK),
+                "length" => return self.charge(VEC_LEN),
+                "borrow" => return self.charge(VEC_IMM_BORROW),
+                "borrow_mut" => return self.charge(VEC_MUT_BORROW),
+                "push_back" => {
+                    return self.charge_sized(VEC_PUSH_BACK, self.value_size(srcs[1]));
+                }
+                "pop_back" => return self.charge(VEC_POP_BACK),
+                "destroy_empty" => return self.charge(VEC_UNPACK),
+                "swap" => return self.charge(VEC_SWAP),
+                _ => {}
+            }
+        }
+        let call = if inst.is_empty() {
+            self.charge_sized(CALL, GasBound::constant(srcs.len() as u64 + 1))
+        } else {
+            self.charge_sized(
+                CALL_GENERIC,
+                GasBound::constant((inst.len() + srcs.len()) as u64 + 1),
+            )
+        };
+        let body = if is_native {
+            match self.schedule.native_table.get(&name) {
+                Some(cost) => GasBound::constant(*cost),
+                None => GasBound::symbol(GasSymbol::Native(fun_id)),
+            }
+        } else if self.recursive.contains(&fun_id) {
+            GasBound::unbounded(format!("recursive call to `{}`", name))
+        } else {
+            match self
+                .targets
+                .get_data(&fun_id, &FunctionVariant::Baseline)
+                .and_then(|data| data.annotations.get::<GasBoundAnnotation>())
+            {
+                Some(GasBoundAnnotation(bound)) => bound
+                    .substitute(&mut |sym| self.instantiate_symbol(&callee_env, sym, inst, srcs)),
+                None => GasBound::unbounded(format!("no gas bound for `{}`", name)),
+            }
+        };
+        call.add(&body)
+    }
+
+    /// Translates a symbol of a callee's bound into the context of the caller.
+    fn instantiate_symbol(
+        &self,
+        callee_env: &FunctionEnv,
+        symbol: &GasSymbol,
+        inst: &[Type],
+        srcs: &[TempIndex],
+    ) -> GasBound {
+        match symbol {
+            GasSymbol::Param(idx) => match self.origin(srcs[*idx]) {
+                Origin::Param(param) => GasBound::symbol(GasSymbol::Param(param)),
+                Origin::Constant(c) => GasBound::constant(c),
+                Origin::Unknown => GasBound::unbounded(format!(
+                    "gas of `{}` depends on the value of `{}`",
+                    callee_env.get_full_name_str(),
+                    callee_env
+                        .get_local_name(*idx)
+                        .display(self.env.symbol_pool())
+                )),
+            },
+            GasSymbol::ParamLen(idx) => self.vector_len(srcs[*idx]),
+            GasSymbol::Len(ty) => GasBound::symbol(GasSymbol::Len(ty.instantiate(inst))),
+            GasSymbol::Size(idx) => type_size(self.env, &inst[*idx as usize]),
+            GasSymbol::Native(_) => GasBound::symbol(symbol.clone()),
+        }
+    }
+}
+
+/// Returns the header of a loop, which is the only block of the body which is a successor of
+/// a latch.
+fn cfg_header(
+    body: &BTreeSet<BlockId>,
+    latches: &BTreeSet<BlockId>,
+    cfg: &StacklessControlFlowGraph,
+) -> BlockId {
+    latches
+        .iter()
+        .flat_map(|latch| cfg.successors(*latch))
+        .find(|succ| {
+            body.contains(succ)
+                && latches
+                    .iter()
+                    .all(|latch| cfg.successors(*latch).contains(succ))
+        })
+        .copied()
+        .expect("loop header")
+}
+
+/// Returns the abstract memory size of values of the given type.
+fn type_size(env: &GlobalEnv, ty: &Type) -> GasBound {
+    match ty {
+        Type::Primitive(PrimitiveType::Address | PrimitiveType::Signer) => {
+            GasBound::constant(ADDRESS_SIZE)
+        }
+        Type::Primitive(_) => GasBound::constant(CONST_SIZE),
+        Type::Reference(..) => GasBound::constant(REFERENCE_SIZE),
+        Type::Vector(elem) => vector_size(env, GasBound::symbol(GasSymbol::Len(ty.clone())), elem),
+        Type::Struct(mid, sid, inst) => env
+            .get_module(*mid)
+            .into_struct(*sid)
+            .get_fields()
+            .fold(GasBound::constant(STRUCT_SIZE), |acc, field| {
+                acc.add(&type_size(env, &field.get_type().instantiate(inst)))
+            }),
+        Type::TypeParameter(idx) => GasBound::symbol(GasSymbol::Size(*idx)),
+        _ => GasBound::zero(),
+    }
+}
+
+/// Returns the abstract memory size of a vector with the given length and element type.
+fn vector_size(env: &GlobalEnv, len: GasBound, elem: &Type) -> GasBound {
+    let elem_size = match elem {
+        Type::Primitive(PrimitiveType::Bool | PrimitiveType::U8) => GasBound::constant(1),
+        Type::Primitive(PrimitiveType::U16) => GasBound::constant(2),
+        Type::Primitive(PrimitiveType::U32) => GasBound::constant(4),
+        Type::Primitive(PrimitiveType::U64) => GasBound::constant(8),
+        Type::Primitive(PrimitiveType::U128) => GasBound::constant(16),
+        Type::Primitive(PrimitiveType::U256) => GasBound::constant(32),
+        _ => type_size(env, elem),
+    };
+    GasBound::constant(STRUCT_SIZE).add(&len.mul(&elem_size))
+}
+
+/// Returns the size of the serialized form of a constant, as charged by `LdConst`.
+fn constant_size(constant: &Constant) -> u64 {
+    match constant {
+        Constant::Bool(_) | Constant::U8(_) => 1,
+        Constant::U16(_) => 2,
+        Constant::U32(_) => 4,
+        Constant::U64(_) => 8,
+        Constant::U128(_) => 16,
+        Constant::U256(_) | Constant::Address(_) => 32,
+        Constant::ByteArray(bytes) => 4 + bytes.len() as u64,
+        Constant::AddressArray(addrs) => 4 + 32 * addrs.len() as u64,
+        Constant::Vector(elems) => 4 + elems.iter().map(constant_size).sum::<u64>(),
+    }
+}
+
+// =================================================================================================
+// Processor
+
+pub struct GasBoundProcessor {
+    schedule: GasSchedule,
+}
+
+impl GasBoundProcessor {
+    pub fn new(schedule: GasSchedule) -> Box<Self> {
+        Box::new(Self { schedule })
+    }
+
+    /// Checks the bound against `pragma max_gas`, asserting it at the entry of the function if
+    /// it depends on parameters.
+    fn check_max_gas(fun_env: &FunctionEnv, data: FunctionData, bound: &GasBound) -> FunctionData {
+        let env = fun_env.module_env.env;
+        let max_gas = fun_env.get_num_pragma(MAX_GAS_PRAGMA, || 0) as u64;
+        let loc = fun_env.get_loc();
+        if let Some(value) = bound.as_constant() {
+            if value > max_gas {
+                env.error(
+                    &loc,
+                    &format!("gas bound {} exceeds `pragma max_gas = {}`", value, max_gas),
+                );
+            }
+            return data;
+        }
+        if let GasBound::Unbounded(reason) = bound {
+            env.error(
+                &loc,
+                &format!(
+                    "cannot check `pragma max_gas`: the gas of this function is unbounded ({})",
+                    reason
+                ),
+            );
+            return data;
+        }
+        if let Some(symbol) = bound
+            .symbols()
+            .into_iter()
+            .find(|s| !matches!(s, GasSymbol::Param(_) | GasSymbol::ParamLen(_)))
+        {
+            env.error(
+                &loc,
+                &format!(
+                    "cannot check `pragma max_gas`: the gas bound {} depends on `{}`, which is \
+                     not a parameter",
+                    bound.display(fun_env),
+                    GasBound::symbol(symbol.clone()).display(fun_env)
+                ),
+            );
+            return data;
+        }
+        let mut builder = FunctionDataBuilder::new(fun_env, data);
+        let code = std::mem::take(&mut builder.data.code);
+        builder.set_loc_and_vc_info(loc, MAX_GAS_FAILED);
+        let bound_exp = Self::bound_exp(&builder, bound);
+        let exp = builder.mk_bool_call(
+            SpecOperation::Le,
+            vec![bound_exp, builder.mk_num_const(BigInt::from(max_gas))],
+        );
+        builder.emit_with(|attr_id| Bytecode::Prop(attr_id, PropKind::Assert, exp));
+        for bc in code {
+            builder.emit(bc);
+        }
+        builder.data
+    }
+
+    /// Translates a bound over parameters into a specification expression.
+    fn bound_exp(builder: &FunctionDataBuilder, bound: &GasBound) -> Exp {
+        let terms = match bound {
+            GasBound::Bounded(terms) => terms,
+            GasBound::Unbounded(_) => unreachable!("bounded"),
+        };
+        let symbol_exp = |symbol: &GasSymbol| {
+            let temp_exp = |temp: TempIndex| {
+                let ty = builder.get_local_type(temp).skip_reference().clone();
+                ExpData::Temporary(builder.new_node(ty, None), temp).into_exp()
+            };
+            match symbol {
+                GasSymbol::Param(temp) => temp_exp(*temp),
+                GasSymbol::ParamLen(temp) => {
+                    builder.mk_call(&NUM_TYPE, SpecOperation::Len, vec![temp_exp(*temp)])
+                }
+                _ => unreachable!("parameter symbol"),
+            }
+        };
+        terms
+            .iter()
+            .map(|(mono, coeff)| {
+                mono.iter()
+                    .fold(builder.mk_num_const(BigInt::from(*coeff)), |acc, sym| {
+                        builder.mk_call(&NUM_TYPE, SpecOperation::Mul, vec![acc, symbol_exp(sym)])
+                    })
+            })
+            .reduce(|acc, term| builder.mk_call(&NUM_TYPE, SpecOperation::Add, vec![acc, term]))
+            .unwrap_or_else(|| builder.mk_num_const(BigInt::from(0)))
+    }
+}
+
+impl FunctionTargetProcessor for GasBoundProcessor {
+    fn process(
+        &self,
+        targets: &mut FunctionTargetsHolder,
+        fun_env: &FunctionEnv,
+        mut data: FunctionData,
+        scc_opt: Option<&[FunctionEnv]>,
+    ) -> FunctionData {
+        if fun_env.is_native() || fun_env.is_intrinsic() {
+            return data;
+        }
+        let bound = GasBoundAnalysis::new(
+            FunctionTarget::new(fun_env, &data),
+            targets,
+            &self.schedule,
+            scc_opt,
+        )
+        .compute();
+        let options = ProverOptions::get(fun_env.module_env.env);
+        if options.check_max_gas
+            && fun_env.module_env.is_target()
+            && fun_env.is_num_pragma_set(MAX_GAS_PRAGMA)
+        {
+            data = Self::check_max_gas(fun_env, data, &bound);
+        }
+        data.annotations.set(GasBoundAnnotation(bound), true);
+        data
+    }
+
+    fn name(&self) -> String {
+        "gas_bound_analysis".to_string()
+    }
+
+    fn dump_result(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        env: &GlobalEnv,
+        targets: &FunctionTargetsHolder,
+    ) -> fmt::Result {
+        writeln!(
+            f,
+            "\n\n********* Result of gas bound analysis *********\n\n"
+        )?;
+        for module in env.get_modules() {
+            if !module.is_target() {
+                continue;
+            }
+            for fun in module.get_functions() {
+                let target = targets.get_target(&fun, &FunctionVariant::Baseline);
+                if let Some(bound) = get_gas_bound(&target) {
+                    writeln!(
+                        f,
+                        "function {}: {}",
+                        fun.get_full_name_str(),
+                        bound.display(&fun)
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -125,7 +125,9 @@ impl FunctionTargetProcessor for GlobalInvariantInstrumentationProcessor {
         }
         debug_assert!(matches!(
             data.variant,
-            FunctionVariant::Verification(VerificationFlavor::Regular)
+            FunctionVariant::Verification(
+                VerificationFlavor::Regular | VerificationFlavor::Vacuity(_)
+            )
         ));
 
         // retrieve and transpose the analysis result
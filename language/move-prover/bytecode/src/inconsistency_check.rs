@@ -19,8 +19,21 @@
 //! can prove that `spec always_abort { ensures 1 == 2; }`. If this function aborts unconditionally,
 //! any post-condition can be proved. Checking of this behavior is turned-off by default, and can
 //! be enabled with the `unconditional-abort-as-inconsistency` flag.
+//!
+//! The `SpecCoverageInstrumenter` uses the same scheme to check the coverage of specifications.
+//! It finishes the `ensures` vacuity variants created by the spec instrumenter, and adds a variant
+//! with an `assert false` before the `abort` of functions which have no `aborts_if`. For functions
+//! with `pragma aborts_if_is_partial`, the variant instead keeps the assertion of the `aborts_if`
+//! disjunction the spec instrumenter places before the `abort`, which is removed from the regular
+//! variant. In all of them, other assertions are turned into assumptions, so only the coverage
+//! check is reported.
+
+use std::collections::BTreeSet;
 
-use move_model::{exp_generator::ExpGenerator, model::FunctionEnv};
+use move_model::{
+    ast::ConditionKind, exp_generator::ExpGenerator, model::FunctionEnv,
+    pragmas::ABORTS_IF_IS_PARTIAL_PRAGMA,
+};
 
 use crate::{
     function_data_builder::FunctionDataBuilder,
@@ -29,12 +42,17 @@ use crate::{
         FunctionTargetProcessor, FunctionTargetsHolder, FunctionVariant, VerificationFlavor,
     },
     options::ProverOptions,
-    stackless_bytecode::{Bytecode, PropKind},
+    spec_instrumentation::VACUITY_CHECK_MESSAGE,
+    stackless_bytecode::{AttrId, Bytecode, PropKind},
 };
 
 // This message is for the boogie wrapper, and not shown to the users.
 const EXPECTED_TO_FAIL: &str = "expected to fail";
 
+/// The message of the check for aborts not covered by an `aborts_if`. The boogie wrapper
+/// recognizes it to report the abort as a coverage finding instead of an error.
+pub const UNCOVERED_ABORT_MESSAGE: &str = "abort not covered by a specification";
+
 pub struct InconsistencyCheckInstrumenter {}
 
 impl InconsistencyCheckInstrumenter {
@@ -57,8 +75,13 @@ impl FunctionTargetProcessor for InconsistencyCheckInstrumenter {
         }
         let flavor = match &data.variant {
             FunctionVariant::Baseline
-            | FunctionVariant::Verification(VerificationFlavor::Inconsistency(..)) => {
-                // instrumentation only applies to non-inconsistency verification variants
+            | FunctionVariant::Verification(
+                VerificationFlavor::Inconsistency(..)
+                | VerificationFlavor::Vacuity(..)
+                | VerificationFlavor::UncoveredAbort,
+            ) => {
+                // instrumentation only applies to non-inconsistency, non-coverage verification
+                // variants
                 return data;
             }
             FunctionVariant::Verification(flavor) => flavor.clone(),
@@ -104,3 +127,101 @@ impl FunctionTargetProcessor for InconsistencyCheckInstrumenter {
         "inconsistency_check_instrumenter".to_string()
     }
 }
+
+pub struct SpecCoverageInstrumenter {}
+
+impl SpecCoverageInstrumenter {
+    pub fn new() -> Box<Self> {
+        Box::new(Self {})
+    }
+}
+
+impl FunctionTargetProcessor for SpecCoverageInstrumenter {
+    fn process(
+        &self,
+        targets: &mut FunctionTargetsHolder,
+        fun_env: &FunctionEnv,
+        data: FunctionData,
+        _scc_opt: Option<&[FunctionEnv]>,
+    ) -> FunctionData {
+        if fun_env.is_native() || fun_env.is_intrinsic() {
+            // Nothing to do.
+            return data;
+        }
+        match &data.variant {
+            FunctionVariant::Verification(VerificationFlavor::Vacuity(..)) => {
+                // only the vacuity check itself remains an assertion
+                let mut data = data;
+                let vc_infos = &data.vc_infos;
+                for bc in &mut data.code {
+                    if let Bytecode::Prop(id, kind @ PropKind::Assert, _) = bc {
+                        if vc_infos.get(id).map(String::as_str) != Some(VACUITY_CHECK_MESSAGE) {
+                            *kind = PropKind::Assume;
+                        }
+                    }
+                }
+                data
+            }
+            FunctionVariant::Verification(VerificationFlavor::Regular) => {
+                let spec = fun_env.get_spec();
+                let is_partial = fun_env.is_pragma_true(ABORTS_IF_IS_PARTIAL_PRAGMA, || false);
+                if spec.filter_kind(ConditionKind::AbortsIf).next().is_some() && !is_partial {
+                    // aborts are already checked against the `aborts_if`
+                    return data;
+                }
+
+                // the spec instrumenter asserts the `aborts_if` of partial functions before the
+                // abort; these assertions are only checked in the coverage variant
+                let coverage_checks: BTreeSet<AttrId> = data
+                    .vc_infos
+                    .iter()
+                    .filter(|(_, info)| info.as_str() == UNCOVERED_ABORT_MESSAGE)
+                    .map(|(id, _)| *id)
+                    .collect();
+
+                let new_data = data.fork(FunctionVariant::Verification(
+                    VerificationFlavor::UncoveredAbort,
+                ));
+                let mut builder = FunctionDataBuilder::new(fun_env, new_data);
+                let old_code = std::mem::take(&mut builder.data.code);
+                for bc in old_code {
+                    match bc {
+                        Bytecode::Prop(id, PropKind::Assert, exp)
+                            if !coverage_checks.contains(&id) =>
+                        {
+                            builder.emit(Bytecode::Prop(id, PropKind::Assume, exp));
+                        }
+                        Bytecode::Abort(..) if !is_partial => {
+                            let loc = builder.fun_env.get_loc();
+                            builder.set_loc_and_vc_info(loc, UNCOVERED_ABORT_MESSAGE);
+                            let exp = builder.mk_bool_const(false);
+                            builder.emit_with(|id| Bytecode::Prop(id, PropKind::Assert, exp));
+                            builder.emit(bc);
+                        }
+                        _ => builder.emit(bc),
+                    }
+                }
+
+                let new_data = builder.data;
+                targets.insert_target_data(
+                    &fun_env.get_qualified_id(),
+                    new_data.variant.clone(),
+                    new_data,
+                );
+
+                // the regular variant does not check the coverage
+                let mut data = data;
+                data.code.retain(|bc| {
+                    !matches!(bc, Bytecode::Prop(id, PropKind::Assert, _)
+                        if coverage_checks.contains(id))
+                });
+                data
+            }
+            _ => data,
+        }
+    }
+
+    fn name(&self) -> String {
+        "spec_coverage_instrumenter".to_string()
+    }
+}
@@ -23,6 +23,7 @@ pub mod escape_analysis;
 pub mod function_data_builder;
 pub mod function_target;
 pub mod function_target_pipeline;
+pub mod gas_bound_analysis;
 pub mod global_invariant_analysis;
 pub mod global_invariant_instrumentation;
 pub mod global_invariant_instrumentation_v2;
@@ -30,6 +31,7 @@ pub mod graph;
 pub mod inconsistency_check;
 pub mod livevar_analysis;
 pub mod loop_analysis;
+pub mod loop_invariant_inference;
 pub mod memory_instrumentation;
 pub mod mono_analysis;
 pub mod mut_ref_instrumentation;
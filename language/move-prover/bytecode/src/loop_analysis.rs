@@ -4,19 +4,22 @@
 
 use std::collections::{BTreeMap, BTreeSet};
 
+use codespan_reporting::diagnostic::Severity;
 use move_binary_format::file_format::CodeOffset;
 use move_model::{
     ast::{self, TempIndex},
     exp_generator::ExpGenerator,
     model::FunctionEnv,
+    spec_translator::TranslatedSpec,
     ty::{PrimitiveType, Type},
 };
 
 use crate::{
     function_data_builder::{FunctionDataBuilder, FunctionDataBuilderOptions},
     function_target::{FunctionData, FunctionTarget},
-    function_target_pipeline::{FunctionTargetProcessor, FunctionTargetsHolder},
+    function_target_pipeline::{FunctionTargetProcessor, FunctionTargetsHolder, FunctionVariant},
     graph::{Graph, NaturalLoop},
+    loop_invariant_inference::{InferredInvariant, LoopInvariantInference},
     options::ProverOptions,
     stackless_bytecode::{AttrId, Bytecode, HavocKind, Label, Operation, PropKind},
     stackless_control_flow_graph::{BlockContent, BlockId, StacklessControlFlowGraph},
@@ -24,6 +27,11 @@ use crate::{
 
 const LOOP_INVARIANT_BASE_FAILED: &str = "base case of the loop invariant does not hold";
 const LOOP_INVARIANT_INDUCTION_FAILED: &str = "induction case of the loop invariant does not hold";
+const LOOP_MEASURE_FAILED: &str = "loop measure does not decrease";
+const INFERRED_LOOP_INVARIANT_BASE_FAILED: &str =
+    "base case of the inferred loop invariant does not hold";
+const INFERRED_LOOP_INVARIANT_INDUCTION_FAILED: &str =
+    "induction case of the inferred loop invariant does not hold";
 
 /// A fat-loop captures the information of one or more natural loops that share the same loop
 /// header. This shared header is called the header of the fat-loop.
@@ -37,6 +45,8 @@ const LOOP_INVARIANT_INDUCTION_FAILED: &str = "induction case of the loop invari
 #[derive(Debug, Clone)]
 pub struct FatLoop {
     pub invariants: BTreeMap<CodeOffset, (AttrId, ast::Exp)>,
+    pub measures: BTreeMap<CodeOffset, (AttrId, ast::Exp)>,
+    pub inferred: Vec<InferredInvariant>,
     pub val_targets: BTreeSet<TempIndex>,
     pub mut_targets: BTreeMap<TempIndex, bool>,
     pub back_edges: BTreeSet<CodeOffset>,
@@ -59,7 +69,7 @@ impl LoopAnnotation {
     fn invariants_locations(&self) -> BTreeSet<CodeOffset> {
         self.fat_loops
             .values()
-            .flat_map(|l| l.invariants.keys())
+            .flat_map(|l| l.invariants.keys().chain(l.measures.keys()))
             .copied()
             .collect()
     }
@@ -76,7 +86,7 @@ impl LoopAnalysisProcessor {
 impl FunctionTargetProcessor for LoopAnalysisProcessor {
     fn process(
         &self,
-        _targets: &mut FunctionTargetsHolder,
+        targets: &mut FunctionTargetsHolder,
         func_env: &FunctionEnv,
         data: FunctionData,
         _scc_opt: Option<&[FunctionEnv]>,
@@ -84,7 +94,7 @@ impl FunctionTargetProcessor for LoopAnalysisProcessor {
         if func_env.is_native() {
             return data;
         }
-        let loop_annotation = Self::build_loop_annotation(func_env, &data);
+        let loop_annotation = Self::build_loop_annotation(targets, func_env, &data);
         Self::transform(func_env, data, &loop_annotation)
     }
 
@@ -104,8 +114,10 @@ impl LoopAnalysisProcessor {
     ///     - assert L;
     ///     - havoc T;
     ///     - assume L;
+    ///     - let M0 = M; (if the loop has a `decreases` measure M)
     /// - Create a new dummy block (say, block X) with only the following statements
     ///     - assert L;
+    ///     - assert 0 <= M0 && M < M0;
     ///     - stop;
     /// - For each backedge in this loop:
     ///     - In the source block of the back edge, replace the last statement (must be a jump or
@@ -127,6 +139,8 @@ impl LoopAnalysisProcessor {
             },
         );
         let mut goto_fixes = vec![];
+        let mut saved_measures = BTreeMap::new();
+        let mut header_locs = BTreeMap::new();
         let code = std::mem::take(&mut builder.data.code);
         for (offset, bytecode) in code.into_iter().enumerate() {
             match bytecode {
@@ -145,6 +159,20 @@ impl LoopAnalysisProcessor {
                             });
                         }
 
+                        // assert inferred loop invariants
+                        let header_loc = builder.get_loc(attr_id);
+                        header_locs.insert(label, header_loc.clone());
+                        for inv in &loop_info.inferred {
+                            builder.set_loc_and_vc_info(
+                                header_loc.clone(),
+                                INFERRED_LOOP_INVARIANT_BASE_FAILED,
+                            );
+                            let exp = inv.to_exp(&builder);
+                            builder.emit_with(|attr_id| {
+                                Bytecode::Prop(attr_id, PropKind::Assert, exp)
+                            });
+                        }
+
                         // havoc all loop targets
                         for idx in &loop_info.val_targets {
                             builder.emit_with(|attr_id| {
@@ -263,6 +291,24 @@ impl LoopAnalysisProcessor {
                         for (attr_id, exp) in loop_info.invariants.values() {
                             builder.emit(Bytecode::Prop(*attr_id, PropKind::Assume, exp.clone()));
                         }
+                        for inv in &loop_info.inferred {
+                            builder.set_next_debug_comment(format!(
+                                "inferred loop invariant: {}",
+                                inv.display(&builder.get_target())
+                            ));
+                            let exp = inv.to_exp(&builder);
+                            builder.emit_with(|attr_id| {
+                                Bytecode::Prop(attr_id, PropKind::Assume, exp)
+                            });
+                        }
+
+                        // save the loop measures for the check that they decrease
+                        let saved = loop_info
+                            .measures
+                            .values()
+                            .map(|(_, exp)| builder.emit_let(exp.clone()).1)
+                            .collect::<Vec<_>>();
+                        saved_measures.insert(label, saved);
                     }
                 }
                 Bytecode::Prop(_, PropKind::Assert, _)
@@ -306,6 +352,33 @@ impl LoopAnalysisProcessor {
                 builder.emit_with(|attr_id| Bytecode::Prop(attr_id, PropKind::Assert, exp.clone()));
             }
 
+            for inv in &loop_info.inferred {
+                builder.set_loc_and_vc_info(
+                    header_locs[label].clone(),
+                    INFERRED_LOOP_INVARIANT_INDUCTION_FAILED,
+                );
+                let exp = inv.to_exp(&builder);
+                builder.emit_with(|attr_id| Bytecode::Prop(attr_id, PropKind::Assert, exp));
+            }
+
+            // add instrumentations to assert that the loop measures decrease
+            let measures = loop_info
+                .measures
+                .values()
+                .map(|(_, exp)| exp.clone())
+                .collect::<Vec<_>>();
+            if let Some(cond) = TranslatedSpec::decreases_condition(
+                &builder,
+                saved_measures
+                    .get(label)
+                    .map_or(&[][..], |saved| &saved[..]),
+                &measures,
+            ) {
+                let (attr_id, _) = loop_info.measures.values().next().unwrap();
+                builder.set_loc_and_vc_info(builder.get_loc(*attr_id), LOOP_MEASURE_FAILED);
+                builder.emit_with(|attr_id| Bytecode::Prop(attr_id, PropKind::Assert, cond));
+            }
+
             // stop the checking in proving mode (branch back to loop header for interpretation mode)
             builder.emit_with(|attr_id| {
                 if options.for_interpretation {
@@ -336,17 +409,19 @@ impl LoopAnalysisProcessor {
 
         // we have unrolled the loop into a DAG, and there will be no loop invariants left
         builder.data.loop_invariants.clear();
+        builder.data.loop_measures.clear();
         builder.data
     }
 
-    /// Collect invariants in the given loop header block
+    /// Collect invariants and measures in the given loop header block
     ///
     /// Loop invariants are defined as
     /// 1) the longest sequence of consecutive
     /// 2) `PropKind::Assert` propositions
     /// 3) in the loop header block, immediately after the `Label` statement,
     /// 4) which are also marked in the `loop_invariants` field in the `FunctionData`.
-    /// All above conditions must be met to be qualified as a loop invariant.
+    /// All above conditions must be met to be qualified as a loop invariant. Loop measures are
+    /// the propositions in the same sequence which are marked in the `loop_measures` field.
     ///
     /// The reason we piggyback on `PropKind::Assert` instead of introducing a new
     /// `PropKind::Invariant` is that we don't want to introduce a`PropKind::Invariant` type which
@@ -356,17 +431,24 @@ impl LoopAnalysisProcessor {
     /// In other words, for the loop header block:
     /// - the first statement must be a `label`,
     /// - followed by N `assert` statements, N >= 0
-    /// - all these N `assert` statements are marked as loop invariants,
-    /// - statement N + 1 is either not an `assert` or is not marked in `loop_invariants`.
+    /// - all these N `assert` statements are marked as loop invariants or measures,
+    /// - statement N + 1 is either not an `assert` or is not marked in `loop_invariants` or
+    ///   `loop_measures`.
+    #[allow(clippy::type_complexity)]
     fn collect_loop_invariants(
         cfg: &StacklessControlFlowGraph,
         func_target: &FunctionTarget<'_>,
         loop_header: BlockId,
-    ) -> BTreeMap<CodeOffset, (AttrId, ast::Exp)> {
+    ) -> (
+        BTreeMap<CodeOffset, (AttrId, ast::Exp)>,
+        BTreeMap<CodeOffset, (AttrId, ast::Exp)>,
+    ) {
         let code = func_target.get_bytecode();
         let asserts_as_invariants = &func_target.data.loop_invariants;
+        let asserts_as_measures = &func_target.data.loop_measures;
 
         let mut invariants = BTreeMap::new();
+        let mut measures = BTreeMap::new();
         for (index, code_offset) in cfg.instr_indexes(loop_header).unwrap().enumerate() {
             let bytecode = &code[code_offset as usize];
             if index == 0 {
@@ -378,11 +460,16 @@ impl LoopAnalysisProcessor {
                     {
                         invariants.insert(code_offset, (*attr_id, exp.clone()));
                     }
+                    Bytecode::Prop(attr_id, PropKind::Assert, exp)
+                        if asserts_as_measures.contains(attr_id) =>
+                    {
+                        measures.insert(code_offset, (*attr_id, exp.clone()));
+                    }
                     _ => break,
                 }
             }
         }
-        invariants
+        (invariants, measures)
     }
 
     /// Collect variables that may be changed during the loop execution.
@@ -457,7 +544,14 @@ impl LoopAnalysisProcessor {
 
     /// Find all loops in the function and collect information needed for invariant instrumentation
     /// and loop-to-DAG transformation.
-    fn build_loop_annotation(func_env: &FunctionEnv<'_>, data: &FunctionData) -> LoopAnnotation {
+    fn build_loop_annotation(
+        targets: &FunctionTargetsHolder,
+        func_env: &FunctionEnv<'_>,
+        data: &FunctionData,
+    ) -> LoopAnnotation {
+        let options = ProverOptions::get(func_env.module_env.env);
+        let infer_invariants = options.infer_loop_invariants && !options.for_interpretation;
+
         // build for natural loops
         let func_target = FunctionTarget::new(func_env, data);
         let code = func_target.get_bytecode();
@@ -487,6 +581,18 @@ impl LoopAnalysisProcessor {
                 .push(single_loop);
         }
 
+        let fat_bodies: BTreeMap<_, BTreeSet<_>> = fat_headers
+            .iter()
+            .map(|(header, sub_loops)| {
+                let body = sub_loops
+                    .iter()
+                    .flat_map(|l| l.loop_body.iter())
+                    .copied()
+                    .collect();
+                (*header, body)
+            })
+            .collect();
+
         // build fat loops by label
         let mut fat_loops = BTreeMap::new();
         for (fat_root, sub_loops) in fat_headers {
@@ -499,16 +605,39 @@ impl LoopAnalysisProcessor {
                 },
             };
 
-            let invariants = Self::collect_loop_invariants(&cfg, &func_target, fat_root);
+            let (invariants, measures) =
+                Self::collect_loop_invariants(&cfg, &func_target, fat_root);
             let (val_targets, mut_targets) =
                 Self::collect_loop_targets(&cfg, &func_target, &sub_loops);
             let back_edges = Self::collect_loop_back_edges(code, &cfg, label, &sub_loops);
+            let inferred = if infer_invariants {
+                let body = fat_bodies[&fat_root].clone();
+                let nested = fat_bodies
+                    .iter()
+                    .filter(|(header, _)| **header != fat_root && body.contains(header))
+                    .flat_map(|(_, nested_body)| nested_body.iter().copied())
+                    .collect();
+                LoopInvariantInference::new(
+                    targets,
+                    &func_target,
+                    &cfg,
+                    fat_root,
+                    body,
+                    nested,
+                    val_targets.clone(),
+                )
+                .infer()
+            } else {
+                vec![]
+            };
 
             // done with all information collection.
             fat_loops.insert(
                 label,
                 FatLoop {
                     invariants,
+                    measures,
+                    inferred,
                     val_targets,
                     mut_targets,
                     back_edges,
@@ -531,6 +660,54 @@ impl LoopAnalysisProcessor {
             );
         }
 
+        let all_measures: BTreeSet<_> = fat_loops
+            .values()
+            .flat_map(|l| l.measures.values().map(|(attr_id, _)| *attr_id))
+            .collect();
+        for attr_id in data.loop_measures.difference(&all_measures) {
+            env.error(
+                &func_target.get_bytecode_loc(*attr_id),
+                "Loop measures must be declared at the beginning of the loop header, together \
+                with the loop invariants",
+            );
+        }
+
+        if options.suggest_loop_invariants && data.variant == FunctionVariant::Baseline {
+            Self::suggest_loop_invariants(&func_target, code, &fat_loops);
+        }
+
         LoopAnnotation { fat_loops }
     }
+
+    /// Report the inferred invariants which can be expressed in the source, so they can be
+    /// added to the loops.
+    fn suggest_loop_invariants(
+        func_target: &FunctionTarget<'_>,
+        code: &[Bytecode],
+        fat_loops: &BTreeMap<Label, FatLoop>,
+    ) {
+        let label_offsets = Bytecode::label_offsets(code);
+        for (label, loop_info) in fat_loops {
+            let notes = loop_info
+                .inferred
+                .iter()
+                .filter(|inv| {
+                    inv.temporaries()
+                        .into_iter()
+                        .all(|idx| !func_target.is_temporary(idx))
+                })
+                .map(|inv| format!("invariant {};", inv.display(func_target)))
+                .collect::<Vec<_>>();
+            if notes.is_empty() {
+                continue;
+            }
+            let attr_id = code[label_offsets[label] as usize].get_attr_id();
+            func_target.global_env().diag_with_notes(
+                Severity::Note,
+                &func_target.get_bytecode_loc(attr_id),
+                "inferred loop invariants",
+                notes,
+            );
+        }
+    }
 }
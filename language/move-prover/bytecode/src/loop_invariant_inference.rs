@@ -0,0 +1,721 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Inference of simple loop invariants.
+//!
+//! Without invariants, the loop analysis havocs every variable a loop modifies, which often
+//! loses the facts needed to verify the code after the loop. This module recognizes a few
+//! common loop shapes and infers invariants for them, which are sound by construction:
+//!
+//! - a counter which is only increased (decreased) in the loop is bounded below (above) by its
+//!   value on loop entry;
+//! - an index which is increased by one in the loop while it is below a bound which does not
+//!   change in the loop (a variable, a constant, the length of a vector, or a field of a global
+//!   resource which is not modified in the loop) is bounded by it;
+//! - a vector which starts empty and is pushed to whenever an index is increased has a length
+//!   which follows the index;
+//! - a variable which is assigned the same constant on loop entry and in the loop keeps it.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use itertools::Itertools;
+use move_binary_format::file_format::CodeOffset;
+use move_model::{
+    ast::{self, Exp, TempIndex},
+    exp_generator::ExpGenerator,
+    model::{FunId, QualifiedId, QualifiedInstId, StructId},
+    ty::{PrimitiveType, Type},
+    well_known::{VECTOR_EMPTY, VECTOR_LENGTH, VECTOR_PUSH_BACK},
+};
+use num::BigInt;
+
+use crate::{
+    function_target::FunctionTarget,
+    function_target_pipeline::{FunctionTargetsHolder, FunctionVariant},
+    stackless_bytecode::{BorrowNode, Bytecode, Constant, Operation},
+    stackless_control_flow_graph::{BlockId, StacklessControlFlowGraph},
+    usage_analysis::UsageState,
+};
+
+/// An invariant inferred for a loop.
+#[derive(Debug, Clone)]
+pub enum InferredInvariant {
+    /// `var >= bound`, for a counter which is only increased in the loop.
+    LowerBound(TempIndex, BigInt),
+    /// `var <= bound`, for a counter which is only decreased in the loop.
+    UpperBound(TempIndex, BigInt),
+    /// `init <= bound ==> var <= bound`, for an index which is increased by one while it is
+    /// below the bound.
+    IndexBound {
+        var: TempIndex,
+        init: BigInt,
+        bound: LoopBound,
+    },
+    /// `len(vec) + init == var`, for a vector which is pushed to whenever the index is
+    /// increased by one.
+    VectorLength {
+        vec: TempIndex,
+        var: TempIndex,
+        init: BigInt,
+    },
+    /// `var == value`, for a variable which is always assigned the same constant.
+    Unchanged(TempIndex, Constant),
+}
+
+/// A bound of a loop index which does not change during the loop.
+#[derive(Debug, Clone)]
+pub enum LoopBound {
+    Temp(TempIndex),
+    Const(BigInt),
+    Length(TempIndex),
+    GlobalField(QualifiedInstId<StructId>, TempIndex, usize),
+}
+
+impl InferredInvariant {
+    /// Translates the invariant into a specification expression.
+    pub fn to_exp<'env, G: ExpGenerator<'env>>(&self, gen: &G) -> Exp {
+        use InferredInvariant::*;
+        let num = |v: &BigInt| gen.mk_num_const(v.clone());
+        let le = |a: Exp, b: Exp| gen.mk_bool_call(ast::Operation::Le, vec![a, b]);
+        match self {
+            LowerBound(var, bound) => {
+                gen.mk_bool_call(ast::Operation::Ge, vec![gen.mk_temporary(*var), num(bound)])
+            }
+            UpperBound(var, bound) => le(gen.mk_temporary(*var), num(bound)),
+            IndexBound { var, init, bound } => {
+                let bound = bound.to_exp(gen);
+                let holds = le(gen.mk_temporary(*var), bound.clone());
+                if init == &BigInt::from(0) {
+                    holds
+                } else {
+                    gen.mk_implies(le(num(init), bound), holds)
+                }
+            }
+            VectorLength { vec, var, init } => {
+                let mut len = mk_len(gen, *vec);
+                if init != &BigInt::from(0) {
+                    len = gen.mk_call(&num_type(), ast::Operation::Add, vec![len, num(init)]);
+                }
+                gen.mk_eq(len, gen.mk_temporary(*var))
+            }
+            Unchanged(var, value) => {
+                let value = match value {
+                    Constant::Bool(b) => gen.mk_bool_const(*b),
+                    _ => num(&constant_value(value).expect("numeric constant")),
+                };
+                gen.mk_eq(gen.mk_temporary(*var), value)
+            }
+        }
+    }
+
+    /// Returns the temporaries the invariant refers to.
+    pub fn temporaries(&self) -> Vec<TempIndex> {
+        use InferredInvariant::*;
+        match self {
+            LowerBound(var, _) | UpperBound(var, _) | Unchanged(var, _) => vec![*var],
+            IndexBound { var, bound, .. } => match bound {
+                LoopBound::Temp(temp)
+                | LoopBound::Length(temp)
+                | LoopBound::GlobalField(_, temp, _) => vec![*var, *temp],
+                LoopBound::Const(_) => vec![*var],
+            },
+            VectorLength { vec, var, .. } => vec![*vec, *var],
+        }
+    }
+
+    /// Displays the invariant in the syntax of the specification language.
+    pub fn display(&self, func_target: &FunctionTarget<'_>) -> String {
+        use InferredInvariant::*;
+        let name = |idx: &TempIndex| {
+            func_target
+                .get_local_name(*idx)
+                .display(func_target.symbol_pool())
+                .to_string()
+        };
+        match self {
+            LowerBound(var, bound) => format!("{} >= {}", name(var), bound),
+            UpperBound(var, bound) => format!("{} <= {}", name(var), bound),
+            IndexBound { var, init, bound } => {
+                let bound = bound.display(func_target);
+                if init == &BigInt::from(0) {
+                    format!("{} <= {}", name(var), bound)
+                } else {
+                    format!("{} <= {} ==> {} <= {}", init, bound, name(var), bound)
+                }
+            }
+            VectorLength { vec, var, init } => {
+                if init == &BigInt::from(0) {
+                    format!("len({}) == {}", name(vec), name(var))
+                } else {
+                    format!("len({}) + {} == {}", name(vec), init, name(var))
+                }
+            }
+            Unchanged(var, value) => format!("{} == {}", name(var), value),
+        }
+    }
+}
+
+impl LoopBound {
+    fn to_exp<'env, G: ExpGenerator<'env>>(&self, gen: &G) -> Exp {
+        match self {
+            LoopBound::Temp(temp) => gen.mk_temporary(*temp),
+            LoopBound::Const(value) => gen.mk_num_const(value.clone()),
+            LoopBound::Length(vec) => mk_len(gen, *vec),
+            LoopBound::GlobalField(mem, addr, offset) => {
+                let env = gen.global_env();
+                let struct_env = env.get_struct_qid(mem.to_qualified_id());
+                let mem_ty = mem.to_type();
+                let resource = gen.mk_call_with_inst(
+                    &mem_ty,
+                    vec![mem_ty.clone()],
+                    ast::Operation::Global(None),
+                    vec![gen.mk_temporary(*addr)],
+                );
+                gen.mk_field_select(
+                    &struct_env.get_field_by_offset(*offset),
+                    &mem.inst,
+                    resource,
+                )
+            }
+        }
+    }
+
+    fn display(&self, func_target: &FunctionTarget<'_>) -> String {
+        let name = |idx: &TempIndex| {
+            func_target
+                .get_local_name(*idx)
+                .display(func_target.symbol_pool())
+                .to_string()
+        };
+        match self {
+            LoopBound::Temp(temp) => name(temp),
+            LoopBound::Const(value) => value.to_string(),
+            LoopBound::Length(vec) => format!("len({})", name(vec)),
+            LoopBound::GlobalField(mem, addr, offset) => {
+                let env = func_target.global_env();
+                let struct_env = env.get_struct_qid(mem.to_qualified_id());
+                let field_env = struct_env.get_field_by_offset(*offset);
+                let tctx = func_target.func_env.get_type_display_ctx();
+                // Resources of the function's own module are referred to by their simple name.
+                let resource = if mem.module_id == func_target.module_env().get_id() {
+                    let mut resource = struct_env.get_name().display(env.symbol_pool()).to_string();
+                    if !mem.inst.is_empty() {
+                        resource = format!(
+                            "{}<{}>",
+                            resource,
+                            mem.inst.iter().map(|ty| ty.display(&tctx)).join(", ")
+                        );
+                    }
+                    resource
+                } else {
+                    mem.to_type().display(&tctx).to_string()
+                };
+                format!(
+                    "global<{}>({}).{}",
+                    resource,
+                    name(addr),
+                    field_env.get_name().display(env.symbol_pool())
+                )
+            }
+        }
+    }
+}
+
+fn num_type() -> Type {
+    Type::Primitive(PrimitiveType::Num)
+}
+
+fn mk_len<'env, G: ExpGenerator<'env>>(gen: &G, vec: TempIndex) -> Exp {
+    gen.mk_call(
+        &num_type(),
+        ast::Operation::Len,
+        vec![gen.mk_temporary(vec)],
+    )
+}
+
+fn constant_value(value: &Constant) -> Option<BigInt> {
+    match value {
+        Constant::U8(v) => Some(BigInt::from(*v)),
+        Constant::U16(v) => Some(BigInt::from(*v)),
+        Constant::U32(v) => Some(BigInt::from(*v)),
+        Constant::U64(v) => Some(BigInt::from(*v)),
+        Constant::U128(v) => Some(BigInt::from(*v)),
+        Constant::U256(v) => v.to_string().parse().ok(),
+        _ => None,
+    }
+}
+
+/// How a counter is modified in a loop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    /// `var := var + k`, with `k` if it is a constant.
+    Increase(Option<BigInt>),
+    /// `var := var - k`, with `k` if it is a constant.
+    Decrease(Option<BigInt>),
+}
+
+/// The context for inferring the invariants of one fat loop.
+pub struct LoopInvariantInference<'a> {
+    targets: &'a FunctionTargetsHolder,
+    func_target: &'a FunctionTarget<'a>,
+    cfg: &'a StacklessControlFlowGraph,
+    header: BlockId,
+    /// The blocks of the loop, including the header.
+    body: BTreeSet<BlockId>,
+    /// The blocks of loops nested in this loop, which may execute more than once per iteration.
+    nested: BTreeSet<BlockId>,
+    /// The variables modified in the loop.
+    loop_targets: BTreeSet<TempIndex>,
+    predecessors: BTreeMap<BlockId, Vec<BlockId>>,
+    block_of: BTreeMap<CodeOffset, BlockId>,
+}
+
+impl<'a> LoopInvariantInference<'a> {
+    pub fn new(
+        targets: &'a FunctionTargetsHolder,
+        func_target: &'a FunctionTarget<'a>,
+        cfg: &'a StacklessControlFlowGraph,
+        header: BlockId,
+        body: BTreeSet<BlockId>,
+        nested: BTreeSet<BlockId>,
+        loop_targets: BTreeSet<TempIndex>,
+    ) -> Self {
+        let mut predecessors: BTreeMap<BlockId, Vec<BlockId>> = BTreeMap::new();
+        let mut block_of = BTreeMap::new();
+        for block_id in cfg.blocks() {
+            for succ in cfg.successors(block_id) {
+                predecessors.entry(*succ).or_default().push(block_id);
+            }
+            if let Some(offsets) = cfg.instr_indexes(block_id) {
+                for offset in offsets {
+                    block_of.insert(offset, block_id);
+                }
+            }
+        }
+        Self {
+            targets,
+            func_target,
+            cfg,
+            header,
+            body,
+            nested,
+            loop_targets,
+            predecessors,
+            block_of,
+        }
+    }
+
+    /// Infers the invariants of the loop.
+    pub fn infer(&self) -> Vec<InferredInvariant> {
+        let mut result = vec![];
+        for var in &self.loop_targets {
+            let var = *var;
+            if self.func_target.get_local_type(var).is_reference() {
+                continue;
+            }
+            let defs = self.loop_defs(var);
+            let entry = self.entry_constant(var);
+            // A variable which is always assigned the same constant.
+            if let Some(value) = &entry {
+                if !defs.is_empty()
+                    && defs
+                        .iter()
+                        .all(|offset| self.constant_at(*offset).as_ref() == Some(value))
+                {
+                    result.push(InferredInvariant::Unchanged(var, value.clone()));
+                    continue;
+                }
+            }
+            let init = match entry.as_ref().and_then(constant_value) {
+                Some(init) => init,
+                None => continue,
+            };
+            let steps = match defs
+                .iter()
+                .map(|offset| self.step_at(var, *offset))
+                .collect::<Option<Vec<_>>>()
+            {
+                Some(steps) if !steps.is_empty() => steps,
+                _ => continue,
+            };
+            if steps.iter().all(|s| matches!(s, Step::Decrease(_))) {
+                result.push(InferredInvariant::UpperBound(var, init));
+                continue;
+            }
+            if !steps.iter().all(|s| matches!(s, Step::Increase(_))) {
+                continue;
+            }
+            if init > BigInt::from(0) {
+                result.push(InferredInvariant::LowerBound(var, init.clone()));
+            }
+            // The remaining invariants need an index which is increased by one exactly once
+            // per iteration.
+            if steps != [Step::Increase(Some(BigInt::from(1)))] {
+                continue;
+            }
+            let inc_block = self.block_of[&defs[0]];
+            if inc_block == self.header || self.nested.contains(&inc_block) {
+                continue;
+            }
+            if let Some(bound) = self.index_bound(var) {
+                result.push(InferredInvariant::IndexBound {
+                    var,
+                    init: init.clone(),
+                    bound,
+                });
+            }
+            for vec in &self.loop_targets {
+                if self.is_pushed_with(*vec, inc_block) {
+                    result.push(InferredInvariant::VectorLength {
+                        vec: *vec,
+                        var,
+                        init: init.clone(),
+                    });
+                }
+            }
+        }
+        result
+    }
+
+    fn code(&self) -> &'a [Bytecode] {
+        self.func_target.get_bytecode()
+    }
+
+    fn block_offsets(&self, block_id: BlockId) -> Vec<CodeOffset> {
+        self.cfg
+            .instr_indexes(block_id)
+            .map(|offsets| offsets.collect())
+            .unwrap_or_default()
+    }
+
+    fn body_offsets(&self) -> Vec<CodeOffset> {
+        self.body
+            .iter()
+            .flat_map(|block_id| self.block_offsets(*block_id))
+            .collect()
+    }
+
+    fn defines(&self, offset: CodeOffset, var: TempIndex) -> bool {
+        let (val_targets, mut_targets) = self.code()[offset as usize].modifies(self.func_target);
+        val_targets.contains(&var) || mut_targets.iter().any(|(idx, _)| *idx == var)
+    }
+
+    /// Returns the offsets in the loop which define the variable.
+    fn loop_defs(&self, var: TempIndex) -> Vec<CodeOffset> {
+        self.body_offsets()
+            .into_iter()
+            .filter(|offset| self.defines(*offset, var))
+            .collect()
+    }
+
+    /// Returns the last definition of the variable before the offset in its block.
+    fn def_before(&self, var: TempIndex, offset: CodeOffset) -> Option<&'a Bytecode> {
+        let block_id = self.block_of[&offset];
+        self.block_offsets(block_id)
+            .into_iter()
+            .take_while(|o| *o < offset)
+            .filter(|o| self.defines(*o, var))
+            .last()
+            .map(|o| &self.code()[o as usize])
+    }
+
+    /// Returns the bytecode which computes the value defined at the offset, looking through
+    /// an assignment from a temporary defined in the same block.
+    fn value_def_at(&self, offset: CodeOffset) -> Option<&'a Bytecode> {
+        match &self.code()[offset as usize] {
+            Bytecode::Assign(_, _, src, _) => self.def_before(*src, offset),
+            bc => Some(bc),
+        }
+    }
+
+    fn constant_at(&self, offset: CodeOffset) -> Option<Constant> {
+        match self.value_def_at(offset)? {
+            Bytecode::Load(_, _, value) => Some(value.clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns the definitions of the variable which may reach the loop on entry, or `None`
+    /// if the variable may hold a value not defined by an instruction, e.g. a parameter.
+    fn entry_defs(&self, var: TempIndex) -> Option<Vec<CodeOffset>> {
+        let mut defs = vec![];
+        let mut visited = BTreeSet::new();
+        let mut todo = self.predecessors_of(self.header);
+        while let Some(block_id) = todo.pop() {
+            if self.body.contains(&block_id) || !visited.insert(block_id) {
+                continue;
+            }
+            match self
+                .block_offsets(block_id)
+                .into_iter()
+                .filter(|o| self.defines(*o, var))
+                .last()
+            {
+                Some(offset) => defs.push(offset),
+                None => {
+                    let preds = self.predecessors_of(block_id);
+                    if preds.is_empty() {
+                        // Reached the function entry without a definition.
+                        return None;
+                    }
+                    todo.extend(preds);
+                }
+            }
+        }
+        Some(defs)
+    }
+
+    fn predecessors_of(&self, block_id: BlockId) -> Vec<BlockId> {
+        self.predecessors
+            .get(&block_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn entry_constant(&self, var: TempIndex) -> Option<Constant> {
+        let defs = self.entry_defs(var)?;
+        let mut values = defs.iter().map(|offset| self.constant_at(*offset));
+        let first = values.next()??;
+        values.all(|v| v.as_ref() == Some(&first)).then_some(first)
+    }
+
+    /// Determines how the definition at the offset modifies the counter.
+    fn step_at(&self, var: TempIndex, offset: CodeOffset) -> Option<Step> {
+        let (op_offset, op, srcs) = match &self.code()[offset as usize] {
+            Bytecode::Call(_, _, op @ (Operation::Add | Operation::Sub), srcs, _) => {
+                (offset, op, srcs)
+            }
+            Bytecode::Assign(_, _, src, _) => {
+                let (op_offset, op, srcs) = self
+                    .block_offsets(self.block_of[&offset])
+                    .into_iter()
+                    .take_while(|o| *o < offset)
+                    .filter(|o| self.defines(*o, *src))
+                    .last()
+                    .and_then(|o| match &self.code()[o as usize] {
+                        Bytecode::Call(_, _, op @ (Operation::Add | Operation::Sub), srcs, _) => {
+                            Some((o, op, srcs))
+                        }
+                        _ => None,
+                    })?;
+                // The counter must not be modified between the operation and the assignment.
+                if (op_offset + 1..offset).any(|o| self.defines(o, var)) {
+                    return None;
+                }
+                (op_offset, op, srcs)
+            }
+            _ => return None,
+        };
+        let amount = |k: TempIndex| {
+            self.def_before(k, op_offset).and_then(|bc| match bc {
+                Bytecode::Load(_, _, value) => constant_value(value),
+                _ => None,
+            })
+        };
+        match (op, srcs.as_slice()) {
+            (Operation::Add, [x, k]) | (Operation::Add, [k, x]) if *x == var => {
+                Some(Step::Increase(amount(*k)))
+            }
+            (Operation::Sub, [x, k]) if *x == var => Some(Step::Decrease(amount(*k))),
+            _ => None,
+        }
+    }
+
+    /// Determines the bound the index is compared against on loop entry, if the loop is
+    /// exited as soon as the index reaches it.
+    fn index_bound(&self, var: TempIndex) -> Option<LoopBound> {
+        let offsets = self.block_offsets(self.header);
+        let last = *offsets.last()?;
+        let (then_label, else_label, cond) = match &self.code()[last as usize] {
+            Bytecode::Branch(_, then_label, else_label, cond) => (*then_label, *else_label, *cond),
+            _ => return None,
+        };
+        let label_offsets = Bytecode::label_offsets(self.code());
+        let in_body = |label| self.body.contains(&self.block_of[&label_offsets[&label]]);
+        if !in_body(then_label) || in_body(else_label) {
+            return None;
+        }
+        if offsets.iter().any(|o| self.defines(*o, var)) {
+            return None;
+        }
+        let bound = match self.def_before(cond, last)? {
+            Bytecode::Call(_, _, Operation::Lt, srcs, _) if srcs[0] == var => srcs[1],
+            Bytecode::Call(_, _, Operation::Gt, srcs, _) if srcs[1] == var => srcs[0],
+            _ => return None,
+        };
+        self.resolve_bound(bound, last)
+    }
+
+    /// Resolves the value of the temporary at the offset in the header into an expression which
+    /// does not change in the loop.
+    fn resolve_bound(&self, temp: TempIndex, offset: CodeOffset) -> Option<LoopBound> {
+        let def = match self.def_before(temp, offset) {
+            Some(def) => def,
+            None if !self.loop_targets.contains(&temp) => {
+                return if self.func_target.is_temporary(temp) {
+                    self.entry_constant(temp)
+                        .as_ref()
+                        .and_then(constant_value)
+                        .map(LoopBound::Const)
+                } else {
+                    Some(LoopBound::Temp(temp))
+                };
+            }
+            None => return None,
+        };
+        let def_offset = self.offset_of(def);
+        match def {
+            Bytecode::Load(_, _, value) => constant_value(value).map(LoopBound::Const),
+            Bytecode::Assign(_, _, src, _) => self.resolve_bound(*src, def_offset),
+            Bytecode::Call(_, _, Operation::Function(mid, fid, _), srcs, _)
+                if self.is_well_known(mid.qualified(*fid), VECTOR_LENGTH) =>
+            {
+                let vec = srcs[0];
+                (!self.loop_targets.contains(&vec)
+                    && !self.func_target.get_local_type(vec).is_reference()
+                    && self.def_before(vec, def_offset).is_none())
+                .then_some(LoopBound::Length(vec))
+            }
+            Bytecode::Call(_, _, Operation::GetField(mid, sid, inst, field), srcs, _) => {
+                let resource = srcs[0];
+                match self.def_before(resource, def_offset)? {
+                    Bytecode::Call(_, _, Operation::GetGlobal(gmid, gsid, ginst), gsrcs, _)
+                        if gmid == mid && gsid == sid && ginst == inst =>
+                    {
+                        let addr = gsrcs[0];
+                        let mem = mid.qualified_inst(*sid, inst.clone());
+                        (!self.loop_targets.contains(&addr)
+                            && !self.func_target.get_local_type(addr).is_reference()
+                            && self.def_before(addr, def_offset).is_none()
+                            && !self.modifies_memory(mem.to_qualified_id()))
+                        .then_some(LoopBound::GlobalField(mem, addr, *field))
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn offset_of(&self, bc: &Bytecode) -> CodeOffset {
+        self.code()
+            .iter()
+            .position(|other| std::ptr::eq(other, bc))
+            .expect("bytecode of function") as CodeOffset
+    }
+
+    fn is_well_known(&self, fun_id: QualifiedId<FunId>, name: &str) -> bool {
+        self.func_target
+            .global_env()
+            .get_function(fun_id)
+            .is_well_known(name)
+    }
+
+    /// Returns true if the loop may modify the given memory.
+    fn modifies_memory(&self, mem: QualifiedId<StructId>) -> bool {
+        let env = self.func_target.global_env();
+        self.body_offsets()
+            .into_iter()
+            .any(|offset| match &self.code()[offset as usize] {
+                Bytecode::Call(
+                    _,
+                    _,
+                    Operation::MoveTo(mid, sid, _)
+                    | Operation::MoveFrom(mid, sid, _)
+                    | Operation::BorrowGlobal(mid, sid, _),
+                    _,
+                    _,
+                ) => mid.qualified(*sid) == mem,
+                Bytecode::Call(_, _, Operation::Function(mid, fid, _), _, _) => {
+                    let fun_id = mid.qualified(*fid);
+                    let callee_env = env.get_function(fun_id);
+                    if callee_env.is_native_or_intrinsic() {
+                        return false;
+                    }
+                    match self
+                        .targets
+                        .get_data(&fun_id, &FunctionVariant::Baseline)
+                        .and_then(|data| data.annotations.get::<UsageState>())
+                    {
+                        Some(usage) => usage.modified.get_all_uninst().contains(&mem),
+                        None => true,
+                    }
+                }
+                _ => false,
+            })
+    }
+
+    /// Returns true if the vector starts empty on loop entry, and its only modification in
+    /// the loop is pushing an element in the given block.
+    fn is_pushed_with(&self, vec: TempIndex, block_id: BlockId) -> bool {
+        let entry_empty = self.entry_defs(vec).map_or(false, |defs| {
+            !defs.is_empty()
+                && defs.iter().all(|offset| {
+                    matches!(
+                        self.value_def_at(*offset),
+                        Some(Bytecode::Call(_, _, Operation::Function(mid, fid, _), _, _))
+                            if self.is_well_known(mid.qualified(*fid), VECTOR_EMPTY)
+                    )
+                })
+        });
+        if !entry_empty {
+            return false;
+        }
+        // The vector is only modified by writing back a reference to it...
+        let defs = self.loop_defs(vec);
+        let write_back = match defs.as_slice() {
+            [offset] => &self.code()[*offset as usize],
+            _ => return false,
+        };
+        let reference = match write_back {
+            Bytecode::Call(_, _, Operation::WriteBack(BorrowNode::LocalRoot(root), _), srcs, _)
+                if *root == vec && self.block_of[&defs[0]] == block_id =>
+            {
+                srcs[0]
+            }
+            _ => return false,
+        };
+        // ... which is borrowed from it, and only used to push one element.
+        let mut borrowed = false;
+        let mut pushed = false;
+        for offset in self.body_offsets() {
+            match &self.code()[offset as usize] {
+                Bytecode::Call(_, dests, Operation::BorrowLoc, srcs, _)
+                    if dests[0] == reference =>
+                {
+                    if borrowed || srcs[0] != vec {
+                        return false;
+                    }
+                    borrowed = true;
+                }
+                Bytecode::Call(_, _, Operation::Function(mid, fid, _), srcs, _)
+                    if srcs.contains(&reference) =>
+                {
+                    if pushed
+                        || srcs[0] != reference
+                        || self.block_of[&offset] != block_id
+                        || !self.is_well_known(mid.qualified(*fid), VECTOR_PUSH_BACK)
+                    {
+                        return false;
+                    }
+                    pushed = true;
+                }
+                Bytecode::Call(_, _, Operation::TraceLocal(_), _, _) => {}
+                _ if offset == defs[0] => {}
+                bc if uses(bc, reference) || self.defines(offset, reference) => return false,
+                _ => {}
+            }
+        }
+        borrowed && pushed
+    }
+}
+
+/// Returns true if the bytecode reads the temporary.
+fn uses(bc: &Bytecode, temp: TempIndex) -> bool {
+    match bc {
+        Bytecode::Assign(_, _, src, _) => *src == temp,
+        Bytecode::Call(_, _, _, srcs, _) | Bytecode::Ret(_, srcs) => srcs.contains(&temp),
+        Bytecode::Branch(_, _, _, cond) | Bytecode::Abort(_, cond) => *cond == temp,
+        _ => false,
+    }
+}
@@ -80,15 +80,31 @@ pub struct ProverOptions {
     pub check_inconsistency: bool,
     /// Whether to consider a function that abort unconditionally as an inconsistency violation
     pub unconditional_abort_as_inconsistency: bool,
+    /// Whether to check specification coverage: unspecified public functions, uncovered aborts,
+    /// and vacuous `ensures`.
+    pub spec_coverage: bool,
     /// Whether to run the transformation passes for concrete interpretation (instead of proving)
     pub for_interpretation: bool,
     /// Whether to skip loop analysis.
     pub skip_loop_analysis: bool,
+    /// Whether to infer simple loop invariants in loop analysis.
+    pub infer_loop_invariants: bool,
+    /// Whether to report the inferred loop invariants as suggestions for the source.
+    pub suggest_loop_invariants: bool,
     /// Optional names of native methods (qualified with module name, e.g., m::foo) implementing
     /// mutable borrow semantics
     pub borrow_natives: Vec<String>,
     /// Whether to ban convertion from int to bv at the boogie backend
     pub ban_int_2_bv: bool,
+    /// Whether to trace the global memory accessed by a verified function at its entry, so
+    /// that counterexamples describe the initial state.
+    pub trace_initial_memory: bool,
+    /// Whether to check the statically computed gas bound of functions against their
+    /// `pragma max_gas`.
+    pub check_max_gas: bool,
+    /// Optional path to a JSON gas schedule for the gas bound analysis. The initial cost
+    /// schedule of the VM is used if absent.
+    pub gas_schedule: Option<String>,
 }
 
 // add custom struct for mutation options
@@ -119,10 +135,16 @@ impl Default for ProverOptions {
             sequential_task: false,
             check_inconsistency: false,
             unconditional_abort_as_inconsistency: false,
+            spec_coverage: false,
             for_interpretation: false,
             skip_loop_analysis: false,
+            infer_loop_invariants: false,
+            suggest_loop_invariants: false,
             borrow_natives: vec![],
             ban_int_2_bv: false,
+            trace_initial_memory: false,
+            check_max_gas: false,
+            gas_schedule: None,
         }
     }
 }
@@ -9,9 +9,10 @@ use crate::{
     debug_instrumentation::DebugInstrumenter,
     eliminate_imm_refs::EliminateImmRefsProcessor,
     function_target_pipeline::{FunctionTargetPipeline, FunctionTargetProcessor},
+    gas_bound_analysis::{GasBoundProcessor, GasSchedule},
     global_invariant_analysis::GlobalInvariantAnalysisProcessor,
     global_invariant_instrumentation::GlobalInvariantInstrumentationProcessor,
-    inconsistency_check::InconsistencyCheckInstrumenter,
+    inconsistency_check::{InconsistencyCheckInstrumenter, SpecCoverageInstrumenter},
     livevar_analysis::LiveVarAnalysisProcessor,
     loop_analysis::LoopAnalysisProcessor,
     memory_instrumentation::MemoryInstrumentationProcessor,
@@ -43,6 +44,15 @@ pub fn default_pipeline_with_options(options: &ProverOptions) -> FunctionTargetP
         VerificationAnalysisProcessor::new(),
     ];
 
+    if options.check_max_gas {
+        // runs on the initial bytecode, and instruments the checks of `pragma max_gas`
+        // before any other transformation
+        processors.insert(
+            0,
+            GasBoundProcessor::new(GasSchedule::from_options(options)),
+        );
+    }
+
     if !options.skip_loop_analysis {
         processors.push(LoopAnalysisProcessor::new());
     }
@@ -63,6 +73,10 @@ pub fn default_pipeline_with_options(options: &ProverOptions) -> FunctionTargetP
         processors.push(MutationTester::new());
     }
 
+    if options.spec_coverage {
+        processors.push(SpecCoverageInstrumenter::new());
+    }
+
     // inconsistency check instrumentation should be the last one in the pipeline
     if options.check_inconsistency {
         processors.push(InconsistencyCheckInstrumenter::new());
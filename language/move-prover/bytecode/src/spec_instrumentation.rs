@@ -18,7 +18,7 @@ use move_model::{
     model::{FunId, FunctionEnv, GlobalEnv, Loc, ModuleId, QualifiedId, QualifiedInstId, StructId},
     pragmas::{ABORTS_IF_IS_PARTIAL_PRAGMA, EMITS_IS_PARTIAL_PRAGMA, EMITS_IS_STRICT_PRAGMA},
     spec_translator::{SpecTranslator, TranslatedSpec},
-    ty::{Type, TypeDisplayContext, BOOL_TYPE, NUM_TYPE},
+    ty::{PrimitiveType, Type, TypeDisplayContext, BOOL_TYPE, NUM_TYPE},
 };
 
 use crate::{
@@ -27,6 +27,7 @@ use crate::{
     function_target_pipeline::{
         FunctionTargetProcessor, FunctionTargetsHolder, FunctionVariant, VerificationFlavor,
     },
+    inconsistency_check::UNCOVERED_ABORT_MESSAGE,
     livevar_analysis::LiveVarAnalysisProcessor,
     options::ProverOptions,
     reaching_def_analysis::ReachingDefProcessor,
@@ -38,13 +39,17 @@ use crate::{
 };
 
 const REQUIRES_FAILS_MESSAGE: &str = "precondition does not hold at this call";
-const ENSURES_FAILS_MESSAGE: &str = "post-condition does not hold";
-const ABORTS_IF_FAILS_MESSAGE: &str = "function does not abort under this condition";
+pub const ENSURES_FAILS_MESSAGE: &str = "post-condition does not hold";
+pub const ABORTS_IF_FAILS_MESSAGE: &str = "function does not abort under this condition";
+const SUCCEEDS_IF_FAILS_MESSAGE: &str = "function does not succeed under this condition";
+const DECREASES_FAILS_MESSAGE: &str = "measure does not decrease at this recursive call";
 const ABORT_NOT_COVERED: &str = "abort not covered by any of the `aborts_if` clauses";
 const ABORTS_CODE_NOT_COVERED: &str =
     "abort code not covered by any of the `aborts_if` or `aborts_with` clauses";
 const EMITS_FAILS_MESSAGE: &str = "function does not emit the expected event";
 const EMITS_NOT_COVERED: &str = "emitted event not covered by any of the `emits` clauses";
+// This message is for the boogie wrapper, and not shown to the users.
+pub const VACUITY_CHECK_MESSAGE: &str = "expected to fail";
 
 fn modify_check_fails_message(
     env: &GlobalEnv,
@@ -119,17 +124,43 @@ impl FunctionTargetProcessor for SpecInstrumentationProcessor {
             let mut verification_data =
                 data.fork(FunctionVariant::Verification(VerificationFlavor::Regular));
             verification_data =
-                Instrumenter::run(&options, targets, fun_env, verification_data, scc_opt);
+                Instrumenter::run(&options, targets, fun_env, verification_data, scc_opt, None);
+            let ensures_count = if options.spec_coverage {
+                count_ensures(&verification_data)
+            } else {
+                0
+            };
             targets.insert_target_data(
                 &fun_env.get_qualified_id(),
                 verification_data.variant.clone(),
                 verification_data,
             );
+
+            // For specification coverage, create one variant per `ensures` in which the body
+            // is havocked. If the `ensures` can still be proven, it is vacuous.
+            for index in 0..ensures_count {
+                let mut vacuity_data = data.fork(FunctionVariant::Verification(
+                    VerificationFlavor::Vacuity(index),
+                ));
+                vacuity_data = Instrumenter::run(
+                    &options,
+                    targets,
+                    fun_env,
+                    vacuity_data,
+                    scc_opt,
+                    Some(index),
+                );
+                targets.insert_target_data(
+                    &fun_env.get_qualified_id(),
+                    vacuity_data.variant.clone(),
+                    vacuity_data,
+                );
+            }
         }
 
         // Instrument baseline variant only if it is inlined.
         if is_inlined {
-            Instrumenter::run(&options, targets, fun_env, data, scc_opt)
+            Instrumenter::run(&options, targets, fun_env, data, scc_opt, None)
         } else {
             // Clear code but keep function data stub.
             // TODO(refactoring): the stub is currently still needed because boogie_wrapper
@@ -179,6 +210,19 @@ impl FunctionTargetProcessor for SpecInstrumentationProcessor {
     }
 }
 
+/// Counts the `ensures` asserted in the return block of an instrumented verification variant.
+fn count_ensures(data: &FunctionData) -> usize {
+    data.code
+        .iter()
+        .filter(|bc| match bc {
+            Bytecode::Prop(id, PropKind::Assert, _) => {
+                data.vc_infos.get(id).map(String::as_str) == Some(ENSURES_FAILS_MESSAGE)
+            }
+            _ => false,
+        })
+        .count()
+}
+
 struct Instrumenter<'a> {
     options: &'a ProverOptions,
     builder: FunctionDataBuilder<'a>,
@@ -189,6 +233,10 @@ struct Instrumenter<'a> {
     abort_label: Label,
     can_abort: bool,
     mem_info: &'a BTreeSet<QualifiedInstId<StructId>>,
+    /// The functions which are mutually recursive with this one.
+    recursive: BTreeSet<QualifiedId<FunId>>,
+    /// If set, the body is havocked and only the `ensures` with this index is checked.
+    vacuity: Option<usize>,
 }
 
 impl<'a> Instrumenter<'a> {
@@ -198,6 +246,7 @@ impl<'a> Instrumenter<'a> {
         fun_env: &FunctionEnv<'a>,
         data: FunctionData,
         scc_opt: Option<&[FunctionEnv]>,
+        vacuity: Option<usize>,
     ) -> FunctionData {
         // Pre-collect properties in the original function data
         let props: Vec<_> = data
@@ -265,7 +314,7 @@ impl<'a> Instrumenter<'a> {
 
         let mut mem_info = BTreeSet::new();
 
-        if auto_trace {
+        if auto_trace || options.trace_initial_memory && builder.data.variant.is_verified() {
             // Retrieve the memory used by the function
             mem_info =
                 usage_analysis::get_memory_usage(&FunctionTarget::new(fun_env, &builder.data))
@@ -284,6 +333,12 @@ impl<'a> Instrumenter<'a> {
             abort_label,
             can_abort: false,
             mem_info: &mem_info,
+            recursive: scc_opt
+                .unwrap_or_default()
+                .iter()
+                .map(|fun_env| fun_env.get_qualified_id())
+                .collect(),
+            vacuity,
         };
         instrumenter.instrument(&spec, &inlined_props);
 
@@ -325,6 +380,22 @@ impl<'a> Instrumenter<'a> {
         }
 
         if self.is_verified() {
+            // Trace the memory the function accesses as it is on entry, so a counterexample
+            // contains the initial state.
+            if self.options.trace_initial_memory {
+                for memory in self.mem_info {
+                    self.builder.emit_with(|id| {
+                        Call(
+                            id,
+                            vec![],
+                            Operation::TraceGlobalMem(memory.clone()),
+                            vec![],
+                            None,
+                        )
+                    });
+                }
+            }
+
             // Inject 'CanModify' assumptions for this function.
             for (loc, exp) in &spec.modifies {
                 let struct_ty = self.builder.global_env().get_node_type(exp.node_id());
@@ -373,8 +444,12 @@ impl<'a> Instrumenter<'a> {
         }
 
         // Instrument and generate new code
-        for bc in old_code {
-            self.instrument_bytecode(spec, inlined_props, bc);
+        if self.vacuity.is_some() {
+            self.generate_havoc_body();
+        } else {
+            for bc in old_code {
+                self.instrument_bytecode(spec, inlined_props, bc);
+            }
         }
 
         // Generate return and abort blocks
@@ -386,6 +461,67 @@ impl<'a> Instrumenter<'a> {
         }
     }
 
+    /// Generates a body which havocs the results, the `&mut` parameters, and the memory the
+    /// function modifies at an arbitrary address, and then returns.
+    fn generate_havoc_body(&mut self) {
+        use Bytecode::*;
+
+        self.builder.set_loc(self.builder.fun_env.get_loc());
+        let mut_params = (0..self.builder.fun_env.get_parameter_count())
+            .filter(|idx| self.builder.data.local_types[*idx].is_mutable_reference())
+            .map(|idx| (idx, HavocKind::MutationValue));
+        let results = self
+            .ret_locals
+            .clone()
+            .into_iter()
+            .map(|idx| (idx, HavocKind::Value));
+        for (idx, kind) in mut_params.collect_vec().into_iter().chain(results) {
+            self.builder
+                .emit_with(|id| Call(id, vec![idx], Operation::Havoc(kind), vec![], None));
+            let exp = self.builder.mk_call(
+                &BOOL_TYPE,
+                ast::Operation::WellFormed,
+                vec![self.builder.mk_temporary(idx)],
+            );
+            self.builder
+                .emit_with(move |id| Prop(id, PropKind::Assume, exp));
+        }
+
+        let modified = usage_analysis::get_memory_usage(&FunctionTarget::new(
+            self.builder.fun_env,
+            &self.builder.data,
+        ))
+        .modified
+        .get_all_inst(&self.builder.data.type_args);
+        for mem in modified {
+            let addr = self
+                .builder
+                .new_temp(Type::Primitive(PrimitiveType::Address));
+            self.builder.emit_with(|id| {
+                Call(
+                    id,
+                    vec![addr],
+                    Operation::Havoc(HavocKind::Value),
+                    vec![],
+                    None,
+                )
+            });
+            let mem_ty = mem.to_type();
+            let exp = self.builder.mk_call_with_inst(
+                &mem_ty,
+                vec![mem_ty.clone()],
+                ast::Operation::Global(None),
+                vec![self.builder.mk_temporary(addr)],
+            );
+            self.builder
+                .emit_with(move |id| Prop(id, PropKind::Modifies, exp));
+        }
+
+        let ret_label = self.ret_label;
+        self.builder.emit_with(|id| Jump(id, ret_label));
+        self.can_return = true;
+    }
+
     fn instrument_bytecode(
         &mut self,
         spec: &TranslatedSpec,
@@ -447,7 +583,7 @@ impl<'a> Instrumenter<'a> {
                 self.can_abort = true;
             }
             Call(id, dests, Function(mid, fid, targs), srcs, aa) => {
-                self.instrument_call(id, dests, mid, fid, targs, srcs, aa);
+                self.instrument_call(spec, id, dests, mid, fid, targs, srcs, aa);
             }
             Call(id, dests, oper, srcs, _) if oper.can_abort() => {
                 self.builder.emit(Call(
@@ -459,6 +595,9 @@ impl<'a> Instrumenter<'a> {
                 ));
                 self.can_abort = true;
             }
+            Prop(id, PropKind::Assert, _) if self.builder.data.loop_measures.contains(&id) => {
+                // Loop measures are only checked by the loop analysis, which did not run.
+            }
             Prop(id, kind @ PropKind::Assume, prop) | Prop(id, kind @ PropKind::Assert, prop) => {
                 match inlined_props.get(&id) {
                     None => {
@@ -479,8 +618,10 @@ impl<'a> Instrumenter<'a> {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn instrument_call(
         &mut self,
+        spec: &TranslatedSpec,
         id: AttrId,
         dests: Vec<TempIndex>,
         mid: ModuleId,
@@ -533,6 +674,24 @@ impl<'a> Instrumenter<'a> {
             }
         }
 
+        // Emit the termination check if this is the verification variant and the call is
+        // recursive.
+        if self.is_verified() && self.recursive.contains(&mid.qualified(fid)) {
+            let old = spec.decreases.iter().map(|(_, e)| e.clone()).collect_vec();
+            let new = callee_spec
+                .decreases
+                .iter()
+                .map(|(_, e)| e.clone())
+                .collect_vec();
+            if let Some(cond) = TranslatedSpec::decreases_condition(&self.builder, &old, &new) {
+                let loc = self.builder.get_loc(id);
+                self.builder
+                    .set_loc_and_vc_info(loc, DECREASES_FAILS_MESSAGE);
+                self.builder.emit_with(|id| Prop(id, Assert, cond));
+                self.builder.set_loc_from_attr(id);
+            }
+        }
+
         // Emit modify permissions as assertions if this is the verification variant. For
         // non-verification variants, we don't need to do this because they are independently
         // verified.
@@ -897,6 +1056,27 @@ impl<'a> Instrumenter<'a> {
                 self.builder.set_loc_and_vc_info(loc, ABORT_NOT_COVERED);
                 self.builder.emit_with(move |id| Prop(id, Assert, cond));
             }
+        } else if self.options.spec_coverage && self.vacuity.is_none() {
+            // If partial, the aborts condition is not required to hold, but the coverage check
+            // reports aborts it does not cover. The assertion is only kept in the coverage
+            // variant.
+            let cond = spec
+                .aborts_condition(&self.builder)
+                .unwrap_or_else(|| self.builder.mk_bool_const(false));
+            let loc = self.builder.fun_env.get_spec_loc();
+            self.emit_traces(spec, &cond);
+            self.builder
+                .set_loc_and_vc_info(loc, UNCOVERED_ABORT_MESSAGE);
+            self.builder.emit_with(move |id| Prop(id, Assert, cond));
+        }
+
+        // Emit the negation of all success conditions.
+        for (loc, cond) in &spec.succeeds {
+            self.emit_traces(spec, cond);
+            let exp = self.builder.mk_not(cond.clone());
+            self.builder
+                .set_loc_and_vc_info(loc.clone(), SUCCEEDS_IF_FAILS_MESSAGE);
+            self.builder.emit_with(|id| Prop(id, Assert, exp))
         }
 
         if spec.has_aborts_code_specs() {
@@ -927,7 +1107,7 @@ impl<'a> Instrumenter<'a> {
         } else {
             spec.aborts_condition(&self.builder)
         };
-        let aborts_cond_temp = if let Some(cond) = aborts_cond {
+        let mut aborts_cond_temp = if let Some(cond) = aborts_cond {
             if matches!(cond.as_ref(), ExpData::Value(_, Value::Bool(false))) {
                 return (None, None);
             }
@@ -938,6 +1118,14 @@ impl<'a> Instrumenter<'a> {
             // condition.
             self.builder.emit_let_havoc(BOOL_TYPE.clone()).0
         };
+        if let Some(succeeds_cond) = spec.succeeds_condition(&self.builder) {
+            // The function does not abort if any of its success conditions holds.
+            let cond = self.builder.mk_and(
+                self.builder.mk_temporary(aborts_cond_temp),
+                self.builder.mk_not(succeeds_cond),
+            );
+            aborts_cond_temp = self.builder.emit_let(cond).0;
+        }
         let aborts_code_cond = if spec.has_aborts_code_specs() {
             let actual_code = self.builder.mk_temporary(self.abort_local);
             spec.aborts_code_condition(&self.builder, &actual_code)
@@ -973,6 +1161,19 @@ impl<'a> Instrumenter<'a> {
                 self.emit_lets(spec, true);
             }
 
+            if let Some(index) = self.vacuity {
+                // Only check the given post-condition. It is expected to fail for the havocked
+                // body; if it does not, the boogie wrapper reports it as vacuous.
+                let (loc, cond) = &spec.post[index];
+                self.builder
+                    .set_loc_and_vc_info(loc.clone(), VACUITY_CHECK_MESSAGE);
+                self.builder
+                    .emit_with(move |id| Prop(id, Assert, cond.clone()));
+                let ret_locals = self.ret_locals.clone();
+                self.builder.emit_with(move |id| Ret(id, ret_locals));
+                return;
+            }
+
             // Emit the negation of all aborts conditions.
             for (loc, abort_cond, _) in &spec.aborts {
                 self.emit_traces(spec, abort_cond);
@@ -43,6 +43,7 @@ pub struct StacklessBytecodeGenerator<'a> {
     code: Vec<Bytecode>,
     location_table: BTreeMap<AttrId, Loc>,
     loop_invariants: BTreeSet<AttrId>,
+    loop_measures: BTreeSet<AttrId>,
     fallthrough_labels: BTreeSet<Label>,
 }
 
@@ -60,6 +61,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             code: vec![],
             location_table: BTreeMap::new(),
             loop_invariants: BTreeSet::new(),
+            loop_measures: BTreeSet::new(),
             fallthrough_labels: BTreeSet::new(),
         }
     }
@@ -115,6 +117,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             code,
             location_table,
             loop_invariants,
+            loop_measures,
             ..
         } = self;
 
@@ -126,6 +129,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             location_table,
             func_env.get_acquires_global_resources(),
             loop_invariants,
+            loop_measures,
         )
     }
 
@@ -182,6 +186,10 @@ impl<'a> StacklessBytecodeGenerator<'a> {
                         self.loop_invariants.insert(attr_id);
                         PropKind::Assert
                     }
+                    ConditionKind::Decreases => {
+                        self.loop_measures.insert(attr_id);
+                        PropKind::Assert
+                    }
                     // Updating global spec variables are translated to Assume, which will be replaced when instrumenting the spec
                     ConditionKind::Update => PropKind::Assume,
                     _ => {
@@ -284,7 +284,7 @@ impl<'a> MemoryUsageAnalysis<'a> {
                 used_memory.extend(exp.used_memory(self.cache.global_env()));
             }
             match &cond.kind {
-                Ensures | AbortsIf | Emits => {
+                Ensures | AbortsIf | SucceedsIf | Decreases | Emits => {
                     state.add_direct_asserted_iter(used_memory.into_iter().map(|(usage, _)| usage));
                 }
                 _ => {
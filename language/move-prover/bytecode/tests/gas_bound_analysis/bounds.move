@@ -0,0 +1,87 @@
+// dep: ../../move-stdlib/sources/vector.move
+
+module 0x42::GasBounds {
+    use std::vector;
+
+    struct Counter has key { value: u64 }
+
+    // Straight-line code has a constant bound.
+    public fun add(x: u64, y: u64): u64 {
+        x + y
+    }
+
+    // The bound of a conditional is the bound of its most expensive branch.
+    public fun max(x: u64, y: u64): u64 {
+        if (x > y) x else add(x, y) - y
+    }
+
+    // The loop is bounded by the length of the vector parameter.
+    public fun sum(v: &vector<u64>): u64 {
+        let i = 0;
+        let s = 0;
+        while ({
+            spec { invariant i <= len(v); };
+            i < vector::length(v)
+        }) {
+            s = s + *vector::borrow(v, i);
+            i = i + 1;
+        };
+        s
+    }
+
+    // The loop is bounded by an integer parameter.
+    public fun range(n: u64): vector<u64> {
+        let r = vector::empty();
+        let i = 0;
+        while ({
+            spec { invariant i <= n; };
+            i < n
+        }) {
+            vector::push_back(&mut r, i);
+            i = i + 1;
+        };
+        r
+    }
+
+    // The bounds of callees are instantiated with the arguments.
+    public entry fun sum_range(n: u64) {
+        let r = range(n);
+        sum(&r);
+    }
+
+    // Without an invariant, the loop is unbounded.
+    public fun count(n: u64): u64 {
+        let i = 0;
+        while (i < n) {
+            i = i + 1;
+        };
+        i
+    }
+
+    // The counter must not be decremented in the loop.
+    public fun back_and_forth(n: u64): u64 {
+        let i = 0;
+        while ({
+            spec { invariant i <= n; };
+            i < n
+        }) {
+            i = i + 2;
+            i = i - 1;
+        };
+        i
+    }
+
+    public fun recursive(n: u64): u64 {
+        if (n == 0) 0 else recursive(n - 1)
+    }
+
+    public entry fun increment(account: address) acquires Counter {
+        let counter = borrow_global_mut<Counter>(account);
+        counter.value = counter.value + 1;
+    }
+
+    public fun generic<T: drop>(x: T, v: vector<T>): vector<T> {
+        vector::push_back(&mut v, x);
+        v
+    }
+}
@@ -0,0 +1,67 @@
+// dep: ../../move-stdlib/sources/vector.move
+
+module 0x42::InferredInvariants {
+    use std::vector;
+
+    struct Config has key { max: u64 }
+
+    fun sum(v: &vector<u64>): u64 {
+        let i = 0;
+        let s = 0;
+        while (i < vector::length(v)) {
+            s = s + *vector::borrow(v, i);
+            i = i + 1;
+        };
+        s
+    }
+
+    fun range(n: u64): vector<u64> {
+        let r = vector::empty();
+        let i = 1;
+        while (i < n) {
+            vector::push_back(&mut r, i);
+            i = i + 1;
+        };
+        r
+    }
+
+    fun count(a: address): u64 acquires Config {
+        let i = 0;
+        while (i < borrow_global<Config>(a).max) {
+            i = i + 1;
+        };
+        i
+    }
+
+    fun count_down(n: u64): u64 {
+        let k = 10;
+        let j = 5;
+        while (k > 0 && n > 0) {
+            k = k - 1;
+            j = j + 2;
+        };
+        k + j
+    }
+
+    fun nested(n: u64): u64 {
+        let i = 0;
+        let t = 0;
+        while (i < n) {
+            let j = 0;
+            while (j < 3) {
+                j = j + 1;
+                t = t + 1;
+            };
+            i = i + 1;
+        };
+        t
+    }
+
+    fun not_inferred(n: u64): u64 {
+        let i = 0;
+        while (i < n) {
+            i = i + 2;
+        };
+        i
+    }
+}
@@ -0,0 +1,21 @@
+module 0x42::TestSpecCoverage {
+    struct R has key { v: u64 }
+
+    public fun inc(x: u64): u64 {
+        x + 1
+    }
+    spec inc {
+        ensures result == x + 1;
+        ensures result >= 0;
+    }
+
+    public fun bump(a: address) acquires R {
+        let r = borrow_global_mut<R>(a);
+        r.v = r.v + 1;
+    }
+    spec bump {
+        aborts_if !exists<R>(a);
+        aborts_if global<R>(a).v + 1 > MAX_U64;
+        ensures global<R>(a).v == old(global<R>(a).v) + 1;
+    }
+}
@@ -0,0 +1,9 @@
+module 0x42::TestPartialCoverage {
+    public fun dec(x: u64): u64 {
+        x - 1
+    }
+    spec dec {
+        pragma aborts_if_is_partial;
+        aborts_if x == 0;
+    }
+}
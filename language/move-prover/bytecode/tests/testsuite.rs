@@ -17,9 +17,12 @@ use move_stackless_bytecode::{
     function_target_pipeline::{
         FunctionTargetPipeline, FunctionTargetsHolder, ProcessorResultDisplay,
     },
+    gas_bound_analysis::{GasBoundProcessor, GasSchedule},
     global_invariant_analysis::GlobalInvariantAnalysisProcessor,
     global_invariant_instrumentation::GlobalInvariantInstrumentationProcessor,
+    inconsistency_check::SpecCoverageInstrumenter,
     livevar_analysis::LiveVarAnalysisProcessor,
+    loop_analysis::LoopAnalysisProcessor,
     memory_instrumentation::MemoryInstrumentationProcessor,
     mono_analysis::MonoAnalysisProcessor,
     mut_ref_instrumentation::MutRefInstrumenter,
@@ -88,6 +91,11 @@ fn get_tested_transformation_pipeline(
             pipeline.add_processor(Box::new(EscapeAnalysisProcessor {}));
             Ok(Some(pipeline))
         }
+        "gas_bound_analysis" => {
+            let mut pipeline = FunctionTargetPipeline::default();
+            pipeline.add_processor(GasBoundProcessor::new(GasSchedule::default()));
+            Ok(Some(pipeline))
+        }
         "memory_instr" => {
             let mut pipeline = FunctionTargetPipeline::default();
             pipeline.add_processor(EliminateImmRefsProcessor::new());
@@ -184,6 +192,37 @@ fn get_tested_transformation_pipeline(
             pipeline.add_processor(GlobalInvariantInstrumentationProcessor::new());
             Ok(Some(pipeline))
         }
+        "spec_coverage" => {
+            let mut pipeline = FunctionTargetPipeline::default();
+            pipeline.add_processor(EliminateImmRefsProcessor::new());
+            pipeline.add_processor(MutRefInstrumenter::new());
+            pipeline.add_processor(ReachingDefProcessor::new());
+            pipeline.add_processor(LiveVarAnalysisProcessor::new());
+            pipeline.add_processor(BorrowAnalysisProcessor::new());
+            pipeline.add_processor(MemoryInstrumentationProcessor::new());
+            pipeline.add_processor(CleanAndOptimizeProcessor::new());
+            pipeline.add_processor(UsageProcessor::new());
+            pipeline.add_processor(VerificationAnalysisProcessor::new());
+            pipeline.add_processor(SpecInstrumentationProcessor::new());
+            pipeline.add_processor(GlobalInvariantAnalysisProcessor::new());
+            pipeline.add_processor(GlobalInvariantInstrumentationProcessor::new());
+            pipeline.add_processor(SpecCoverageInstrumenter::new());
+            Ok(Some(pipeline))
+        }
+        "loop_analysis" => {
+            let mut pipeline = FunctionTargetPipeline::default();
+            pipeline.add_processor(EliminateImmRefsProcessor::new());
+            pipeline.add_processor(MutRefInstrumenter::new());
+            pipeline.add_processor(ReachingDefProcessor::new());
+            pipeline.add_processor(LiveVarAnalysisProcessor::new());
+            pipeline.add_processor(BorrowAnalysisProcessor::new());
+            pipeline.add_processor(MemoryInstrumentationProcessor::new());
+            pipeline.add_processor(CleanAndOptimizeProcessor::new());
+            pipeline.add_processor(UsageProcessor::new());
+            pipeline.add_processor(VerificationAnalysisProcessor::new());
+            pipeline.add_processor(LoopAnalysisProcessor::new());
+            Ok(Some(pipeline))
+        }
         "read_write_set" => {
             let mut pipeline = FunctionTargetPipeline::default();
             pipeline.add_processor(Box::new(ReadWriteSetProcessor {}));
@@ -229,16 +268,18 @@ fn test_runner(path: &Path) -> datatest_stable::Result<()> {
         env.report_diag(&mut error_writer, Severity::Error);
         String::from_utf8_lossy(&error_writer.into_inner()).to_string()
     } else {
-        let options = ProverOptions {
-            stable_test_output: true,
-            ..Default::default()
-        };
-        env.set_extension(options);
         let dir_name = path
             .parent()
             .and_then(|p| p.file_name())
             .and_then(|p| p.to_str())
             .ok_or_else(|| anyhow!("bad file name"))?;
+        let options = ProverOptions {
+            stable_test_output: true,
+            infer_loop_invariants: dir_name == "loop_analysis",
+            spec_coverage: dir_name == "spec_coverage",
+            ..Default::default()
+        };
+        env.set_extension(options);
         let pipeline_opt = get_tested_transformation_pipeline(dir_name)?;
 
         // Initialize and print function targets
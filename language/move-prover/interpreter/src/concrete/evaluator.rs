@@ -19,6 +19,7 @@ use move_model::{
 };
 use move_stackless_bytecode::{
     function_target::FunctionTarget, function_target_pipeline::FunctionTargetsHolder,
+    stackless_bytecode::Bytecode,
 };
 
 use crate::{
@@ -1355,7 +1356,40 @@ impl<'env> Evaluator<'env> {
     fn record_checking_failure(&self, exp: &Exp) {
         let env = self.target.global_env();
         let loc = env.get_node_loc(exp.node_id());
-        env.error(&loc, "property does not hold");
+        // report the values of the locals the property depends on
+        let notes = exp
+            .used_temporaries(env)
+            .into_iter()
+            .filter(|(idx, _)| self.local_state.has_value(*idx))
+            .filter_map(|(idx, _)| {
+                let name = self.spec_local_name(idx)?;
+                Some(format!("`{}` = {}", name, self.local_state.get_value(idx)))
+            })
+            .collect();
+        env.error_with_notes(&loc, "property does not hold", notes);
+    }
+
+    /// Returns the name under which a local appears in the specification, which is `result`
+    /// (or `result_N`) for the return values, and none for other temporaries.
+    fn spec_local_name(&self, idx: TempIndex) -> Option<String> {
+        let ret_pos = self.target.get_bytecode().iter().find_map(|bc| match bc {
+            Bytecode::Ret(_, rets) => rets.iter().position(|ret| *ret == idx),
+            _ => None,
+        });
+        match ret_pos {
+            Some(_) if self.target.get_return_count() == 1 => Some("result".to_string()),
+            Some(pos) => Some(format!("result_{}", pos + 1)),
+            None if self.target.has_local_user_name(idx) && !self.target.is_temporary(idx) => {
+                let env = self.target.global_env();
+                Some(
+                    self.target
+                        .get_local_name(idx)
+                        .display(env.symbol_pool())
+                        .to_string(),
+                )
+            }
+            None => None,
+        }
     }
 
     fn eval_failure_code() -> BigInt {
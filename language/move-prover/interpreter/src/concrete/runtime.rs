@@ -29,7 +29,7 @@ use crate::{
             convert_model_base_type, BaseType, IntType, PrimitiveType, StructField,
             StructInstantiation,
         },
-        value::{GlobalState, TypedValue},
+        value::{BaseValue, GlobalState, TypedValue},
     },
     shared::{ident::StructIdent, variant::choose_variant},
 };
@@ -148,6 +148,15 @@ fn check_and_convert_type_args_and_args(
                     }
                 }
             }
+            // NOTE: unit tests pass `signer` arguments as addresses
+            MT::Type::Primitive(MT::PrimitiveType::Signer) => match arg {
+                MoveValue::Address(v) | MoveValue::Signer(v) => {
+                    converted_args.push(TypedValue::mk_signer(*v));
+                }
+                _ => {
+                    return Err(PartialVMError::new(StatusCode::TYPE_MISMATCH));
+                }
+            },
             _ => {
                 let base_ty = convert_model_base_type(env, &local_ty, &converted_ty_args);
                 let converted = convert_move_value(arg, &base_ty)?;
@@ -232,12 +241,21 @@ pub fn convert_move_value(val: &MoveValue, ty: &BaseType) -> PartialVMResult<Typ
         (MoveValue::U8(v), BaseType::Primitive(PrimitiveType::Int(IntType::U8))) => {
             TypedValue::mk_u8(*v)
         }
+        (MoveValue::U16(v), BaseType::Primitive(PrimitiveType::Int(IntType::U16))) => {
+            TypedValue::mk_u16(*v)
+        }
+        (MoveValue::U32(v), BaseType::Primitive(PrimitiveType::Int(IntType::U32))) => {
+            TypedValue::mk_u32(*v)
+        }
         (MoveValue::U64(v), BaseType::Primitive(PrimitiveType::Int(IntType::U64))) => {
             TypedValue::mk_u64(*v)
         }
         (MoveValue::U128(v), BaseType::Primitive(PrimitiveType::Int(IntType::U128))) => {
             TypedValue::mk_u128(*v)
         }
+        (MoveValue::U256(v), BaseType::Primitive(PrimitiveType::Int(IntType::U256))) => {
+            TypedValue::fuse_base(ty.clone(), BaseValue::mk_u256(*v))
+        }
         (MoveValue::Address(v), BaseType::Primitive(PrimitiveType::Address)) => {
             TypedValue::mk_address(*v)
         }
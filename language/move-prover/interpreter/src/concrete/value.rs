@@ -9,7 +9,10 @@
 //! and each type must also have a way to construct a value of this type).
 
 use num::{BigInt, ToPrimitive};
-use std::collections::{BTreeMap, BTreeSet};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
 
 use move_core_types::{
     account_address::AccountAddress,
@@ -997,6 +1000,47 @@ impl TypedValue {
     }
 }
 
+//**************************************************************************************************
+// Display
+//**************************************************************************************************
+
+impl fmt::Display for TypedValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ty = match &self.ty {
+            Type::Base(base_ty) | Type::Reference(_, base_ty) => base_ty,
+        };
+        fmt_base_value(f, ty, &self.val)
+    }
+}
+
+fn fmt_base_value(f: &mut fmt::Formatter<'_>, ty: &BaseType, val: &BaseValue) -> fmt::Result {
+    match (ty, val) {
+        (_, BaseValue::Bool(v)) => write!(f, "{}", v),
+        (_, BaseValue::Int(v)) => write!(f, "{}", v),
+        (_, BaseValue::Address(v)) => write!(f, "@0x{}", v.short_str_lossless()),
+        (_, BaseValue::Signer(v)) => write!(f, "signer(@0x{})", v.short_str_lossless()),
+        (BaseType::Vector(elem_ty), BaseValue::Vector(elems)) => {
+            write!(f, "[")?;
+            for (i, elem) in elems.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                fmt_base_value(f, elem_ty, elem)?;
+            }
+            write!(f, "]")
+        }
+        (BaseType::Struct(inst), BaseValue::Struct(fields)) => {
+            write!(f, "{} {{", inst.ident)?;
+            for (i, (field, info)) in fields.iter().zip(inst.fields.iter()).enumerate() {
+                write!(f, "{} {}: ", if i > 0 { "," } else { "" }, info.name)?;
+                fmt_base_value(f, &info.ty, field)?;
+            }
+            write!(f, " }}")
+        }
+        _ => write!(f, "{:?}", val),
+    }
+}
+
 //**************************************************************************************************
 // Local state
 //**************************************************************************************************
@@ -0,0 +1,1404 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Translation of verified functions into SMT-LIB2 verification conditions.
+//!
+//! After loop analysis, the control flow graph of a verified function is acyclic. Its code,
+//! with calls of functions which are not opaque inlined, is turned into a graph of blocks in
+//! static single assignment form: every assigned value is bound to a fresh constant, and
+//! values which differ between the predecessors of a block are joined by equations on the
+//! incoming edges. Each block `b` is then encoded by its weakest precondition `$ok_b`: the
+//! assumptions of the block imply its assertions and the `$ok` of every successor whose guard
+//! holds. The function is correct iff `$ok` of the root block is valid. Assertions are checked
+//! one at a time: the one whose index equals `$check` is asserted, all others are assumed.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use itertools::Itertools;
+use log::info;
+use move_model::{
+    ast::{Exp, MemoryLabel, TempIndex, TraceKind},
+    model::{FunId, FunctionEnv, GlobalEnv, Loc, NodeId, QualifiedId, QualifiedInstId, StructId},
+    pragmas::{ADDITION_OVERFLOW_UNCHECKED_PRAGMA, SEED_PRAGMA, TIMEOUT_PRAGMA},
+    ty::{PrimitiveType, Type},
+};
+use move_prover_boogie_backend::{
+    boogie_helpers::boogie_resource_memory_name, options::BoogieOptions,
+};
+use move_stackless_bytecode::{
+    function_target::FunctionTarget,
+    function_target_pipeline::{FunctionTargetsHolder, FunctionVariant},
+    stackless_bytecode::{
+        AbortAction, BorrowEdge, BorrowNode, Bytecode, Constant, HavocKind, IndexEdgeKind, Label,
+        Operation, PropKind,
+    },
+};
+
+use crate::{
+    smt_helpers::{is_atom, max_value, smt_and, smt_seq, Declarations, EXECUTION_FAILURE_CODE},
+    spec_translator::{SpecContext, SpecTranslator},
+};
+
+/// The verification condition of a verified function variant.
+pub struct VerificationCondition {
+    pub fun_id: QualifiedId<FunId>,
+    pub variant: FunctionVariant,
+    /// The location of the function.
+    pub loc: Loc,
+    /// The timeout of each check, in seconds.
+    pub timeout: usize,
+    pub seed: usize,
+    /// The declarations and definitions, ending in the assertion that the root block fails.
+    pub script: String,
+    pub checks: Vec<Check>,
+    pub blocks: Vec<BlockInfo>,
+}
+
+/// An assertion, checked when `$check` equals its index.
+pub struct Check {
+    pub loc: Loc,
+    pub message: String,
+}
+
+/// A block of a verification condition, as needed to reconstruct the execution trace of a
+/// counterexample.
+pub struct BlockInfo {
+    /// The name of the weakest precondition of the block.
+    pub ok: String,
+    /// The names of the guards of the outgoing edges, with the blocks they lead to.
+    pub edges: Vec<(String, usize)>,
+    pub trace: Vec<TraceItem>,
+}
+
+/// An item of an execution trace, with the term of the value to display, if any.
+pub enum TraceItem {
+    Loc(Loc),
+    Temporary(QualifiedId<FunId>, TempIndex, String),
+    Result(QualifiedId<FunId>, usize, String),
+    Abort(QualifiedId<FunId>, String),
+    Exp(NodeId, String),
+    SubExp(NodeId, String),
+    /// The assertion with the given index.
+    Check(usize),
+}
+
+/// Translates the verified variants of all functions into verification conditions.
+pub struct SmtTranslator<'env> {
+    env: &'env GlobalEnv,
+    options: &'env BoogieOptions,
+    targets: &'env FunctionTargetsHolder,
+}
+
+impl<'env> SmtTranslator<'env> {
+    pub fn new(
+        env: &'env GlobalEnv,
+        options: &'env BoogieOptions,
+        targets: &'env FunctionTargetsHolder,
+    ) -> Self {
+        Self {
+            env,
+            options,
+            targets,
+        }
+    }
+
+    /// Returns the verification conditions. Functions using constructs which cannot be
+    /// translated are reported as errors in the environment, and skipped.
+    pub fn translate(&self) -> Vec<VerificationCondition> {
+        info!("generating SMT verification conditions");
+        let mut conditions = vec![];
+        for module_env in self.env.get_modules() {
+            for fun_env in module_env.get_functions() {
+                if fun_env.is_native_or_intrinsic() {
+                    continue;
+                }
+                for (variant, target) in self.targets.get_targets(&fun_env) {
+                    // Instantiations of generic functions are not verified separately.
+                    if variant.is_verified() && target.data.type_args.is_empty() {
+                        let translator = FunctionTranslator::new(self.env, self.targets);
+                        conditions.extend(translator.translate(self.options, &variant, &target));
+                    }
+                }
+            }
+        }
+        conditions
+    }
+}
+
+/// A variable of the translated code. Temporaries, results and abort codes belong to the
+/// frame of the function they are declared in.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Var {
+    Temp(usize, TempIndex),
+    Ret(usize, usize),
+    AbortCode(usize),
+    Memory(QualifiedInstId<StructId>),
+    SavedMemory(MemoryLabel, QualifiedInstId<StructId>),
+}
+
+impl Var {
+    fn frame(&self) -> Option<usize> {
+        match self {
+            Var::Temp(frame, _) | Var::Ret(frame, _) | Var::AbortCode(frame) => Some(*frame),
+            Var::Memory(_) | Var::SavedMemory(..) => None,
+        }
+    }
+}
+
+/// The terms denoting the values of variables at a program point.
+type Values = BTreeMap<Var, String>;
+
+enum Stmt {
+    Assume(String),
+    Assert(usize, String),
+}
+
+#[derive(Default)]
+struct Block {
+    stmts: Vec<Stmt>,
+    /// The conjuncts of the guards of the outgoing edges, with the blocks they lead to.
+    edges: Vec<(Vec<String>, usize)>,
+    trace: Vec<TraceItem>,
+}
+
+/// An edge leaving a block, with the values of variables along it.
+struct Edge {
+    from: usize,
+    guard: Option<String>,
+    values: Values,
+}
+
+/// The basic blocks of a function's code.
+struct CodeBlocks {
+    /// The offsets at which the blocks start; a block ends where the next one starts.
+    starts: Vec<usize>,
+    label_blocks: BTreeMap<Label, usize>,
+}
+
+impl CodeBlocks {
+    fn new(code: &[Bytecode]) -> Self {
+        let mut starts = BTreeSet::new();
+        if !code.is_empty() {
+            starts.insert(0);
+        }
+        for (offset, bytecode) in code.iter().enumerate() {
+            match bytecode {
+                Bytecode::Label(..) => {
+                    starts.insert(offset);
+                }
+                Bytecode::Jump(..)
+                | Bytecode::Branch(..)
+                | Bytecode::Ret(..)
+                | Bytecode::Abort(..)
+                    if offset + 1 < code.len() =>
+                {
+                    starts.insert(offset + 1);
+                }
+                _ => {}
+            }
+        }
+        let starts = starts.into_iter().collect_vec();
+        let label_blocks = starts
+            .iter()
+            .enumerate()
+            .filter_map(|(block, offset)| match &code[*offset] {
+                Bytecode::Label(_, label) => Some((*label, block)),
+                _ => None,
+            })
+            .collect();
+        Self {
+            starts,
+            label_blocks,
+        }
+    }
+
+    /// Returns the code offsets of the given block.
+    fn offsets(&self, block: usize, code_len: usize) -> std::ops::Range<usize> {
+        self.starts[block]..self.starts.get(block + 1).cloned().unwrap_or(code_len)
+    }
+
+    fn successors(&self, block: usize, code: &[Bytecode]) -> Vec<usize> {
+        let offsets = self.offsets(block, code.len());
+        let mut succs = vec![];
+        for bytecode in &code[offsets.clone()] {
+            if let Bytecode::Call(_, _, _, _, Some(AbortAction(label, _))) = bytecode {
+                succs.push(self.label_blocks[label]);
+            }
+        }
+        match &code[offsets.end - 1] {
+            Bytecode::Jump(_, label) => succs.push(self.label_blocks[label]),
+            Bytecode::Branch(_, then_label, else_label, _) => {
+                succs.push(self.label_blocks[then_label]);
+                succs.push(self.label_blocks[else_label]);
+            }
+            Bytecode::Ret(..) | Bytecode::Abort(..) => {}
+            _ if block + 1 < self.starts.len() => succs.push(block + 1),
+            _ => {}
+        }
+        succs
+    }
+
+    /// Returns the blocks in an order in which every block comes after its predecessors, or
+    /// `None` if the code has a loop.
+    fn topological_order(&self, code: &[Bytecode]) -> Option<Vec<usize>> {
+        // 0: not visited, 1: on the stack, 2: done.
+        let mut state = vec![0u8; self.starts.len()];
+        let mut postorder = vec![];
+        if self.starts.is_empty() {
+            return Some(postorder);
+        }
+        let mut stack = vec![(0, self.successors(0, code), 0)];
+        state[0] = 1;
+        while let Some((block, succs, next)) = stack.last_mut() {
+            if let Some(succ) = succs.get(*next).cloned() {
+                *next += 1;
+                match state[succ] {
+                    0 => {
+                        state[succ] = 1;
+                        let succs = self.successors(succ, code);
+                        stack.push((succ, succs, 0));
+                    }
+                    1 => return None,
+                    _ => {}
+                }
+            } else {
+                state[*block] = 2;
+                postorder.push(*block);
+                stack.pop();
+            }
+        }
+        postorder.reverse();
+        Some(postorder)
+    }
+}
+
+/// A function whose code is being translated: the verified function, or an inlined callee.
+struct Frame<'a> {
+    id: usize,
+    target: &'a FunctionTarget<'a>,
+    type_inst: &'a [Type],
+    code_blocks: CodeBlocks,
+    /// The edges into the code blocks which are not translated yet.
+    incoming: BTreeMap<usize, Vec<Edge>>,
+    /// The edges leaving the function by returning.
+    returns: Vec<Edge>,
+    /// The edges leaving the function by aborting.
+    aborts: Vec<Edge>,
+}
+
+impl<'a> Frame<'a> {
+    fn new(id: usize, target: &'a FunctionTarget<'a>, type_inst: &'a [Type]) -> Self {
+        Self {
+            id,
+            target,
+            type_inst,
+            code_blocks: CodeBlocks::new(target.get_bytecode()),
+            incoming: BTreeMap::new(),
+            returns: vec![],
+            aborts: vec![],
+        }
+    }
+
+    fn temp(&self, idx: TempIndex) -> Var {
+        Var::Temp(self.id, idx)
+    }
+
+    fn local_type(&self, idx: TempIndex) -> Type {
+        self.target.get_local_type(idx).instantiate(self.type_inst)
+    }
+
+    fn fun_id(&self) -> QualifiedId<FunId> {
+        self.target.func_env.get_qualified_id()
+    }
+}
+
+struct FunctionTranslator<'env> {
+    env: &'env GlobalEnv,
+    targets: &'env FunctionTargetsHolder,
+    decls: Declarations<'env>,
+    blocks: Vec<Block>,
+    checks: Vec<Check>,
+    /// The names and sorts of the variables.
+    vars: BTreeMap<Var, (String, String)>,
+    /// The block being translated and the current values of variables, or `None` if the code
+    /// being translated is unreachable.
+    current: Option<(usize, Values)>,
+    /// The functions being translated, to detect recursion.
+    call_stack: Vec<QualifiedId<FunId>>,
+    frame_count: usize,
+    /// The locations of borrowed locals.
+    local_locs: BTreeMap<Var, usize>,
+}
+
+impl<'env> FunctionTranslator<'env> {
+    fn new(env: &'env GlobalEnv, targets: &'env FunctionTargetsHolder) -> Self {
+        Self {
+            env,
+            targets,
+            decls: Declarations::new(env),
+            blocks: vec![],
+            checks: vec![],
+            vars: BTreeMap::new(),
+            current: None,
+            call_stack: vec![],
+            frame_count: 0,
+            local_locs: BTreeMap::new(),
+        }
+    }
+
+    fn translate(
+        mut self,
+        options: &BoogieOptions,
+        variant: &FunctionVariant,
+        target: &FunctionTarget<'_>,
+    ) -> Option<VerificationCondition> {
+        let fun_env = target.func_env;
+        self.decls.set_loc(fun_env.get_loc());
+
+        // The root block leads to the entry of the function.
+        self.blocks.push(Block::default());
+        self.current = Some((0, Values::new()));
+        let entry = self.leave(None);
+        self.current = None;
+        let frame_id = self.new_frame(target, &[]);
+        let mut frame = Frame::new(frame_id, target, &[]);
+        frame.incoming.insert(0, vec![entry]);
+        self.call_stack.push(fun_env.get_qualified_id());
+        self.translate_frame(&mut frame);
+        if self.decls.has_failed() {
+            return None;
+        }
+
+        let mut definitions = String::new();
+        let mut infos = vec![];
+        for (idx, block) in self.blocks.iter_mut().enumerate() {
+            let mut edges = vec![];
+            for (k, (guard, to)) in block.edges.iter().enumerate() {
+                let name = format!("$g_{}_{}", idx, k);
+                definitions.push_str(&format!(
+                    "(define-fun {} () Bool {})\n",
+                    name,
+                    smt_and(guard.clone())
+                ));
+                edges.push((name, *to));
+            }
+            infos.push(BlockInfo {
+                ok: format!("$ok_{}", idx),
+                edges,
+                trace: std::mem::take(&mut block.trace),
+            });
+        }
+        for (block, info) in self.blocks.iter().zip(&infos).rev() {
+            definitions.push_str(&format!(
+                "(define-fun {} () Bool {})\n",
+                info.ok,
+                Self::weakest_precondition(block, info, &infos)
+            ));
+        }
+        definitions.push_str("(assert (not $ok_0))\n");
+
+        Some(VerificationCondition {
+            fun_id: fun_env.get_qualified_id(),
+            variant: variant.clone(),
+            loc: fun_env.get_loc(),
+            timeout: fun_env.get_num_pragma(TIMEOUT_PRAGMA, || options.vc_timeout),
+            seed: fun_env.get_num_pragma(SEED_PRAGMA, || options.random_seed),
+            script: format!("{}{}", self.decls.render(), definitions),
+            checks: self.checks,
+            blocks: infos,
+        })
+    }
+
+    fn weakest_precondition(block: &Block, info: &BlockInfo, infos: &[BlockInfo]) -> String {
+        let succs = info
+            .edges
+            .iter()
+            .map(|(guard, to)| format!("(=> {} {})", guard, infos[*to].ok))
+            .collect_vec();
+        let mut wp = smt_and(succs);
+        for stmt in block.stmts.iter().rev() {
+            wp = match stmt {
+                Stmt::Assume(e) => format!("(=> {} {})", e, wp),
+                Stmt::Assert(idx, e) => {
+                    format!("(and (=> (= $check {}) {}) (=> {} {}))", idx, e, e, wp)
+                }
+            };
+        }
+        wp
+    }
+
+    /// Declares the variables of a new frame for the given function, and returns its id.
+    fn new_frame(&mut self, target: &FunctionTarget<'_>, type_inst: &[Type]) -> usize {
+        let id = self.frame_count;
+        self.frame_count += 1;
+        let suffix = if id == 0 {
+            String::new()
+        } else {
+            format!("#{}", id)
+        };
+        for idx in 0..target.get_local_count() {
+            let sort = self
+                .decls
+                .sort(&target.get_local_type(idx).instantiate(type_inst));
+            self.vars
+                .insert(Var::Temp(id, idx), (format!("$t{}{}", idx, suffix), sort));
+        }
+        for idx in 0..target.get_return_count() {
+            let sort = self
+                .decls
+                .sort(&target.get_return_type(idx).instantiate(type_inst));
+            self.vars
+                .insert(Var::Ret(id, idx), (format!("$ret{}{}", idx, suffix), sort));
+        }
+        self.vars.insert(
+            Var::AbortCode(id),
+            (format!("$abort_code{}", suffix), "Int".to_string()),
+        );
+        id
+    }
+
+    /// Declares the variable of the given memory, and returns it.
+    fn memory_var(
+        &mut self,
+        memory: &QualifiedInstId<StructId>,
+        label: Option<MemoryLabel>,
+    ) -> Var {
+        let var = match label {
+            Some(label) => Var::SavedMemory(label, memory.clone()),
+            None => Var::Memory(memory.clone()),
+        };
+        if !self.vars.contains_key(&var) {
+            let name = boogie_resource_memory_name(self.env, memory, &label);
+            let sort = self.decls.memory_sort(memory);
+            self.vars.insert(var.clone(), (name, sort));
+        }
+        var
+    }
+
+    fn translate_frame(&mut self, frame: &mut Frame<'_>) {
+        let target = frame.target;
+        let code = target.get_bytecode();
+        let order = match frame.code_blocks.topological_order(code) {
+            Some(order) => order,
+            None => {
+                self.decls.set_loc(target.get_loc());
+                self.decls.unsupported("loop in an inlined function");
+                return;
+            }
+        };
+        for block in order {
+            let edges = frame.incoming.remove(&block).unwrap_or_default();
+            if !self.enter(edges) {
+                continue;
+            }
+            for offset in frame.code_blocks.offsets(block, code.len()) {
+                if self.current.is_none() || self.decls.has_failed() {
+                    break;
+                }
+                self.translate_bytecode(frame, &code[offset]);
+            }
+            if self.current.is_some() && block + 1 < frame.code_blocks.starts.len() {
+                let edge = self.leave(None);
+                frame.incoming.entry(block + 1).or_default().push(edge);
+                self.current = None;
+            }
+        }
+    }
+
+    // ------------------------------------------------------------------------------------------
+    // Blocks and Values
+
+    /// Returns an edge leaving the current block under the given guard.
+    fn leave(&self, guard: Option<String>) -> Edge {
+        let (from, values) = self.current.as_ref().expect("reachable code");
+        Edge {
+            from: *from,
+            guard,
+            values: values.clone(),
+        }
+    }
+
+    /// Starts a new block reached by the given edges, joining the values along them. Returns
+    /// false if there are no edges, in which case the code is unreachable.
+    fn enter(&mut self, mut edges: Vec<Edge>) -> bool {
+        if edges.is_empty() {
+            self.current = None;
+            return false;
+        }
+        let block = self.blocks.len();
+        self.blocks.push(Block::default());
+        let mut joins = vec![vec![]; edges.len()];
+        let values = if edges.len() == 1 {
+            std::mem::take(&mut edges[0].values)
+        } else {
+            let vars: BTreeSet<Var> = edges
+                .iter()
+                .flat_map(|edge| edge.values.keys().cloned())
+                .collect();
+            let mut values = Values::new();
+            for var in vars {
+                let terms = edges.iter().map(|edge| edge.values.get(&var)).collect_vec();
+                if terms[0].is_some() && terms.iter().all(|term| *term == terms[0]) {
+                    values.insert(var, terms[0].unwrap().clone());
+                } else {
+                    let joined = self.fresh(&var);
+                    for (join, term) in joins.iter_mut().zip(terms) {
+                        if let Some(term) = term {
+                            join.push(format!("(= {} {})", joined, term));
+                        }
+                    }
+                    values.insert(var, joined);
+                }
+            }
+            values
+        };
+        for (edge, join) in edges.into_iter().zip(joins) {
+            let mut guard = edge.guard.into_iter().collect_vec();
+            guard.extend(join);
+            self.blocks[edge.from].edges.push((guard, block));
+        }
+        self.current = Some((block, values));
+        true
+    }
+
+    fn add_stmt(&mut self, stmt: Stmt) {
+        if let Some((block, _)) = &self.current {
+            self.blocks[*block].stmts.push(stmt);
+        }
+    }
+
+    fn add_trace(&mut self, item: TraceItem) {
+        if let Some((block, _)) = &self.current {
+            let trace = &mut self.blocks[*block].trace;
+            if let (TraceItem::Loc(loc), Some(TraceItem::Loc(last))) = (&item, trace.last()) {
+                if loc == last {
+                    return;
+                }
+            }
+            trace.push(item);
+        }
+    }
+
+    fn fresh(&mut self, var: &Var) -> String {
+        let (name, sort) = self.vars.get(var).cloned().expect("declared variable");
+        self.decls.fresh_const(&name, &sort)
+    }
+
+    /// Returns the current value of the variable, which is arbitrary if it is not assigned.
+    fn read(&mut self, var: &Var) -> String {
+        if let Some((_, values)) = &self.current {
+            if let Some(term) = values.get(var) {
+                return term.clone();
+            }
+        }
+        let term = self.fresh(var);
+        self.write(var.clone(), term.clone());
+        term
+    }
+
+    fn write(&mut self, var: Var, term: String) {
+        if let Some((_, values)) = &mut self.current {
+            values.insert(var, term);
+        }
+    }
+
+    /// Assigns the term to the variable, binding it to a fresh constant unless it is atomic.
+    fn assign(&mut self, var: Var, term: String) {
+        if is_atom(&term) {
+            self.write(var, term);
+        } else {
+            let value = self.fresh(&var);
+            self.add_stmt(Stmt::Assume(format!("(= {} {})", value, term)));
+            self.write(var, value);
+        }
+    }
+
+    /// Reads a temporary, dereferencing it if it is a mutable reference.
+    fn read_value(&mut self, frame: &Frame<'_>, idx: TempIndex) -> String {
+        let term = self.read(&frame.temp(idx));
+        if frame.target.get_local_type(idx).is_mutable_reference() {
+            format!("($mut_val {})", term)
+        } else {
+            term
+        }
+    }
+
+    /// Routes the edge to the abort action, or out of the function if there is none.
+    fn abort(
+        &mut self,
+        frame: &mut Frame<'_>,
+        mut edge: Edge,
+        aa: &Option<AbortAction>,
+        code: String,
+    ) {
+        match aa {
+            Some(AbortAction(label, temp)) => {
+                edge.values.insert(frame.temp(*temp), code);
+                let block = frame.code_blocks.label_blocks[label];
+                frame.incoming.entry(block).or_default().push(edge);
+            }
+            None => {
+                edge.values.insert(Var::AbortCode(frame.id), code);
+                frame.aborts.push(edge);
+            }
+        }
+    }
+
+    /// Aborts with an execution failure if the condition holds, and continues otherwise.
+    fn abort_if(&mut self, frame: &mut Frame<'_>, cond: String, aa: &Option<AbortAction>) {
+        let edge = self.leave(Some(cond.clone()));
+        self.abort(frame, edge, aa, EXECUTION_FAILURE_CODE.to_string());
+        let edge = self.leave(Some(format!("(not {})", cond)));
+        self.enter(vec![edge]);
+    }
+
+    fn jump(&mut self, frame: &mut Frame<'_>, label: Label, guard: Option<String>) {
+        let edge = self.leave(guard);
+        let block = frame.code_blocks.label_blocks[&label];
+        frame.incoming.entry(block).or_default().push(edge);
+    }
+
+    fn translate_exp(&mut self, frame: &Frame<'_>, exp: &Exp) -> String {
+        let env = self.env;
+        let mut ctx = FrameContext {
+            translator: self,
+            frame_id: frame.id,
+            target: frame.target,
+        };
+        SpecTranslator::new(env, &mut ctx, frame.type_inst).translate(exp)
+    }
+
+    // ------------------------------------------------------------------------------------------
+    // Bytecode
+
+    fn translate_bytecode(&mut self, frame: &mut Frame<'_>, bytecode: &Bytecode) {
+        use Bytecode::*;
+        let target = frame.target;
+        let loc = target.get_bytecode_loc(bytecode.get_attr_id());
+        self.decls.set_loc(loc.clone());
+        self.add_trace(TraceItem::Loc(loc.clone()));
+        match bytecode {
+            Assign(_, dest, src, _) => {
+                let value = self.read(&frame.temp(*src));
+                self.write(frame.temp(*dest), value);
+            }
+            Load(_, dest, constant) => {
+                let ty = frame.local_type(*dest);
+                let value = self.translate_constant(constant, &ty);
+                self.assign(frame.temp(*dest), value);
+            }
+            Label(..) | Nop(..) => {}
+            Jump(_, label) => {
+                self.jump(frame, *label, None);
+                self.current = None;
+            }
+            Branch(_, then_label, else_label, cond) => {
+                let cond = self.read(&frame.temp(*cond));
+                self.jump(frame, *then_label, Some(cond.clone()));
+                self.jump(frame, *else_label, Some(format!("(not {})", cond)));
+                self.current = None;
+            }
+            Ret(_, srcs) => {
+                for (idx, src) in srcs.iter().enumerate() {
+                    let value = self.read(&frame.temp(*src));
+                    self.write(Var::Ret(frame.id, idx), value);
+                }
+                let edge = self.leave(None);
+                frame.returns.push(edge);
+                self.current = None;
+            }
+            Abort(_, code) => {
+                let code = self.read(&frame.temp(*code));
+                let edge = self.leave(None);
+                self.abort(frame, edge, &None, code);
+                self.current = None;
+            }
+            SaveMem(_, label, memory) => {
+                let memory = memory.instantiate_ref(frame.type_inst);
+                let var = self.memory_var(&memory, None);
+                let value = self.read(&var);
+                let saved = self.memory_var(&memory, Some(*label));
+                self.write(saved, value);
+            }
+            SaveSpecVar(..) => self.decls.unsupported("global specification variable"),
+            Prop(attr_id, kind, exp) => match kind {
+                PropKind::Assume => {
+                    let cond = self.translate_exp(frame, exp);
+                    self.add_stmt(Stmt::Assume(cond));
+                }
+                PropKind::Assert => {
+                    let cond = self.translate_exp(frame, exp);
+                    let idx = self.checks.len();
+                    self.checks.push(Check {
+                        loc,
+                        message: target
+                            .get_vc_info(*attr_id)
+                            .cloned()
+                            .unwrap_or_else(|| "unknown assertion failed".to_string()),
+                    });
+                    self.add_trace(TraceItem::Check(idx));
+                    self.add_stmt(Stmt::Assert(idx, cond));
+                }
+                PropKind::Modifies => {
+                    let ty = self
+                        .env
+                        .get_node_type(exp.node_id())
+                        .instantiate(frame.type_inst);
+                    let (mid, sid, inst) = ty.require_struct();
+                    let memory = mid.qualified_inst(sid, inst.to_vec());
+                    let addr = self.translate_exp(frame, &exp.call_args()[0]);
+                    let sort = self.decls.struct_name(mid, sid, inst);
+                    let value = self.decls.fresh_const("$havoc", &sort);
+                    let exists = self.decls.fresh_const("$havoc", "Bool");
+                    if let Some(wf) = self.decls.well_formed(&ty, &value) {
+                        self.add_stmt(Stmt::Assume(wf));
+                    }
+                    let var = self.memory_var(&memory, None);
+                    let mem = self.read(&var);
+                    self.assign(
+                        var,
+                        format!(
+                            "($memory (store ($domain {0}) {1} {2}) (store ($contents {0}) {1} {3}))",
+                            mem, addr, exists, value
+                        ),
+                    );
+                }
+            },
+            Call(_, dests, oper, srcs, aa) => self.translate_call(frame, dests, oper, srcs, aa),
+        }
+    }
+
+    fn translate_constant(&mut self, constant: &Constant, ty: &Type) -> String {
+        let elem_ty = match ty {
+            Type::Vector(elem) => elem.as_ref().clone(),
+            _ => Type::Primitive(PrimitiveType::U8),
+        };
+        match constant {
+            Constant::Bool(b) => b.to_string(),
+            Constant::U8(n) => n.to_string(),
+            Constant::U16(n) => n.to_string(),
+            Constant::U32(n) => n.to_string(),
+            Constant::U64(n) => n.to_string(),
+            Constant::U128(n) => n.to_string(),
+            Constant::U256(n) => n.to_string(),
+            Constant::Address(a) => a.to_string(),
+            Constant::ByteArray(bytes) => {
+                smt_seq(bytes.iter().map(|b| b.to_string()).collect(), "Int")
+            }
+            Constant::AddressArray(addrs) => {
+                smt_seq(addrs.iter().map(|a| a.to_string()).collect(), "Int")
+            }
+            Constant::Vector(elems) => {
+                let sort = self.decls.sort(&elem_ty);
+                let elems = elems
+                    .iter()
+                    .map(|c| self.translate_constant(c, &elem_ty))
+                    .collect();
+                smt_seq(elems, &sort)
+            }
+        }
+    }
+
+    fn translate_call(
+        &mut self,
+        frame: &mut Frame<'_>,
+        dests: &[TempIndex],
+        oper: &Operation,
+        srcs: &[TempIndex],
+        aa: &Option<AbortAction>,
+    ) {
+        use Operation::*;
+        let type_inst = frame.type_inst;
+        let inst = |types: &[Type]| {
+            types
+                .iter()
+                .map(|ty| ty.instantiate(type_inst))
+                .collect_vec()
+        };
+        match oper {
+            Function(mid, fid, type_args) => {
+                let callee_env = self.env.get_function(mid.qualified(*fid));
+                let type_args = inst(type_args);
+                if callee_env.is_native_or_intrinsic() {
+                    self.translate_native(frame, &callee_env, &type_args, dests, srcs, aa);
+                } else {
+                    self.inline_call(frame, &callee_env, &type_args, dests, srcs, aa);
+                }
+            }
+            OpaqueCallBegin(..) | OpaqueCallEnd(..) | Uninit | Destroy | UnpackRef | PackRef
+            | UnpackRefDeep | PackRefDeep | TraceGlobalMem(_) => {}
+            Pack(mid, sid, type_args) => {
+                let ctor = self.decls.struct_name(*mid, *sid, &inst(type_args));
+                let fields = srcs
+                    .iter()
+                    .map(|src| self.read(&frame.temp(*src)))
+                    .collect_vec();
+                let value = if fields.is_empty() {
+                    ctor
+                } else {
+                    format!("({} {})", ctor, fields.join(" "))
+                };
+                self.assign(frame.temp(dests[0]), value);
+            }
+            Unpack(mid, sid, type_args) => {
+                let selectors = self.decls.field_selectors(*mid, *sid, &inst(type_args));
+                let value = self.read(&frame.temp(srcs[0]));
+                for (dest, sel) in dests.iter().zip(selectors) {
+                    self.assign(frame.temp(*dest), format!("({} {})", sel, value));
+                }
+            }
+            GetField(mid, sid, type_args, offset) => {
+                let sel = self.decls.field_selectors(*mid, *sid, &inst(type_args))[*offset].clone();
+                let value = self.read_value(frame, srcs[0]);
+                self.assign(frame.temp(dests[0]), format!("({} {})", sel, value));
+            }
+            BorrowField(mid, sid, type_args, offset) => {
+                let sel = self.decls.field_selectors(*mid, *sid, &inst(type_args))[*offset].clone();
+                let r = self.read(&frame.temp(srcs[0]));
+                self.assign(
+                    frame.temp(dests[0]),
+                    format!(
+                        "($mutation ({} ($mut_val {})) ($mut_loc {}) (seq.++ ($mut_path {}) (seq.unit {})))",
+                        sel, r, r, r, offset
+                    ),
+                );
+            }
+            BorrowLoc => {
+                let value = self.read(&frame.temp(srcs[0]));
+                let next = self.local_locs.len() + 1;
+                let loc = *self.local_locs.entry(frame.temp(srcs[0])).or_insert(next);
+                self.assign(
+                    frame.temp(dests[0]),
+                    format!("($mutation {} (- {}) (as seq.empty (Seq Int)))", value, loc),
+                );
+            }
+            BorrowGlobal(mid, sid, type_args)
+            | GetGlobal(mid, sid, type_args)
+            | MoveFrom(mid, sid, type_args) => {
+                let memory = mid.qualified_inst(*sid, inst(type_args));
+                let var = self.memory_var(&memory, None);
+                let mem = self.read(&var);
+                let addr = self.read(&frame.temp(srcs[0]));
+                self.abort_if(
+                    frame,
+                    format!("(not (select ($domain {}) {}))", mem, addr),
+                    aa,
+                );
+                let value = format!("(select ($contents {}) {})", mem, addr);
+                match oper {
+                    BorrowGlobal(..) => self.assign(
+                        frame.temp(dests[0]),
+                        format!("($mutation {} {} (as seq.empty (Seq Int)))", value, addr),
+                    ),
+                    GetGlobal(..) => self.assign(frame.temp(dests[0]), value),
+                    _ => {
+                        self.assign(frame.temp(dests[0]), value);
+                        self.assign(
+                            var,
+                            format!(
+                                "($memory (store ($domain {0}) {1} false) ($contents {0}))",
+                                mem, addr
+                            ),
+                        );
+                    }
+                }
+            }
+            MoveTo(mid, sid, type_args) => {
+                let memory = mid.qualified_inst(*sid, inst(type_args));
+                let var = self.memory_var(&memory, None);
+                let mem = self.read(&var);
+                let value = self.read(&frame.temp(srcs[0]));
+                let addr = format!("($addr {})", self.read(&frame.temp(srcs[1])));
+                self.abort_if(frame, format!("(select ($domain {}) {})", mem, addr), aa);
+                self.assign(
+                    var,
+                    format!(
+                        "($memory (store ($domain {0}) {1} true) (store ($contents {0}) {1} {2}))",
+                        mem, addr, value
+                    ),
+                );
+            }
+            Exists(mid, sid, type_args) => {
+                let memory = mid.qualified_inst(*sid, inst(type_args));
+                let var = self.memory_var(&memory, None);
+                let mem = self.read(&var);
+                let addr = self.read(&frame.temp(srcs[0]));
+                self.assign(
+                    frame.temp(dests[0]),
+                    format!("(select ($domain {}) {})", mem, addr),
+                );
+            }
+            ReadRef | FreezeRef => {
+                let value = self.read_value(frame, srcs[0]);
+                self.assign(frame.temp(dests[0]), value);
+            }
+            WriteRef => {
+                let r = self.read(&frame.temp(srcs[0]));
+                let value = self.read(&frame.temp(srcs[1]));
+                self.assign(
+                    frame.temp(srcs[0]),
+                    format!("($mutation {} ($mut_loc {}) ($mut_path {}))", value, r, r),
+                );
+            }
+            Havoc(HavocKind::Value) | Havoc(HavocKind::MutationAll) => {
+                let value = self.fresh(&frame.temp(dests[0]));
+                self.write(frame.temp(dests[0]), value);
+            }
+            Havoc(HavocKind::MutationValue) => {
+                let r = self.read(&frame.temp(dests[0]));
+                let ty = frame.local_type(dests[0]).skip_reference().clone();
+                let sort = self.decls.sort(&ty);
+                let value = self.decls.fresh_const("$havoc", &sort);
+                if let Some(wf) = self.decls.well_formed(&ty, &value) {
+                    self.add_stmt(Stmt::Assume(wf));
+                }
+                self.assign(
+                    frame.temp(dests[0]),
+                    format!("($mutation {} ($mut_loc {}) ($mut_path {}))", value, r, r),
+                );
+            }
+            Stop => self.current = None,
+            IsParent(BorrowNode::Reference(parent), edge) => {
+                let p = self.read(&frame.temp(*parent));
+                let c = self.read(&frame.temp(srcs[0]));
+                let mut conds = vec![format!("(= ($mut_loc {}) ($mut_loc {}))", p, c)];
+                let steps = edge
+                    .flatten()
+                    .into_iter()
+                    .filter(|e| !matches!(e, BorrowEdge::Direct))
+                    .collect_vec();
+                if steps.is_empty() {
+                    conds.push(format!("(= ($mut_path {}) ($mut_path {}))", p, c));
+                } else {
+                    let len = format!("(seq.len ($mut_path {}))", p);
+                    conds.push(format!(
+                        "(= (seq.len ($mut_path {})) (+ {} {}))",
+                        c,
+                        len,
+                        steps.len()
+                    ));
+                    conds.push(format!(
+                        "(= (seq.extract ($mut_path {}) 0 {}) ($mut_path {}))",
+                        c, len, p
+                    ));
+                    for (pos, step) in steps.iter().enumerate() {
+                        if let BorrowEdge::Field(_, offset) = step {
+                            conds.push(format!(
+                                "(= (seq.nth ($mut_path {}) (+ {} {})) {})",
+                                c, len, pos, offset
+                            ));
+                        }
+                    }
+                }
+                self.assign(frame.temp(dests[0]), smt_and(conds));
+            }
+            IsParent(..) => self.decls.unsupported("parent check of a non-reference"),
+            WriteBack(node, edge) => self.translate_write_back(frame, node, edge, srcs[0]),
+            CastU8 | CastU16 | CastU32 | CastU64 | CastU128 | CastU256 => {
+                let ty = frame.local_type(dests[0]);
+                let value = self.read(&frame.temp(srcs[0]));
+                if let Some(max) = max_value(&ty) {
+                    self.abort_if(frame, format!("(> {} {})", value, max), aa);
+                }
+                self.assign(frame.temp(dests[0]), value);
+            }
+            Not => {
+                let value = self.read(&frame.temp(srcs[0]));
+                self.assign(frame.temp(dests[0]), format!("(not {})", value));
+            }
+            Add | Sub | Mul | Div | Mod => {
+                let a = self.read(&frame.temp(srcs[0]));
+                let b = self.read(&frame.temp(srcs[1]));
+                let max = max_value(&frame.local_type(dests[0]));
+                let value = match oper {
+                    Add => {
+                        let value = format!("(+ {} {})", a, b);
+                        let unchecked = frame
+                            .target
+                            .func_env
+                            .is_pragma_true(ADDITION_OVERFLOW_UNCHECKED_PRAGMA, || false);
+                        if let (Some(max), false) = (max, unchecked) {
+                            self.abort_if(frame, format!("(> {} {})", value, max), aa);
+                        }
+                        value
+                    }
+                    Sub => {
+                        self.abort_if(frame, format!("(< {} {})", a, b), aa);
+                        format!("(- {} {})", a, b)
+                    }
+                    Mul => {
+                        let value = format!("(* {} {})", a, b);
+                        if let Some(max) = max {
+                            self.abort_if(frame, format!("(> {} {})", value, max), aa);
+                        }
+                        value
+                    }
+                    _ => {
+                        self.abort_if(frame, format!("(= {} 0)", b), aa);
+                        let op = if matches!(oper, Div) { "div" } else { "mod" };
+                        format!("({} {} {})", op, a, b)
+                    }
+                };
+                self.assign(frame.temp(dests[0]), value);
+            }
+            Lt | Gt | Le | Ge | Or | And | Eq | Neq => {
+                let a = self.read_value(frame, srcs[0]);
+                let b = self.read_value(frame, srcs[1]);
+                let value = match oper {
+                    Lt => format!("(< {} {})", a, b),
+                    Gt => format!("(> {} {})", a, b),
+                    Le => format!("(<= {} {})", a, b),
+                    Ge => format!("(>= {} {})", a, b),
+                    Or => format!("(or {} {})", a, b),
+                    And => format!("(and {} {})", a, b),
+                    Eq => format!("(= {} {})", a, b),
+                    _ => format!("(not (= {} {}))", a, b),
+                };
+                self.assign(frame.temp(dests[0]), value);
+            }
+            BitOr | BitAnd | Xor | Shl | Shr => self.decls.unsupported("bitwise operation"),
+            TraceLocal(idx) => {
+                let value = self.read_value(frame, srcs[0]);
+                self.add_trace(TraceItem::Temporary(frame.fun_id(), *idx, value));
+            }
+            TraceReturn(idx) => {
+                let value = self.read_value(frame, srcs[0]);
+                self.add_trace(TraceItem::Result(frame.fun_id(), *idx, value));
+            }
+            TraceAbort => {
+                let value = self.read(&frame.temp(srcs[0]));
+                self.add_trace(TraceItem::Abort(frame.fun_id(), value));
+            }
+            TraceExp(kind, node_id) => {
+                let value = self.read_value(frame, srcs[0]);
+                self.add_trace(match kind {
+                    TraceKind::SubAuto => TraceItem::SubExp(*node_id, value),
+                    _ => TraceItem::Exp(*node_id, value),
+                });
+            }
+            EmitEvent | EventStoreDiverge => self.decls.unsupported("event"),
+        }
+    }
+
+    fn translate_write_back(
+        &mut self,
+        frame: &mut Frame<'_>,
+        node: &BorrowNode,
+        edge: &BorrowEdge,
+        src: TempIndex,
+    ) {
+        let c = self.read(&frame.temp(src));
+        match node {
+            BorrowNode::GlobalRoot(memory) => {
+                let memory = memory.instantiate_ref(frame.type_inst);
+                let var = self.memory_var(&memory, None);
+                let mem = self.read(&var);
+                self.assign(
+                    var,
+                    format!(
+                        "($memory (store ($domain {0}) ($mut_loc {1}) true) (store ($contents {0}) ($mut_loc {1}) ($mut_val {1})))",
+                        mem, c
+                    ),
+                );
+            }
+            BorrowNode::LocalRoot(idx) => {
+                self.assign(frame.temp(*idx), format!("($mut_val {})", c));
+            }
+            BorrowNode::Reference(idx) => {
+                let p = self.read(&frame.temp(*idx));
+                let edge = edge.instantiate(frame.type_inst);
+                let steps = edge
+                    .flatten()
+                    .into_iter()
+                    .filter(|e| !matches!(e, BorrowEdge::Direct))
+                    .cloned()
+                    .collect_vec();
+                let path_index = |pos: usize| {
+                    format!(
+                        "(seq.nth ($mut_path {}) (+ (seq.len ($mut_path {})) {}))",
+                        c, p, pos
+                    )
+                };
+                let value = self.update_path(
+                    format!("($mut_val {})", p),
+                    &steps,
+                    0,
+                    &path_index,
+                    format!("($mut_val {})", c),
+                );
+                self.assign(
+                    frame.temp(*idx),
+                    format!("($mutation {} ($mut_loc {}) ($mut_path {}))", value, p, p),
+                );
+            }
+            BorrowNode::ReturnPlaceholder(_) => {
+                self.decls.unsupported("write back to a return placeholder")
+            }
+        }
+    }
+
+    /// Returns the value `dest` with the part at the path given by the borrow steps from `pos`
+    /// on replaced by `src`.
+    fn update_path(
+        &mut self,
+        dest: String,
+        steps: &[BorrowEdge],
+        pos: usize,
+        path_index: &dyn Fn(usize) -> String,
+        src: String,
+    ) -> String {
+        let step = match steps.get(pos) {
+            Some(step) => step,
+            None => return src,
+        };
+        match step {
+            BorrowEdge::Field(memory, offset) => {
+                let ctor = self
+                    .decls
+                    .struct_name(memory.module_id, memory.id, &memory.inst);
+                let selectors =
+                    self.decls
+                        .field_selectors(memory.module_id, memory.id, &memory.inst);
+                let inner = self.update_path(
+                    format!("({} {})", selectors[*offset], dest),
+                    steps,
+                    pos + 1,
+                    path_index,
+                    src,
+                );
+                let fields = selectors
+                    .iter()
+                    .enumerate()
+                    .map(|(i, sel)| {
+                        if i == *offset {
+                            inner.clone()
+                        } else {
+                            format!("({} {})", sel, dest)
+                        }
+                    })
+                    .join(" ");
+                format!("({} {})", ctor, fields)
+            }
+            BorrowEdge::Index(IndexEdgeKind::Vector) => {
+                let idx = path_index(pos);
+                let inner = self.update_path(
+                    format!("(seq.nth {} {})", dest, idx),
+                    steps,
+                    pos + 1,
+                    path_index,
+                    src,
+                );
+                format!("(seq.update {} {} (seq.unit {}))", dest, idx, inner)
+            }
+            _ => {
+                self.decls.unsupported("table or custom index borrow");
+                src
+            }
+        }
+    }
+
+    // ------------------------------------------------------------------------------------------
+    // Calls
+
+    fn inline_call(
+        &mut self,
+        frame: &mut Frame<'_>,
+        callee_env: &FunctionEnv<'_>,
+        type_args: &[Type],
+        dests: &[TempIndex],
+        srcs: &[TempIndex],
+        aa: &Option<AbortAction>,
+    ) {
+        let callee = callee_env.get_qualified_id();
+        if self.call_stack.contains(&callee) {
+            self.decls.unsupported(&format!(
+                "recursive call of `{}`",
+                callee_env.get_full_name_str()
+            ));
+            return;
+        }
+        let target = self
+            .targets
+            .get_target(callee_env, &FunctionVariant::Baseline);
+        if target.get_bytecode().is_empty() {
+            self.decls.unsupported(&format!(
+                "call of `{}`, which is neither verified nor opaque,",
+                callee_env.get_full_name_str()
+            ));
+            return;
+        }
+        let args = srcs
+            .iter()
+            .map(|src| self.read(&frame.temp(*src)))
+            .collect_vec();
+        let callee_id = self.new_frame(&target, type_args);
+        let mut entry = self.leave(None);
+        self.current = None;
+        for (idx, arg) in args.into_iter().enumerate() {
+            entry.values.insert(Var::Temp(callee_id, idx), arg);
+        }
+        let mut callee_frame = Frame::new(callee_id, &target, type_args);
+        callee_frame.incoming.insert(0, vec![entry]);
+        self.call_stack.push(callee);
+        self.translate_frame(&mut callee_frame);
+        self.call_stack.pop();
+
+        let in_callee = |var: &Var, _: &mut String| var.frame() != Some(callee_id);
+        for mut edge in callee_frame.aborts {
+            let code = edge
+                .values
+                .get(&Var::AbortCode(callee_id))
+                .cloned()
+                .unwrap_or_else(|| EXECUTION_FAILURE_CODE.to_string());
+            edge.values.retain(in_callee);
+            self.abort(frame, edge, aa, code);
+        }
+        let mut_params = (0..target.get_parameter_count())
+            .filter(|idx| target.get_local_type(*idx).is_mutable_reference())
+            .collect_vec();
+        let returns = callee_frame
+            .returns
+            .into_iter()
+            .map(|mut edge| {
+                let mut updates = vec![];
+                for (idx, dest) in dests.iter().enumerate() {
+                    if let Some(value) = edge.values.get(&Var::Ret(callee_id, idx)) {
+                        updates.push((frame.temp(*dest), value.clone()));
+                    }
+                }
+                for idx in &mut_params {
+                    if let Some(value) = edge.values.get(&Var::Temp(callee_id, *idx)) {
+                        updates.push((frame.temp(srcs[*idx]), value.clone()));
+                    }
+                }
+                edge.values.retain(in_callee);
+                edge.values.extend(updates);
+                edge
+            })
+            .collect_vec();
+        self.enter(returns);
+    }
+
+    fn translate_native(
+        &mut self,
+        frame: &mut Frame<'_>,
+        callee_env: &FunctionEnv<'_>,
+        type_args: &[Type],
+        dests: &[TempIndex],
+        srcs: &[TempIndex],
+        aa: &Option<AbortAction>,
+    ) {
+        let module_env = &callee_env.module_env;
+        let name = callee_env.get_name_str();
+        let is_signer = module_env.get_name().addr() == &self.env.get_stdlib_address()
+            && module_env.get_name().name() == self.env.symbol_pool().make("signer");
+        let update = |r: &str, value: String| {
+            format!("($mutation {} ($mut_loc {}) ($mut_path {}))", value, r, r)
+        };
+        match (module_env.is_std_vector(), name.as_str()) {
+            (true, "empty") => {
+                let sort = self.decls.sort(&type_args[0]);
+                self.assign(
+                    frame.temp(dests[0]),
+                    format!("(as seq.empty (Seq {}))", sort),
+                );
+            }
+            (true, "length") => {
+                let v = self.read_value(frame, srcs[0]);
+                self.assign(frame.temp(dests[0]), format!("(seq.len {})", v));
+            }
+            (true, "borrow") => {
+                let v = self.read_value(frame, srcs[0]);
+                let i = self.read(&frame.temp(srcs[1]));
+                self.abort_if(frame, format!("(>= {} (seq.len {}))", i, v), aa);
+                self.assign(frame.temp(dests[0]), format!("(seq.nth {} {})", v, i));
+            }
+            (true, "borrow_mut") => {
+                let r = self.read(&frame.temp(srcs[0]));
+                let i = self.read(&frame.temp(srcs[1]));
+                self.abort_if(frame, format!("(>= {} (seq.len ($mut_val {})))", i, r), aa);
+                self.assign(
+                    frame.temp(dests[0]),
+                    format!(
+                        "($mutation (seq.nth ($mut_val {}) {}) ($mut_loc {}) (seq.++ ($mut_path {}) (seq.unit {})))",
+                        r, i, r, r, i
+                    ),
+                );
+            }
+            (true, "push_back") => {
+                let r = self.read(&frame.temp(srcs[0]));
+                let e = self.read(&frame.temp(srcs[1]));
+                let value = format!("(seq.++ ($mut_val {}) (seq.unit {}))", r, e);
+                self.assign(frame.temp(srcs[0]), update(&r, value));
+            }
+            (true, "pop_back") => {
+                let r = self.read(&frame.temp(srcs[0]));
+                let v = format!("($mut_val {})", r);
+                self.abort_if(frame, format!("(= (seq.len {}) 0)", v), aa);
+                let last = format!("(- (seq.len {}) 1)", v);
+                self.assign(frame.temp(dests[0]), format!("(seq.nth {} {})", v, last));
+                let value = format!("(seq.extract {} 0 {})", v, last);
+                self.assign(frame.temp(srcs[0]), update(&r, value));
+            }
+            (true, "destroy_empty") => {
+                let v = self.read_value(frame, srcs[0]);
+                self.abort_if(frame, format!("(not (= (seq.len {}) 0))", v), aa);
+            }
+            (true, "swap") => {
+                let r = self.read(&frame.temp(srcs[0]));
+                let i = self.read(&frame.temp(srcs[1]));
+                let j = self.read(&frame.temp(srcs[2]));
+                let v = format!("($mut_val {})", r);
+                self.abort_if(
+                    frame,
+                    format!("(or (>= {} (seq.len {})) (>= {} (seq.len {})))", i, v, j, v),
+                    aa,
+                );
+                let value = format!(
+                    "(seq.update (seq.update {0} {1} (seq.unit (seq.nth {0} {2}))) {2} (seq.unit (seq.nth {0} {1})))",
+                    v, i, j
+                );
+                self.assign(frame.temp(srcs[0]), update(&r, value));
+            }
+            (false, "borrow_address") if is_signer => {
+                let s = self.read_value(frame, srcs[0]);
+                self.assign(frame.temp(dests[0]), format!("($addr {})", s));
+            }
+            _ => self.decls.unsupported(&format!(
+                "native function `{}`",
+                callee_env.get_full_name_str()
+            )),
+        }
+    }
+}
+
+/// The context of specification expressions in the code of a frame.
+struct FrameContext<'a, 'env> {
+    translator: &'a mut FunctionTranslator<'env>,
+    frame_id: usize,
+    target: &'a FunctionTarget<'a>,
+}
+
+impl<'a, 'env> SpecContext<'env> for FrameContext<'a, 'env> {
+    fn decls(&mut self) -> &mut Declarations<'env> {
+        &mut self.translator.decls
+    }
+
+    fn temporary(&mut self, idx: TempIndex) -> Option<String> {
+        let term = self.translator.read(&Var::Temp(self.frame_id, idx));
+        Some(if self.target.get_local_type(idx).is_mutable_reference() {
+            format!("($mut_val {})", term)
+        } else {
+            term
+        })
+    }
+
+    fn memory(
+        &mut self,
+        memory: &QualifiedInstId<StructId>,
+        label: Option<MemoryLabel>,
+    ) -> Option<String> {
+        let var = self.translator.memory_var(memory, label);
+        Some(self.translator.read(&var))
+    }
+}
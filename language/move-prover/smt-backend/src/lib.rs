@@ -0,0 +1,12 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![forbid(unsafe_code)]
+
+//! A prover backend which translates verified functions directly into SMT-LIB2 verification
+//! conditions and checks them with an SMT solver, without going through Boogie.
+
+pub mod bytecode_translator;
+pub mod smt_helpers;
+pub mod solver_wrapper;
+pub mod spec_translator;
@@ -0,0 +1,334 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Helpers for emitting SMT-LIB2: symbols, sorts, datatypes and well-formedness constraints.
+
+use itertools::Itertools;
+use move_model::{
+    model::{GlobalEnv, Loc, ModuleId, QualifiedInstId, StructId},
+    ty::{PrimitiveType, Type},
+};
+use move_prover_boogie_backend::boogie_helpers::{boogie_field_sel, boogie_struct_name};
+use num::{BigInt, BigUint, One, Signed};
+use std::collections::BTreeSet;
+
+/// Declarations shared by all verification conditions. A signer wraps an address, a mutable
+/// reference is a value together with the location and the path it was borrowed from, and the
+/// memory of a resource is a domain of addresses together with the values stored at them.
+pub const PRELUDE: &str = "\
+(set-option :produce-models true)
+(set-logic ALL)
+(declare-datatypes (($signer 0)) ((($signer ($addr Int)))))
+(declare-datatypes (($Mutation 1)) ((par (T) (($mutation ($mut_val T) ($mut_loc Int) ($mut_path (Seq Int)))))))
+(declare-datatypes (($Memory 1)) ((par (T) (($memory ($domain (Array Int Bool)) ($contents (Array Int T)))))))
+(declare-const $check Int)
+";
+
+/// The abort code of an execution failure, like an arithmetic overflow.
+pub const EXECUTION_FAILURE_CODE: &str = "(- 1)";
+
+/// Returns the given name as an SMT-LIB symbol, quoting it if it is not a simple symbol.
+pub fn smt_symbol(name: &str) -> String {
+    let is_simple = !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "~!@$%^&*_-+=<>.?/".contains(c));
+    if is_simple {
+        name.to_string()
+    } else {
+        format!("|{}|", name)
+    }
+}
+
+/// Returns an integer literal.
+pub fn smt_int(n: &BigInt) -> String {
+    if n.is_negative() {
+        format!("(- {})", n.abs())
+    } else {
+        n.to_string()
+    }
+}
+
+/// Returns the maximal value of an integer type, or `None` if the type is unbounded or not an
+/// integer.
+pub fn max_value(ty: &Type) -> Option<BigUint> {
+    let bits = match ty {
+        Type::Primitive(PrimitiveType::U8) => 8,
+        Type::Primitive(PrimitiveType::U16) => 16,
+        Type::Primitive(PrimitiveType::U32) => 32,
+        Type::Primitive(PrimitiveType::U64) => 64,
+        Type::Primitive(PrimitiveType::U128) => 128,
+        Type::Primitive(PrimitiveType::U256) | Type::Primitive(PrimitiveType::Address) => 256,
+        _ => return None,
+    };
+    Some((BigUint::one() << bits) - BigUint::one())
+}
+
+/// Returns a sequence with the given elements of the given sort.
+pub fn smt_seq(elems: Vec<String>, elem_sort: &str) -> String {
+    match elems.len() {
+        0 => format!("(as seq.empty (Seq {}))", elem_sort),
+        1 => format!("(seq.unit {})", elems[0]),
+        _ => format!(
+            "(seq.++ {})",
+            elems.iter().map(|e| format!("(seq.unit {})", e)).join(" ")
+        ),
+    }
+}
+
+/// Returns the conjunction of the given formulas.
+pub fn smt_and(conjuncts: Vec<String>) -> String {
+    match conjuncts.len() {
+        0 => "true".to_string(),
+        1 => conjuncts.into_iter().next().unwrap(),
+        _ => format!("(and {})", conjuncts.join(" ")),
+    }
+}
+
+/// Returns true if the term needs no parentheses, so it can be duplicated freely.
+pub fn is_atom(term: &str) -> bool {
+    !term.contains(|c: char| c == '(' || c.is_whitespace())
+}
+
+/// Collects the declarations a verification condition depends on, in dependency order.
+pub struct Declarations<'env> {
+    env: &'env GlobalEnv,
+    loc: Loc,
+    failed: bool,
+    type_params: BTreeSet<u16>,
+    structs: BTreeSet<String>,
+    datatypes: Vec<String>,
+    function_names: BTreeSet<String>,
+    functions: Vec<String>,
+    consts: Vec<String>,
+    fresh_count: usize,
+}
+
+impl<'env> Declarations<'env> {
+    pub fn new(env: &'env GlobalEnv) -> Self {
+        Self {
+            env,
+            loc: env.unknown_loc(),
+            failed: false,
+            type_params: BTreeSet::new(),
+            structs: BTreeSet::new(),
+            datatypes: vec![],
+            function_names: BTreeSet::new(),
+            functions: vec![],
+            consts: vec![],
+            fresh_count: 0,
+        }
+    }
+
+    /// Sets the location at which unsupported constructs are reported.
+    pub fn set_loc(&mut self, loc: Loc) {
+        self.loc = loc;
+    }
+
+    /// Reports a construct which cannot be translated. Only the first one is reported, as the
+    /// verification condition is dropped anyway.
+    pub fn unsupported(&mut self, what: &str) {
+        if !self.failed {
+            self.failed = true;
+            self.env.error(
+                &self.loc,
+                &format!("{} not supported by the SMT backend", what),
+            );
+        }
+    }
+
+    /// Returns true if an unsupported construct has been reported.
+    pub fn has_failed(&self) -> bool {
+        self.failed
+    }
+
+    /// Declares a fresh constant of the given sort, with a name derived from the given one.
+    pub fn fresh_const(&mut self, base: &str, sort: &str) -> String {
+        let name = smt_symbol(&format!("{}@{}", base, self.fresh_count));
+        self.fresh_count += 1;
+        self.consts
+            .push(format!("(declare-const {} {})", name, sort));
+        name
+    }
+
+    /// Returns true if a function with the given name has been declared.
+    pub fn has_function(&self, name: &str) -> bool {
+        self.function_names.contains(name)
+    }
+
+    /// Reserves the name of a function whose declaration is added later, once the functions
+    /// it depends on are declared.
+    pub fn reserve_function(&mut self, name: &str) {
+        self.function_names.insert(name.to_string());
+    }
+
+    /// Adds the declaration or definition of a function.
+    pub fn add_function(&mut self, decl: String) {
+        self.functions.push(decl);
+    }
+
+    /// Returns the sort of the given type, declaring the datatypes it depends on.
+    pub fn sort(&mut self, ty: &Type) -> String {
+        use PrimitiveType::*;
+        match ty {
+            Type::Primitive(Bool) => "Bool".to_string(),
+            Type::Primitive(U8 | U16 | U32 | U64 | U128 | U256 | Num | Address) => {
+                "Int".to_string()
+            }
+            Type::Primitive(Signer) => "$signer".to_string(),
+            Type::Vector(elem) => format!("(Seq {})", self.sort(elem)),
+            Type::Struct(mid, sid, inst) => self.struct_name(*mid, *sid, inst),
+            Type::TypeParameter(idx) => {
+                self.type_params.insert(*idx);
+                smt_symbol(&format!("#{}", idx))
+            }
+            Type::Reference(true, elem) => format!("($Mutation {})", self.sort(elem)),
+            Type::Reference(false, elem) => self.sort(elem),
+            _ => {
+                self.unsupported(&format!(
+                    "type `{}`",
+                    ty.display(&self.env.get_type_display_ctx())
+                ));
+                "Int".to_string()
+            }
+        }
+    }
+
+    /// Returns the sort of the memory of the given resource.
+    pub fn memory_sort(&mut self, memory: &QualifiedInstId<StructId>) -> String {
+        format!(
+            "($Memory {})",
+            self.struct_name(memory.module_id, memory.id, &memory.inst)
+        )
+    }
+
+    /// Returns the name of the datatype of the given struct instance, which is also the name of
+    /// its constructor, declaring it if needed.
+    pub fn struct_name(&mut self, mid: ModuleId, sid: StructId, inst: &[Type]) -> String {
+        let struct_env = self.env.get_module(mid).into_struct(sid);
+        if struct_env.is_native_or_intrinsic() {
+            self.unsupported(&format!(
+                "intrinsic type `{}`",
+                struct_env.get_full_name_str()
+            ));
+            return "Int".to_string();
+        }
+        let name = smt_symbol(&boogie_struct_name(&struct_env, inst));
+        if self.structs.insert(name.clone()) {
+            let mut fields = vec![];
+            for field_env in struct_env.get_fields() {
+                let sort = self.sort(&field_env.get_type().instantiate(inst));
+                fields.push(format!(
+                    "({} {})",
+                    smt_symbol(&boogie_field_sel(&field_env, inst)),
+                    sort
+                ));
+            }
+            self.datatypes.push(format!(
+                "(declare-datatypes (({0} 0)) ((({0} {1}))))",
+                name,
+                fields.join(" ")
+            ));
+        }
+        name
+    }
+
+    /// Returns the selectors of the fields of the given struct instance, by offset.
+    pub fn field_selectors(&mut self, mid: ModuleId, sid: StructId, inst: &[Type]) -> Vec<String> {
+        self.struct_name(mid, sid, inst);
+        self.env
+            .get_module(mid)
+            .into_struct(sid)
+            .get_fields()
+            .map(|field_env| smt_symbol(&boogie_field_sel(&field_env, inst)))
+            .collect()
+    }
+
+    /// Returns the constraint that the given term is a well-formed value of the given type,
+    /// or `None` if every value of its sort is.
+    pub fn well_formed(&mut self, ty: &Type, term: &str) -> Option<String> {
+        self.well_formed_at(ty, term, 0)
+    }
+
+    fn well_formed_at(&mut self, ty: &Type, term: &str, depth: usize) -> Option<String> {
+        match ty {
+            Type::Primitive(PrimitiveType::Signer) => self.well_formed_at(
+                &Type::Primitive(PrimitiveType::Address),
+                &format!("($addr {})", term),
+                depth,
+            ),
+            Type::Primitive(_) => {
+                max_value(ty).map(|max| format!("(and (<= 0 {}) (<= {} {}))", term, term, max))
+            }
+            Type::Vector(elem) => {
+                let len = format!("(<= (seq.len {}) {})", term, u64::MAX);
+                let idx = format!("$i{}", depth);
+                let elem_term = format!("(seq.nth {} {})", term, idx);
+                Some(match self.well_formed_at(elem, &elem_term, depth + 1) {
+                    Some(elem_wf) => format!(
+                        "(and {} (forall (({} Int)) (=> (and (<= 0 {}) (< {} (seq.len {}))) {})))",
+                        len, idx, idx, idx, term, elem_wf
+                    ),
+                    None => len,
+                })
+            }
+            Type::Struct(mid, sid, inst) => {
+                if self
+                    .env
+                    .get_module(*mid)
+                    .into_struct(*sid)
+                    .is_native_or_intrinsic()
+                {
+                    return None;
+                }
+                let field_types = self
+                    .env
+                    .get_module(*mid)
+                    .into_struct(*sid)
+                    .get_fields()
+                    .map(|f| f.get_type().instantiate(inst))
+                    .collect_vec();
+                let selectors = self.field_selectors(*mid, *sid, inst);
+                let conjuncts = field_types
+                    .iter()
+                    .zip(selectors)
+                    .filter_map(|(ty, sel)| {
+                        self.well_formed_at(ty, &format!("({} {})", sel, term), depth)
+                    })
+                    .collect_vec();
+                if conjuncts.is_empty() {
+                    None
+                } else {
+                    Some(smt_and(conjuncts))
+                }
+            }
+            Type::Reference(true, elem) => {
+                self.well_formed_at(elem, &format!("($mut_val {})", term), depth)
+            }
+            Type::Reference(false, elem) => self.well_formed_at(elem, term, depth),
+            _ => None,
+        }
+    }
+
+    /// Renders the declarations.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for idx in &self.type_params {
+            out.push_str(&format!(
+                "(declare-sort {} 0)\n",
+                smt_symbol(&format!("#{}", idx))
+            ));
+        }
+        for decl in self
+            .datatypes
+            .iter()
+            .chain(&self.functions)
+            .chain(&self.consts)
+        {
+            out.push_str(decl);
+            out.push('\n');
+        }
+        out
+    }
+}
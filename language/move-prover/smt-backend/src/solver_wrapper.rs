@@ -0,0 +1,407 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Running an SMT solver on verification conditions and reporting its findings.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    time::Instant,
+};
+
+use anyhow::{anyhow, bail};
+use itertools::Itertools;
+use log::{debug, info};
+use move_model::{code_writer::CodeWriter, model::GlobalEnv};
+use move_prover_boogie_backend::{
+    boogie_wrapper::{
+        BoogieError, BoogieErrorKind, BoogieWrapper, Model, ModelValue, TraceEntry,
+        VerificationStats,
+    },
+    options::{BoogieOptions, VectorTheory},
+};
+use move_stackless_bytecode::{
+    function_target_pipeline::{FunctionTargetsHolder, FunctionVariant, VerificationFlavor},
+    inconsistency_check::UNCOVERED_ABORT_MESSAGE,
+};
+
+use crate::{
+    bytecode_translator::{TraceItem, VerificationCondition},
+    smt_helpers::PRELUDE,
+};
+
+/// The message of assertions whose failure is the expected outcome of a check.
+const EXPECTED_TO_FAIL: &str = "expected to fail";
+
+pub struct SmtWrapper<'env> {
+    pub env: &'env GlobalEnv,
+    pub targets: &'env FunctionTargetsHolder,
+    pub options: &'env BoogieOptions,
+}
+
+impl<'env> SmtWrapper<'env> {
+    /// Writes the verification conditions into a file which can be passed to a solver.
+    pub fn write_file(
+        &self,
+        conditions: &[VerificationCondition],
+        path: &str,
+    ) -> anyhow::Result<()> {
+        let mut out = String::new();
+        for (idx, cond) in conditions.iter().enumerate() {
+            if idx > 0 {
+                out.push_str("(reset)\n");
+            }
+            out.push_str(&format!(
+                "; {} ({})\n",
+                self.env.get_function(cond.fun_id).get_full_name_str(),
+                cond.variant
+            ));
+            out.push_str(PRELUDE);
+            out.push_str(&cond.script);
+            for check in 0..cond.checks.len() {
+                out.push_str(&check_command(check));
+                out.push_str("(pop 1)\n");
+            }
+        }
+        fs::write(path, out)?;
+        Ok(())
+    }
+
+    /// Checks the verification conditions, reports the errors found in the environment, and
+    /// returns them, together with the statistics of each condition.
+    pub fn check_and_report(
+        &self,
+        conditions: &[VerificationCondition],
+    ) -> anyhow::Result<(Vec<BoogieError>, Vec<VerificationStats>)> {
+        info!("running SMT solver");
+        let mut errors = vec![];
+        let mut stats = vec![];
+        for cond in conditions {
+            let start = Instant::now();
+            errors.extend(self.check(cond)?);
+            stats.push(VerificationStats {
+                fun_id: cond.fun_id,
+                procedure: format!(
+                    "{} ({})",
+                    self.env.get_function(cond.fun_id).get_full_name_str(),
+                    cond.variant
+                ),
+                duration: start.elapsed(),
+                vc_count: cond.checks.len(),
+            });
+        }
+        let writer = CodeWriter::new(self.env.internal_loc());
+        let wrapper = BoogieWrapper {
+            env: self.env,
+            targets: self.targets,
+            writer: &writer,
+            options: self.options,
+        };
+        for error in errors.iter().filter(|e| !e.kind.is_spec_coverage()) {
+            wrapper.add_error(error);
+        }
+        Ok((errors, stats))
+    }
+
+    fn check(&self, cond: &VerificationCondition) -> anyhow::Result<Vec<BoogieError>> {
+        debug!(
+            "checking {} ({})",
+            self.env.get_function(cond.fun_id).get_full_name_str(),
+            cond.variant
+        );
+        let timeout = self.options.adjust_timeout(cond.timeout);
+        let mut solver = Solver::start(self.options, timeout, cond.seed)?;
+        solver.send(PRELUDE)?;
+        solver.send(&cond.script)?;
+        let mut errors = vec![];
+        let mut refuted = false;
+        for (idx, check) in cond.checks.iter().enumerate() {
+            solver.send(&check_command(idx))?;
+            match solver.response()?.as_str() {
+                "unsat" => {}
+                "sat" => {
+                    refuted = true;
+                    if check.message != EXPECTED_TO_FAIL {
+                        errors.push(BoogieError {
+                            kind: if check.message == UNCOVERED_ABORT_MESSAGE {
+                                BoogieErrorKind::UncoveredAbort
+                            } else {
+                                BoogieErrorKind::Assertion
+                            },
+                            loc: check.loc.clone(),
+                            message: check.message.clone(),
+                            execution_trace: self.execution_trace(&mut solver, cond, idx)?,
+                            model: Some(Model::with_vector_theory(VectorTheory::SmtSeq)),
+                        });
+                    }
+                }
+                _ => {
+                    refuted = true;
+                    solver.send("(get-info :reason-unknown)\n")?;
+                    let reason = solver.response()?;
+                    let out_of_resources = ["timeout", "canceled", "resource"]
+                        .iter()
+                        .any(|r| reason.contains(r));
+                    errors.push(BoogieError {
+                        kind: BoogieErrorKind::Inconclusive,
+                        loc: check.loc.clone(),
+                        message: if out_of_resources {
+                            format!(
+                                "verification out of resources/timeout (global timeout set to {}s)",
+                                timeout
+                            )
+                        } else {
+                            "verification inconclusive".to_string()
+                        },
+                        execution_trace: vec![],
+                        model: None,
+                    });
+                }
+            }
+            solver.send("(pop 1)\n")?;
+        }
+        solver.exit();
+
+        // The inconsistency and vacuity checks report an error if their assertions, which are
+        // expected to fail, all hold.
+        if !refuted {
+            let finding = match &cond.variant {
+                FunctionVariant::Verification(VerificationFlavor::Inconsistency(_)) => Some((
+                    BoogieErrorKind::Inconsistency,
+                    cond.loc.clone(),
+                    "there is an inconsistent assumption in the function, which may allow any post-condition (including false) to be proven",
+                )),
+                FunctionVariant::Verification(VerificationFlavor::Vacuity(_)) => Some((
+                    BoogieErrorKind::VacuousEnsures,
+                    cond.checks
+                        .first()
+                        .map(|check| check.loc.clone())
+                        .unwrap_or_else(|| cond.loc.clone()),
+                    "post-condition holds for any function body",
+                )),
+                _ => None,
+            };
+            if let Some((kind, loc, message)) = finding {
+                errors.push(BoogieError {
+                    kind,
+                    loc,
+                    message: message.to_string(),
+                    execution_trace: vec![],
+                    model: None,
+                });
+            }
+        }
+        Ok(errors)
+    }
+
+    /// Reconstructs the execution trace leading to the failure of the given check from the
+    /// solver's model: starting at the root, follow an edge whose guard holds into a block
+    /// which fails, until the block with the check is reached.
+    fn execution_trace(
+        &self,
+        solver: &mut Solver,
+        cond: &VerificationCondition,
+        check: usize,
+    ) -> anyhow::Result<Vec<TraceEntry>> {
+        let names = cond
+            .blocks
+            .iter()
+            .flat_map(|block| {
+                std::iter::once(block.ok.clone()).chain(block.edges.iter().map(|(g, _)| g.clone()))
+            })
+            .collect_vec();
+        let truth: BTreeMap<String, bool> = names
+            .iter()
+            .cloned()
+            .zip(
+                solver
+                    .values(&names)?
+                    .into_iter()
+                    .map(|v| v == ModelValue::Literal("true".to_string())),
+            )
+            .collect();
+
+        let mut path = vec![];
+        let mut block = 0;
+        'path: loop {
+            for item in &cond.blocks[block].trace {
+                path.push(item);
+                if matches!(item, TraceItem::Check(idx) if *idx == check) {
+                    break 'path;
+                }
+            }
+            match cond.blocks[block]
+                .edges
+                .iter()
+                .find(|(guard, to)| truth[guard] && !truth[&cond.blocks[*to].ok])
+            {
+                Some((_, to)) => block = *to,
+                None => break,
+            }
+        }
+
+        let terms = path
+            .iter()
+            .filter_map(|item| match item {
+                TraceItem::Temporary(_, _, term)
+                | TraceItem::Result(_, _, term)
+                | TraceItem::Abort(_, term)
+                | TraceItem::Exp(_, term)
+                | TraceItem::SubExp(_, term) => Some(term.clone()),
+                TraceItem::Loc(_) | TraceItem::Check(_) => None,
+            })
+            .unique()
+            .collect_vec();
+        let values: BTreeMap<String, ModelValue> =
+            terms.iter().cloned().zip(solver.values(&terms)?).collect();
+        Ok(path
+            .into_iter()
+            .filter_map(|item| {
+                Some(match item {
+                    TraceItem::Loc(loc) => TraceEntry::AtLocation(loc.clone()),
+                    TraceItem::Temporary(fun, idx, term) => {
+                        TraceEntry::Temporary(*fun, *idx, values[term].clone())
+                    }
+                    TraceItem::Result(fun, idx, term) => {
+                        TraceEntry::Result(*fun, *idx, values[term].clone())
+                    }
+                    TraceItem::Abort(fun, term) => TraceEntry::Abort(*fun, values[term].clone()),
+                    TraceItem::Exp(node_id, term) => {
+                        TraceEntry::Exp(*node_id, values[term].clone())
+                    }
+                    TraceItem::SubExp(node_id, term) => {
+                        TraceEntry::SubExp(*node_id, values[term].clone())
+                    }
+                    TraceItem::Check(_) => return None,
+                })
+            })
+            .collect())
+    }
+}
+
+/// Returns the commands checking the assertion with the given index.
+fn check_command(check: usize) -> String {
+    format!("(push 1)\n(assert (= $check {}))\n(check-sat)\n", check)
+}
+
+/// A running solver process, talked to in SMT-LIB2 through its standard input and output.
+struct Solver {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Solver {
+    /// Starts the configured solver with the given timeout per check, in seconds.
+    fn start(options: &BoogieOptions, timeout: usize, seed: usize) -> anyhow::Result<Self> {
+        let ms = timeout * 1000;
+        let (exe, args) = if options.use_cvc5 {
+            (
+                &options.cvc5_exe,
+                vec![
+                    "--lang=smt2".to_string(),
+                    "--incremental".to_string(),
+                    "--produce-models".to_string(),
+                    format!("--tlimit-per={}", ms),
+                    format!("--seed={}", seed),
+                ],
+            )
+        } else {
+            (
+                &options.z3_exe,
+                vec![
+                    "-smt2".to_string(),
+                    "-in".to_string(),
+                    format!("-t:{}", ms),
+                    format!("smt.random_seed={}", seed),
+                ],
+            )
+        };
+        if exe.is_empty() {
+            bail!("no SMT solver configured (set Z3_EXE or CVC5_EXE)")
+        }
+        debug!("command line: {} {}", exe, args.iter().join(" "));
+        let mut child = Command::new(exe)
+            .args(&args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| anyhow!("cannot execute `{}`: {}", exe, e))?;
+        let stdin = child.stdin.take().expect("piped stdin");
+        let stdout = BufReader::new(child.stdout.take().expect("piped stdout"));
+        Ok(Self {
+            child,
+            stdin,
+            stdout,
+        })
+    }
+
+    fn send(&mut self, commands: &str) -> anyhow::Result<()> {
+        self.stdin.write_all(commands.as_bytes())?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    /// Reads the response to a command, which is a symbol or a balanced s-expression.
+    fn response(&mut self) -> anyhow::Result<String> {
+        let mut response = String::new();
+        let mut depth = 0i64;
+        let mut quoted = None;
+        loop {
+            let mut line = String::new();
+            if self.stdout.read_line(&mut line)? == 0 {
+                bail!("SMT solver terminated unexpectedly")
+            }
+            for c in line.chars() {
+                match (quoted, c) {
+                    (Some(q), _) if q == c => quoted = None,
+                    (Some(_), _) => {}
+                    (None, '|' | '"') => quoted = Some(c),
+                    (None, '(') => depth += 1,
+                    (None, ')') => depth -= 1,
+                    _ => {}
+                }
+            }
+            response.push_str(&line);
+            if depth <= 0 && quoted.is_none() && !response.trim().is_empty() {
+                break;
+            }
+        }
+        let response = response.trim().to_string();
+        if response.starts_with("(error") {
+            bail!("SMT solver reported {}", response)
+        }
+        Ok(response)
+    }
+
+    /// Returns the values of the given terms in the current model, as printed by the solver.
+    fn values(&mut self, terms: &[String]) -> anyhow::Result<Vec<ModelValue>> {
+        if terms.is_empty() {
+            return Ok(vec![]);
+        }
+        self.send(&format!("(get-value ({}))\n", terms.join(" ")))?;
+        let response = self.response()?;
+        let values = match ModelValue::parse(&response) {
+            Some(ModelValue::List(pairs)) => pairs
+                .into_iter()
+                .map(|pair| match pair {
+                    ModelValue::List(mut elems) if elems.len() == 2 => elems.pop(),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>(),
+            _ => None,
+        };
+        match values {
+            Some(values) if values.len() == terms.len() => Ok(values),
+            _ => bail!("cannot parse model values `{}`", response),
+        }
+    }
+
+    fn exit(mut self) {
+        self.send("(exit)\n").unwrap_or_default();
+        self.child.wait().map(|_| ()).unwrap_or_default();
+    }
+}
@@ -0,0 +1,583 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Translation of specification expressions into SMT-LIB2 terms.
+
+use itertools::Itertools;
+use move_model::{
+    ast::{Exp, ExpData, LocalVarDecl, MemoryLabel, Operation, QuantKind, TempIndex, Value},
+    model::{GlobalEnv, ModuleEnv, ModuleId, NodeId, QualifiedInstId, SpecFunId, StructId},
+    symbol::Symbol,
+    ty::{PrimitiveType, Type},
+};
+use move_prover_boogie_backend::boogie_helpers::boogie_spec_fun_name;
+
+use crate::smt_helpers::{max_value, smt_and, smt_int, smt_seq, smt_symbol, Declarations};
+
+/// The context in which a specification expression is translated, which determines the
+/// terms denoting temporaries and memories.
+pub trait SpecContext<'env> {
+    /// Returns the declarations of the verification condition.
+    fn decls(&mut self) -> &mut Declarations<'env>;
+
+    /// Returns the value of the given temporary, dereferencing mutable references, or `None`
+    /// if temporaries are not available in this context.
+    fn temporary(&mut self, idx: TempIndex) -> Option<String>;
+
+    /// Returns the given memory, as saved under the label if one is given, or `None` if the
+    /// memory is not available in this context.
+    fn memory(
+        &mut self,
+        memory: &QualifiedInstId<StructId>,
+        label: Option<MemoryLabel>,
+    ) -> Option<String>;
+}
+
+/// The context of the body of a specification function, in which the memory is passed in
+/// as parameters.
+struct SpecFunContext<'a, 'env> {
+    decls: &'a mut Declarations<'env>,
+    memory: Vec<(QualifiedInstId<StructId>, String)>,
+}
+
+impl<'a, 'env> SpecContext<'env> for SpecFunContext<'a, 'env> {
+    fn decls(&mut self) -> &mut Declarations<'env> {
+        self.decls
+    }
+
+    fn temporary(&mut self, _idx: TempIndex) -> Option<String> {
+        None
+    }
+
+    fn memory(
+        &mut self,
+        memory: &QualifiedInstId<StructId>,
+        _label: Option<MemoryLabel>,
+    ) -> Option<String> {
+        self.memory
+            .iter()
+            .find(|(m, _)| m == memory)
+            .map(|(_, name)| name.clone())
+    }
+}
+
+/// Translates specification expressions in a given context.
+pub struct SpecTranslator<'a, 'env> {
+    env: &'env GlobalEnv,
+    ctx: &'a mut dyn SpecContext<'env>,
+    type_inst: &'a [Type],
+    /// The terms of the variables bound by quantifiers, lets and specification function
+    /// parameters. Later bindings shadow earlier ones.
+    locals: Vec<(Symbol, String)>,
+}
+
+impl<'a, 'env> SpecTranslator<'a, 'env> {
+    pub fn new(
+        env: &'env GlobalEnv,
+        ctx: &'a mut dyn SpecContext<'env>,
+        type_inst: &'a [Type],
+    ) -> Self {
+        Self {
+            env,
+            ctx,
+            type_inst,
+            locals: vec![],
+        }
+    }
+
+    /// Translates the given expression into a term.
+    pub fn translate(&mut self, exp: &Exp) -> String {
+        match exp.as_ref() {
+            ExpData::Value(id, value) => self.translate_value(&self.node_type(*id), value),
+            ExpData::LocalVar(id, name) => {
+                match self.locals.iter().rev().find(|(n, _)| n == name) {
+                    Some((_, term)) => term.clone(),
+                    None => self.unsupported(*id, "free specification variable"),
+                }
+            }
+            ExpData::Temporary(id, idx) => match self.ctx.temporary(*idx) {
+                Some(term) => term,
+                None => self.unsupported(*id, "temporary outside of function code"),
+            },
+            ExpData::Call(id, oper, args) => self.translate_call(*id, oper, args),
+            ExpData::Quant(id, kind, ranges, _, condition, body) => {
+                self.translate_quant(*id, *kind, ranges, condition.as_ref(), body)
+            }
+            ExpData::Block(_, decls, body) => self.translate_block(decls, body),
+            ExpData::IfElse(_, cond, then, els) => format!(
+                "(ite {} {} {})",
+                self.translate(cond),
+                self.translate(then),
+                self.translate(els)
+            ),
+            ExpData::Invoke(id, ..) | ExpData::Lambda(id, ..) | ExpData::Invalid(id) => {
+                self.unsupported(*id, "function value")
+            }
+        }
+    }
+
+    fn node_type(&self, id: NodeId) -> Type {
+        self.env.get_node_type(id).instantiate(self.type_inst)
+    }
+
+    fn node_inst(&self, id: NodeId) -> Vec<Type> {
+        self.env
+            .get_node_instantiation(id)
+            .iter()
+            .map(|ty| ty.instantiate(self.type_inst))
+            .collect()
+    }
+
+    fn unsupported(&mut self, id: NodeId, what: &str) -> String {
+        let decls = self.ctx.decls();
+        decls.set_loc(self.env.get_node_loc(id));
+        decls.unsupported(what);
+        "false".to_string()
+    }
+
+    fn bound_name(&self) -> String {
+        format!("$q{}", self.locals.len())
+    }
+
+    fn translate_value(&mut self, ty: &Type, value: &Value) -> String {
+        match value {
+            Value::Address(addr) => addr.to_string(),
+            Value::Number(n) => smt_int(n),
+            Value::Bool(b) => b.to_string(),
+            Value::ByteArray(bytes) => {
+                smt_seq(bytes.iter().map(|b| b.to_string()).collect(), "Int")
+            }
+            Value::AddressArray(addrs) => {
+                smt_seq(addrs.iter().map(|a| a.to_string()).collect(), "Int")
+            }
+            Value::Vector(values) => {
+                let elem_ty = match ty {
+                    Type::Vector(elem_ty) => elem_ty.as_ref().clone(),
+                    _ => Type::Error,
+                };
+                let elems = values
+                    .iter()
+                    .map(|v| self.translate_value(&elem_ty, v))
+                    .collect();
+                let elem_sort = self.ctx.decls().sort(&elem_ty);
+                smt_seq(elems, &elem_sort)
+            }
+        }
+    }
+
+    fn translate_block(&mut self, decls: &[LocalVarDecl], body: &Exp) -> String {
+        let mut bindings = vec![];
+        for decl in decls {
+            let term = match &decl.binding {
+                Some(binding) => self.translate(binding),
+                None => self.unsupported(decl.id, "let without binding"),
+            };
+            let name = self.bound_name();
+            bindings.push((name.clone(), term));
+            self.locals.push((decl.name, name));
+        }
+        let mut result = self.translate(body);
+        for (name, term) in bindings.into_iter().rev() {
+            self.locals.pop();
+            result = format!("(let (({} {})) {})", name, term, result);
+        }
+        result
+    }
+
+    fn translate_quant(
+        &mut self,
+        id: NodeId,
+        kind: QuantKind,
+        ranges: &[(LocalVarDecl, Exp)],
+        condition: Option<&Exp>,
+        body: &Exp,
+    ) -> String {
+        if kind.is_choice() {
+            return self.unsupported(id, "choice operator");
+        }
+        let mut vars = vec![];
+        let mut constraints = vec![];
+        let locals_count = self.locals.len();
+        for (decl, range) in ranges {
+            let name = self.bound_name();
+            match self.node_type(range.node_id()).skip_reference() {
+                Type::TypeDomain(elem_ty) => {
+                    let sort = self.ctx.decls().sort(elem_ty);
+                    vars.push(format!("({} {})", name, sort));
+                    constraints.extend(self.ctx.decls().well_formed(elem_ty, &name));
+                    self.locals.push((decl.name, name));
+                }
+                Type::ResourceDomain(mid, sid, inst) => {
+                    // Ranges over the resources stored at any address.
+                    let memory = mid.qualified_inst(*sid, inst.clone().unwrap_or_default());
+                    let memory_term = match self.ctx.memory(&memory, None) {
+                        Some(term) => term,
+                        None => return self.unsupported(range.node_id(), "resource access"),
+                    };
+                    vars.push(format!("({} Int)", name));
+                    self.locals.push((
+                        decl.name,
+                        format!("(select ($contents {}) {})", memory_term, name),
+                    ));
+                }
+                Type::Vector(_) => {
+                    let vec = self.translate(range);
+                    vars.push(format!("({} Int)", name));
+                    constraints.push(format!(
+                        "(and (<= 0 {}) (< {} (seq.len {})))",
+                        name, name, vec
+                    ));
+                    self.locals
+                        .push((decl.name, format!("(seq.nth {} {})", vec, name)));
+                }
+                Type::Primitive(PrimitiveType::Range) => {
+                    let (lo, hi) = match range.as_ref() {
+                        ExpData::Call(_, Operation::Range, args) => {
+                            (self.translate(&args[0]), self.translate(&args[1]))
+                        }
+                        _ => return self.unsupported(range.node_id(), "quantifier range"),
+                    };
+                    vars.push(format!("({} Int)", name));
+                    constraints.push(format!("(and (<= {} {}) (< {} {}))", lo, name, name, hi));
+                    self.locals.push((decl.name, name));
+                }
+                _ => return self.unsupported(range.node_id(), "quantifier range"),
+            }
+        }
+        if let Some(condition) = condition {
+            constraints.push(self.translate(condition));
+        }
+        let body = self.translate(body);
+        self.locals.truncate(locals_count);
+        if kind == QuantKind::Forall {
+            format!(
+                "(forall ({}) (=> {} {}))",
+                vars.join(" "),
+                smt_and(constraints),
+                body
+            )
+        } else {
+            constraints.push(body);
+            format!("(exists ({}) {})", vars.join(" "), smt_and(constraints))
+        }
+    }
+
+    fn translate_call(&mut self, id: NodeId, oper: &Operation, args: &[Exp]) -> String {
+        use Operation::*;
+        let binary = |this: &mut Self, op: &str| {
+            format!(
+                "({} {} {})",
+                op,
+                this.translate(&args[0]),
+                this.translate(&args[1])
+            )
+        };
+        match oper {
+            Function(mid, fid, labels) => {
+                self.translate_spec_fun_call(id, *mid, *fid, labels, args)
+            }
+            Pack(..) => {
+                let (mid, sid, inst) = match self.node_type(id) {
+                    Type::Struct(mid, sid, inst) => (mid, sid, inst),
+                    _ => return self.unsupported(id, "pack of non-struct"),
+                };
+                let ctor = self.ctx.decls().struct_name(mid, sid, &inst);
+                let args = args.iter().map(|a| self.translate(a)).collect_vec();
+                format!("({} {})", ctor, args.join(" "))
+            }
+            Select(mid, sid, fid) => {
+                let inst = match self.node_type(args[0].node_id()).skip_reference() {
+                    Type::Struct(_, _, inst) => inst.clone(),
+                    _ => return self.unsupported(id, "select of non-struct"),
+                };
+                let offset = self
+                    .env
+                    .get_module(*mid)
+                    .into_struct(*sid)
+                    .get_field(*fid)
+                    .get_offset();
+                let sel = self.ctx.decls().field_selectors(*mid, *sid, &inst)[offset].clone();
+                format!("({} {})", sel, self.translate(&args[0]))
+            }
+            UpdateField(mid, sid, fid) => {
+                let inst = match self.node_type(id) {
+                    Type::Struct(_, _, inst) => inst,
+                    _ => return self.unsupported(id, "update of non-struct"),
+                };
+                let offset = self
+                    .env
+                    .get_module(*mid)
+                    .into_struct(*sid)
+                    .get_field(*fid)
+                    .get_offset();
+                let ctor = self.ctx.decls().struct_name(*mid, *sid, &inst);
+                let selectors = self.ctx.decls().field_selectors(*mid, *sid, &inst);
+                let value = self.translate(&args[0]);
+                let new_field = self.translate(&args[1]);
+                let fields = selectors
+                    .iter()
+                    .enumerate()
+                    .map(|(i, sel)| {
+                        if i == offset {
+                            new_field.clone()
+                        } else {
+                            format!("({} {})", sel, value)
+                        }
+                    })
+                    .join(" ");
+                format!("({} {})", ctor, fields)
+            }
+            Index => match self.node_type(args[0].node_id()).skip_reference() {
+                Type::Vector(_) => binary(self, "seq.nth"),
+                _ => self.unsupported(id, "index into non-vector"),
+            },
+            Slice => match args[1].as_ref() {
+                ExpData::Call(_, Range, range) => {
+                    let vec = self.translate(&args[0]);
+                    let lo = self.translate(&range[0]);
+                    let hi = self.translate(&range[1]);
+                    format!("(seq.extract {} {} (- {} {}))", vec, lo, hi, lo)
+                }
+                _ => self.unsupported(id, "slice with a computed range"),
+            },
+            Add => binary(self, "+"),
+            Sub => binary(self, "-"),
+            Mul => binary(self, "*"),
+            Div => binary(self, "div"),
+            Mod => binary(self, "mod"),
+            Implies => binary(self, "=>"),
+            Iff | Eq | Identical => binary(self, "="),
+            Neq => format!("(not {})", binary(self, "=")),
+            Lt => binary(self, "<"),
+            Gt => binary(self, ">"),
+            Le => binary(self, "<="),
+            Ge => binary(self, ">="),
+            And => smt_and(args.iter().map(|a| self.translate(a)).collect()),
+            Or => match args.len() {
+                0 => "false".to_string(),
+                1 => self.translate(&args[0]),
+                _ => format!("(or {})", args.iter().map(|a| self.translate(a)).join(" ")),
+            },
+            Not => format!("(not {})", self.translate(&args[0])),
+            Cast | Trace(_) => self.translate(&args[0]),
+            Len => format!("(seq.len {})", self.translate(&args[0])),
+            Global(label) | Exists(label) => {
+                let memory = match self.node_inst(id).first() {
+                    Some(Type::Struct(mid, sid, inst)) => mid.qualified_inst(*sid, inst.clone()),
+                    _ => return self.unsupported(id, "resource access"),
+                };
+                let memory_term = match self.ctx.memory(&memory, *label) {
+                    Some(term) => term,
+                    None => return self.unsupported(id, "resource access"),
+                };
+                let addr = self.translate(&args[0]);
+                if matches!(oper, Global(_)) {
+                    format!("(select ($contents {}) {})", memory_term, addr)
+                } else {
+                    format!("(select ($domain {}) {})", memory_term, addr)
+                }
+            }
+            EmptyVec => {
+                let ty = self.node_type(id);
+                let sort = self.ctx.decls().sort(&ty);
+                format!("(as seq.empty {})", sort)
+            }
+            SingleVec => format!("(seq.unit {})", self.translate(&args[0])),
+            UpdateVec => format!(
+                "(seq.update {} {} (seq.unit {}))",
+                self.translate(&args[0]),
+                self.translate(&args[1]),
+                self.translate(&args[2])
+            ),
+            ConcatVec => binary(self, "seq.++"),
+            IndexOfVec => format!(
+                "(seq.indexof {} (seq.unit {}))",
+                self.translate(&args[0]),
+                self.translate(&args[1])
+            ),
+            ContainsVec => format!(
+                "(seq.contains {} (seq.unit {}))",
+                self.translate(&args[0]),
+                self.translate(&args[1])
+            ),
+            InRangeVec => {
+                let vec = self.translate(&args[0]);
+                let idx = self.translate(&args[1]);
+                format!("(and (<= 0 {}) (< {} (seq.len {})))", idx, idx, vec)
+            }
+            InRangeRange => match args[0].as_ref() {
+                ExpData::Call(_, Range, range) => {
+                    let lo = self.translate(&range[0]);
+                    let hi = self.translate(&range[1]);
+                    let idx = self.translate(&args[1]);
+                    format!("(and (<= {} {}) (< {} {}))", lo, idx, idx, hi)
+                }
+                _ => self.unsupported(id, "computed range"),
+            },
+            MaxU8 | MaxU16 | MaxU32 | MaxU64 | MaxU128 | MaxU256 => {
+                let ty = match oper {
+                    MaxU8 => PrimitiveType::U8,
+                    MaxU16 => PrimitiveType::U16,
+                    MaxU32 => PrimitiveType::U32,
+                    MaxU64 => PrimitiveType::U64,
+                    MaxU128 => PrimitiveType::U128,
+                    _ => PrimitiveType::U256,
+                };
+                max_value(&Type::Primitive(ty)).unwrap().to_string()
+            }
+            WellFormed => {
+                let ty = self.node_type(args[0].node_id());
+                let term = self.translate(&args[0]);
+                self.ctx
+                    .decls()
+                    .well_formed(ty.skip_reference(), &term)
+                    .unwrap_or_else(|| "true".to_string())
+            }
+            NoOp => "true".to_string(),
+            // Specifications are only evaluated on paths which did not abort.
+            AbortFlag => "false".to_string(),
+            BitOr | BitAnd | Xor | Shl | Shr | Bv2Int | Int2Bv => {
+                self.unsupported(id, "bitwise operation")
+            }
+            EmptyEventStore | ExtendEventStore | EventStoreIncludes | EventStoreIncludedIn => {
+                self.unsupported(id, "event specification")
+            }
+            CanModify => self.unsupported(id, "`modifies` specification"),
+            TypeValue | TypeDomain | ResourceDomain | RangeVec | Range | Tuple | Result(_)
+            | Old | AbortCode | BoxValue | UnboxValue => {
+                self.unsupported(id, &format!("specification operation `{:?}`", oper))
+            }
+        }
+    }
+
+    fn translate_spec_fun_call(
+        &mut self,
+        id: NodeId,
+        mid: ModuleId,
+        fid: SpecFunId,
+        labels: &Option<Vec<MemoryLabel>>,
+        args: &[Exp],
+    ) -> String {
+        let inst = self.node_inst(id);
+        let module_env = self.env.get_module(mid);
+        let decl = module_env.get_spec_fun(fid);
+        if decl.body.is_none() && !decl.uninterpreted {
+            if let Some(term) = self.translate_native_spec_fun(id, &module_env, fid, args) {
+                return term;
+            }
+            return self.unsupported(
+                id,
+                &format!(
+                    "native specification function `{}`",
+                    decl.name.display(self.env.symbol_pool())
+                ),
+            );
+        }
+        let name = smt_symbol(&boogie_spec_fun_name(&module_env, fid, &inst, false));
+        if !self.ctx.decls().has_function(&name) {
+            self.define_spec_fun(mid, fid, &inst, &name);
+        }
+        let mut call_args = vec![];
+        for (i, memory) in decl.used_memory.iter().enumerate() {
+            let memory = memory.clone().instantiate(&inst);
+            let label = labels.as_ref().map(|labels| labels[i]);
+            match self.ctx.memory(&memory, label) {
+                Some(term) => call_args.push(term),
+                None => return self.unsupported(id, "resource access"),
+            }
+        }
+        call_args.extend(args.iter().map(|arg| self.translate(arg)));
+        if call_args.is_empty() {
+            name
+        } else {
+            format!("({} {})", name, call_args.join(" "))
+        }
+    }
+
+    /// Translates a call of a specification function derived from a native function of the
+    /// standard library, or returns `None` if the function is not supported.
+    fn translate_native_spec_fun(
+        &mut self,
+        id: NodeId,
+        module_env: &ModuleEnv<'_>,
+        fid: SpecFunId,
+        args: &[Exp],
+    ) -> Option<String> {
+        if *module_env.get_name().addr() != self.env.get_stdlib_address() {
+            return None;
+        }
+        let pool = self.env.symbol_pool();
+        let module_name = pool.string(module_env.get_name().name());
+        let fun_name = pool.string(module_env.get_spec_fun(fid).name);
+        let mut args = args.iter().map(|arg| self.translate(arg)).collect_vec();
+        Some(match (module_name.as_str(), fun_name.as_str()) {
+            ("signer", "$borrow_address") => format!("($addr {})", args.remove(0)),
+            ("vector", "$empty") => {
+                let ty = self.node_type(id);
+                let sort = self.ctx.decls().sort(&ty);
+                format!("(as seq.empty {})", sort)
+            }
+            ("vector", "$length") => format!("(seq.len {})", args.remove(0)),
+            ("vector", "$borrow") | ("vector", "$borrow_mut") => {
+                format!("(seq.nth {} {})", args[0], args[1])
+            }
+            _ => return None,
+        })
+    }
+
+    fn define_spec_fun(&mut self, mid: ModuleId, fid: SpecFunId, inst: &[Type], name: &str) {
+        let module_env = self.env.get_module(mid);
+        let decl = module_env.get_spec_fun(fid);
+        let decls = self.ctx.decls();
+        decls.reserve_function(name);
+        decls.set_loc(decl.loc.clone());
+        let memory = decl
+            .used_memory
+            .iter()
+            .enumerate()
+            .map(|(i, memory)| (memory.clone().instantiate(inst), format!("$m{}", i)))
+            .collect_vec();
+        let mut params = vec![];
+        for (memory, param) in &memory {
+            params.push((param.clone(), decls.memory_sort(memory)));
+        }
+        let mut locals = vec![];
+        for (i, (param_name, ty)) in decl.params.iter().enumerate() {
+            let param = format!("$p{}", i);
+            params.push((param.clone(), decls.sort(&ty.instantiate(inst))));
+            locals.push((*param_name, param));
+        }
+        let result_sort = decls.sort(&decl.result_type.instantiate(inst));
+        let definition = match &decl.body {
+            Some(body) if !decl.uninterpreted => {
+                let mut ctx = SpecFunContext { decls, memory };
+                let mut translator = SpecTranslator::new(self.env, &mut ctx, inst);
+                translator.locals = locals;
+                let body = translator.translate(body);
+                let keyword = if self.env.is_spec_fun_recursive(mid.qualified(fid)) {
+                    "define-fun-rec"
+                } else {
+                    "define-fun"
+                };
+                format!(
+                    "({} {} ({}) {} {})",
+                    keyword,
+                    name,
+                    params
+                        .iter()
+                        .map(|(param, sort)| format!("({} {})", param, sort))
+                        .join(" "),
+                    result_sort,
+                    body
+                )
+            }
+            _ => format!(
+                "(declare-fun {} ({}) {})",
+                name,
+                params.iter().map(|(_, sort)| sort).join(" "),
+                result_sort
+            ),
+        };
+        self.ctx.decls().add_function(definition);
+    }
+}
@@ -8,6 +8,7 @@
 
 use std::{
     collections::BTreeMap,
+    path::Path,
     str::FromStr,
     sync::atomic::{AtomicBool, Ordering},
 };
@@ -30,7 +31,12 @@ use move_model::{
     model::VerificationScope, options::ModelBuilderOptions, simplifier::SimplificationPass,
 };
 use move_prover_boogie_backend::options::{BoogieOptions, VectorTheory};
-use move_stackless_bytecode::options::{AutoTraceLevel, ProverOptions};
+
+use crate::report::ReportFormat;
+use move_stackless_bytecode::{
+    gas_bound_analysis::GasSchedule,
+    options::{AutoTraceLevel, ProverOptions},
+};
 
 /// Atomic used to prevent re-initialization of logging.
 static LOGGER_CONFIGURED: AtomicBool = AtomicBool::new(false);
@@ -61,6 +67,8 @@ pub struct Options {
     pub run_read_write_set: bool,
     /// Whether to run the internal reference escape analysis instead of the prover
     pub run_escape: bool,
+    /// Whether to run the gas bound analysis instead of the prover
+    pub run_gas_bound: bool,
     /// The paths to the Move sources.
     pub move_sources: Vec<String>,
     /// The paths to any dependencies for the Move sources. Those will not be verified but
@@ -72,6 +80,17 @@ pub struct Options {
     pub experimental_pipeline: bool,
     /// Options for printing out modules and functions reachable by script functions
     pub script_reach: bool,
+    /// The path to a cache of verification results. If set, functions whose verification
+    /// conditions did not change since they were last verified are not verified again.
+    pub verification_cache: Option<String>,
+    /// Whether to generate SMT-LIB directly and check it with the solver, instead of going
+    /// through Boogie.
+    pub smt_backend: bool,
+    /// The format of a report of the results per verified function and module, if one
+    /// should be written.
+    pub report: Option<ReportFormat>,
+    /// The file the report is written to.
+    pub report_file: String,
 
     /// BEGIN OF STRUCTURED OPTIONS. DO NOT ADD VALUE FIELDS AFTER THIS
     /// Options for the model builder.
@@ -99,6 +118,7 @@ impl Default for Options {
             run_errmapgen: false,
             run_read_write_set: false,
             run_escape: false,
+            run_gas_bound: false,
             verbosity_level: LevelFilter::Info,
             move_sources: vec![],
             move_deps: vec![],
@@ -111,6 +131,10 @@ impl Default for Options {
             errmapgen: ErrmapOptions::default(),
             experimental_pipeline: false,
             script_reach: false,
+            verification_cache: None,
+            smt_backend: false,
+            report: None,
+            report_file: "prover-report.json".to_string(),
         }
     }
 }
@@ -212,6 +236,37 @@ impl Options {
                     .short('t')
                     .help("enables automatic tracing of expressions in prover errors")
             )
+            .arg(
+                Arg::new("emit-counterexample-tests")
+                    .long("emit-counterexample-tests")
+                    .help("writes Move unit tests which replay the counterexamples of \
+                     verification errors into a `counterexamples` directory next to the \
+                     boogie output")
+            )
+            .arg(
+                Arg::new("cache")
+                    .long("cache")
+                    .takes_value(true)
+                    .value_name("FILE")
+                    .help("caches verification results in the given file, and only verifies \
+                     functions whose verification conditions changed since the last run")
+            )
+            .arg(
+                Arg::new("report")
+                    .long("report")
+                    .takes_value(true)
+                    .value_name("FORMAT")
+                    .possible_values(["json"])
+                    .help("writes a report with the status, solver time, number of verification \
+                     conditions, and errors of each verified function and module")
+            )
+            .arg(
+                Arg::new("report-file")
+                    .long("report-file")
+                    .takes_value(true)
+                    .value_name("FILE")
+                    .help("the file the report is written to (default prover-report.json)")
+            )
             .arg(
                 Arg::new("keep")
                     .long("keep")
@@ -317,6 +372,26 @@ impl Options {
                     .long("escape")
                     .help("runs the escape analysis instead of the prover.")
             )
+            .arg(
+                Arg::new("gas-bounds")
+                    .long("gas-bounds")
+                    .help("runs the gas bound analysis instead of the prover, printing the \
+                     worst-case gas of each function in terms of its parameters.")
+            )
+            .arg(
+                Arg::new("check-max-gas")
+                    .long("check-max-gas")
+                    .help("checks the gas bound of functions with a `pragma max_gas` \
+                     against the declared maximum")
+            )
+            .arg(
+                Arg::new("gas-schedule")
+                    .long("gas-schedule")
+                    .takes_value(true)
+                    .value_name("FILE")
+                    .help("a JSON gas schedule for the gas bound analysis \
+                     (default the VM's initial cost schedule)")
+            )
             .arg(
                 Arg::new("read-write-set")
                     .long("read-write-set")
@@ -480,6 +555,12 @@ impl Options {
                     .long("generate-smt")
                     .help("instructs boogie to log smtlib files for verified functions")
             )
+            .arg(
+                Arg::new("smt-backend")
+                    .long("smt-backend")
+                    .help("generates SMT-LIB directly and checks it with the solver, without \
+                     Boogie. Supports a subset of Move and specifications")
+            )
             .arg(
                 Arg::new("experimental-pipeline")
                     .long("experimental-pipeline")
@@ -507,6 +588,24 @@ impl Options {
                     .help("treat functions that do not return (i.e., abort unconditionally) \
                     as inconsistency violations")
             )
+            .arg(
+                Arg::new("spec-coverage")
+                    .long("spec-coverage")
+                    .help("reports public functions without specifications, aborts not covered \
+                    by an `aborts_if`, and vacuous `ensures`")
+            )
+            .arg(
+                Arg::new("infer-loop-invariants")
+                    .long("infer-loop-invariants")
+                    .help("infers simple loop invariants, like index bounds and vector lengths, \
+                    which are assumed in addition to the ones declared")
+            )
+            .arg(
+                Arg::new("suggest-loop-invariants")
+                    .long("suggest-loop-invariants")
+                    .help("reports the inferred loop invariants as notes which can be added to \
+                    the source (implies --infer-loop-invariants)")
+            )
             .arg(
                 Arg::new("verify-only")
                     .long("verify-only")
@@ -691,9 +790,42 @@ impl Options {
         if matches.is_present("escape") {
             options.run_escape = true;
         }
+        if matches.is_present("gas-bounds") {
+            options.run_gas_bound = true;
+        }
+        if matches.is_present("check-max-gas") {
+            options.prover.check_max_gas = true;
+        }
+        if matches.is_present("gas-schedule") {
+            let path = matches.value_of("gas-schedule").unwrap();
+            GasSchedule::load(path)
+                .map_err(|e| anyhow!("cannot load gas schedule `{}`: {}", path, e))?;
+            options.prover.gas_schedule = Some(path.to_string());
+        }
         if matches.is_present("trace") {
             options.prover.auto_trace_level = AutoTraceLevel::VerifiedFunction;
         }
+        if matches.is_present("emit-counterexample-tests") {
+            options.prover.trace_initial_memory = true;
+            options.backend.counterexample_tests_dir = Some(
+                Path::new(&options.output_path)
+                    .with_file_name("counterexamples")
+                    .to_string_lossy()
+                    .to_string(),
+            );
+        }
+        if matches.is_present("cache") {
+            options.verification_cache = matches.value_of("cache").map(|s| s.to_string());
+        }
+        if matches.is_present("report") {
+            options.report = match matches.value_of("report").unwrap() {
+                "json" => Some(ReportFormat::Json),
+                _ => unreachable!("should not happen"),
+            };
+        }
+        if matches.is_present("report-file") {
+            options.report_file = matches.value_of("report-file").unwrap().to_string();
+        }
         if matches.is_present("dump-bytecode") {
             options.prover.dump_bytecode = true;
         }
@@ -754,6 +886,9 @@ impl Options {
         if matches.is_present("generate-smt") {
             options.backend.generate_smt = true;
         }
+        if matches.is_present("smt-backend") {
+            options.smt_backend = true;
+        }
 
         if matches.is_present("check-inconsistency") {
             options.prover.check_inconsistency = true;
@@ -761,6 +896,18 @@ impl Options {
         if matches.is_present("unconditional-abort-as-inconsistency") {
             options.prover.unconditional_abort_as_inconsistency = true;
         }
+        if matches.is_present("spec-coverage") {
+            options.prover.spec_coverage = true;
+        }
+
+        if matches.is_present("infer-loop-invariants") {
+            options.prover.infer_loop_invariants = true;
+        }
+        if matches.is_present("suggest-loop-invariants") {
+            options.prover.infer_loop_invariants = true;
+            options.prover.suggest_loop_invariants = true;
+            options.prover.report_severity = Severity::Note;
+        }
 
         if matches.is_present("verify-only") {
             options.prover.verify_scope =
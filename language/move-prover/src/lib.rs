@@ -4,7 +4,10 @@
 
 #![forbid(unsafe_code)]
 
-use crate::cli::Options;
+use crate::{
+    cli::Options, report::VerificationReport, spec_coverage::SpecCoverage,
+    verification_cache::VerificationCache,
+};
 use anyhow::anyhow;
 use codespan_reporting::{
     diagnostic::Severity,
@@ -17,15 +20,23 @@ use move_compiler::shared::PackagePaths;
 use move_docgen::Docgen;
 use move_errmapgen::ErrmapGen;
 use move_model::{
-    code_writer::CodeWriter, model::GlobalEnv, parse_addresses_from_options,
-    run_model_builder_with_options,
+    code_writer::CodeWriter,
+    model::{FunId, GlobalEnv, QualifiedId},
+    parse_addresses_from_options, run_model_builder_with_options,
 };
 use move_prover_boogie_backend::{
-    add_prelude, boogie_wrapper::BoogieWrapper, bytecode_translator::BoogieTranslator,
+    add_prelude,
+    boogie_wrapper::{BoogieError, BoogieWrapper, VerificationStats},
+    bytecode_translator::BoogieTranslator,
+};
+use move_prover_smt_backend::{
+    bytecode_translator::{SmtTranslator, VerificationCondition},
+    solver_wrapper::SmtWrapper,
 };
 use move_stackless_bytecode::{
     escape_analysis::EscapeAnalysisProcessor,
-    function_target_pipeline::{FunctionTargetPipeline, FunctionTargetsHolder},
+    function_target_pipeline::{FunctionTargetPipeline, FunctionTargetsHolder, FunctionVariant},
+    gas_bound_analysis::{get_gas_bound, GasBoundProcessor, GasSchedule},
     number_operation::GlobalNumberOperationState,
     pipeline_factory,
     read_write_set_analysis::{self, ReadWriteSetProcessor},
@@ -38,6 +49,9 @@ use std::{
 };
 
 pub mod cli;
+pub mod report;
+pub mod spec_coverage;
+pub mod verification_cache;
 
 // =================================================================================================
 // Prover API
@@ -88,10 +102,13 @@ pub fn create_init_num_operation_state(env: &GlobalEnv) {
 pub fn run_move_prover_with_model<W: WriteColor>(
     env: &GlobalEnv,
     error_writer: &mut W,
-    options: Options,
+    mut options: Options,
     timer: Option<Instant>,
 ) -> anyhow::Result<()> {
     let now = timer.unwrap_or_else(Instant::now);
+    if options.report.is_some() {
+        options.backend.collect_stats = true;
+    }
 
     let build_duration = now.elapsed();
     check_errors(
@@ -138,6 +155,13 @@ pub fn run_move_prover_with_model<W: WriteColor>(
             Ok(())
         };
     }
+    // Same for gas bound analysis
+    if options.run_gas_bound {
+        return {
+            run_gas_bound(env, &options, now);
+            Ok(())
+        };
+    }
 
     // Check correct backend versions.
     options.backend.check_tool_versions()?;
@@ -149,7 +173,7 @@ pub fn run_move_prover_with_model<W: WriteColor>(
 
     // Create and process bytecode
     let now = Instant::now();
-    let targets = create_and_process_bytecode(&options, env);
+    let mut targets = create_and_process_bytecode(&options, env);
     let trafo_duration = now.elapsed();
     check_errors(
         env,
@@ -158,9 +182,31 @@ pub fn run_move_prover_with_model<W: WriteColor>(
         "exiting with bytecode transformation errors",
     )?;
 
-    // Generate boogie code
+    // Skip functions whose verification conditions did not change since they last verified.
+    let verified_functions = VerificationReport::verified_functions(env, &targets);
+    let mut cached = BTreeSet::new();
+    let mut cache = match &options.verification_cache {
+        Some(path) if !options.prover.generate_only && !options.prover.spec_coverage => {
+            let cache = VerificationCache::load(path);
+            let fingerprints = VerificationCache::fingerprints(env, &options, &targets);
+            cached = cache.remove_cached(env, &mut targets, &fingerprints);
+            if cached.len() == fingerprints.len() {
+                write_report(env, &options, &verified_functions, &cached, &[], &[])?;
+                return Ok(());
+            }
+            Some((path, cache, fingerprints))
+        }
+        _ => None,
+    };
+
+    // Generate boogie code, or SMT-LIB verification conditions for the SMT backend.
     let now = Instant::now();
-    let code_writer = generate_boogie(env, &options, &targets)?;
+    let (code_writer, conditions) = if options.smt_backend {
+        let conditions = SmtTranslator::new(env, &options.backend, &targets).translate();
+        (None, conditions)
+    } else {
+        (Some(generate_boogie(env, &options, &targets)?), vec![])
+    };
     let gen_duration = now.elapsed();
     check_errors(
         env,
@@ -171,8 +217,21 @@ pub fn run_move_prover_with_model<W: WriteColor>(
 
     // Verify boogie code.
     let now = Instant::now();
-    verify_boogie(env, &options, &targets, code_writer)?;
+    let (errors, stats) = match code_writer {
+        Some(code_writer) => verify_boogie(env, &options, &targets, code_writer)?,
+        None => verify_smt(env, &options, &targets, &conditions)?,
+    };
     let verify_duration = now.elapsed();
+    if !options.prover.generate_only {
+        write_report(env, &options, &verified_functions, &cached, &errors, &stats)?;
+    }
+    if let Some((path, cache, fingerprints)) = &mut cache {
+        cache.update(env, fingerprints, &errors);
+        cache.save(path)?;
+    }
+    if options.prover.spec_coverage && !options.prover.generate_only {
+        print!("{}", SpecCoverage::new(env, &targets, &errors));
+    }
 
     // Report durations.
     info!(
@@ -194,6 +253,23 @@ pub fn run_move_prover_with_model<W: WriteColor>(
     )
 }
 
+/// Writes the verification report, if one is requested.
+fn write_report(
+    env: &GlobalEnv,
+    options: &Options,
+    functions: &BTreeSet<QualifiedId<FunId>>,
+    cached: &BTreeSet<QualifiedId<FunId>>,
+    errors: &[BoogieError],
+    stats: &[VerificationStats],
+) -> anyhow::Result<()> {
+    if let Some(format) = options.report {
+        debug!("writing verification report to `{}`", options.report_file);
+        VerificationReport::new(env, options, functions, cached, errors, stats)
+            .write(format, &options.report_file)?;
+    }
+    Ok(())
+}
+
 pub fn check_errors<W: WriteColor>(
     env: &GlobalEnv,
     options: &Options,
@@ -220,15 +296,18 @@ pub fn generate_boogie(
     Ok(writer)
 }
 
+/// Writes the boogie code and verifies it, unless only generation is requested. Returns the
+/// errors reported by the backend, and the statistics of the verified procedures.
 pub fn verify_boogie(
     env: &GlobalEnv,
     options: &Options,
     targets: &FunctionTargetsHolder,
     writer: CodeWriter,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<(Vec<BoogieError>, Vec<VerificationStats>)> {
     let output_existed = std::path::Path::new(&options.output_path).exists();
     debug!("writing boogie to `{}`", &options.output_path);
     writer.process_result(|result| fs::write(&options.output_path, result))?;
+    let mut result = (vec![], vec![]);
     if !options.prover.generate_only {
         let boogie = BoogieWrapper {
             env,
@@ -236,12 +315,43 @@ pub fn verify_boogie(
             writer: &writer,
             options: &options.backend,
         };
-        boogie.call_boogie_and_verify_output(&options.output_path)?;
+        result = boogie.call_boogie_and_verify_output(&options.output_path)?;
         if !output_existed && !options.backend.keep_artifacts {
             std::fs::remove_file(&options.output_path).unwrap_or_default();
         }
     }
-    Ok(())
+    Ok(result)
+}
+
+/// Writes the SMT-LIB verification conditions next to the boogie output and checks them,
+/// unless only generation is requested. Returns the errors reported by the solver, and the
+/// statistics of the verification conditions.
+pub fn verify_smt(
+    env: &GlobalEnv,
+    options: &Options,
+    targets: &FunctionTargetsHolder,
+    conditions: &[VerificationCondition],
+) -> anyhow::Result<(Vec<BoogieError>, Vec<VerificationStats>)> {
+    let smt_file = Path::new(&options.output_path)
+        .with_extension("smt2")
+        .to_string_lossy()
+        .to_string();
+    let output_existed = Path::new(&smt_file).exists();
+    let wrapper = SmtWrapper {
+        env,
+        targets,
+        options: &options.backend,
+    };
+    debug!("writing SMT-LIB to `{}`", &smt_file);
+    wrapper.write_file(conditions, &smt_file)?;
+    let mut result = (vec![], vec![]);
+    if !options.prover.generate_only {
+        result = wrapper.check_and_report(conditions)?;
+        if !output_existed && !options.backend.keep_artifacts {
+            std::fs::remove_file(&smt_file).unwrap_or_default();
+        }
+    }
+    Ok(result)
 }
 
 /// Create bytecode and process it.
@@ -405,6 +515,42 @@ fn run_read_write_set(env: &GlobalEnv, options: &Options, now: Instant) {
     info!("{:.3}s analyzing", (end - start).as_secs_f64());
 }
 
+fn run_gas_bound(env: &GlobalEnv, options: &Options, now: Instant) {
+    let mut targets = FunctionTargetsHolder::default();
+    for module_env in env.get_modules() {
+        for func_env in module_env.get_functions() {
+            targets.add_target(&func_env)
+        }
+    }
+    let mut pipeline = FunctionTargetPipeline::default();
+    pipeline.add_processor(GasBoundProcessor::new(GasSchedule::from_options(
+        &options.prover,
+    )));
+
+    let start = now.elapsed();
+    pipeline.run(env, &mut targets);
+    let end = now.elapsed();
+
+    // print the bounds of the functions in the target modules, marking entry functions
+    for module_env in env.get_modules() {
+        if !module_env.is_target() {
+            continue;
+        }
+        for func_env in module_env.get_functions() {
+            let target = targets.get_target(&func_env, &FunctionVariant::Baseline);
+            if let Some(bound) = get_gas_bound(&target) {
+                println!(
+                    "{}{}: {}",
+                    if func_env.is_entry() { "entry " } else { "" },
+                    func_env.get_full_name_str(),
+                    bound.display(&func_env)
+                );
+            }
+        }
+    }
+    info!("in ms, analysis took {:.3}", (end - start).as_millis())
+}
+
 fn run_escape(env: &GlobalEnv, options: &Options, now: Instant) {
     let mut targets = FunctionTargetsHolder::default();
     for module_env in env.get_modules() {
@@ -0,0 +1,392 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Structured report of verification results.
+//!
+//! Records, for each verified function and each target module, the verification status, the
+//! time the solver took, the number of verification conditions, the errors with their
+//! locations, and the solver options used. Unlike the diagnostics, the report is meant to be
+//! consumed by tools, e.g. to track verification regressions and slow functions in CI.
+
+use crate::cli::Options;
+use move_model::{
+    model::{FunId, GlobalEnv, Loc, QualifiedId},
+    pragmas::{SEED_PRAGMA, TIMEOUT_PRAGMA},
+};
+use move_prover_boogie_backend::{
+    boogie_wrapper::{BoogieError, BoogieErrorKind, VerificationStats},
+    options::VectorTheory,
+};
+use move_stackless_bytecode::function_target_pipeline::{FunctionTargetsHolder, FunctionVariant};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+};
+
+/// The format of the verification report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    Json,
+}
+
+/// The verification status of a function or module. The order reflects severity, so the
+/// status of a module is the maximum of the status of its functions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Verified,
+    Timeout,
+    Inconsistent,
+    Failed,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerificationReport {
+    pub functions: Vec<FunctionRecord>,
+    pub modules: Vec<ModuleRecord>,
+    /// Errors which could not be attributed to a function.
+    pub other_errors: Vec<ErrorRecord>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FunctionRecord {
+    pub module: String,
+    pub function: String,
+    pub status: Status,
+    /// Whether the result was taken from the verification cache, without running the solver.
+    pub cached: bool,
+    /// The solver time, summed over all verification variants of the function.
+    pub time_ms: u64,
+    /// The number of verification conditions, summed over all verification variants.
+    pub vc_count: usize,
+    pub errors: Vec<ErrorRecord>,
+    pub solver: SolverRecord,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModuleRecord {
+    pub module: String,
+    pub status: Status,
+    pub functions: usize,
+    pub failed_functions: usize,
+    pub cached_functions: usize,
+    pub time_ms: u64,
+    pub vc_count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorRecord {
+    pub kind: String,
+    pub message: String,
+    pub file: Option<String>,
+    /// The 1-based line of the error, if known.
+    pub line: Option<usize>,
+    /// The 1-based column of the error, if known.
+    pub column: Option<usize>,
+}
+
+/// The solver options a function has been verified with.
+#[derive(Debug, Serialize)]
+pub struct SolverRecord {
+    pub backend: String,
+    pub solver: String,
+    /// The timeout per verification condition, in seconds, after pragmas are applied.
+    pub timeout: usize,
+    pub seed: usize,
+    pub vector_theory: VectorTheory,
+    pub proc_cores: usize,
+    pub flags: Vec<String>,
+}
+
+impl VerificationReport {
+    /// Returns the functions which have verification variants and hence appear in the report.
+    pub fn verified_functions(
+        env: &GlobalEnv,
+        targets: &FunctionTargetsHolder,
+    ) -> BTreeSet<QualifiedId<FunId>> {
+        targets
+            .get_funs()
+            .filter(|fun_id| {
+                targets
+                    .get_target_variants(&env.get_function(*fun_id))
+                    .iter()
+                    .any(FunctionVariant::is_verified)
+            })
+            .collect()
+    }
+
+    /// Creates the report for the given verified functions, of which `cached` were not
+    /// sent to the backend, from the errors and statistics of the backend.
+    pub fn new(
+        env: &GlobalEnv,
+        options: &Options,
+        functions: &BTreeSet<QualifiedId<FunId>>,
+        cached: &BTreeSet<QualifiedId<FunId>>,
+        errors: &[BoogieError],
+        stats: &[VerificationStats],
+    ) -> Self {
+        let mut fun_errors: BTreeMap<QualifiedId<FunId>, Vec<&BoogieError>> = BTreeMap::new();
+        let mut other_errors = vec![];
+        for error in errors.iter().filter(|e| !e.kind.is_spec_coverage()) {
+            match error.verified_function().or_else(|| {
+                env.get_enclosing_function(&error.loc)
+                    .map(|f| f.get_qualified_id())
+            }) {
+                Some(fun_id) if functions.contains(&fun_id) => {
+                    fun_errors.entry(fun_id).or_default().push(error)
+                }
+                _ => other_errors.push(ErrorRecord::new(env, error)),
+            }
+        }
+
+        let mut function_records = vec![];
+        let mut modules: BTreeMap<String, ModuleRecord> = BTreeMap::new();
+        for fun_id in functions {
+            let fun_env = env.get_function(*fun_id);
+            let module = fun_env.module_env.get_full_name_str();
+            let errors = fun_errors.remove(fun_id).unwrap_or_default();
+            let status = errors
+                .iter()
+                .map(|e| match e.kind {
+                    BoogieErrorKind::Inconclusive => Status::Timeout,
+                    BoogieErrorKind::Inconsistency => Status::Inconsistent,
+                    _ => Status::Failed,
+                })
+                .max()
+                .unwrap_or(Status::Verified);
+            let (time_ms, vc_count) = stats
+                .iter()
+                .filter(|s| s.fun_id == *fun_id)
+                .fold((0, 0), |(time, vcs), s| {
+                    (time + s.duration.as_millis() as u64, vcs + s.vc_count)
+                });
+            let record = FunctionRecord {
+                module: module.clone(),
+                function: fun_env.get_full_name_str(),
+                status,
+                cached: cached.contains(fun_id),
+                time_ms,
+                vc_count,
+                errors: errors.iter().map(|e| ErrorRecord::new(env, e)).collect(),
+                solver: SolverRecord {
+                    backend: if options.smt_backend { "smt" } else { "boogie" }.to_string(),
+                    solver: if options.backend.use_cvc5 {
+                        "cvc5"
+                    } else {
+                        "z3"
+                    }
+                    .to_string(),
+                    timeout: options.backend.adjust_timeout(
+                        fun_env.get_num_pragma(TIMEOUT_PRAGMA, || options.backend.vc_timeout),
+                    ),
+                    seed: fun_env.get_num_pragma(SEED_PRAGMA, || options.backend.random_seed),
+                    vector_theory: options.backend.vector_theory,
+                    proc_cores: options.backend.proc_cores,
+                    flags: options.backend.boogie_flags.clone(),
+                },
+            };
+            let module_record = modules
+                .entry(module.clone())
+                .or_insert_with(|| ModuleRecord {
+                    module,
+                    status: Status::Verified,
+                    functions: 0,
+                    failed_functions: 0,
+                    cached_functions: 0,
+                    time_ms: 0,
+                    vc_count: 0,
+                });
+            module_record.status = module_record.status.max(record.status);
+            module_record.functions += 1;
+            if record.status != Status::Verified {
+                module_record.failed_functions += 1;
+            }
+            if record.cached {
+                module_record.cached_functions += 1;
+            }
+            module_record.time_ms += record.time_ms;
+            module_record.vc_count += record.vc_count;
+            function_records.push(record);
+        }
+        Self {
+            functions: function_records,
+            modules: modules.into_values().collect(),
+            other_errors,
+        }
+    }
+
+    /// Writes the report in the given format to the given file.
+    pub fn write(&self, format: ReportFormat, path: &str) -> anyhow::Result<()> {
+        if let Some(dir) = Path::new(path).parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let content = match format {
+            ReportFormat::Json => serde_json::to_string_pretty(self)?,
+        };
+        fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+impl ErrorRecord {
+    fn new(env: &GlobalEnv, error: &BoogieError) -> Self {
+        let kind = match error.kind {
+            BoogieErrorKind::Assertion => "assertion",
+            BoogieErrorKind::Inconclusive => "inconclusive",
+            BoogieErrorKind::Inconsistency => "inconsistency",
+            BoogieErrorKind::Internal => "internal",
+            BoogieErrorKind::VacuousEnsures => "vacuous_ensures",
+            BoogieErrorKind::UncoveredAbort => "uncovered_abort",
+        };
+        let (file, line, column) = match Self::position(env, &error.loc) {
+            Some((file, line, column)) => (Some(file), Some(line), Some(column)),
+            None => (None, None, None),
+        };
+        Self {
+            kind: kind.to_string(),
+            message: error.message.clone(),
+            file,
+            line,
+            column,
+        }
+    }
+
+    fn position(env: &GlobalEnv, loc: &Loc) -> Option<(String, usize, usize)> {
+        if loc == &env.unknown_loc() {
+            return None;
+        }
+        env.get_file_and_location(loc).map(|(file, location)| {
+            (
+                file,
+                location.line.0 as usize + 1,
+                location.column.0 as usize + 1,
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use move_compiler::shared::PackagePaths;
+    use move_model::{parse_addresses_from_options, run_model_builder_with_options};
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    const SOURCE: &str = r#"
+module 0x42::M {
+    fun inconsistent(): u64 { 1 }
+    fun failed(): u64 { 2 }
+    fun timeout(): u64 { 3 }
+    fun cached(): u64 { 4 }
+}
+"#;
+
+    fn build_env(options: &Options) -> GlobalEnv {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("M.move");
+        fs::write(&path, SOURCE).unwrap();
+        let env = run_model_builder_with_options(
+            vec![PackagePaths {
+                name: None,
+                paths: vec![path.to_string_lossy().to_string()],
+                named_address_map: parse_addresses_from_options(vec![]).unwrap(),
+            }],
+            vec![],
+            options.model_builder.clone(),
+        )
+        .unwrap();
+        assert!(!env.has_errors());
+        env
+    }
+
+    #[test]
+    fn test_report() {
+        let options = Options::default();
+        let env = build_env(&options);
+        let module_env = env
+            .find_module_by_name(env.symbol_pool().make("M"))
+            .unwrap();
+        let fun_env = |name: &str| {
+            module_env
+                .find_function(env.symbol_pool().make(name))
+                .unwrap()
+        };
+        let error = |kind: BoogieErrorKind, loc: Loc| BoogieError {
+            kind,
+            loc,
+            message: "error".to_string(),
+            execution_trace: vec![],
+            model: None,
+        };
+        let in_fun = |kind: BoogieErrorKind, name: &str| error(kind, fun_env(name).get_loc());
+        let stats = |name: &str, millis: u64, vc_count: usize| VerificationStats {
+            fun_id: fun_env(name).get_qualified_id(),
+            procedure: format!("$42_M_{}$verify", name),
+            duration: Duration::from_millis(millis),
+            vc_count,
+        };
+
+        let functions = ["inconsistent", "failed", "timeout", "cached"]
+            .into_iter()
+            .map(|name| fun_env(name).get_qualified_id())
+            .collect();
+        let cached = BTreeSet::from([fun_env("cached").get_qualified_id()]);
+        let errors = [
+            in_fun(BoogieErrorKind::Inconclusive, "inconsistent"),
+            in_fun(BoogieErrorKind::Inconsistency, "inconsistent"),
+            in_fun(BoogieErrorKind::Assertion, "failed"),
+            in_fun(BoogieErrorKind::Inconclusive, "failed"),
+            in_fun(BoogieErrorKind::Inconclusive, "timeout"),
+            // Spec coverage findings are not verification errors.
+            in_fun(BoogieErrorKind::UncoveredAbort, "cached"),
+            error(BoogieErrorKind::Internal, env.unknown_loc()),
+        ];
+        let stats = [
+            stats("inconsistent", 100, 2),
+            stats("inconsistent", 50, 1),
+            stats("failed", 200, 4),
+        ];
+        let report = VerificationReport::new(&env, &options, &functions, &cached, &errors, &stats);
+
+        let functions = report
+            .functions
+            .iter()
+            .map(|f| {
+                (
+                    f.function.as_str(),
+                    f.status,
+                    f.cached,
+                    f.time_ms,
+                    f.vc_count,
+                    f.errors.len(),
+                )
+            })
+            .collect::<BTreeSet<_>>();
+        assert_eq!(
+            functions,
+            BTreeSet::from([
+                ("M::inconsistent", Status::Inconsistent, false, 150, 3, 2),
+                ("M::failed", Status::Failed, false, 200, 4, 2),
+                ("M::timeout", Status::Timeout, false, 0, 0, 1),
+                ("M::cached", Status::Verified, true, 0, 0, 0),
+            ])
+        );
+
+        assert_eq!(report.modules.len(), 1);
+        let module = &report.modules[0];
+        assert_eq!(module.status, Status::Failed);
+        assert_eq!(module.functions, 4);
+        assert_eq!(module.failed_functions, 3);
+        assert_eq!(module.cached_functions, 1);
+        assert_eq!(module.time_ms, 350);
+        assert_eq!(module.vc_count, 7);
+
+        assert_eq!(report.other_errors.len(), 1);
+        assert_eq!(report.other_errors[0].kind, "internal");
+        assert_eq!(report.other_errors[0].line, None);
+    }
+}
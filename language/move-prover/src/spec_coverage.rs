@@ -0,0 +1,182 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Specification coverage report.
+//!
+//! Summarizes, per target module, the public functions which have neither an `ensures` nor an
+//! `aborts_if`, together with the findings of the coverage checks of the backend: aborts of
+//! functions without `aborts_if`, and `ensures` which also hold if the function body is
+//! havocked, and therefore do not say anything about the function.
+
+use move_model::{
+    ast::ConditionKind,
+    model::{FunctionVisibility, GlobalEnv, Loc, ModuleId},
+};
+use move_prover_boogie_backend::boogie_wrapper::{BoogieError, BoogieErrorKind, TraceEntry};
+use move_stackless_bytecode::function_target_pipeline::{
+    FunctionTargetsHolder, FunctionVariant, VerificationFlavor,
+};
+use std::{collections::BTreeMap, fmt};
+
+/// The specification coverage of the target modules.
+pub struct SpecCoverage {
+    modules: BTreeMap<ModuleId, ModuleCoverage>,
+}
+
+#[derive(Default)]
+struct ModuleCoverage {
+    name: String,
+    functions: usize,
+    unspecified: Vec<String>,
+    uncovered_aborts: Vec<(String, String)>,
+    vacuous_ensures: Vec<(String, String)>,
+}
+
+impl SpecCoverage {
+    /// Computes the coverage from the specs of the target modules and the coverage findings
+    /// in the errors reported by the backend.
+    pub fn new(env: &GlobalEnv, targets: &FunctionTargetsHolder, errors: &[BoogieError]) -> Self {
+        let mut modules = BTreeMap::new();
+        for module_env in env.get_modules().filter(|m| m.is_target()) {
+            let mut coverage = ModuleCoverage {
+                name: module_env.get_full_name_str(),
+                ..Default::default()
+            };
+            for fun_env in module_env.get_functions() {
+                if fun_env.is_native() || fun_env.is_intrinsic() {
+                    continue;
+                }
+                if targets.has_target(
+                    &fun_env,
+                    &FunctionVariant::Verification(VerificationFlavor::Regular),
+                ) {
+                    coverage.functions += 1;
+                }
+                let spec = fun_env.get_spec();
+                if fun_env.visibility() == FunctionVisibility::Public
+                    && spec.filter_kind(ConditionKind::Ensures).next().is_none()
+                    && spec.filter_kind(ConditionKind::AbortsIf).next().is_none()
+                {
+                    coverage.unspecified.push(fun_env.get_full_name_str());
+                }
+            }
+            modules.insert(module_env.get_id(), coverage);
+        }
+
+        for error in errors {
+            let (loc, module_id, name) = match error.kind {
+                BoogieErrorKind::UncoveredAbort => {
+                    let loc = Self::abort_loc(error).unwrap_or(&error.loc);
+                    match env.get_enclosing_function(&error.loc) {
+                        Some(fun_env) => (
+                            loc,
+                            fun_env.module_env.get_id(),
+                            fun_env.get_full_name_str(),
+                        ),
+                        None => continue,
+                    }
+                }
+                BoogieErrorKind::VacuousEnsures => match env.get_enclosing_function(&error.loc) {
+                    Some(fun_env) => (
+                        &error.loc,
+                        fun_env.module_env.get_id(),
+                        fun_env.get_full_name_str(),
+                    ),
+                    None => match env.get_enclosing_module(&error.loc) {
+                        Some(module_env) => (
+                            &error.loc,
+                            module_env.get_id(),
+                            module_env.get_full_name_str(),
+                        ),
+                        None => continue,
+                    },
+                },
+                _ => continue,
+            };
+            if let Some(coverage) = modules.get_mut(&module_id) {
+                let entry = (name, loc.display_line_only(env).to_string());
+                if error.kind == BoogieErrorKind::UncoveredAbort {
+                    coverage.uncovered_aborts.push(entry);
+                } else {
+                    coverage.vacuous_ensures.push(entry);
+                }
+            }
+        }
+        for coverage in modules.values_mut() {
+            coverage.uncovered_aborts.sort();
+            coverage.uncovered_aborts.dedup();
+            coverage.vacuous_ensures.sort();
+            coverage.vacuous_ensures.dedup();
+        }
+        Self { modules }
+    }
+
+    /// Returns the location at which the execution trace of an uncovered abort aborted.
+    fn abort_loc(error: &BoogieError) -> Option<&Loc> {
+        let mut last_loc = None;
+        for entry in &error.execution_trace {
+            match entry {
+                TraceEntry::AtLocation(loc) => last_loc = Some(loc),
+                TraceEntry::Abort(..) => return last_loc,
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+impl fmt::Display for SpecCoverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .modules
+            .values()
+            .map(|m| m.name.len())
+            .max()
+            .unwrap_or_default()
+            .max("module".len());
+        writeln!(f, "Specification coverage:")?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:<width$}  functions  unspecified  uncovered aborts  vacuous ensures",
+            "module",
+            width = width
+        )?;
+        for coverage in self.modules.values() {
+            writeln!(
+                f,
+                "{:<width$}  {:>9}  {:>11}  {:>16}  {:>15}",
+                coverage.name,
+                coverage.functions,
+                coverage.unspecified.len(),
+                coverage.uncovered_aborts.len(),
+                coverage.vacuous_ensures.len(),
+                width = width
+            )?;
+        }
+        let mut first = true;
+        for coverage in self.modules.values() {
+            let findings = coverage
+                .unspecified
+                .iter()
+                .map(|name| format!("{}: public function without `ensures` or `aborts_if`", name))
+                .chain(coverage.uncovered_aborts.iter().map(|(name, loc)| {
+                    format!("{}: abort not covered by an `aborts_if` {}", name, loc)
+                }))
+                .chain(
+                    coverage
+                        .vacuous_ensures
+                        .iter()
+                        .map(|(name, loc)| format!("{}: vacuous `ensures` {}", name, loc)),
+                );
+            for finding in findings {
+                if first {
+                    writeln!(f)?;
+                    first = false;
+                }
+                writeln!(f, "{}", finding)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,519 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! A persistent cache of verification results for incremental verification.
+//!
+//! Each verified function is associated with a fingerprint of the inputs of its verification
+//! conditions: the stackless bytecode of its verification variants after the function target
+//! pipeline (which includes its specs, the invariants it uses, and the specs of opaque callees
+//! inlined as assumptions and assertions), the code of the non-opaque callees it depends on,
+//! and the spec functions, structs, and axioms referenced by any of those. Functions whose
+//! fingerprint matches the one recorded after their last successful verification are not
+//! sent to the backend again.
+
+use crate::cli::Options;
+use log::{debug, info, warn};
+use move_command_line_common::files::FileHash;
+use move_model::{
+    ast::{Exp, ExpData, Operation},
+    model::{FunId, GlobalEnv, QualifiedId, SpecFunId, StructId, VerificationScope},
+    ty::Type,
+};
+use move_prover_boogie_backend::boogie_wrapper::BoogieError;
+use move_stackless_bytecode::{
+    function_target::FunctionTarget,
+    function_target_pipeline::{FunctionTargetsHolder, FunctionVariant},
+    stackless_bytecode::Bytecode,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+    fs,
+    path::Path,
+};
+
+/// The verification cache, mapping the full name of each function which verified successfully
+/// to the fingerprint of its verification conditions at that time.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct VerificationCache {
+    verified: BTreeMap<String, String>,
+}
+
+impl VerificationCache {
+    /// Loads the cache from the given file. A missing or unreadable cache is treated as empty.
+    pub fn load(path: &str) -> Self {
+        if !Path::new(path).exists() {
+            return Self::default();
+        }
+        match fs::read_to_string(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|s| Ok(serde_json::from_str(&s)?))
+        {
+            Ok(cache) => cache,
+            Err(e) => {
+                warn!("ignoring verification cache `{}`: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Saves the cache to the given file.
+    pub fn save(&self, path: &str) -> anyhow::Result<()> {
+        if let Some(dir) = Path::new(path).parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Computes the fingerprints of all functions which have verification variants.
+    pub fn fingerprints(
+        env: &GlobalEnv,
+        options: &Options,
+        targets: &FunctionTargetsHolder,
+    ) -> BTreeMap<QualifiedId<FunId>, String> {
+        // Options which determine which functions are verified, but not how, do not
+        // invalidate the cache. The backend which checks the conditions does, as a function
+        // verified by one backend may fail, or time out, with the other.
+        let mut prover_options = options.prover.clone();
+        prover_options.verify_scope = VerificationScope::All;
+        let options_text = format!(
+            "{}\n{}\nsmt_backend: {}\n",
+            serde_json::to_string(&prover_options).expect("serializable options"),
+            serde_json::to_string(&options.backend).expect("serializable options"),
+            options.smt_backend,
+        );
+        let mut axioms_text = String::new();
+        let mut axiom_spec_funs = BTreeSet::new();
+        for module_env in env.get_modules() {
+            for cond in module_env.get_spec().filter_kind_axiom() {
+                writeln!(axioms_text, "axiom {}", cond.exp.display(env)).unwrap();
+                collect_spec_funs(&cond.exp, &mut axiom_spec_funs);
+            }
+        }
+
+        let mut result = BTreeMap::new();
+        for fun_id in targets.get_funs() {
+            let fun_env = env.get_function(fun_id);
+            let variants = targets
+                .get_target_variants(&fun_env)
+                .into_iter()
+                .filter(FunctionVariant::is_verified)
+                .collect::<Vec<_>>();
+            if variants.is_empty() {
+                continue;
+            }
+            let mut text = options_text.clone();
+            text.push_str(&axioms_text);
+            let mut spec_funs = axiom_spec_funs.clone();
+            let mut structs = BTreeSet::new();
+            for variant in &variants {
+                let target = targets.get_target(&fun_env, variant);
+                add_target(&target, &mut text, &mut spec_funs, &mut structs);
+            }
+            // Non-opaque callees are inlined into the verification conditions, so their
+            // code matters. The code of opaque callees does not, as only their specs are used.
+            let mut todo = fun_env
+                .get_called_functions()
+                .into_iter()
+                .collect::<Vec<_>>();
+            let mut visited = BTreeSet::new();
+            while let Some(callee_id) = todo.pop() {
+                if !visited.insert(callee_id) {
+                    continue;
+                }
+                let callee_env = env.get_function(callee_id);
+                if callee_env.is_opaque() {
+                    continue;
+                }
+                if let Some(data) = targets.get_data(&callee_id, &FunctionVariant::Baseline) {
+                    let target = FunctionTarget::new(&callee_env, data);
+                    add_target(&target, &mut text, &mut spec_funs, &mut structs);
+                }
+                todo.extend(callee_env.get_called_functions());
+            }
+            add_spec_funs(env, spec_funs, &mut text, &mut structs);
+            add_structs(env, structs, &mut text);
+            result.insert(fun_id, FileHash::new(&text).to_string());
+        }
+        result
+    }
+
+    /// Removes the verification variants of all functions whose fingerprint matches the cached
+    /// one, so they are not sent to the backend. Returns the functions removed.
+    pub fn remove_cached(
+        &self,
+        env: &GlobalEnv,
+        targets: &mut FunctionTargetsHolder,
+        fingerprints: &BTreeMap<QualifiedId<FunId>, String>,
+    ) -> BTreeSet<QualifiedId<FunId>> {
+        let mut removed = BTreeSet::new();
+        for (fun_id, fingerprint) in fingerprints {
+            let name = full_name(env, *fun_id);
+            if self.verified.get(&name) != Some(fingerprint) {
+                continue;
+            }
+            debug!("cached-verified `{}`", name);
+            let fun_env = env.get_function(*fun_id);
+            for variant in targets.get_target_variants(&fun_env) {
+                if variant.is_verified() {
+                    targets.remove_target_data(fun_id, &variant);
+                }
+            }
+            removed.insert(*fun_id);
+        }
+        info!("{} functions cached-verified", removed.len());
+        removed
+    }
+
+    /// Updates the cache with the outcome of verifying the functions with the given
+    /// fingerprints. Functions with errors are removed from the cache. If an error cannot be
+    /// attributed to a function, no function is recorded as verified.
+    pub fn update(
+        &mut self,
+        env: &GlobalEnv,
+        fingerprints: &BTreeMap<QualifiedId<FunId>, String>,
+        errors: &[BoogieError],
+    ) {
+        let mut failed = BTreeSet::new();
+        let mut all_attributed = true;
+        for error in errors {
+            match error.verified_function().or_else(|| {
+                env.get_enclosing_function(&error.loc)
+                    .map(|f| f.get_qualified_id())
+            }) {
+                Some(fun_id) => {
+                    failed.insert(fun_id);
+                }
+                None => all_attributed = false,
+            }
+        }
+        for (fun_id, fingerprint) in fingerprints {
+            let name = full_name(env, *fun_id);
+            if failed.contains(fun_id) {
+                self.verified.remove(&name);
+            } else if all_attributed {
+                self.verified.insert(name, fingerprint.clone());
+            }
+        }
+    }
+}
+
+fn full_name(env: &GlobalEnv, fun_id: QualifiedId<FunId>) -> String {
+    let fun_env = env.get_function(fun_id);
+    format!(
+        "{}::{}",
+        fun_env
+            .module_env
+            .get_name()
+            .display_full(env.symbol_pool()),
+        fun_env.get_name_str()
+    )
+}
+
+/// Adds the code of the target to the text, collecting the spec functions and structs it uses.
+/// Comments are skipped as they carry source locations, which do not affect verification.
+fn add_target(
+    target: &FunctionTarget,
+    text: &mut String,
+    spec_funs: &mut BTreeSet<QualifiedId<SpecFunId>>,
+    structs: &mut BTreeSet<QualifiedId<StructId>>,
+) {
+    for line in target.to_string().lines() {
+        if !line.trim_start().starts_with('#') {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+    for code in target.get_bytecode() {
+        if let Bytecode::Prop(_, _, exp) = code {
+            collect_spec_funs(exp, spec_funs);
+        }
+    }
+    for idx in 0..target.get_local_count() {
+        collect_structs(target.get_local_type(idx), structs);
+    }
+}
+
+fn collect_spec_funs(exp: &Exp, spec_funs: &mut BTreeSet<QualifiedId<SpecFunId>>) {
+    exp.visit(&mut |e| {
+        if let ExpData::Call(_, Operation::Function(mid, fid, _), _) = e {
+            spec_funs.insert(mid.qualified(*fid));
+        }
+    });
+}
+
+fn collect_structs(ty: &Type, structs: &mut BTreeSet<QualifiedId<StructId>>) {
+    ty.visit(&mut |t| {
+        if let Type::Struct(mid, sid, _) = t {
+            structs.insert(mid.qualified(*sid));
+        }
+    });
+}
+
+/// Adds the declarations of the spec functions, and the ones they transitively call, to the text.
+fn add_spec_funs(
+    env: &GlobalEnv,
+    spec_funs: BTreeSet<QualifiedId<SpecFunId>>,
+    text: &mut String,
+    structs: &mut BTreeSet<QualifiedId<StructId>>,
+) {
+    let tctx = env.get_type_display_ctx();
+    let mut todo = spec_funs.into_iter().collect::<Vec<_>>();
+    let mut visited = BTreeSet::new();
+    while let Some(id) = todo.pop() {
+        if !visited.insert(id) {
+            continue;
+        }
+        let module_env = env.get_module(id.module_id);
+        let decl = module_env.get_spec_fun(id.id);
+        write!(
+            text,
+            "spec fun {}::{}(",
+            module_env.get_name().display_full(env.symbol_pool()),
+            decl.name.display(env.symbol_pool())
+        )
+        .unwrap();
+        for (name, ty) in &decl.params {
+            write!(
+                text,
+                "{}: {}, ",
+                name.display(env.symbol_pool()),
+                ty.display(&tctx)
+            )
+            .unwrap();
+            collect_structs(ty, structs);
+        }
+        write!(text, "): {}", decl.result_type.display(&tctx)).unwrap();
+        if let Some(body) = &decl.body {
+            write!(text, " = {}", body.display(env)).unwrap();
+        }
+        text.push('\n');
+        todo.extend(decl.callees.iter().cloned());
+    }
+}
+
+/// Adds the declarations of the structs, and the ones used in their fields, to the text.
+fn add_structs(env: &GlobalEnv, structs: BTreeSet<QualifiedId<StructId>>, text: &mut String) {
+    let tctx = env.get_type_display_ctx();
+    let mut todo = structs.into_iter().collect::<Vec<_>>();
+    let mut visited = BTreeSet::new();
+    while let Some(id) = todo.pop() {
+        if !visited.insert(id) {
+            continue;
+        }
+        let struct_env = env.get_struct_qid(id);
+        write!(
+            text,
+            "struct {} {{",
+            struct_env.get_full_name_with_address()
+        )
+        .unwrap();
+        for field_env in struct_env.get_fields() {
+            let ty = field_env.get_type();
+            write!(
+                text,
+                "{}: {}, ",
+                field_env.get_name().display(env.symbol_pool()),
+                ty.display(&tctx)
+            )
+            .unwrap();
+            let mut used = BTreeSet::new();
+            collect_structs(&ty, &mut used);
+            todo.extend(used);
+        }
+        text.push_str("}\n");
+        for cond in &struct_env.get_spec().conditions {
+            writeln!(text, "{} {}", cond.kind, cond.exp.display(env)).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_and_process_bytecode, create_init_num_operation_state};
+    use move_compiler::shared::PackagePaths;
+    use move_model::{parse_addresses_from_options, run_model_builder_with_options};
+    use move_prover_boogie_backend::boogie_wrapper::BoogieErrorKind;
+    use tempfile::TempDir;
+
+    const SOURCE: &str = r#"
+module 0x42::M {
+    spec module {
+        fun bound(): u64 { 100 }
+        fun limit(): u64;
+        axiom limit() > 1;
+    }
+
+    fun callee(x: u64): u64 { x / 1 }
+    fun caller(x: u64): u64 { callee(x) }
+    spec caller { ensures result == x; }
+
+    fun opaque_callee(x: u64): u64 { x * 1 }
+    spec opaque_callee { pragma opaque; ensures result == x; }
+    fun opaque_caller(x: u64): u64 { opaque_callee(x) }
+    spec opaque_caller { ensures result == x; }
+
+    fun bounded(x: u64): u64 { x }
+    spec bounded { requires x < bound(); ensures result < bound(); }
+
+    fun unrelated(): u64 { 1 }
+}
+"#;
+
+    /// Builds the model of `source`, written to a file in `dir`, and returns the function
+    /// targets after the prover pipeline along with their fingerprints.
+    fn build(
+        dir: &TempDir,
+        source: &str,
+    ) -> (
+        GlobalEnv,
+        FunctionTargetsHolder,
+        BTreeMap<QualifiedId<FunId>, String>,
+    ) {
+        let path = dir.path().join("M.move");
+        fs::write(&path, source).unwrap();
+        let options = Options::default();
+        let env = run_model_builder_with_options(
+            vec![PackagePaths {
+                name: None,
+                paths: vec![path.to_string_lossy().to_string()],
+                named_address_map: parse_addresses_from_options(vec![]).unwrap(),
+            }],
+            vec![],
+            options.model_builder.clone(),
+        )
+        .unwrap();
+        assert!(!env.has_errors());
+        env.set_extension(options.prover.clone());
+        create_init_num_operation_state(&env);
+        let targets = create_and_process_bytecode(&options, &env);
+        let fingerprints = VerificationCache::fingerprints(&env, &options, &targets);
+        (env, targets, fingerprints)
+    }
+
+    fn names(
+        env: &GlobalEnv,
+        fun_ids: impl IntoIterator<Item = QualifiedId<FunId>>,
+    ) -> Vec<String> {
+        fun_ids
+            .into_iter()
+            .map(|fun_id| env.get_function(fun_id).get_name_str())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// Verifies `SOURCE` without errors, and returns the functions which are verified again
+    /// once it is replaced by `new_source`.
+    fn reverified(new_source: &str) -> Vec<String> {
+        let dir = TempDir::new().unwrap();
+        let mut cache = VerificationCache::default();
+        let (env, _, fingerprints) = build(&dir, SOURCE);
+        cache.update(&env, &fingerprints, &[]);
+        assert_eq!(cache.verified.len(), fingerprints.len());
+
+        let (env, mut targets, fingerprints) = build(&dir, new_source);
+        let cached = cache.remove_cached(&env, &mut targets, &fingerprints);
+        for fun_id in &cached {
+            let fun_env = env.get_function(*fun_id);
+            assert!(!targets
+                .get_target_variants(&fun_env)
+                .iter()
+                .any(FunctionVariant::is_verified));
+        }
+        names(
+            &env,
+            fingerprints
+                .keys()
+                .filter(|fun_id| !cached.contains(fun_id))
+                .cloned(),
+        )
+    }
+
+    #[test]
+    fn unchanged_functions_are_skipped() {
+        assert!(reverified(SOURCE).is_empty());
+        assert_eq!(
+            reverified(&SOURCE.replace("{ 1 }", "{ 2 }")),
+            vec!["unrelated"]
+        );
+    }
+
+    #[test]
+    fn callee_body_change_invalidates_caller() {
+        assert_eq!(
+            reverified(&SOURCE.replace("x / 1", "x / 2")),
+            vec!["callee", "caller"]
+        );
+    }
+
+    #[test]
+    fn opaque_callee_spec_change_invalidates_caller() {
+        assert_eq!(
+            reverified(&SOURCE.replace(
+                "pragma opaque; ensures result == x;",
+                "pragma opaque; ensures result >= x;"
+            )),
+            vec!["opaque_callee", "opaque_caller"]
+        );
+        // Only the spec of an opaque callee is used by its callers.
+        assert_eq!(
+            reverified(&SOURCE.replace("x * 1", "x * 2")),
+            vec!["opaque_callee"]
+        );
+    }
+
+    #[test]
+    fn spec_fun_and_axiom_changes_invalidate_users() {
+        assert_eq!(
+            reverified(&SOURCE.replace("{ 100 }", "{ 200 }")),
+            vec!["bounded"]
+        );
+        // Axioms are assumed in the verification conditions of all functions.
+        assert_eq!(
+            reverified(&SOURCE.replace("limit() > 1", "limit() > 2")),
+            vec![
+                "bounded",
+                "callee",
+                "caller",
+                "opaque_callee",
+                "opaque_caller",
+                "unrelated"
+            ]
+        );
+    }
+
+    #[test]
+    fn errors_prevent_recording() {
+        let dir = TempDir::new().unwrap();
+        let (env, _, fingerprints) = build(&dir, SOURCE);
+        let error = |loc| BoogieError {
+            kind: BoogieErrorKind::Assertion,
+            loc,
+            message: "post-condition does not hold".to_string(),
+            execution_trace: vec![],
+            model: None,
+        };
+
+        // An error in a function only keeps that function from being recorded.
+        let caller = env
+            .get_modules()
+            .flat_map(|module_env| module_env.into_functions())
+            .find(|fun_env| fun_env.get_name_str() == "caller")
+            .unwrap();
+        let mut cache = VerificationCache::default();
+        cache.update(&env, &fingerprints, &[error(caller.get_loc())]);
+        assert_eq!(cache.verified.len(), fingerprints.len() - 1);
+        assert!(!cache
+            .verified
+            .contains_key(&full_name(&env, caller.get_qualified_id())));
+
+        // An error which cannot be attributed to a function keeps all from being recorded.
+        let mut cache = VerificationCache::default();
+        cache.update(&env, &fingerprints, &[error(env.unknown_loc())]);
+        assert!(cache.verified.is_empty());
+    }
+}
@@ -0,0 +1,34 @@
+// flag: --emit-counterexample-tests
+module 0x42::CounterexampleTests {
+
+    struct Pair has copy, drop {
+        x: u64,
+        y: u64,
+    }
+
+    // fails, and the test replaying it asserts the violated post-condition.
+    fun first(p: Pair): u64 {
+        p.x
+    }
+    spec first {
+        requires p.x == 1 && p.y == 2;
+        ensures result == p.y;
+    }
+
+    // fails, and the test replaying it expects the function to abort.
+    fun half(x: u64): u64 {
+        x / 2
+    }
+    spec half {
+        requires x == 4;
+        aborts_if x == 4;
+    }
+
+    // fails, but no test is generated for a generic function.
+    fun id<T>(x: T): T {
+        x
+    }
+    spec id {
+        ensures false;
+    }
+}
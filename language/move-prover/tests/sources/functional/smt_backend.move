@@ -0,0 +1,145 @@
+// also_include_for: smt
+module 0x42::SmtBackend {
+    use std::signer;
+    use std::vector;
+
+    struct Counter has key, drop {
+        value: u64,
+    }
+
+    struct Pair has copy, drop {
+        first: u64,
+        second: u64,
+    }
+
+    // -------------------------
+    // Arithmetic and branching
+    // -------------------------
+
+    fun max(a: u64, b: u64): u64 {
+        if (a >= b) a else b
+    }
+    spec max {
+        aborts_if false;
+        ensures result >= a && result >= b;
+        ensures result == a || result == b;
+    }
+
+    fun add_checked(a: u64, b: u64): u64 {
+        a + b
+    }
+    spec add_checked {
+        aborts_if a + b > MAX_U64;
+        ensures result == a + b;
+    }
+
+    fun div_or_zero(a: u64, b: u64): u64 {
+        if (b == 0) return 0;
+        a / b
+    }
+    spec div_or_zero {
+        aborts_if false;
+        ensures b == 0 ==> result == 0;
+        ensures b > 0 ==> result == a / b;
+    }
+
+    // -------------------------
+    // Structs and references
+    // -------------------------
+
+    fun swap_pair(p: &mut Pair) {
+        let tmp = p.first;
+        p.first = p.second;
+        p.second = tmp;
+    }
+    spec swap_pair {
+        aborts_if false;
+        ensures p.first == old(p.second);
+        ensures p.second == old(p.first);
+    }
+
+    fun swapped(p: Pair): Pair {
+        swap_pair(&mut p);
+        p
+    }
+    spec swapped {
+        aborts_if false;
+        ensures result == Pair { first: p.second, second: p.first };
+    }
+
+    // -------------------------
+    // Global storage
+    // -------------------------
+
+    fun publish(account: &signer) {
+        move_to(account, Counter { value: 0 })
+    }
+    spec publish {
+        aborts_if exists<Counter>(signer::address_of(account));
+        ensures global<Counter>(signer::address_of(account)).value == 0;
+    }
+
+    fun increment(addr: address) acquires Counter {
+        let counter = borrow_global_mut<Counter>(addr);
+        counter.value = counter.value + 1;
+    }
+    spec increment {
+        aborts_if !exists<Counter>(addr);
+        aborts_if global<Counter>(addr).value + 1 > MAX_U64;
+        ensures global<Counter>(addr).value == old(global<Counter>(addr).value) + 1;
+    }
+
+    fun remove(addr: address): u64 acquires Counter {
+        let Counter { value } = move_from<Counter>(addr);
+        value
+    }
+    spec remove {
+        aborts_if !exists<Counter>(addr);
+        ensures result == old(global<Counter>(addr).value);
+        ensures !exists<Counter>(addr);
+    }
+
+    // -------------------------
+    // Vectors and loops
+    // -------------------------
+
+    fun push_two(v: &mut vector<u64>, x: u64) {
+        vector::push_back(v, x);
+        vector::push_back(v, x);
+    }
+    spec push_two {
+        aborts_if false;
+        ensures len(v) == len(old(v)) + 2;
+        ensures v[len(v) - 1] == x;
+    }
+
+    fun fill(n: u64): vector<u64> {
+        let v = vector::empty();
+        let i = 0;
+        while ({
+            spec {
+                invariant i <= n;
+                invariant len(v) == i;
+            };
+            i < n
+        }) {
+            vector::push_back(&mut v, i);
+            i = i + 1;
+        };
+        v
+    }
+    spec fill {
+        aborts_if false;
+        ensures len(result) == n;
+    }
+
+    // -------------------------
+    // Expected failures
+    // -------------------------
+
+    fun assert_incorrect() {
+        spec {
+            assert MAX_U64 == 0;
+        };
+    }
+}
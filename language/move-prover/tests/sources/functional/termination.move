@@ -0,0 +1,99 @@
+module 0x42::Termination {
+
+    // ------------------------------
+    // `decreases` on recursive calls
+    // ------------------------------
+
+    fun count(n: u64): u64 {
+        if (n == 0) 0 else 1 + count(n - 1)
+    }
+    spec count {
+        pragma opaque;
+        decreases n;
+        aborts_if false;
+        ensures result == n;
+    }
+
+    fun is_even(n: u64): bool {
+        if (n == 0) true else is_odd(n - 1)
+    }
+    spec is_even {
+        pragma opaque;
+        decreases n;
+        aborts_if false;
+        ensures result == (n % 2 == 0);
+    }
+
+    fun is_odd(n: u64): bool {
+        if (n == 0) false else is_even(n - 1)
+    }
+    spec is_odd {
+        pragma opaque;
+        decreases n;
+        aborts_if false;
+        ensures result == (n % 2 == 1);
+    }
+
+    // The measures are compared lexicographically.
+    fun ackermann(m: u64, n: u64): u64 {
+        if (m == 0) {
+            n + 1
+        } else if (n == 0) {
+            ackermann(m - 1, 1)
+        } else {
+            ackermann(m - 1, ackermann(m, n - 1))
+        }
+    }
+    spec ackermann {
+        pragma opaque;
+        pragma aborts_if_is_partial;
+        decreases m;
+        decreases n;
+    }
+
+    // ---------------------------------
+    // `decreases` on loops
+    // ---------------------------------
+
+    fun count_down(n: u64): u64 {
+        let i = n;
+        let steps = 0;
+        while ({
+            spec {
+                invariant i + steps == n;
+                decreases i;
+            };
+            (i > 0)
+        }) {
+            i = i - 1;
+            steps = steps + 1;
+        };
+        steps
+    }
+    spec count_down {
+        aborts_if false;
+        ensures result == n;
+    }
+
+    // ------------------
+    // `succeeds_if`
+    // ------------------
+
+    fun div(x: u64, y: u64): u64 {
+        x / y
+    }
+    spec div {
+        pragma opaque;
+        pragma aborts_if_is_partial;
+        succeeds_if y > 0;
+        ensures result == x / y;
+    }
+
+    fun div_by_two(x: u64): u64 {
+        div(x, 2)
+    }
+    spec div_by_two {
+        aborts_if false;
+        ensures result == x / 2;
+    }
+}
@@ -0,0 +1,12 @@
+// no-boogie-test
+module 0x42::TerminationErr {
+    fun count_down(n: u64) {
+        let i = n;
+        while (i > 0) {
+            i = i - 1;
+            spec {
+                decreases i;
+            };
+        }
+    }
+}
@@ -55,7 +55,6 @@ struct Feature {
 #[derive(Clone, Copy)]
 enum InclusionMode {
     /// Only a test which has the comment `// also_include_for: <feature>` will be included.
-    #[allow(dead_code)]
     Explicit,
     /// Every test will be included unless it has the comment `// exclude_for: <feature>`.
     Implicit,
@@ -87,6 +86,17 @@ fn get_features() -> &'static [Feature] {
                 runner: |p| test_runner_for_feature(p, get_feature_by_name("cvc5")),
                 enabling_condition: |group, _| group == "unit",
             },
+            // Tests with the SMT backend, which does not use boogie.
+            Feature {
+                name: "smt",
+                flags: &["--smt-backend"],
+                inclusion_mode: InclusionMode::Explicit,
+                enable_in_ci: true, // Only needs Z3, which CI provides as for the default feature
+                only_if_requested: false,
+                separate_baseline: true,
+                runner: |p| test_runner_for_feature(p, get_feature_by_name("smt")),
+                enabling_condition: |group, _| group == "unit",
+            },
         ]
     })
 }
@@ -128,7 +138,7 @@ fn test_runner_for_feature(path: &Path, feature: &Feature) -> datatest_stable::R
 
     let mut options = Options::create_from_args(&args)?;
     options.setup_logging_for_test();
-    let no_tools = read_env_var("BOOGIE_EXE").is_empty()
+    let no_tools = read_env_var("BOOGIE_EXE").is_empty() && !options.smt_backend
         || !options.backend.use_cvc5 && read_env_var("Z3_EXE").is_empty()
         || options.backend.use_cvc5 && read_env_var("CVC5_EXE").is_empty();
     let baseline_valid =
@@ -150,6 +160,7 @@ fn test_runner_for_feature(path: &Path, feature: &Feature) -> datatest_stable::R
     options.backend.check_tool_versions()?;
     options.prover.stable_test_output = true;
     options.backend.stable_test_output = true;
+    let counterexample_tests_dir = options.backend.counterexample_tests_dir.clone();
 
     let mut error_writer = Buffer::no_color();
     let mut diags = match run_move_prover(&mut error_writer, options) {
@@ -158,6 +169,9 @@ fn test_runner_for_feature(path: &Path, feature: &Feature) -> datatest_stable::R
     };
     if baseline_valid {
         diags += &String::from_utf8_lossy(&error_writer.into_inner());
+        if let Some(dir) = counterexample_tests_dir {
+            diags += &read_counterexample_tests(Path::new(&dir))?;
+        }
         if let Some(ref path) = baseline_path {
             verify_or_update_baseline(path.as_path(), &diags)?
         } else if !diags.is_empty() {
@@ -168,6 +182,28 @@ fn test_runner_for_feature(path: &Path, feature: &Feature) -> datatest_stable::R
     Ok(())
 }
 
+/// Returns the counterexample tests written to the given directory, if any, so they are
+/// part of the baseline.
+fn read_counterexample_tests(dir: &Path) -> anyhow::Result<String> {
+    let mut result = String::new();
+    if !dir.exists() {
+        return Ok(result);
+    }
+    for entry in std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .sorted()
+    {
+        result += &format!(
+            "\n// {}\n{}",
+            entry.file_name().unwrap().to_string_lossy(),
+            std::fs::read_to_string(&entry)?
+        );
+    }
+    Ok(result)
+}
+
 /// Returns flags and baseline file for this test run
 fn get_flags_and_baseline(
     temp_dir: &Path,
@@ -0,0 +1,131 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::{as_module, compile_units};
+use move_binary_format::errors::VMResult;
+use move_core_types::{
+    account_address::AccountAddress,
+    identifier::Identifier,
+    language_storage::ModuleId,
+    value::{serialize_values, MoveValue},
+};
+use move_vm_runtime::{config::VMConfig, move_vm::MoveVM};
+use move_vm_test_utils::{
+    gas_schedule::{Gas, GasStatus, INITIAL_COST_SCHEDULE},
+    InMemoryStorage,
+};
+
+const TEST_ADDR: AccountAddress = AccountAddress::new([42; AccountAddress::LENGTH]);
+
+// Borrows fields through local structs, references in locals, and generic structs, which is
+// what the interpreter fuses into single instructions.
+const CODE: &str = r#"
+    module TEST_ADDR::Fused {
+        struct Point has copy, drop { x: u64, y: u64 }
+        struct Box<T> has copy, drop { item: T }
+
+        fun norm(p: &Point): u64 {
+            p.x * p.x + p.y * p.y
+        }
+
+        fun shift(p: &mut Point, d: u64) {
+            p.x = p.x + d;
+            p.y = p.y + d;
+        }
+
+        fun unbox<T: copy>(b: &Box<T>): T {
+            *&b.item
+        }
+
+        fun set<T: drop>(b: &mut Box<T>, item: T) {
+            b.item = item
+        }
+
+        public fun run(n: u64): u64 {
+            let p = Point { x: n, y: n + 1 };
+            let b = Box { item: p };
+            let i = 0;
+            let acc = 0;
+            while (i < n) {
+                shift(&mut p, i);
+                acc = acc + norm(&p) + p.x;
+                set(&mut b, p);
+                acc = acc + unbox(&b).y + b.item.x;
+                i = i + 1;
+            };
+            acc
+        }
+    }
+"#;
+
+/// Runs `Fused::run` with fused instructions, or without them if `paranoid` is set, and
+/// returns the return values and the remaining gas.
+fn run(paranoid: bool, max_operand_stack_size: usize, gas: u64) -> (VMResult<Vec<Vec<u8>>>, u64) {
+    let mut storage = InMemoryStorage::new();
+    let code = CODE.replace("TEST_ADDR", &format!("0x{}", TEST_ADDR));
+    let module = as_module(compile_units(&code).unwrap().pop().unwrap());
+    let mut blob = vec![];
+    module.serialize(&mut blob).unwrap();
+    storage.publish_or_overwrite_module(module.self_id(), blob);
+
+    let vm = MoveVM::new_with_config(
+        vec![],
+        VMConfig {
+            paranoid_type_checks: paranoid,
+            max_operand_stack_size,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let mut session = vm.new_session(&storage);
+    let mut gas_status = GasStatus::new(&INITIAL_COST_SCHEDULE, Gas::new(gas));
+    let result = session
+        .execute_function_bypass_visibility(
+            &ModuleId::new(TEST_ADDR, Identifier::new("Fused").unwrap()),
+            &Identifier::new("run").unwrap(),
+            vec![],
+            serialize_values(&vec![MoveValue::U64(5)]),
+            &mut gas_status,
+        )
+        .map(|values| {
+            values
+                .return_values
+                .into_iter()
+                .map(|(bytes, _)| bytes)
+                .collect()
+        });
+    (result, gas_status.remaining_gas().into())
+}
+
+fn assert_same_execution(max_operand_stack_size: usize, gas: u64) {
+    let (fused, fused_gas) = run(false, max_operand_stack_size, gas);
+    let (unfused, unfused_gas) = run(true, max_operand_stack_size, gas);
+    match (fused, unfused) {
+        (Ok(fused), Ok(unfused)) => assert_eq!(fused, unfused),
+        (Err(fused), Err(unfused)) => {
+            assert_eq!(fused.major_status(), unfused.major_status());
+            assert_eq!(fused.location(), unfused.location());
+            assert_eq!(fused.offsets(), unfused.offsets());
+        }
+        (fused, unfused) => panic!("{:?} != {:?}", fused, unfused),
+    }
+    assert_eq!(fused_gas, unfused_gas);
+}
+
+#[test]
+fn fused_instructions_charge_the_same_gas() {
+    let budget = 1_000_000;
+    let (result, remaining) = run(false, 1024, budget);
+    assert!(result.is_ok());
+    // running out of gas at any point must fail at the same instruction
+    for gas in 0..=budget - remaining {
+        assert_same_execution(1024, gas);
+    }
+}
+
+#[test]
+fn fused_instructions_overflow_the_same_stack() {
+    for max_operand_stack_size in 0..8 {
+        assert_same_execution(max_operand_stack_size, 1_000_000);
+    }
+}
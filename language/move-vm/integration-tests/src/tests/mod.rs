@@ -8,9 +8,15 @@ mod binary_format_version;
 mod exec_func_effects_tests;
 mod function_arg_tests;
 mod instantiation_tests;
+mod instruction_fusion_tests;
 mod invariant_violation_tests;
 mod leak_tests;
 mod loader_tests;
+mod module_upgrade_tests;
 mod mutated_accounts_tests;
 mod nested_loop_tests;
+mod parallel_execution_tests;
 mod return_value_tests;
+mod runtime_limits_tests;
+mod savepoint_tests;
+mod verified_module_cache_tests;
@@ -0,0 +1,203 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::{as_module, compile_units};
+use move_binary_format::errors::VMResult;
+use move_core_types::{
+    account_address::AccountAddress,
+    identifier::Identifier,
+    language_storage::{ModuleId, StructTag},
+    resolver::{ModuleResolver, ResourceResolver},
+    value::MoveValue,
+    vm_status::StatusCode,
+};
+use move_vm_runtime::move_vm::MoveVM;
+use move_vm_test_utils::InMemoryStorage;
+use move_vm_types::gas::UnmeteredGasMeter;
+use std::{cell::RefCell, collections::BTreeSet};
+
+const TEST_ADDR: AccountAddress = AccountAddress::new([42; AccountAddress::LENGTH]);
+
+/// A storage which records which modules the VM loads from it, i.e. which are not cached.
+struct RecordingStorage {
+    store: InMemoryStorage,
+    loaded: RefCell<BTreeSet<String>>,
+}
+
+impl ModuleResolver for RecordingStorage {
+    type Error = ();
+
+    fn get_module(&self, module_id: &ModuleId) -> Result<Option<Vec<u8>>, Self::Error> {
+        self.loaded
+            .borrow_mut()
+            .insert(module_id.name().to_string());
+        self.store.get_module(module_id)
+    }
+}
+
+impl ResourceResolver for RecordingStorage {
+    type Error = ();
+
+    fn get_resource(
+        &self,
+        address: &AccountAddress,
+        tag: &StructTag,
+    ) -> Result<Option<Vec<u8>>, Self::Error> {
+        self.store.get_resource(address, tag)
+    }
+}
+
+impl RecordingStorage {
+    fn new() -> Self {
+        Self {
+            store: InMemoryStorage::new(),
+            loaded: RefCell::new(BTreeSet::new()),
+        }
+    }
+
+    fn take_loaded(&self) -> Vec<String> {
+        std::mem::take(&mut *self.loaded.borrow_mut())
+            .into_iter()
+            .collect()
+    }
+}
+
+/// Compiles the code and returns the modules with the given names.
+fn compile(code: &str, names: &[&str]) -> Vec<Vec<u8>> {
+    compile_units(&code.replace("TEST_ADDR", &format!("0x{}", TEST_ADDR)))
+        .unwrap()
+        .into_iter()
+        .map(as_module)
+        .filter(|m| names.contains(&m.self_id().name().as_str()))
+        .map(|m| {
+            let mut blob = vec![];
+            m.serialize(&mut blob).unwrap();
+            blob
+        })
+        .collect()
+}
+
+fn publish(vm: &MoveVM, storage: &mut RecordingStorage, code: &str, names: &[&str]) {
+    let mut sess = vm.new_session(&*storage);
+    sess.publish_module_bundle(compile(code, names), TEST_ADDR, &mut UnmeteredGasMeter)
+        .unwrap();
+    let (changeset, _) = sess.finish().unwrap();
+    storage.store.apply(changeset).unwrap();
+}
+
+fn call(vm: &MoveVM, storage: &RecordingStorage, module: &str, function: &str) -> VMResult<u64> {
+    let mut sess = vm.new_session(storage);
+    let mut result = sess.execute_function_bypass_visibility(
+        &ModuleId::new(TEST_ADDR, Identifier::new(module).unwrap()),
+        &Identifier::new(function).unwrap(),
+        vec![],
+        Vec::<Vec<u8>>::new(),
+        &mut UnmeteredGasMeter,
+    )?;
+    let (bytes, layout) = result.return_values.pop().unwrap();
+    match MoveValue::simple_deserialize(&bytes, &layout).unwrap() {
+        MoveValue::U64(v) => Ok(v),
+        v => panic!("unexpected return value {:?}", v),
+    }
+}
+
+const MODULES: &str = r#"
+    module TEST_ADDR::A {
+        public fun f(): u64 { 1 }
+    }
+    module TEST_ADDR::B {
+        public fun g(): u64 { TEST_ADDR::A::f() }
+    }
+    module TEST_ADDR::C {
+        public fun h(): u64 { 3 }
+    }
+"#;
+
+#[test]
+fn upgrade_evicts_dependents_only() {
+    let vm = MoveVM::new(vec![]).unwrap();
+    let mut storage = RecordingStorage::new();
+    publish(&vm, &mut storage, MODULES, &["A", "B", "C"]);
+    assert_eq!(call(&vm, &storage, "B", "g").unwrap(), 1);
+    assert_eq!(call(&vm, &storage, "C", "h").unwrap(), 3);
+    assert_eq!(storage.take_loaded(), vec!["A", "B", "C"]);
+
+    publish(
+        &vm,
+        &mut storage,
+        "module TEST_ADDR::A { public fun f(): u64 { 2 } }",
+        &["A"],
+    );
+    storage.take_loaded();
+    assert!(vm.is_loader_cache_invalidated());
+
+    // the old version stays in use until the cache is flushed
+    assert_eq!(call(&vm, &storage, "B", "g").unwrap(), 1);
+    assert!(storage.take_loaded().is_empty());
+
+    vm.flush_loader_cache_if_invalidated();
+    assert!(!vm.is_loader_cache_invalidated());
+    assert_eq!(call(&vm, &storage, "B", "g").unwrap(), 2);
+    assert_eq!(call(&vm, &storage, "C", "h").unwrap(), 3);
+    assert_eq!(storage.take_loaded(), vec!["A", "B"]);
+}
+
+#[test]
+fn abandoned_publish_is_rolled_back() {
+    let vm = MoveVM::new(vec![]).unwrap();
+    let mut storage = RecordingStorage::new();
+    publish(&vm, &mut storage, MODULES, &["A", "B", "C"]);
+    assert_eq!(call(&vm, &storage, "B", "g").unwrap(), 1);
+    assert_eq!(call(&vm, &storage, "C", "h").unwrap(), 3);
+
+    // a new module which is used by the publishing transaction, which is then abandoned
+    let mut sess = vm.new_session(&storage);
+    sess.publish_module_bundle(
+        compile(
+            &format!(
+                "{} module TEST_ADDR::D {{ public fun k(): u64 {{ TEST_ADDR::B::g() }} }}",
+                MODULES
+            ),
+            &["D"],
+        ),
+        TEST_ADDR,
+        &mut UnmeteredGasMeter,
+    )
+    .unwrap();
+    sess.execute_function_bypass_visibility(
+        &ModuleId::new(TEST_ADDR, Identifier::new("D").unwrap()),
+        &Identifier::new("k").unwrap(),
+        vec![],
+        Vec::<Vec<u8>>::new(),
+        &mut UnmeteredGasMeter,
+    )
+    .unwrap();
+    drop(sess);
+    storage.take_loaded();
+
+    vm.mark_modules_as_invalid([ModuleId::new(TEST_ADDR, Identifier::new("D").unwrap())]);
+    vm.flush_loader_cache_if_invalidated();
+    assert_eq!(
+        call(&vm, &storage, "D", "k").unwrap_err().major_status(),
+        StatusCode::LINKER_ERROR
+    );
+    assert_eq!(call(&vm, &storage, "B", "g").unwrap(), 1);
+    assert_eq!(call(&vm, &storage, "C", "h").unwrap(), 3);
+    assert_eq!(storage.take_loaded(), vec!["D"]);
+}
+
+#[test]
+fn full_invalidation_flushes_everything() {
+    let vm = MoveVM::new(vec![]).unwrap();
+    let mut storage = RecordingStorage::new();
+    publish(&vm, &mut storage, MODULES, &["A", "B", "C"]);
+    assert_eq!(call(&vm, &storage, "B", "g").unwrap(), 1);
+    assert_eq!(call(&vm, &storage, "C", "h").unwrap(), 3);
+    storage.take_loaded();
+
+    vm.mark_loader_cache_as_invalid();
+    vm.flush_loader_cache_if_invalidated();
+    assert_eq!(call(&vm, &storage, "B", "g").unwrap(), 1);
+    assert_eq!(call(&vm, &storage, "C", "h").unwrap(), 3);
+    assert_eq!(storage.take_loaded(), vec!["A", "B", "C"]);
+}
@@ -0,0 +1,284 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::{as_module, compile_units};
+use move_binary_format::errors::VMResult;
+use move_core_types::{
+    account_address::AccountAddress,
+    effects::{ChangeSet, Event},
+    identifier::Identifier,
+    language_storage::{ModuleId, StructTag},
+    resolver::MoveResolver,
+    value::MoveValue,
+    vm_status::StatusCode,
+};
+use move_vm_parallel_executor::ParallelExecutor;
+use move_vm_runtime::{
+    data_cache::{AccessKey, ReadWriteSet},
+    move_vm::MoveVM,
+    session::Session,
+};
+use move_vm_test_utils::InMemoryStorage;
+use move_vm_types::gas::UnmeteredGasMeter;
+
+const TEST_ADDR: AccountAddress = AccountAddress::new([42; AccountAddress::LENGTH]);
+const ACCOUNTS: u8 = 8;
+
+const BANK: &str = r#"
+    module TEST_ADDR::Bank {
+        struct Coin has key { value: u64 }
+
+        public fun open(account: signer, value: u64) {
+            move_to(&account, Coin { value })
+        }
+
+        public fun transfer(from: address, to: address, amount: u64): u64 acquires Coin {
+            let coin = borrow_global_mut<Coin>(from);
+            assert!(coin.value >= amount, 1);
+            coin.value = coin.value - amount;
+            let coin = borrow_global_mut<Coin>(to);
+            coin.value = coin.value + amount;
+            coin.value
+        }
+
+        public fun close(account: address): u64 acquires Coin {
+            let Coin { value } = move_from<Coin>(account);
+            value
+        }
+
+        public fun balance(account: address): u64 acquires Coin {
+            borrow_global<Coin>(account).value
+        }
+    }
+"#;
+
+const AUDIT: &str = r#"
+    module TEST_ADDR::Audit {
+        public fun total(a: address, b: address): u64 {
+            TEST_ADDR::Bank::balance(a) + TEST_ADDR::Bank::balance(b)
+        }
+    }
+"#;
+
+#[derive(Clone, Debug)]
+enum Txn {
+    Open(u8, u64),
+    Transfer(u8, u8, u64),
+    Close(u8),
+    Publish(Vec<u8>),
+    Audit(u8, u8),
+}
+
+type Outcome = Result<(Vec<Vec<u8>>, ChangeSet, Vec<Event>), StatusCode>;
+
+fn account(i: u8) -> AccountAddress {
+    AccountAddress::new([i + 1; AccountAddress::LENGTH])
+}
+
+fn compile(code: &str, name: &str) -> Vec<u8> {
+    let units = compile_units(
+        &format!("{}{}", BANK, code).replace("TEST_ADDR", &format!("0x{}", TEST_ADDR)),
+    )
+    .unwrap();
+    let module = units
+        .into_iter()
+        .map(as_module)
+        .find(|m| m.self_id().name().as_str() == name)
+        .unwrap();
+    let mut blob = vec![];
+    module.serialize(&mut blob).unwrap();
+    blob
+}
+
+fn coin_tag() -> StructTag {
+    StructTag {
+        address: TEST_ADDR,
+        module: Identifier::new("Bank").unwrap(),
+        name: Identifier::new("Coin").unwrap(),
+        type_params: vec![],
+    }
+}
+
+/// A storage with the bank module published and a coin in every other account.
+fn base_storage() -> InMemoryStorage {
+    let mut storage = InMemoryStorage::new();
+    storage.publish_or_overwrite_module(
+        ModuleId::new(TEST_ADDR, Identifier::new("Bank").unwrap()),
+        compile("", "Bank"),
+    );
+    for i in (0..ACCOUNTS).step_by(2) {
+        storage.publish_or_overwrite_resource(
+            account(i),
+            coin_tag(),
+            MoveValue::U64(100).simple_serialize().unwrap(),
+        );
+    }
+    storage
+}
+
+fn run_txn<S: MoveResolver>(txn: &Txn, session: &mut Session<S>) -> VMResult<Vec<Vec<u8>>> {
+    let (module, function, args) = match txn {
+        Txn::Open(a, value) => (
+            "Bank",
+            "open",
+            vec![MoveValue::Signer(account(*a)), MoveValue::U64(*value)],
+        ),
+        Txn::Transfer(from, to, amount) => (
+            "Bank",
+            "transfer",
+            vec![
+                MoveValue::Address(account(*from)),
+                MoveValue::Address(account(*to)),
+                MoveValue::U64(*amount),
+            ],
+        ),
+        Txn::Close(a) => ("Bank", "close", vec![MoveValue::Address(account(*a))]),
+        Txn::Audit(a, b) => (
+            "Audit",
+            "total",
+            vec![
+                MoveValue::Address(account(*a)),
+                MoveValue::Address(account(*b)),
+            ],
+        ),
+        Txn::Publish(blob) => {
+            session.publish_module(blob.clone(), TEST_ADDR, &mut UnmeteredGasMeter)?;
+            return Ok(vec![]);
+        }
+    };
+    let result = session.execute_function_bypass_visibility(
+        &ModuleId::new(TEST_ADDR, Identifier::new(module).unwrap()),
+        &Identifier::new(function).unwrap(),
+        vec![],
+        args.iter()
+            .map(|arg| arg.simple_serialize().unwrap())
+            .collect(),
+        &mut UnmeteredGasMeter,
+    )?;
+    Ok(result
+        .return_values
+        .into_iter()
+        .map(|(bytes, _)| bytes)
+        .collect())
+}
+
+fn execute_sequential(txns: &[Txn]) -> Vec<Outcome> {
+    let vm = MoveVM::new(vec![]).unwrap();
+    let mut storage = base_storage();
+    let mut outcomes = vec![];
+    for txn in txns {
+        let mut session = vm.new_session(&storage);
+        let output = run_txn(txn, &mut session).and_then(|values| {
+            let (change_set, events) = session.finish()?;
+            Ok((values, change_set, events))
+        });
+        if let Ok((_, change_set, _)) = &output {
+            storage.apply(change_set.clone()).unwrap();
+            vm.flush_loader_cache_if_invalidated();
+        }
+        outcomes.push(output.map_err(|e| e.major_status()));
+    }
+    outcomes
+}
+
+fn execute_parallel(txns: &[Txn], concurrency: usize) -> Vec<Outcome> {
+    let vm = MoveVM::new(vec![]).unwrap();
+    let storage = base_storage();
+    ParallelExecutor::new(&vm, concurrency)
+        .execute_block(&storage, txns, |txn, session| run_txn(txn, session))
+        .into_iter()
+        .map(|output| output.map_err(|e| e.major_status()))
+        .collect()
+}
+
+/// Generates a block of transactions on a few accounts, with many conflicts and failures.
+fn random_block(len: usize, seed: u64) -> Vec<Txn> {
+    let mut state = seed;
+    let mut next = |bound: u64| {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (state >> 33) % bound
+    };
+    (0..len)
+        .map(|_| match next(10) {
+            0 => Txn::Open(next(ACCOUNTS as u64) as u8, next(50)),
+            1 => Txn::Close(next(ACCOUNTS as u64) as u8),
+            _ => Txn::Transfer(
+                next(ACCOUNTS as u64) as u8,
+                next(ACCOUNTS as u64) as u8,
+                next(60),
+            ),
+        })
+        .collect()
+}
+
+#[test]
+fn parallel_matches_sequential() {
+    for seed in 0..4 {
+        let txns = random_block(200, seed);
+        let expected = execute_sequential(&txns);
+        assert!(expected.iter().any(|outcome| outcome.is_ok()));
+        assert!(expected.iter().any(|outcome| outcome.is_err()));
+        for concurrency in [1, 4, 16] {
+            assert_eq!(
+                execute_parallel(&txns, concurrency),
+                expected,
+                "seed {} concurrency {}",
+                seed,
+                concurrency
+            );
+        }
+    }
+}
+
+#[test]
+fn parallel_with_module_publishing() {
+    let mut txns = random_block(40, 7);
+    txns.insert(10, Txn::Audit(0, 2));
+    txns.insert(20, Txn::Publish(compile(AUDIT, "Audit")));
+    txns.insert(30, Txn::Audit(0, 2));
+    txns.push(Txn::Audit(4, 6));
+
+    let expected = execute_sequential(&txns);
+    assert_eq!(
+        expected[10].as_ref().unwrap_err(),
+        &StatusCode::LINKER_ERROR
+    );
+    assert!(expected[20].is_ok());
+    for concurrency in [1, 4] {
+        assert_eq!(execute_parallel(&txns, concurrency), expected);
+    }
+}
+
+#[test]
+fn session_read_write_set() {
+    let vm = MoveVM::new(vec![]).unwrap();
+    let storage = base_storage();
+    let mut session = vm.new_session(&storage);
+    run_txn(&Txn::Transfer(0, 2, 10), &mut session).unwrap();
+    assert!(run_txn(&Txn::Transfer(1, 2, 10), &mut session).is_err());
+
+    let bank = ModuleId::new(TEST_ADDR, Identifier::new("Bank").unwrap());
+    let read_write_set = session.read_write_set().unwrap();
+    assert_eq!(
+        read_write_set,
+        ReadWriteSet {
+            reads: [
+                AccessKey::Module(bank),
+                AccessKey::Resource(account(0), coin_tag()),
+                AccessKey::Resource(account(1), coin_tag()),
+                AccessKey::Resource(account(2), coin_tag()),
+            ]
+            .into_iter()
+            .map(|key| (key, None))
+            .collect(),
+            writes: [
+                AccessKey::Resource(account(0), coin_tag()),
+                AccessKey::Resource(account(2), coin_tag()),
+            ]
+            .into_iter()
+            .collect(),
+        }
+    );
+}
@@ -0,0 +1,416 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::{as_module, compile_units};
+use move_core_types::{
+    account_address::AccountAddress,
+    identifier::Identifier,
+    language_storage::{ModuleId, TypeTag},
+    value::{serialize_values, MoveValue},
+    vm_status::StatusCode,
+};
+use move_vm_runtime::{config::VMConfig, data_cache::MemoryUsage, move_vm::MoveVM};
+use move_vm_test_utils::InMemoryStorage;
+use move_vm_types::gas::UnmeteredGasMeter;
+
+const STD_ADDR: AccountAddress = AccountAddress::ONE;
+
+const CODE: &str = r#"
+    module std::event {
+        native fun write_to_event_store<T: drop + store>(guid: vector<u8>, count: u64, msg: T);
+
+        public fun emit(n: u64) {
+            let i = 0;
+            while (i < n) {
+                write_to_event_store(x"", i, i);
+                i = i + 1;
+            }
+        }
+    }
+
+    module std::bcs {
+        native public fun to_bytes<MoveValue>(v: &MoveValue): vector<u8>;
+    }
+
+    module std::vector {
+        #[bytecode_instruction]
+        native public fun push_back<Element>(v: &mut vector<Element>, e: Element);
+
+        #[bytecode_instruction]
+        native public fun pop_back<Element>(v: &mut vector<Element>): Element;
+    }
+
+    module std::M {
+        use std::bcs;
+        use std::vector;
+
+        struct R has key { v: vector<u64> }
+        struct Box has copy, drop { v: vector<u64>, n: u64 }
+        struct Quad<T> has store { a: T, b: T, c: T, d: T }
+        struct Holder<T: store> has key { v: T }
+
+        public fun recurse(n: u64) {
+            if (n > 0) recurse(n - 1)
+        }
+
+        public fun stack(a: u64): u64 {
+            a + (a + (a + a))
+        }
+
+        public fun pack(a: u64): vector<u64> {
+            vector[a, a, a, a]
+        }
+
+        public fun publish(s: &signer, a: u64) {
+            move_to(s, R { v: vector[a, a, a, a] })
+        }
+
+        // The layout of the resource has more than 256 nodes.
+        public fun publish_big(s: &signer) {
+            move_to(s, Holder { v: vector<Quad<Quad<Quad<Quad<u64>>>>>[] })
+        }
+
+        public fun serialize(a: u64): vector<u8> {
+            bcs::to_bytes(&a)
+        }
+
+        public fun id<T>(x: T): T {
+            x
+        }
+
+        public fun wrap<T>(x: vector<vector<T>>): vector<vector<T>> {
+            id(x)
+        }
+
+        fun grow(n: u64): vector<u64> {
+            let v = vector[];
+            let i = 0;
+            while (i < n) {
+                vector::push_back(&mut v, i);
+                i = i + 1;
+            };
+            v
+        }
+
+        public fun churn(n: u64, m: u64) {
+            let b = Box { v: grow(n), n };
+            let j = 0;
+            while (j < m) {
+                let c = copy b;
+                let w = *&c.v;
+                assert!(w == b.v, 0);
+                c.v = grow(c.n);
+                let Box { v, n: _ } = c;
+                vector::pop_back(&mut v);
+                j = j + 1;
+            }
+        }
+    }
+"#;
+
+/// Runs the function with the given config, and returns the status code it fails with, if any,
+/// including failures when finishing the session.
+fn run(
+    config: VMConfig,
+    module: &str,
+    function: &str,
+    ty_args: Vec<TypeTag>,
+    args: Vec<MoveValue>,
+) -> Result<(), StatusCode> {
+    run_with_memory_usage(config, module, function, ty_args, args).0
+}
+
+/// Same as `run`, but also returns the memory usage of the session, as reported when it finishes
+/// or, if execution fails, before.
+fn run_with_memory_usage(
+    config: VMConfig,
+    module: &str,
+    function: &str,
+    ty_args: Vec<TypeTag>,
+    args: Vec<MoveValue>,
+) -> (Result<(), StatusCode>, MemoryUsage) {
+    let mut storage = InMemoryStorage::new();
+    for unit in compile_units(CODE).unwrap() {
+        let m = as_module(unit);
+        let mut blob = vec![];
+        m.serialize(&mut blob).unwrap();
+        storage.publish_or_overwrite_module(m.self_id(), blob);
+    }
+    let natives =
+        move_stdlib::natives::all_natives(STD_ADDR, move_stdlib::natives::GasParameters::zeros())
+            .into_iter()
+            .chain(move_stdlib::natives::nursery_natives(
+                STD_ADDR,
+                move_stdlib::natives::NurseryGasParameters::zeros(),
+            ));
+    let vm = MoveVM::new_with_config(natives, config).unwrap();
+    let mut sess = vm.new_session(&storage);
+    let res = sess.execute_function_bypass_visibility(
+        &ModuleId::new(STD_ADDR, Identifier::new(module).unwrap()),
+        &Identifier::new(function).unwrap(),
+        ty_args,
+        serialize_values(&args),
+        &mut UnmeteredGasMeter,
+    );
+    match res {
+        Ok(_) => {
+            let (_, _, memory_usage) = sess.finish_with_memory_usage().unwrap();
+            (Ok(()), memory_usage)
+        }
+        Err(e) => (Err(e.major_status()), sess.memory_usage()),
+    }
+}
+
+#[test]
+fn call_stack_size() {
+    let recurse = |max_call_stack_size| {
+        run(
+            VMConfig {
+                max_call_stack_size,
+                ..Default::default()
+            },
+            "M",
+            "recurse",
+            vec![],
+            vec![MoveValue::U64(20)],
+        )
+    };
+    assert_eq!(recurse(30), Ok(()));
+    assert_eq!(recurse(10), Err(StatusCode::CALL_STACK_OVERFLOW));
+}
+
+#[test]
+fn operand_stack_size() {
+    let stack = |max_operand_stack_size| {
+        run(
+            VMConfig {
+                max_operand_stack_size,
+                ..Default::default()
+            },
+            "M",
+            "stack",
+            vec![],
+            vec![MoveValue::U64(1)],
+        )
+    };
+    assert_eq!(stack(8), Ok(()));
+    assert_eq!(stack(3), Err(StatusCode::EXECUTION_STACK_OVERFLOW));
+}
+
+#[test]
+fn vector_length() {
+    let pack = |max_vector_length| {
+        run(
+            VMConfig {
+                max_vector_length,
+                ..Default::default()
+            },
+            "M",
+            "pack",
+            vec![],
+            vec![MoveValue::U64(1)],
+        )
+    };
+    assert_eq!(pack(None), Ok(()));
+    assert_eq!(pack(Some(4)), Ok(()));
+    assert_eq!(pack(Some(3)), Err(StatusCode::VM_MAX_VECTOR_LENGTH_REACHED));
+}
+
+#[test]
+fn vector_length_in_natives() {
+    let call = |max_vector_length, function, n| {
+        run(
+            VMConfig {
+                max_vector_length,
+                ..Default::default()
+            },
+            "M",
+            function,
+            vec![],
+            vec![MoveValue::U64(n)],
+        )
+    };
+    // Calls to `vector::push_back` from Move code go through the native function, which grows
+    // the vector behind a mutable reference.
+    assert_eq!(call(Some(4), "grow", 4), Ok(()));
+    assert_eq!(
+        call(Some(4), "grow", 5),
+        Err(StatusCode::VM_MAX_VECTOR_LENGTH_REACHED)
+    );
+    // `bcs::to_bytes` returns a new vector of the 8 bytes of a u64.
+    assert_eq!(call(Some(8), "serialize", 1), Ok(()));
+    assert_eq!(
+        call(Some(7), "serialize", 1),
+        Err(StatusCode::VM_MAX_VECTOR_LENGTH_REACHED)
+    );
+}
+
+#[test]
+fn events() {
+    let emit = |max_events| {
+        run(
+            VMConfig {
+                max_events,
+                ..Default::default()
+            },
+            "event",
+            "emit",
+            vec![],
+            vec![MoveValue::U64(5)],
+        )
+    };
+    assert_eq!(emit(None), Ok(()));
+    assert_eq!(emit(Some(5)), Ok(()));
+    assert_eq!(emit(Some(4)), Err(StatusCode::VM_MAX_EVENTS_REACHED));
+}
+
+#[test]
+fn value_bytes() {
+    let publish = |max_value_bytes| {
+        run(
+            VMConfig {
+                max_value_bytes,
+                ..Default::default()
+            },
+            "M",
+            "publish",
+            vec![],
+            vec![MoveValue::Signer(STD_ADDR), MoveValue::U64(1)],
+        )
+    };
+    // The resource is serialized as the length of the vector followed by four u64s.
+    assert_eq!(publish(None), Ok(()));
+    assert_eq!(publish(Some(33)), Ok(()));
+    assert_eq!(
+        publish(Some(32)),
+        Err(StatusCode::VM_MAX_VALUE_BYTES_REACHED)
+    );
+}
+
+#[test]
+fn type_limits() {
+    let publish = |config| {
+        run(
+            config,
+            "M",
+            "publish",
+            vec![],
+            vec![MoveValue::Signer(STD_ADDR), MoveValue::U64(1)],
+        )
+    };
+    assert_eq!(
+        publish(VMConfig {
+            max_value_depth: 1,
+            ..Default::default()
+        }),
+        Err(StatusCode::VM_MAX_VALUE_DEPTH_REACHED)
+    );
+    assert_eq!(
+        publish(VMConfig {
+            max_type_to_layout_nodes: 1,
+            ..Default::default()
+        }),
+        Err(StatusCode::VM_MAX_TYPE_NODES_REACHED)
+    );
+    let publish_big = |max_type_to_layout_nodes| {
+        run(
+            VMConfig {
+                max_type_to_layout_nodes,
+                ..Default::default()
+            },
+            "M",
+            "publish_big",
+            vec![],
+            vec![MoveValue::Signer(STD_ADDR)],
+        )
+    };
+    assert_eq!(publish_big(256), Err(StatusCode::VM_MAX_TYPE_NODES_REACHED));
+    assert_eq!(publish_big(512), Ok(()));
+
+    let wrap = |max_type_instantiation_nodes| {
+        run(
+            VMConfig {
+                max_type_instantiation_nodes,
+                ..Default::default()
+            },
+            "M",
+            "wrap",
+            vec![TypeTag::U8],
+            vec![MoveValue::Vector(vec![])],
+        )
+    };
+    assert_eq!(wrap(128), Ok(()));
+    assert_eq!(
+        wrap(2),
+        Err(StatusCode::VM_MAX_TYPE_INSTANTIATION_NODES_REACHED)
+    );
+}
+
+#[test]
+fn memory_usage() {
+    // Memory is only accounted for with a quota.
+    let untracked = run_with_memory_usage(
+        VMConfig::default(),
+        "M",
+        "churn",
+        vec![],
+        vec![MoveValue::U64(1000), MoveValue::U64(3)],
+    );
+    assert_eq!(untracked.0, Ok(()));
+    assert_eq!(u64::from(untracked.1.peak), 0);
+
+    let config = || VMConfig {
+        max_memory_usage: Some(u64::MAX),
+        ..Default::default()
+    };
+    let publish = run_with_memory_usage(
+        config(),
+        "M",
+        "publish",
+        vec![],
+        vec![MoveValue::Signer(STD_ADDR), MoveValue::U64(1)],
+    );
+    // The resource holds a struct and a vector of four u64s, and stays in the data cache. The
+    // signer argument is only live during the call.
+    assert_eq!(publish.0, Ok(()));
+    assert_eq!(u64::from(publish.1.current), 2 + 4 * 8);
+    assert!(publish.1.peak > publish.1.current);
+
+    // Vectors of 1000 u64s are built and dropped, and the memory returns to zero.
+    let churn = run_with_memory_usage(
+        config(),
+        "M",
+        "churn",
+        vec![],
+        vec![MoveValue::U64(1000), MoveValue::U64(3)],
+    );
+    assert_eq!(churn.0, Ok(()));
+    assert_eq!(u64::from(churn.1.current), 0);
+    assert!(u64::from(churn.1.peak) > 4 * 1000 * 8);
+}
+
+#[test]
+fn memory_quota() {
+    let churn = |max_memory_usage, m| {
+        run_with_memory_usage(
+            VMConfig {
+                max_memory_usage,
+                ..Default::default()
+            },
+            "M",
+            "churn",
+            vec![],
+            vec![MoveValue::U64(100), MoveValue::U64(m)],
+        )
+    };
+    // The values of an iteration stay in locals until the next iteration overwrites them, so the
+    // peak is reached in the second one.
+    let (res, MemoryUsage { peak, .. }) = churn(Some(u64::MAX), 2);
+    assert_eq!(res, Ok(()));
+    // The memory of the values dropped is released, so that more iterations fit in the quota.
+    let peak = u64::from(peak);
+    assert_eq!(churn(Some(peak), 50).0, Ok(()));
+    let (res, usage) = churn(Some(peak - 1), 2);
+    assert_eq!(res, Err(StatusCode::MEMORY_LIMIT_EXCEEDED));
+    assert_eq!(u64::from(usage.current), 0);
+}
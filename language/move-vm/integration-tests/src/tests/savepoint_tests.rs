@@ -0,0 +1,307 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::{as_module, compile_units};
+use move_binary_format::errors::VMResult;
+use move_core_types::{
+    account_address::AccountAddress,
+    effects::{ChangeSet, Event},
+    identifier::Identifier,
+    language_storage::{ModuleId, TypeTag},
+    resolver::MoveResolver,
+    value::MoveValue,
+    vm_status::StatusCode,
+};
+use move_vm_runtime::{move_vm::MoveVM, session::Session};
+use move_vm_test_utils::InMemoryStorage;
+use move_vm_types::{gas::UnmeteredGasMeter, values::Value};
+
+const TEST_ADDR: AccountAddress = AccountAddress::new([42; AccountAddress::LENGTH]);
+const ALICE: AccountAddress = AccountAddress::new([1; AccountAddress::LENGTH]);
+const BOB: AccountAddress = AccountAddress::new([2; AccountAddress::LENGTH]);
+
+const COUNTER: &str = r#"
+    module TEST_ADDR::Counter {
+        struct Counter has key { value: u64 }
+
+        public fun create(account: signer) {
+            move_to(&account, Counter { value: 0 })
+        }
+
+        public fun bump(account: address) acquires Counter {
+            let counter = borrow_global_mut<Counter>(account);
+            counter.value = counter.value + 1;
+        }
+
+        public fun remove(account: address) acquires Counter {
+            let Counter { value: _ } = move_from<Counter>(account);
+        }
+
+        public fun fail() {
+            abort 42
+        }
+    }
+"#;
+
+const EXTRA: &str = r#"
+    module TEST_ADDR::Extra {
+        public fun get(): u64 { 7 }
+    }
+"#;
+
+fn compile(code: &str) -> Vec<u8> {
+    let units = compile_units(&code.replace("TEST_ADDR", &format!("0x{}", TEST_ADDR))).unwrap();
+    let module = as_module(units.into_iter().next().unwrap());
+    let mut blob = vec![];
+    module.serialize(&mut blob).unwrap();
+    blob
+}
+
+fn setup() -> (MoveVM, InMemoryStorage) {
+    let mut storage = InMemoryStorage::new();
+    storage.publish_or_overwrite_module(
+        ModuleId::new(TEST_ADDR, Identifier::new("Counter").unwrap()),
+        compile(COUNTER),
+    );
+    (MoveVM::new(vec![]).unwrap(), storage)
+}
+
+fn call<S: MoveResolver>(
+    session: &mut Session<S>,
+    module: &str,
+    function: &str,
+    args: Vec<MoveValue>,
+) -> VMResult<()> {
+    session.execute_function_bypass_visibility(
+        &ModuleId::new(TEST_ADDR, Identifier::new(module).unwrap()),
+        &Identifier::new(function).unwrap(),
+        vec![],
+        args.iter()
+            .map(|arg| arg.simple_serialize().unwrap())
+            .collect(),
+        &mut UnmeteredGasMeter,
+    )?;
+    Ok(())
+}
+
+fn emit<S: MoveResolver>(session: &mut Session<S>, seq_num: u64) {
+    let ty = session.load_type(&TypeTag::U64).unwrap();
+    session
+        .get_data_store()
+        .emit_event(vec![0], seq_num, ty, Value::u64(seq_num))
+        .unwrap();
+}
+
+fn prologue<S: MoveResolver>(session: &mut Session<S>) {
+    call(session, "Counter", "create", vec![MoveValue::Signer(ALICE)]).unwrap();
+    emit(session, 0);
+}
+
+fn epilogue<S: MoveResolver>(session: &mut Session<S>) {
+    call(session, "Counter", "bump", vec![MoveValue::Address(ALICE)]).unwrap();
+    emit(session, 2);
+}
+
+fn payload<S: MoveResolver>(session: &mut Session<S>) -> VMResult<()> {
+    call(session, "Counter", "bump", vec![MoveValue::Address(ALICE)])?;
+    call(session, "Counter", "create", vec![MoveValue::Signer(BOB)])?;
+    emit(session, 1);
+    call(session, "Counter", "fail", vec![])
+}
+
+/// The effects of a session which only runs the prologue and the epilogue.
+fn expected_effects(vm: &MoveVM, storage: &InMemoryStorage) -> (ChangeSet, Vec<Event>) {
+    let mut session = vm.new_session(storage);
+    prologue(&mut session);
+    epilogue(&mut session);
+    session.finish().unwrap()
+}
+
+#[test]
+fn rollback_aborted_payload() {
+    let (vm, storage) = setup();
+    let mut session = vm.new_session(&storage);
+    prologue(&mut session);
+    let savepoint = session.savepoint().unwrap();
+    let err = payload(&mut session).unwrap_err();
+    assert_eq!(err.major_status(), StatusCode::ABORTED);
+    session.rollback_to(savepoint);
+    epilogue(&mut session);
+
+    let (change_set, events) = session.finish().unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!((change_set, events), expected_effects(&vm, &storage));
+}
+
+#[test]
+fn nested_savepoints() {
+    let (vm, storage) = setup();
+    let mut session = vm.new_session(&storage);
+    prologue(&mut session);
+    let inner = session.savepoint().unwrap();
+    call(
+        &mut session,
+        "Counter",
+        "remove",
+        vec![MoveValue::Address(ALICE)],
+    )
+    .unwrap();
+    let innermost = session.savepoint().unwrap();
+    call(
+        &mut session,
+        "Counter",
+        "create",
+        vec![MoveValue::Signer(ALICE)],
+    )
+    .unwrap();
+    session.rollback_to(innermost);
+    session.rollback_to(inner);
+    epilogue(&mut session);
+    assert_eq!(session.finish().unwrap(), expected_effects(&vm, &storage));
+
+    let mut session = vm.new_session(&storage);
+    let outer = session.savepoint().unwrap();
+    prologue(&mut session);
+    let inner = session.savepoint().unwrap();
+    epilogue(&mut session);
+    // a savepoint is independent of the savepoints taken after it
+    session.rollback_to(outer);
+    drop(inner);
+    let (change_set, events) = session.finish().unwrap();
+    assert!(change_set.accounts().is_empty());
+    assert!(events.is_empty());
+}
+
+#[test]
+fn rollback_published_module() {
+    let (vm, storage) = setup();
+    let mut session = vm.new_session(&storage);
+    prologue(&mut session);
+    let savepoint = session.savepoint().unwrap();
+    session
+        .publish_module(compile(EXTRA), TEST_ADDR, &mut UnmeteredGasMeter)
+        .unwrap();
+    call(&mut session, "Extra", "get", vec![]).unwrap();
+    session.rollback_to(savepoint);
+    epilogue(&mut session);
+    assert_eq!(session.finish().unwrap(), expected_effects(&vm, &storage));
+
+    // the module has been loaded, so it must be evicted from the loader cache
+    assert!(vm.is_loader_cache_invalidated());
+    vm.flush_loader_cache_if_invalidated();
+    let mut session = vm.new_session(&storage);
+    assert_eq!(
+        call(&mut session, "Extra", "get", vec![])
+            .unwrap_err()
+            .major_status(),
+        StatusCode::LINKER_ERROR
+    );
+}
+
+#[cfg(feature = "table-extension")]
+mod tables {
+    use super::*;
+    use move_table_extension::{table_natives, GasParameters, NativeTableContext};
+    use move_vm_runtime::native_extensions::NativeContextExtensions;
+
+    const TABLE: &str = r#"
+        module TEST_ADDR::table {
+            struct Table<phantom K: copy + drop, phantom V> has store {
+                handle: address,
+            }
+            struct Box<V> has key, drop, store { val: V }
+            struct Holder has key { table: Table<u64, u64> }
+
+            public fun create(account: signer) {
+                let table = Table { handle: new_table_handle<u64, u64>() };
+                move_to(&account, Holder { table })
+            }
+
+            public fun add(account: address, key: u64) acquires Holder {
+                let table = &mut borrow_global_mut<Holder>(account).table;
+                add_box<u64, u64, Box<u64>>(table, key, Box { val: key })
+            }
+
+            public fun contains(account: address, key: u64): bool acquires Holder {
+                contains_box<u64, u64, Box<u64>>(&borrow_global<Holder>(account).table, key)
+            }
+
+            native fun new_table_handle<K, V>(): address;
+            native fun add_box<K: copy + drop, V, B>(table: &mut Table<K, V>, key: K, val: Box<V>);
+            native fun contains_box<K: copy + drop, V, B>(table: &Table<K, V>, key: K): bool;
+        }
+    "#;
+
+    fn call_table<S: MoveResolver>(
+        session: &mut Session<S>,
+        function: &str,
+        args: Vec<MoveValue>,
+    ) -> Vec<Vec<u8>> {
+        session
+            .execute_function_bypass_visibility(
+                &ModuleId::new(TEST_ADDR, Identifier::new("table").unwrap()),
+                &Identifier::new(function).unwrap(),
+                vec![],
+                args.iter()
+                    .map(|arg| arg.simple_serialize().unwrap())
+                    .collect(),
+                &mut UnmeteredGasMeter,
+            )
+            .unwrap()
+            .return_values
+            .into_iter()
+            .map(|(bytes, _)| bytes)
+            .collect()
+    }
+
+    fn add<S: MoveResolver>(session: &mut Session<S>, key: u64) {
+        call_table(
+            session,
+            "add",
+            vec![MoveValue::Address(ALICE), MoveValue::U64(key)],
+        );
+    }
+
+    fn contains<S: MoveResolver>(session: &mut Session<S>, key: u64) -> bool {
+        let result = call_table(
+            session,
+            "contains",
+            vec![MoveValue::Address(ALICE), MoveValue::U64(key)],
+        );
+        result == vec![MoveValue::Bool(true).simple_serialize().unwrap()]
+    }
+
+    #[test]
+    fn rollback_table_context() {
+        let mut storage = InMemoryStorage::new();
+        storage.publish_or_overwrite_module(
+            ModuleId::new(TEST_ADDR, Identifier::new("table").unwrap()),
+            compile(TABLE),
+        );
+        let vm = MoveVM::new(table_natives(TEST_ADDR, GasParameters::zeros())).unwrap();
+        let mut extensions = NativeContextExtensions::default();
+        extensions.add_with_savepoints(NativeTableContext::new([0; 32], &storage));
+        let mut session = vm.new_session_with_extensions(&storage, extensions);
+
+        call_table(&mut session, "create", vec![MoveValue::Signer(ALICE)]);
+        add(&mut session, 1);
+        let savepoint = session.savepoint().unwrap();
+        add(&mut session, 2);
+        assert!(contains(&mut session, 2));
+        session.rollback_to(savepoint);
+        assert!(contains(&mut session, 1));
+        assert!(!contains(&mut session, 2));
+
+        let (_, _, mut extensions) = session.finish_with_extensions().unwrap();
+        let table_change_set = extensions
+            .remove::<NativeTableContext>()
+            .into_change_set()
+            .unwrap();
+        assert_eq!(table_change_set.new_tables.len(), 1);
+        let change = table_change_set.changes.values().next().unwrap();
+        assert_eq!(change.entries.len(), 1);
+        assert!(change
+            .entries
+            .contains_key(&MoveValue::U64(1).simple_serialize().unwrap()));
+    }
+}
@@ -0,0 +1,121 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compiler::{as_module, compile_units};
+use move_bytecode_verifier::VerifierConfig;
+use move_core_types::{language_storage::ModuleId, vm_status::StatusCode};
+use move_vm_runtime::{
+    config::VMConfig,
+    move_vm::MoveVM,
+    verified_module_cache::{InMemoryVerifiedModuleCache, VerifiedModuleCache, VerifiedModuleKey},
+};
+use move_vm_test_utils::InMemoryStorage;
+use std::sync::Arc;
+
+const CODE: &str = r#"
+    module 0x2a::M {
+        public fun f(): u64 { 1 }
+        public fun g(): u64 { 2 }
+    }
+"#;
+
+fn setup() -> (InMemoryStorage, ModuleId, Vec<u8>) {
+    let mut storage = InMemoryStorage::new();
+    let module = as_module(compile_units(CODE).unwrap().pop().unwrap());
+    let mut blob = vec![];
+    module.serialize(&mut blob).unwrap();
+    storage.publish_or_overwrite_module(module.self_id(), blob.clone());
+    (storage, module.self_id(), blob)
+}
+
+fn load(
+    storage: &InMemoryStorage,
+    id: &ModuleId,
+    verifier: VerifierConfig,
+    cache: &Arc<InMemoryVerifiedModuleCache>,
+    reverify_cached_modules_one_in: Option<u64>,
+) -> Result<(), StatusCode> {
+    let vm = MoveVM::new_with_config(
+        vec![],
+        VMConfig {
+            verifier,
+            verified_module_cache: Some(cache.clone()),
+            reverify_cached_modules_one_in,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    vm.load_module(id, storage)
+        .map(|_| ())
+        .map_err(|e| e.major_status())
+}
+
+// A config under which the module fails verification.
+fn restrictive_config() -> VerifierConfig {
+    VerifierConfig {
+        max_function_definitions: Some(1),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn verified_modules_are_cached() {
+    let (storage, id, blob) = setup();
+    let cache = Arc::new(InMemoryVerifiedModuleCache::new());
+
+    assert_eq!(
+        load(&storage, &id, VerifierConfig::default(), &cache, None),
+        Ok(())
+    );
+    assert!(cache.contains(&VerifiedModuleKey::new(&blob, &VerifierConfig::default())));
+
+    // Modules which fail verification are not cached, and the module verified under the default
+    // config does not count as verified under another one.
+    assert_eq!(
+        load(&storage, &id, restrictive_config(), &cache, None),
+        Err(StatusCode::UNEXPECTED_VERIFIER_ERROR)
+    );
+    assert_eq!(cache.len(), 1);
+}
+
+#[test]
+fn cached_modules_skip_verification() {
+    let (storage, id, blob) = setup();
+    let cache = Arc::new(InMemoryVerifiedModuleCache::new());
+    cache.insert(VerifiedModuleKey::new(&blob, &restrictive_config()));
+
+    assert_eq!(
+        load(&storage, &id, restrictive_config(), &cache, None),
+        Ok(())
+    );
+    // Re-verifying every cached module catches the bad entry, which is removed so that later
+    // loads verify the module again.
+    assert_eq!(
+        load(&storage, &id, restrictive_config(), &cache, Some(1)),
+        Err(StatusCode::UNEXPECTED_VERIFIER_ERROR)
+    );
+    assert!(!cache.contains(&VerifiedModuleKey::new(&blob, &restrictive_config())));
+    assert_eq!(
+        load(&storage, &id, restrictive_config(), &cache, None),
+        Err(StatusCode::UNEXPECTED_VERIFIER_ERROR)
+    );
+}
+
+#[test]
+fn save_and_load() {
+    let (storage, id, blob) = setup();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("verified_modules");
+
+    let cache = Arc::new(InMemoryVerifiedModuleCache::load(&path).unwrap());
+    assert!(cache.is_empty());
+    assert_eq!(
+        load(&storage, &id, VerifierConfig::default(), &cache, None),
+        Ok(())
+    );
+    cache.save(&path).unwrap();
+
+    let cache = InMemoryVerifiedModuleCache::load(&path).unwrap();
+    assert_eq!(cache.len(), 1);
+    assert!(cache.contains(&VerifiedModuleKey::new(&blob, &VerifierConfig::default())));
+}
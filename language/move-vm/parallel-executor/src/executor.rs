@@ -0,0 +1,208 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::multi_version::{BlockView, MultiVersionStore, STORAGE_VERSION};
+use move_binary_format::errors::VMResult;
+use move_core_types::{
+    effects::{ChangeSet, Event},
+    language_storage::ModuleId,
+    resolver::{MoveResolver, Version},
+};
+use move_vm_runtime::{data_cache::AccessKey, move_vm::MoveVM, session::Session};
+use parking_lot::Mutex;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    thread,
+};
+
+/// The output of a transaction: the value returned by the host's execution function along with
+/// the effects of the session, or the error of either.
+pub type TransactionOutput<R> = VMResult<(R, ChangeSet, Vec<Event>)>;
+
+/// An execution of a transaction.
+struct Execution<R> {
+    output: TransactionOutput<R>,
+    /// The versions read, or `None` if the accesses of the session are not known, in which
+    /// case the execution can never be validated.
+    reads: Option<BTreeMap<AccessKey, Option<Version>>>,
+    /// The modules published by the session, whether or not the transaction succeeded.
+    modules: Vec<ModuleId>,
+}
+
+/// Executes blocks of transactions on the Move VM across multiple threads.
+///
+/// The outputs of a block are the same as if each transaction were executed in a session on
+/// the base storage with the change sets of the preceding successful transactions applied. The
+/// host applies the change sets to its storage after the block.
+///
+/// Transactions which publish modules cannot be executed speculatively, since the loader caches
+/// modules across sessions. Once a transaction publishes a module, the modules published
+/// speculatively are evicted from the loader cache and the rest of the block is executed
+/// sequentially. No other session of the VM may be alive while a block is executed.
+pub struct ParallelExecutor<'v> {
+    vm: &'v MoveVM,
+    concurrency: usize,
+    next_version: AtomicU64,
+}
+
+impl<'v> ParallelExecutor<'v> {
+    /// Creates an executor which runs up to `concurrency` transactions at a time.
+    pub fn new(vm: &'v MoveVM, concurrency: usize) -> Self {
+        Self {
+            vm,
+            concurrency: concurrency.max(1),
+            next_version: AtomicU64::new(STORAGE_VERSION + 1),
+        }
+    }
+
+    /// Executes the block of transactions on the base storage, calling `execute` with a new
+    /// session for each execution of a transaction. `execute` may be called more than once for
+    /// a transaction and must only depend on the transaction and the session.
+    pub fn execute_block<S, T, R, F>(
+        &self,
+        base: &S,
+        txns: &[T],
+        execute: F,
+    ) -> Vec<TransactionOutput<R>>
+    where
+        S: MoveResolver + Sync,
+        T: Sync,
+        R: Send,
+        F: Fn(&T, &mut Session<BlockView<S>>) -> VMResult<R> + Sync,
+    {
+        let store = MultiVersionStore::default();
+        let executions = Mutex::new((0..txns.len()).map(|_| None).collect::<Vec<_>>());
+        let run = |txn_idx: usize| {
+            let execution = self.execute_txn(base, &store, txn_idx, &txns[txn_idx], &execute);
+            let modules_published = !execution.modules.is_empty();
+            executions.lock()[txn_idx] = Some(execution);
+            modules_published
+        };
+        let is_valid = |txn_idx: usize| -> bool {
+            let executions = executions.lock();
+            match &executions[txn_idx] {
+                Some(Execution {
+                    reads: Some(reads), ..
+                }) => reads
+                    .iter()
+                    .all(|(key, version)| *version == Some(store.version_at(key, txn_idx))),
+                _ => false,
+            }
+        };
+
+        let mut committed = 0;
+        let mut pending: Vec<usize> = (0..txns.len()).collect();
+        let mut sequential = false;
+        while committed < txns.len() {
+            if sequential {
+                // all preceding transactions are committed, so the execution is final
+                if run(committed) {
+                    self.evict_modules(&executions.lock()[committed..=committed]);
+                }
+                committed += 1;
+                continue;
+            }
+
+            let next = AtomicUsize::new(0);
+            let modules_published = AtomicUsize::new(0);
+            thread::scope(|scope| {
+                for _ in 0..self.concurrency.min(pending.len()) {
+                    scope.spawn(|| loop {
+                        let i = next.fetch_add(1, Ordering::SeqCst);
+                        if i >= pending.len() {
+                            break;
+                        }
+                        if run(pending[i]) {
+                            modules_published.fetch_add(1, Ordering::SeqCst);
+                        }
+                    });
+                }
+            });
+            if modules_published.into_inner() > 0 {
+                self.evict_modules(&executions.lock()[committed..]);
+                sequential = true;
+                continue;
+            }
+
+            while committed < txns.len() && is_valid(committed) {
+                committed += 1;
+            }
+            if committed == txns.len() {
+                break;
+            }
+            // the first transaction which read a stale version reads committed data only when
+            // executed again
+            if run(committed) {
+                self.evict_modules(&executions.lock()[committed..]);
+                sequential = true;
+            }
+            committed += 1;
+            pending = (committed..txns.len()).filter(|i| !is_valid(*i)).collect();
+        }
+
+        executions
+            .into_inner()
+            .into_iter()
+            .map(|execution| execution.expect("all transactions are executed").output)
+            .collect()
+    }
+
+    fn execute_txn<S, T, R, F>(
+        &self,
+        base: &S,
+        store: &MultiVersionStore,
+        txn_idx: usize,
+        txn: &T,
+        execute: &F,
+    ) -> Execution<R>
+    where
+        S: MoveResolver,
+        F: Fn(&T, &mut Session<BlockView<S>>) -> VMResult<R>,
+    {
+        let version = self.next_version.fetch_add(1, Ordering::SeqCst);
+        let view = BlockView::new(base, store, txn_idx);
+        let mut session = self.vm.new_session(&view);
+        let result = execute(txn, &mut session);
+        let (reads, modules, output) = match session.read_write_set() {
+            Ok(read_write_set) => {
+                let modules = read_write_set
+                    .writes
+                    .into_iter()
+                    .filter_map(|key| match key {
+                        AccessKey::Module(module_id) => Some(module_id),
+                        AccessKey::Resource(..) => None,
+                    })
+                    .collect();
+                let output = result.and_then(|value| {
+                    let (change_set, events) = session.finish()?;
+                    Ok((value, change_set, events))
+                });
+                (Some(read_write_set.reads), modules, output)
+            }
+            Err(err) => (None, vec![], Err(err)),
+        };
+        store.record(
+            txn_idx,
+            version,
+            output.as_ref().ok().map(|(_, change_set, _)| change_set),
+        );
+        Execution {
+            output,
+            reads,
+            modules,
+        }
+    }
+
+    /// Evicts the modules published by the executions from the loader cache, along with the
+    /// modules depending on them.
+    fn evict_modules<R>(&self, executions: &[Option<Execution<R>>]) {
+        let modules: BTreeSet<_> = executions
+            .iter()
+            .flatten()
+            .flat_map(|execution| execution.modules.iter().cloned())
+            .collect();
+        self.vm.mark_modules_as_invalid(modules);
+        self.vm.flush_loader_cache_if_invalidated();
+    }
+}
@@ -0,0 +1,18 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![forbid(unsafe_code)]
+
+//! Optimistic parallel execution of a block of transactions on the Move VM.
+//!
+//! Each transaction is a session on a view of the shared storage which includes the writes of
+//! all transactions preceding it in the block. Transactions are executed speculatively across
+//! threads, and the versions they read, as recorded by the session's data cache, are validated
+//! in block order. A transaction which read a stale version is executed again, so that the
+//! outputs are the same as if the block were executed sequentially.
+
+mod executor;
+mod multi_version;
+
+pub use executor::{ParallelExecutor, TransactionOutput};
+pub use multi_version::BlockView;
@@ -0,0 +1,148 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use move_core_types::{
+    account_address::AccountAddress,
+    effects::ChangeSet,
+    language_storage::{ModuleId, StructTag},
+    resolver::{ModuleResolver, MoveResolver, ResourceResolver, Version},
+};
+use move_vm_runtime::data_cache::AccessKey;
+use parking_lot::RwLock;
+use std::collections::{BTreeMap, HashMap};
+
+/// The version reported for data read from the base storage.
+pub(crate) const STORAGE_VERSION: Version = 0;
+
+/// The data written by each transaction of a block, as of its latest execution. `None` marks a
+/// deletion.
+#[derive(Default)]
+struct Writes {
+    data: HashMap<AccessKey, BTreeMap<usize, (Version, Option<Vec<u8>>)>>,
+    keys_by_txn: HashMap<usize, Vec<AccessKey>>,
+}
+
+/// A store holding multiple versions of the data written by the transactions of a block.
+#[derive(Default)]
+pub(crate) struct MultiVersionStore {
+    writes: RwLock<Writes>,
+}
+
+impl MultiVersionStore {
+    /// Replaces the writes of the transaction by those of its latest execution with the given
+    /// version. A failed execution writes nothing.
+    pub(crate) fn record(&self, txn_idx: usize, version: Version, change_set: Option<&ChangeSet>) {
+        let mut new_writes = vec![];
+        if let Some(change_set) = change_set {
+            for (addr, name, op) in change_set.modules() {
+                let key = AccessKey::Module(ModuleId::new(addr, name.clone()));
+                new_writes.push((key, op.ok().map(<[u8]>::to_vec)));
+            }
+            for (addr, tag, op) in change_set.resources() {
+                let key = AccessKey::Resource(addr, tag.clone());
+                new_writes.push((key, op.ok().map(<[u8]>::to_vec)));
+            }
+        }
+
+        let mut writes = self.writes.write();
+        for key in writes.keys_by_txn.remove(&txn_idx).unwrap_or_default() {
+            if let Some(versions) = writes.data.get_mut(&key) {
+                versions.remove(&txn_idx);
+            }
+        }
+        let keys = new_writes.iter().map(|(key, _)| key.clone()).collect();
+        for (key, blob) in new_writes {
+            writes
+                .data
+                .entry(key)
+                .or_default()
+                .insert(txn_idx, (version, blob));
+        }
+        writes.keys_by_txn.insert(txn_idx, keys);
+    }
+
+    /// Returns the data and version of the latest write to the key by a transaction preceding
+    /// `txn_idx`, if any.
+    fn read(&self, key: &AccessKey, txn_idx: usize) -> Option<(Option<Vec<u8>>, Version)> {
+        let writes = self.writes.read();
+        let (_, (version, blob)) = writes.data.get(key)?.range(..txn_idx).next_back()?;
+        Some((blob.clone(), *version))
+    }
+
+    /// Returns the version of the key which the transaction at `txn_idx` would read now.
+    pub(crate) fn version_at(&self, key: &AccessKey, txn_idx: usize) -> Version {
+        let writes = self.writes.read();
+        writes
+            .data
+            .get(key)
+            .and_then(|versions| versions.range(..txn_idx).next_back())
+            .map_or(STORAGE_VERSION, |(_, (version, _))| *version)
+    }
+}
+
+/// The view of a transaction on the storage: the base storage with the writes of the preceding
+/// transactions in the block applied. Data is reported with the version of the execution which
+/// wrote it, or `STORAGE_VERSION` if it comes from the base storage.
+pub struct BlockView<'a, S> {
+    base: &'a S,
+    store: &'a MultiVersionStore,
+    txn_idx: usize,
+}
+
+impl<'a, S: MoveResolver> BlockView<'a, S> {
+    pub(crate) fn new(base: &'a S, store: &'a MultiVersionStore, txn_idx: usize) -> Self {
+        Self {
+            base,
+            store,
+            txn_idx,
+        }
+    }
+
+    /// Returns the index of the transaction in the block.
+    pub fn txn_idx(&self) -> usize {
+        self.txn_idx
+    }
+}
+
+impl<'a, S: MoveResolver> ModuleResolver for BlockView<'a, S> {
+    type Error = S::Err;
+
+    fn get_module(&self, module_id: &ModuleId) -> Result<Option<Vec<u8>>, Self::Error> {
+        Ok(self.get_module_with_version(module_id)?.0)
+    }
+
+    fn get_module_with_version(
+        &self,
+        module_id: &ModuleId,
+    ) -> Result<(Option<Vec<u8>>, Option<Version>), Self::Error> {
+        let key = AccessKey::Module(module_id.clone());
+        Ok(match self.store.read(&key, self.txn_idx) {
+            Some((blob, version)) => (blob, Some(version)),
+            None => (self.base.get_module(module_id)?, Some(STORAGE_VERSION)),
+        })
+    }
+}
+
+impl<'a, S: MoveResolver> ResourceResolver for BlockView<'a, S> {
+    type Error = S::Err;
+
+    fn get_resource(
+        &self,
+        address: &AccountAddress,
+        tag: &StructTag,
+    ) -> Result<Option<Vec<u8>>, Self::Error> {
+        Ok(self.get_resource_with_version(address, tag)?.0)
+    }
+
+    fn get_resource_with_version(
+        &self,
+        address: &AccountAddress,
+        tag: &StructTag,
+    ) -> Result<(Option<Vec<u8>>, Option<Version>), Self::Error> {
+        let key = AccessKey::Resource(*address, tag.clone());
+        Ok(match self.store.read(&key, self.txn_idx) {
+            Some((blob, version)) => (blob, Some(version)),
+            None => (self.base.get_resource(address, tag)?, Some(STORAGE_VERSION)),
+        })
+    }
+}
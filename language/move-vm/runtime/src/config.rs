@@ -1,8 +1,10 @@
 // Copyright (c) The Move Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::verified_module_cache::VerifiedModuleCache;
 use move_binary_format::file_format_common::VERSION_MAX;
 use move_bytecode_verifier::VerifierConfig;
+use std::sync::Arc;
 
 /// Dynamic config options for the Move VM.
 pub struct VMConfig {
@@ -11,6 +13,46 @@ pub struct VMConfig {
     // When this flag is set to true, MoveVM will perform type check at every instruction
     // execution to ensure that type safety cannot be violated at runtime.
     pub paranoid_type_checks: bool,
+    /// Maximal number of values on the operand stack. Exceeding it fails with
+    /// `EXECUTION_STACK_OVERFLOW`.
+    pub max_operand_stack_size: usize,
+    /// Maximal number of frames on the call stack. Exceeding it fails with
+    /// `CALL_STACK_OVERFLOW`.
+    pub max_call_stack_size: usize,
+    /// Maximal depth of a value in terms of type depth. Exceeding it fails with
+    /// `VM_MAX_VALUE_DEPTH_REACHED`.
+    pub max_value_depth: usize,
+    /// Maximal nodes which are allowed when converting to layout. This includes the types
+    /// of fields for struct types. Exceeding it fails with `VM_MAX_TYPE_NODES_REACHED`.
+    pub max_type_to_layout_nodes: usize,
+    /// Maximal nodes which are all allowed when instantiating a generic type. This does not
+    /// include field types of structs. Exceeding it fails with
+    /// `VM_MAX_TYPE_INSTANTIATION_NODES_REACHED`.
+    pub max_type_instantiation_nodes: usize,
+    /// Maximal length of vectors created or grown by instructions and native functions, if any.
+    /// Exceeding it fails with `VM_MAX_VECTOR_LENGTH_REACHED`.
+    pub max_vector_length: Option<u64>,
+    /// Maximal number of bytes of the values a session produces, i.e. of the serialized
+    /// resources it writes and events it emits, if any. Exceeding it fails with
+    /// `VM_MAX_VALUE_BYTES_REACHED`.
+    pub max_value_bytes: Option<u64>,
+    /// Maximal number of events a session emits, if any. Exceeding it fails with
+    /// `VM_MAX_EVENTS_REACHED`.
+    pub max_events: Option<usize>,
+    /// Maximal memory held by the live values of a session at any point, in abstract memory
+    /// units, if any. Exceeding it fails with `MEMORY_LIMIT_EXCEEDED`. Memory is only accounted
+    /// for if this is set, so set it to `u64::MAX` to track the usage without limiting it.
+    pub max_memory_usage: Option<u64>,
+    /// Cache of the modules which passed the bytecode verifier, if any. Modules loaded from the
+    /// data store which are in the cache skip the verifier, and those which pass it are added.
+    pub verified_module_cache: Option<Arc<dyn VerifiedModuleCache>>,
+    /// Re-verify one in this many of the modules found in the verified module cache, if any,
+    /// as a safety check of the cache.
+    pub reverify_cached_modules_one_in: Option<u64>,
+    /// Whether the stack traces attached to errors, when enabled by the `testing` or
+    /// `stacktrace` features, include the values of the locals of each frame. Rendering them
+    /// costs time proportional to the size of the stack, so it is off by default.
+    pub stack_trace_locals: bool,
 }
 
 impl Default for VMConfig {
@@ -19,6 +61,18 @@ impl Default for VMConfig {
             verifier: VerifierConfig::default(),
             max_binary_format_version: VERSION_MAX,
             paranoid_type_checks: false,
+            max_operand_stack_size: 1024,
+            max_call_stack_size: 1024,
+            max_value_depth: 128,
+            max_type_to_layout_nodes: 256,
+            max_type_instantiation_nodes: 128,
+            max_vector_length: None,
+            max_value_bytes: None,
+            max_events: None,
+            max_memory_usage: None,
+            verified_module_cache: None,
+            reverify_cached_modules_one_in: None,
+            stack_trace_locals: false,
         }
     }
 }
@@ -2,16 +2,16 @@
 // Copyright (c) The Move Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::loader::Loader;
+use crate::{loader::Loader, memory::held_memory};
 
 use move_binary_format::errors::*;
 use move_core_types::{
     account_address::AccountAddress,
     effects::{AccountChangeSet, ChangeSet, Event, Op},
-    gas_algebra::NumBytes,
+    gas_algebra::{AbstractMemorySize, NumBytes},
     identifier::Identifier,
-    language_storage::{ModuleId, TypeTag},
-    resolver::MoveResolver,
+    language_storage::{ModuleId, StructTag, TypeTag},
+    resolver::{MoveResolver, Version},
     value::MoveTypeLayout,
     vm_status::StatusCode,
 };
@@ -19,8 +19,73 @@ use move_vm_types::{
     data_store::DataStore,
     loaded_data::runtime_types::Type,
     values::{GlobalValue, Value},
+    views::ValueView,
 };
-use std::collections::btree_map::BTreeMap;
+use std::{
+    cell::RefCell,
+    collections::{btree_map::BTreeMap, BTreeSet},
+};
+
+/// A module or resource in global storage.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AccessKey {
+    Resource(AccountAddress, StructTag),
+    Module(ModuleId),
+}
+
+/// The accesses of a transaction to global storage, which allow a host to detect conflicts
+/// between transactions executed concurrently.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReadWriteSet {
+    /// The modules and resources read from the remote storage, along with the version reported
+    /// by the remote on the first read.
+    pub reads: BTreeMap<AccessKey, Option<Version>>,
+    /// The modules published and the resources mutated by the transaction.
+    pub writes: BTreeSet<AccessKey>,
+}
+
+/// The memory held by the live values of a session, i.e. the values on the operand stack, in
+/// locals and in the data cache, in abstract memory units.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// The memory currently held.
+    pub current: AbstractMemorySize,
+    /// The most memory held at any point of the session.
+    pub peak: AbstractMemorySize,
+}
+
+impl MemoryUsage {
+    fn new() -> Self {
+        Self {
+            current: AbstractMemorySize::zero(),
+            peak: AbstractMemorySize::zero(),
+        }
+    }
+
+    fn use_memory(&mut self, size: AbstractMemorySize, max: Option<u64>) -> PartialVMResult<()> {
+        self.current += size;
+        self.peak = self.peak.max(self.current);
+        match max {
+            Some(max) if u64::from(self.current) > max => {
+                Err(PartialVMError::new(StatusCode::MEMORY_LIMIT_EXCEEDED))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn release_memory(&mut self, size: AbstractMemorySize) {
+        debug_assert!(
+            size <= self.current,
+            "released {:?} of memory but only {:?} is held",
+            size,
+            self.current
+        );
+        self.current = self
+            .current
+            .checked_sub(size)
+            .unwrap_or_else(AbstractMemorySize::zero);
+    }
+}
 
 pub struct AccountDataCache {
     data_map: BTreeMap<Type, (MoveTypeLayout, GlobalValue)>,
@@ -34,6 +99,24 @@ impl AccountDataCache {
             module_map: BTreeMap::new(),
         }
     }
+
+    fn copy(&self) -> PartialVMResult<Self> {
+        let data_map = self
+            .data_map
+            .iter()
+            .map(|(ty, (layout, gv))| Ok((ty.clone(), (layout.clone(), gv.copy_value()?))))
+            .collect::<PartialVMResult<_>>()?;
+        Ok(Self {
+            data_map,
+            module_map: self.module_map.clone(),
+        })
+    }
+}
+
+/// The saved state of a `TransactionDataCache`.
+pub(crate) struct DataCacheSavepoint {
+    account_map: BTreeMap<AccountAddress, AccountDataCache>,
+    num_events: usize,
 }
 
 /// Transaction data cache. Keep updates within a transaction so they can all be published at
@@ -54,6 +137,8 @@ pub(crate) struct TransactionDataCache<'r, 'l, S> {
     loader: &'l Loader,
     account_map: BTreeMap<AccountAddress, AccountDataCache>,
     event_data: Vec<(Vec<u8>, u64, Type, MoveTypeLayout, Value)>,
+    reads: RefCell<BTreeMap<AccessKey, Option<Version>>>,
+    memory_usage: MemoryUsage,
 }
 
 impl<'r, 'l, S: MoveResolver> TransactionDataCache<'r, 'l, S> {
@@ -65,7 +150,110 @@ impl<'r, 'l, S: MoveResolver> TransactionDataCache<'r, 'l, S> {
             loader,
             account_map: BTreeMap::new(),
             event_data: vec![],
+            reads: RefCell::new(BTreeMap::new()),
+            memory_usage: MemoryUsage::new(),
+        }
+    }
+
+    /// Returns the accesses to global storage made so far. Only the first read of a key from
+    /// the remote is recorded, as later reads are served from this cache.
+    pub(crate) fn read_write_set(&self) -> PartialVMResult<ReadWriteSet> {
+        let mut writes = BTreeSet::new();
+        for (addr, account_data_cache) in &self.account_map {
+            for module_name in account_data_cache.module_map.keys() {
+                writes.insert(AccessKey::Module(ModuleId::new(*addr, module_name.clone())));
+            }
+            for (ty, (_, gv)) in &account_data_cache.data_map {
+                if gv.is_mutated() {
+                    writes.insert(AccessKey::Resource(*addr, self.struct_tag(ty)?));
+                }
+            }
         }
+        Ok(ReadWriteSet {
+            reads: self.reads.borrow().clone(),
+            writes,
+        })
+    }
+
+    /// Saves the resources and modules in the cache and the events emitted so far.
+    pub(crate) fn savepoint(&self) -> PartialVMResult<DataCacheSavepoint> {
+        let account_map = self
+            .account_map
+            .iter()
+            .map(|(addr, account_data_cache)| Ok((*addr, account_data_cache.copy()?)))
+            .collect::<PartialVMResult<_>>()?;
+        Ok(DataCacheSavepoint {
+            account_map,
+            num_events: self.event_data.len(),
+        })
+    }
+
+    /// Restores the saved state. The modules published since the savepoint may have been loaded
+    /// into the loader cache, so they are marked as invalid there, like the modules of an
+    /// abandoned session. The reads since the savepoint remain recorded.
+    pub(crate) fn rollback_to(&mut self, savepoint: DataCacheSavepoint) {
+        let mut published = vec![];
+        for (addr, account_data_cache) in &self.account_map {
+            let saved_modules = savepoint
+                .account_map
+                .get(addr)
+                .map(|saved| &saved.module_map);
+            for (name, (blob, _)) in &account_data_cache.module_map {
+                let saved_blob = saved_modules.and_then(|modules| modules.get(name));
+                if saved_blob.map_or(true, |(saved_blob, _)| saved_blob != blob) {
+                    published.push(ModuleId::new(*addr, name.clone()));
+                }
+            }
+        }
+        if !published.is_empty() {
+            self.loader.mark_modules_as_invalid(published);
+        }
+        self.account_map = savepoint.account_map;
+        self.event_data.truncate(savepoint.num_events);
+        self.reset_memory_usage();
+    }
+
+    /// Returns the memory held by the live values so far.
+    pub(crate) fn memory_usage(&self) -> MemoryUsage {
+        self.memory_usage
+    }
+
+    /// Recomputes the memory currently held from the resources and events in the cache. This must
+    /// only be called when no other value is live, i.e. between executions, and releases the
+    /// values a failed execution left on the operand stack and in locals.
+    pub(crate) fn reset_memory_usage(&mut self) {
+        if self.loader.vm_config().max_memory_usage.is_none() {
+            return;
+        }
+        let mut current = AbstractMemorySize::zero();
+        for account_data_cache in self.account_map.values() {
+            for (_, gv) in account_data_cache.data_map.values() {
+                if let Some(view) = gv.view() {
+                    current += view.legacy_abstract_memory_size();
+                }
+            }
+        }
+        for (_, _, _, _, val) in &self.event_data {
+            current += held_memory(val);
+        }
+        self.memory_usage.current = current;
+    }
+
+    fn struct_tag(&self, ty: &Type) -> PartialVMResult<StructTag> {
+        match self.loader.type_to_type_tag(ty)? {
+            TypeTag::Struct(struct_tag) => Ok(*struct_tag),
+            _ => Err(PartialVMError::new(StatusCode::INTERNAL_TYPE_ERROR)),
+        }
+    }
+
+    fn record_read(&self, key: AccessKey, version: Option<Version>) {
+        self.reads.borrow_mut().entry(key).or_insert(version);
+    }
+
+    fn get_remote_module(&self, module_id: &ModuleId) -> Result<Option<Vec<u8>>, S::Err> {
+        let (blob, version) = self.remote.get_module_with_version(module_id)?;
+        self.record_read(AccessKey::Module(module_id.clone()), version);
+        Ok(blob)
     }
 
     /// Make a write set from the updated (dirty, deleted) global resources along with
@@ -73,6 +261,17 @@ impl<'r, 'l, S: MoveResolver> TransactionDataCache<'r, 'l, S> {
     ///
     /// Gives all proper guarantees on lifetime of global data as well.
     pub(crate) fn into_effects(self) -> PartialVMResult<(ChangeSet, Vec<Event>)> {
+        let max_value_bytes = self.loader.vm_config().max_value_bytes;
+        let mut value_bytes: u64 = 0;
+        let mut charge_value_bytes = |blob: &[u8]| {
+            value_bytes = value_bytes.saturating_add(blob.len() as u64);
+            match max_value_bytes {
+                Some(max) if value_bytes > max => {
+                    Err(PartialVMError::new(StatusCode::VM_MAX_VALUE_BYTES_REACHED))
+                }
+                _ => Ok(()),
+            }
+        };
         let mut change_set = ChangeSet::new();
         for (addr, account_data_cache) in self.account_map.into_iter() {
             let mut modules = BTreeMap::new();
@@ -102,12 +301,14 @@ impl<'r, 'l, S: MoveResolver> TransactionDataCache<'r, 'l, S> {
                         let resource_blob = val
                             .simple_serialize(&layout)
                             .ok_or_else(|| PartialVMError::new(StatusCode::INTERNAL_TYPE_ERROR))?;
+                        charge_value_bytes(&resource_blob)?;
                         resources.insert(struct_tag, Op::New(resource_blob));
                     }
                     Op::Modify(val) => {
                         let resource_blob = val
                             .simple_serialize(&layout)
                             .ok_or_else(|| PartialVMError::new(StatusCode::INTERNAL_TYPE_ERROR))?;
+                        charge_value_bytes(&resource_blob)?;
                         resources.insert(struct_tag, Op::Modify(resource_blob));
                     }
                     Op::Delete => {
@@ -131,6 +332,7 @@ impl<'r, 'l, S: MoveResolver> TransactionDataCache<'r, 'l, S> {
             let blob = val
                 .simple_serialize(&ty_layout)
                 .ok_or_else(|| PartialVMError::new(StatusCode::INTERNAL_TYPE_ERROR))?;
+            charge_value_bytes(&blob)?;
             events.push((guid, seq_num, ty_tag, blob))
         }
 
@@ -188,7 +390,14 @@ impl<'r, 'l, S: MoveResolver> DataStore for TransactionDataCache<'r, 'l, S> {
             // TODO(Gas): Shall we charge for this?
             let ty_layout = self.loader.type_to_type_layout(ty)?;
 
-            let gv = match self.remote.get_resource(&addr, &ty_tag) {
+            let remote_res = self.remote.get_resource_with_version(&addr, &ty_tag);
+            if let Ok((_, version)) = &remote_res {
+                self.reads
+                    .get_mut()
+                    .entry(AccessKey::Resource(addr, (*ty_tag).clone()))
+                    .or_insert(*version);
+            }
+            let gv = match remote_res.map(|(blob, _)| blob) {
                 Ok(Some(blob)) => {
                     load_res = Some(Some(NumBytes::new(blob.len() as u64)));
                     let val = match Value::simple_deserialize(&blob, &ty_layout) {
@@ -203,6 +412,10 @@ impl<'r, 'l, S: MoveResolver> DataStore for TransactionDataCache<'r, 'l, S> {
                         }
                     };
 
+                    if let Some(max_memory_usage) = self.loader.vm_config().max_memory_usage {
+                        self.memory_usage
+                            .use_memory(held_memory(&val), Some(max_memory_usage))?;
+                    }
                     GlobalValue::cached(val)?
                 }
                 Ok(None) => {
@@ -237,7 +450,7 @@ impl<'r, 'l, S: MoveResolver> DataStore for TransactionDataCache<'r, 'l, S> {
                 return Ok(blob.clone());
             }
         }
-        match self.remote.get_module(module_id) {
+        match self.get_remote_module(module_id) {
             Ok(Some(bytes)) => Ok(bytes),
             Ok(None) => Err(PartialVMError::new(StatusCode::LINKER_ERROR)
                 .with_message(format!("Cannot find {:?} in data cache", module_id))
@@ -278,8 +491,7 @@ impl<'r, 'l, S: MoveResolver> DataStore for TransactionDataCache<'r, 'l, S> {
             }
         }
         Ok(self
-            .remote
-            .get_module(module_id)
+            .get_remote_module(module_id)
             .map_err(|_| {
                 PartialVMError::new(StatusCode::STORAGE_ERROR).finish(Location::Undefined)
             })?
@@ -293,11 +505,28 @@ impl<'r, 'l, S: MoveResolver> DataStore for TransactionDataCache<'r, 'l, S> {
         ty: Type,
         val: Value,
     ) -> PartialVMResult<()> {
+        if let Some(max_events) = self.loader.vm_config().max_events {
+            if self.event_data.len() >= max_events {
+                return Err(PartialVMError::new(StatusCode::VM_MAX_EVENTS_REACHED));
+            }
+        }
         let ty_layout = self.loader.type_to_type_layout(&ty)?;
+        if self.loader.vm_config().max_memory_usage.is_some() {
+            self.use_memory(held_memory(&val))?;
+        }
         Ok(self.event_data.push((guid, seq_num, ty, ty_layout, val)))
     }
 
     fn events(&self) -> &Vec<(Vec<u8>, u64, Type, MoveTypeLayout, Value)> {
         &self.event_data
     }
+
+    fn use_memory(&mut self, size: AbstractMemorySize) -> PartialVMResult<()> {
+        self.memory_usage
+            .use_memory(size, self.loader.vm_config().max_memory_usage)
+    }
+
+    fn release_memory(&mut self, size: AbstractMemorySize) {
+        self.memory_usage.release_memory(size)
+    }
 }
@@ -0,0 +1,120 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! The instructions the interpreter executes.
+//!
+//! The code of every function is decoded once, when its module or script is loaded, from
+//! `Bytecode` into `Instruction`s. Decoding resolves what the interpreter would otherwise look
+//! up on every execution of an instruction: call targets, struct field offsets and field counts,
+//! struct types and the element types of vector instructions. Instructions of generic code which
+//! depend on the type arguments of the frame keep their instantiation index and are instantiated
+//! when executed.
+//!
+//! The decoded code has an instruction per bytecode, so code offsets are the same in both.
+//! Common sequences of two bytecodes are fused into a single instruction at the offset of the
+//! first one; the instruction at the offset of the second one is decoded as usual, so branching
+//! to it is unaffected. A fused instruction executes, charges gas and reports errors exactly as
+//! the sequence it replaces.
+
+use crate::loader::Function;
+use move_binary_format::file_format::{
+    CodeOffset, ConstantPoolIndex, FunctionInstantiationIndex, LocalIndex,
+    StructDefInstantiationIndex,
+};
+use move_core_types::u256::U256;
+use move_vm_types::{gas::SimpleInstruction, loaded_data::runtime_types::Type};
+use std::sync::Arc;
+
+/// A decoded `Bytecode`. Variants with the name of a bytecode have the same semantics as it,
+/// with their operands resolved.
+#[derive(Debug)]
+pub(crate) enum Instruction {
+    Pop,
+    Ret,
+    BrTrue(CodeOffset),
+    BrFalse(CodeOffset),
+    Branch(CodeOffset),
+    LdU8(u8),
+    LdU16(u16),
+    LdU32(u32),
+    LdU64(u64),
+    LdU128(u128),
+    LdU256(U256),
+    LdConst(ConstantPoolIndex),
+    LdTrue,
+    LdFalse,
+    CopyLoc(LocalIndex),
+    MoveLoc(LocalIndex),
+    StLoc(LocalIndex),
+    Call(Arc<Function>),
+    CallGeneric(Arc<Function>, FunctionInstantiationIndex),
+    MutBorrowLoc(LocalIndex),
+    ImmBorrowLoc(LocalIndex),
+    /// A field borrow with the offset of the field and the instruction it is charged as.
+    BorrowField(usize, SimpleInstruction),
+    /// Fused `CopyLoc`, `MoveLoc`, `MutBorrowLoc` or `ImmBorrowLoc` of a local followed by a
+    /// field borrow of the reference it pushes.
+    BorrowLocalField(LocalRef, LocalIndex, usize, SimpleInstruction),
+    /// A `Pack` or `PackGeneric` with the field count of the struct.
+    Pack(u16, bool),
+    Unpack(bool),
+    ReadRef,
+    WriteRef,
+    FreezeRef,
+    CastU8,
+    CastU16,
+    CastU32,
+    CastU64,
+    CastU128,
+    CastU256,
+    Add,
+    Sub,
+    Mul,
+    Mod,
+    Div,
+    BitOr,
+    BitAnd,
+    Xor,
+    Or,
+    And,
+    Not,
+    Eq,
+    Neq,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Shl,
+    Shr,
+    Abort,
+    Nop,
+    MutBorrowGlobal(Type),
+    ImmBorrowGlobal(Type),
+    MutBorrowGlobalGeneric(StructDefInstantiationIndex),
+    ImmBorrowGlobalGeneric(StructDefInstantiationIndex),
+    Exists(Type),
+    ExistsGeneric(StructDefInstantiationIndex),
+    MoveFrom(Type),
+    MoveFromGeneric(StructDefInstantiationIndex),
+    MoveTo(Type),
+    MoveToGeneric(StructDefInstantiationIndex),
+    /// Vector instructions carry their element type, which still has to be instantiated with
+    /// the type arguments of generic frames.
+    VecPack(Type, u64),
+    VecLen(Type),
+    VecImmBorrow(Type),
+    VecMutBorrow(Type),
+    VecPushBack(Type),
+    VecPopBack(Type),
+    VecUnpack(Type, u64),
+    VecSwap(Type),
+}
+
+/// How the first instruction of a fused `BorrowLocalField` pushes the reference to a local.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum LocalRef {
+    Copy,
+    Move,
+    MutBorrow,
+    ImmBorrow,
+}
@@ -3,18 +3,20 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    loader::{Function, Loader, Resolver},
+    instruction::{Instruction, LocalRef},
+    loader::{BinaryType, Function, Loader, Resolver},
+    memory::{element_memory, field_memory, held_memory, vector_memory, CONTAINER_MEMORY},
     native_functions::NativeContext,
     trace,
 };
 use fail::fail_point;
 use move_binary_format::{
     errors::*,
-    file_format::{Ability, AbilitySet, Bytecode, FunctionHandleIndex, FunctionInstantiationIndex},
+    file_format::{Ability, AbilitySet, Bytecode, FunctionInstantiationIndex},
 };
 use move_core_types::{
     account_address::AccountAddress,
-    gas_algebra::{NumArgs, NumBytes},
+    gas_algebra::{AbstractMemorySize, NumArgs, NumBytes},
     language_storage::TypeTag,
     vm_status::{StatusCode, StatusType},
 };
@@ -26,11 +28,11 @@ use move_vm_types::{
         self, GlobalValue, IntegerValue, Locals, Reference, Struct, StructRef, VMValueCast, Value,
         Vector, VectorRef,
     },
-    views::TypeView,
+    views::{TypeView, ValueView, ValueVisitor},
 };
 
 use crate::native_extensions::NativeContextExtensions;
-use std::{cmp::min, collections::VecDeque, fmt::Write, sync::Arc};
+use std::{borrow::Cow, cmp::min, collections::VecDeque, fmt::Write, sync::Arc};
 use tracing::error;
 
 macro_rules! debug_write {
@@ -69,6 +71,11 @@ pub(crate) struct Interpreter {
     call_stack: CallStack,
     /// Whether to perform a paranoid type safety checks at runtime.
     paranoid_type_checks: bool,
+    /// Whether stack traces attached to errors include the values of the locals of each frame.
+    stack_trace_locals: bool,
+    /// Whether the memory held by live values is accounted for, which is only the case if it is
+    /// limited.
+    track_memory: bool,
 }
 
 struct TypeWithLoader<'a, 'b> {
@@ -82,6 +89,78 @@ impl<'a, 'b> TypeView for TypeWithLoader<'a, 'b> {
     }
 }
 
+/// Finds the length of a value if it is a vector, without visiting its elements.
+struct VectorLength(usize);
+
+impl ValueVisitor for VectorLength {
+    fn visit_u8(&mut self, _depth: usize, _val: u8) {}
+
+    fn visit_u16(&mut self, _depth: usize, _val: u16) {}
+
+    fn visit_u32(&mut self, _depth: usize, _val: u32) {}
+
+    fn visit_u64(&mut self, _depth: usize, _val: u64) {}
+
+    fn visit_u128(&mut self, _depth: usize, _val: u128) {}
+
+    fn visit_u256(&mut self, _depth: usize, _val: move_core_types::u256::U256) {}
+
+    fn visit_bool(&mut self, _depth: usize, _val: bool) {}
+
+    fn visit_address(&mut self, _depth: usize, _val: AccountAddress) {}
+
+    fn visit_struct(&mut self, _depth: usize, _len: usize) -> bool {
+        false
+    }
+
+    fn visit_vec(&mut self, _depth: usize, len: usize) -> bool {
+        self.0 = len;
+        false
+    }
+
+    fn visit_ref(&mut self, _depth: usize, _is_global: bool) -> bool {
+        false
+    }
+
+    fn visit_vec_u8(&mut self, _depth: usize, vals: &[u8]) {
+        self.0 = vals.len();
+    }
+
+    fn visit_vec_u16(&mut self, _depth: usize, vals: &[u16]) {
+        self.0 = vals.len();
+    }
+
+    fn visit_vec_u32(&mut self, _depth: usize, vals: &[u32]) {
+        self.0 = vals.len();
+    }
+
+    fn visit_vec_u64(&mut self, _depth: usize, vals: &[u64]) {
+        self.0 = vals.len();
+    }
+
+    fn visit_vec_u128(&mut self, _depth: usize, vals: &[u128]) {
+        self.0 = vals.len();
+    }
+
+    fn visit_vec_u256(&mut self, _depth: usize, vals: &[move_core_types::u256::U256]) {
+        self.0 = vals.len();
+    }
+
+    fn visit_vec_bool(&mut self, _depth: usize, vals: &[bool]) {
+        self.0 = vals.len();
+    }
+
+    fn visit_vec_address(&mut self, _depth: usize, vals: &[AccountAddress]) {
+        self.0 = vals.len();
+    }
+}
+
+fn vector_length(val: &impl ValueView) -> u64 {
+    let mut visitor = VectorLength(0);
+    val.visit(&mut visitor);
+    visitor.0 as u64
+}
+
 impl Interpreter {
     /// Entrypoint into the interpreter. All external calls need to be routed through this
     /// function.
@@ -95,9 +174,11 @@ impl Interpreter {
         loader: &Loader,
     ) -> VMResult<Vec<Value>> {
         Interpreter {
-            operand_stack: Stack::new(),
-            call_stack: CallStack::new(),
+            operand_stack: Stack::new(loader.vm_config().max_operand_stack_size),
+            call_stack: CallStack::new(loader.vm_config().max_call_stack_size),
             paranoid_type_checks: loader.vm_config().paranoid_type_checks,
+            stack_trace_locals: loader.vm_config().stack_trace_locals,
+            track_memory: loader.vm_config().max_memory_usage.is_some(),
         }
         .execute_main(
             loader, data_store, gas_meter, extensions, function, ty_args, args,
@@ -122,13 +203,16 @@ impl Interpreter {
     ) -> VMResult<Vec<Value>> {
         let mut locals = Locals::new(function.local_count());
         for (i, value) in args.into_iter().enumerate() {
+            self.use_memory(data_store, || held_memory(&value))
+                .map_err(|e| self.set_location(e))?;
             locals
                 .store_loc(i, value)
                 .map_err(|e| self.set_location(e))?;
         }
 
+        let binary = function.get_resolver(loader).binary().clone();
         let mut current_frame = self
-            .make_new_frame(loader, function, ty_args, locals)
+            .make_new_frame(loader, function, binary, ty_args, locals)
             .map_err(|err| self.set_location(err))?;
         loop {
             let resolver = current_frame.resolver(loader);
@@ -138,6 +222,14 @@ impl Interpreter {
                     .map_err(|err| self.maybe_core_dump(err, &current_frame))?;
             match exit_code {
                 ExitCode::Return => {
+                    self.release_memory(data_store, || {
+                        current_frame
+                            .locals
+                            .local_views()
+                            .fold(AbstractMemorySize::zero(), |size, local| {
+                                size + held_memory(&local)
+                            })
+                    });
                     let non_ref_vals = current_frame
                         .locals
                         .drop_all_values()
@@ -154,12 +246,18 @@ impl Interpreter {
                         current_frame.pc += 1; // advance past the Call instruction in the caller
                     } else {
                         // end of execution. `self` should no longer be used afterward
+                        self.release_memory(data_store, || {
+                            self.operand_stack
+                                .value
+                                .iter()
+                                .fold(AbstractMemorySize::zero(), |size, value| {
+                                    size + held_memory(value)
+                                })
+                        });
                         return Ok(self.operand_stack.value);
                     }
                 }
-                ExitCode::Call(fh_idx) => {
-                    let func = resolver.function_from_handle(fh_idx);
-
+                ExitCode::Call(func) => {
                     if self.paranoid_type_checks {
                         self.check_friend_or_private_call(&current_frame.function, &func)?;
                     }
@@ -191,12 +289,13 @@ impl Interpreter {
                             extensions,
                             func,
                             vec![],
-                        )?;
+                        )
+                        .map_err(|err| self.attach_stack_trace(err, &current_frame))?;
                         current_frame.pc += 1; // advance past the Call instruction in the caller
                         continue;
                     }
                     let frame = self
-                        .make_call_frame(loader, func, vec![])
+                        .make_call_frame(loader, &resolver, func, vec![])
                         .map_err(|e| self.set_location(e))
                         .map_err(|err| self.maybe_core_dump(err, &current_frame))?;
                     self.call_stack.push(current_frame).map_err(|frame| {
@@ -207,12 +306,11 @@ impl Interpreter {
                     // Note: the caller will find the the callee's return values at the top of the shared operand stack
                     current_frame = frame;
                 }
-                ExitCode::CallGeneric(idx) => {
+                ExitCode::CallGeneric(func, idx) => {
                     // TODO(Gas): We should charge gas as we do type substitution...
                     let ty_args = resolver
                         .instantiate_generic_function(idx, current_frame.ty_args())
                         .map_err(|e| set_err_info!(current_frame, e))?;
-                    let func = resolver.function_from_instantiation(idx);
 
                     if self.paranoid_type_checks {
                         self.check_friend_or_private_call(&current_frame.function, &func)?;
@@ -241,12 +339,13 @@ impl Interpreter {
                     if func.is_native() {
                         self.call_native(
                             &resolver, data_store, gas_meter, extensions, func, ty_args,
-                        )?;
+                        )
+                        .map_err(|err| self.attach_stack_trace(err, &current_frame))?;
                         current_frame.pc += 1; // advance past the Call instruction in the caller
                         continue;
                     }
                     let frame = self
-                        .make_call_frame(loader, func, ty_args)
+                        .make_call_frame(loader, &resolver, func, ty_args)
                         .map_err(|e| self.set_location(e))
                         .map_err(|err| self.maybe_core_dump(err, &current_frame))?;
                     self.call_stack.push(current_frame).map_err(|frame| {
@@ -268,6 +367,7 @@ impl Interpreter {
     fn make_call_frame(
         &mut self,
         loader: &Loader,
+        resolver: &Resolver,
         func: Arc<Function>,
         ty_args: Vec<Type>,
     ) -> PartialVMResult<Frame> {
@@ -290,7 +390,8 @@ impl Interpreter {
                 }
             }
         }
-        self.make_new_frame(loader, func, ty_args, locals)
+        let binary = func.get_binary(resolver);
+        self.make_new_frame(loader, func, binary, ty_args, locals)
     }
 
     /// Create a new `Frame` given a `Function` and the function `Locals`.
@@ -300,6 +401,7 @@ impl Interpreter {
         &self,
         loader: &Loader,
         function: Arc<Function>,
+        binary: BinaryType,
         ty_args: Vec<Type>,
         locals: Locals,
     ) -> PartialVMResult<Frame> {
@@ -321,6 +423,7 @@ impl Interpreter {
             pc: 0,
             locals,
             function,
+            binary,
             ty_args,
             local_tys,
         })
@@ -372,6 +475,46 @@ impl Interpreter {
         for _ in 0..expected_args {
             args.push_front(self.operand_stack.pop()?);
         }
+        self.release_memory(data_store, || {
+            args.iter().fold(AbstractMemorySize::zero(), |size, arg| {
+                size + held_memory(arg)
+            })
+        });
+        // Unlike instructions, natives may grow or shrink the values behind mutable references
+        // in any way, so these are measured before and after the call.
+        let mut_refs = function
+            .parameter_types()
+            .iter()
+            .zip(args.iter())
+            .filter(|(ty, _)| matches!(ty, Type::MutableReference(_)))
+            .map(|(_, arg)| arg.copy_value()?.value_as::<Reference>())
+            .collect::<PartialVMResult<Vec<_>>>()?;
+        // Natives may only grow the vectors passed to them as `&mut vector<T>`.
+        let max_vector_length = resolver.loader().vm_config().max_vector_length;
+        let mut_vec_refs = function
+            .parameter_types()
+            .iter()
+            .zip(args.iter())
+            .filter(|(ty, _)| match ty {
+                Type::MutableReference(inner) => {
+                    max_vector_length.is_some() && matches!(**inner, Type::Vector(_))
+                }
+                _ => false,
+            })
+            .map(|(_, arg)| arg.copy_value()?.value_as::<Reference>())
+            .collect::<PartialVMResult<Vec<_>>>()?;
+        let mut_refs_memory = |mut_refs: &[Reference]| {
+            mut_refs
+                .iter()
+                .fold(AbstractMemorySize::zero(), |size, mut_ref| {
+                    size + held_memory(&mut_ref.value_view())
+                })
+        };
+        let held_before_call = if self.track_memory {
+            mut_refs_memory(&mut_refs)
+        } else {
+            AbstractMemorySize::zero()
+        };
 
         if self.paranoid_type_checks {
             for i in 0..expected_args {
@@ -421,10 +564,28 @@ impl Interpreter {
                 ),
             );
         }
+        // Natives may return new vectors, or grow those behind mutable references, in any way.
+        // Only these vectors themselves are checked, not the vectors nested in them.
+        if let Some(max_len) = max_vector_length {
+            if mut_vec_refs
+                .iter()
+                .any(|mut_ref| vector_length(&mut_ref.value_view()) > max_len)
+                || return_values
+                    .iter()
+                    .any(|value| vector_length(value) > max_len)
+            {
+                return Err(PartialVMError::new(
+                    StatusCode::VM_MAX_VECTOR_LENGTH_REACHED,
+                ));
+            }
+        }
         // Put return values on the top of the operand stack, where the caller will find them.
         // This is one of only two times the operand stack is shared across call stack frames; the other is in handling
         // the Return instruction for normal calls
+        self.release_memory(data_store, || held_before_call);
+        self.use_memory(data_store, || mut_refs_memory(&mut_refs))?;
         for value in return_values {
+            self.use_memory(data_store, || held_memory(&value))?;
             self.operand_stack.push(value)?;
         }
 
@@ -647,6 +808,35 @@ impl Interpreter {
         }
     }
 
+    //
+    // Memory accounting helpers.
+    //
+
+    /// Charges the memory computed by `size` to the data store. Computing a size may walk an
+    /// entire value, so this is skipped altogether if memory usage is not limited.
+    fn use_memory<D: DataStore + ?Sized>(
+        &self,
+        data_store: &mut D,
+        size: impl FnOnce() -> AbstractMemorySize,
+    ) -> PartialVMResult<()> {
+        if self.track_memory {
+            data_store.use_memory(size())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns the memory computed by `size` to the data store, if memory usage is limited.
+    fn release_memory<D: DataStore + ?Sized>(
+        &self,
+        data_store: &mut D,
+        size: impl FnOnce() -> AbstractMemorySize,
+    ) {
+        if self.track_memory {
+            data_store.release_memory(size())
+        }
+    }
+
     //
     // Debugging and logging helpers.
     //
@@ -812,12 +1002,42 @@ impl Interpreter {
         internal_state
     }
 
+    /// Attaches the stack trace to an error raised by the current frame, if stack traces are
+    /// enabled.
+    fn attach_stack_trace(&self, err: VMError, current_frame: &Frame) -> VMError {
+        if cfg!(feature = "testing") || cfg!(feature = "stacktrace") {
+            err.with_exec_state(self.get_internal_state(current_frame))
+        } else {
+            err
+        }
+    }
+
     fn set_location(&self, err: PartialVMError) -> VMError {
         err.finish(self.call_stack.current_location())
     }
 
-    fn get_internal_state(&self) -> ExecutionState {
-        self.get_stack_frames(usize::MAX)
+    /// Get the full stack trace, starting with the current frame, along with the values of the
+    /// locals of each frame if they are requested.
+    fn get_internal_state(&self, current_frame: &Frame) -> ExecutionState {
+        let frames = std::iter::once(current_frame).chain(self.call_stack.0.iter().rev());
+        let stack_trace = frames
+            .clone()
+            .map(|frame| {
+                (
+                    frame.function.module_id().cloned(),
+                    frame.function.index(),
+                    frame.pc,
+                )
+            })
+            .collect();
+        let exec_state = ExecutionState::new(stack_trace);
+        if !self.stack_trace_locals {
+            return exec_state;
+        }
+        let locals = frames
+            .map(|frame| values::debug::render_locals(&frame.locals).unwrap_or_default())
+            .collect();
+        exec_state.with_locals(locals)
     }
 
     /// Get count stack frames starting from the top of the stack.
@@ -843,30 +1063,34 @@ impl Interpreter {
     }
 }
 
-// TODO Determine stack size limits based on gas limit
-const OPERAND_STACK_SIZE_LIMIT: usize = 1024;
-const CALL_STACK_SIZE_LIMIT: usize = 1024;
-
 /// The operand stack.
 struct Stack {
     value: Vec<Value>,
     types: Vec<Type>,
+    max_size: usize,
 }
 
 impl Stack {
-    /// Create a new empty operand stack.
-    fn new() -> Self {
+    /// Create a new empty operand stack with the given maximal size.
+    fn new(max_size: usize) -> Self {
         Stack {
             value: vec![],
             types: vec![],
+            max_size,
         }
     }
 
     /// Push a `Value` on the stack if the max stack size has not been reached. Abort execution
     /// otherwise.
     fn push(&mut self, value: Value) -> PartialVMResult<()> {
-        if self.value.len() < OPERAND_STACK_SIZE_LIMIT {
-            self.value.push(value);
+        self.check_capacity()?;
+        self.value.push(value);
+        Ok(())
+    }
+
+    /// Abort execution if the max stack size has been reached, i.e. if a `push` would fail.
+    fn check_capacity(&self) -> PartialVMResult<()> {
+        if self.value.len() < self.max_size {
             Ok(())
         } else {
             Err(PartialVMError::new(StatusCode::EXECUTION_STACK_OVERFLOW))
@@ -911,7 +1135,7 @@ impl Stack {
     /// Push a `Value` on the stack if the max stack size has not been reached. Abort execution
     /// otherwise.
     fn push_ty(&mut self, ty: Type) -> PartialVMResult<()> {
-        if self.types.len() < OPERAND_STACK_SIZE_LIMIT {
+        if self.types.len() < self.max_size {
             self.types.push(ty);
             Ok(())
         } else {
@@ -951,17 +1175,17 @@ impl Stack {
 
 /// A call stack.
 // #[derive(Debug)]
-struct CallStack(Vec<Frame>);
+struct CallStack(Vec<Frame>, usize);
 
 impl CallStack {
-    /// Create a new empty call stack.
-    fn new() -> Self {
-        CallStack(vec![])
+    /// Create a new empty call stack with the given maximal size.
+    fn new(max_size: usize) -> Self {
+        CallStack(vec![], max_size)
     }
 
     /// Push a `Frame` on the call stack.
     fn push(&mut self, frame: Frame) -> ::std::result::Result<(), Frame> {
-        if self.0.len() < CALL_STACK_SIZE_LIMIT {
+        if self.0.len() < self.1 {
             self.0.push(frame);
             Ok(())
         } else {
@@ -987,16 +1211,17 @@ struct Frame {
     pc: u16,
     locals: Locals,
     function: Arc<Function>,
+    // the module or script of the function, kept to resolve against it without a lookup
+    binary: BinaryType,
     ty_args: Vec<Type>,
     local_tys: Vec<Type>,
 }
 
 /// An `ExitCode` from `execute_code_unit`.
-#[derive(Debug)]
 enum ExitCode {
     Return,
-    Call(FunctionHandleIndex),
-    CallGeneric(FunctionInstantiationIndex),
+    Call(Arc<Function>),
+    CallGeneric(Arc<Function>, FunctionInstantiationIndex),
 }
 
 fn check_ability(has_ability: bool) -> PartialVMResult<()> {
@@ -1021,13 +1246,10 @@ impl Frame {
     ) -> VMResult<ExitCode> {
         self.execute_code_impl(resolver, interpreter, data_store, gas_meter)
             .map_err(|e| {
-                let e = if cfg!(feature = "testing") || cfg!(feature = "stacktrace") {
-                    e.with_exec_state(interpreter.get_internal_state())
-                } else {
-                    e
-                };
-                e.at_code_offset(self.function.index(), self.pc)
-                    .finish(self.location())
+                let e = e
+                    .at_code_offset(self.function.index(), self.pc)
+                    .finish(self.location());
+                interpreter.attach_stack_trace(e, self)
             })
     }
 
@@ -1649,609 +1871,658 @@ impl Frame {
             };
         }
 
+        // The decoded instructions are executed, the bytecode is only used for tracing and the
+        // paranoid type checks. Both have an entry per code offset.
+        let instructions = resolver.instructions(self.function.index());
         let code = self.function.code();
         loop {
-            for instruction in &code[self.pc as usize..] {
-                trace!(
-                    &self.function,
+            let instruction = match instructions.get(self.pc as usize) {
+                Some(instruction) => instruction,
+                // ok we are out, it's a branch or the end of the code
+                // TODO: re-work the logic here. Tests should have a more
+                // natural way to plug in
+                None if cfg!(test) => {
+                    // In order to test the behavior of an instruction stream, hitting end of the
+                    // code should report no error so that we can check the
+                    // locals.
+                    return Ok(ExitCode::Return);
+                }
+                None => return Err(PartialVMError::new(StatusCode::PC_OVERFLOW)),
+            };
+            trace!(
+                &self.function,
+                &self.locals,
+                self.pc,
+                (code[self.pc as usize]),
+                resolver,
+                interpreter
+            );
+
+            fail_point!("move_vm::interpreter_loop", |_| {
+                Err(
+                    PartialVMError::new(StatusCode::VERIFIER_INVARIANT_VIOLATION)
+                        .with_message("Injected move_vm::interpreter verifier failure".to_owned()),
+                )
+            });
+
+            // Paranoid Mode: Perform the type stack transition check to make sure all type safety requirements has been met.
+            //
+            // We will run the checks for only the control flow instructions and StLoc here. The majority of checks will be
+            // performed after the instruction execution, i.e: the big match block below.
+            //
+            // The reason for this design is we charge gas during instruction execution and we want to perform checks only after
+            // proper gas has been charged for each instruction.
+
+            if interpreter.paranoid_type_checks {
+                interpreter.operand_stack.check_balance()?;
+                Self::pre_execution_type_stack_transition(
+                    &self.local_tys,
                     &self.locals,
-                    self.pc,
-                    instruction,
+                    self.ty_args(),
                     resolver,
-                    interpreter
-                );
+                    interpreter,
+                    &code[self.pc as usize],
+                )?;
+            }
 
-                fail_point!("move_vm::interpreter_loop", |_| {
-                    Err(
-                        PartialVMError::new(StatusCode::VERIFIER_INVARIANT_VIOLATION).with_message(
-                            "Injected move_vm::interpreter verifier failure".to_owned(),
-                        ),
-                    )
-                });
-
-                // Paranoid Mode: Perform the type stack transition check to make sure all type safety requirements has been met.
-                //
-                // We will run the checks for only the control flow instructions and StLoc here. The majority of checks will be
-                // performed after the instruction execution, i.e: the big match block below.
-                //
-                // The reason for this design is we charge gas during instruction execution and we want to perform checks only after
-                // proper gas has been charged for each instruction.
-
-                if interpreter.paranoid_type_checks {
-                    interpreter.operand_stack.check_balance()?;
-                    Self::pre_execution_type_stack_transition(
-                        &self.local_tys,
-                        &self.locals,
-                        self.ty_args(),
-                        resolver,
-                        interpreter,
-                        instruction,
-                    )?;
+            match instruction {
+                Instruction::Pop => {
+                    let popped_val = interpreter.operand_stack.pop()?;
+                    interpreter.release_memory(data_store, || held_memory(&popped_val));
+                    gas_meter.charge_pop(popped_val)?;
                 }
-
-                match instruction {
-                    Bytecode::Pop => {
-                        let popped_val = interpreter.operand_stack.pop()?;
-                        gas_meter.charge_pop(popped_val)?;
-                    }
-                    Bytecode::Ret => {
-                        gas_meter.charge_simple_instr(S::Ret)?;
-                        return Ok(ExitCode::Return);
-                    }
-                    Bytecode::BrTrue(offset) => {
-                        gas_meter.charge_simple_instr(S::BrTrue)?;
-                        if interpreter.operand_stack.pop_as::<bool>()? {
-                            self.pc = *offset;
-                            break;
-                        }
-                    }
-                    Bytecode::BrFalse(offset) => {
-                        gas_meter.charge_simple_instr(S::BrFalse)?;
-                        if !interpreter.operand_stack.pop_as::<bool>()? {
-                            self.pc = *offset;
-                            break;
-                        }
-                    }
-                    Bytecode::Branch(offset) => {
-                        gas_meter.charge_simple_instr(S::Branch)?;
+                Instruction::Ret => {
+                    gas_meter.charge_simple_instr(S::Ret)?;
+                    return Ok(ExitCode::Return);
+                }
+                Instruction::BrTrue(offset) => {
+                    gas_meter.charge_simple_instr(S::BrTrue)?;
+                    if interpreter.operand_stack.pop_as::<bool>()? {
                         self.pc = *offset;
-                        break;
-                    }
-                    Bytecode::LdU8(int_const) => {
-                        gas_meter.charge_simple_instr(S::LdU8)?;
-                        interpreter.operand_stack.push(Value::u8(*int_const))?;
-                    }
-                    Bytecode::LdU16(int_const) => {
-                        gas_meter.charge_simple_instr(S::LdU16)?;
-                        interpreter.operand_stack.push(Value::u16(*int_const))?;
-                    }
-                    Bytecode::LdU32(int_const) => {
-                        gas_meter.charge_simple_instr(S::LdU32)?;
-                        interpreter.operand_stack.push(Value::u32(*int_const))?;
-                    }
-                    Bytecode::LdU64(int_const) => {
-                        gas_meter.charge_simple_instr(S::LdU64)?;
-                        interpreter.operand_stack.push(Value::u64(*int_const))?;
-                    }
-                    Bytecode::LdU128(int_const) => {
-                        gas_meter.charge_simple_instr(S::LdU128)?;
-                        interpreter.operand_stack.push(Value::u128(*int_const))?;
+                        continue;
                     }
-                    Bytecode::LdU256(int_const) => {
-                        gas_meter.charge_simple_instr(S::LdU256)?;
-                        interpreter.operand_stack.push(Value::u256(*int_const))?;
+                }
+                Instruction::BrFalse(offset) => {
+                    gas_meter.charge_simple_instr(S::BrFalse)?;
+                    if !interpreter.operand_stack.pop_as::<bool>()? {
+                        self.pc = *offset;
+                        continue;
                     }
-                    Bytecode::LdConst(idx) => {
-                        let constant = resolver.constant_at(*idx);
-                        gas_meter.charge_ld_const(NumBytes::new(constant.data.len() as u64))?;
+                }
+                Instruction::Branch(offset) => {
+                    gas_meter.charge_simple_instr(S::Branch)?;
+                    self.pc = *offset;
+                    continue;
+                }
+                Instruction::LdU8(int_const) => {
+                    gas_meter.charge_simple_instr(S::LdU8)?;
+                    interpreter.operand_stack.push(Value::u8(*int_const))?;
+                }
+                Instruction::LdU16(int_const) => {
+                    gas_meter.charge_simple_instr(S::LdU16)?;
+                    interpreter.operand_stack.push(Value::u16(*int_const))?;
+                }
+                Instruction::LdU32(int_const) => {
+                    gas_meter.charge_simple_instr(S::LdU32)?;
+                    interpreter.operand_stack.push(Value::u32(*int_const))?;
+                }
+                Instruction::LdU64(int_const) => {
+                    gas_meter.charge_simple_instr(S::LdU64)?;
+                    interpreter.operand_stack.push(Value::u64(*int_const))?;
+                }
+                Instruction::LdU128(int_const) => {
+                    gas_meter.charge_simple_instr(S::LdU128)?;
+                    interpreter.operand_stack.push(Value::u128(*int_const))?;
+                }
+                Instruction::LdU256(int_const) => {
+                    gas_meter.charge_simple_instr(S::LdU256)?;
+                    interpreter.operand_stack.push(Value::u256(*int_const))?;
+                }
+                Instruction::LdConst(idx) => {
+                    let constant = resolver.constant_at(*idx);
+                    gas_meter.charge_ld_const(NumBytes::new(constant.data.len() as u64))?;
 
-                        let val = Value::deserialize_constant(constant).ok_or_else(|| {
-                            PartialVMError::new(StatusCode::VERIFIER_INVARIANT_VIOLATION)
-                                .with_message(
-                                    "Verifier failed to verify the deserialization of constants"
-                                        .to_owned(),
-                                )
-                        })?;
+                    let val = Value::deserialize_constant(constant).ok_or_else(|| {
+                        PartialVMError::new(StatusCode::VERIFIER_INVARIANT_VIOLATION).with_message(
+                            "Verifier failed to verify the deserialization of constants".to_owned(),
+                        )
+                    })?;
 
-                        gas_meter.charge_ld_const_after_deserialization(&val)?;
+                    gas_meter.charge_ld_const_after_deserialization(&val)?;
+                    interpreter.use_memory(data_store, || held_memory(&val))?;
 
-                        interpreter.operand_stack.push(val)?
-                    }
-                    Bytecode::LdTrue => {
-                        gas_meter.charge_simple_instr(S::LdTrue)?;
-                        interpreter.operand_stack.push(Value::bool(true))?;
-                    }
-                    Bytecode::LdFalse => {
-                        gas_meter.charge_simple_instr(S::LdFalse)?;
-                        interpreter.operand_stack.push(Value::bool(false))?;
-                    }
-                    Bytecode::CopyLoc(idx) => {
-                        // TODO(Gas): We should charge gas before copying the value.
-                        let local = self.locals.copy_loc(*idx as usize)?;
-                        gas_meter.charge_copy_loc(&local)?;
-                        interpreter.operand_stack.push(local)?;
-                    }
-                    Bytecode::MoveLoc(idx) => {
-                        let local = self.locals.move_loc(*idx as usize)?;
-                        gas_meter.charge_move_loc(&local)?;
+                    interpreter.operand_stack.push(val)?
+                }
+                Instruction::LdTrue => {
+                    gas_meter.charge_simple_instr(S::LdTrue)?;
+                    interpreter.operand_stack.push(Value::bool(true))?;
+                }
+                Instruction::LdFalse => {
+                    gas_meter.charge_simple_instr(S::LdFalse)?;
+                    interpreter.operand_stack.push(Value::bool(false))?;
+                }
+                Instruction::CopyLoc(idx) => {
+                    // TODO(Gas): We should charge gas before copying the value.
+                    let local = self.locals.copy_loc(*idx as usize)?;
+                    gas_meter.charge_copy_loc(&local)?;
+                    interpreter.use_memory(data_store, || held_memory(&local))?;
+                    interpreter.operand_stack.push(local)?;
+                }
+                Instruction::MoveLoc(idx) => {
+                    let local = self.locals.move_loc(*idx as usize)?;
+                    gas_meter.charge_move_loc(&local)?;
 
-                        interpreter.operand_stack.push(local)?;
-                    }
-                    Bytecode::StLoc(idx) => {
-                        let value_to_store = interpreter.operand_stack.pop()?;
-                        gas_meter.charge_store_loc(&value_to_store)?;
-                        self.locals.store_loc(*idx as usize, value_to_store)?;
-                    }
-                    Bytecode::Call(idx) => {
-                        return Ok(ExitCode::Call(*idx));
-                    }
-                    Bytecode::CallGeneric(idx) => {
-                        return Ok(ExitCode::CallGeneric(*idx));
-                    }
-                    Bytecode::MutBorrowLoc(idx) | Bytecode::ImmBorrowLoc(idx) => {
-                        let instr = match instruction {
-                            Bytecode::MutBorrowLoc(_) => S::MutBorrowLoc,
-                            _ => S::ImmBorrowLoc,
-                        };
-                        gas_meter.charge_simple_instr(instr)?;
-                        interpreter
-                            .operand_stack
-                            .push(self.locals.borrow_loc(*idx as usize)?)?;
-                    }
-                    Bytecode::ImmBorrowField(fh_idx) | Bytecode::MutBorrowField(fh_idx) => {
-                        let instr = match instruction {
-                            Bytecode::MutBorrowField(_) => S::MutBorrowField,
-                            _ => S::ImmBorrowField,
-                        };
-                        gas_meter.charge_simple_instr(instr)?;
-
-                        let reference = interpreter.operand_stack.pop_as::<StructRef>()?;
-
-                        let offset = resolver.field_offset(*fh_idx);
-                        let field_ref = reference.borrow_field(offset)?;
-                        interpreter.operand_stack.push(field_ref)?;
-                    }
-                    Bytecode::ImmBorrowFieldGeneric(fi_idx)
-                    | Bytecode::MutBorrowFieldGeneric(fi_idx) => {
-                        let instr = match instruction {
-                            Bytecode::MutBorrowField(_) => S::MutBorrowFieldGeneric,
-                            _ => S::ImmBorrowFieldGeneric,
-                        };
-                        gas_meter.charge_simple_instr(instr)?;
-
-                        let reference = interpreter.operand_stack.pop_as::<StructRef>()?;
-
-                        let offset = resolver.field_instantiation_offset(*fi_idx);
-                        let field_ref = reference.borrow_field(offset)?;
-                        interpreter.operand_stack.push(field_ref)?;
-                    }
-                    Bytecode::Pack(sd_idx) => {
-                        let field_count = resolver.field_count(*sd_idx);
-                        gas_meter.charge_pack(
-                            false,
-                            interpreter.operand_stack.last_n(field_count as usize)?,
-                        )?;
-                        let args = interpreter.operand_stack.popn(field_count)?;
-                        interpreter
-                            .operand_stack
-                            .push(Value::struct_(Struct::pack(args)))?;
-                    }
-                    Bytecode::PackGeneric(si_idx) => {
-                        let field_count = resolver.field_instantiation_count(*si_idx);
-                        gas_meter.charge_pack(
-                            true,
-                            interpreter.operand_stack.last_n(field_count as usize)?,
-                        )?;
-                        let args = interpreter.operand_stack.popn(field_count)?;
-                        interpreter
-                            .operand_stack
-                            .push(Value::struct_(Struct::pack(args)))?;
+                    interpreter.operand_stack.push(local)?;
+                }
+                Instruction::StLoc(idx) => {
+                    let value_to_store = interpreter.operand_stack.pop()?;
+                    gas_meter.charge_store_loc(&value_to_store)?;
+                    if let Some(local) = self.locals.local_view(*idx as usize) {
+                        interpreter.release_memory(data_store, || held_memory(&local));
                     }
-                    Bytecode::Unpack(_sd_idx) => {
-                        let struct_ = interpreter.operand_stack.pop_as::<Struct>()?;
+                    self.locals.store_loc(*idx as usize, value_to_store)?;
+                }
+                Instruction::Call(func) => {
+                    return Ok(ExitCode::Call(Arc::clone(func)));
+                }
+                Instruction::CallGeneric(func, idx) => {
+                    return Ok(ExitCode::CallGeneric(Arc::clone(func), *idx));
+                }
+                Instruction::MutBorrowLoc(idx) => {
+                    gas_meter.charge_simple_instr(S::MutBorrowLoc)?;
+                    interpreter
+                        .operand_stack
+                        .push(self.locals.borrow_loc(*idx as usize)?)?;
+                }
+                Instruction::ImmBorrowLoc(idx) => {
+                    gas_meter.charge_simple_instr(S::ImmBorrowLoc)?;
+                    interpreter
+                        .operand_stack
+                        .push(self.locals.borrow_loc(*idx as usize)?)?;
+                }
+                Instruction::BorrowField(offset, instr) => {
+                    gas_meter.charge_simple_instr(*instr)?;
 
-                        gas_meter.charge_unpack(false, struct_.field_views())?;
+                    let reference = interpreter.operand_stack.pop_as::<StructRef>()?;
 
-                        for value in struct_.unpack()? {
-                            interpreter.operand_stack.push(value)?;
+                    let field_ref = reference.borrow_field(*offset)?;
+                    interpreter.operand_stack.push(field_ref)?;
+                }
+                Instruction::BorrowLocalField(local_ref, idx, offset, instr) => {
+                    let reference = match local_ref {
+                        LocalRef::Copy => {
+                            let local = self.locals.copy_loc(*idx as usize)?;
+                            gas_meter.charge_copy_loc(&local)?;
+                            local
                         }
-                    }
-                    Bytecode::UnpackGeneric(_si_idx) => {
-                        let struct_ = interpreter.operand_stack.pop_as::<Struct>()?;
-
-                        gas_meter.charge_unpack(true, struct_.field_views())?;
-
-                        // TODO: Whether or not we want this gas metering in the loop is
-                        // questionable.  However, if we don't have it in the loop we could wind up
-                        // doing a fair bit of work before charging for it.
-                        for value in struct_.unpack()? {
-                            interpreter.operand_stack.push(value)?;
+                        LocalRef::Move => {
+                            let local = self.locals.move_loc(*idx as usize)?;
+                            gas_meter.charge_move_loc(&local)?;
+                            local
                         }
-                    }
-                    Bytecode::ReadRef => {
-                        let reference = interpreter.operand_stack.pop_as::<Reference>()?;
-                        gas_meter.charge_read_ref(reference.value_view())?;
-                        let value = reference.read_ref()?;
-                        interpreter.operand_stack.push(value)?;
-                    }
-                    Bytecode::WriteRef => {
-                        let reference = interpreter.operand_stack.pop_as::<Reference>()?;
-                        let value = interpreter.operand_stack.pop()?;
-                        gas_meter.charge_write_ref(&value, reference.value_view())?;
-                        reference.write_ref(value)?;
-                    }
-                    Bytecode::CastU8 => {
-                        gas_meter.charge_simple_instr(S::CastU8)?;
-                        let integer_value = interpreter.operand_stack.pop_as::<IntegerValue>()?;
-                        interpreter
-                            .operand_stack
-                            .push(Value::u8(integer_value.cast_u8()?))?;
-                    }
-                    Bytecode::CastU16 => {
-                        gas_meter.charge_simple_instr(S::CastU16)?;
-                        let integer_value = interpreter.operand_stack.pop_as::<IntegerValue>()?;
-                        interpreter
-                            .operand_stack
-                            .push(Value::u16(integer_value.cast_u16()?))?;
-                    }
-                    Bytecode::CastU32 => {
-                        gas_meter.charge_simple_instr(S::CastU16)?;
-                        let integer_value = interpreter.operand_stack.pop_as::<IntegerValue>()?;
-                        interpreter
-                            .operand_stack
-                            .push(Value::u32(integer_value.cast_u32()?))?;
-                    }
-                    Bytecode::CastU64 => {
-                        gas_meter.charge_simple_instr(S::CastU64)?;
-                        let integer_value = interpreter.operand_stack.pop_as::<IntegerValue>()?;
-                        interpreter
-                            .operand_stack
-                            .push(Value::u64(integer_value.cast_u64()?))?;
-                    }
-                    Bytecode::CastU128 => {
-                        gas_meter.charge_simple_instr(S::CastU128)?;
-                        let integer_value = interpreter.operand_stack.pop_as::<IntegerValue>()?;
-                        interpreter
-                            .operand_stack
-                            .push(Value::u128(integer_value.cast_u128()?))?;
-                    }
-                    Bytecode::CastU256 => {
-                        gas_meter.charge_simple_instr(S::CastU16)?;
-                        let integer_value = interpreter.operand_stack.pop_as::<IntegerValue>()?;
-                        interpreter
-                            .operand_stack
-                            .push(Value::u256(integer_value.cast_u256()?))?;
-                    }
-                    // Arithmetic Operations
-                    Bytecode::Add => {
-                        gas_meter.charge_simple_instr(S::Add)?;
-                        interpreter.binop_int(IntegerValue::add_checked)?
-                    }
-                    Bytecode::Sub => {
-                        gas_meter.charge_simple_instr(S::Sub)?;
-                        interpreter.binop_int(IntegerValue::sub_checked)?
-                    }
-                    Bytecode::Mul => {
-                        gas_meter.charge_simple_instr(S::Mul)?;
-                        interpreter.binop_int(IntegerValue::mul_checked)?
-                    }
-                    Bytecode::Mod => {
-                        gas_meter.charge_simple_instr(S::Mod)?;
-                        interpreter.binop_int(IntegerValue::rem_checked)?
-                    }
-                    Bytecode::Div => {
-                        gas_meter.charge_simple_instr(S::Div)?;
-                        interpreter.binop_int(IntegerValue::div_checked)?
-                    }
-                    Bytecode::BitOr => {
-                        gas_meter.charge_simple_instr(S::BitOr)?;
-                        interpreter.binop_int(IntegerValue::bit_or)?
-                    }
-                    Bytecode::BitAnd => {
-                        gas_meter.charge_simple_instr(S::BitAnd)?;
-                        interpreter.binop_int(IntegerValue::bit_and)?
-                    }
-                    Bytecode::Xor => {
-                        gas_meter.charge_simple_instr(S::Xor)?;
-                        interpreter.binop_int(IntegerValue::bit_xor)?
-                    }
-                    Bytecode::Shl => {
-                        gas_meter.charge_simple_instr(S::Shl)?;
-                        let rhs = interpreter.operand_stack.pop_as::<u8>()?;
-                        let lhs = interpreter.operand_stack.pop_as::<IntegerValue>()?;
-                        interpreter
-                            .operand_stack
-                            .push(lhs.shl_checked(rhs)?.into_value())?;
-                    }
-                    Bytecode::Shr => {
-                        gas_meter.charge_simple_instr(S::Shr)?;
-                        let rhs = interpreter.operand_stack.pop_as::<u8>()?;
-                        let lhs = interpreter.operand_stack.pop_as::<IntegerValue>()?;
-                        interpreter
-                            .operand_stack
-                            .push(lhs.shr_checked(rhs)?.into_value())?;
-                    }
-                    Bytecode::Or => {
-                        gas_meter.charge_simple_instr(S::Or)?;
-                        interpreter.binop_bool(|l, r| Ok(l || r))?
-                    }
-                    Bytecode::And => {
-                        gas_meter.charge_simple_instr(S::And)?;
-                        interpreter.binop_bool(|l, r| Ok(l && r))?
-                    }
-                    Bytecode::Lt => {
-                        gas_meter.charge_simple_instr(S::Lt)?;
-                        interpreter.binop_bool(IntegerValue::lt)?
-                    }
-                    Bytecode::Gt => {
-                        gas_meter.charge_simple_instr(S::Gt)?;
-                        interpreter.binop_bool(IntegerValue::gt)?
-                    }
-                    Bytecode::Le => {
-                        gas_meter.charge_simple_instr(S::Le)?;
-                        interpreter.binop_bool(IntegerValue::le)?
-                    }
-                    Bytecode::Ge => {
-                        gas_meter.charge_simple_instr(S::Ge)?;
-                        interpreter.binop_bool(IntegerValue::ge)?
-                    }
-                    Bytecode::Abort => {
-                        gas_meter.charge_simple_instr(S::Abort)?;
-                        let error_code = interpreter.operand_stack.pop_as::<u64>()?;
-                        let error = PartialVMError::new(StatusCode::ABORTED)
-                            .with_sub_status(error_code)
-                            .with_message(format!(
-                                "{} at offset {}",
-                                self.function.pretty_string(),
-                                self.pc,
-                            ));
-                        return Err(error);
-                    }
-                    Bytecode::Eq => {
-                        let lhs = interpreter.operand_stack.pop()?;
-                        let rhs = interpreter.operand_stack.pop()?;
-                        gas_meter.charge_eq(&lhs, &rhs)?;
-                        interpreter
-                            .operand_stack
-                            .push(Value::bool(lhs.equals(&rhs)?))?;
-                    }
-                    Bytecode::Neq => {
-                        let lhs = interpreter.operand_stack.pop()?;
-                        let rhs = interpreter.operand_stack.pop()?;
-                        gas_meter.charge_neq(&lhs, &rhs)?;
+                        LocalRef::MutBorrow => {
+                            gas_meter.charge_simple_instr(S::MutBorrowLoc)?;
+                            self.locals.borrow_loc(*idx as usize)?
+                        }
+                        LocalRef::ImmBorrow => {
+                            gas_meter.charge_simple_instr(S::ImmBorrowLoc)?;
+                            self.locals.borrow_loc(*idx as usize)?
+                        }
+                    };
+                    // the reference is not pushed, but it must fit on the stack
+                    interpreter.operand_stack.check_capacity()?;
+
+                    // the field borrow is executed at the next code offset
+                    self.pc += 1;
+                    trace!(
+                        &self.function,
+                        &self.locals,
+                        self.pc,
+                        (code[self.pc as usize]),
+                        resolver,
                         interpreter
+                    );
+                    gas_meter.charge_simple_instr(*instr)?;
+
+                    let field_ref = reference.value_as::<StructRef>()?.borrow_field(*offset)?;
+                    interpreter.operand_stack.push(field_ref)?;
+                }
+                Instruction::Pack(field_count, is_generic) => {
+                    gas_meter.charge_pack(
+                        *is_generic,
+                        interpreter.operand_stack.last_n(*field_count as usize)?,
+                    )?;
+                    if interpreter.track_memory {
+                        let size = interpreter
                             .operand_stack
-                            .push(Value::bool(!lhs.equals(&rhs)?))?;
-                    }
-                    Bytecode::MutBorrowGlobal(sd_idx) | Bytecode::ImmBorrowGlobal(sd_idx) => {
-                        let is_mut = matches!(instruction, Bytecode::MutBorrowGlobal(_));
-                        let addr = interpreter.operand_stack.pop_as::<AccountAddress>()?;
-                        let ty = resolver.get_struct_type(*sd_idx);
-                        interpreter.borrow_global(
-                            is_mut,
-                            false,
-                            resolver.loader(),
-                            gas_meter,
-                            data_store,
-                            addr,
-                            &ty,
-                        )?;
-                    }
-                    Bytecode::MutBorrowGlobalGeneric(si_idx)
-                    | Bytecode::ImmBorrowGlobalGeneric(si_idx) => {
-                        let is_mut = matches!(instruction, Bytecode::MutBorrowGlobalGeneric(_));
-                        let addr = interpreter.operand_stack.pop_as::<AccountAddress>()?;
-                        let ty = resolver.instantiate_generic_type(*si_idx, self.ty_args())?;
-                        interpreter.borrow_global(
-                            is_mut,
-                            true,
-                            resolver.loader(),
-                            gas_meter,
-                            data_store,
-                            addr,
-                            &ty,
-                        )?;
+                            .last_n(*field_count as usize)?
+                            .fold(CONTAINER_MEMORY, |size, field| size + field_memory(field));
+                        data_store.use_memory(size)?;
                     }
-                    Bytecode::Exists(sd_idx) => {
-                        let addr = interpreter.operand_stack.pop_as::<AccountAddress>()?;
-                        let ty = resolver.get_struct_type(*sd_idx);
-                        interpreter.exists(
-                            false,
-                            resolver.loader(),
-                            gas_meter,
-                            data_store,
-                            addr,
-                            &ty,
-                        )?;
-                    }
-                    Bytecode::ExistsGeneric(si_idx) => {
-                        let addr = interpreter.operand_stack.pop_as::<AccountAddress>()?;
-                        let ty = resolver.instantiate_generic_type(*si_idx, self.ty_args())?;
-                        interpreter.exists(
-                            true,
-                            resolver.loader(),
-                            gas_meter,
-                            data_store,
-                            addr,
-                            &ty,
-                        )?;
-                    }
-                    Bytecode::MoveFrom(sd_idx) => {
-                        let addr = interpreter.operand_stack.pop_as::<AccountAddress>()?;
-                        let ty = resolver.get_struct_type(*sd_idx);
-                        interpreter.move_from(
-                            false,
-                            resolver.loader(),
-                            gas_meter,
-                            data_store,
-                            addr,
-                            &ty,
-                        )?;
-                    }
-                    Bytecode::MoveFromGeneric(si_idx) => {
-                        let addr = interpreter.operand_stack.pop_as::<AccountAddress>()?;
-                        let ty = resolver.instantiate_generic_type(*si_idx, self.ty_args())?;
-                        interpreter.move_from(
-                            true,
-                            resolver.loader(),
-                            gas_meter,
-                            data_store,
-                            addr,
-                            &ty,
-                        )?;
-                    }
-                    Bytecode::MoveTo(sd_idx) => {
-                        let resource = interpreter.operand_stack.pop()?;
-                        let signer_reference = interpreter.operand_stack.pop_as::<StructRef>()?;
-                        let addr = signer_reference
-                            .borrow_field(0)?
-                            .value_as::<Reference>()?
-                            .read_ref()?
-                            .value_as::<AccountAddress>()?;
-                        let ty = resolver.get_struct_type(*sd_idx);
-                        // REVIEW: Can we simplify Interpreter::move_to?
-                        interpreter.move_to(
-                            false,
-                            resolver.loader(),
-                            gas_meter,
-                            data_store,
-                            addr,
-                            &ty,
-                            resource,
-                        )?;
-                    }
-                    Bytecode::MoveToGeneric(si_idx) => {
-                        let resource = interpreter.operand_stack.pop()?;
-                        let signer_reference = interpreter.operand_stack.pop_as::<StructRef>()?;
-                        let addr = signer_reference
-                            .borrow_field(0)?
-                            .value_as::<Reference>()?
-                            .read_ref()?
-                            .value_as::<AccountAddress>()?;
-                        let ty = resolver.instantiate_generic_type(*si_idx, self.ty_args())?;
-                        interpreter.move_to(
-                            true,
-                            resolver.loader(),
-                            gas_meter,
-                            data_store,
-                            addr,
-                            &ty,
-                            resource,
-                        )?;
-                    }
-                    Bytecode::FreezeRef => {
-                        gas_meter.charge_simple_instr(S::FreezeRef)?;
-                        // FreezeRef should just be a null op as we don't distinguish between mut
-                        // and immut ref at runtime.
-                    }
-                    Bytecode::Not => {
-                        gas_meter.charge_simple_instr(S::Not)?;
-                        let value = !interpreter.operand_stack.pop_as::<bool>()?;
-                        interpreter.operand_stack.push(Value::bool(value))?;
-                    }
-                    Bytecode::Nop => {
-                        gas_meter.charge_simple_instr(S::Nop)?;
-                    }
-                    Bytecode::VecPack(si, num) => {
-                        let ty = resolver.instantiate_single_type(*si, self.ty_args())?;
-                        gas_meter.charge_vec_pack(
-                            make_ty!(&ty),
-                            interpreter.operand_stack.last_n(*num as usize)?,
-                        )?;
-                        let elements = interpreter.operand_stack.popn(*num as u16)?;
-                        let value = Vector::pack(&ty, elements)?;
-                        interpreter.operand_stack.push(value)?;
-                    }
-                    Bytecode::VecLen(si) => {
-                        let vec_ref = interpreter.operand_stack.pop_as::<VectorRef>()?;
-                        let ty = &resolver.instantiate_single_type(*si, self.ty_args())?;
-                        gas_meter.charge_vec_len(TypeWithLoader {
-                            ty,
-                            loader: resolver.loader(),
-                        })?;
-                        let value = vec_ref.len(ty)?;
+                    let args = interpreter.operand_stack.popn(*field_count)?;
+                    interpreter
+                        .operand_stack
+                        .push(Value::struct_(Struct::pack(args)))?;
+                }
+                Instruction::Unpack(is_generic) => {
+                    let struct_ = interpreter.operand_stack.pop_as::<Struct>()?;
+
+                    gas_meter.charge_unpack(*is_generic, struct_.field_views())?;
+                    interpreter.release_memory(data_store, || {
+                        struct_
+                            .field_views()
+                            .fold(CONTAINER_MEMORY, |size, field| size + field_memory(&field))
+                    });
+
+                    // TODO: Whether or not we want this gas metering in the loop is
+                    // questionable.  However, if we don't have it in the loop we could wind up
+                    // doing a fair bit of work before charging for it.
+                    for value in struct_.unpack()? {
                         interpreter.operand_stack.push(value)?;
                     }
-                    Bytecode::VecImmBorrow(si) => {
-                        let idx = interpreter.operand_stack.pop_as::<u64>()? as usize;
-                        let vec_ref = interpreter.operand_stack.pop_as::<VectorRef>()?;
-                        let ty = resolver.instantiate_single_type(*si, self.ty_args())?;
-                        let res = vec_ref.borrow_elem(idx, &ty);
-                        gas_meter.charge_vec_borrow(false, make_ty!(&ty), res.is_ok())?;
-                        interpreter.operand_stack.push(res?)?;
-                    }
-                    Bytecode::VecMutBorrow(si) => {
-                        let idx = interpreter.operand_stack.pop_as::<u64>()? as usize;
-                        let vec_ref = interpreter.operand_stack.pop_as::<VectorRef>()?;
-                        let ty = &resolver.instantiate_single_type(*si, self.ty_args())?;
-                        let res = vec_ref.borrow_elem(idx, ty);
-                        gas_meter.charge_vec_borrow(true, make_ty!(ty), res.is_ok())?;
-                        interpreter.operand_stack.push(res?)?;
-                    }
-                    Bytecode::VecPushBack(si) => {
-                        let elem = interpreter.operand_stack.pop()?;
-                        let vec_ref = interpreter.operand_stack.pop_as::<VectorRef>()?;
-                        let ty = &resolver.instantiate_single_type(*si, self.ty_args())?;
-                        gas_meter.charge_vec_push_back(make_ty!(ty), &elem)?;
-                        vec_ref.push_back(elem, ty)?;
-                    }
-                    Bytecode::VecPopBack(si) => {
-                        let vec_ref = interpreter.operand_stack.pop_as::<VectorRef>()?;
-                        let ty = &resolver.instantiate_single_type(*si, self.ty_args())?;
-                        let res = vec_ref.pop(ty);
-                        gas_meter.charge_vec_pop_back(make_ty!(ty), res.as_ref().ok())?;
-                        interpreter.operand_stack.push(res?)?;
+                }
+                Instruction::ReadRef => {
+                    let reference = interpreter.operand_stack.pop_as::<Reference>()?;
+                    gas_meter.charge_read_ref(reference.value_view())?;
+                    let value = reference.read_ref()?;
+                    interpreter.use_memory(data_store, || held_memory(&value))?;
+                    interpreter.operand_stack.push(value)?;
+                }
+                Instruction::WriteRef => {
+                    let reference = interpreter.operand_stack.pop_as::<Reference>()?;
+                    let value = interpreter.operand_stack.pop()?;
+                    gas_meter.charge_write_ref(&value, reference.value_view())?;
+                    // the value written is moved, the value overwritten is dropped
+                    interpreter.release_memory(data_store, || held_memory(&reference.value_view()));
+                    reference.write_ref(value)?;
+                }
+                Instruction::CastU8 => {
+                    gas_meter.charge_simple_instr(S::CastU8)?;
+                    let integer_value = interpreter.operand_stack.pop_as::<IntegerValue>()?;
+                    interpreter
+                        .operand_stack
+                        .push(Value::u8(integer_value.cast_u8()?))?;
+                }
+                Instruction::CastU16 => {
+                    gas_meter.charge_simple_instr(S::CastU16)?;
+                    let integer_value = interpreter.operand_stack.pop_as::<IntegerValue>()?;
+                    interpreter
+                        .operand_stack
+                        .push(Value::u16(integer_value.cast_u16()?))?;
+                }
+                Instruction::CastU32 => {
+                    gas_meter.charge_simple_instr(S::CastU16)?;
+                    let integer_value = interpreter.operand_stack.pop_as::<IntegerValue>()?;
+                    interpreter
+                        .operand_stack
+                        .push(Value::u32(integer_value.cast_u32()?))?;
+                }
+                Instruction::CastU64 => {
+                    gas_meter.charge_simple_instr(S::CastU64)?;
+                    let integer_value = interpreter.operand_stack.pop_as::<IntegerValue>()?;
+                    interpreter
+                        .operand_stack
+                        .push(Value::u64(integer_value.cast_u64()?))?;
+                }
+                Instruction::CastU128 => {
+                    gas_meter.charge_simple_instr(S::CastU128)?;
+                    let integer_value = interpreter.operand_stack.pop_as::<IntegerValue>()?;
+                    interpreter
+                        .operand_stack
+                        .push(Value::u128(integer_value.cast_u128()?))?;
+                }
+                Instruction::CastU256 => {
+                    gas_meter.charge_simple_instr(S::CastU16)?;
+                    let integer_value = interpreter.operand_stack.pop_as::<IntegerValue>()?;
+                    interpreter
+                        .operand_stack
+                        .push(Value::u256(integer_value.cast_u256()?))?;
+                }
+                // Arithmetic Operations
+                Instruction::Add => {
+                    gas_meter.charge_simple_instr(S::Add)?;
+                    interpreter.binop_int(IntegerValue::add_checked)?
+                }
+                Instruction::Sub => {
+                    gas_meter.charge_simple_instr(S::Sub)?;
+                    interpreter.binop_int(IntegerValue::sub_checked)?
+                }
+                Instruction::Mul => {
+                    gas_meter.charge_simple_instr(S::Mul)?;
+                    interpreter.binop_int(IntegerValue::mul_checked)?
+                }
+                Instruction::Mod => {
+                    gas_meter.charge_simple_instr(S::Mod)?;
+                    interpreter.binop_int(IntegerValue::rem_checked)?
+                }
+                Instruction::Div => {
+                    gas_meter.charge_simple_instr(S::Div)?;
+                    interpreter.binop_int(IntegerValue::div_checked)?
+                }
+                Instruction::BitOr => {
+                    gas_meter.charge_simple_instr(S::BitOr)?;
+                    interpreter.binop_int(IntegerValue::bit_or)?
+                }
+                Instruction::BitAnd => {
+                    gas_meter.charge_simple_instr(S::BitAnd)?;
+                    interpreter.binop_int(IntegerValue::bit_and)?
+                }
+                Instruction::Xor => {
+                    gas_meter.charge_simple_instr(S::Xor)?;
+                    interpreter.binop_int(IntegerValue::bit_xor)?
+                }
+                Instruction::Shl => {
+                    gas_meter.charge_simple_instr(S::Shl)?;
+                    let rhs = interpreter.operand_stack.pop_as::<u8>()?;
+                    let lhs = interpreter.operand_stack.pop_as::<IntegerValue>()?;
+                    interpreter
+                        .operand_stack
+                        .push(lhs.shl_checked(rhs)?.into_value())?;
+                }
+                Instruction::Shr => {
+                    gas_meter.charge_simple_instr(S::Shr)?;
+                    let rhs = interpreter.operand_stack.pop_as::<u8>()?;
+                    let lhs = interpreter.operand_stack.pop_as::<IntegerValue>()?;
+                    interpreter
+                        .operand_stack
+                        .push(lhs.shr_checked(rhs)?.into_value())?;
+                }
+                Instruction::Or => {
+                    gas_meter.charge_simple_instr(S::Or)?;
+                    interpreter.binop_bool(|l, r| Ok(l || r))?
+                }
+                Instruction::And => {
+                    gas_meter.charge_simple_instr(S::And)?;
+                    interpreter.binop_bool(|l, r| Ok(l && r))?
+                }
+                Instruction::Lt => {
+                    gas_meter.charge_simple_instr(S::Lt)?;
+                    interpreter.binop_bool(IntegerValue::lt)?
+                }
+                Instruction::Gt => {
+                    gas_meter.charge_simple_instr(S::Gt)?;
+                    interpreter.binop_bool(IntegerValue::gt)?
+                }
+                Instruction::Le => {
+                    gas_meter.charge_simple_instr(S::Le)?;
+                    interpreter.binop_bool(IntegerValue::le)?
+                }
+                Instruction::Ge => {
+                    gas_meter.charge_simple_instr(S::Ge)?;
+                    interpreter.binop_bool(IntegerValue::ge)?
+                }
+                Instruction::Abort => {
+                    gas_meter.charge_simple_instr(S::Abort)?;
+                    let error_code = interpreter.operand_stack.pop_as::<u64>()?;
+                    let error = PartialVMError::new(StatusCode::ABORTED)
+                        .with_sub_status(error_code)
+                        .with_message(format!(
+                            "{} at offset {}",
+                            self.function.pretty_string(),
+                            self.pc,
+                        ));
+                    return Err(error);
+                }
+                Instruction::Eq => {
+                    let lhs = interpreter.operand_stack.pop()?;
+                    let rhs = interpreter.operand_stack.pop()?;
+                    gas_meter.charge_eq(&lhs, &rhs)?;
+                    interpreter
+                        .release_memory(data_store, || held_memory(&lhs) + held_memory(&rhs));
+                    interpreter
+                        .operand_stack
+                        .push(Value::bool(lhs.equals(&rhs)?))?;
+                }
+                Instruction::Neq => {
+                    let lhs = interpreter.operand_stack.pop()?;
+                    let rhs = interpreter.operand_stack.pop()?;
+                    gas_meter.charge_neq(&lhs, &rhs)?;
+                    interpreter
+                        .release_memory(data_store, || held_memory(&lhs) + held_memory(&rhs));
+                    interpreter
+                        .operand_stack
+                        .push(Value::bool(!lhs.equals(&rhs)?))?;
+                }
+                Instruction::MutBorrowGlobal(ty) | Instruction::ImmBorrowGlobal(ty) => {
+                    let is_mut = matches!(instruction, Instruction::MutBorrowGlobal(_));
+                    let addr = interpreter.operand_stack.pop_as::<AccountAddress>()?;
+                    interpreter.borrow_global(
+                        is_mut,
+                        false,
+                        resolver.loader(),
+                        gas_meter,
+                        data_store,
+                        addr,
+                        ty,
+                    )?;
+                }
+                Instruction::MutBorrowGlobalGeneric(si_idx)
+                | Instruction::ImmBorrowGlobalGeneric(si_idx) => {
+                    let is_mut = matches!(instruction, Instruction::MutBorrowGlobalGeneric(_));
+                    let addr = interpreter.operand_stack.pop_as::<AccountAddress>()?;
+                    let ty = resolver.instantiate_generic_type(*si_idx, self.ty_args())?;
+                    interpreter.borrow_global(
+                        is_mut,
+                        true,
+                        resolver.loader(),
+                        gas_meter,
+                        data_store,
+                        addr,
+                        &ty,
+                    )?;
+                }
+                Instruction::Exists(ty) => {
+                    let addr = interpreter.operand_stack.pop_as::<AccountAddress>()?;
+                    interpreter.exists(
+                        false,
+                        resolver.loader(),
+                        gas_meter,
+                        data_store,
+                        addr,
+                        ty,
+                    )?;
+                }
+                Instruction::ExistsGeneric(si_idx) => {
+                    let addr = interpreter.operand_stack.pop_as::<AccountAddress>()?;
+                    let ty = resolver.instantiate_generic_type(*si_idx, self.ty_args())?;
+                    interpreter.exists(
+                        true,
+                        resolver.loader(),
+                        gas_meter,
+                        data_store,
+                        addr,
+                        &ty,
+                    )?;
+                }
+                Instruction::MoveFrom(ty) => {
+                    let addr = interpreter.operand_stack.pop_as::<AccountAddress>()?;
+                    interpreter.move_from(
+                        false,
+                        resolver.loader(),
+                        gas_meter,
+                        data_store,
+                        addr,
+                        ty,
+                    )?;
+                }
+                Instruction::MoveFromGeneric(si_idx) => {
+                    let addr = interpreter.operand_stack.pop_as::<AccountAddress>()?;
+                    let ty = resolver.instantiate_generic_type(*si_idx, self.ty_args())?;
+                    interpreter.move_from(
+                        true,
+                        resolver.loader(),
+                        gas_meter,
+                        data_store,
+                        addr,
+                        &ty,
+                    )?;
+                }
+                Instruction::MoveTo(ty) => {
+                    let resource = interpreter.operand_stack.pop()?;
+                    let signer_reference = interpreter.operand_stack.pop_as::<StructRef>()?;
+                    let addr = signer_reference
+                        .borrow_field(0)?
+                        .value_as::<Reference>()?
+                        .read_ref()?
+                        .value_as::<AccountAddress>()?;
+                    // REVIEW: Can we simplify Interpreter::move_to?
+                    interpreter.move_to(
+                        false,
+                        resolver.loader(),
+                        gas_meter,
+                        data_store,
+                        addr,
+                        ty,
+                        resource,
+                    )?;
+                }
+                Instruction::MoveToGeneric(si_idx) => {
+                    let resource = interpreter.operand_stack.pop()?;
+                    let signer_reference = interpreter.operand_stack.pop_as::<StructRef>()?;
+                    let addr = signer_reference
+                        .borrow_field(0)?
+                        .value_as::<Reference>()?
+                        .read_ref()?
+                        .value_as::<AccountAddress>()?;
+                    let ty = resolver.instantiate_generic_type(*si_idx, self.ty_args())?;
+                    interpreter.move_to(
+                        true,
+                        resolver.loader(),
+                        gas_meter,
+                        data_store,
+                        addr,
+                        &ty,
+                        resource,
+                    )?;
+                }
+                Instruction::FreezeRef => {
+                    gas_meter.charge_simple_instr(S::FreezeRef)?;
+                    // FreezeRef should just be a null op as we don't distinguish between mut
+                    // and immut ref at runtime.
+                }
+                Instruction::Not => {
+                    gas_meter.charge_simple_instr(S::Not)?;
+                    let value = !interpreter.operand_stack.pop_as::<bool>()?;
+                    interpreter.operand_stack.push(Value::bool(value))?;
+                }
+                Instruction::Nop => {
+                    gas_meter.charge_simple_instr(S::Nop)?;
+                }
+                Instruction::VecPack(ty, num) => {
+                    let ty = &self.instantiate(ty, resolver)?;
+                    gas_meter.charge_vec_pack(
+                        make_ty!(ty),
+                        interpreter.operand_stack.last_n(*num as usize)?,
+                    )?;
+                    if let Some(max_len) = resolver.loader().vm_config().max_vector_length {
+                        if *num > max_len {
+                            return Err(PartialVMError::new(
+                                StatusCode::VM_MAX_VECTOR_LENGTH_REACHED,
+                            ));
+                        }
                     }
-                    Bytecode::VecUnpack(si, num) => {
-                        let vec_val = interpreter.operand_stack.pop_as::<Vector>()?;
-                        let ty = &resolver.instantiate_single_type(*si, self.ty_args())?;
-                        gas_meter.charge_vec_unpack(
-                            make_ty!(ty),
-                            NumArgs::new(*num),
-                            vec_val.elem_views(),
-                        )?;
-                        let elements = vec_val.unpack(ty, *num)?;
-                        for value in elements {
-                            interpreter.operand_stack.push(value)?;
+                    interpreter.use_memory(data_store, || vector_memory(ty, *num))?;
+                    let elements = interpreter.operand_stack.popn(*num as u16)?;
+                    let value = Vector::pack(ty, elements)?;
+                    interpreter.operand_stack.push(value)?;
+                }
+                Instruction::VecLen(ty) => {
+                    let vec_ref = interpreter.operand_stack.pop_as::<VectorRef>()?;
+                    let ty = &self.instantiate(ty, resolver)?;
+                    gas_meter.charge_vec_len(make_ty!(ty))?;
+                    let value = vec_ref.len(ty)?;
+                    interpreter.operand_stack.push(value)?;
+                }
+                Instruction::VecImmBorrow(ty) => {
+                    let idx = interpreter.operand_stack.pop_as::<u64>()? as usize;
+                    let vec_ref = interpreter.operand_stack.pop_as::<VectorRef>()?;
+                    let ty = &self.instantiate(ty, resolver)?;
+                    let res = vec_ref.borrow_elem(idx, ty);
+                    gas_meter.charge_vec_borrow(false, make_ty!(ty), res.is_ok())?;
+                    interpreter.operand_stack.push(res?)?;
+                }
+                Instruction::VecMutBorrow(ty) => {
+                    let idx = interpreter.operand_stack.pop_as::<u64>()? as usize;
+                    let vec_ref = interpreter.operand_stack.pop_as::<VectorRef>()?;
+                    let ty = &self.instantiate(ty, resolver)?;
+                    let res = vec_ref.borrow_elem(idx, ty);
+                    gas_meter.charge_vec_borrow(true, make_ty!(ty), res.is_ok())?;
+                    interpreter.operand_stack.push(res?)?;
+                }
+                Instruction::VecPushBack(ty) => {
+                    let elem = interpreter.operand_stack.pop()?;
+                    let vec_ref = interpreter.operand_stack.pop_as::<VectorRef>()?;
+                    let ty = &self.instantiate(ty, resolver)?;
+                    gas_meter.charge_vec_push_back(make_ty!(ty), &elem)?;
+                    if let Some(max_len) = resolver.loader().vm_config().max_vector_length {
+                        if vec_ref.len(ty)?.value_as::<u64>()? >= max_len {
+                            return Err(PartialVMError::new(
+                                StatusCode::VM_MAX_VECTOR_LENGTH_REACHED,
+                            ));
                         }
                     }
-                    Bytecode::VecSwap(si) => {
-                        let idx2 = interpreter.operand_stack.pop_as::<u64>()? as usize;
-                        let idx1 = interpreter.operand_stack.pop_as::<u64>()? as usize;
-                        let vec_ref = interpreter.operand_stack.pop_as::<VectorRef>()?;
-                        let ty = &resolver.instantiate_single_type(*si, self.ty_args())?;
-                        gas_meter.charge_vec_swap(make_ty!(ty))?;
-                        vec_ref.swap(idx1, idx2, ty)?;
+                    if let Some(size) = element_memory(ty) {
+                        interpreter.use_memory(data_store, || size)?;
                     }
+                    vec_ref.push_back(elem, ty)?;
                 }
-                if interpreter.paranoid_type_checks {
-                    Self::post_execution_type_stack_transition(
-                        &self.local_tys,
-                        &self.ty_args,
-                        resolver,
-                        interpreter,
-                        instruction,
+                Instruction::VecPopBack(ty) => {
+                    let vec_ref = interpreter.operand_stack.pop_as::<VectorRef>()?;
+                    let ty = &self.instantiate(ty, resolver)?;
+                    let res = vec_ref.pop(ty);
+                    gas_meter.charge_vec_pop_back(make_ty!(ty), res.as_ref().ok())?;
+                    let value = res?;
+                    if let Some(size) = element_memory(ty) {
+                        interpreter.release_memory(data_store, || size);
+                    }
+                    interpreter.operand_stack.push(value)?;
+                }
+                Instruction::VecUnpack(ty, num) => {
+                    let vec_val = interpreter.operand_stack.pop_as::<Vector>()?;
+                    let ty = &self.instantiate(ty, resolver)?;
+                    gas_meter.charge_vec_unpack(
+                        make_ty!(ty),
+                        NumArgs::new(*num),
+                        vec_val.elem_views(),
                     )?;
-
-                    interpreter.operand_stack.check_balance()?;
+                    let elements = vec_val.unpack(ty, *num)?;
+                    interpreter.release_memory(data_store, || vector_memory(ty, *num));
+                    for value in elements {
+                        interpreter.operand_stack.push(value)?;
+                    }
                 }
-
-                // invariant: advance to pc +1 is iff instruction at pc executed without aborting
-                self.pc += 1;
-            }
-            // ok we are out, it's a branch, check the pc for good luck
-            // TODO: re-work the logic here. Tests should have a more
-            // natural way to plug in
-            if self.pc as usize >= code.len() {
-                if cfg!(test) {
-                    // In order to test the behavior of an instruction stream, hitting end of the
-                    // code should report no error so that we can check the
-                    // locals.
-                    return Ok(ExitCode::Return);
-                } else {
-                    return Err(PartialVMError::new(StatusCode::PC_OVERFLOW));
+                Instruction::VecSwap(ty) => {
+                    let idx2 = interpreter.operand_stack.pop_as::<u64>()? as usize;
+                    let idx1 = interpreter.operand_stack.pop_as::<u64>()? as usize;
+                    let vec_ref = interpreter.operand_stack.pop_as::<VectorRef>()?;
+                    let ty = &self.instantiate(ty, resolver)?;
+                    gas_meter.charge_vec_swap(make_ty!(ty))?;
+                    vec_ref.swap(idx1, idx2, ty)?;
                 }
             }
+            if interpreter.paranoid_type_checks {
+                Self::post_execution_type_stack_transition(
+                    &self.local_tys,
+                    &self.ty_args,
+                    resolver,
+                    interpreter,
+                    &code[self.pc as usize],
+                )?;
+
+                interpreter.operand_stack.check_balance()?;
+            }
+
+            // invariant: advance to pc +1 is iff instruction at pc executed without aborting
+            self.pc += 1;
+        }
+    }
+
+    // Instantiates the type of a vector instruction with the type arguments of the frame.
+    fn instantiate<'t>(&self, ty: &'t Type, resolver: &Resolver) -> PartialVMResult<Cow<'t, Type>> {
+        if self.ty_args.is_empty() {
+            Ok(Cow::Borrowed(ty))
+        } else {
+            Ok(Cow::Owned(resolver.subst(ty, &self.ty_args)?))
         }
     }
 
@@ -2260,7 +2531,7 @@ impl Frame {
     }
 
     fn resolver<'a>(&self, loader: &'a Loader) -> Resolver<'a> {
-        self.function.get_resolver(loader)
+        Resolver::for_binary(loader, self.binary.clone())
     }
 
     fn location(&self) -> Location {
@@ -11,9 +11,11 @@
 //! soon.
 
 pub mod data_cache;
+mod instruction;
 mod interpreter;
 mod loader;
 pub mod logging;
+mod memory;
 pub mod move_vm;
 pub mod native_extensions;
 pub mod native_functions;
@@ -22,6 +24,7 @@ pub mod session;
 #[macro_use]
 mod tracing;
 pub mod config;
+pub mod verified_module_cache;
 
 // Only include debugging functionality in debug builds
 #[cfg(any(debug_assertions, feature = "debugging"))]
@@ -4,9 +4,11 @@
 
 use crate::{
     config::VMConfig,
+    instruction::{Instruction, LocalRef},
     logging::expect_no_verification_errors,
     native_functions::{NativeFunction, NativeFunctions, UnboxedNativeFunction},
     session::LoadedFunctionInstantiation,
+    verified_module_cache::VerifiedModuleKey,
 };
 use move_binary_format::{
     access::{ModuleAccess, ScriptAccess},
@@ -15,7 +17,7 @@ use move_binary_format::{
     file_format::{
         AbilitySet, Bytecode, CompiledModule, CompiledScript, Constant, ConstantPoolIndex,
         FieldHandleIndex, FieldInstantiationIndex, FunctionDefinition, FunctionDefinitionIndex,
-        FunctionHandleIndex, FunctionInstantiationIndex, Signature, SignatureIndex, SignatureToken,
+        FunctionInstantiationIndex, Signature, SignatureIndex, SignatureToken,
         StructDefInstantiationIndex, StructDefinition, StructDefinitionIndex,
         StructFieldInformation, TableIndex, Visibility,
     },
@@ -31,15 +33,19 @@ use move_core_types::{
 };
 use move_vm_types::{
     data_store::DataStore,
+    gas::SimpleInstruction,
     loaded_data::runtime_types::{CachedStructIndex, StructType, Type},
 };
 use parking_lot::RwLock;
 use sha3::{Digest, Sha3_256};
 use std::{
     collections::{BTreeMap, BTreeSet, HashMap},
-    fmt::Debug,
+    fmt::{self, Debug},
     hash::Hash,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
 };
 use tracing::error;
 
@@ -76,6 +82,20 @@ where
     fn get(&self, key: &K) -> Option<&Arc<V>> {
         self.id_map.get(key).and_then(|idx| self.binaries.get(*idx))
     }
+
+    // Removes all entries for which `keep` returns false. Binaries still referenced elsewhere
+    // stay alive through their `Arc`.
+    fn retain(&mut self, mut keep: impl FnMut(&K, &V) -> bool) {
+        let binaries = std::mem::take(&mut self.binaries);
+        let id_map = std::mem::take(&mut self.id_map);
+        for (key, idx) in id_map {
+            let binary = &binaries[idx];
+            if keep(&key, binary) {
+                self.binaries.push(Arc::clone(binary));
+                self.id_map.insert(key, self.binaries.len() - 1);
+            }
+        }
+    }
 }
 
 // A script cache is a map from the hash value of a script and the `Script` itself.
@@ -119,16 +139,36 @@ impl ScriptCache {
             }
         }
     }
+
+    // Removes all scripts which depend on one of the given modules.
+    fn remove_dependents(&mut self, module_ids: &BTreeSet<ModuleId>) {
+        self.scripts.retain(|_, script| {
+            !script
+                .script
+                .immediate_dependencies()
+                .iter()
+                .any(|dep| module_ids.contains(dep))
+        })
+    }
 }
 
 // A ModuleCache is the core structure in the Loader.
 // It holds all Modules, Types and Functions loaded.
 // Types and Functions are pushed globally to the ModuleCache.
 // All accesses to the ModuleCache are under lock (exclusive).
+//
+// Entries are versioned by their position in the global vectors: when a module is evicted and
+// loaded again, e.g. after an upgrade, the new version gets fresh struct and function indices.
+// The vectors are not truncated on eviction, so the indices of an evicted version stay valid for
+// the modules, types and values still referring to them. Instead, the loader rebuilds the whole
+// cache once the evicted entries outnumber the live ones (see `needs_rebuild`), which bounds the
+// vectors to about twice the entries of the cached modules.
 pub struct ModuleCache {
     modules: BinaryCache<ModuleId, Module>,
     structs: Vec<Arc<StructType>>,
     functions: Vec<Arc<Function>>,
+    // number of entries in `structs` and `functions` which belong to evicted modules
+    evicted_entries: usize,
 }
 
 impl ModuleCache {
@@ -137,6 +177,7 @@ impl ModuleCache {
             modules: BinaryCache::new(),
             structs: vec![],
             functions: vec![],
+            evicted_entries: 0,
         }
     }
 
@@ -170,6 +211,7 @@ impl ModuleCache {
         natives: &NativeFunctions,
         id: ModuleId,
         module: CompiledModule,
+        fuse: bool,
     ) -> VMResult<Arc<Module>> {
         if let Some(cached) = self.module_at(&id) {
             return Ok(cached);
@@ -178,7 +220,7 @@ impl ModuleCache {
         // we need this operation to be transactional, if an error occurs we must
         // leave a clean state
         self.add_module(natives, &module)?;
-        match Module::new(module, self) {
+        match Module::new(module, self, fuse) {
             Ok(module) => Ok(Arc::clone(self.modules.insert(id, module))),
             Err((err, module)) => {
                 // remove all structs and functions that have been pushed
@@ -324,8 +366,15 @@ impl ModuleCache {
             tok,
             &|struct_name, module_id| {
                 if module_id == &self_id {
-                    // module has not been published yet, loop through the types
-                    for (idx, struct_type) in self.structs.iter().enumerate().rev() {
+                    // module has not been published yet, loop through the types, which are the
+                    // last ones pushed. Older versions of the module may precede them.
+                    for (idx, struct_type) in self
+                        .structs
+                        .iter()
+                        .enumerate()
+                        .rev()
+                        .take(module.struct_defs().len())
+                    {
                         if &struct_type.module != module_id {
                             break;
                         }
@@ -416,6 +465,61 @@ impl ModuleCache {
         self.modules.id_map.contains_key(module_id)
     }
 
+    // Evicts the given modules together with all cached modules transitively depending on them,
+    // and returns the evicted modules and the indices of their structs. Unrelated modules stay
+    // cached.
+    fn evict(
+        &mut self,
+        module_ids: &BTreeSet<ModuleId>,
+    ) -> (BTreeSet<ModuleId>, Vec<CachedStructIndex>) {
+        let mut dependents: BTreeMap<ModuleId, Vec<ModuleId>> = BTreeMap::new();
+        for id in self.modules.id_map.keys() {
+            let module = self.module_at(id).expect("module in id_map must be cached");
+            for dep in module.module().immediate_dependencies() {
+                dependents.entry(dep).or_default().push(id.clone());
+            }
+        }
+
+        let mut evicted = BTreeSet::new();
+        let mut todo: Vec<ModuleId> = module_ids
+            .iter()
+            .filter(|id| self.has_module(id))
+            .cloned()
+            .collect();
+        while let Some(id) = todo.pop() {
+            if evicted.insert(id.clone()) {
+                todo.extend(dependents.remove(&id).unwrap_or_default());
+            }
+        }
+
+        let mut struct_idxs = vec![];
+        let mut evicted_entries = 0;
+        self.modules.retain(|id, module| {
+            if evicted.contains(id) {
+                struct_idxs.extend(module.struct_map.values().copied());
+                evicted_entries += module.struct_map.len() + module.function_map.len();
+                false
+            } else {
+                true
+            }
+        });
+        self.evicted_entries += evicted_entries;
+        (evicted, struct_idxs)
+    }
+
+    // Whether the entries of evicted modules outnumber the live ones, in which case the cache
+    // should be rebuilt to reclaim them.
+    fn needs_rebuild(&self) -> bool {
+        let entries = self.structs.len() + self.functions.len();
+        self.evicted_entries > entries - self.evicted_entries
+    }
+
+    // The number of entries in the global vectors of types and functions.
+    #[cfg(test)]
+    pub(crate) fn num_entries(&self) -> usize {
+        self.structs.len() + self.functions.len()
+    }
+
     // Given a ModuleId::struct_name, retrieve the `StructType` and the index associated.
     // Return and error if the type has not been loaded
     fn resolve_struct_by_name(
@@ -465,6 +569,15 @@ impl ModuleCache {
 // Loader
 //
 
+// The parts of the loader cache which must be flushed, see `Loader::invalidated`.
+#[derive(Default)]
+struct Invalidation {
+    // the whole cache is invalid
+    all: bool,
+    // the cached versions of these modules, and of everything depending on them, are invalid
+    modules: BTreeSet<ModuleId>,
+}
+
 // A Loader is responsible to load scripts and modules and holds the cache of all loaded
 // entities. Each cache is protected by a `RwLock`. Operation in the Loader must be thread safe
 // (operating on values on the stack) and when cache needs updating the mutex must be taken.
@@ -475,38 +588,38 @@ pub(crate) struct Loader {
     type_cache: RwLock<TypeCache>,
     natives: NativeFunctions,
 
-    // The below field supports a hack to workaround well-known issues with the
-    // loader cache. This cache is not designed to support module upgrade or deletion.
-    // This leads to situations where the cache does not reflect the state of storage:
+    // The below field tracks which parts of the loader cache do not reflect the state of storage
+    // anymore:
     //
     // 1. On module upgrade, the upgraded module is in storage, but the old one still in the cache.
     // 2. On an abandoned code publishing transaction, the cache may contain a module which was
     //    never committed to storage by the adapter.
     //
-    // The solution is to add a flag to Loader marking it as 'invalidated'. For scenario (1),
-    // the VM sets the flag itself. For scenario (2), a public API allows the adapter to set
-    // the flag.
+    // For scenario (1), the VM marks the upgraded module as invalid itself. For scenario (2), a
+    // public API allows the adapter to mark the modules published by the transaction as invalid,
+    // or the whole cache if it does not know them.
     //
     // If the cache is invalidated, it can (and must) still be used until there are no more
     // sessions alive which are derived from a VM with this loader. This is because there are
     // internal data structures derived from the loader which can become inconsistent. Therefore
-    // the adapter must explicitly call a function to flush the invalidated loader.
-    //
-    // This code (the loader) needs a complete refactoring. The new loader should
+    // the adapter must explicitly call a function to flush the invalidated loader. Flushing
+    // evicts the invalid modules and everything depending on them, while unrelated modules stay
+    // cached; see `ModuleCache` for how evicted versions stay valid for remaining references.
     //
-    // - support upgrade and deletion of modules, while still preserving max cache lookup
-    //   performance. This is essential for a cache like this in a multi-tenant execution
-    //   environment.
-    // - should delegate lifetime ownership to the adapter. Code loading (including verification)
-    //   is a major execution bottleneck. We should be able to reuse a cache for the lifetime of
-    //   the adapter/node, not just a VM or even session (as effectively today).
-    invalidated: RwLock<bool>,
+    // This code (the loader) still needs refactoring to delegate lifetime ownership to the
+    // adapter. Code loading (including verification) is a major execution bottleneck. We should
+    // be able to reuse a cache for the lifetime of the adapter/node, not just a VM.
+    invalidated: RwLock<Invalidation>,
 
     // Collects the cache hits on module loads. This information can be read and reset by
     // an adapter to reason about read/write conflicts of code publishing transactions and
     // other transactions.
     module_cache_hits: RwLock<BTreeSet<ModuleId>>,
 
+    // Counts the modules found in the verified module cache of the config, to pick those which
+    // are re-verified.
+    verified_module_cache_hits: AtomicU64,
+
     vm_config: VMConfig,
 }
 
@@ -517,8 +630,9 @@ impl Loader {
             module_cache: RwLock::new(ModuleCache::new()),
             type_cache: RwLock::new(TypeCache::new()),
             natives,
-            invalidated: RwLock::new(false),
+            invalidated: RwLock::new(Invalidation::default()),
             module_cache_hits: RwLock::new(BTreeSet::new()),
+            verified_module_cache_hits: AtomicU64::new(0),
             vm_config,
         }
     }
@@ -527,6 +641,12 @@ impl Loader {
         &self.vm_config
     }
 
+    // Whether to fuse instruction sequences when decoding code. Paranoid type checks are defined
+    // per bytecode, so code is not fused when they are enabled.
+    fn fuse(&self) -> bool {
+        !self.vm_config.paranoid_type_checks
+    }
+
     /// Gets and clears module cache hits. A cache hit may also be caused indirectly by
     /// loading a function or a type. This not only returns the direct hit, but also
     /// indirect ones, that is all dependencies.
@@ -543,38 +663,67 @@ impl Loader {
         if !visited.insert(id.clone()) {
             return;
         }
-        let deps = self
-            .module_cache
-            .read()
-            .modules
-            .get(id)
-            .unwrap()
-            .module
-            .immediate_dependencies();
+        // The module may have been evicted since it was hit, in which case its dependencies
+        // have been reported by the hits on them.
+        let deps = match self.module_cache.read().modules.get(id) {
+            Some(module) => module.module.immediate_dependencies(),
+            None => return,
+        };
         for dep in deps {
             self.transitive_dep_closure(&dep, visited)
         }
     }
 
-    /// Flush this cache if it is marked as invalidated.
+    /// Flush this cache if it is marked as invalidated. If only some modules are invalidated,
+    /// only those and the modules and scripts depending on them are evicted, unless the entries
+    /// of all modules evicted so far outnumber the live ones, in which case the whole cache is
+    /// flushed to reclaim them.
     pub(crate) fn flush_if_invalidated(&self) {
         let mut invalidated = self.invalidated.write();
-        if *invalidated {
+        let mut flush_all = invalidated.all;
+        if !flush_all && !invalidated.modules.is_empty() {
+            let (evicted, struct_idxs, needs_rebuild) = {
+                let mut module_cache = self.module_cache.write();
+                let (evicted, struct_idxs) = module_cache.evict(&invalidated.modules);
+                (evicted, struct_idxs, module_cache.needs_rebuild())
+            };
+            flush_all = needs_rebuild;
+            if !flush_all {
+                self.scripts.write().remove_dependents(&evicted);
+                let mut type_cache = self.type_cache.write();
+                for idx in struct_idxs {
+                    type_cache.structs.remove(&idx);
+                }
+            }
+        }
+        if flush_all {
             *self.scripts.write() = ScriptCache::new();
             *self.module_cache.write() = ModuleCache::new();
             *self.type_cache.write() = TypeCache::new();
-            *invalidated = false;
         }
+        *invalidated = Invalidation::default();
+    }
+
+    #[cfg(test)]
+    pub(crate) fn module_cache(&self) -> &RwLock<ModuleCache> {
+        &self.module_cache
     }
 
     /// Mark this cache as invalidated.
     pub(crate) fn mark_as_invalid(&self) {
-        *self.invalidated.write() = true;
+        self.invalidated.write().all = true;
     }
 
-    /// Check whether this cache is invalidated.
+    /// Mark the given modules, and with them all modules and scripts depending on them, as
+    /// invalidated.
+    pub(crate) fn mark_modules_as_invalid(&self, module_ids: impl IntoIterator<Item = ModuleId>) {
+        self.invalidated.write().modules.extend(module_ids);
+    }
+
+    /// Check whether this cache is invalidated, completely or for some modules.
     pub(crate) fn is_invalidated(&self) -> bool {
-        *self.invalidated.read()
+        let invalidated = self.invalidated.read();
+        invalidated.all || !invalidated.modules.is_empty()
     }
 
     /// Copies metadata out of a modules bytecode if available.
@@ -615,7 +764,12 @@ impl Loader {
             Some(cached) => cached,
             None => {
                 let ver_script = self.deserialize_and_verify_script(script_blob, data_store)?;
-                let script = Script::new(ver_script, &hash_value, &self.module_cache.read())?;
+                let script = Script::new(
+                    ver_script,
+                    &hash_value,
+                    &self.module_cache.read(),
+                    self.fuse(),
+                )?;
                 scripts.insert(hash_value, script)
             }
         };
@@ -809,8 +963,8 @@ impl Loader {
     ) -> VMResult<()> {
         // Performs all verification steps to load the module without loading it, i.e., the new
         // module will NOT show up in `module_cache`. In the module republishing case, it means
-        // that the old module is still in the `module_cache`, unless a new Loader is created,
-        // which means that a new MoveVM instance needs to be created.
+        // that the old module is still in the `module_cache`, until the adapter flushes the
+        // invalidated modules from the cache.
         move_bytecode_verifier::verify_module_with_config(&self.vm_config.verifier, module)?;
         self.check_natives(module)?;
 
@@ -1059,13 +1213,49 @@ impl Loader {
         }
 
         // bytecode verifier checks that can be performed with the module itself
-        move_bytecode_verifier::verify_module_with_config(&self.vm_config.verifier, &module)
+        self.verify_module_with_cache(&bytes, &module)
             .map_err(expect_no_verification_errors)?;
         self.check_natives(&module)
             .map_err(expect_no_verification_errors)?;
         Ok(module)
     }
 
+    // Run the bytecode verifier on a module loaded from the data store, unless the verified
+    // module cache says its bytes passed it under the same verifier config. Modules found in the
+    // cache are sampled for re-verification if the config asks for it.
+    fn verify_module_with_cache(&self, bytes: &[u8], module: &CompiledModule) -> VMResult<()> {
+        let cache = match &self.vm_config.verified_module_cache {
+            Some(cache) => cache,
+            None => {
+                return move_bytecode_verifier::verify_module_with_config(
+                    &self.vm_config.verifier,
+                    module,
+                )
+            }
+        };
+        let key = VerifiedModuleKey::new(bytes, &self.vm_config.verifier);
+        if cache.contains(&key) {
+            let hits = self
+                .verified_module_cache_hits
+                .fetch_add(1, Ordering::Relaxed)
+                + 1;
+            match self.vm_config.reverify_cached_modules_one_in {
+                Some(one_in) if one_in > 0 && hits % one_in == 0 => (),
+                _ => return Ok(()),
+            }
+        }
+        if let Err(err) =
+            move_bytecode_verifier::verify_module_with_config(&self.vm_config.verifier, module)
+        {
+            // Do not trust the cache for this module again, e.g. if the entry was sampled for
+            // re-verification.
+            cache.remove(&key);
+            return Err(err);
+        }
+        cache.insert(key);
+        Ok(())
+    }
+
     // Everything in `load_and_verify_module` and also recursively load and verify all the
     // dependencies of the target module.
     fn load_and_verify_module_and_dependencies(
@@ -1103,7 +1293,7 @@ impl Loader {
 
         // if linking goes well, insert the module to the code cache
         let mut locked_cache = self.module_cache.write();
-        let module_ref = locked_cache.insert(&self.natives, id.clone(), module)?;
+        let module_ref = locked_cache.insert(&self.natives, id.clone(), module, self.fuse())?;
         drop(locked_cache); // explicit unlock
 
         Ok(module_ref)
@@ -1276,14 +1466,17 @@ impl Loader {
     fn subst(&self, ty: &Type, ty_args: &[Type]) -> PartialVMResult<Type> {
         // Before instantiating the type, count the # of nodes of all type arguments plus
         // existing type instantiation.
-        // If that number is larger than MAX_TYPE_INSTANTIATION_NODES, refuse to construct this type.
+        // If that number is larger than `max_type_instantiation_nodes`, refuse to construct this
+        // type.
         // This prevents constructing larger and lager types via struct instantiation.
         if let Type::StructInstantiation(_, struct_inst) = ty {
             let mut sum_nodes: usize = 1;
             for ty in ty_args.iter().chain(struct_inst.iter()) {
                 sum_nodes = sum_nodes.saturating_add(self.count_type_nodes(ty));
-                if sum_nodes > MAX_TYPE_INSTANTIATION_NODES {
-                    return Err(PartialVMError::new(StatusCode::TOO_MANY_TYPE_NODES));
+                if sum_nodes > self.vm_config.max_type_instantiation_nodes {
+                    return Err(PartialVMError::new(
+                        StatusCode::VM_MAX_TYPE_INSTANTIATION_NODES_REACHED,
+                    ));
                 }
             }
         }
@@ -1316,10 +1509,6 @@ impl Loader {
     // Internal helpers
     //
 
-    fn function_at(&self, idx: usize) -> Arc<Function> {
-        self.module_cache.read().function_at(idx)
-    }
-
     fn get_module(&self, idx: &ModuleId) -> Arc<Module> {
         Arc::clone(
             self.module_cache
@@ -1394,7 +1583,8 @@ impl Loader {
 //
 
 // A simple wrapper for a `Module` or a `Script` in the `Resolver`
-enum BinaryType {
+#[derive(Clone)]
+pub(crate) enum BinaryType {
     Module(Arc<Module>),
     Script(Arc<Script>),
 }
@@ -1418,6 +1608,26 @@ impl<'a> Resolver<'a> {
         Self { loader, binary }
     }
 
+    pub(crate) fn for_binary(loader: &'a Loader, binary: BinaryType) -> Self {
+        Self { loader, binary }
+    }
+
+    // The module or script this resolver resolves against, for frames to keep it across calls.
+    pub(crate) fn binary(&self) -> &BinaryType {
+        &self.binary
+    }
+
+    //
+    // Code resolution
+    //
+
+    pub(crate) fn instructions(&self, idx: FunctionDefinitionIndex) -> &[Instruction] {
+        match &self.binary {
+            BinaryType::Module(module) => &module.code[idx.0 as usize],
+            BinaryType::Script(script) => &script.code,
+        }
+    }
+
     //
     // Constant resolution
     //
@@ -1433,25 +1643,6 @@ impl<'a> Resolver<'a> {
     // Function resolution
     //
 
-    pub(crate) fn function_from_handle(&self, idx: FunctionHandleIndex) -> Arc<Function> {
-        let idx = match &self.binary {
-            BinaryType::Module(module) => module.function_at(idx.0),
-            BinaryType::Script(script) => script.function_at(idx.0),
-        };
-        self.loader.function_at(idx)
-    }
-
-    pub(crate) fn function_from_instantiation(
-        &self,
-        idx: FunctionInstantiationIndex,
-    ) -> Arc<Function> {
-        let func_inst = match &self.binary {
-            BinaryType::Module(module) => module.function_instantiation_at(idx.0),
-            BinaryType::Script(script) => script.function_instantiation_at(idx.0),
-        };
-        self.loader.function_at(func_inst.handle)
-    }
-
     pub(crate) fn instantiate_generic_function(
         &self,
         idx: FunctionInstantiationIndex,
@@ -1466,12 +1657,14 @@ impl<'a> Resolver<'a> {
             instantiation.push(self.subst(ty, type_params)?);
         }
         // Check if the function instantiation over all generics is larger
-        // than MAX_TYPE_INSTANTIATION_NODES.
+        // than `max_type_instantiation_nodes`.
         let mut sum_nodes: usize = 1;
         for ty in type_params.iter().chain(instantiation.iter()) {
             sum_nodes = sum_nodes.saturating_add(self.loader.count_type_nodes(ty));
-            if sum_nodes > MAX_TYPE_INSTANTIATION_NODES {
-                return Err(PartialVMError::new(StatusCode::TOO_MANY_TYPE_NODES));
+            if sum_nodes > self.loader.vm_config.max_type_instantiation_nodes {
+                return Err(PartialVMError::new(
+                    StatusCode::VM_MAX_TYPE_INSTANTIATION_NODES_REACHED,
+                ));
             }
         }
         Ok(instantiation)
@@ -1510,13 +1703,16 @@ impl<'a> Resolver<'a> {
 
         // Before instantiating the type, count the # of nodes of all type arguments plus
         // existing type instantiation.
-        // If that number is larger than MAX_TYPE_INSTANTIATION_NODES, refuse to construct this type.
+        // If that number is larger than `max_type_instantiation_nodes`, refuse to construct this
+        // type.
         // This prevents constructing larger and lager types via struct instantiation.
         let mut sum_nodes: usize = 1;
         for ty in ty_args.iter().chain(struct_inst.instantiation.iter()) {
             sum_nodes = sum_nodes.saturating_add(self.loader.count_type_nodes(ty));
-            if sum_nodes > MAX_TYPE_INSTANTIATION_NODES {
-                return Err(PartialVMError::new(StatusCode::TOO_MANY_TYPE_NODES));
+            if sum_nodes > self.loader.vm_config.max_type_instantiation_nodes {
+                return Err(PartialVMError::new(
+                    StatusCode::VM_MAX_TYPE_INSTANTIATION_NODES_REACHED,
+                ));
             }
         }
 
@@ -1642,20 +1838,6 @@ impl<'a> Resolver<'a> {
     // Fields resolution
     //
 
-    pub(crate) fn field_offset(&self, idx: FieldHandleIndex) -> usize {
-        match &self.binary {
-            BinaryType::Module(module) => module.field_offset(idx),
-            BinaryType::Script(_) => unreachable!("Scripts cannot have field instructions"),
-        }
-    }
-
-    pub(crate) fn field_instantiation_offset(&self, idx: FieldInstantiationIndex) -> usize {
-        match &self.binary {
-            BinaryType::Module(module) => module.field_instantiation_offset(idx),
-            BinaryType::Script(_) => unreachable!("Scripts cannot have field instructions"),
-        }
-    }
-
     pub(crate) fn field_count(&self, idx: StructDefinitionIndex) -> u16 {
         match &self.binary {
             BinaryType::Module(module) => module.field_count(idx.0),
@@ -1718,7 +1900,6 @@ impl<'a> Resolver<'a> {
 // so that any data needed for execution is immediately available
 #[derive(Debug)]
 pub(crate) struct Module {
-    #[allow(dead_code)]
     id: ModuleId,
     // primitive pools
     module: Arc<CompiledModule>,
@@ -1737,13 +1918,8 @@ pub(crate) struct Module {
     // materialized instantiations, whether partial or not
     struct_instantiations: Vec<StructInstantiation>,
 
-    // functions as indexes into the Loader function list
-    // That is effectively an indirection over the ref table:
-    // the instruction carries an index into this table which contains the index into the
-    // glabal table of functions. No instantiation of generic functions is saved into
-    // the global table.
-    function_refs: Vec<usize>,
-    // materialized instantiations, whether partial or not
+    // materialized function instantiations, whether partial or not. Calls are resolved to
+    // their functions in the decoded code.
     function_instantiations: Vec<FunctionInstantiation>,
 
     // fields as a pair of index, first to the type, second to the field position in that type
@@ -1763,12 +1939,17 @@ pub(crate) struct Module {
     // `VecMutBorrow(SignatureIndex)`, the `SignatureIndex` maps to a single `SignatureToken`, and
     // hence, a single type.
     single_signature_token_map: BTreeMap<SignatureIndex, Type>,
+
+    // the decoded code of the functions, by function definition index. Empty for native
+    // functions.
+    code: Vec<Vec<Instruction>>,
 }
 
 impl Module {
     fn new(
         module: CompiledModule,
         cache: &ModuleCache,
+        fuse: bool,
     ) -> Result<Self, (PartialVMError, CompiledModule)> {
         let id = module.self_id();
 
@@ -1940,20 +2121,40 @@ impl Module {
         };
 
         match create() {
-            Ok(_) => Ok(Self {
-                id,
-                module: Arc::new(module),
-                struct_refs,
-                structs,
-                struct_instantiations,
-                function_refs,
-                function_instantiations,
-                field_handles,
-                field_instantiations,
-                function_map,
-                struct_map,
-                single_signature_token_map,
-            }),
+            Ok(_) => {
+                let tables = CodeTables {
+                    cache,
+                    function_refs: &function_refs,
+                    function_instantiations: &function_instantiations,
+                    structs: &structs,
+                    struct_instantiations: &struct_instantiations,
+                    field_handles: &field_handles,
+                    field_instantiations: &field_instantiations,
+                    single_signature_token_map: &single_signature_token_map,
+                };
+                let code = module
+                    .function_defs()
+                    .iter()
+                    .map(|func_def| match &func_def.code {
+                        Some(code_unit) => tables.decode(&code_unit.code, fuse),
+                        None => vec![],
+                    })
+                    .collect();
+                Ok(Self {
+                    id,
+                    module: Arc::new(module),
+                    struct_refs,
+                    structs,
+                    struct_instantiations,
+                    function_instantiations,
+                    field_handles,
+                    field_instantiations,
+                    function_map,
+                    struct_map,
+                    single_signature_token_map,
+                    code,
+                })
+            }
             Err(err) => Err((err, module)),
         }
     }
@@ -1966,10 +2167,6 @@ impl Module {
         &self.struct_instantiations[idx as usize]
     }
 
-    fn function_at(&self, idx: u16) -> usize {
-        self.function_refs[idx as usize]
-    }
-
     fn function_instantiation_at(&self, idx: u16) -> &FunctionInstantiation {
         &self.function_instantiations[idx as usize]
     }
@@ -1990,14 +2187,6 @@ impl Module {
         self.module.clone()
     }
 
-    fn field_offset(&self, idx: FieldHandleIndex) -> usize {
-        self.field_handles[idx.0 as usize].offset
-    }
-
-    fn field_instantiation_offset(&self, idx: FieldInstantiationIndex) -> usize {
-        self.field_instantiations[idx.0 as usize].offset
-    }
-
     fn single_type_at(&self, idx: SignatureIndex) -> &Type {
         self.single_signature_token_map.get(&idx).unwrap()
     }
@@ -2008,7 +2197,7 @@ impl Module {
 // When code executes, indexes in instructions are resolved against runtime structures
 // (rather then "compiled") to make available data needed for execution
 // #[derive(Debug)]
-struct Script {
+pub(crate) struct Script {
     // primitive pools
     script: CompiledScript,
 
@@ -2017,9 +2206,7 @@ struct Script {
     #[allow(dead_code)]
     struct_refs: Vec<CachedStructIndex>,
 
-    // functions as indexes into the Loader function list
-    function_refs: Vec<usize>,
-    // materialized instantiations, whether partial or not
+    // materialized function instantiations, whether partial or not
     function_instantiations: Vec<FunctionInstantiation>,
 
     // entry point
@@ -2033,6 +2220,9 @@ struct Script {
 
     // a map of single-token signature indices to type
     single_signature_token_map: BTreeMap<SignatureIndex, Type>,
+
+    // the decoded code of main
+    code: Vec<Instruction>,
 }
 
 impl Script {
@@ -2040,6 +2230,7 @@ impl Script {
         script: CompiledScript,
         script_hash: &ScriptHash,
         cache: &ModuleCache,
+        fuse: bool,
     ) -> VMResult<Self> {
         let mut struct_refs = vec![];
         for struct_handle in script.struct_handles() {
@@ -2180,15 +2371,29 @@ impl Script {
             }
         }
 
+        // scripts have no struct and field tables, and the verifier rejects scripts with
+        // instructions referring to them
+        let code = CodeTables {
+            cache,
+            function_refs: &function_refs,
+            function_instantiations: &function_instantiations,
+            structs: &[],
+            struct_instantiations: &[],
+            field_handles: &[],
+            field_instantiations: &[],
+            single_signature_token_map: &single_signature_token_map,
+        }
+        .decode(&script.code.code, fuse);
+
         Ok(Self {
             script,
             struct_refs,
-            function_refs,
             function_instantiations,
             main,
             parameter_tys,
             return_tys,
             single_signature_token_map,
+            code,
         })
     }
 
@@ -2196,10 +2401,6 @@ impl Script {
         self.main.clone()
     }
 
-    fn function_at(&self, idx: u16) -> usize {
-        self.function_refs[idx as usize]
-    }
-
     fn function_instantiation_at(&self, idx: u16) -> &FunctionInstantiation {
         &self.function_instantiations[idx as usize]
     }
@@ -2331,6 +2532,17 @@ impl Function {
         }
     }
 
+    // Returns the module or script of the function. The one of `resolver` is reused if the
+    // function belongs to it, which saves a lookup in the loader for calls within a module.
+    pub(crate) fn get_binary(&self, resolver: &Resolver) -> BinaryType {
+        match (&self.scope, &resolver.binary) {
+            (Scope::Module(module_id), BinaryType::Module(module)) if &module.id == module_id => {
+                resolver.binary.clone()
+            }
+            _ => self.get_resolver(resolver.loader).binary,
+        }
+    }
+
     pub(crate) fn local_count(&self) -> usize {
         self.locals.len()
     }
@@ -2405,6 +2617,15 @@ impl Function {
     }
 }
 
+// Functions are shown by name in the decoded code of modules.
+impl Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Function")
+            .field(&self.pretty_string())
+            .finish()
+    }
+}
+
 //
 // Internal structures that are saved at the proper index in the proper tables to access
 // execution information (interpreter).
@@ -2456,6 +2677,167 @@ struct FieldInstantiation {
     instantiation: Vec<Type>,
 }
 
+//
+// Decoding of code into the instructions the interpreter executes
+//
+
+// The tables of a module or script the bytecodes of its code are resolved against.
+struct CodeTables<'a> {
+    cache: &'a ModuleCache,
+    function_refs: &'a [usize],
+    function_instantiations: &'a [FunctionInstantiation],
+    structs: &'a [StructDef],
+    struct_instantiations: &'a [StructInstantiation],
+    field_handles: &'a [FieldHandle],
+    field_instantiations: &'a [FieldInstantiation],
+    single_signature_token_map: &'a BTreeMap<SignatureIndex, Type>,
+}
+
+impl<'a> CodeTables<'a> {
+    // Decodes the code of a function, fusing the sequences of bytecodes which have a fused
+    // instruction if `fuse` is set.
+    fn decode(&self, code: &[Bytecode], fuse: bool) -> Vec<Instruction> {
+        let mut instructions: Vec<_> = code.iter().map(|bc| self.decode_bytecode(bc)).collect();
+        if fuse {
+            for (offset, pair) in code.windows(2).enumerate() {
+                if let Some(fused) = self.fuse(&pair[0], &pair[1]) {
+                    instructions[offset] = fused;
+                }
+            }
+        }
+        instructions
+    }
+
+    fn fuse(&self, first: &Bytecode, second: &Bytecode) -> Option<Instruction> {
+        let (local_ref, idx) = match first {
+            Bytecode::CopyLoc(idx) => (LocalRef::Copy, *idx),
+            Bytecode::MoveLoc(idx) => (LocalRef::Move, *idx),
+            Bytecode::MutBorrowLoc(idx) => (LocalRef::MutBorrow, *idx),
+            Bytecode::ImmBorrowLoc(idx) => (LocalRef::ImmBorrow, *idx),
+            _ => return None,
+        };
+        match self.decode_bytecode(second) {
+            Instruction::BorrowField(offset, instr) => {
+                Some(Instruction::BorrowLocalField(local_ref, idx, offset, instr))
+            }
+            _ => None,
+        }
+    }
+
+    fn decode_bytecode(&self, bytecode: &Bytecode) -> Instruction {
+        use SimpleInstruction as S;
+
+        match bytecode {
+            Bytecode::Pop => Instruction::Pop,
+            Bytecode::Ret => Instruction::Ret,
+            Bytecode::BrTrue(offset) => Instruction::BrTrue(*offset),
+            Bytecode::BrFalse(offset) => Instruction::BrFalse(*offset),
+            Bytecode::Branch(offset) => Instruction::Branch(*offset),
+            Bytecode::LdU8(int_const) => Instruction::LdU8(*int_const),
+            Bytecode::LdU16(int_const) => Instruction::LdU16(*int_const),
+            Bytecode::LdU32(int_const) => Instruction::LdU32(*int_const),
+            Bytecode::LdU64(int_const) => Instruction::LdU64(*int_const),
+            Bytecode::LdU128(int_const) => Instruction::LdU128(*int_const),
+            Bytecode::LdU256(int_const) => Instruction::LdU256(*int_const),
+            Bytecode::LdConst(idx) => Instruction::LdConst(*idx),
+            Bytecode::LdTrue => Instruction::LdTrue,
+            Bytecode::LdFalse => Instruction::LdFalse,
+            Bytecode::CopyLoc(idx) => Instruction::CopyLoc(*idx),
+            Bytecode::MoveLoc(idx) => Instruction::MoveLoc(*idx),
+            Bytecode::StLoc(idx) => Instruction::StLoc(*idx),
+            Bytecode::Call(idx) => {
+                Instruction::Call(self.cache.function_at(self.function_refs[idx.0 as usize]))
+            }
+            Bytecode::CallGeneric(idx) => Instruction::CallGeneric(
+                self.cache
+                    .function_at(self.function_instantiations[idx.0 as usize].handle),
+                *idx,
+            ),
+            Bytecode::MutBorrowLoc(idx) => Instruction::MutBorrowLoc(*idx),
+            Bytecode::ImmBorrowLoc(idx) => Instruction::ImmBorrowLoc(*idx),
+            Bytecode::MutBorrowField(idx) => Instruction::BorrowField(
+                self.field_handles[idx.0 as usize].offset,
+                S::MutBorrowField,
+            ),
+            Bytecode::ImmBorrowField(idx) => Instruction::BorrowField(
+                self.field_handles[idx.0 as usize].offset,
+                S::ImmBorrowField,
+            ),
+            // both generic field borrows are charged as `ImmBorrowFieldGeneric`
+            Bytecode::MutBorrowFieldGeneric(idx) | Bytecode::ImmBorrowFieldGeneric(idx) => {
+                Instruction::BorrowField(
+                    self.field_instantiations[idx.0 as usize].offset,
+                    S::ImmBorrowFieldGeneric,
+                )
+            }
+            Bytecode::Pack(idx) => {
+                Instruction::Pack(self.structs[idx.0 as usize].field_count, false)
+            }
+            Bytecode::PackGeneric(idx) => {
+                Instruction::Pack(self.struct_instantiations[idx.0 as usize].field_count, true)
+            }
+            Bytecode::Unpack(_) => Instruction::Unpack(false),
+            Bytecode::UnpackGeneric(_) => Instruction::Unpack(true),
+            Bytecode::ReadRef => Instruction::ReadRef,
+            Bytecode::WriteRef => Instruction::WriteRef,
+            Bytecode::FreezeRef => Instruction::FreezeRef,
+            Bytecode::CastU8 => Instruction::CastU8,
+            Bytecode::CastU16 => Instruction::CastU16,
+            Bytecode::CastU32 => Instruction::CastU32,
+            Bytecode::CastU64 => Instruction::CastU64,
+            Bytecode::CastU128 => Instruction::CastU128,
+            Bytecode::CastU256 => Instruction::CastU256,
+            Bytecode::Add => Instruction::Add,
+            Bytecode::Sub => Instruction::Sub,
+            Bytecode::Mul => Instruction::Mul,
+            Bytecode::Mod => Instruction::Mod,
+            Bytecode::Div => Instruction::Div,
+            Bytecode::BitOr => Instruction::BitOr,
+            Bytecode::BitAnd => Instruction::BitAnd,
+            Bytecode::Xor => Instruction::Xor,
+            Bytecode::Or => Instruction::Or,
+            Bytecode::And => Instruction::And,
+            Bytecode::Not => Instruction::Not,
+            Bytecode::Eq => Instruction::Eq,
+            Bytecode::Neq => Instruction::Neq,
+            Bytecode::Lt => Instruction::Lt,
+            Bytecode::Gt => Instruction::Gt,
+            Bytecode::Le => Instruction::Le,
+            Bytecode::Ge => Instruction::Ge,
+            Bytecode::Shl => Instruction::Shl,
+            Bytecode::Shr => Instruction::Shr,
+            Bytecode::Abort => Instruction::Abort,
+            Bytecode::Nop => Instruction::Nop,
+            Bytecode::MutBorrowGlobal(idx) => Instruction::MutBorrowGlobal(self.struct_type(*idx)),
+            Bytecode::ImmBorrowGlobal(idx) => Instruction::ImmBorrowGlobal(self.struct_type(*idx)),
+            Bytecode::MutBorrowGlobalGeneric(idx) => Instruction::MutBorrowGlobalGeneric(*idx),
+            Bytecode::ImmBorrowGlobalGeneric(idx) => Instruction::ImmBorrowGlobalGeneric(*idx),
+            Bytecode::Exists(idx) => Instruction::Exists(self.struct_type(*idx)),
+            Bytecode::ExistsGeneric(idx) => Instruction::ExistsGeneric(*idx),
+            Bytecode::MoveFrom(idx) => Instruction::MoveFrom(self.struct_type(*idx)),
+            Bytecode::MoveFromGeneric(idx) => Instruction::MoveFromGeneric(*idx),
+            Bytecode::MoveTo(idx) => Instruction::MoveTo(self.struct_type(*idx)),
+            Bytecode::MoveToGeneric(idx) => Instruction::MoveToGeneric(*idx),
+            Bytecode::VecPack(idx, num) => Instruction::VecPack(self.single_type(*idx), *num),
+            Bytecode::VecLen(idx) => Instruction::VecLen(self.single_type(*idx)),
+            Bytecode::VecImmBorrow(idx) => Instruction::VecImmBorrow(self.single_type(*idx)),
+            Bytecode::VecMutBorrow(idx) => Instruction::VecMutBorrow(self.single_type(*idx)),
+            Bytecode::VecPushBack(idx) => Instruction::VecPushBack(self.single_type(*idx)),
+            Bytecode::VecPopBack(idx) => Instruction::VecPopBack(self.single_type(*idx)),
+            Bytecode::VecUnpack(idx, num) => Instruction::VecUnpack(self.single_type(*idx), *num),
+            Bytecode::VecSwap(idx) => Instruction::VecSwap(self.single_type(*idx)),
+        }
+    }
+
+    fn struct_type(&self, idx: StructDefinitionIndex) -> Type {
+        Type::Struct(self.structs[idx.0 as usize].idx)
+    }
+
+    fn single_type(&self, idx: SignatureIndex) -> Type {
+        self.single_signature_token_map[&idx].clone()
+    }
+}
+
 //
 // Cache for data associated to a Struct, used for de/serialization and more
 //
@@ -2492,17 +2874,6 @@ impl TypeCache {
     }
 }
 
-/// Maximal depth of a value in terms of type depth.
-const VALUE_DEPTH_MAX: usize = 128;
-
-/// Maximal nodes which are allowed when converting to layout. This includes the the types of
-/// fields for struct types.
-const MAX_TYPE_TO_LAYOUT_NODES: usize = 256;
-
-/// Maximal nodes which are all allowed when instantiating a generic type. This does not include
-/// field types of structs.
-const MAX_TYPE_INSTANTIATION_NODES: usize = 128;
-
 impl Loader {
     fn struct_gidx_to_type_tag(
         &self,
@@ -2641,10 +3012,10 @@ impl Loader {
         count: &mut usize,
         depth: usize,
     ) -> PartialVMResult<MoveTypeLayout> {
-        if *count > MAX_TYPE_TO_LAYOUT_NODES {
-            return Err(PartialVMError::new(StatusCode::TOO_MANY_TYPE_NODES));
+        if *count > self.vm_config.max_type_to_layout_nodes {
+            return Err(PartialVMError::new(StatusCode::VM_MAX_TYPE_NODES_REACHED));
         }
-        if depth > VALUE_DEPTH_MAX {
+        if depth > self.vm_config.max_value_depth {
             return Err(PartialVMError::new(StatusCode::VM_MAX_VALUE_DEPTH_REACHED));
         }
         Ok(match ty {
@@ -2772,10 +3143,10 @@ impl Loader {
         count: &mut usize,
         depth: usize,
     ) -> PartialVMResult<MoveTypeLayout> {
-        if *count > MAX_TYPE_TO_LAYOUT_NODES {
-            return Err(PartialVMError::new(StatusCode::TOO_MANY_TYPE_NODES));
+        if *count > self.vm_config.max_type_to_layout_nodes {
+            return Err(PartialVMError::new(StatusCode::VM_MAX_TYPE_NODES_REACHED));
         }
-        if depth > VALUE_DEPTH_MAX {
+        if depth > self.vm_config.max_value_depth {
             return Err(PartialVMError::new(StatusCode::VM_MAX_VALUE_DEPTH_REACHED));
         }
         Ok(match ty {
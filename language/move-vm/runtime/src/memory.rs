@@ -0,0 +1,171 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Accounting of the memory held by the live values of a session.
+//!
+//! Memory is measured in abstract memory units, as for gas. Vectors and structs hold memory
+//! wherever they live: on the operand stack, in locals or in the data cache. Primitive values and
+//! references on the operand stack or in locals hold none, as there cannot be more of them than
+//! there are slots, but primitive values do once moved into a vector or a struct.
+//!
+//! The interpreter records the memory taken and released as values are created, moved into or
+//! out of containers, and dropped. This keeps the account exact without measuring values on
+//! every move.
+//!
+//! Values moved into native extensions, such as tables, leave the account, but changes made to
+//! them through references still count until the session recomputes its memory from the data
+//! cache after each execution.
+
+use move_core_types::{account_address::AccountAddress, gas_algebra::AbstractMemorySize};
+use move_vm_types::{
+    loaded_data::runtime_types::Type,
+    values::LEGACY_STRUCT_SIZE,
+    views::{ValueView, ValueVisitor},
+};
+use std::mem::size_of;
+
+/// The memory held by a struct, or a vector of boxed elements, in addition to its elements.
+pub(crate) const CONTAINER_MEMORY: AbstractMemorySize = LEGACY_STRUCT_SIZE;
+
+#[derive(Clone, Copy)]
+enum Root {
+    Primitive,
+    Reference,
+    Container,
+}
+
+/// Visits the root of a value only.
+struct RootVisitor(Option<Root>);
+
+impl RootVisitor {
+    fn visit(&mut self, root: Root) -> bool {
+        self.0.get_or_insert(root);
+        false
+    }
+}
+
+impl ValueVisitor for RootVisitor {
+    fn visit_u8(&mut self, _depth: usize, _val: u8) {
+        self.visit(Root::Primitive);
+    }
+
+    fn visit_u16(&mut self, _depth: usize, _val: u16) {
+        self.visit(Root::Primitive);
+    }
+
+    fn visit_u32(&mut self, _depth: usize, _val: u32) {
+        self.visit(Root::Primitive);
+    }
+
+    fn visit_u64(&mut self, _depth: usize, _val: u64) {
+        self.visit(Root::Primitive);
+    }
+
+    fn visit_u128(&mut self, _depth: usize, _val: u128) {
+        self.visit(Root::Primitive);
+    }
+
+    fn visit_u256(&mut self, _depth: usize, _val: move_core_types::u256::U256) {
+        self.visit(Root::Primitive);
+    }
+
+    fn visit_bool(&mut self, _depth: usize, _val: bool) {
+        self.visit(Root::Primitive);
+    }
+
+    fn visit_address(&mut self, _depth: usize, _val: AccountAddress) {
+        self.visit(Root::Primitive);
+    }
+
+    fn visit_struct(&mut self, _depth: usize, _len: usize) -> bool {
+        self.visit(Root::Container)
+    }
+
+    fn visit_vec(&mut self, _depth: usize, _len: usize) -> bool {
+        self.visit(Root::Container)
+    }
+
+    fn visit_ref(&mut self, _depth: usize, _is_global: bool) -> bool {
+        self.visit(Root::Reference)
+    }
+
+    fn visit_vec_u8(&mut self, _depth: usize, _vals: &[u8]) {
+        self.visit(Root::Container);
+    }
+
+    fn visit_vec_u16(&mut self, _depth: usize, _vals: &[u16]) {
+        self.visit(Root::Container);
+    }
+
+    fn visit_vec_u32(&mut self, _depth: usize, _vals: &[u32]) {
+        self.visit(Root::Container);
+    }
+
+    fn visit_vec_u64(&mut self, _depth: usize, _vals: &[u64]) {
+        self.visit(Root::Container);
+    }
+
+    fn visit_vec_u128(&mut self, _depth: usize, _vals: &[u128]) {
+        self.visit(Root::Container);
+    }
+
+    fn visit_vec_u256(&mut self, _depth: usize, _vals: &[move_core_types::u256::U256]) {
+        self.visit(Root::Container);
+    }
+
+    fn visit_vec_bool(&mut self, _depth: usize, _vals: &[bool]) {
+        self.visit(Root::Container);
+    }
+
+    fn visit_vec_address(&mut self, _depth: usize, _vals: &[AccountAddress]) {
+        self.visit(Root::Container);
+    }
+}
+
+fn root(val: &impl ValueView) -> Option<Root> {
+    let mut visitor = RootVisitor(None);
+    val.visit(&mut visitor);
+    visitor.0
+}
+
+/// Returns the memory a value holds on the operand stack or in a local.
+pub(crate) fn held_memory(val: &impl ValueView) -> AbstractMemorySize {
+    match root(val) {
+        Some(Root::Container) => val.legacy_abstract_memory_size(),
+        _ => AbstractMemorySize::zero(),
+    }
+}
+
+/// Returns the memory a value takes once moved into a struct, in addition to the memory it holds.
+pub(crate) fn field_memory(val: &impl ValueView) -> AbstractMemorySize {
+    match root(val) {
+        Some(Root::Primitive) => val.legacy_abstract_memory_size(),
+        _ => AbstractMemorySize::zero(),
+    }
+}
+
+/// Returns the memory an element takes in a vector of `ty`, if such vectors store their elements
+/// unboxed. These vectors hold no memory of their own.
+pub(crate) fn element_memory(ty: &Type) -> Option<AbstractMemorySize> {
+    let size = match ty {
+        Type::Bool => size_of::<bool>(),
+        Type::U8 => size_of::<u8>(),
+        Type::U16 => size_of::<u16>(),
+        Type::U32 => size_of::<u32>(),
+        Type::U64 => size_of::<u64>(),
+        Type::U128 => size_of::<u128>(),
+        Type::U256 => size_of::<move_core_types::u256::U256>(),
+        Type::Address => size_of::<AccountAddress>(),
+        _ => return None,
+    };
+    Some(AbstractMemorySize::new(size as u64))
+}
+
+/// Returns the memory a vector of `ty` with `len` elements holds, in addition to the memory its
+/// elements hold on the operand stack or in locals.
+pub(crate) fn vector_memory(ty: &Type, len: u64) -> AbstractMemorySize {
+    match element_memory(ty) {
+        Some(size) => AbstractMemorySize::new(u64::from(size).saturating_mul(len)),
+        None => CONTAINER_MEMORY,
+    }
+}
@@ -82,23 +82,32 @@ impl MoveVM {
 
     /// Allows the adapter to announce to the VM that the code loading cache should be considered
     /// outdated. This can happen if the adapter executed a particular code publishing transaction
-    /// but decided to not commit the result to the data store. Because the code cache currently
-    /// does not support deletion, the cache will, incorrectly, still contain this module.
-    /// TODO: new loader architecture
+    /// but decided to not commit the result to the data store, in which case the cache may,
+    /// incorrectly, still contain the published modules. If the adapter knows the modules, it
+    /// should prefer `mark_modules_as_invalid`, which preserves the rest of the cache.
     pub fn mark_loader_cache_as_invalid(&self) {
         self.runtime.loader().mark_as_invalid()
     }
 
+    /// Allows the adapter to announce to the VM that the cached versions of the given modules
+    /// should be considered outdated, e.g. because they were published by a transaction which
+    /// was not committed. On the next flush, the modules, and all cached modules and scripts
+    /// depending on them, are evicted from the cache and reloaded from the data store on next
+    /// use, while unrelated modules stay cached. Upgrades of modules are invalidated this way by
+    /// the VM itself.
+    pub fn mark_modules_as_invalid(&self, module_ids: impl IntoIterator<Item = ModuleId>) {
+        self.runtime.loader().mark_modules_as_invalid(module_ids)
+    }
+
     /// Returns true if the loader cache has been invalidated (either by explicit call above
     /// or by the runtime)
     pub fn is_loader_cache_invalidated(&self) -> bool {
         self.runtime.loader().is_invalidated()
     }
 
-    /// If the loader cache has been invalidated (either by the above call or by internal logic)
+    /// If the loader cache has been invalidated (either by the above calls or by internal logic)
     /// flush it so it is valid again. Notice that should only be called if there are no
     /// outstanding sessions created from this VM.
-    /// TODO: new loader architecture
     pub fn flush_loader_cache_if_invalidated(&self) {
         self.runtime.loader().flush_if_invalidated()
     }
@@ -3,8 +3,30 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use better_any::{Tid, TidAble, TidExt};
+use move_binary_format::errors::PartialVMResult;
 use std::{any::TypeId, collections::HashMap};
 
+/// An extension whose state can be saved and restored, so that it is rolled back along with
+/// the session it is attached to. See `Session::savepoint`.
+pub trait SavepointExtension<'a>: TidAble<'a> {
+    /// The saved state of the extension.
+    type Savepoint: 'a;
+
+    /// Saves the current state of the extension.
+    fn savepoint(&self) -> PartialVMResult<Self::Savepoint>;
+
+    /// Restores the extension to a saved state.
+    fn rollback_to(&mut self, savepoint: Self::Savepoint);
+}
+
+type Restore<'a> = Box<dyn FnOnce(&mut dyn Tid<'a>) + 'a>;
+type Save<'a> = fn(&dyn Tid<'a>) -> PartialVMResult<Restore<'a>>;
+
+/// The saved state of the extensions which support savepoints.
+pub(crate) struct ExtensionsSavepoint<'a> {
+    restores: Vec<(TypeId, Restore<'a>)>,
+}
+
 /// A data type to represent a heterogeneous collection of extensions which are available to
 /// native functions. A value to this is passed into the session function execution.
 ///
@@ -13,9 +35,13 @@ use std::{any::TypeId, collections::HashMap};
 /// avoids that extensions need to have `'static` lifetime, which `Any` requires. In order to make a
 /// struct suitable to be a 'Tid', use `#[derive(Tid)]` in the struct declaration. (See also
 /// tests at the end of this module.)
+///
+/// Extensions added with `add_with_savepoints` are rolled back along with the session, while the
+/// state of other extensions is kept on rollback.
 #[derive(Default)]
 pub struct NativeContextExtensions<'a> {
     map: HashMap<TypeId, Box<dyn Tid<'a>>>,
+    saves: HashMap<TypeId, Save<'a>>,
 }
 
 impl<'a> NativeContextExtensions<'a> {
@@ -26,6 +52,18 @@ impl<'a> NativeContextExtensions<'a> {
         )
     }
 
+    pub fn add_with_savepoints<T: SavepointExtension<'a>>(&mut self, ext: T) {
+        fn save<'a, T: SavepointExtension<'a>>(ext: &dyn Tid<'a>) -> PartialVMResult<Restore<'a>> {
+            let savepoint = ext.downcast_ref::<T>().unwrap().savepoint()?;
+            Ok(Box::new(move |ext: &mut dyn Tid<'a>| {
+                ext.downcast_mut::<T>().unwrap().rollback_to(savepoint)
+            }))
+        }
+
+        self.add(ext);
+        self.saves.insert(T::id(), save::<T>);
+    }
+
     pub fn get<T: TidAble<'a>>(&self) -> &T {
         self.map
             .get(&T::id())
@@ -45,6 +83,7 @@ impl<'a> NativeContextExtensions<'a> {
     }
 
     pub fn remove<T: TidAble<'a>>(&mut self) -> T {
+        self.saves.remove(&T::id());
         // can't use expect below because it requires `T: Debug`.
         match self
             .map
@@ -56,6 +95,24 @@ impl<'a> NativeContextExtensions<'a> {
             Err(_) => panic!("downcast error"),
         }
     }
+
+    pub(crate) fn savepoint(&self) -> PartialVMResult<ExtensionsSavepoint<'a>> {
+        let restores = self
+            .saves
+            .iter()
+            .map(|(id, save)| Ok((*id, save(self.map[id].as_ref())?)))
+            .collect::<PartialVMResult<_>>()?;
+        Ok(ExtensionsSavepoint { restores })
+    }
+
+    /// Restores the extensions to the saved state. Extensions removed since are not restored.
+    pub(crate) fn rollback_to(&mut self, savepoint: ExtensionsSavepoint<'a>) {
+        for (id, restore) in savepoint.restores {
+            if let (Some(ext), true) = (self.map.get_mut(&id), self.saves.contains_key(&id)) {
+                restore(ext.as_mut())
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -128,6 +128,8 @@ impl<'a, 'b> NativeContext<'a, 'b> {
         match self.data_store.emit_event(guid, seq_num, ty, val) {
             Ok(()) => Ok(true),
             Err(e) if e.major_status().status_type() == StatusType::InvariantViolation => Err(e),
+            // Exceeding the configured maximal number of events is not recoverable by the caller.
+            Err(e) if e.major_status() == StatusCode::VM_MAX_EVENTS_REACHED => Err(e),
             Err(_) => Ok(false),
         }
     }
@@ -7,6 +7,7 @@ use crate::{
     data_cache::TransactionDataCache,
     interpreter::Interpreter,
     loader::{Function, Loader},
+    memory::held_memory,
     native_extensions::NativeContextExtensions,
     native_functions::{NativeFunction, NativeFunctions},
     session::{LoadedFunctionInstantiation, SerializedReturnValues, Session},
@@ -21,6 +22,7 @@ use move_binary_format::{
 use move_bytecode_verifier::script_signature;
 use move_core_types::{
     account_address::AccountAddress,
+    gas_algebra::AbstractMemorySize,
     identifier::{IdentStr, Identifier},
     language_storage::{ModuleId, TypeTag},
     resolver::MoveResolver,
@@ -195,9 +197,9 @@ impl VMRuntime {
         for (module, blob) in compiled_modules.into_iter().zip(modules.into_iter()) {
             let is_republishing = data_store.exists_module(&module.self_id())?;
             if is_republishing {
-                // This is an upgrade, so invalidate the loader cache, which still contains the
-                // old module.
-                self.loader.mark_as_invalid();
+                // This is an upgrade, so invalidate the old module in the loader cache, together
+                // with the cached modules depending on it.
+                self.loader.mark_modules_as_invalid([module.self_id()]);
             }
             data_store.publish_module(&module.self_id(), blob, is_republishing)?;
         }
@@ -349,6 +351,22 @@ impl VMRuntime {
             .collect::<PartialVMResult<Vec<_>>>()
             .map_err(|err| err.finish(Location::Undefined))?;
 
+        // the values behind mutable reference arguments are live until the call returns
+        let track_memory = self.loader.vm_config().max_memory_usage.is_some();
+        let locals_memory = |locals: &Locals| {
+            if !track_memory {
+                return AbstractMemorySize::zero();
+            }
+            locals
+                .local_views()
+                .fold(AbstractMemorySize::zero(), |acc, val| {
+                    acc + held_memory(&val)
+                })
+        };
+        data_store
+            .use_memory(locals_memory(&dummy_locals))
+            .map_err(|e| e.finish(Location::Undefined))?;
+
         let return_values = Interpreter::entrypoint(
             func,
             ty_args,
@@ -362,6 +380,7 @@ impl VMRuntime {
         let serialized_return_values = self
             .serialize_return_values(&return_types, return_values)
             .map_err(|e| e.finish(Location::Undefined))?;
+        data_store.release_memory(locals_memory(&dummy_locals));
         let serialized_mut_ref_outputs = mut_ref_args
             .into_iter()
             .map(|(idx, ty)| {
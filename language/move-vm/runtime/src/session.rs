@@ -3,7 +3,8 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    data_cache::TransactionDataCache, native_extensions::NativeContextExtensions,
+    data_cache::{DataCacheSavepoint, MemoryUsage, ReadWriteSet, TransactionDataCache},
+    native_extensions::{ExtensionsSavepoint, NativeContextExtensions},
     runtime::VMRuntime,
 };
 use move_binary_format::{
@@ -32,6 +33,12 @@ pub struct Session<'r, 'l, S> {
     pub(crate) native_extensions: NativeContextExtensions<'r>,
 }
 
+/// The saved state of a session, which the session can be rolled back to.
+pub struct Savepoint<'r> {
+    data_cache: DataCacheSavepoint,
+    native_extensions: ExtensionsSavepoint<'r>,
+}
+
 /// Serialized return values from function/script execution
 /// Simple struct is designed just to convey meaning behind serialized values
 #[derive(Debug)]
@@ -78,7 +85,7 @@ impl<'r, 'l, S: MoveResolver> Session<'r, 'l, S> {
         gas_meter: &mut impl GasMeter,
     ) -> VMResult<SerializedReturnValues> {
         let bypass_declared_entry_check = false;
-        self.runtime.execute_function(
+        let result = self.runtime.execute_function(
             module,
             function_name,
             ty_args,
@@ -87,7 +94,9 @@ impl<'r, 'l, S: MoveResolver> Session<'r, 'l, S> {
             gas_meter,
             &mut self.native_extensions,
             bypass_declared_entry_check,
-        )
+        );
+        self.data_cache.reset_memory_usage();
+        result
     }
 
     /// Similar to execute_entry_function, but it bypasses visibility checks
@@ -100,7 +109,7 @@ impl<'r, 'l, S: MoveResolver> Session<'r, 'l, S> {
         gas_meter: &mut impl GasMeter,
     ) -> VMResult<SerializedReturnValues> {
         let bypass_declared_entry_check = true;
-        self.runtime.execute_function(
+        let result = self.runtime.execute_function(
             module,
             function_name,
             ty_args,
@@ -109,7 +118,9 @@ impl<'r, 'l, S: MoveResolver> Session<'r, 'l, S> {
             gas_meter,
             &mut self.native_extensions,
             bypass_declared_entry_check,
-        )
+        );
+        self.data_cache.reset_memory_usage();
+        result
     }
 
     /// Execute a transaction script.
@@ -135,14 +146,16 @@ impl<'r, 'l, S: MoveResolver> Session<'r, 'l, S> {
         args: Vec<impl Borrow<[u8]>>,
         gas_meter: &mut impl GasMeter,
     ) -> VMResult<SerializedReturnValues> {
-        self.runtime.execute_script(
+        let result = self.runtime.execute_script(
             script,
             ty_args,
             args,
             &mut self.data_cache,
             gas_meter,
             &mut self.native_extensions,
-        )
+        );
+        self.data_cache.reset_memory_usage();
+        result
     }
 
     /// Publish the given module.
@@ -233,6 +246,50 @@ impl<'r, 'l, S: MoveResolver> Session<'r, 'l, S> {
         self.data_cache.num_mutated_accounts(sender)
     }
 
+    /// Returns the modules and resources read from the remote storage so far, along with their
+    /// versions, and the modules and resources written by the session. The accesses are
+    /// available whether or not the executed functions succeeded.
+    pub fn read_write_set(&self) -> VMResult<ReadWriteSet> {
+        self.data_cache
+            .read_write_set()
+            .map_err(|e| e.finish(Location::Undefined))
+    }
+
+    /// Returns the memory held by the live values of the session, and the most it held at any
+    /// point, which hosts may charge for. Exceeding `VMConfig::max_memory_usage` fails with
+    /// `MEMORY_LIMIT_EXCEEDED`. Both are zero if no `max_memory_usage` is set.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        self.data_cache.memory_usage()
+    }
+
+    /// Saves the state of the session: the resources and modules in its data cache, the events
+    /// emitted so far, and the state of the native extensions which support savepoints.
+    ///
+    /// This allows to keep the effects of some of the functions executed in a session, e.g. those
+    /// of a prologue and an epilogue, while discarding the effects of a payload which aborted.
+    pub fn savepoint(&self) -> VMResult<Savepoint<'r>> {
+        Ok(Savepoint {
+            data_cache: self
+                .data_cache
+                .savepoint()
+                .map_err(|e| e.finish(Location::Undefined))?,
+            native_extensions: self
+                .native_extensions
+                .savepoint()
+                .map_err(|e| e.finish(Location::Undefined))?,
+        })
+    }
+
+    /// Rolls back the session to a savepoint taken from it, discarding all effects since.
+    ///
+    /// Modules published since the savepoint are marked as invalid in the loader cache, but they
+    /// remain loadable from it until the cache is flushed, as for an abandoned session.
+    pub fn rollback_to(&mut self, savepoint: Savepoint<'r>) {
+        self.data_cache.rollback_to(savepoint.data_cache);
+        self.native_extensions
+            .rollback_to(savepoint.native_extensions);
+    }
+
     /// Finish up the session and produce the side effects.
     ///
     /// This function should always succeed with no user errors returned, barring invariant violations.
@@ -244,6 +301,14 @@ impl<'r, 'l, S: MoveResolver> Session<'r, 'l, S> {
             .map_err(|e| e.finish(Location::Undefined))
     }
 
+    /// Same like `finish`, but also returns the memory usage of the session, including the most
+    /// memory it held at any point.
+    pub fn finish_with_memory_usage(self) -> VMResult<(ChangeSet, Vec<Event>, MemoryUsage)> {
+        let memory_usage = self.data_cache.memory_usage();
+        let (change_set, events) = self.finish()?;
+        Ok((change_set, events, memory_usage))
+    }
+
     /// Same like `finish`, but also extracts the native context extensions from the session.
     pub fn finish_with_extensions(
         self,
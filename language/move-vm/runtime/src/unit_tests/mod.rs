@@ -2,4 +2,5 @@
 // Copyright (c) The Move Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+pub mod module_cache_tests;
 pub mod vm_arguments_tests;
@@ -0,0 +1,116 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{config::VMConfig, data_cache::TransactionDataCache, runtime::VMRuntime};
+use move_binary_format::errors::VMError;
+use move_core_types::{
+    account_address::AccountAddress,
+    language_storage::{ModuleId, StructTag},
+    resolver::{ModuleResolver, ResourceResolver},
+};
+use move_ir_compiler::Compiler;
+use std::collections::HashMap;
+
+#[derive(Default)]
+struct ModuleStore {
+    modules: HashMap<ModuleId, Vec<u8>>,
+}
+
+impl ModuleStore {
+    fn add_module(&mut self, code: &str) -> ModuleId {
+        let module = Compiler::new(vec![]).into_compiled_module(code).unwrap();
+        let mut bytes = vec![];
+        module.serialize(&mut bytes).unwrap();
+        self.modules.insert(module.self_id(), bytes);
+        module.self_id()
+    }
+}
+
+impl ModuleResolver for ModuleStore {
+    type Error = VMError;
+
+    fn get_module(&self, module_id: &ModuleId) -> Result<Option<Vec<u8>>, Self::Error> {
+        Ok(self.modules.get(module_id).cloned())
+    }
+}
+
+impl ResourceResolver for ModuleStore {
+    type Error = VMError;
+
+    fn get_resource(
+        &self,
+        _address: &AccountAddress,
+        _tag: &StructTag,
+    ) -> Result<Option<Vec<u8>>, Self::Error> {
+        Ok(None)
+    }
+}
+
+// A module with a struct and two functions, which is upgraded over and over.
+const UPGRADED: &str = r#"
+module 0x2a.M {
+    struct S { x: u64 }
+
+    public f(): u64 {
+    label b0:
+        return 1;
+    }
+
+    public g(): u64 {
+    label b0:
+        return 2;
+    }
+}
+"#;
+
+// An unrelated module with more entries, which stays cached across upgrades of the other one.
+const UNRELATED: &str = r#"
+module 0x2a.N {
+    struct T { x: u64 }
+    struct U { x: u64 }
+
+    public f(): u64 {
+    label b0:
+        return 1;
+    }
+
+    public g(): u64 {
+    label b0:
+        return 2;
+    }
+
+    public h(): u64 {
+    label b0:
+        return 3;
+    }
+}
+"#;
+
+#[test]
+fn repeated_upgrades_keep_the_cache_bounded() {
+    let runtime = VMRuntime::new(vec![], VMConfig::default()).unwrap();
+    let loader = runtime.loader();
+    let mut store = ModuleStore::default();
+    let upgraded = store.add_module(UPGRADED);
+    let unrelated = store.add_module(UNRELATED);
+    let load_all = || {
+        let data_store = TransactionDataCache::new(&store, loader);
+        loader.load_module(&upgraded, &data_store).unwrap();
+        loader.load_module(&unrelated, &data_store).unwrap();
+    };
+
+    load_all();
+    let entries = loader.module_cache().read().num_entries();
+    assert_eq!(entries, 3 + 5);
+
+    // Each upgrade leaves the entries of the previous version behind, until they outnumber the
+    // live ones and the cache is rebuilt.
+    let mut max_entries = entries;
+    for _ in 0..20 {
+        loader.mark_modules_as_invalid([upgraded.clone()]);
+        loader.flush_if_invalidated();
+        load_all();
+        max_entries = max_entries.max(loader.module_cache().read().num_entries());
+    }
+    assert_eq!(max_entries, entries + 3);
+}
@@ -0,0 +1,129 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! A cache of the modules which passed the bytecode verifier, so that VMs loading the same module
+//! bytes again can skip it.
+//!
+//! The cache only remembers that a module verified, keyed by the hash of its bytes, of the
+//! verifier config it verified under and of `move_bytecode_verifier::VERIFIER_VERSION`, so a cache
+//! can be shared across VMs with different configs and across processes, and is invalidated by
+//! changes to the verifier. Checks which depend on other modules, such as linking and cyclic
+//! dependencies, are still performed on every load.
+//!
+//! A cache read from disk is trusted: a module whose key is in it is not verified at all. Hosts
+//! should only load caches they wrote themselves, and may set
+//! `VMConfig::reverify_cached_modules_one_in` to re-verify a sample of the modules found in it.
+//! Modules failing re-verification are removed from the cache.
+
+use move_bytecode_verifier::{VerifierConfig, VERIFIER_VERSION};
+use parking_lot::RwLock;
+use sha3::{Digest, Sha3_256};
+use std::{
+    collections::BTreeSet,
+    fs,
+    io::{self, Read, Write},
+    path::Path,
+};
+
+/// Identifies module bytes which passed the bytecode verifier under a verifier config.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VerifiedModuleKey([u8; 32]);
+
+impl VerifiedModuleKey {
+    pub const LENGTH: usize = 32;
+
+    pub fn new(module_bytes: &[u8], config: &VerifierConfig) -> Self {
+        let mut sha3_256 = Sha3_256::new();
+        sha3_256.update(VERIFIER_VERSION.to_le_bytes());
+        sha3_256.update(module_bytes);
+        // The config has no canonical serialization, but its debug representation covers all
+        // its fields, so that changing any of them changes the key.
+        sha3_256.update(format!("{:?}", config).as_bytes());
+        Self(sha3_256.finalize().into())
+    }
+}
+
+/// A cache of the modules which passed the bytecode verifier. It is shared across the threads
+/// running sessions, and may be shared across VMs.
+pub trait VerifiedModuleCache: Send + Sync {
+    /// Returns true if the module with `key` passed the bytecode verifier.
+    fn contains(&self, key: &VerifiedModuleKey) -> bool;
+
+    /// Records that the module with `key` passed the bytecode verifier.
+    fn insert(&self, key: VerifiedModuleKey);
+
+    /// Forgets the module with `key`, e.g. after it failed to re-verify.
+    fn remove(&self, key: &VerifiedModuleKey);
+}
+
+/// A `VerifiedModuleCache` held in memory, which can be saved to and loaded from a file.
+#[derive(Debug, Default)]
+pub struct InMemoryVerifiedModuleCache {
+    keys: RwLock<BTreeSet<VerifiedModuleKey>>,
+}
+
+impl InMemoryVerifiedModuleCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of modules in the cache.
+    pub fn len(&self) -> usize {
+        self.keys.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.read().is_empty()
+    }
+
+    /// Loads a cache saved by `save`. A missing file is read as an empty cache.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut bytes = vec![];
+        match fs::File::open(path) {
+            Ok(mut file) => file.read_to_end(&mut bytes)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::new()),
+            Err(err) => return Err(err),
+        };
+        if bytes.len() % VerifiedModuleKey::LENGTH != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("truncated verified module cache {}", path.display()),
+            ));
+        }
+        let keys = bytes
+            .chunks_exact(VerifiedModuleKey::LENGTH)
+            .map(|chunk| VerifiedModuleKey(chunk.try_into().unwrap()))
+            .collect();
+        Ok(Self {
+            keys: RwLock::new(keys),
+        })
+    }
+
+    /// Saves the cache to `path`. The file is replaced atomically, so that a concurrent `load`
+    /// never reads a partially written cache.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let tmp_path = path.with_extension("tmp");
+        {
+            let mut file = io::BufWriter::new(fs::File::create(&tmp_path)?);
+            for key in self.keys.read().iter() {
+                file.write_all(&key.0)?;
+            }
+            file.flush()?;
+        }
+        fs::rename(tmp_path, path)
+    }
+}
+
+impl VerifiedModuleCache for InMemoryVerifiedModuleCache {
+    fn contains(&self, key: &VerifiedModuleKey) -> bool {
+        self.keys.read().contains(key)
+    }
+
+    fn insert(&self, key: VerifiedModuleKey) {
+        self.keys.write().insert(key);
+    }
+
+    fn remove(&self, key: &VerifiedModuleKey) {
+        self.keys.write().remove(key);
+    }
+}
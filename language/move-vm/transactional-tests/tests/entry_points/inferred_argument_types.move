@@ -0,0 +1,22 @@
+//# publish
+module 0x42::M {
+    struct S has drop { x: u64, v: vector<u8> }
+
+    public fun numbers(a: u8, b: u128, c: address): (u8, u128, address) {
+        (a, b, c)
+    }
+
+    public fun structs(s: S): S {
+        s
+    }
+}
+
+//# run --args 42 @42
+// numbers without a suffix take the type of their parameter, addresses are written with @
+script {
+fun main(_x: u64, _y: address) {}
+}
+
+//# run 0x42::M::numbers --args 1 2 @3
+
+//# run 0x42::M::structs --args 0x42::M::S{x:1,v:b"hi"}
@@ -8,7 +8,9 @@ use crate::{
 };
 use move_binary_format::errors::{PartialVMResult, VMResult};
 use move_core_types::{
-    account_address::AccountAddress, gas_algebra::NumBytes, language_storage::ModuleId,
+    account_address::AccountAddress,
+    gas_algebra::{AbstractMemorySize, NumBytes},
+    language_storage::ModuleId,
     value::MoveTypeLayout,
 };
 
@@ -59,4 +61,17 @@ pub trait DataStore {
     ) -> PartialVMResult<()>;
 
     fn events(&self) -> &Vec<(Vec<u8>, u64, Type, MoveTypeLayout, Value)>;
+
+    // ---
+    // Memory accounting
+    // ---
+
+    /// Record that the live values of the VM grew by `size`, failing if this exceeds the
+    /// memory quota. Data stores which do not account for memory accept all uses.
+    fn use_memory(&mut self, _size: AbstractMemorySize) -> PartialVMResult<()> {
+        Ok(())
+    }
+
+    /// Record that live values of the VM of `size` were dropped.
+    fn release_memory(&mut self, _size: AbstractMemorySize) {}
 }
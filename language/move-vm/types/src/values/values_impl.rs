@@ -2290,7 +2290,7 @@ pub(crate) const LEGACY_CONST_SIZE: AbstractMemorySize = AbstractMemorySize::new
 pub(crate) const LEGACY_REFERENCE_SIZE: AbstractMemorySize = AbstractMemorySize::new(8);
 
 /// The size of a struct in bytes
-pub(crate) const LEGACY_STRUCT_SIZE: AbstractMemorySize = AbstractMemorySize::new(2);
+pub const LEGACY_STRUCT_SIZE: AbstractMemorySize = AbstractMemorySize::new(2);
 
 impl Container {
     #[cfg(test)]
@@ -2528,6 +2528,30 @@ impl GlobalValueImpl {
         }
     }
 
+    fn copy_value(&self) -> PartialVMResult<Self> {
+        let copy_fields = |fields: &Rc<RefCell<Vec<ValueImpl>>>| -> PartialVMResult<_> {
+            Ok(Rc::new(RefCell::new(
+                fields
+                    .borrow()
+                    .iter()
+                    .map(|v| v.copy_value())
+                    .collect::<PartialVMResult<_>>()?,
+            )))
+        };
+
+        Ok(match self {
+            Self::None => Self::None,
+            Self::Deleted => Self::Deleted,
+            Self::Fresh { fields } => Self::Fresh {
+                fields: copy_fields(fields)?,
+            },
+            Self::Cached { fields, status } => Self::Cached {
+                fields: copy_fields(fields)?,
+                status: Rc::new(RefCell::new(*status.borrow())),
+            },
+        })
+    }
+
     fn is_mutated(&self) -> bool {
         match self {
             Self::None => false,
@@ -2577,6 +2601,12 @@ impl GlobalValue {
     pub fn is_mutated(&self) -> bool {
         self.0.is_mutated()
     }
+
+    /// Returns a deep copy of the slot, which shares no state with the original. Must not be
+    /// called while references to the resource are alive.
+    pub fn copy_value(&self) -> PartialVMResult<Self> {
+        Ok(Self(self.0.copy_value()?))
+    }
 }
 
 /***************************************************************************************
@@ -2885,6 +2915,23 @@ pub mod debug {
     pub fn print_value<B: Write>(buf: &mut B, val: &Value) -> PartialVMResult<()> {
         print_value_impl(buf, &val.0)
     }
+
+    /// Renders the value of each local, or `None` if the local holds no value.
+    pub fn render_locals(locals: &Locals) -> PartialVMResult<Vec<Option<String>>> {
+        locals
+            .0
+            .borrow()
+            .iter()
+            .map(|val| match val {
+                ValueImpl::Invalid => Ok(None),
+                val => {
+                    let mut buf = String::new();
+                    print_value_impl(&mut buf, val)?;
+                    Ok(Some(buf))
+                }
+            })
+            .collect()
+    }
 }
 
 /***************************************************************************************
@@ -3446,6 +3493,37 @@ impl Vector {
     }
 }
 
+impl Locals {
+    /// Returns a view of the value in the local at `idx`, if it holds one.
+    #[allow(clippy::needless_lifetimes)]
+    pub fn local_view<'a>(&'a self, idx: usize) -> Option<impl ValueView + 'a> {
+        struct LocalView<'b> {
+            locals: &'b RefCell<Vec<ValueImpl>>,
+            idx: usize,
+        }
+
+        impl<'b> ValueView for LocalView<'b> {
+            fn visit(&self, visitor: &mut impl ValueVisitor) {
+                self.locals.borrow()[self.idx].visit_impl(visitor, 0)
+            }
+        }
+
+        match self.0.borrow().get(idx) {
+            None | Some(ValueImpl::Invalid) => None,
+            Some(_) => Some(LocalView {
+                locals: &self.0,
+                idx,
+            }),
+        }
+    }
+
+    /// Returns views of the values in the locals which hold one.
+    #[allow(clippy::needless_lifetimes)]
+    pub fn local_views<'a>(&'a self) -> impl Iterator<Item = impl ValueView + 'a> {
+        (0..self.0.borrow().len()).filter_map(move |idx| self.local_view(idx))
+    }
+}
+
 impl Reference {
     #[allow(clippy::needless_lifetimes)]
     pub fn value_view<'a>(&'a self) -> impl ValueView + 'a {
@@ -5,8 +5,8 @@
 #![forbid(unsafe_code)]
 
 use crate::tasks::{
-    taskify, InitCommand, PrintBytecodeCommand, PrintBytecodeInputChoice, PublishCommand,
-    RunCommand, SyntaxChoice, TaskCommand, TaskInput, ViewCommand,
+    taskify, EventsCommand, InitCommand, PrintBytecodeCommand, PrintBytecodeInputChoice,
+    PublishCommand, RunCommand, SyntaxChoice, TaskCommand, TaskInput, ViewCommand,
 };
 use anyhow::{anyhow, Result};
 use clap::Parser;
@@ -15,6 +15,7 @@ use move_binary_format::{
     file_format::{CompiledModule, CompiledScript},
 };
 use move_bytecode_source_map::mapping::SourceMapping;
+use move_bytecode_utils::{layout::TypeLayoutBuilder, module_cache::GetModule};
 use move_command_line_common::{
     address::ParsedAddress,
     env::read_bool_env_var,
@@ -36,6 +37,7 @@ use move_core_types::{
 };
 use move_disassembler::disassembler::{Disassembler, DisassemblerOptions};
 use move_ir_types::location::Spanned;
+use move_resource_viewer::events::{parse_guid, EventFilter};
 use move_symbol_pool::Symbol;
 use move_vm_runtime::session::SerializedReturnValues;
 use rayon::iter::Either;
@@ -89,6 +91,40 @@ impl<'a> CompiledState<'a> {
             .collect()
     }
 
+    /// Resolve `args` with the types of the parameters of the script in `view`, or of its
+    /// function `function_name` if `view` is a module. Falls back to `resolve_args` if an
+    /// argument cannot be converted to the type of its parameter, so that the VM reports the
+    /// mismatch, or if it is a custom value, which has no known type.
+    pub fn resolve_typed_args<Extra: ParsableValue>(
+        &self,
+        args: Vec<ParsedValue<Extra>>,
+        view: BinaryIndexedView,
+        function_name: Option<&IdentStr>,
+        type_args: &[TypeTag],
+    ) -> Result<Vec<Extra::ConcreteValue>> {
+        let typed =
+            TypeLayoutBuilder::build_parameters_with_types(view, function_name, type_args, &self)
+                .ok()
+                .filter(|layouts| layouts.len() == args.len())
+                .and_then(|layouts| {
+                    args.iter()
+                        .zip(&layouts)
+                        .map(|(arg, layout)| {
+                            without_custom(arg)?
+                                .into_typed_value(layout, &|s| Some(self.resolve_named_address(s)))
+                                .ok()
+                        })
+                        .collect::<Option<Vec<_>>>()
+                });
+        match typed {
+            Some(values) => values
+                .into_iter()
+                .map(Extra::move_value_into_concrete)
+                .collect(),
+            None => self.resolve_args(args),
+        }
+    }
+
     pub fn resolve_type_args(&self, type_args: Vec<ParsedType>) -> Result<Vec<TypeTag>> {
         type_args
             .into_iter()
@@ -97,6 +133,43 @@ impl<'a> CompiledState<'a> {
     }
 }
 
+impl<'a, 'b> GetModule for &'b CompiledState<'a> {
+    type Error = anyhow::Error;
+    type Item = &'b CompiledModule;
+
+    fn get_module_by_id(&self, id: &ModuleId) -> Result<Option<Self::Item>, Self::Error> {
+        Ok(self.modules.get(id).map(|pmod| &pmod.module))
+    }
+}
+
+/// A copy of `value`, unless it contains custom values.
+fn without_custom<Extra: ParsableValue>(value: &ParsedValue<Extra>) -> Option<ParsedValue> {
+    Some(match value {
+        ParsedValue::Address(a) => ParsedValue::Address(a.clone()),
+        ParsedValue::InferredNum(n) => ParsedValue::InferredNum(*n),
+        ParsedValue::U8(n) => ParsedValue::U8(*n),
+        ParsedValue::U16(n) => ParsedValue::U16(*n),
+        ParsedValue::U32(n) => ParsedValue::U32(*n),
+        ParsedValue::U64(n) => ParsedValue::U64(*n),
+        ParsedValue::U128(n) => ParsedValue::U128(*n),
+        ParsedValue::U256(n) => ParsedValue::U256(*n),
+        ParsedValue::Bool(b) => ParsedValue::Bool(*b),
+        ParsedValue::Vector(elems) => {
+            ParsedValue::Vector(elems.iter().map(without_custom).collect::<Option<_>>()?)
+        }
+        ParsedValue::Struct(address, module, name, fields) => ParsedValue::Struct(
+            address.clone(),
+            module.clone(),
+            name.clone(),
+            fields
+                .iter()
+                .map(|(field, value)| Some((field.clone(), without_custom(value)?)))
+                .collect::<Option<_>>()?,
+        ),
+        ParsedValue::Custom(_) => return None,
+    })
+}
+
 fn merge_output(left: Option<String>, right: Option<String>) -> Option<String> {
     match (left, right) {
         (None, right) => right,
@@ -156,6 +229,13 @@ pub trait MoveTestAdapter<'a>: Sized {
         type_args: Vec<TypeTag>,
     ) -> Result<String>;
 
+    /// Returns the events emitted by the transactions run so far which match `filter`.
+    fn view_events(&mut self, _filter: EventFilter) -> Result<String> {
+        Err(anyhow!(
+            "This adapter does not support the 'events' command"
+        ))
+    }
+
     fn handle_subcommand(
         &mut self,
         subcommand: TaskInput<Self::Subcommand>,
@@ -313,8 +393,13 @@ pub trait MoveTestAdapter<'a>: Sized {
                     }
                     SyntaxChoice::IR => (compile_ir_script(state.dep_modules(), data_path)?, None),
                 };
-                let args = self.compiled_state().resolve_args(args)?;
                 let type_args = self.compiled_state().resolve_type_args(type_args)?;
+                let args = self.compiled_state().resolve_typed_args(
+                    args,
+                    BinaryIndexedView::Script(&script),
+                    None,
+                    &type_args,
+                )?;
                 let (output, return_values) =
                     self.execute_script(script, type_args, signers, args, gas_budget, extra_args)?;
                 let rendered_return_value = display_return_values(return_values);
@@ -341,7 +426,16 @@ pub trait MoveTestAdapter<'a>: Sized {
                 let addr = self.compiled_state().resolve_address(&raw_addr);
                 let module_id = ModuleId::new(addr, module_name);
                 let type_args = self.compiled_state().resolve_type_args(type_args)?;
-                let args = self.compiled_state().resolve_args(args)?;
+                let state = self.compiled_state();
+                let args = match state.modules.get(&module_id) {
+                    Some(pmod) => state.resolve_typed_args(
+                        args,
+                        BinaryIndexedView::Module(&pmod.module),
+                        Some(name.as_ident_str()),
+                        &type_args,
+                    )?,
+                    None => state.resolve_args(args)?,
+                };
                 let (output, return_values) = self.call_function(
                     &module_id,
                     name.as_ident_str(),
@@ -373,6 +467,24 @@ pub trait MoveTestAdapter<'a>: Sized {
                     type_arguments,
                 )?))
             }
+            TaskCommand::Events(EventsCommand {
+                type_,
+                guid,
+                txn,
+                fields,
+            }) => {
+                let state: &CompiledState = self.compiled_state();
+                let type_ = type_
+                    .map(|ty| ty.into_type_tag(&|s| Some(state.resolve_named_address(s))))
+                    .transpose()?;
+                let filter = EventFilter {
+                    guid: guid.as_deref().map(parse_guid).transpose()?,
+                    type_,
+                    txn,
+                    fields,
+                };
+                Ok(Some(self.view_events(filter)?))
+            }
             TaskCommand::Subcommand(c) => self.handle_subcommand(TaskInput {
                 command: c,
                 name,
@@ -14,6 +14,7 @@ use move_command_line_common::{
 };
 use move_compiler::shared::NumericalAddress;
 use move_core_types::identifier::Identifier;
+use move_resource_viewer::events::FieldFilter;
 use std::{convert::TryInto, fmt::Debug, path::Path, str::FromStr};
 use tempfile::NamedTempFile;
 
@@ -278,6 +279,23 @@ pub struct ViewCommand {
     pub resource: ParsedStructType,
 }
 
+#[derive(Debug, Parser)]
+pub struct EventsCommand {
+    #[clap(long = "type", parse(try_from_str = ParsedType::parse))]
+    pub type_: Option<ParsedType>,
+    #[clap(long = "guid")]
+    pub guid: Option<String>,
+    #[clap(long = "txn")]
+    pub txn: Option<u64>,
+    #[clap(
+        long = "field",
+        takes_value(true),
+        multiple_values(true),
+        multiple_occurrences(true)
+    )]
+    pub fields: Vec<FieldFilter>,
+}
+
 #[derive(Debug)]
 pub enum TaskCommand<
     ExtraInitArgs: Parser,
@@ -291,6 +309,7 @@ pub enum TaskCommand<
     Publish(PublishCommand, ExtraPublishArgs),
     Run(RunCommand<ExtraValueArgs>, ExtraRunArgs),
     View(ViewCommand),
+    Events(EventsCommand),
     Subcommand(SubCommands),
 }
 
@@ -323,6 +342,9 @@ impl<
             Some(("view", matches)) => {
                 TaskCommand::View(FromArgMatches::from_arg_matches(matches)?)
             }
+            Some(("events", matches)) => {
+                TaskCommand::Events(FromArgMatches::from_arg_matches(matches)?)
+            }
             _ => TaskCommand::Subcommand(SubCommands::from_arg_matches(matches)?),
         })
     }
@@ -352,6 +374,7 @@ impl<
                 RunCommand::<ExtraValueArgs>::augment_args(ExtraRunArgs::command()).name("run"),
             )
             .subcommand(ViewCommand::command().name("view"))
+            .subcommand(EventsCommand::command().name("events"))
     }
 
     fn into_app_for_update<'help>() -> Command<'help> {
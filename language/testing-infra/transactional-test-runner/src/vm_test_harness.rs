@@ -29,7 +29,10 @@ use move_core_types::{
     resolver::MoveResolver,
     value::MoveValue,
 };
-use move_resource_viewer::MoveValueAnnotator;
+use move_resource_viewer::{
+    events::{EventFilter, EventIndex},
+    MoveValueAnnotator,
+};
 use move_stdlib::move_stdlib_named_addresses;
 use move_symbol_pool::Symbol;
 use move_vm_runtime::{
@@ -45,6 +48,7 @@ const STD_ADDR: AccountAddress = AccountAddress::ONE;
 struct SimpleVMTestAdapter<'a> {
     compiled_state: CompiledState<'a>,
     storage: InMemoryStorage,
+    events: EventIndex,
     default_syntax: SyntaxChoice,
 }
 
@@ -130,6 +134,7 @@ impl<'a> MoveTestAdapter<'a> for SimpleVMTestAdapter<'a> {
             compiled_state: CompiledState::new(named_address_mapping, pre_compiled_deps, None),
             default_syntax,
             storage: InMemoryStorage::new(),
+            events: EventIndex::new(),
         };
 
         adapter
@@ -151,6 +156,8 @@ impl<'a> MoveTestAdapter<'a> for SimpleVMTestAdapter<'a> {
                 VMConfig::default(),
             )
             .unwrap();
+        // Count transactions from the first task, not from the publishing of the standard library
+        adapter.events = EventIndex::new();
         let mut addr_to_name_mapping = BTreeMap::new();
         for (name, addr) in move_stdlib_named_addresses() {
             let prev = addr_to_name_mapping.insert(addr, Symbol::from(name));
@@ -305,6 +312,20 @@ impl<'a> MoveTestAdapter<'a> for SimpleVMTestAdapter<'a> {
         view_resource_in_move_storage(&self.storage, address, module, resource, type_args)
     }
 
+    fn view_events(&mut self, filter: EventFilter) -> Result<String> {
+        let events = self
+            .events
+            .query(&filter, &MoveValueAnnotator::new(&self.storage))?;
+        if events.is_empty() {
+            return Ok("[No Events]".to_owned());
+        }
+        Ok(events
+            .iter()
+            .map(|event| event.to_string())
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
     fn handle_subcommand(&mut self, _: TaskInput<Self::Subcommand>) -> Result<Option<String>> {
         unreachable!()
     }
@@ -346,7 +367,12 @@ impl<'a> SimpleVMTestAdapter<'a> {
                 STD_ADDR,
                 // TODO: come up with a suitable gas schedule
                 move_stdlib::natives::GasParameters::zeros(),
-            ),
+            )
+            .into_iter()
+            .chain(move_stdlib::natives::nursery_natives(
+                STD_ADDR,
+                move_stdlib::natives::NurseryGasParameters::zeros(),
+            )),
             vm_config,
         )
         .unwrap();
@@ -364,9 +390,9 @@ impl<'a> SimpleVMTestAdapter<'a> {
         let res = f(&mut session, &mut gas_status)?;
 
         // save changeset
-        // TODO support events
-        let (changeset, _events) = session.finish()?;
+        let (changeset, events) = session.finish()?;
         self.storage.apply(changeset).unwrap();
+        self.events.add_transaction(events);
         Ok(res)
     }
 }
@@ -0,0 +1,42 @@
+//# init --addresses A=0x42
+
+//# publish
+module std::event {
+    native public fun write_to_event_store<T: drop + store>(guid: vector<u8>, count: u64, msg: T);
+}
+
+//# publish
+module A::Bank {
+    use std::event;
+
+    struct Deposit has drop, store { amount: u64, to: address }
+    struct Withdraw has drop, store { amount: u64 }
+
+    public entry fun deposit(seq: u64, amount: u64, to: address) {
+        event::write_to_event_store(x"00", seq, Deposit { amount, to })
+    }
+
+    public entry fun withdraw(seq: u64, amount: u64) {
+        event::write_to_event_store(x"01", seq, Withdraw { amount })
+    }
+}
+
+//# run --args 0 10 @0xb -- 0x42::Bank::deposit
+
+//# run --args 0 3 -- 0x42::Bank::withdraw
+
+//# run --args 1 7 @0xc -- 0x42::Bank::deposit
+
+//# events
+
+//# events --type A::Bank::Deposit
+
+//# events --guid 0x01
+
+//# events --txn 4
+
+//# events --field amount=7
+
+//# events --type A::Bank::Deposit --field to=0xb
+
+//# events --type A::Bank::Withdraw --field amount=10
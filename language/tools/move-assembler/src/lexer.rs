@@ -0,0 +1,83 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{bail, Result};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Tok {
+    /// A keyword, table name, opcode, or primitive type.
+    Word(String),
+    /// A decimal number.
+    Num(String),
+    /// The hex digits of a `0x` literal, which is either an address or a byte string.
+    Hex(String),
+    /// The contents of a string literal; only identifiers are written as strings.
+    Str(String),
+    Punct(char),
+}
+
+impl std::fmt::Display for Tok {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Tok::Word(s) | Tok::Num(s) => write!(f, "`{}`", s),
+            Tok::Hex(s) => write!(f, "`0x{}`", s),
+            Tok::Str(s) => write!(f, "`\"{}\"`", s),
+            Tok::Punct(c) => write!(f, "`{}`", c),
+        }
+    }
+}
+
+/// Split `text` into tokens, each paired with the line it is on.
+pub fn tokenize(text: &str) -> Result<Vec<(Tok, usize)>> {
+    let mut tokens = vec![];
+    for (line_idx, line) in text.lines().enumerate() {
+        let line_no = line_idx + 1;
+        let line = match line.find("//") {
+            Some(comment) => &line[..comment],
+            None => line,
+        };
+        // The end of the run of characters satisfying `pred` that starts at `from`.
+        let scan = |from: usize, pred: fn(char) -> bool| {
+            line[from..]
+                .char_indices()
+                .find(|(_, c)| !pred(*c))
+                .map_or(line.len(), |(i, _)| from + i)
+        };
+        let mut pos = 0;
+        while let Some(c) = line[pos..].chars().next() {
+            let start = pos;
+            pos += c.len_utf8();
+            let tok = match c {
+                c if c.is_whitespace() => continue,
+                '[' | ']' | '<' | '>' | ',' | ':' | '&' | '#' => Tok::Punct(c),
+                '"' => match line[pos..].find('"') {
+                    Some(len) => {
+                        let contents = &line[pos..pos + len];
+                        pos += len + 1;
+                        Tok::Str(contents.to_string())
+                    }
+                    None => bail!("line {}: unterminated string", line_no),
+                },
+                '0' if line[start..].starts_with("0x") => {
+                    pos = scan(start + 2, |c| c.is_ascii_alphanumeric());
+                    let digits = &line[start + 2..pos];
+                    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                        bail!("line {}: invalid hex literal `0x{}`", line_no, digits)
+                    }
+                    Tok::Hex(digits.to_string())
+                }
+                c if c.is_ascii_digit() => {
+                    pos = scan(start, |c| c.is_ascii_digit());
+                    Tok::Num(line[start..pos].to_string())
+                }
+                c if c.is_ascii_alphabetic() || c == '_' => {
+                    pos = scan(start, |c| c.is_ascii_alphanumeric() || c == '_');
+                    Tok::Word(line[start..pos].to_string())
+                }
+                c => bail!("line {}: unexpected character `{}`", line_no, c),
+            };
+            tokens.push((tok, line_no));
+        }
+    }
+    Ok(tokens)
+}
@@ -0,0 +1,48 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! An assembler for the lossless textual form of Move bytecode printed by
+//! `move_disassembler::assembly`. Assembling the text printed for a binary gives back that
+//! binary, byte for byte.
+//!
+//! The assembler does not check that what it assembles is well-formed beyond what is needed to
+//! serialize it, so it can be used to produce binaries that the deserializer or the bytecode
+//! verifier reject.
+
+mod lexer;
+mod parser;
+
+use anyhow::Result;
+use move_binary_format::file_format::{CompiledModule, CompiledScript};
+pub use parser::Unit;
+
+/// Parse the assembly of a module or script.
+pub fn parse(text: &str) -> Result<Unit> {
+    parser::parse(text)
+}
+
+/// Parse the assembly of a module.
+pub fn parse_module(text: &str) -> Result<CompiledModule> {
+    match parse(text)? {
+        Unit::Module(module) => Ok(module),
+        Unit::Script(_) => anyhow::bail!("expected the assembly of a module, found a script"),
+    }
+}
+
+/// Parse the assembly of a script.
+pub fn parse_script(text: &str) -> Result<CompiledScript> {
+    match parse(text)? {
+        Unit::Script(script) => Ok(script),
+        Unit::Module(_) => anyhow::bail!("expected the assembly of a script, found a module"),
+    }
+}
+
+/// Assemble a module or script into a binary, in the bytecode version the assembly declares.
+pub fn assemble(text: &str) -> Result<Vec<u8>> {
+    let mut binary = vec![];
+    match parse(text)? {
+        Unit::Module(module) => module.serialize_for_version(Some(module.version), &mut binary)?,
+        Unit::Script(script) => script.serialize_for_version(Some(script.version), &mut binary)?,
+    }
+    Ok(binary)
+}
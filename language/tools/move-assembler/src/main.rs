@@ -0,0 +1,51 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![forbid(unsafe_code)]
+
+use clap::Parser;
+use move_command_line_common::files::{MOVE_ASSEMBLY_EXTENSION, MOVE_COMPILED_EXTENSION};
+use std::{fs, path::Path};
+
+#[derive(Debug, Parser)]
+#[clap(author, version, about)]
+struct Args {
+    /// The path to the assembly file to assemble, as printed by `move-disassembler --assembly`;
+    /// let's call it file.masm.
+    #[clap(short = 'i', long = "input")]
+    pub assembly_file_path: String,
+
+    /// (Optional) Path to write the binary to; defaults to file.mv next to the input.
+    #[clap(short = 'o', long = "output")]
+    pub output_file_path: Option<String>,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let assembly_path = Path::new(&args.assembly_file_path);
+    let extension = assembly_path
+        .extension()
+        .expect("Missing file extension for assembly file");
+    if extension != MOVE_ASSEMBLY_EXTENSION {
+        println!(
+            "Bad source file extension {:?}; expected {}",
+            extension, MOVE_ASSEMBLY_EXTENSION
+        );
+        std::process::exit(1);
+    }
+
+    let assembly = fs::read_to_string(assembly_path).expect("Unable to read assembly file");
+    let binary = match move_assembler::assemble(&assembly) {
+        Ok(binary) => binary,
+        Err(e) => {
+            println!("{}: {:#}", args.assembly_file_path, e);
+            std::process::exit(1);
+        }
+    };
+    let output_path = match &args.output_file_path {
+        Some(path) => Path::new(path).to_path_buf(),
+        None => assembly_path.with_extension(MOVE_COMPILED_EXTENSION),
+    };
+    fs::write(output_path, binary).expect("Unable to write bytecode file");
+}
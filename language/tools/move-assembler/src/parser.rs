@@ -0,0 +1,608 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::lexer::{tokenize, Tok};
+use anyhow::{anyhow, Result};
+use move_binary_format::file_format::{
+    Ability, AbilitySet, AddressIdentifierIndex, Bytecode, CodeUnit, CompiledModule,
+    CompiledScript, Constant, ConstantPoolIndex, FieldDefinition, FieldHandle, FieldHandleIndex,
+    FieldInstantiation, FieldInstantiationIndex, FunctionDefinition, FunctionHandle,
+    FunctionHandleIndex, FunctionInstantiation, FunctionInstantiationIndex, IdentifierIndex,
+    ModuleHandle, ModuleHandleIndex, Signature, SignatureIndex, SignatureToken,
+    StructDefInstantiation, StructDefInstantiationIndex, StructDefinition, StructDefinitionIndex,
+    StructFieldInformation, StructHandle, StructHandleIndex, StructTypeParameter, TableIndex,
+    TypeSignature, Visibility,
+};
+use move_core_types::{
+    account_address::AccountAddress, identifier::Identifier, metadata::Metadata, u256::U256,
+};
+use std::{collections::BTreeSet, str::FromStr};
+
+/// A module or script parsed from assembly.
+pub enum Unit {
+    Module(CompiledModule),
+    Script(CompiledScript),
+}
+
+pub fn parse(text: &str) -> Result<Unit> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        pos: 0,
+    };
+    let unit = if parser.eat_word("module") {
+        Unit::Module(parser.module()?)
+    } else if parser.eat_word("script") {
+        Unit::Script(parser.script()?)
+    } else {
+        return Err(parser.unexpected("`module` or `script`"));
+    };
+    if parser.pos < parser.tokens.len() {
+        return Err(parser.unexpected("end of input"));
+    }
+    Ok(unit)
+}
+
+struct Parser {
+    tokens: Vec<(Tok, usize)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Tok> {
+        self.tokens.get(self.pos).map(|(tok, _)| tok)
+    }
+
+    fn unexpected(&self, expected: &str) -> anyhow::Error {
+        match self.tokens.get(self.pos) {
+            Some((tok, line)) => anyhow!("line {}: expected {}, found {}", line, expected, tok),
+            None => anyhow!("expected {}, found end of input", expected),
+        }
+    }
+
+    fn error(&self, msg: String) -> anyhow::Error {
+        let line = self
+            .tokens
+            .get(self.pos.saturating_sub(1))
+            .map_or(0, |(_, line)| *line);
+        anyhow!("line {}: {}", line, msg)
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        if matches!(self.peek(), Some(Tok::Word(w)) if w == word) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_punct(&mut self, c: char) -> bool {
+        if self.peek() == Some(&Tok::Punct(c)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_word(&mut self, word: &str) -> Result<()> {
+        if self.eat_word(word) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{}`", word)))
+        }
+    }
+
+    fn expect_punct(&mut self, c: char) -> Result<()> {
+        if self.eat_punct(c) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{}`", c)))
+        }
+    }
+
+    fn word(&mut self) -> Result<String> {
+        match self.peek() {
+            Some(Tok::Word(w)) => {
+                let w = w.clone();
+                self.pos += 1;
+                Ok(w)
+            }
+            _ => Err(self.unexpected("a keyword")),
+        }
+    }
+
+    fn num<T: FromStr>(&mut self) -> Result<T> {
+        match self.peek() {
+            Some(Tok::Num(n)) => {
+                let n = n.clone();
+                self.pos += 1;
+                n.parse()
+                    .map_err(|_| self.error(format!("number `{}` is out of range", n)))
+            }
+            _ => Err(self.unexpected("a number")),
+        }
+    }
+
+    /// A number following `keyword`, as in `name 3`.
+    fn field<T: FromStr>(&mut self, keyword: &str) -> Result<T> {
+        self.expect_word(keyword)?;
+        self.num()
+    }
+
+    fn hex(&mut self) -> Result<String> {
+        match self.peek() {
+            Some(Tok::Hex(digits)) => {
+                let digits = digits.clone();
+                self.pos += 1;
+                Ok(digits)
+            }
+            _ => Err(self.unexpected("a hex literal")),
+        }
+    }
+
+    fn bytes(&mut self) -> Result<Vec<u8>> {
+        let digits = self.hex()?;
+        hex::decode(&digits).map_err(|e| self.error(format!("invalid byte string: {}", e)))
+    }
+
+    /// A comma separated list in brackets.
+    fn list<T>(&mut self, mut elem: impl FnMut(&mut Self) -> Result<T>) -> Result<Vec<T>> {
+        self.expect_punct('[')?;
+        let mut elems = vec![];
+        if self.eat_punct(']') {
+            return Ok(elems);
+        }
+        loop {
+            elems.push(elem(self)?);
+            if self.eat_punct(']') {
+                return Ok(elems);
+            }
+            self.expect_punct(',')?;
+        }
+    }
+
+    /// A table, whose entries are each prefixed by their index.
+    fn table<T>(&mut self, mut entry: impl FnMut(&mut Self) -> Result<T>) -> Result<Vec<T>> {
+        self.expect_punct('[')?;
+        let mut entries = vec![];
+        while !self.eat_punct(']') {
+            let idx: usize = self.num()?;
+            if idx != entries.len() {
+                return Err(self.error(format!(
+                    "entry {} is out of order, expected entry {}",
+                    idx,
+                    entries.len()
+                )));
+            }
+            self.expect_punct(':')?;
+            entries.push(entry(self)?);
+        }
+        Ok(entries)
+    }
+
+    fn module(&mut self) -> Result<CompiledModule> {
+        let mut module = CompiledModule {
+            version: self.field("version")?,
+            self_module_handle_idx: index(self.field("self_module_handle")?),
+            ..CompiledModule::default()
+        };
+        let mut seen = BTreeSet::new();
+        while self.pos < self.tokens.len() {
+            let name = self.word()?;
+            if !seen.insert(name.clone()) {
+                return Err(self.error(format!("duplicate table `{}`", name)));
+            }
+            match name.as_str() {
+                "module_handles" => module.module_handles = self.table(Self::module_handle)?,
+                "struct_handles" => module.struct_handles = self.table(Self::struct_handle)?,
+                "function_handles" => {
+                    module.function_handles = self.table(Self::function_handle)?
+                }
+                "field_handles" => module.field_handles = self.table(Self::field_handle)?,
+                "friend_decls" => module.friend_decls = self.table(Self::module_handle)?,
+                "struct_def_instantiations" => {
+                    module.struct_def_instantiations = self.table(Self::struct_def_inst)?
+                }
+                "function_instantiations" => {
+                    module.function_instantiations = self.table(Self::function_inst)?
+                }
+                "field_instantiations" => {
+                    module.field_instantiations = self.table(Self::field_inst)?
+                }
+                "signatures" => module.signatures = self.table(Self::signature)?,
+                "identifiers" => module.identifiers = self.table(Self::identifier)?,
+                "address_identifiers" => module.address_identifiers = self.table(Self::address)?,
+                "constant_pool" => module.constant_pool = self.table(Self::constant)?,
+                "metadata" => module.metadata = self.table(Self::metadata)?,
+                "struct_defs" => module.struct_defs = self.table(Self::struct_def)?,
+                "function_defs" => module.function_defs = self.table(Self::function_def)?,
+                _ => return Err(self.error(format!("unknown module table `{}`", name))),
+            }
+        }
+        Ok(module)
+    }
+
+    fn script(&mut self) -> Result<CompiledScript> {
+        let mut script = CompiledScript {
+            version: self.field("version")?,
+            ..CompiledScript::default()
+        };
+        let mut seen = BTreeSet::new();
+        loop {
+            let name = self.word()?;
+            if name == "main" {
+                break;
+            }
+            if !seen.insert(name.clone()) {
+                return Err(self.error(format!("duplicate table `{}`", name)));
+            }
+            match name.as_str() {
+                "module_handles" => script.module_handles = self.table(Self::module_handle)?,
+                "struct_handles" => script.struct_handles = self.table(Self::struct_handle)?,
+                "function_handles" => {
+                    script.function_handles = self.table(Self::function_handle)?
+                }
+                "function_instantiations" => {
+                    script.function_instantiations = self.table(Self::function_inst)?
+                }
+                "signatures" => script.signatures = self.table(Self::signature)?,
+                "identifiers" => script.identifiers = self.table(Self::identifier)?,
+                "address_identifiers" => script.address_identifiers = self.table(Self::address)?,
+                "constant_pool" => script.constant_pool = self.table(Self::constant)?,
+                "metadata" => script.metadata = self.table(Self::metadata)?,
+                _ => return Err(self.error(format!("unknown script table `{}`", name))),
+            }
+        }
+        self.expect_word("type_parameters")?;
+        script.type_parameters = self.list(Self::abilities)?;
+        script.parameters = index(self.field("parameters")?);
+        script.code = self.code()?;
+        Ok(script)
+    }
+
+    fn module_handle(&mut self) -> Result<ModuleHandle> {
+        Ok(ModuleHandle {
+            address: index(self.field("address")?),
+            name: index(self.field("name")?),
+        })
+    }
+
+    fn struct_handle(&mut self) -> Result<StructHandle> {
+        let module = index(self.field("module")?);
+        let name = index(self.field("name")?);
+        self.expect_word("abilities")?;
+        let abilities = self.abilities()?;
+        self.expect_word("type_parameters")?;
+        let type_parameters = self.list(|p| {
+            let is_phantom = p.eat_word("phantom");
+            Ok(StructTypeParameter {
+                constraints: p.abilities()?,
+                is_phantom,
+            })
+        })?;
+        Ok(StructHandle {
+            module,
+            name,
+            abilities,
+            type_parameters,
+        })
+    }
+
+    fn function_handle(&mut self) -> Result<FunctionHandle> {
+        let module = index(self.field("module")?);
+        let name = index(self.field("name")?);
+        let parameters = index(self.field("parameters")?);
+        let return_ = index(self.field("return")?);
+        self.expect_word("type_parameters")?;
+        Ok(FunctionHandle {
+            module,
+            name,
+            parameters,
+            return_,
+            type_parameters: self.list(Self::abilities)?,
+        })
+    }
+
+    fn field_handle(&mut self) -> Result<FieldHandle> {
+        Ok(FieldHandle {
+            owner: index(self.field("owner")?),
+            field: self.field("field")?,
+        })
+    }
+
+    fn struct_def_inst(&mut self) -> Result<StructDefInstantiation> {
+        Ok(StructDefInstantiation {
+            def: index(self.field("def")?),
+            type_parameters: index(self.field("type_parameters")?),
+        })
+    }
+
+    fn function_inst(&mut self) -> Result<FunctionInstantiation> {
+        Ok(FunctionInstantiation {
+            handle: index(self.field("handle")?),
+            type_parameters: index(self.field("type_parameters")?),
+        })
+    }
+
+    fn field_inst(&mut self) -> Result<FieldInstantiation> {
+        Ok(FieldInstantiation {
+            handle: index(self.field("handle")?),
+            type_parameters: index(self.field("type_parameters")?),
+        })
+    }
+
+    fn signature(&mut self) -> Result<Signature> {
+        Ok(Signature(self.list(Self::type_)?))
+    }
+
+    fn identifier(&mut self) -> Result<Identifier> {
+        match self.peek() {
+            Some(Tok::Str(s)) => {
+                let s = s.clone();
+                self.pos += 1;
+                Identifier::new(s.clone())
+                    .map_err(|_| self.error(format!("invalid identifier `{}`", s)))
+            }
+            _ => Err(self.unexpected("an identifier string")),
+        }
+    }
+
+    fn address(&mut self) -> Result<AccountAddress> {
+        let digits = self.hex()?;
+        AccountAddress::from_hex_literal(&format!("0x{}", digits))
+            .map_err(|e| self.error(format!("invalid address: {}", e)))
+    }
+
+    fn constant(&mut self) -> Result<Constant> {
+        Ok(Constant {
+            type_: self.type_()?,
+            data: self.bytes()?,
+        })
+    }
+
+    fn metadata(&mut self) -> Result<Metadata> {
+        self.expect_word("key")?;
+        let key = self.bytes()?;
+        self.expect_word("value")?;
+        Ok(Metadata {
+            key,
+            value: self.bytes()?,
+        })
+    }
+
+    fn struct_def(&mut self) -> Result<StructDefinition> {
+        let struct_handle = index(self.field("handle")?);
+        let field_information = if self.eat_word("native") {
+            StructFieldInformation::Native
+        } else {
+            self.expect_word("fields")?;
+            StructFieldInformation::Declared(self.list(|p| {
+                let name = index(p.num()?);
+                p.expect_punct(':')?;
+                Ok(FieldDefinition {
+                    name,
+                    signature: TypeSignature(p.type_()?),
+                })
+            })?)
+        };
+        Ok(StructDefinition {
+            struct_handle,
+            field_information,
+        })
+    }
+
+    fn function_def(&mut self) -> Result<FunctionDefinition> {
+        let function = index(self.field("handle")?);
+        let visibility = match self.word()?.as_str() {
+            "private" => Visibility::Private,
+            "public" => Visibility::Public,
+            "friend" => Visibility::Friend,
+            v => return Err(self.error(format!("unknown visibility `{}`", v))),
+        };
+        let is_entry = self.eat_word("entry");
+        self.expect_word("acquires")?;
+        let acquires_global_resources = self.list(|p| Ok(index(p.num()?)))?;
+        let code = if self.eat_word("native") {
+            None
+        } else {
+            Some(self.code()?)
+        };
+        Ok(FunctionDefinition {
+            function,
+            visibility,
+            is_entry,
+            acquires_global_resources,
+            code,
+        })
+    }
+
+    fn code(&mut self) -> Result<CodeUnit> {
+        let locals = index(self.field("locals")?);
+        self.expect_word("code")?;
+        Ok(CodeUnit {
+            locals,
+            code: self.table(Self::instruction)?,
+        })
+    }
+
+    fn abilities(&mut self) -> Result<AbilitySet> {
+        let abilities = self.list(|p| match p.word()?.as_str() {
+            "copy" => Ok(Ability::Copy),
+            "drop" => Ok(Ability::Drop),
+            "store" => Ok(Ability::Store),
+            "key" => Ok(Ability::Key),
+            a => Err(p.error(format!("unknown ability `{}`", a))),
+        })?;
+        Ok(abilities
+            .into_iter()
+            .fold(AbilitySet::EMPTY, |set, a| set | a))
+    }
+
+    fn type_(&mut self) -> Result<SignatureToken> {
+        if self.eat_punct('&') {
+            let is_mut = self.eat_word("mut");
+            let ty = Box::new(self.type_()?);
+            return Ok(if is_mut {
+                SignatureToken::MutableReference(ty)
+            } else {
+                SignatureToken::Reference(ty)
+            });
+        }
+        Ok(match self.word()?.as_str() {
+            "bool" => SignatureToken::Bool,
+            "u8" => SignatureToken::U8,
+            "u16" => SignatureToken::U16,
+            "u32" => SignatureToken::U32,
+            "u64" => SignatureToken::U64,
+            "u128" => SignatureToken::U128,
+            "u256" => SignatureToken::U256,
+            "address" => SignatureToken::Address,
+            "signer" => SignatureToken::Signer,
+            "vector" => {
+                self.expect_punct('<')?;
+                let elem = self.type_()?;
+                self.expect_punct('>')?;
+                SignatureToken::Vector(Box::new(elem))
+            }
+            "S" => {
+                self.expect_punct('#')?;
+                let idx = index(self.num()?);
+                if self.eat_punct('<') {
+                    let mut args = vec![self.type_()?];
+                    while self.eat_punct(',') {
+                        args.push(self.type_()?);
+                    }
+                    self.expect_punct('>')?;
+                    SignatureToken::StructInstantiation(idx, args)
+                } else {
+                    SignatureToken::Struct(idx)
+                }
+            }
+            "T" => {
+                self.expect_punct('#')?;
+                SignatureToken::TypeParameter(self.num()?)
+            }
+            ty => return Err(self.error(format!("unknown type `{}`", ty))),
+        })
+    }
+
+    fn instruction(&mut self) -> Result<Bytecode> {
+        use Bytecode::*;
+        let opcode = self.word()?;
+        Ok(match opcode.as_str() {
+            "Pop" => Pop,
+            "Ret" => Ret,
+            "BrTrue" => BrTrue(self.num()?),
+            "BrFalse" => BrFalse(self.num()?),
+            "Branch" => Branch(self.num()?),
+            "LdU8" => LdU8(self.num()?),
+            "LdU16" => LdU16(self.num()?),
+            "LdU32" => LdU32(self.num()?),
+            "LdU64" => LdU64(self.num()?),
+            "LdU128" => LdU128(self.num()?),
+            "LdU256" => LdU256(self.num::<U256>()?),
+            "CastU8" => CastU8,
+            "CastU16" => CastU16,
+            "CastU32" => CastU32,
+            "CastU64" => CastU64,
+            "CastU128" => CastU128,
+            "CastU256" => CastU256,
+            "LdConst" => LdConst(index(self.num()?)),
+            "LdTrue" => LdTrue,
+            "LdFalse" => LdFalse,
+            "CopyLoc" => CopyLoc(self.num()?),
+            "MoveLoc" => MoveLoc(self.num()?),
+            "StLoc" => StLoc(self.num()?),
+            "Call" => Call(index(self.num()?)),
+            "CallGeneric" => CallGeneric(index(self.num()?)),
+            "Pack" => Pack(index(self.num()?)),
+            "PackGeneric" => PackGeneric(index(self.num()?)),
+            "Unpack" => Unpack(index(self.num()?)),
+            "UnpackGeneric" => UnpackGeneric(index(self.num()?)),
+            "ReadRef" => ReadRef,
+            "WriteRef" => WriteRef,
+            "FreezeRef" => FreezeRef,
+            "MutBorrowLoc" => MutBorrowLoc(self.num()?),
+            "ImmBorrowLoc" => ImmBorrowLoc(self.num()?),
+            "MutBorrowField" => MutBorrowField(index(self.num()?)),
+            "MutBorrowFieldGeneric" => MutBorrowFieldGeneric(index(self.num()?)),
+            "ImmBorrowField" => ImmBorrowField(index(self.num()?)),
+            "ImmBorrowFieldGeneric" => ImmBorrowFieldGeneric(index(self.num()?)),
+            "MutBorrowGlobal" => MutBorrowGlobal(index(self.num()?)),
+            "MutBorrowGlobalGeneric" => MutBorrowGlobalGeneric(index(self.num()?)),
+            "ImmBorrowGlobal" => ImmBorrowGlobal(index(self.num()?)),
+            "ImmBorrowGlobalGeneric" => ImmBorrowGlobalGeneric(index(self.num()?)),
+            "Add" => Add,
+            "Sub" => Sub,
+            "Mul" => Mul,
+            "Mod" => Mod,
+            "Div" => Div,
+            "BitOr" => BitOr,
+            "BitAnd" => BitAnd,
+            "Xor" => Xor,
+            "Or" => Or,
+            "And" => And,
+            "Not" => Not,
+            "Eq" => Eq,
+            "Neq" => Neq,
+            "Lt" => Lt,
+            "Gt" => Gt,
+            "Le" => Le,
+            "Ge" => Ge,
+            "Abort" => Abort,
+            "Nop" => Nop,
+            "Exists" => Exists(index(self.num()?)),
+            "ExistsGeneric" => ExistsGeneric(index(self.num()?)),
+            "MoveFrom" => MoveFrom(index(self.num()?)),
+            "MoveFromGeneric" => MoveFromGeneric(index(self.num()?)),
+            "MoveTo" => MoveTo(index(self.num()?)),
+            "MoveToGeneric" => MoveToGeneric(index(self.num()?)),
+            "Shl" => Shl,
+            "Shr" => Shr,
+            "VecPack" => VecPack(index(self.num()?), self.num()?),
+            "VecLen" => VecLen(index(self.num()?)),
+            "VecImmBorrow" => VecImmBorrow(index(self.num()?)),
+            "VecMutBorrow" => VecMutBorrow(index(self.num()?)),
+            "VecPushBack" => VecPushBack(index(self.num()?)),
+            "VecPopBack" => VecPopBack(index(self.num()?)),
+            "VecUnpack" => VecUnpack(index(self.num()?), self.num()?),
+            "VecSwap" => VecSwap(index(self.num()?)),
+            _ => return Err(self.error(format!("unknown opcode `{}`", opcode))),
+        })
+    }
+}
+
+/// The index types of the file format, which all wrap a `TableIndex`.
+trait Index {
+    fn new(idx: TableIndex) -> Self;
+}
+
+macro_rules! impl_index {
+    ($($name:ident),*) => {
+        $(impl Index for $name {
+            fn new(idx: TableIndex) -> Self {
+                $name(idx)
+            }
+        })*
+    };
+}
+
+impl_index!(
+    ModuleHandleIndex,
+    StructHandleIndex,
+    FunctionHandleIndex,
+    FieldHandleIndex,
+    StructDefInstantiationIndex,
+    FunctionInstantiationIndex,
+    FieldInstantiationIndex,
+    IdentifierIndex,
+    AddressIdentifierIndex,
+    ConstantPoolIndex,
+    SignatureIndex,
+    StructDefinitionIndex
+);
+
+fn index<I: Index>(idx: TableIndex) -> I {
+    I::new(idx)
+}
@@ -0,0 +1,70 @@
+module 0x42::coin {
+    use std::vector;
+
+    friend 0x42::bank;
+
+    const ENOT_ENOUGH: u64 = 1;
+    const SYMBOL: vector<u8> = b"COIN";
+    const OWNER: address = @0x42;
+
+    struct Coin<phantom T> has key, store {
+        value: u64,
+    }
+
+    struct Pair<T: copy + drop, U> has copy, drop {
+        first: T,
+        second: U,
+    }
+
+    native fun hash(bytes: &vector<u8>): vector<u8>;
+
+    public fun value<T>(coin: &Coin<T>): u64 {
+        coin.value
+    }
+
+    public(friend) fun split<T>(coin: &mut Coin<T>, amount: u64): Coin<T> {
+        assert!(coin.value >= amount, ENOT_ENOUGH);
+        coin.value = coin.value - amount;
+        Coin { value: amount }
+    }
+
+    public fun publish<T>(account: &signer, value: u64) {
+        move_to(account, Coin<T> { value })
+    }
+
+    public fun balance<T>(owner: address): u64 acquires Coin {
+        if (exists<Coin<T>>(owner)) borrow_global<Coin<T>>(owner).value else 0
+    }
+
+    public fun sum(v: &vector<u64>): u128 {
+        let i = 0;
+        let sum = 0u128;
+        while (i < vector::length(v)) {
+            sum = sum + (*vector::borrow(v, i) as u128);
+            i = i + 1;
+        };
+        sum
+    }
+
+    public fun swap<T: copy + drop, U: copy + drop>(p: Pair<T, U>): Pair<U, T> {
+        let Pair { first, second } = p;
+        Pair { first: second, second: first }
+    }
+
+    entry fun symbol(): vector<u8> {
+        let v = vector[SYMBOL, hash(&SYMBOL)];
+        vector::pop_back(&mut v)
+    }
+
+    fun owner(): address {
+        OWNER
+    }
+}
+
+module 0x42::bank {
+    use 0x42::coin::{Self, Coin};
+
+    public fun withdraw<T>(coin: &mut Coin<T>): Coin<T> {
+        coin::split(coin, 10)
+    }
+}
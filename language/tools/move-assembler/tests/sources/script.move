@@ -0,0 +1,12 @@
+script {
+    use std::vector;
+
+    fun main<T: drop>(account: signer, amounts: vector<u64>, flag: bool) {
+        let total = 0;
+        while (!vector::is_empty(&amounts)) {
+            total = total + vector::pop_back(&mut amounts);
+        };
+        if (flag) abort total;
+        let _ = account;
+    }
+}
@@ -0,0 +1,28 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use move_binary_format::file_format::CompiledModule;
+use move_compiler::Compiler;
+use move_disassembler::assembly::module_assembly;
+
+#[test]
+fn stdlib_modules_roundtrip() {
+    let (_files, units) = Compiler::from_files(
+        move_stdlib::move_stdlib_files(),
+        vec![],
+        move_stdlib::move_stdlib_named_addresses(),
+    )
+    .build_and_report()
+    .unwrap();
+    assert!(!units.is_empty());
+    for unit in units {
+        let binary = unit.into_compiled_unit().serialize(None);
+        let assembly = module_assembly(&CompiledModule::deserialize(&binary).unwrap());
+        assert_eq!(
+            move_assembler::assemble(&assembly).unwrap(),
+            binary,
+            "{}",
+            assembly
+        );
+    }
+}
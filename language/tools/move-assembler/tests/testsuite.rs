@@ -0,0 +1,95 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use move_binary_format::{
+    file_format::{CompiledModule, CompiledScript},
+    file_format_common::{VERSION_5, VERSION_MAX},
+};
+use move_command_line_common::testing::EXP_EXT;
+use move_compiler::{
+    compiled_unit::CompiledUnit, diagnostics::report_diagnostics_to_buffer, Compiler,
+};
+use move_disassembler::assembly::{module_assembly, script_assembly};
+use move_prover_test_utils::baseline_test::verify_or_update_baseline;
+use std::path::Path;
+
+/// Print the assembly of `binary`, and check that assembling it gives back `binary`.
+fn roundtrip(binary: &[u8], is_script: bool) -> anyhow::Result<String> {
+    let assembly = if is_script {
+        script_assembly(&CompiledScript::deserialize(binary)?)
+    } else {
+        module_assembly(&CompiledModule::deserialize(binary)?)
+    };
+    let assembled = move_assembler::assemble(&assembly)?;
+    if assembled != binary {
+        anyhow::bail!(
+            "assembling\n{}\ndid not give back the original binary",
+            assembly
+        )
+    }
+    Ok(assembly)
+}
+
+fn roundtrip_runner(path: &Path) -> datatest_stable::Result<()> {
+    let (files, units) = Compiler::from_files(
+        vec![path.to_str().unwrap().to_string()],
+        move_stdlib::move_stdlib_files(),
+        move_stdlib::move_stdlib_named_addresses(),
+    )
+    .build()?;
+    let units = match units {
+        Ok((units, _warnings)) => units,
+        Err(diags) => {
+            return Err(
+                String::from_utf8_lossy(&report_diagnostics_to_buffer(&files, diags))
+                    .into_owned()
+                    .into(),
+            )
+        }
+    };
+    let mut out = String::new();
+    for unit in units {
+        let unit = unit.into_compiled_unit();
+        let is_script = matches!(unit, CompiledUnit::Script(_));
+        // The default version is printed; older versions must round trip as well.
+        out.push_str(&roundtrip(&unit.serialize(Some(VERSION_MAX)), is_script)?);
+        roundtrip(&unit.serialize(Some(VERSION_5)), is_script)?;
+        out.push('\n');
+    }
+    verify_or_update_baseline(path.with_extension(EXP_EXT).as_path(), &out)?;
+    Ok(())
+}
+
+/// Assemble hand-written assembly, and report whether the binary it gives is accepted by the
+/// deserializer.
+fn assemble_runner(path: &Path) -> datatest_stable::Result<()> {
+    let assembly = std::fs::read_to_string(path)?;
+    let out = match move_assembler::assemble(&assembly) {
+        Err(e) => format!("assembly error: {:#}\n", e),
+        Ok(binary) => {
+            let deserialized = match move_assembler::parse(&assembly)? {
+                move_assembler::Unit::Module(_) => CompiledModule::deserialize(&binary).map(|_| ()),
+                move_assembler::Unit::Script(_) => CompiledScript::deserialize(&binary).map(|_| ()),
+            };
+            match deserialized {
+                Ok(()) => format!("binary: 0x{}\n", hex::encode(&binary)),
+                Err(e) => format!(
+                    "binary: 0x{}\ndeserialization error: {:?}\n",
+                    hex::encode(&binary),
+                    e.major_status()
+                ),
+            }
+        }
+    };
+    verify_or_update_baseline(path.with_extension(EXP_EXT).as_path(), &out)?;
+    Ok(())
+}
+
+datatest_stable::harness!(
+    roundtrip_runner,
+    "tests/sources",
+    r".*\.move$",
+    assemble_runner,
+    "tests/sources",
+    r".*\.masm$"
+);
@@ -0,0 +1,246 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! The representation of a function while it is optimized, and the analyses the optimizations
+//! share.
+
+use move_binary_format::{
+    access::ModuleAccess,
+    binary_views::BinaryIndexedView,
+    file_format::{
+        AbilitySet, Bytecode, CodeOffset, CompiledModule, FunctionHandleIndex, LocalIndex,
+        SignatureToken, StructDefinitionIndex,
+    },
+};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// An instruction, together with the offset of the instruction in the original code it derives
+/// from, which is used to keep the source map up to date.
+#[derive(Clone, Debug)]
+pub struct Instr {
+    pub bytecode: Bytecode,
+    pub origin: CodeOffset,
+}
+
+/// Where a local of the optimized function comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LocalOrigin {
+    /// The local with this index in the original function.
+    Original(LocalIndex),
+    /// A local introduced by an optimization.
+    New,
+}
+
+/// A function being optimized.
+pub struct FunctionCode<'a> {
+    pub module: &'a CompiledModule,
+    pub handle: FunctionHandleIndex,
+    /// The abilities of the type parameters of the function.
+    pub type_parameters: &'a [AbilitySet],
+    /// The number of parameters, which are the first locals.
+    pub parameter_count: usize,
+    /// The types of the parameters and locals, with where each comes from.
+    pub locals: Vec<(SignatureToken, LocalOrigin)>,
+    pub code: Vec<Instr>,
+}
+
+impl<'a> FunctionCode<'a> {
+    pub fn add_local(&mut self, ty: SignatureToken) -> LocalIndex {
+        self.locals.push((ty, LocalOrigin::New));
+        (self.locals.len() - 1) as LocalIndex
+    }
+
+    pub fn local_abilities(&self, local: LocalIndex) -> AbilitySet {
+        BinaryIndexedView::Module(self.module)
+            .abilities(&self.locals[local as usize].0, self.type_parameters)
+            .unwrap_or(AbilitySet::EMPTY)
+    }
+
+    /// The locals which are borrowed somewhere in the function. Their value can change without
+    /// a store to them, so the optimizations leave them alone.
+    pub fn borrowed_locals(&self) -> BTreeSet<LocalIndex> {
+        self.code
+            .iter()
+            .filter_map(|instr| match instr.bytecode {
+                Bytecode::MutBorrowLoc(l) | Bytecode::ImmBorrowLoc(l) => Some(l),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The instructions control can reach right after the instruction at `pc`.
+    pub fn successors(&self, pc: usize) -> Vec<usize> {
+        match &self.code[pc].bytecode {
+            Bytecode::Ret | Bytecode::Abort => vec![],
+            Bytecode::Branch(target) => vec![*target as usize],
+            Bytecode::BrTrue(target) | Bytecode::BrFalse(target) => {
+                vec![pc + 1, *target as usize]
+            }
+            _ => vec![pc + 1],
+        }
+    }
+
+    /// Whether `pc` starts a basic block. The stack is empty at the start of every block.
+    pub fn block_starts(&self) -> Vec<bool> {
+        let mut starts = vec![false; self.code.len()];
+        if let Some(first) = starts.first_mut() {
+            *first = true;
+        }
+        for (pc, instr) in self.code.iter().enumerate() {
+            match &instr.bytecode {
+                Bytecode::Branch(target) | Bytecode::BrTrue(target) | Bytecode::BrFalse(target) => {
+                    starts[*target as usize] = true;
+                    if let Some(next) = starts.get_mut(pc + 1) {
+                        *next = true;
+                    }
+                }
+                Bytecode::Ret | Bytecode::Abort => {
+                    if let Some(next) = starts.get_mut(pc + 1) {
+                        *next = true;
+                    }
+                }
+                _ => (),
+            }
+        }
+        starts
+    }
+
+    /// The number of values `bytecode` pops off the stack and pushes onto it.
+    pub fn stack_effect(&self, bytecode: &Bytecode) -> (usize, usize) {
+        use Bytecode::*;
+        let module = self.module;
+        let call = |handle: FunctionHandleIndex| {
+            let handle = module.function_handle_at(handle);
+            (
+                module.signature_at(handle.parameters).len(),
+                module.signature_at(handle.return_).len(),
+            )
+        };
+        let field_count = |def: StructDefinitionIndex| {
+            module
+                .struct_def_at(def)
+                .declared_field_count()
+                .unwrap_or(0) as usize
+        };
+        match bytecode {
+            Nop | Branch(_) => (0, 0),
+            Pop | BrTrue(_) | BrFalse(_) | StLoc(_) | Abort => (1, 0),
+            Ret => (call(self.handle).1, 0),
+            LdU8(_) | LdU16(_) | LdU32(_) | LdU64(_) | LdU128(_) | LdU256(_) | LdConst(_)
+            | LdTrue | LdFalse | CopyLoc(_) | MoveLoc(_) | MutBorrowLoc(_) | ImmBorrowLoc(_) => {
+                (0, 1)
+            }
+            CastU8
+            | CastU16
+            | CastU32
+            | CastU64
+            | CastU128
+            | CastU256
+            | ReadRef
+            | FreezeRef
+            | Not
+            | MutBorrowField(_)
+            | MutBorrowFieldGeneric(_)
+            | ImmBorrowField(_)
+            | ImmBorrowFieldGeneric(_)
+            | MutBorrowGlobal(_)
+            | MutBorrowGlobalGeneric(_)
+            | ImmBorrowGlobal(_)
+            | ImmBorrowGlobalGeneric(_)
+            | Exists(_)
+            | ExistsGeneric(_)
+            | MoveFrom(_)
+            | MoveFromGeneric(_)
+            | VecLen(_)
+            | VecPopBack(_) => (1, 1),
+            Add | Sub | Mul | Mod | Div | BitOr | BitAnd | Xor | Shl | Shr | Or | And | Eq
+            | Neq | Lt | Gt | Le | Ge | VecImmBorrow(_) | VecMutBorrow(_) => (2, 1),
+            WriteRef | MoveTo(_) | MoveToGeneric(_) | VecPushBack(_) => (2, 0),
+            VecSwap(_) => (3, 0),
+            Call(handle) => call(*handle),
+            CallGeneric(inst) => call(module.function_instantiation_at(*inst).handle),
+            Pack(def) => (field_count(*def), 1),
+            PackGeneric(inst) => (field_count(module.struct_instantiation_at(*inst).def), 1),
+            Unpack(def) => (1, field_count(*def)),
+            UnpackGeneric(inst) => (1, field_count(module.struct_instantiation_at(*inst).def)),
+            VecPack(_, n) => (*n as usize, 1),
+            VecUnpack(_, n) => (1, *n as usize),
+        }
+    }
+
+    /// For every instruction, the instructions which pushed the values it pops, from the bottom
+    /// of the stack to the top. Values never cross basic blocks, so this is exact.
+    pub fn operands(&self) -> Vec<Vec<usize>> {
+        let starts = self.block_starts();
+        let mut stack: Vec<usize> = vec![];
+        let mut operands = vec![];
+        for (pc, instr) in self.code.iter().enumerate() {
+            if starts[pc] {
+                stack.clear();
+            }
+            let (pops, pushes) = self.stack_effect(&instr.bytecode);
+            let at = stack.len().saturating_sub(pops);
+            operands.push(stack.split_off(at));
+            stack.extend(std::iter::repeat(pc).take(pushes));
+        }
+        operands
+    }
+
+    /// Solve a forward dataflow problem whose facts are sets which are intersected where control
+    /// flow joins. Returns the facts holding before each instruction; there are none for
+    /// unreachable instructions.
+    pub fn forward_must<T: Ord + Clone>(
+        &self,
+        transfer: impl Fn(usize, &mut BTreeSet<T>),
+    ) -> Vec<Option<BTreeSet<T>>> {
+        let mut facts: Vec<Option<BTreeSet<T>>> = vec![None; self.code.len()];
+        if self.code.is_empty() {
+            return facts;
+        }
+        facts[0] = Some(BTreeSet::new());
+        let mut worklist = vec![0];
+        while let Some(pc) = worklist.pop() {
+            let mut out = facts[pc].clone().unwrap();
+            transfer(pc, &mut out);
+            for succ in self.successors(pc) {
+                let joined = match &facts[succ] {
+                    None => out.clone(),
+                    Some(old) => old.intersection(&out).cloned().collect(),
+                };
+                if facts[succ].as_ref() != Some(&joined) {
+                    facts[succ] = Some(joined);
+                    worklist.push(succ);
+                }
+            }
+        }
+        facts
+    }
+
+    /// Replace the instructions in `edits` by the given sequences, which inherit their origin,
+    /// and adjust branch targets. A branch to a removed instruction goes to the instruction
+    /// following it.
+    pub fn rewrite(&mut self, edits: BTreeMap<usize, Vec<Bytecode>>) {
+        let mut new_offsets = Vec::with_capacity(self.code.len() + 1);
+        let mut code = vec![];
+        for (pc, instr) in std::mem::take(&mut self.code).into_iter().enumerate() {
+            new_offsets.push(code.len() as CodeOffset);
+            match edits.get(&pc) {
+                Some(replacement) => code.extend(replacement.iter().map(|bytecode| Instr {
+                    bytecode: bytecode.clone(),
+                    origin: instr.origin,
+                })),
+                None => code.push(instr),
+            }
+        }
+        new_offsets.push(code.len() as CodeOffset);
+        for instr in &mut code {
+            match &mut instr.bytecode {
+                Bytecode::Branch(target) | Bytecode::BrTrue(target) | Bytecode::BrFalse(target) => {
+                    *target = new_offsets[*target as usize]
+                }
+                _ => (),
+            }
+        }
+        self.code = code;
+    }
+}
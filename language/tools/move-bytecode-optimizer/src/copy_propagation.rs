@@ -0,0 +1,65 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+// Replaces reads of a local holding a copy of another local by reads of the other local, which
+// often makes the copy dead.
+
+use crate::code::FunctionCode;
+use move_binary_format::file_format::{Bytecode, LocalIndex};
+use std::collections::{BTreeMap, BTreeSet};
+
+pub fn optimize(func: &mut FunctionCode) -> bool {
+    let borrowed = func.borrowed_locals();
+    let starts = func.block_starts();
+    // The copy `CopyLoc(x); StLoc(y)` stored by the `StLoc` at each offset, as `(y, x)`.
+    let copies = (1..func.code.len())
+        .filter(|pc| !starts[*pc])
+        .filter_map(
+            |pc| match (&func.code[pc - 1].bytecode, &func.code[pc].bytecode) {
+                (Bytecode::CopyLoc(x), Bytecode::StLoc(y))
+                    if x != y && !borrowed.contains(x) && !borrowed.contains(y) =>
+                {
+                    Some((pc, (*y, *x)))
+                }
+                _ => None,
+            },
+        )
+        .collect::<BTreeMap<_, _>>();
+    if copies.is_empty() {
+        return false;
+    }
+
+    // The copies `(y, x)` which hold on all paths to an instruction: `y` was assigned a copy of
+    // `x`, and neither was assigned or moved since.
+    let available = func.forward_must(|pc, facts: &mut BTreeSet<(LocalIndex, LocalIndex)>| {
+        match &func.code[pc].bytecode {
+            Bytecode::StLoc(l) | Bytecode::MoveLoc(l) => {
+                facts.retain(|(y, x)| y != l && x != l);
+            }
+            _ => (),
+        }
+        if let Some(copy) = copies.get(&pc) {
+            facts.insert(*copy);
+        }
+    });
+
+    let mut edits = BTreeMap::new();
+    for (pc, instr) in func.code.iter().enumerate() {
+        let facts = match &available[pc] {
+            Some(facts) => facts,
+            None => continue,
+        };
+        let (Bytecode::CopyLoc(l) | Bytecode::MoveLoc(l)) = &instr.bytecode else {
+            continue;
+        };
+        if let Some((_, x)) = facts.iter().find(|(y, _)| y == l) {
+            // Reading `x` instead of moving `y` leaves `y` in place, which is fine as its value
+            // is a copy, and therefore droppable unless the type is a copyable resource. The
+            // verifier rejects the function in that case, and the optimization is undone.
+            edits.insert(pc, vec![Bytecode::CopyLoc(*x)]);
+        }
+    }
+    let changed = !edits.is_empty();
+    func.rewrite(edits);
+    changed
+}
@@ -0,0 +1,226 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+// Eliminates common subexpressions across basic blocks: when an arithmetic or comparison
+// expression over locals and constants was computed on every path to a place where it is
+// computed again, and none of its locals was assigned since, its value is saved in a new local
+// where it is first computed, and read from that local instead of being recomputed.
+
+use crate::code::FunctionCode;
+use move_binary_format::{
+    access::ModuleAccess,
+    file_format::{Bytecode, LocalIndex, SignatureToken},
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// An occurrence of an expression, which is the code `start..=root`.
+struct Occurrence {
+    start: usize,
+    root: usize,
+    expression: usize,
+}
+
+pub fn optimize(func: &mut FunctionCode) -> bool {
+    let borrowed = func.borrowed_locals();
+    let operands = func.operands();
+
+    // Find all occurrences of expressions with at least one operator, whose code is contiguous,
+    // so that no other code runs while they are evaluated.
+    let mut tree_start: Vec<Option<usize>> = vec![None; func.code.len()];
+    let mut expressions: HashMap<Vec<Bytecode>, usize> = HashMap::new();
+    let mut occurrences = vec![];
+    for (pc, instr) in func.code.iter().enumerate() {
+        if is_leaf(func, &instr.bytecode, &borrowed) {
+            tree_start[pc] = Some(pc);
+            continue;
+        }
+        if !is_operator(&instr.bytecode) {
+            continue;
+        }
+        let mut next = pc;
+        for operand in operands[pc].iter().rev() {
+            match tree_start[*operand] {
+                Some(start) if *operand + 1 == next => next = start,
+                _ => {
+                    next = usize::MAX;
+                    break;
+                }
+            }
+        }
+        if next == usize::MAX || operands[pc].is_empty() {
+            continue;
+        }
+        tree_start[pc] = Some(next);
+        // A local is read the same way whether it is copied or moved.
+        let key = func.code[next..=pc]
+            .iter()
+            .map(|instr| match instr.bytecode {
+                Bytecode::MoveLoc(l) => Bytecode::CopyLoc(l),
+                ref bytecode => bytecode.clone(),
+            })
+            .collect::<Vec<_>>();
+        let count = expressions.len();
+        let expression = *expressions.entry(key).or_insert(count);
+        occurrences.push(Occurrence {
+            start: next,
+            root: pc,
+            expression,
+        });
+    }
+    if occurrences.is_empty() {
+        return false;
+    }
+    let mut keys = vec![vec![]; expressions.len()];
+    for (key, expression) in expressions {
+        keys[expression] = key;
+    }
+    let roots = occurrences
+        .iter()
+        .map(|occ| (occ.root, occ.expression))
+        .collect::<BTreeMap<_, _>>();
+
+    // The expressions which have been computed on all paths to an instruction, with none of
+    // their locals assigned since.
+    let available = func.forward_must(|pc, facts: &mut BTreeSet<usize>| {
+        if let Bytecode::StLoc(l) | Bytecode::MoveLoc(l) = &func.code[pc].bytecode {
+            facts.retain(|expression| !keys[*expression].contains(&Bytecode::CopyLoc(*l)));
+        }
+        if let Some(expression) = roots.get(&pc) {
+            facts.insert(*expression);
+        }
+    });
+
+    // Pick the expression whose elimination saves the most instructions. Eliminating one
+    // expression at a time keeps the occurrences of the others intact, which their
+    // availability relies on.
+    let mut redundant: BTreeMap<usize, Vec<&Occurrence>> = BTreeMap::new();
+    let mut computed: BTreeMap<usize, Vec<&Occurrence>> = BTreeMap::new();
+    for occ in &occurrences {
+        let is_redundant = available[occ.start]
+            .as_ref()
+            .map_or(false, |facts| facts.contains(&occ.expression));
+        let occs = if is_redundant {
+            &mut redundant
+        } else {
+            &mut computed
+        };
+        occs.entry(occ.expression).or_default().push(occ);
+    }
+    let best = redundant
+        .iter()
+        .map(|(expression, occs)| {
+            let size = keys[*expression].len() as isize;
+            // Every computation saves the value with `StLoc`, `CopyLoc`.
+            let cost = 2 * computed.get(expression).map_or(0, |occs| occs.len()) as isize;
+            (occs.len() as isize * (size - 1) - cost, *expression)
+        })
+        .max();
+    let expression = match best {
+        Some((saving, expression)) if saving > 0 => expression,
+        _ => return false,
+    };
+    if func.locals.len() > LocalIndex::MAX as usize {
+        return false;
+    }
+    let ty = match expression_type(func, &keys[expression]) {
+        Some(ty) => ty,
+        None => return false,
+    };
+    let temp = func.add_local(ty);
+
+    let mut edits = BTreeMap::new();
+    for occ in computed.get(&expression).into_iter().flatten() {
+        edits.insert(
+            occ.root,
+            vec![
+                func.code[occ.root].bytecode.clone(),
+                Bytecode::StLoc(temp),
+                Bytecode::CopyLoc(temp),
+            ],
+        );
+    }
+    for occ in &redundant[&expression] {
+        for pc in occ.start..occ.root {
+            edits.insert(pc, vec![]);
+        }
+        edits.insert(occ.root, vec![Bytecode::CopyLoc(temp)]);
+    }
+    func.rewrite(edits);
+    true
+}
+
+/// Constants, and reads of locals which are not borrowed. A local which is moved must be
+/// droppable, as it keeps its value when the read is eliminated.
+fn is_leaf(func: &FunctionCode, bytecode: &Bytecode, borrowed: &BTreeSet<LocalIndex>) -> bool {
+    use Bytecode::*;
+    match bytecode {
+        LdU8(_) | LdU16(_) | LdU32(_) | LdU64(_) | LdU128(_) | LdU256(_) | LdConst(_) | LdTrue
+        | LdFalse => true,
+        CopyLoc(l) => !borrowed.contains(l),
+        MoveLoc(l) => !borrowed.contains(l) && func.local_abilities(*l).has_drop(),
+        _ => false,
+    }
+}
+
+/// Operators whose result only depends on their operands. Some of them abort, but only when
+/// every computation with the same operands does.
+fn is_operator(bytecode: &Bytecode) -> bool {
+    use Bytecode::*;
+    matches!(
+        bytecode,
+        Add | Sub
+            | Mul
+            | Mod
+            | Div
+            | BitOr
+            | BitAnd
+            | Xor
+            | Shl
+            | Shr
+            | Or
+            | And
+            | Not
+            | Eq
+            | Neq
+            | Lt
+            | Gt
+            | Le
+            | Ge
+            | CastU8
+            | CastU16
+            | CastU32
+            | CastU64
+            | CastU128
+            | CastU256
+    )
+}
+
+/// The type of the value of the expression `code`.
+fn expression_type(func: &FunctionCode, code: &[Bytecode]) -> Option<SignatureToken> {
+    use Bytecode::*;
+    let mut stack = vec![];
+    for bytecode in code {
+        let (pops, _) = func.stack_effect(bytecode);
+        let operands = stack.split_off(stack.len().checked_sub(pops)?);
+        stack.push(match bytecode {
+            LdU8(_) | CastU8 => SignatureToken::U8,
+            LdU16(_) | CastU16 => SignatureToken::U16,
+            LdU32(_) | CastU32 => SignatureToken::U32,
+            LdU64(_) | CastU64 => SignatureToken::U64,
+            LdU128(_) | CastU128 => SignatureToken::U128,
+            LdU256(_) | CastU256 => SignatureToken::U256,
+            LdTrue | LdFalse | Or | And | Not | Eq | Neq | Lt | Gt | Le | Ge => {
+                SignatureToken::Bool
+            }
+            LdConst(idx) => func.module.constant_at(*idx).type_.clone(),
+            CopyLoc(l) => func.locals[*l as usize].0.clone(),
+            // The result has the type of the left operand, which is also the type of the right
+            // operand except for shifts.
+            Add | Sub | Mul | Mod | Div | BitOr | BitAnd | Xor | Shl | Shr => {
+                operands.into_iter().next()?
+            }
+            _ => return None,
+        });
+    }
+    stack.pop()
+}
@@ -0,0 +1,96 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+// Removes stores to locals which are not read afterwards, and values which are computed only to
+// be popped off the stack.
+
+use crate::code::FunctionCode;
+use move_binary_format::file_format::{Bytecode, LocalIndex};
+use std::collections::{BTreeMap, BTreeSet};
+
+pub fn optimize(func: &mut FunctionCode) -> bool {
+    let borrowed = func.borrowed_locals();
+    let live_after = live_after(func);
+    let operands = func.operands();
+    let mut edits = BTreeMap::new();
+    for (pc, instr) in func.code.iter().enumerate() {
+        let drops_value = match &instr.bytecode {
+            Bytecode::Pop => true,
+            Bytecode::StLoc(l) => {
+                !borrowed.contains(l)
+                    && !live_after[pc].contains(l)
+                    && func.local_abilities(*l).has_drop()
+            }
+            _ => false,
+        };
+        if !drops_value {
+            continue;
+        }
+        match operands[pc].as_slice() {
+            // The value has no other use, and computing it has no effect.
+            [producer] if *producer + 1 == pc && is_pure_leaf(&func.code[*producer].bytecode) => {
+                edits.insert(*producer, vec![]);
+                edits.insert(pc, vec![]);
+            }
+            _ if matches!(instr.bytecode, Bytecode::StLoc(_)) => {
+                edits.insert(pc, vec![Bytecode::Pop]);
+            }
+            _ => (),
+        }
+    }
+    let changed = !edits.is_empty();
+    func.rewrite(edits);
+    changed
+}
+
+/// Instructions which push a value without reading the stack or having any other effect.
+fn is_pure_leaf(bytecode: &Bytecode) -> bool {
+    use Bytecode::*;
+    matches!(
+        bytecode,
+        LdU8(_)
+            | LdU16(_)
+            | LdU32(_)
+            | LdU64(_)
+            | LdU128(_)
+            | LdU256(_)
+            | LdConst(_)
+            | LdTrue
+            | LdFalse
+            | CopyLoc(_)
+    )
+}
+
+/// The locals which may be read after each instruction before they are stored to again.
+fn live_after(func: &FunctionCode) -> Vec<BTreeSet<LocalIndex>> {
+    let mut live_before: Vec<BTreeSet<LocalIndex>> = vec![BTreeSet::new(); func.code.len()];
+    let mut live_after = live_before.clone();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for pc in (0..func.code.len()).rev() {
+            let mut live = BTreeSet::new();
+            for succ in func.successors(pc) {
+                live.extend(live_before[succ].iter().copied());
+            }
+            live_after[pc] = live.clone();
+            match &func.code[pc].bytecode {
+                Bytecode::StLoc(l) => {
+                    live.remove(l);
+                }
+                Bytecode::CopyLoc(l)
+                | Bytecode::MoveLoc(l)
+                | Bytecode::MutBorrowLoc(l)
+                | Bytecode::ImmBorrowLoc(l) => {
+                    live.insert(*l);
+                }
+                _ => (),
+            }
+            if live != live_before[pc] {
+                live_before[pc] = live;
+                changed = true;
+            }
+        }
+    }
+    live_after
+}
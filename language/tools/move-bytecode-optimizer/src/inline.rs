@@ -0,0 +1,125 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+// Inlines calls of small private functions of the same module whose body is a single basic
+// block. The arguments are stored in new locals of the caller, which take the place of the
+// callee's parameters and locals, and the results stay on the stack, as after the call.
+
+use crate::code::FunctionCode;
+use move_binary_format::{
+    access::ModuleAccess,
+    file_format::{Bytecode, FunctionDefinition, LocalIndex, SignatureToken, Visibility},
+};
+use std::collections::BTreeMap;
+
+/// The maximum number of instructions of a function which is inlined, not counting its `Ret`.
+pub const INLINE_SIZE_LIMIT: usize = 8;
+
+pub fn optimize(func: &mut FunctionCode) -> bool {
+    let module = func.module;
+    // The first of the locals of the caller which hold the locals of each inlined callee.
+    let mut bases: BTreeMap<usize, LocalIndex> = BTreeMap::new();
+    let mut edits = BTreeMap::new();
+    for pc in 0..func.code.len() {
+        let handle = match &func.code[pc].bytecode {
+            Bytecode::Call(handle) if *handle != func.handle => *handle,
+            _ => continue,
+        };
+        let def_idx = match module
+            .function_defs
+            .iter()
+            .position(|def| def.function == handle)
+        {
+            Some(def_idx) => def_idx,
+            None => continue,
+        };
+        let callee = &module.function_defs[def_idx];
+        let body = match inlinable_body(callee) {
+            Some(body) => body,
+            None => continue,
+        };
+        let callee_handle = module.function_handle_at(handle);
+        let parameters = &module.signature_at(callee_handle.parameters).0;
+        let callee_locals = parameters
+            .iter()
+            .chain(&module.signature_at(callee.code.as_ref().unwrap().locals).0)
+            .cloned()
+            .collect::<Vec<_>>();
+        let base = match bases.get(&def_idx) {
+            Some(base) => *base,
+            None => {
+                if func.locals.len() + callee_locals.len() > LocalIndex::MAX as usize + 1 {
+                    continue;
+                }
+                let base = func.locals.len() as LocalIndex;
+                for ty in &callee_locals {
+                    func.add_local(ty.clone());
+                }
+                bases.insert(def_idx, base);
+                base
+            }
+        };
+
+        let local = |l: &LocalIndex| base + *l;
+        let mut code = (0..parameters.len())
+            .rev()
+            .map(|p| Bytecode::StLoc(base + p as LocalIndex))
+            .collect::<Vec<_>>();
+        let mut holds_value = (0..callee_locals.len())
+            .map(|l| l < parameters.len())
+            .collect::<Vec<_>>();
+        for bytecode in body {
+            code.push(match bytecode {
+                Bytecode::CopyLoc(l) => Bytecode::CopyLoc(local(l)),
+                Bytecode::MoveLoc(l) => {
+                    holds_value[*l as usize] = false;
+                    Bytecode::MoveLoc(local(l))
+                }
+                Bytecode::StLoc(l) => {
+                    holds_value[*l as usize] = true;
+                    Bytecode::StLoc(local(l))
+                }
+                Bytecode::MutBorrowLoc(l) => Bytecode::MutBorrowLoc(local(l)),
+                Bytecode::ImmBorrowLoc(l) => Bytecode::ImmBorrowLoc(local(l)),
+                bytecode => bytecode.clone(),
+            });
+        }
+        // References left in locals would keep what they borrow borrowed in the caller, while
+        // they went away with the callee's frame before.
+        for (l, ty) in callee_locals.iter().enumerate() {
+            if holds_value[l]
+                && matches!(
+                    ty,
+                    SignatureToken::Reference(_) | SignatureToken::MutableReference(_)
+                )
+            {
+                code.push(Bytecode::MoveLoc(base + l as LocalIndex));
+                code.push(Bytecode::Pop);
+            }
+        }
+        edits.insert(pc, code);
+    }
+    let changed = !edits.is_empty();
+    func.rewrite(edits);
+    changed
+}
+
+/// The code of `def` without its final `Ret`, if it is a small private function whose body is
+/// a single basic block.
+fn inlinable_body(def: &FunctionDefinition) -> Option<&[Bytecode]> {
+    if def.visibility != Visibility::Private {
+        return None;
+    }
+    let (last, body) = def.code.as_ref()?.code.split_last()?;
+    let single_block = body.iter().all(|bytecode| {
+        !matches!(
+            bytecode,
+            Bytecode::Ret
+                | Bytecode::Abort
+                | Bytecode::Branch(_)
+                | Bytecode::BrTrue(_)
+                | Bytecode::BrFalse(_)
+        )
+    });
+    (*last == Bytecode::Ret && single_block && body.len() <= INLINE_SIZE_LIMIT).then_some(body)
+}
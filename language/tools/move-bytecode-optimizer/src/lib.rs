@@ -0,0 +1,266 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! An optimizer for the bytecode of whole modules, which runs after compilation.
+//!
+//! The compiler only performs peephole optimizations on the code it generates. This optimizer
+//! works on the verified bytecode of a module, and can therefore look across basic blocks and
+//! functions: it inlines small private functions, and then propagates copies, eliminates
+//! common subexpressions, and removes dead stores until the code does not change anymore.
+//!
+//! Every optimized function is verified again, together with the rest of its module. A function
+//! keeps its original code if its optimized code is rejected by the bytecode verifier, or is not
+//! an improvement: the optimized code must have a lower static gas cost, or the same cost with
+//! fewer instructions. Code which is cheaper to run is kept even if it has more instructions, as
+//! after inlining.
+
+mod code;
+mod copy_propagation;
+mod cse;
+mod dead_stores;
+mod inline;
+mod unused_locals;
+
+use code::{FunctionCode, Instr, LocalOrigin};
+use move_binary_format::{
+    access::ModuleAccess,
+    file_format::{
+        Bytecode, CodeOffset, CodeUnit, CompiledModule, FunctionDefinitionIndex, LocalIndex,
+        Signature, SignatureIndex, TableIndex,
+    },
+    file_format_common::instruction_key,
+};
+use move_bytecode_source_map::source_map::{FunctionSourceMap, SourceMap};
+use move_vm_test_utils::gas_schedule::INITIAL_COST_SCHEDULE;
+
+type Optimization = fn(&mut FunctionCode) -> bool;
+
+const OPTIMIZATIONS: &[Optimization] = &[
+    copy_propagation::optimize,
+    cse::optimize,
+    dead_stores::optimize,
+];
+
+/// How the optimization of a function changed its code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FunctionReport {
+    pub function: String,
+    pub instructions_before: usize,
+    pub instructions_after: usize,
+    pub gas_before: u64,
+    pub gas_after: u64,
+}
+
+impl std::fmt::Display for FunctionReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} -> {} instructions ({:+}), static gas {} -> {} ({:+})",
+            self.function,
+            self.instructions_before,
+            self.instructions_after,
+            self.instructions_after as i64 - self.instructions_before as i64,
+            self.gas_before,
+            self.gas_after,
+            self.gas_after as i64 - self.gas_before as i64
+        )
+    }
+}
+
+/// The gas it costs to execute each instruction of `code` once, according to the default
+/// cost schedule.
+pub fn static_gas(code: &[Bytecode]) -> u64 {
+    code.iter()
+        .map(|bytecode| {
+            INITIAL_COST_SCHEDULE
+                .instruction_cost(instruction_key(bytecode))
+                .total()
+        })
+        .sum()
+}
+
+/// Optimize the functions of `module` and update `source_map` accordingly. Returns a report for
+/// every function whose code changed. A module which does not verify is left alone.
+pub fn optimize_module(
+    module: &mut CompiledModule,
+    source_map: &mut SourceMap,
+) -> Vec<FunctionReport> {
+    let mut reports = vec![];
+    if move_bytecode_verifier::verify_module(module).is_err() {
+        return reports;
+    }
+    for def_idx in 0..module.function_defs.len() {
+        let optimized = match optimize_function(module, def_idx) {
+            Some(optimized) => optimized,
+            None => continue,
+        };
+        let def = &module.function_defs[def_idx];
+        let original = &def.code.as_ref().unwrap().code;
+        let gas_before = static_gas(original);
+        let gas_after = static_gas(&optimized.bytecode);
+        // Static gas comes first, and the number of instructions only breaks ties.
+        if (gas_after, optimized.bytecode.len()) >= (gas_before, original.len()) {
+            continue;
+        }
+        let report = FunctionReport {
+            function: format!(
+                "{}::{}",
+                module.self_id(),
+                module.identifier_at(module.function_handle_at(def.function).name)
+            ),
+            instructions_before: original.len(),
+            instructions_after: optimized.bytecode.len(),
+            gas_before,
+            gas_after,
+        };
+
+        let mut candidate = module.clone();
+        let locals = signature_index(&mut candidate, optimized.locals);
+        candidate.function_defs[def_idx].code = Some(CodeUnit {
+            locals,
+            code: optimized.bytecode,
+        });
+        if move_bytecode_verifier::verify_module(&candidate).is_err() {
+            continue;
+        }
+        *module = candidate;
+        if let Ok(function_map) =
+            source_map.get_function_source_map_mut(FunctionDefinitionIndex(def_idx as TableIndex))
+        {
+            update_source_map(function_map, &optimized.code, &optimized.local_origins);
+        }
+        reports.push(report);
+    }
+    reports
+}
+
+/// The optimized code of a function.
+struct OptimizedFunction {
+    /// The instructions, with the offsets of the original instructions they derive from.
+    code: Vec<Instr>,
+    bytecode: Vec<Bytecode>,
+    /// The types of the locals which are not parameters, and where each comes from.
+    locals: Signature,
+    local_origins: Vec<LocalOrigin>,
+}
+
+/// Optimize the function defined at `def_idx`. Returns `None` if the function is native or its
+/// code did not change.
+fn optimize_function(module: &CompiledModule, def_idx: usize) -> Option<OptimizedFunction> {
+    let def = &module.function_defs[def_idx];
+    let code = def.code.as_ref()?;
+    let handle = module.function_handle_at(def.function);
+    let parameters = &module.signature_at(handle.parameters).0;
+    let mut func = FunctionCode {
+        module,
+        handle: def.function,
+        type_parameters: &handle.type_parameters,
+        parameter_count: parameters.len(),
+        locals: parameters
+            .iter()
+            .chain(&module.signature_at(code.locals).0)
+            .cloned()
+            .enumerate()
+            .map(|(l, ty)| (ty, LocalOrigin::Original(l as LocalIndex)))
+            .collect(),
+        code: code
+            .code
+            .iter()
+            .enumerate()
+            .map(|(offset, bytecode)| Instr {
+                bytecode: bytecode.clone(),
+                origin: offset as CodeOffset,
+            })
+            .collect(),
+    };
+
+    let mut changed = inline::optimize(&mut func);
+    let mut count = 0;
+    for optimization in OPTIMIZATIONS.iter().cycle() {
+        // if we have fully cycled through the list of optimizations without a change,
+        // it is safe to stop
+        if count >= OPTIMIZATIONS.len() {
+            break;
+        }
+        if optimization(&mut func) {
+            changed = true;
+            count = 0
+        } else {
+            count += 1
+        }
+    }
+    changed |= unused_locals::optimize(&mut func);
+    if !changed {
+        return None;
+    }
+
+    let (local_types, local_origins) = func.locals.into_iter().skip(func.parameter_count).unzip();
+    Some(OptimizedFunction {
+        bytecode: func
+            .code
+            .iter()
+            .map(|instr| instr.bytecode.clone())
+            .collect(),
+        code: func.code,
+        locals: Signature(local_types),
+        local_origins,
+    })
+}
+
+/// The index of `signature` in the signature pool of `module`, which is added to the pool if it
+/// is not in there yet.
+fn signature_index(module: &mut CompiledModule, signature: Signature) -> SignatureIndex {
+    match module.signatures.iter().position(|s| s == &signature) {
+        Some(idx) => SignatureIndex(idx as TableIndex),
+        None => {
+            module.signatures.push(signature);
+            SignatureIndex((module.signatures.len() - 1) as TableIndex)
+        }
+    }
+}
+
+/// Map the optimized code and locals of a function back to the source, through the original
+/// instructions and locals they derive from.
+fn update_source_map(
+    function_map: &mut FunctionSourceMap,
+    code: &[Instr],
+    local_origins: &[LocalOrigin],
+) {
+    let original = std::mem::replace(
+        function_map,
+        FunctionSourceMap {
+            definition_location: function_map.definition_location,
+            type_parameters: function_map.type_parameters.clone(),
+            parameters: function_map.parameters.clone(),
+            locals: vec![],
+            nops: Default::default(),
+            code_map: Default::default(),
+            is_native: function_map.is_native,
+        },
+    );
+    for (offset, instr) in code.iter().enumerate() {
+        if let Some(loc) = original.get_code_location(instr.origin) {
+            function_map.add_code_mapping(offset as CodeOffset, loc);
+        }
+    }
+    let parameter_count = original.parameters.len();
+    let mut new_locals = 0;
+    for origin in local_origins {
+        let name = match origin {
+            LocalOrigin::Original(l) => original.locals[*l as usize - parameter_count].clone(),
+            LocalOrigin::New => {
+                new_locals += 1;
+                (format!("%#opt{}", new_locals), original.definition_location)
+            }
+        };
+        function_map.add_local_mapping(name);
+    }
+    for (label, old_offset) in &original.nops {
+        let new_offset = code.iter().position(|instr| {
+            instr.origin == *old_offset && matches!(instr.bytecode, Bytecode::Nop)
+        });
+        if let Some(new_offset) = new_offset {
+            function_map.add_nop_mapping(label.clone(), new_offset as CodeOffset);
+        }
+    }
+}
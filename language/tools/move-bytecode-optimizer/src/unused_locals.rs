@@ -0,0 +1,49 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+// Removes locals which are not used anymore, which are most likely left behind by other
+// optimizations.
+
+use crate::code::FunctionCode;
+use move_binary_format::file_format::{Bytecode, LocalIndex};
+
+pub fn optimize(func: &mut FunctionCode) -> bool {
+    // Parameters stay, as they are part of the signature of the function.
+    let mut used = (0..func.locals.len())
+        .map(|l| l < func.parameter_count)
+        .collect::<Vec<_>>();
+    for instr in &func.code {
+        match &instr.bytecode {
+            Bytecode::CopyLoc(l)
+            | Bytecode::MoveLoc(l)
+            | Bytecode::StLoc(l)
+            | Bytecode::MutBorrowLoc(l)
+            | Bytecode::ImmBorrowLoc(l) => used[*l as usize] = true,
+            _ => (),
+        }
+    }
+    if used.iter().all(|used| *used) {
+        return false;
+    }
+    let mut new_index = vec![0; used.len()];
+    let mut count = 0;
+    for (l, used) in used.iter().enumerate() {
+        new_index[l] = count as LocalIndex;
+        if *used {
+            count += 1;
+        }
+    }
+    for instr in &mut func.code {
+        match &mut instr.bytecode {
+            Bytecode::CopyLoc(l)
+            | Bytecode::MoveLoc(l)
+            | Bytecode::StLoc(l)
+            | Bytecode::MutBorrowLoc(l)
+            | Bytecode::ImmBorrowLoc(l) => *l = new_index[*l as usize],
+            _ => (),
+        }
+    }
+    let mut used = used.into_iter();
+    func.locals.retain(|_| used.next().unwrap());
+    true
+}
@@ -0,0 +1,20 @@
+module 0x42::copy_propagation {
+    public fun chain(x: u64): u64 {
+        let y = x;
+        let z = y;
+        z + y + x
+    }
+
+    public fun across_branches(x: u64, c: bool): u64 {
+        let y = x;
+        if (c) y + 1 else y + 2
+    }
+
+    // `y` is borrowed, so its copies are not propagated.
+    public fun borrowed(x: u64): u64 {
+        let y = x;
+        let r = &mut y;
+        *r = 1;
+        y + x
+    }
+}
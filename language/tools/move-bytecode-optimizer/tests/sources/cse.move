@@ -0,0 +1,28 @@
+module 0x42::cse {
+    public fun same_block(a: u64, b: u64): u64 {
+        (a * b + 1) + (a * b + 1)
+    }
+
+    public fun across_blocks(a: u64, b: u64, c: bool): u64 {
+        let x = a + b * 2;
+        if (c) {
+            x + (a + b * 2)
+        } else {
+            x * (a + b * 2)
+        }
+    }
+
+    // `a` is assigned between the two computations, so nothing is common.
+    public fun killed(a: u64, b: u64): u64 {
+        let x = a * b;
+        a = a + 1;
+        x + a * b
+    }
+
+    // The expression is only computed on one path to the second computation.
+    public fun partial(a: u64, b: u64, c: bool): u64 {
+        let x = 0;
+        if (c) x = a * b;
+        x + a * b
+    }
+}
@@ -0,0 +1,22 @@
+module 0x42::dead_stores {
+    // The first value of `y` is never read.
+    public fun overwritten_on_all_paths(x: u64, c: bool): u64 {
+        let y = x;
+        if (c) y = 1 else y = 2;
+        y + x
+    }
+
+    // The computation may abort, so only the store is removed.
+    public fun computed(x: u64, c: bool): u64 {
+        let y = x + 1;
+        if (c) y = 1 else y = 2;
+        y + x
+    }
+
+    // The first value of `y` is read on one of the paths.
+    public fun read_on_a_path(x: u64, c: bool): u64 {
+        let y = x;
+        if (c) y = 1;
+        y + x
+    }
+}
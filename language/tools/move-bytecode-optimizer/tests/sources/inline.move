@@ -0,0 +1,37 @@
+module 0x42::inline {
+    struct S has drop { f: u64 }
+
+    fun square(x: u64): u64 {
+        x * x
+    }
+
+    fun field(s: &S): u64 {
+        s.f
+    }
+
+    // Has more than one basic block, so is not inlined.
+    fun max(x: u64, y: u64): u64 {
+        if (x > y) x else y
+    }
+
+    public fun sum_of_squares(x: u64, y: u64): u64 {
+        square(x) + square(y)
+    }
+
+    public fun read(s: S): u64 {
+        field(&s) + 1
+    }
+
+    public fun maximum(x: u64, y: u64): u64 {
+        max(x, y)
+    }
+
+    // Public functions are not inlined.
+    public fun cube(x: u64): u64 {
+        x * square(x)
+    }
+
+    public fun call_public(x: u64): u64 {
+        cube(x)
+    }
+}
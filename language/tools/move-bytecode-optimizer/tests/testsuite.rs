@@ -0,0 +1,55 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use move_bytecode_optimizer::optimize_module;
+use move_command_line_common::testing::EXP_EXT;
+use move_compiler::{
+    compiled_unit::CompiledUnit, diagnostics::report_diagnostics_to_buffer, Compiler,
+};
+use move_disassembler::disassembler::Disassembler;
+use move_prover_test_utils::baseline_test::verify_or_update_baseline;
+use std::path::Path;
+
+/// Optimize the modules of a source file, and print the report of the optimizer together with
+/// the optimized code.
+fn test_runner(path: &Path) -> datatest_stable::Result<()> {
+    let (files, units) = Compiler::from_files(
+        vec![path.to_str().unwrap().to_string()],
+        move_stdlib::move_stdlib_files(),
+        move_stdlib::move_stdlib_named_addresses(),
+    )
+    .build()?;
+    let units = match units {
+        Ok((units, _warnings)) => units,
+        Err(diags) => {
+            return Err(
+                String::from_utf8_lossy(&report_diagnostics_to_buffer(&files, diags))
+                    .into_owned()
+                    .into(),
+            )
+        }
+    };
+    let mut out = String::new();
+    for unit in units {
+        let mut unit = unit.into_compiled_unit();
+        let reports = match &mut unit {
+            CompiledUnit::Module(module) => {
+                optimize_module(&mut module.module, &mut module.source_map)
+            }
+            CompiledUnit::Script(_) => continue,
+        };
+        if let CompiledUnit::Module(module) = &unit {
+            move_bytecode_verifier::verify_module(&module.module)?;
+        }
+        for report in reports {
+            out.push_str(&format!("{}\n", report));
+        }
+        out.push('\n');
+        out.push_str(&Disassembler::from_unit(&unit).disassemble()?);
+        out.push('\n');
+    }
+    verify_or_update_baseline(path.with_extension(EXP_EXT).as_path(), &out)?;
+    Ok(())
+}
+
+datatest_stable::harness!(test_runner, "tests/sources", r".*\.move$");
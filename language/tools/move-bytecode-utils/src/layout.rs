@@ -5,7 +5,8 @@
 use crate::module_cache::GetModule;
 use anyhow::{anyhow, bail, Result};
 use move_binary_format::{
-    access::ModuleAccess,
+    access::{ModuleAccess, ScriptAccess},
+    binary_views::BinaryIndexedView,
     file_format::{SignatureToken, StructDefinition, StructFieldInformation, StructHandleIndex},
     normalized::{Struct, Type},
     CompiledModule,
@@ -336,6 +337,50 @@ impl TypeLayoutBuilder {
         Self::build(t, resolver, LayoutType::Runtime)
     }
 
+    /// Construct WithTypes `TypeLayout`s for the parameters of the script in `view`, or of its
+    /// function `function_name` if `view` is a module, other than the signers they start with.
+    /// The type parameters of the function are instantiated with `type_args`.
+    pub fn build_parameters_with_types(
+        view: BinaryIndexedView,
+        function_name: Option<&IdentStr>,
+        type_args: &[TypeTag],
+        resolver: &impl GetModule,
+    ) -> Result<Vec<MoveTypeLayout>> {
+        let parameters = match (view, function_name) {
+            (BinaryIndexedView::Script(script), None) => script.signature_at(script.parameters),
+            (BinaryIndexedView::Module(module), Some(name)) => {
+                let handle = module
+                    .function_defs()
+                    .iter()
+                    .map(|def| module.function_handle_at(def.function))
+                    .find(|handle| module.identifier_at(handle.name) == name)
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "Could not find function named {} in module {}",
+                            name,
+                            module.self_id()
+                        )
+                    })?;
+                module.signature_at(handle.parameters)
+            }
+            (BinaryIndexedView::Script(_), Some(_)) => bail!("Scripts have no named functions"),
+            (BinaryIndexedView::Module(_), None) => bail!("Modules have no script function"),
+        };
+        parameters
+            .0
+            .iter()
+            .skip_while(|t| match t {
+                SignatureToken::Signer => true,
+                SignatureToken::Reference(inner) => inner.is_signer(),
+                _ => false,
+            })
+            .map(|t| {
+                let type_tag = type_tag_from_signature_token(&view, t, type_args)?;
+                Self::build_with_types(&type_tag, resolver)
+            })
+            .collect()
+    }
+
     fn build(
         t: &TypeTag,
         resolver: &impl GetModule,
@@ -420,6 +465,51 @@ impl TypeLayoutBuilder {
     }
 }
 
+/// The type of the signature token `s` in `view`, whose type parameters are instantiated with
+/// `type_args`.
+fn type_tag_from_signature_token(
+    view: &BinaryIndexedView,
+    s: &SignatureToken,
+    type_args: &[TypeTag],
+) -> Result<TypeTag> {
+    use SignatureToken::*;
+    let struct_tag = |idx: StructHandleIndex, type_params: Vec<TypeTag>| {
+        let handle = view.struct_handle_at(idx);
+        let module = view.module_handle_at(handle.module);
+        TypeTag::Struct(Box::new(StructTag {
+            address: *view.address_identifier_at(module.address),
+            module: view.identifier_at(module.name).to_owned(),
+            name: view.identifier_at(handle.name).to_owned(),
+            type_params,
+        }))
+    };
+    Ok(match s {
+        Bool => TypeTag::Bool,
+        U8 => TypeTag::U8,
+        U16 => TypeTag::U16,
+        U32 => TypeTag::U32,
+        U64 => TypeTag::U64,
+        U128 => TypeTag::U128,
+        U256 => TypeTag::U256,
+        Address => TypeTag::Address,
+        Signer => TypeTag::Signer,
+        Vector(t) => TypeTag::Vector(Box::new(type_tag_from_signature_token(view, t, type_args)?)),
+        Struct(idx) => struct_tag(*idx, vec![]),
+        StructInstantiation(idx, type_actuals) => struct_tag(
+            *idx,
+            type_actuals
+                .iter()
+                .map(|t| type_tag_from_signature_token(view, t, type_args))
+                .collect::<Result<_>>()?,
+        ),
+        TypeParameter(i) => type_args
+            .get(*i as usize)
+            .cloned()
+            .ok_or_else(|| anyhow!("Missing type argument for type parameter {}", i))?,
+        Reference(_) | MutableReference(_) => bail!("Type layouts cannot contain references"),
+    })
+}
+
 impl StructLayoutBuilder {
     pub fn build_runtime(s: &StructTag, resolver: &impl GetModule) -> Result<MoveStructLayout> {
         Self::build(s, resolver, LayoutType::Runtime)
@@ -0,0 +1,47 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use super::reroot_path;
+use clap::*;
+use move_decompiler::decompiler::Decompiler;
+use move_package::BuildConfig;
+use std::path::PathBuf;
+
+/// Reconstruct Move source code from the bytecode of a module
+#[derive(Parser)]
+#[clap(name = "decompile")]
+pub struct Decompile {
+    /// The package name. If not provided defaults to current package modules only
+    #[clap(long = "package")]
+    pub package_name: Option<String>,
+    /// The name of the module in the package to decompile
+    #[clap(long = "name")]
+    pub module_name: String,
+}
+
+impl Decompile {
+    pub fn execute(self, path: Option<PathBuf>, config: BuildConfig) -> anyhow::Result<()> {
+        let rerooted_path = reroot_path(path)?;
+        let Self {
+            package_name,
+            module_name,
+        } = self;
+        // Make sure the package is built
+        let package = config.compile_package(&rerooted_path, &mut Vec::new())?;
+        let needle_package = package_name
+            .as_deref()
+            .unwrap_or(package.compiled_package_info.package_name.as_str());
+        match package
+            .get_module_by_name(needle_package, &module_name)
+            .ok()
+        {
+            None => anyhow::bail!(
+                "Unable to find module with name '{}' in package '{}'",
+                module_name,
+                needle_package,
+            ),
+            Some(unit) => print!("{}", Decompiler::from_unit(&unit.unit)?.decompile()?),
+        }
+        Ok(())
+    }
+}
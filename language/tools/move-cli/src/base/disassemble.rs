@@ -3,8 +3,11 @@
 
 use super::reroot_path;
 use clap::*;
-use move_compiler::compiled_unit::{CompiledUnit, NamedCompiledModule};
-use move_disassembler::disassembler::Disassembler;
+use move_compiler::compiled_unit::{CompiledUnit, NamedCompiledModule, NamedCompiledScript};
+use move_disassembler::{
+    assembly::{module_assembly, script_assembly},
+    disassembler::Disassembler,
+};
 use move_package::{compilation::compiled_package::CompiledUnitWithSource, BuildConfig};
 use std::path::PathBuf;
 
@@ -15,6 +18,9 @@ pub struct Disassemble {
     /// Start a disassembled bytecode-to-source explorer
     #[clap(long = "interactive")]
     pub interactive: bool,
+    /// Print the lossless assembly form of the bytecode, which `move-assembler` accepts
+    #[clap(long = "assembly", conflicts_with = "interactive")]
+    pub assembly: bool,
     /// The package name. If not provided defaults to current package modules only
     #[clap(long = "package")]
     pub package_name: Option<String>,
@@ -28,6 +34,7 @@ impl Disassemble {
         let rerooted_path = reroot_path(path)?;
         let Self {
             interactive,
+            assembly,
             package_name,
             module_or_script_name,
         } = self;
@@ -65,6 +72,15 @@ impl Disassemble {
                         ),
                         _ => anyhow::bail!("Interactive disassembler not supported for scripts"),
                     }
+                } else if assembly {
+                    match &unit.unit {
+                        CompiledUnit::Module(NamedCompiledModule { module, .. }) => {
+                            print!("{}", module_assembly(module))
+                        }
+                        CompiledUnit::Script(NamedCompiledScript { script, .. }) => {
+                            print!("{}", script_assembly(script))
+                        }
+                    }
                 } else {
                     println!("{}", Disassembler::from_unit(&unit.unit).disassemble()?);
                 }
@@ -36,6 +36,22 @@ pub struct Prove {
     /// this string will be part of verification.
     #[clap(short = 't', long = "target")]
     pub target_filter: Option<String>,
+    /// Write Move unit tests which replay the counterexamples of verification errors into
+    /// the `counterexamples` directory of the package.
+    #[clap(long = "emit-counterexample-tests")]
+    pub emit_counterexample_tests: bool,
+    /// Cache verification results in `build/prover-cache.json`, and only verify functions
+    /// whose verification conditions changed since the last run.
+    #[clap(long = "cache")]
+    pub cache: bool,
+    /// Report, per module, the public functions without specifications, the aborts which are
+    /// not covered by an `aborts_if`, and the `ensures` which hold for any function body.
+    #[clap(long = "spec-coverage")]
+    pub spec_coverage: bool,
+    /// Write a report with the status, solver time, number of verification conditions, and
+    /// errors of each verified function and module to `build/prover-report.<format>`.
+    #[clap(long = "report", value_name = "FORMAT", possible_values = &["json"])]
+    pub report: Option<String>,
     /// Internal field indicating that this prover run is for a test.
     #[clap(skip)]
     pub for_test: bool,
@@ -49,6 +65,10 @@ impl Prove {
         let rerooted_path = reroot_path(path)?;
         let Self {
             target_filter,
+            emit_counterexample_tests,
+            cache,
+            spec_coverage,
+            report,
             for_test,
             options,
         } = self;
@@ -62,6 +82,23 @@ impl Prove {
             args.push(format!("--config={}", prover_toml.to_string_lossy()));
         }
         args.extend(opts.iter().cloned());
+        if emit_counterexample_tests {
+            args.push("--emit-counterexample-tests".to_string());
+        }
+        if cache {
+            let cache_file = rerooted_path.join("build").join("prover-cache.json");
+            args.push(format!("--cache={}", cache_file.to_string_lossy()));
+        }
+        if spec_coverage {
+            args.push("--spec-coverage".to_string());
+        }
+        if let Some(format) = report {
+            let report_file = rerooted_path
+                .join("build")
+                .join(format!("prover-report.{}", format));
+            args.push(format!("--report={}", format));
+            args.push(format!("--report-file={}", report_file.to_string_lossy()));
+        }
         let options = move_prover::cli::Options::create_from_args(&args)?;
         if for_test {
             options.setup_logging_for_test();
@@ -124,6 +161,10 @@ impl ProverTest {
         let pkg_path = path_in_crate(std::mem::take(&mut self.path));
         let cmd = Prove {
             target_filter: None,
+            emit_counterexample_tests: false,
+            cache: false,
+            spec_coverage: false,
+            report: None,
             for_test: true,
             options: Some(ProverOptions::Options(std::mem::take(&mut self.options))),
         };
@@ -61,6 +61,10 @@ pub struct Test {
     /// Show the storage state at the end of execution of a failing test
     #[clap(name = "global_state_on_error", short = 'g', long = "state_on_error")]
     pub report_storage_on_error: bool,
+    /// Show the stack trace of a failing test along with the values of the arguments and locals
+    /// of each frame
+    #[clap(name = "report_stacktrace_locals", long = "stacktrace_locals")]
+    pub report_stacktrace_locals: bool,
 
     /// Ignore compiler's warning, and continue run tests
     #[clap(name = "ignore_compile_warnings", long = "ignore_compile_warnings")]
@@ -70,6 +74,10 @@ pub struct Test {
     /// the execution result from Move VM.
     #[clap(long = "stackless")]
     pub check_stackless_vm: bool,
+    /// Also run the tests with the stackless bytecode interpreter, and report any specification
+    /// of a called function which is violated by the concrete execution.
+    #[clap(long = "check-specs")]
+    pub check_specs: bool,
     /// Verbose mode
     #[clap(long = "verbose")]
     pub verbose_mode: bool,
@@ -100,8 +108,10 @@ impl Test {
             num_threads,
             report_statistics,
             report_storage_on_error,
+            report_stacktrace_locals,
             ignore_compile_warnings,
             check_stackless_vm,
+            check_specs,
             verbose_mode,
             compute_coverage,
             #[cfg(feature = "evm-backend")]
@@ -114,7 +124,9 @@ impl Test {
             num_threads,
             report_statistics,
             report_storage_on_error,
+            report_stacktrace_locals,
             check_stackless_vm,
+            check_specs,
             verbose: verbose_mode,
             ignore_compile_warnings,
             #[cfg(feature = "evm-backend")]
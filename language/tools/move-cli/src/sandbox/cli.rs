@@ -11,11 +11,10 @@ use crate::{
 };
 use anyhow::Result;
 use clap::Parser;
-use move_core_types::{
-    errmap::ErrorMapping, language_storage::TypeTag, parser,
-    transaction_argument::TransactionArgument,
-};
+use move_command_line_common::arguments::ArgumentValue;
+use move_core_types::{errmap::ErrorMapping, language_storage::TypeTag, parser};
 use move_package::compilation::package_layout::CompiledPackageLayout;
+use move_resource_viewer::events::{parse_guid, EventFilter, FieldFilter};
 use move_vm_test_utils::gas_schedule::CostTable;
 use std::{
     fs,
@@ -75,21 +74,26 @@ pub enum SandboxCommand {
         )]
         signers: Vec<String>,
         /// Possibly-empty list of arguments passed to the transaction (e.g., `i` in
-        /// `main(i: u64)`). Must match the arguments types expected by `script_file`.
-        /// Supported argument types are
+        /// `main(i: u64)`). Each argument is converted to the type of the parameter it is passed
+        /// to, and is written either as a value or as JSON. Values are
         /// bool literals (true, false),
-        /// u64 literals (e.g., 10, 58),
-        /// address literals (e.g., 0x12, 0x0000000000000000000000000000000f),
-        /// hexadecimal strings (e.g., x"0012" will parse as the vector<u8> value [00, 12]), and
-        /// ASCII strings (e.g., 'b"hi" will parse as the vector<u8> value [68, 69]).
+        /// integer literals, with an optional type suffix (e.g., 10, 58u8),
+        /// address literals (e.g., 0x12, @0x12, @std),
+        /// hexadecimal strings (e.g., x"0012" will parse as the vector<u8> value [00, 12]),
+        /// strings (e.g., b"hi" or "hi", for vector<u8>, std::string::String and
+        /// std::ascii::String),
+        /// vectors (e.g., vector[1, 2], or vector[] and vector[1] for std::option::Option), and
+        /// structs (e.g., 0x42::M::S { f: 1 }).
+        /// In JSON, vectors are arrays, structs are objects, `null` is `option::none`, and
+        /// addresses and integers too large for a JSON number are strings.
         #[clap(
             long = "args",
-            parse(try_from_str = parser::parse_transaction_argument),
+            parse(try_from_str = ArgumentValue::parse),
             takes_value(true),
             multiple_values(true),
             multiple_occurrences(true)
         )]
-        args: Vec<TransactionArgument>,
+        args: Vec<ArgumentValue>,
         /// Possibly-empty list of type arguments passed to the transaction (e.g., `T` in
         /// `main<T>()`). Must match the type arguments kinds expected by `script_file`.
         #[clap(
@@ -109,6 +113,10 @@ pub enum SandboxCommand {
         /// deleted resources) will NOT be committed to disk.
         #[clap(long = "dry-run", short = 'n')]
         dry_run: bool,
+        /// If set, the stack trace of a failed execution also shows the values of the arguments
+        /// and locals of each frame.
+        #[clap(long = "stacktrace-locals")]
+        stacktrace_locals: bool,
     },
     /// Run expected value tests using the given batch file.
     #[clap(name = "exp-test")]
@@ -129,6 +137,30 @@ pub enum SandboxCommand {
         #[clap(name = "file", parse(from_os_str))]
         file: PathBuf,
     },
+    /// Query the events emitted by the transactions run so far, decoded with the modules stored
+    /// on disk.
+    #[clap(name = "events")]
+    Events {
+        /// Only show events of this type (e.g., `0x2::M::Deposit`). A struct type without type
+        /// arguments matches all its instantiations.
+        #[clap(long = "type", parse(try_from_str = parser::parse_type_tag))]
+        type_: Option<TypeTag>,
+        /// Only show events emitted by the event handle with this GUID, written in hex.
+        #[clap(long = "guid")]
+        guid: Option<String>,
+        /// Only show events emitted by this transaction, counting the transactions run from 0.
+        #[clap(long = "txn")]
+        txn: Option<u64>,
+        /// Only show events whose fields have these values (e.g., `amount=10` or
+        /// `to.addr=0x2`). Nested fields are separated by dots.
+        #[clap(
+            long = "field",
+            takes_value(true),
+            multiple_values(true),
+            multiple_occurrences(true)
+        )]
+        fields: Vec<FieldFilter>,
+    },
     /// Delete all resources, events, and modules stored on disk under `storage-dir`.
     /// Does *not* delete anything in `src`.
     Clean {},
@@ -237,6 +269,7 @@ impl SandboxCommand {
                 type_args,
                 gas_budget,
                 dry_run,
+                stacktrace_locals,
             } => {
                 let context =
                     PackageContext::new(&move_args.package_path, &move_args.build_config)?;
@@ -255,6 +288,7 @@ impl SandboxCommand {
                     *gas_budget,
                     bytecode_version,
                     *dry_run,
+                    *stacktrace_locals,
                     move_args.verbose,
                 )
             }
@@ -275,6 +309,22 @@ impl SandboxCommand {
                     .prepare_state(bytecode_version, storage_dir)?;
                 sandbox::commands::view(&state, file)
             }
+            SandboxCommand::Events {
+                type_,
+                guid,
+                txn,
+                fields,
+            } => {
+                let state = PackageContext::new(&move_args.package_path, &move_args.build_config)?
+                    .prepare_state(bytecode_version, storage_dir)?;
+                let filter = EventFilter {
+                    guid: guid.as_deref().map(parse_guid).transpose()?,
+                    type_: type_.clone(),
+                    txn: *txn,
+                    fields: fields.clone(),
+                };
+                sandbox::commands::events(&state, &filter)
+            }
             SandboxCommand::Clean {} => {
                 // delete storage
                 let storage_dir = Path::new(storage_dir);
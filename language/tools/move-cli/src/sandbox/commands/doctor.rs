@@ -6,6 +6,7 @@ use crate::sandbox::utils::on_disk_state_view::OnDiskStateView;
 use move_binary_format::{access::ModuleAccess, errors::PartialVMError};
 use move_bytecode_utils::Modules;
 use move_core_types::vm_status::StatusCode;
+use move_resource_viewer::MoveValueAnnotator;
 
 use anyhow::{bail, Result};
 use std::{ffi::OsStr, path::Path};
@@ -16,7 +17,7 @@ use std::{ffi::OsStr, path::Path};
 /// (1) all modules pass the bytecode verifier
 /// (2) all modules pass the linker
 /// (3) all resources can be deserialized
-/// (4) all events can be deserialized
+/// (4) all events can be deserialized, including those in the event index
 /// (5) build/mv_interfaces is consistent with the global storage (TODO?)
 pub fn doctor(state: &OnDiskStateView) -> Result<()> {
     fn parent_addr(p: &Path) -> &OsStr {
@@ -88,6 +89,18 @@ pub fn doctor(state: &OnDiskStateView) -> Result<()> {
             )
         }
     }
+    // deserialize the event index and the events in it
+    let event_index = state.get_event_index()?;
+    let annotator = MoveValueAnnotator::new(state);
+    for event in event_index.events() {
+        if annotator.view_value(&event.type_, &event.data).is_err() {
+            bail!(
+                "Failed to deserialize event {} of transaction {} in the event index",
+                event.seq_num,
+                event.txn
+            )
+        }
+    }
 
     Ok(())
 }
@@ -0,0 +1,20 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::sandbox::utils::on_disk_state_view::OnDiskStateView;
+use anyhow::Result;
+use move_resource_viewer::{events::EventFilter, MoveValueAnnotator};
+
+/// Print the events emitted by the transactions committed to storage which match `filter`
+pub fn events(state: &OnDiskStateView, filter: &EventFilter) -> Result<()> {
+    let event_index = state.get_event_index()?;
+    let events = event_index.query(filter, &MoveValueAnnotator::new(state))?;
+    if events.is_empty() {
+        println!("Events not found.")
+    } else {
+        for event in events {
+            println!("{}", event)
+        }
+    }
+    Ok(())
+}
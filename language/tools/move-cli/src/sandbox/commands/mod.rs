@@ -3,6 +3,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 pub mod doctor;
+pub mod events;
 pub mod generate;
 pub mod publish;
 pub mod run;
@@ -10,6 +11,7 @@ pub mod test;
 pub mod view;
 
 pub use doctor::*;
+pub use events::*;
 pub use publish::*;
 pub use run::*;
 pub use test::*;
@@ -10,18 +10,18 @@ use crate::{
     NativeFunctionRecord,
 };
 use anyhow::{anyhow, bail, Result};
-use move_binary_format::file_format::CompiledModule;
-use move_command_line_common::env::get_bytecode_version_from_env;
+use move_binary_format::{
+    binary_views::BinaryIndexedView,
+    file_format::{CompiledModule, CompiledScript},
+};
+use move_bytecode_utils::layout::TypeLayoutBuilder;
+use move_command_line_common::{arguments::ArgumentValue, env::get_bytecode_version_from_env};
 use move_core_types::{
-    account_address::AccountAddress,
-    errmap::ErrorMapping,
-    identifier::IdentStr,
-    language_storage::TypeTag,
-    transaction_argument::{convert_txn_args, TransactionArgument},
-    value::MoveValue,
+    account_address::AccountAddress, errmap::ErrorMapping, identifier::IdentStr,
+    language_storage::TypeTag, value::MoveValue,
 };
 use move_package::compilation::compiled_package::CompiledPackage;
-use move_vm_runtime::move_vm::MoveVM;
+use move_vm_runtime::{config::VMConfig, move_vm::MoveVM};
 use move_vm_test_utils::gas_schedule::CostTable;
 use std::{fs, path::Path};
 
@@ -35,11 +35,12 @@ pub fn run(
     script_path: &Path,
     script_name_opt: &Option<String>,
     signers: &[String],
-    txn_args: &[TransactionArgument],
+    txn_args: &[ArgumentValue],
     vm_type_args: Vec<TypeTag>,
     gas_budget: Option<u64>,
     bytecode_version: Option<u32>,
     dry_run: bool,
+    stacktrace_locals: bool,
     verbose: bool,
 ) -> Result<()> {
     if !script_path.exists() {
@@ -47,7 +48,7 @@ pub fn run(
     };
     let bytecode_version = get_bytecode_version_from_env(bytecode_version);
 
-    let bytecode = if is_bytecode_file(script_path) {
+    let (bytecode, script_unit) = if is_bytecode_file(script_path) {
         assert!(
             state.is_module_path(script_path) || !contains_module(script_path),
             "Attempting to run module {:?} outside of the `storage/` directory.
@@ -55,7 +56,7 @@ move run` must be applied to a module inside `storage/`",
             script_path
         );
         // script bytecode; read directly from file
-        fs::read(script_path)?
+        (fs::read(script_path)?, None)
     } else {
         // TODO(tzakian): support calling scripts in transitive deps
         let file_contents = std::fs::read_to_string(script_path)?;
@@ -64,7 +65,7 @@ move run` must be applied to a module inside `storage/`",
             .find(|unit| unit.unit.source_map().check(&file_contents));
         // script source file; package is already compiled so load it up
         match script_opt {
-            Some(unit) => unit.unit.serialize(bytecode_version),
+            Some(unit) => (unit.unit.serialize(bytecode_version), Some(unit)),
             None => bail!("Unable to find script in file {:?}", script_path),
         }
     };
@@ -73,10 +74,23 @@ move run` must be applied to a module inside `storage/`",
         .iter()
         .map(|s| AccountAddress::from_hex_literal(s))
         .collect::<Result<Vec<AccountAddress>, _>>()?;
-    // TODO: parse Value's directly instead of going through the indirection of TransactionArgument?
-    let vm_args: Vec<Vec<u8>> = convert_txn_args(txn_args);
+    let vm_args = typed_args(
+        state,
+        package,
+        &bytecode,
+        script_name_opt,
+        txn_args,
+        &vm_type_args,
+    )?;
 
-    let vm = MoveVM::new(natives).unwrap();
+    let vm = MoveVM::new_with_config(
+        natives,
+        VMConfig {
+            stack_trace_locals: stacktrace_locals,
+            ..Default::default()
+        },
+    )
+    .unwrap();
     let mut gas_status = get_gas_status(cost_table, gas_budget)?;
     let mut session = vm.new_session(state);
 
@@ -118,6 +132,8 @@ move run` must be applied to a module inside `storage/`",
             error_descriptions,
             err,
             state,
+            package,
+            script_unit,
             &script_type_parameters,
             &script_parameters,
             &vm_type_args,
@@ -132,3 +148,58 @@ move run` must be applied to a module inside `storage/`",
         maybe_commit_effects(!dry_run, changeset, events, state)
     }
 }
+
+/// Convert `txn_args` to the types of the parameters of the script in `bytecode`, or of its
+/// function `script_name_opt` if `bytecode` is a module, and serialize them. Named addresses in
+/// the arguments are resolved in `package`.
+fn typed_args(
+    state: &OnDiskStateView,
+    package: &CompiledPackage,
+    bytecode: &[u8],
+    script_name_opt: &Option<String>,
+    txn_args: &[ArgumentValue],
+    vm_type_args: &[TypeTag],
+) -> Result<Vec<Vec<u8>>> {
+    let layouts = match script_name_opt {
+        Some(script_name) => {
+            let module = CompiledModule::deserialize(bytecode)
+                .map_err(|e| anyhow!("Error deserializing module: {:?}", e))?;
+            TypeLayoutBuilder::build_parameters_with_types(
+                BinaryIndexedView::Module(&module),
+                Some(IdentStr::new(script_name)?),
+                vm_type_args,
+                &state,
+            )?
+        }
+        None => {
+            let script = CompiledScript::deserialize(bytecode)
+                .map_err(|e| anyhow!("Error deserializing script: {:?}", e))?;
+            TypeLayoutBuilder::build_parameters_with_types(
+                BinaryIndexedView::Script(&script),
+                None,
+                vm_type_args,
+                &state,
+            )?
+        }
+    };
+    if layouts.len() != txn_args.len() {
+        bail!(
+            "Execution failed with incorrect number of arguments: script expected {:?}, but found \
+             {:?}",
+            layouts.len(),
+            txn_args.len()
+        )
+    }
+    let addresses = &package.compiled_package_info.address_alias_instantiation;
+    let mapping = |name: &str| addresses.get(&name.into()).copied();
+    txn_args
+        .iter()
+        .zip(&layouts)
+        .map(|(arg, layout)| {
+            let value = arg.clone().into_typed_value(layout, &mapping)?;
+            value
+                .simple_serialize()
+                .ok_or_else(|| anyhow!("Unable to serialize argument {:?}", value))
+        })
+        .collect()
+}
@@ -9,12 +9,16 @@ use difference::{Changeset, Difference};
 use move_binary_format::{
     access::ModuleAccess,
     compatibility::Compatibility,
-    errors::VMError,
+    errors::{ExecutionState, VMError},
     file_format::{AbilitySet, CompiledModule, FunctionDefinitionIndex, SignatureToken},
     normalized, IndexKind,
 };
+use move_bytecode_source_map::backtrace::BacktraceRenderer;
 use move_bytecode_utils::Modules;
-use move_command_line_common::files::{FileHash, MOVE_COMPILED_EXTENSION};
+use move_command_line_common::{
+    arguments::ArgumentValue,
+    files::{FileHash, MOVE_COMPILED_EXTENSION},
+};
 use move_compiler::{
     compiled_unit::{CompiledUnit, NamedCompiledModule},
     diagnostics::{self, report_diagnostics, Diagnostic, Diagnostics, FileName},
@@ -24,11 +28,10 @@ use move_core_types::{
     effects::{ChangeSet, Event, Op},
     errmap::ErrorMapping,
     language_storage::{ModuleId, TypeTag},
-    transaction_argument::TransactionArgument,
     vm_status::{AbortLocation, StatusCode, VMStatus},
 };
 use move_ir_types::location::Loc;
-use move_package::compilation::compiled_package::CompiledUnitWithSource;
+use move_package::compilation::compiled_package::{CompiledPackage, CompiledUnitWithSource};
 use move_resource_viewer::{AnnotatedMoveStruct, MoveValueAnnotator};
 use move_vm_test_utils::gas_schedule::Gas;
 use std::{
@@ -264,6 +267,7 @@ pub(crate) fn maybe_commit_effects(
             }
         }
 
+        state.save_transaction_events(&events)?;
         for (event_key, event_sequence_number, event_type, event_data) in events {
             state.save_event(&event_key, event_sequence_number, event_type, event_data)?
         }
@@ -277,7 +281,7 @@ pub(crate) fn maybe_commit_effects(
 pub(crate) fn explain_type_error(
     script_params: &[SignatureToken],
     signers: &[AccountAddress],
-    txn_args: &[TransactionArgument],
+    txn_args: &[ArgumentValue],
 ) {
     use SignatureToken::*;
     let expected_num_signers = script_params
@@ -457,13 +461,16 @@ pub(crate) fn explain_execution_error(
     error_descriptions: &ErrorMapping,
     error: VMError,
     state: &OnDiskStateView,
+    package: &CompiledPackage,
+    script: Option<&CompiledUnitWithSource>,
     script_type_parameters: &[AbilitySet],
     script_parameters: &[SignatureToken],
     vm_type_args: &[TypeTag],
     signers: &[AccountAddress],
-    txn_args: &[TransactionArgument],
+    txn_args: &[ArgumentValue],
 ) -> Result<()> {
     use StatusCode::*;
+    let exec_state = error.exec_state().cloned();
     match error.into_vm_status() {
         VMStatus::MoveAbort(AbortLocation::Module(id), abort_code) => {
             // try to use move-explain to explain the abort
@@ -483,7 +490,6 @@ pub(crate) fn explain_execution_error(
             }
         }
         VMStatus::MoveAbort(AbortLocation::Script, abort_code) => {
-            // TODO: map to source code location
             println!(
                 "Execution aborted with code {} in transaction script",
                 abort_code
@@ -516,7 +522,6 @@ pub(crate) fn explain_execution_error(
                 OUT_OF_GAS => "an out of gas error".to_string(),
                 _ => format!("a {} error", status_code.status_type()),
             };
-            // TODO: map to source code location
             let location_explanation = match location {
                 AbortLocation::Module(id) => {
                     format!(
@@ -553,6 +558,72 @@ pub(crate) fn explain_execution_error(
         }
         VMStatus::Executed => unreachable!(),
     }
+    match exec_state {
+        Some(exec_state) => explain_stack_trace(&exec_state, state, package, script),
+        None => Ok(()),
+    }
+}
+
+/// Explain where an execution failed with its Move-level stack trace. Source locations are
+/// resolved through the source maps of the modules of the package, unless the module in storage
+/// differs from the one in the package.
+fn explain_stack_trace(
+    exec_state: &ExecutionState,
+    state: &OnDiskStateView,
+    package: &CompiledPackage,
+    script: Option<&CompiledUnitWithSource>,
+) -> Result<()> {
+    if exec_state.stack_trace().is_empty() {
+        return Ok(());
+    }
+    let stored_modules = state.get_all_modules()?;
+    let package_modules: HashMap<_, _> = package
+        .all_modules()
+        .filter_map(|unit| match &unit.unit {
+            CompiledUnit::Module(module) => Some((module.module.self_id(), (module, unit))),
+            CompiledUnit::Script(_) => None,
+        })
+        .collect();
+
+    let mut sources = vec![];
+    let mut renderer = BacktraceRenderer::new();
+    for stored in &stored_modules {
+        match package_modules.get(&stored.self_id()) {
+            Some((module, unit))
+                if stored
+                    == &CompiledModule {
+                        version: stored.version,
+                        ..module.module.clone()
+                    } =>
+            {
+                renderer.add_module(&module.module, Some(&module.source_map));
+                sources.push(&unit.source_path);
+            }
+            _ => renderer.add_module(stored, None),
+        }
+    }
+    if let Some(CompiledUnitWithSource {
+        unit: CompiledUnit::Script(script),
+        source_path,
+    }) = script
+    {
+        renderer.set_script(script.name.as_str(), &script.source_map);
+        sources.push(source_path);
+    }
+
+    let current_dir = std::env::current_dir()?;
+    let sources = sources
+        .into_iter()
+        .map(|path| {
+            let contents = fs::read_to_string(path)?;
+            let path = path.strip_prefix(&current_dir).unwrap_or(path);
+            Ok((path.to_string_lossy().to_string(), contents))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    for (path, contents) in &sources {
+        renderer.add_source_file(FileHash::new(contents), path, contents);
+    }
+    print!("stack trace\n{}", renderer.render(exec_state));
     Ok(())
 }
 
@@ -20,7 +20,9 @@ use move_core_types::{
 };
 use move_disassembler::disassembler::Disassembler;
 use move_ir_types::location::Spanned;
-use move_resource_viewer::{AnnotatedMoveStruct, AnnotatedMoveValue, MoveValueAnnotator};
+use move_resource_viewer::{
+    events::EventIndex, AnnotatedMoveStruct, AnnotatedMoveValue, MoveValueAnnotator,
+};
 use std::{
     convert::{TryFrom, TryInto},
     fs,
@@ -35,6 +37,9 @@ pub const RESOURCES_DIR: &str = "resources";
 pub const MODULES_DIR: &str = "modules";
 /// subdirectory of `DEFAULT_STORAGE_DIR`/<addr> where events are stored
 pub const EVENTS_DIR: &str = "events";
+/// file under `DEFAULT_STORAGE_DIR` where the index of the events emitted by each transaction is
+/// stored
+pub const EVENT_INDEX_FILE: &str = "event_index.bcs";
 
 /// file under `DEFAULT_BUILD_DIR` where a registry of generated struct layouts are stored
 pub const STRUCT_LAYOUTS_FILE: &str = "struct_layouts.yaml";
@@ -125,6 +130,10 @@ impl OnDiskStateView {
         path.with_extension(BCS_EXTENSION)
     }
 
+    fn get_event_index_path(&self) -> PathBuf {
+        self.storage_dir.join(EVENT_INDEX_FILE)
+    }
+
     fn get_module_path(&self, module_id: &ModuleId) -> PathBuf {
         let mut path = self.get_addr_path(module_id.address());
         path.push(MODULES_DIR);
@@ -239,6 +248,14 @@ impl OnDiskStateView {
             .collect()
     }
 
+    /// Returns the index of the events emitted by the transactions committed so far
+    pub fn get_event_index(&self) -> Result<EventIndex> {
+        match Self::get_bytes(&self.get_event_index_path())? {
+            Some(index_data) => EventIndex::from_bytes(&index_data),
+            None => Ok(EventIndex::new()),
+        }
+    }
+
     fn view_bytecode(path: &Path, is_module: bool) -> Result<Option<String>> {
         if path.is_dir() {
             bail!("Bad bytecode path {:?}. Needed file, found directory", path)
@@ -323,6 +340,15 @@ impl OnDiskStateView {
         Ok(fs::write(path, &bcs::to_bytes(&event_log)?)?)
     }
 
+    /// Add the events emitted by a committed transaction to the event index, and return the index
+    /// of the transaction
+    pub fn save_transaction_events(&self, events: &[Event]) -> Result<u64> {
+        let mut event_index = self.get_event_index()?;
+        let txn = event_index.add_transaction(events.iter().cloned());
+        fs::write(self.get_event_index_path(), &event_index.to_bytes()?)?;
+        Ok(txn)
+    }
+
     /// Save `module` on disk under the path `module.address()`/`module.name()`
     pub fn save_module(&self, module_id: &ModuleId, module_bytes: &[u8]) -> Result<()> {
         let path = self.get_module_path(module_id);
@@ -0,0 +1,12 @@
+module std::counter {
+    struct Counter has key {
+        value: u64,
+    }
+
+    public fun increment(addr: address, limit: u64) acquires Counter {
+        let counter = borrow_global_mut<Counter>(addr);
+        while (counter.value < limit) {
+            counter.value = counter.value + 1;
+        }
+    }
+}
@@ -0,0 +1,17 @@
+module 0x42::math {
+    fun square(x: u64): u64 {
+        x * x
+    }
+
+    public fun sum_of_squares(x: u64, y: u64): u64 {
+        square(x) + square(y)
+    }
+
+    public fun twice(a: u64, b: u64): u64 {
+        (a * b + 1) + (a * b + 1)
+    }
+
+    public fun unchanged(x: u64): u64 {
+        x + 1
+    }
+}
@@ -0,0 +1,47 @@
+module A::M {
+    struct Counter has key { value: u64 }
+
+    spec Counter {
+        invariant value < 100;
+    }
+
+    public fun inc(x: u64): u64 {
+        x + 2
+    }
+    spec inc {
+        ensures result == x + 1;
+    }
+
+    public fun dec(x: u64): u64 {
+        x - 1
+    }
+    spec dec {
+        aborts_if x == 0;
+        ensures result == x - 1;
+    }
+
+    public fun publish(account: &signer, value: u64) {
+        move_to(account, Counter { value })
+    }
+
+    #[test]
+    fun violates_ensures() {
+        assert!(inc(1) == 3, 0);
+    }
+
+    #[test(account = @0x5)]
+    fun violates_invariant(account: signer) {
+        publish(&account, 200);
+    }
+
+    #[test(account = @0x5)]
+    fun satisfies_invariant(account: signer) {
+        publish(&account, 10);
+    }
+
+    #[test]
+    #[expected_failure(arithmetic_error, location = Self)]
+    fun satisfies_aborts_if() {
+        dec(0);
+    }
+}
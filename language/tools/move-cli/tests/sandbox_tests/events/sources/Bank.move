@@ -0,0 +1,30 @@
+module bank::Bank {
+    use std::event::{Self, EventHandle};
+    use std::signer;
+
+    struct Deposit has drop, store { amount: u64, to: address }
+    struct Withdraw has drop, store { amount: u64 }
+
+    struct Events has key {
+        deposits: EventHandle<Deposit>,
+        withdrawals: EventHandle<Withdraw>,
+    }
+
+    public entry fun init(account: &signer) {
+        move_to(account, Events {
+            deposits: event::new_event_handle(account),
+            withdrawals: event::new_event_handle(account),
+        })
+    }
+
+    public entry fun deposit(account: &signer, amount: u64) acquires Events {
+        let to = signer::address_of(account);
+        let events = borrow_global_mut<Events>(to);
+        event::emit_event(&mut events.deposits, Deposit { amount, to })
+    }
+
+    public entry fun withdraw(account: &signer, amount: u64) acquires Events {
+        let events = borrow_global_mut<Events>(signer::address_of(account));
+        event::emit_event(&mut events.withdrawals, Withdraw { amount })
+    }
+}
@@ -0,0 +1,6 @@
+script {
+    use 0x2::Stack;
+    fun run_stack(limit: u64) {
+        Stack::run(limit);
+    }
+}
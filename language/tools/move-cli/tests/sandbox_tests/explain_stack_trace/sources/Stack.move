@@ -0,0 +1,16 @@
+module 0x2::Stack {
+    public fun check(depth: u64, limit: u64) {
+        let next = depth + 1;
+        if (next > limit) abort 7;
+        check(next, limit)
+    }
+
+    public fun run(limit: u64) {
+        check(0, limit)
+    }
+
+    #[test]
+    fun failing_test() {
+        run(1)
+    }
+}
@@ -0,0 +1,9 @@
+script {
+    use std::debug;
+    use std::string::String;
+
+    fun main(s: String, n: u16) {
+        debug::print(&s);
+        debug::print(&n)
+    }
+}
@@ -0,0 +1,32 @@
+module typed::Args {
+    use std::debug;
+    use std::option::{Self, Option};
+    use std::string::String;
+
+    struct Point has drop { x: u64, y: u64 }
+
+    public entry fun addresses(addrs: vector<address>) {
+        debug::print(&addrs)
+    }
+
+    public entry fun strings(s: String, bytes: vector<u8>) {
+        debug::print(&s);
+        debug::print(&bytes)
+    }
+
+    public entry fun maybe(o: Option<u64>) {
+        if (option::is_some(&o)) debug::print(option::borrow(&o))
+        else debug::print(&false)
+    }
+
+    public entry fun point(s: &signer, p: Point) {
+        debug::print(s);
+        debug::print(&p)
+    }
+
+    public entry fun numbers(a: u8, b: u128, c: vector<u64>) {
+        debug::print(&a);
+        debug::print(&b);
+        debug::print(&c)
+    }
+}
@@ -0,0 +1,323 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! The source-level program reconstructed by the decompiler, and its rendering as Move code.
+
+use std::fmt::Write;
+
+/// An expression. Names of locals, functions and types are already resolved to the strings they
+/// are rendered as.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Exp {
+    Var(String),
+    Value(String),
+    /// A call of a function or builtin; the callee includes any type arguments.
+    Call(String, Vec<Exp>),
+    Unary(&'static str, Box<Exp>),
+    Binary(&'static str, Box<Exp>, Box<Exp>),
+    /// `&e` or `&mut e`, where `e` is a place.
+    Borrow(bool, Box<Exp>),
+    /// `e.f`, where `e` is a struct value or a reference to one.
+    Field(Box<Exp>, String),
+    Pack(String, Vec<(String, Exp)>),
+    Cast(Box<Exp>, String),
+    /// A vector literal; the prefix is `vector<T>`.
+    Vector(String, Vec<Exp>),
+}
+
+/// The left-hand side of a `let` or an assignment.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Bind {
+    Var(String),
+    Tuple(Vec<String>),
+    /// `S { f: x, .. }`, where the name of the struct includes any type arguments.
+    Unpack(String, Vec<(String, String)>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Stmt {
+    Let(Bind, Exp),
+    Assign(Bind, Exp),
+    /// `<place> = e`, where the place is a field or a dereferenced reference.
+    Mutate(Exp, Exp),
+    Exp(Exp),
+    If(Exp, Vec<Stmt>, Option<Vec<Stmt>>),
+    While(Exp, Vec<Stmt>),
+    Loop(Vec<Stmt>),
+    Break,
+    Continue,
+    Return(Vec<Exp>),
+    Abort(Exp),
+}
+
+impl Exp {
+    /// The logical negation of this expression.
+    pub fn negate(self) -> Exp {
+        match self {
+            Exp::Unary("!", e) => *e,
+            Exp::Binary("==", l, r) => Exp::Binary("!=", l, r),
+            Exp::Binary("!=", l, r) => Exp::Binary("==", l, r),
+            // All ordered types in Move are integers, so comparisons can be inverted.
+            Exp::Binary("<", l, r) => Exp::Binary(">=", l, r),
+            Exp::Binary(">=", l, r) => Exp::Binary("<", l, r),
+            Exp::Binary(">", l, r) => Exp::Binary("<=", l, r),
+            Exp::Binary("<=", l, r) => Exp::Binary(">", l, r),
+            e => Exp::Unary("!", Box::new(e)),
+        }
+    }
+
+    fn is_atomic(&self) -> bool {
+        matches!(
+            self,
+            Exp::Var(_) | Exp::Value(_) | Exp::Call(..) | Exp::Field(..) | Exp::Cast(..)
+        )
+    }
+
+    fn render_atomic(&self) -> String {
+        if self.is_atomic() {
+            self.to_string()
+        } else {
+            format!("({})", self)
+        }
+    }
+
+    fn render_operand(&self) -> String {
+        match self {
+            Exp::Binary(..) => format!("({})", self),
+            _ => self.to_string(),
+        }
+    }
+
+    /// Render an operand of the binary operator `op`, which is on the right-hand side of it if
+    /// `right` is set. Operators are left associative, and comparisons are never chained.
+    fn render_binary_operand(&self, op: &str, right: bool) -> String {
+        match self {
+            Exp::Binary(inner, ..)
+                if precedence(inner) < precedence(op)
+                    || (precedence(inner) == precedence(op)
+                        && (right || precedence(op) == COMPARISON)) =>
+            {
+                format!("({})", self)
+            }
+            _ => self.to_string(),
+        }
+    }
+}
+
+const COMPARISON: u32 = 5;
+
+/// The precedence of a binary operator, as defined by the Move parser.
+fn precedence(op: &str) -> u32 {
+    match op {
+        "||" => 3,
+        "&&" => 4,
+        "==" | "!=" | "<" | ">" | "<=" | ">=" => COMPARISON,
+        "|" => 7,
+        "^" => 8,
+        "&" => 9,
+        "<<" | ">>" => 10,
+        "+" | "-" => 11,
+        _ => 12,
+    }
+}
+
+impl std::fmt::Display for Exp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Exp::Var(s) | Exp::Value(s) => write!(f, "{}", s),
+            Exp::Call(callee, args) => write!(f, "{}({})", callee, comma(args)),
+            Exp::Unary(op, e) => write!(f, "{}{}", op, e.render_operand()),
+            Exp::Binary(op, l, r) => {
+                write!(
+                    f,
+                    "{} {} {}",
+                    l.render_binary_operand(op, false),
+                    op,
+                    r.render_binary_operand(op, true)
+                )
+            }
+            Exp::Borrow(is_mut, e) => {
+                write!(f, "&{}{}", if *is_mut { "mut " } else { "" }, e)
+            }
+            Exp::Field(e, field) => write!(f, "{}.{}", e.render_atomic(), field),
+            Exp::Pack(name, fields) => {
+                if fields.is_empty() {
+                    return write!(f, "{} {{}}", name);
+                }
+                let fields = fields
+                    .iter()
+                    .map(|(field, e)| format!("{}: {}", field, e))
+                    .collect::<Vec<_>>();
+                write!(f, "{} {{ {} }}", name, fields.join(", "))
+            }
+            Exp::Cast(e, ty) => write!(f, "({} as {})", e.render_operand(), ty),
+            Exp::Vector(prefix, elems) => write!(f, "{}[{}]", prefix, comma(elems)),
+        }
+    }
+}
+
+impl std::fmt::Display for Bind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Bind::Var(name) => write!(f, "{}", name),
+            Bind::Tuple(names) => write!(f, "({})", names.join(", ")),
+            Bind::Unpack(name, fields) if fields.is_empty() => write!(f, "{} {{}}", name),
+            Bind::Unpack(name, fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|(field, var)| format!("{}: {}", field, var))
+                    .collect::<Vec<_>>();
+                write!(f, "{} {{ {} }}", name, fields.join(", "))
+            }
+        }
+    }
+}
+
+fn comma(exps: &[Exp]) -> String {
+    exps.iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn tuple(exps: &[Exp]) -> String {
+    match exps {
+        [e] => e.to_string(),
+        _ => format!("({})", comma(exps)),
+    }
+}
+
+impl Stmt {
+    /// Whether control never reaches the end of this statement.
+    pub fn diverges(&self) -> bool {
+        match self {
+            Stmt::Break | Stmt::Continue | Stmt::Return(_) | Stmt::Abort(_) => true,
+            Stmt::If(_, then_, Some(else_)) => diverges(then_) && diverges(else_),
+            Stmt::Loop(body) => !breaks(body),
+            _ => false,
+        }
+    }
+
+    fn is_control(&self) -> bool {
+        matches!(
+            self,
+            Stmt::If(..)
+                | Stmt::While(..)
+                | Stmt::Loop(_)
+                | Stmt::Break
+                | Stmt::Continue
+                | Stmt::Return(_)
+                | Stmt::Abort(_)
+        )
+    }
+}
+
+/// Whether control never reaches the end of the sequence `stmts`.
+pub fn diverges(stmts: &[Stmt]) -> bool {
+    stmts.last().map_or(false, Stmt::diverges)
+}
+
+/// Whether `stmts` contain a `break` of the loop they are the body of.
+fn breaks(stmts: &[Stmt]) -> bool {
+    stmts.iter().any(|s| match s {
+        Stmt::Break => true,
+        Stmt::If(_, then_, else_) => breaks(then_) || else_.as_deref().map_or(false, breaks),
+        _ => false,
+    })
+}
+
+/// Render `stmts` as the contents of a block, indented by `indent` levels. When `tail` is given,
+/// it is rendered as the value of the block.
+pub fn render_block(out: &mut String, stmts: &[Stmt], tail: Option<&[Exp]>, indent: usize) {
+    for (i, stmt) in stmts.iter().enumerate() {
+        let last = i + 1 == stmts.len() && tail.is_none();
+        render_stmt(out, stmt, indent);
+        // A control statement at the end of a block is the value of that block, so it must not
+        // be followed by a semicolon: that would change the type of a diverging block to `()`.
+        if !(last && stmt.is_control()) {
+            out.push(';');
+        }
+        out.push('\n');
+    }
+    if let Some(exps) = tail {
+        if !exps.is_empty() {
+            let _ = writeln!(out, "{}{}", pad(indent), tuple(exps));
+        }
+    }
+}
+
+fn render_stmt(out: &mut String, stmt: &Stmt, indent: usize) {
+    let pad = pad(indent);
+    match stmt {
+        Stmt::Let(bind, e) => {
+            let _ = write!(out, "{}let {} = {}", pad, bind, e);
+        }
+        Stmt::Assign(bind, e) => {
+            let _ = write!(out, "{}{} = {}", pad, bind, e);
+        }
+        Stmt::Mutate(place, e) => {
+            let _ = write!(out, "{}{} = {}", pad, place, e);
+        }
+        Stmt::Exp(e) => {
+            let _ = write!(out, "{}{}", pad, e);
+        }
+        // This is what `assert!` expands to.
+        Stmt::If(cond, then_, None) if matches!(then_.as_slice(), [Stmt::Abort(_)]) => {
+            if let [Stmt::Abort(code)] = then_.as_slice() {
+                let _ = write!(out, "{}assert!({}, {})", pad, cond.clone().negate(), code);
+            }
+        }
+        Stmt::If(cond, then_, else_) => {
+            let _ = write!(out, "{}if ({}) ", pad, cond);
+            render_braced(out, then_, indent);
+            if let Some(else_) = else_ {
+                out.push_str(" else ");
+                match else_.as_slice() {
+                    [nested @ Stmt::If(..)] => {
+                        let mut rendered = String::new();
+                        render_stmt(&mut rendered, nested, indent);
+                        out.push_str(rendered.trim_start());
+                    }
+                    _ => render_braced(out, else_, indent),
+                }
+            }
+        }
+        Stmt::While(cond, body) => {
+            let _ = write!(out, "{}while ({}) ", pad, cond);
+            render_braced(out, body, indent);
+        }
+        Stmt::Loop(body) => {
+            let _ = write!(out, "{}loop ", pad);
+            render_braced(out, body, indent);
+        }
+        Stmt::Break => {
+            let _ = write!(out, "{}break", pad);
+        }
+        Stmt::Continue => {
+            let _ = write!(out, "{}continue", pad);
+        }
+        Stmt::Return(exps) if exps.is_empty() => {
+            let _ = write!(out, "{}return", pad);
+        }
+        Stmt::Return(exps) => {
+            let _ = write!(out, "{}return {}", pad, tuple(exps));
+        }
+        Stmt::Abort(e) => {
+            let _ = write!(out, "{}abort {}", pad, e);
+        }
+    }
+}
+
+fn render_braced(out: &mut String, stmts: &[Stmt], indent: usize) {
+    if stmts.is_empty() {
+        out.push_str("{ }");
+        return;
+    }
+    out.push_str("{\n");
+    render_block(out, stmts, None, indent + 1);
+    let _ = write!(out, "{}}}", pad(indent));
+}
+
+pub fn pad(indent: usize) -> String {
+    "    ".repeat(indent)
+}
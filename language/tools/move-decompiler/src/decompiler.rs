@@ -0,0 +1,525 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    ast::{pad, render_block, Stmt},
+    structure::structure,
+    translate::FunctionTranslator,
+};
+use anyhow::{bail, Result};
+use move_binary_format::{
+    access::ModuleAccess,
+    control_flow_graph::{ControlFlowGraph, VMControlFlowGraph},
+    file_format::{
+        Ability, AbilitySet, CompiledModule, Constant, FunctionDefinition, FunctionDefinitionIndex,
+        FunctionHandleIndex, ModuleHandleIndex, SignatureToken, StructDefinition,
+        StructDefinitionIndex, StructFieldInformation, StructHandleIndex, Visibility,
+    },
+};
+use move_bytecode_source_map::source_map::{SourceMap, SourceName};
+use move_compiler::compiled_unit::{CompiledUnit, NamedCompiledModule};
+use move_core_types::value::MoveValue;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+};
+
+/// Reconstructs Move source code from a compiled module.
+///
+/// Control flow is recovered from the control flow graph of each function as structured
+/// `if`/`while`/`loop` statements, and expressions are rebuilt from the stack operations of each
+/// basic block. Names of type parameters and locals are taken from the source map when one is
+/// available; otherwise they are synthesized.
+pub struct Decompiler<'a> {
+    module: &'a CompiledModule,
+    source_map: Option<&'a SourceMap>,
+    /// The names under which other modules are referred to in the decompiled code.
+    module_aliases: BTreeMap<ModuleHandleIndex, String>,
+}
+
+impl<'a> Decompiler<'a> {
+    pub fn new(module: &'a CompiledModule, source_map: Option<&'a SourceMap>) -> Self {
+        let self_handle = module.self_handle_idx();
+        let self_name = module.identifier_at(module.self_handle().name).to_string();
+        let mut taken = BTreeSet::from([self_name]);
+        let mut module_aliases = BTreeMap::new();
+        for (idx, handle) in module.module_handles().iter().enumerate() {
+            let idx = ModuleHandleIndex(idx as u16);
+            if idx == self_handle {
+                continue;
+            }
+            let name = module.identifier_at(handle.name).to_string();
+            let mut alias = name.clone();
+            let mut suffix = 1;
+            while taken.contains(&alias) {
+                alias = format!("{}_{}", name, suffix);
+                suffix += 1;
+            }
+            taken.insert(alias.clone());
+            module_aliases.insert(idx, alias);
+        }
+        Self {
+            module,
+            source_map,
+            module_aliases,
+        }
+    }
+
+    pub fn from_unit(unit: &'a CompiledUnit) -> Result<Self> {
+        match unit {
+            CompiledUnit::Module(NamedCompiledModule {
+                module, source_map, ..
+            }) => Ok(Self::new(module, Some(source_map))),
+            CompiledUnit::Script(_) => bail!("Decompilation of scripts is not supported"),
+        }
+    }
+
+    pub fn decompile(&self) -> Result<String> {
+        let module = self.module;
+        let mut out = String::new();
+        let self_id = module.self_id();
+        writeln!(
+            out,
+            "module {}::{} {{",
+            self_id.address().to_hex_literal(),
+            self_id.name()
+        )?;
+
+        let mut header = vec![];
+        for (idx, alias) in &self.module_aliases {
+            let handle = module.module_handle_at(*idx);
+            let name = module.identifier_at(handle.name);
+            let address = module
+                .address_identifier_at(handle.address)
+                .to_hex_literal();
+            if name.as_str() == alias {
+                header.push(format!("use {}::{};", address, name));
+            } else {
+                header.push(format!("use {}::{} as {};", address, name, alias));
+            }
+        }
+        for friend in module.friend_decls() {
+            header.push(format!(
+                "friend {}::{};",
+                module
+                    .address_identifier_at(friend.address)
+                    .to_hex_literal(),
+                module.identifier_at(friend.name)
+            ));
+        }
+        let mut sections = vec![];
+        if !header.is_empty() {
+            sections.push(
+                header
+                    .iter()
+                    .map(|line| format!("{}{}\n", pad(1), line))
+                    .collect::<String>(),
+            );
+        }
+        for (idx, def) in module.struct_defs().iter().enumerate() {
+            sections.push(self.struct_string(StructDefinitionIndex(idx as u16), def));
+        }
+        for (idx, def) in module.function_defs().iter().enumerate() {
+            sections.push(self.function_string(FunctionDefinitionIndex(idx as u16), def)?);
+        }
+        out.push_str(&sections.join("\n"));
+        out.push_str("}\n");
+        Ok(out)
+    }
+
+    //***************************************************************************
+    // Definitions
+    //***************************************************************************
+
+    fn struct_string(&self, idx: StructDefinitionIndex, def: &StructDefinition) -> String {
+        let handle = self.module.struct_handle_at(def.struct_handle);
+        let names = self.struct_type_parameter_names(idx);
+        let type_parameters = handle
+            .type_parameters
+            .iter()
+            .zip(&names)
+            .map(|(param, name)| {
+                let phantom = if param.is_phantom { "phantom " } else { "" };
+                format!(
+                    "{}{}{}",
+                    phantom,
+                    name,
+                    constraints_string(param.constraints)
+                )
+            })
+            .collect::<Vec<_>>();
+        let mut out = format!(
+            "{}{}struct {}{}",
+            pad(1),
+            match def.field_information {
+                StructFieldInformation::Native => "native ",
+                StructFieldInformation::Declared(_) => "",
+            },
+            self.module.identifier_at(handle.name),
+            angle_list(&type_parameters),
+        );
+        if handle.abilities != AbilitySet::EMPTY {
+            let _ = write!(out, " has {}", abilities(handle.abilities).join(", "));
+        }
+        match &def.field_information {
+            StructFieldInformation::Native => out.push_str(";\n"),
+            StructFieldInformation::Declared(fields) => {
+                out.push_str(" {\n");
+                let fields = fields
+                    .iter()
+                    .map(|field| {
+                        format!(
+                            "{}{}: {}",
+                            pad(2),
+                            self.module.identifier_at(field.name),
+                            self.type_string(&field.signature.0, &names)
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                if !fields.is_empty() {
+                    out.push_str(&fields.join(",\n"));
+                    out.push('\n');
+                }
+                let _ = writeln!(out, "{}}}", pad(1));
+            }
+        }
+        out
+    }
+
+    fn function_string(
+        &self,
+        idx: FunctionDefinitionIndex,
+        def: &FunctionDefinition,
+    ) -> Result<String> {
+        let module = self.module;
+        let handle = module.function_handle_at(def.function);
+        let type_parameter_names = self.function_type_parameter_names(idx, def.function);
+        let mut translator = FunctionTranslator::new(self, idx, def, type_parameter_names.clone());
+
+        let mut out = pad(1);
+        match def.visibility {
+            Visibility::Private => (),
+            Visibility::Public => out.push_str("public "),
+            Visibility::Friend => out.push_str("public(friend) "),
+        }
+        if def.is_entry {
+            out.push_str("entry ");
+        }
+        if def.is_native() {
+            out.push_str("native ");
+        }
+        let type_parameters = handle
+            .type_parameters
+            .iter()
+            .zip(&type_parameter_names)
+            .map(|(constraints, name)| format!("{}{}", name, constraints_string(*constraints)))
+            .collect::<Vec<_>>();
+        let parameters = module
+            .signature_at(handle.parameters)
+            .0
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| {
+                format!(
+                    "{}: {}",
+                    translator.local_name(i as u8),
+                    self.type_string(ty, &type_parameter_names)
+                )
+            })
+            .collect::<Vec<_>>();
+        write!(
+            out,
+            "fun {}{}({})",
+            module.identifier_at(handle.name),
+            angle_list(&type_parameters),
+            parameters.join(", ")
+        )?;
+        let return_ = &module.signature_at(handle.return_).0;
+        match return_.as_slice() {
+            [] => (),
+            [ty] => write!(out, ": {}", self.type_string(ty, &type_parameter_names))?,
+            tys => write!(
+                out,
+                ": ({})",
+                tys.iter()
+                    .map(|ty| self.type_string(ty, &type_parameter_names))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?,
+        }
+        if !def.acquires_global_resources.is_empty() {
+            let acquires = def
+                .acquires_global_resources
+                .iter()
+                .map(|idx| self.struct_handle_string(module.struct_def_at(*idx).struct_handle))
+                .collect::<Vec<_>>();
+            write!(out, " acquires {}", acquires.join(", "))?;
+        }
+
+        let code = match &def.code {
+            None => {
+                out.push_str(";\n");
+                return Ok(out);
+            }
+            Some(code) => code,
+        };
+        out.push_str(" {\n");
+        let cfg = VMControlFlowGraph::new(&code.code);
+        let blocks = cfg
+            .reachable_from(cfg.entry_block_id())
+            .into_iter()
+            .map(|block| Ok((block, translator.translate_block(&cfg, block)?)))
+            .collect::<Result<BTreeMap<_, _>>>()?;
+        let mut body = structure(&blocks, || translator.fresh_name("pc"));
+        // A `return` at the very end of the function is rendered as the value of its body.
+        let tail = match body.last() {
+            Some(Stmt::Return(_)) => match body.pop() {
+                Some(Stmt::Return(exps)) => Some(exps),
+                _ => unreachable!(),
+            },
+            _ => None,
+        };
+        for (name, ty) in translator.declarations() {
+            writeln!(
+                out,
+                "{}let {}: {};",
+                pad(2),
+                name,
+                self.type_string(ty, &type_parameter_names)
+            )?;
+        }
+        render_block(&mut out, &body, tail.as_deref(), 2);
+        writeln!(out, "{}}}", pad(1))?;
+        Ok(out)
+    }
+
+    //***************************************************************************
+    // Names
+    //***************************************************************************
+
+    fn struct_type_parameter_names(&self, idx: StructDefinitionIndex) -> Vec<String> {
+        let def = self.module.struct_def_at(idx);
+        let count = self
+            .module
+            .struct_handle_at(def.struct_handle)
+            .type_parameters
+            .len();
+        let source_map = self
+            .source_map
+            .and_then(|source_map| source_map.get_struct_source_map(idx).ok());
+        type_parameter_names(count, |i| {
+            source_map.and_then(|source_map| source_map.get_type_parameter_name(i))
+        })
+    }
+
+    fn function_type_parameter_names(
+        &self,
+        idx: FunctionDefinitionIndex,
+        handle: FunctionHandleIndex,
+    ) -> Vec<String> {
+        let count = self.module.function_handle_at(handle).type_parameters.len();
+        let source_map = self
+            .source_map
+            .and_then(|source_map| source_map.get_function_source_map(idx).ok());
+        type_parameter_names(count, |i| {
+            source_map.and_then(|source_map| source_map.get_type_parameter_name(i))
+        })
+    }
+
+    pub(crate) fn source_map(&self) -> Option<&'a SourceMap> {
+        self.source_map
+    }
+
+    pub(crate) fn module(&self) -> &'a CompiledModule {
+        self.module
+    }
+
+    fn module_prefix(&self, handle: ModuleHandleIndex) -> String {
+        match self.module_aliases.get(&handle) {
+            Some(alias) => format!("{}::", alias),
+            None => String::new(),
+        }
+    }
+
+    pub(crate) fn struct_handle_string(&self, idx: StructHandleIndex) -> String {
+        let handle = self.module.struct_handle_at(idx);
+        format!(
+            "{}{}",
+            self.module_prefix(handle.module),
+            self.module.identifier_at(handle.name)
+        )
+    }
+
+    pub(crate) fn function_handle_string(&self, idx: FunctionHandleIndex) -> String {
+        let handle = self.module.function_handle_at(idx);
+        format!(
+            "{}{}",
+            self.module_prefix(handle.module),
+            self.module.identifier_at(handle.name)
+        )
+    }
+
+    pub(crate) fn type_string(&self, ty: &SignatureToken, type_parameters: &[String]) -> String {
+        use SignatureToken::*;
+        match ty {
+            Bool => "bool".to_string(),
+            U8 => "u8".to_string(),
+            U16 => "u16".to_string(),
+            U32 => "u32".to_string(),
+            U64 => "u64".to_string(),
+            U128 => "u128".to_string(),
+            U256 => "u256".to_string(),
+            Address => "address".to_string(),
+            Signer => "signer".to_string(),
+            Vector(ty) => format!("vector<{}>", self.type_string(ty, type_parameters)),
+            Struct(idx) => self.struct_handle_string(*idx),
+            StructInstantiation(idx, tys) => format!(
+                "{}{}",
+                self.struct_handle_string(*idx),
+                self.type_arguments_string(tys, type_parameters)
+            ),
+            Reference(ty) => format!("&{}", self.type_string(ty, type_parameters)),
+            MutableReference(ty) => format!("&mut {}", self.type_string(ty, type_parameters)),
+            TypeParameter(idx) => type_parameters
+                .get(*idx as usize)
+                .cloned()
+                .unwrap_or_else(|| format!("T{}", idx)),
+        }
+    }
+
+    pub(crate) fn type_arguments_string(
+        &self,
+        tys: &[SignatureToken],
+        type_parameters: &[String],
+    ) -> String {
+        angle_list(
+            &tys.iter()
+                .map(|ty| self.type_string(ty, type_parameters))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    pub(crate) fn constant_string(&self, constant: &Constant) -> String {
+        match constant.deserialize_constant() {
+            Some(value) => self.value_string(&value, &constant.type_),
+            // The bytecode verifier rejects constants that cannot be deserialized.
+            None => format!("/* invalid constant */ x\"{}\"", hex(&constant.data)),
+        }
+    }
+
+    fn value_string(&self, value: &MoveValue, ty: &SignatureToken) -> String {
+        match value {
+            MoveValue::U8(n) => format!("{}u8", n),
+            MoveValue::U16(n) => format!("{}u16", n),
+            MoveValue::U32(n) => format!("{}u32", n),
+            MoveValue::U64(n) => format!("{}", n),
+            MoveValue::U128(n) => format!("{}u128", n),
+            MoveValue::U256(n) => format!("{}u256", n),
+            MoveValue::Bool(b) => format!("{}", b),
+            MoveValue::Address(a) | MoveValue::Signer(a) => format!("@{}", a.to_hex_literal()),
+            MoveValue::Vector(elems) => {
+                let elem_ty = match ty {
+                    SignatureToken::Vector(elem_ty) => elem_ty.as_ref(),
+                    _ => ty,
+                };
+                if *elem_ty == SignatureToken::U8 && !elems.is_empty() {
+                    let bytes = elems
+                        .iter()
+                        .filter_map(|elem| match elem {
+                            MoveValue::U8(b) => Some(*b),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>();
+                    return byte_string(&bytes);
+                }
+                format!(
+                    "vector<{}>[{}]",
+                    self.type_string(elem_ty, &[]),
+                    elems
+                        .iter()
+                        .map(|elem| self.value_string(elem, elem_ty))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }
+            // Constants never contain structs.
+            MoveValue::Struct(_) => "/* struct constant */".to_string(),
+        }
+    }
+}
+
+fn type_parameter_names(
+    count: usize,
+    source_name: impl Fn(usize) -> Option<SourceName>,
+) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    for i in 0..count {
+        let name = match source_name(i) {
+            Some((name, _)) if is_identifier(&name) && !names.contains(&name) => name,
+            _ => format!("T{}", i),
+        };
+        names.push(name);
+    }
+    names
+}
+
+/// Whether `s` can be used as a name in Move source.
+pub(crate) fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => (),
+        _ => return false,
+    }
+    s != "_" && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn abilities(set: AbilitySet) -> Vec<&'static str> {
+    [
+        (Ability::Copy, "copy"),
+        (Ability::Drop, "drop"),
+        (Ability::Store, "store"),
+        (Ability::Key, "key"),
+    ]
+    .into_iter()
+    .filter(|(ability, _)| set.has_ability(*ability))
+    .map(|(_, name)| name)
+    .collect()
+}
+
+fn constraints_string(set: AbilitySet) -> String {
+    if set == AbilitySet::EMPTY {
+        String::new()
+    } else {
+        format!(": {}", abilities(set).join(" + "))
+    }
+}
+
+fn angle_list(items: &[String]) -> String {
+    if items.is_empty() {
+        String::new()
+    } else {
+        format!("<{}>", items.join(", "))
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Render `bytes` as a byte string literal, or as a hex string when it is not printable.
+fn byte_string(bytes: &[u8]) -> String {
+    if bytes.iter().all(|b| (0x20..0x7f).contains(b)) {
+        let mut out = String::from("b\"");
+        for b in bytes {
+            match b {
+                b'"' => out.push_str("\\\""),
+                b'\\' => out.push_str("\\\\"),
+                _ => out.push(*b as char),
+            }
+        }
+        out.push('"');
+        out
+    } else {
+        format!("x\"{}\"", hex(bytes))
+    }
+}
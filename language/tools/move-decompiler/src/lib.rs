@@ -0,0 +1,7 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+mod ast;
+pub mod decompiler;
+mod structure;
+mod translate;
@@ -0,0 +1,47 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![forbid(unsafe_code)]
+
+use clap::Parser;
+use move_binary_format::file_format::CompiledModule;
+use move_bytecode_source_map::utils::source_map_from_file;
+use move_command_line_common::files::{MOVE_COMPILED_EXTENSION, SOURCE_MAP_EXTENSION};
+use move_decompiler::decompiler::Decompiler;
+use std::{fs, path::Path};
+
+#[derive(Debug, Parser)]
+#[clap(author, version, about)]
+struct Args {
+    /// The path to the module bytecode file to decompile; let's call it file.mv. If a source map
+    /// file.mvsm resides under the same directory, it is used to recover the names of locals and
+    /// type parameters.
+    #[clap(short = 'b', long = "bytecode")]
+    pub bytecode_file_path: String,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let bytecode_path = Path::new(&args.bytecode_file_path);
+    let extension = bytecode_path
+        .extension()
+        .expect("Missing file extension for bytecode file");
+    if extension != MOVE_COMPILED_EXTENSION {
+        println!(
+            "Bad source file extension {:?}; expected {}",
+            extension, MOVE_COMPILED_EXTENSION
+        );
+        std::process::exit(1);
+    }
+
+    let bytecode_bytes = fs::read(bytecode_path).expect("Unable to read bytecode file");
+    let module =
+        CompiledModule::deserialize(&bytecode_bytes).expect("Module blob can't be deserialized");
+    let source_map = source_map_from_file(&bytecode_path.with_extension(SOURCE_MAP_EXTENSION)).ok();
+
+    let decompiled = Decompiler::new(&module, source_map.as_ref())
+        .decompile()
+        .expect("Unable to decompile");
+    print!("{}", decompiled);
+}
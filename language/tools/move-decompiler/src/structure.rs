@@ -0,0 +1,525 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Recovery of structured control flow from the control flow graph of a function.
+//!
+//! Loops are the natural loops of the back edges of the graph. Within a loop, a jump to its head
+//! becomes `continue` and a jump to the block following it becomes `break`. The two arms of a
+//! conditional branch are joined again at the immediate post-dominator of the branching block.
+//! Regions which end the function without rejoining the rest of the code, such as an `abort`
+//! inside of a loop, are inlined where they are jumped to.
+//!
+//! Graphs which cannot be expressed this way, which the Move compiler never produces, are rendered
+//! as a state machine that dispatches on the offset of the next block to run.
+
+use crate::{
+    ast::{diverges, Bind, Exp, Stmt},
+    translate::{BlockCode, Terminator},
+};
+use move_binary_format::file_format::CodeOffset;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    rc::Rc,
+};
+
+/// Turn the translated basic blocks of a function, keyed by their offset, into structured
+/// statements. `fresh_name` provides the name of the state variable if a state machine is needed.
+pub(crate) fn structure(
+    blocks: &BTreeMap<CodeOffset, BlockCode>,
+    fresh_name: impl FnOnce() -> String,
+) -> Vec<Stmt> {
+    match Structurer::new(blocks).and_then(|mut structurer| structurer.seq(0, None, None, false)) {
+        Some(stmts) => stmts,
+        None => state_machine(blocks, fresh_name()),
+    }
+}
+
+struct Loop {
+    head: usize,
+    body: BTreeSet<usize>,
+    follow: Option<usize>,
+}
+
+struct Structurer<'a> {
+    blocks: Vec<&'a BlockCode>,
+    index: BTreeMap<CodeOffset, usize>,
+    preds: Vec<Vec<usize>>,
+    succs: Vec<Vec<usize>>,
+    /// Immediate post-dominators; `None` for blocks that end the function, or never reach its end.
+    ipdom: Vec<Option<usize>>,
+    loops: BTreeMap<usize, Rc<Loop>>,
+    terminal: Vec<Option<bool>>,
+    /// Bounds the number of blocks emitted, which regions inlined in several places could
+    /// otherwise blow up.
+    fuel: usize,
+}
+
+impl<'a> Structurer<'a> {
+    /// Analyze the graph of `blocks`, or return `None` if it is irreducible.
+    fn new(blocks: &'a BTreeMap<CodeOffset, BlockCode>) -> Option<Self> {
+        let index = blocks
+            .keys()
+            .enumerate()
+            .map(|(i, offset)| (*offset, i))
+            .collect::<BTreeMap<_, _>>();
+        let n = blocks.len();
+        let succs = blocks
+            .values()
+            .map(|block| {
+                block
+                    .term
+                    .successors()
+                    .iter()
+                    .map(|offset| index[offset])
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let preds = predecessors(&succs);
+        let idom = dominators(&succs, 0);
+
+        // The exit node `n` succeeds every block which ends the function.
+        let mut reversed = preds.clone();
+        reversed.push(
+            (0..n)
+                .filter(|block| succs[*block].is_empty())
+                .collect::<Vec<_>>(),
+        );
+        let ipdom = dominators(&reversed, n)
+            .into_iter()
+            .take(n)
+            .map(|pdom| pdom.filter(|pdom| *pdom != n))
+            .collect();
+
+        // A retreating edge whose target does not dominate its source makes the graph irreducible.
+        let order = postorder(&succs, 0);
+        let mut rank = vec![0; n];
+        for (i, block) in order.iter().enumerate() {
+            rank[*block] = i;
+        }
+        let mut back_edges = BTreeMap::<usize, Vec<usize>>::new();
+        for (source, targets) in succs.iter().enumerate() {
+            for target in targets {
+                if rank[*target] >= rank[source] {
+                    if !dominates(&idom, *target, source) {
+                        return None;
+                    }
+                    back_edges.entry(*target).or_default().push(source);
+                }
+            }
+        }
+
+        let mut structurer = Self {
+            blocks: blocks.values().collect(),
+            index,
+            preds,
+            succs,
+            ipdom,
+            loops: BTreeMap::new(),
+            terminal: vec![None; n],
+            fuel: 16 * n + 64,
+        };
+        for (head, sources) in back_edges {
+            let body = structurer.natural_loop(head, &sources);
+            let follow = structurer.follow(head, &body)?;
+            structurer
+                .loops
+                .insert(head, Rc::new(Loop { head, body, follow }));
+        }
+        Some(structurer)
+    }
+
+    fn natural_loop(&self, head: usize, sources: &[usize]) -> BTreeSet<usize> {
+        let mut body = BTreeSet::from([head]);
+        let mut todo = sources.to_vec();
+        while let Some(block) = todo.pop() {
+            if body.insert(block) {
+                todo.extend(self.preds[block].iter().copied());
+            }
+        }
+        body
+    }
+
+    /// The block control continues with after the loop, which is the only exit of the loop that
+    /// does not lead into a terminal region, or, if all do, the most likely candidate among them.
+    fn follow(&mut self, head: usize, body: &BTreeSet<usize>) -> Option<Option<usize>> {
+        let exits = body
+            .iter()
+            .flat_map(|block| self.succs[*block].iter().copied())
+            .filter(|block| !body.contains(block))
+            .collect::<BTreeSet<_>>();
+        let continuing = exits
+            .iter()
+            .copied()
+            .filter(|exit| !self.is_terminal(*exit))
+            .collect::<Vec<_>>();
+        match continuing.as_slice() {
+            [] => Some(
+                exits
+                    .iter()
+                    .copied()
+                    .find(|exit| self.succs[head].contains(exit))
+                    .or_else(|| exits.iter().copied().max()),
+            ),
+            [follow] => Some(Some(*follow)),
+            _ => None,
+        }
+    }
+
+    /// Whether `block` starts a region which is entered only through `block` and never rejoins
+    /// the rest of the function.
+    fn is_terminal(&mut self, block: usize) -> bool {
+        if let Some(terminal) = self.terminal[block] {
+            return terminal;
+        }
+        let mut region = BTreeSet::from([block]);
+        let mut todo = vec![block];
+        while let Some(b) = todo.pop() {
+            for succ in &self.succs[b] {
+                if region.insert(*succ) {
+                    todo.push(*succ)
+                }
+            }
+        }
+        let terminal = region
+            .iter()
+            .filter(|b| **b != block)
+            .all(|b| self.preds[*b].iter().all(|pred| region.contains(pred)));
+        self.terminal[block] = Some(terminal);
+        terminal
+    }
+
+    /// Structure the code starting at `cur` until `stop` is reached or control leaves the
+    /// innermost loop `lp`. `in_head` indicates that `cur` is the head of `lp` and the loop has
+    /// been entered already.
+    fn seq(
+        &mut self,
+        mut cur: usize,
+        stop: Option<usize>,
+        lp: Option<&Rc<Loop>>,
+        mut in_head: bool,
+    ) -> Option<Vec<Stmt>> {
+        let mut out = vec![];
+        let mut visited = BTreeSet::new();
+        loop {
+            if Some(cur) == stop {
+                return Some(out);
+            }
+            if !visited.insert(cur) {
+                return None;
+            }
+            self.fuel = self.fuel.checked_sub(1)?;
+            if !in_head {
+                if let Some(inner) = self.loops.get(&cur).cloned() {
+                    let body = self.seq(cur, None, Some(&inner), true)?;
+                    out.push(make_loop(body));
+                    match inner.follow {
+                        Some(follow) => match self.jump(follow, stop, lp, &mut out)? {
+                            Some(next) => {
+                                cur = next;
+                                continue;
+                            }
+                            None => return Some(out),
+                        },
+                        None => return Some(out),
+                    }
+                }
+            }
+            in_head = false;
+            let block = self.blocks[cur];
+            out.extend(block.stmts.iter().cloned());
+            match &block.term {
+                Terminator::Return(exps) => {
+                    out.push(Stmt::Return(exps.clone()));
+                    return Some(out);
+                }
+                Terminator::Abort(e) => {
+                    out.push(Stmt::Abort(e.clone()));
+                    return Some(out);
+                }
+                Terminator::Jump(target) => {
+                    match self.jump(self.index[target], stop, lp, &mut out)? {
+                        Some(next) => cur = next,
+                        None => return Some(out),
+                    }
+                }
+                Terminator::Branch { cond, then_, else_ } => {
+                    let join = self.ipdom[cur]
+                        .filter(|join| in_scope(*join, lp) && lp.map_or(true, |l| l.head != *join));
+                    let arm_stop = join.or(stop);
+                    let then_arm = self.arm(self.index[then_], arm_stop, lp)?;
+                    let else_arm = self.arm(self.index[else_], arm_stop, lp)?;
+                    match join {
+                        Some(join) => {
+                            out.push(make_if(cond.clone(), then_arm, else_arm));
+                            cur = join;
+                        }
+                        None => {
+                            push_unjoined_if(&mut out, cond.clone(), then_arm, else_arm);
+                            return Some(out);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn arm(
+        &mut self,
+        target: usize,
+        stop: Option<usize>,
+        lp: Option<&Rc<Loop>>,
+    ) -> Option<Vec<Stmt>> {
+        let mut out = vec![];
+        if let Some(next) = self.jump(target, stop, lp, &mut out)? {
+            out.extend(self.seq(next, stop, lp, false)?);
+        }
+        Some(out)
+    }
+
+    /// Emit a jump to `target`. Returns the block to continue with if the jump is a plain
+    /// transfer of control within the current region.
+    fn jump(
+        &mut self,
+        target: usize,
+        stop: Option<usize>,
+        lp: Option<&Rc<Loop>>,
+        out: &mut Vec<Stmt>,
+    ) -> Option<Option<usize>> {
+        if Some(target) == stop {
+            return Some(None);
+        }
+        if let Some(lp) = lp {
+            if target == lp.head {
+                out.push(Stmt::Continue);
+                return Some(None);
+            }
+            if Some(target) == lp.follow {
+                out.push(Stmt::Break);
+                return Some(None);
+            }
+        }
+        if in_scope(target, lp) {
+            return Some(Some(target));
+        }
+        if self.is_terminal(target) {
+            out.extend(self.seq(target, None, None, false)?);
+            return Some(None);
+        }
+        None
+    }
+}
+
+fn in_scope(block: usize, lp: Option<&Rc<Loop>>) -> bool {
+    lp.map_or(true, |lp| lp.body.contains(&block))
+}
+
+fn make_if(cond: Exp, then_: Vec<Stmt>, else_: Vec<Stmt>) -> Stmt {
+    // The compiler translates `a && b` and `a || b` into conditionals assigning a temporary.
+    if let ([Stmt::Assign(Bind::Var(x), then_e)], [Stmt::Assign(Bind::Var(y), else_e)]) =
+        (then_.as_slice(), else_.as_slice())
+    {
+        if x == y {
+            let value = |e: &Exp, v: &str| matches!(e, Exp::Value(s) if s == v);
+            if value(else_e, "false") {
+                return Stmt::Assign(
+                    Bind::Var(x.clone()),
+                    Exp::Binary("&&", Box::new(cond), Box::new(then_e.clone())),
+                );
+            }
+            if value(then_e, "true") {
+                return Stmt::Assign(
+                    Bind::Var(x.clone()),
+                    Exp::Binary("||", Box::new(cond), Box::new(else_e.clone())),
+                );
+            }
+        }
+    }
+    if then_.is_empty() && !else_.is_empty() {
+        Stmt::If(cond.negate(), else_, None)
+    } else if else_.is_empty() {
+        Stmt::If(cond, then_, None)
+    } else {
+        Stmt::If(cond, then_, Some(else_))
+    }
+}
+
+/// Emit a conditional whose arms do not join again. If an arm diverges, the other one is
+/// emitted after the conditional rather than as its `else` branch.
+fn push_unjoined_if(out: &mut Vec<Stmt>, cond: Exp, then_: Vec<Stmt>, else_: Vec<Stmt>) {
+    let keep_then = match (diverges(&then_), diverges(&else_)) {
+        (true, true) => then_.len() <= else_.len(),
+        (true, false) => true,
+        (false, true) => false,
+        (false, false) => {
+            out.push(make_if(cond, then_, else_));
+            return;
+        }
+    };
+    if keep_then {
+        out.push(Stmt::If(cond, then_, None));
+        out.extend(else_);
+    } else {
+        out.push(Stmt::If(cond.negate(), else_, None));
+        out.extend(then_);
+    }
+}
+
+/// Build a loop from its body, recognizing `while` loops.
+fn make_loop(mut body: Vec<Stmt>) -> Stmt {
+    strip_continue(&mut body);
+    match body.first() {
+        Some(Stmt::If(cond, then_, None)) if then_ == &[Stmt::Break] => {
+            let cond = cond.clone().negate();
+            body.remove(0);
+            Stmt::While(cond, body)
+        }
+        _ => Stmt::Loop(body),
+    }
+}
+
+/// Remove `continue` statements in tail position of a loop body.
+fn strip_continue(stmts: &mut Vec<Stmt>) {
+    match stmts.pop() {
+        Some(Stmt::Continue) => (),
+        Some(Stmt::If(cond, mut then_, mut else_)) => {
+            strip_continue(&mut then_);
+            if let Some(else_) = &mut else_ {
+                strip_continue(else_);
+            }
+            stmts.push(make_if(cond, then_, else_.unwrap_or_default()));
+        }
+        Some(stmt) => stmts.push(stmt),
+        None => (),
+    }
+}
+
+/// Render the blocks as a loop which dispatches on the offset of the block to run next.
+fn state_machine(blocks: &BTreeMap<CodeOffset, BlockCode>, pc: String) -> Vec<Stmt> {
+    let goto =
+        |target: &CodeOffset| Stmt::Assign(Bind::Var(pc.clone()), Exp::Value(target.to_string()));
+    let mut arms = vec![];
+    for (offset, block) in blocks {
+        let mut body = block.stmts.clone();
+        match &block.term {
+            Terminator::Jump(target) => {
+                body.push(goto(target));
+                body.push(Stmt::Continue);
+            }
+            Terminator::Branch { cond, then_, else_ } => {
+                body.push(Stmt::If(
+                    cond.clone(),
+                    vec![goto(then_)],
+                    Some(vec![goto(else_)]),
+                ));
+                body.push(Stmt::Continue);
+            }
+            Terminator::Return(exps) => body.push(Stmt::Return(exps.clone())),
+            Terminator::Abort(e) => body.push(Stmt::Abort(e.clone())),
+        }
+        arms.push(Stmt::If(
+            Exp::Binary(
+                "==",
+                Box::new(Exp::Var(pc.clone())),
+                Box::new(Exp::Value(offset.to_string())),
+            ),
+            body,
+            None,
+        ));
+    }
+    vec![
+        Stmt::Let(Bind::Var(pc.clone()), Exp::Value("0".to_string())),
+        Stmt::Loop(arms),
+    ]
+}
+
+//***************************************************************************
+// Graph algorithms
+//***************************************************************************
+
+fn predecessors(succs: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let mut preds = vec![vec![]; succs.len()];
+    for (block, targets) in succs.iter().enumerate() {
+        for target in targets {
+            preds[*target].push(block);
+        }
+    }
+    preds
+}
+
+fn postorder(succs: &[Vec<usize>], entry: usize) -> Vec<usize> {
+    let mut order = vec![];
+    let mut visited = vec![false; succs.len()];
+    let mut stack = vec![(entry, 0)];
+    visited[entry] = true;
+    while let Some((block, next)) = stack.pop() {
+        match succs[block].get(next) {
+            Some(succ) => {
+                stack.push((block, next + 1));
+                if !visited[*succ] {
+                    visited[*succ] = true;
+                    stack.push((*succ, 0));
+                }
+            }
+            None => order.push(block),
+        }
+    }
+    order
+}
+
+/// The immediate dominators of the nodes of the graph given by `succs`, as computed by the
+/// algorithm of Cooper, Harvey and Kennedy. The entry dominates itself, and nodes which are not
+/// reachable from it have no dominator.
+fn dominators(succs: &[Vec<usize>], entry: usize) -> Vec<Option<usize>> {
+    let order = postorder(succs, entry);
+    let mut rank = vec![usize::MAX; succs.len()];
+    for (i, node) in order.iter().enumerate() {
+        rank[*node] = i;
+    }
+    let preds = predecessors(succs);
+    let mut idom = vec![None; succs.len()];
+    idom[entry] = Some(entry);
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for node in order.iter().rev().filter(|node| **node != entry) {
+            let mut new_idom: Option<usize> = None;
+            for pred in &preds[*node] {
+                if idom[*pred].is_none() {
+                    continue;
+                }
+                new_idom = Some(match new_idom {
+                    None => *pred,
+                    Some(other) => {
+                        let (mut a, mut b) = (*pred, other);
+                        while a != b {
+                            while rank[a] < rank[b] {
+                                a = idom[a].unwrap();
+                            }
+                            while rank[b] < rank[a] {
+                                b = idom[b].unwrap();
+                            }
+                        }
+                        a
+                    }
+                });
+            }
+            if new_idom != idom[*node] {
+                idom[*node] = new_idom;
+                changed = true;
+            }
+        }
+    }
+    idom
+}
+
+fn dominates(idom: &[Option<usize>], a: usize, mut b: usize) -> bool {
+    loop {
+        if a == b {
+            return true;
+        }
+        match idom[b] {
+            Some(next) if next != b => b = next,
+            _ => return false,
+        }
+    }
+}
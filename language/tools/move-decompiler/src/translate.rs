@@ -0,0 +1,731 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Translation of the basic blocks of a function into statements.
+//!
+//! The stack operations of a block are evaluated symbolically: instructions which produce a value
+//! push an expression, which is consumed by the instruction that pops it. Expressions are only
+//! materialized as statements when a value is stored, dropped, or otherwise consumed by an
+//! effect. Before such a statement is emitted, any expression still pending on the stack is bound
+//! to a fresh temporary, so that the order of evaluation of the bytecode is preserved.
+
+use crate::{
+    ast::{Bind, Exp, Stmt},
+    decompiler::{is_identifier, Decompiler},
+};
+use anyhow::{bail, Result};
+use move_binary_format::{
+    access::ModuleAccess,
+    control_flow_graph::{BlockId, ControlFlowGraph, VMControlFlowGraph},
+    file_format::{
+        Bytecode, CodeOffset, FieldHandleIndex, FunctionDefinition, FunctionDefinitionIndex,
+        FunctionHandleIndex, LocalIndex, SignatureIndex, SignatureToken, StructDefinitionIndex,
+        StructFieldInformation,
+    },
+};
+use std::collections::BTreeSet;
+
+/// The statements of a basic block, and how control leaves it.
+#[derive(Clone, Debug)]
+pub(crate) struct BlockCode {
+    pub stmts: Vec<Stmt>,
+    pub term: Terminator,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum Terminator {
+    Jump(CodeOffset),
+    Branch {
+        cond: Exp,
+        then_: CodeOffset,
+        else_: CodeOffset,
+    },
+    Return(Vec<Exp>),
+    Abort(Exp),
+}
+
+impl Terminator {
+    pub fn successors(&self) -> Vec<CodeOffset> {
+        match self {
+            Terminator::Jump(target) => vec![*target],
+            Terminator::Branch { then_, else_, .. } => vec![*then_, *else_],
+            Terminator::Return(_) | Terminator::Abort(_) => vec![],
+        }
+    }
+}
+
+pub(crate) struct FunctionTranslator<'a> {
+    decompiler: &'a Decompiler<'a>,
+    def: &'a FunctionDefinition,
+    type_parameters: Vec<String>,
+    parameter_count: usize,
+    local_types: Vec<SignatureToken>,
+    local_names: Vec<String>,
+    /// All names in use in the function, including temporaries.
+    taken: BTreeSet<String>,
+    /// Temporaries introduced by the decompiler. These are assigned exactly once.
+    temps: BTreeSet<String>,
+    next_temp: usize,
+    /// Locals (other than parameters) which are referred to by the decompiled code.
+    used: BTreeSet<LocalIndex>,
+}
+
+impl<'a> FunctionTranslator<'a> {
+    pub fn new(
+        decompiler: &'a Decompiler<'a>,
+        idx: FunctionDefinitionIndex,
+        def: &'a FunctionDefinition,
+        type_parameters: Vec<String>,
+    ) -> Self {
+        let module = decompiler.module();
+        let handle = module.function_handle_at(def.function);
+        let mut local_types = module.signature_at(handle.parameters).0.clone();
+        let parameter_count = local_types.len();
+        if let Some(code) = &def.code {
+            local_types.extend(module.signature_at(code.locals).0.iter().cloned());
+        }
+        let mut taken = type_parameters.iter().cloned().collect::<BTreeSet<_>>();
+        let mut local_names = vec![];
+        for i in 0..local_types.len() {
+            // The compiler names locals `<name>#<counter>...`, and temporaries `%#<counter>`.
+            let source_name = decompiler
+                .source_map()
+                .and_then(|source_map| source_map.get_parameter_or_local_name(idx, i as u64).ok())
+                .map(|(name, _)| name.split('#').next().unwrap_or_default().to_string())
+                .filter(|name| is_identifier(name));
+            let base = match source_name {
+                Some(name) => name,
+                None if i < parameter_count => format!("a{}", i),
+                None => format!("l{}", i),
+            };
+            let mut name = base.clone();
+            let mut suffix = 1;
+            while taken.contains(&name) {
+                name = format!("{}_{}", base, suffix);
+                suffix += 1;
+            }
+            taken.insert(name.clone());
+            local_names.push(name);
+        }
+        Self {
+            decompiler,
+            def,
+            type_parameters,
+            parameter_count,
+            local_types,
+            local_names,
+            taken,
+            temps: BTreeSet::new(),
+            next_temp: 0,
+            used: BTreeSet::new(),
+        }
+    }
+
+    pub fn local_name(&self, idx: LocalIndex) -> &str {
+        &self.local_names[idx as usize]
+    }
+
+    /// The locals that need to be declared at the beginning of the function body.
+    pub fn declarations(&self) -> impl Iterator<Item = (&str, &SignatureToken)> {
+        self.used
+            .iter()
+            .map(|idx| (self.local_name(*idx), &self.local_types[*idx as usize]))
+    }
+
+    /// A name based on `base` which is not used anywhere else in the function.
+    pub fn fresh_name(&mut self, base: &str) -> String {
+        let mut name = base.to_string();
+        let mut suffix = 1;
+        while self.taken.contains(&name) {
+            name = format!("{}_{}", base, suffix);
+            suffix += 1;
+        }
+        self.taken.insert(name.clone());
+        name
+    }
+
+    fn fresh_temp(&mut self) -> String {
+        let name = loop {
+            let name = format!("t{}", self.next_temp);
+            self.next_temp += 1;
+            if !self.taken.contains(&name) {
+                break name;
+            }
+        };
+        self.taken.insert(name.clone());
+        self.temps.insert(name.clone());
+        name
+    }
+
+    fn local(&mut self, idx: LocalIndex) -> Exp {
+        if idx as usize >= self.parameter_count {
+            self.used.insert(idx);
+        }
+        Exp::Var(self.local_name(idx).to_string())
+    }
+
+    pub fn translate_block(
+        &mut self,
+        cfg: &VMControlFlowGraph,
+        block: BlockId,
+    ) -> Result<BlockCode> {
+        let code = &self.def.code.as_ref().expect("function with code").code;
+        let module = self.decompiler.module();
+        let mut stack: Vec<Exp> = vec![];
+        let mut stmts = vec![];
+        let mut term = None;
+
+        macro_rules! pop {
+            () => {
+                match stack.pop() {
+                    Some(e) => e,
+                    None => bail!("stack underflow in block {}", block),
+                }
+            };
+        }
+        macro_rules! unary {
+            ($op:expr) => {{
+                let e = pop!();
+                stack.push(Exp::Unary($op, Box::new(e)));
+            }};
+        }
+        macro_rules! binary {
+            ($op:expr) => {{
+                let r = pop!();
+                let l = pop!();
+                stack.push(Exp::Binary($op, Box::new(l), Box::new(r)));
+            }};
+        }
+        macro_rules! cast {
+            ($ty:expr) => {{
+                let e = pop!();
+                stack.push(Exp::Cast(Box::new(e), $ty.to_string()));
+            }};
+        }
+
+        for offset in cfg.block_start(block)..=cfg.block_end(block) {
+            use Bytecode::*;
+            match &code[offset as usize] {
+                Nop => (),
+                Pop => match pop!() {
+                    // Dropping a temporary means it is not needed in the first place.
+                    Exp::Var(name) if self.temps.contains(&name) => {
+                        ignore_temp(&mut stmts, &name);
+                    }
+                    // Dropping these has no effect in source code.
+                    Exp::Var(_) | Exp::Value(_) | Exp::Borrow(..) => (),
+                    e => {
+                        self.spill(&mut stack, &mut stmts);
+                        stmts.push(Stmt::Exp(e));
+                    }
+                },
+                Ret => {
+                    let count = module
+                        .signature_at(module.function_handle_at(self.def.function).return_)
+                        .len();
+                    let at = stack.len().saturating_sub(count);
+                    term = Some(Terminator::Return(stack.split_off(at)));
+                }
+                Abort => {
+                    let e = pop!();
+                    term = Some(Terminator::Abort(e));
+                }
+                Branch(target) => term = Some(Terminator::Jump(*target)),
+                BrTrue(target) => {
+                    let cond = pop!();
+                    term = Some(Terminator::Branch {
+                        cond,
+                        then_: *target,
+                        else_: offset + 1,
+                    })
+                }
+                BrFalse(target) => {
+                    let cond = pop!();
+                    term = Some(Terminator::Branch {
+                        cond,
+                        then_: offset + 1,
+                        else_: *target,
+                    })
+                }
+
+                LdU8(n) => stack.push(Exp::Value(format!("{}u8", n))),
+                LdU16(n) => stack.push(Exp::Value(format!("{}u16", n))),
+                LdU32(n) => stack.push(Exp::Value(format!("{}u32", n))),
+                LdU64(n) => stack.push(Exp::Value(format!("{}", n))),
+                LdU128(n) => stack.push(Exp::Value(format!("{}u128", n))),
+                LdU256(n) => stack.push(Exp::Value(format!("{}u256", n))),
+                LdTrue => stack.push(Exp::Value("true".to_string())),
+                LdFalse => stack.push(Exp::Value("false".to_string())),
+                LdConst(idx) => stack.push(Exp::Value(
+                    self.decompiler.constant_string(module.constant_at(*idx)),
+                )),
+                CastU8 => cast!("u8"),
+                CastU16 => cast!("u16"),
+                CastU32 => cast!("u32"),
+                CastU64 => cast!("u64"),
+                CastU128 => cast!("u128"),
+                CastU256 => cast!("u256"),
+
+                CopyLoc(idx) | MoveLoc(idx) => {
+                    let e = self.local(*idx);
+                    stack.push(e)
+                }
+                StLoc(idx) => {
+                    let e = pop!();
+                    self.spill(&mut stack, &mut stmts);
+                    let name = self.local_name(*idx).to_string();
+                    self.local(*idx);
+                    stmts.push(Stmt::Assign(Bind::Var(name), e));
+                }
+                ImmBorrowLoc(idx) | MutBorrowLoc(idx) => {
+                    let e = self.local(*idx);
+                    let is_mut = matches!(&code[offset as usize], MutBorrowLoc(_));
+                    stack.push(Exp::Borrow(is_mut, Box::new(e)))
+                }
+                ImmBorrowField(idx) | MutBorrowField(idx) => {
+                    let is_mut = matches!(&code[offset as usize], MutBorrowField(_));
+                    let r = pop!();
+                    stack.push(Exp::Borrow(
+                        is_mut,
+                        Box::new(Exp::Field(Box::new(place(r)), self.field_name(*idx))),
+                    ))
+                }
+                ImmBorrowFieldGeneric(idx) | MutBorrowFieldGeneric(idx) => {
+                    let is_mut = matches!(&code[offset as usize], MutBorrowFieldGeneric(_));
+                    let handle = module.field_instantiation_at(*idx).handle;
+                    let r = pop!();
+                    stack.push(Exp::Borrow(
+                        is_mut,
+                        Box::new(Exp::Field(Box::new(place(r)), self.field_name(handle))),
+                    ))
+                }
+                ReadRef => {
+                    let r = pop!();
+                    stack.push(match r {
+                        Exp::Borrow(_, p) => *p,
+                        r => Exp::Unary("*", Box::new(r)),
+                    })
+                }
+                WriteRef => {
+                    let r = pop!();
+                    let v = pop!();
+                    self.spill(&mut stack, &mut stmts);
+                    let place = match r {
+                        Exp::Borrow(_, p) => *p,
+                        r => Exp::Unary("*", Box::new(r)),
+                    };
+                    match place {
+                        Exp::Var(name) => stmts.push(Stmt::Assign(Bind::Var(name), v)),
+                        place => stmts.push(Stmt::Mutate(place, v)),
+                    }
+                }
+                FreezeRef => {
+                    let r = pop!();
+                    stack.push(match r {
+                        Exp::Borrow(_, p) => Exp::Borrow(false, p),
+                        r => Exp::Call("freeze".to_string(), vec![r]),
+                    })
+                }
+
+                Call(idx) => {
+                    let callee = self.decompiler.function_handle_string(*idx);
+                    self.call(*idx, callee, &mut stack, &mut stmts)?
+                }
+                CallGeneric(idx) => {
+                    let inst = module.function_instantiation_at(*idx);
+                    let callee = format!(
+                        "{}{}",
+                        self.decompiler.function_handle_string(inst.handle),
+                        self.type_arguments(inst.type_parameters)
+                    );
+                    self.call(inst.handle, callee, &mut stack, &mut stmts)?
+                }
+                Pack(idx) => {
+                    let name = self.struct_string(*idx, None);
+                    self.pack(*idx, name, &mut stack)?
+                }
+                PackGeneric(idx) => {
+                    let inst = module.struct_instantiation_at(*idx);
+                    let name = self.struct_string(inst.def, Some(inst.type_parameters));
+                    self.pack(inst.def, name, &mut stack)?
+                }
+                Unpack(idx) => {
+                    let name = self.struct_string(*idx, None);
+                    let e = pop!();
+                    self.unpack(*idx, name, e, &mut stack, &mut stmts)
+                }
+                UnpackGeneric(idx) => {
+                    let inst = module.struct_instantiation_at(*idx);
+                    let name = self.struct_string(inst.def, Some(inst.type_parameters));
+                    let e = pop!();
+                    self.unpack(inst.def, name, e, &mut stack, &mut stmts)
+                }
+
+                Exists(idx) => {
+                    let s = self.struct_string(*idx, None);
+                    let a = pop!();
+                    stack.push(Exp::Call(format!("exists<{}>", s), vec![a]))
+                }
+                ExistsGeneric(idx) => {
+                    let inst = module.struct_instantiation_at(*idx);
+                    let s = self.struct_string(inst.def, Some(inst.type_parameters));
+                    let a = pop!();
+                    stack.push(Exp::Call(format!("exists<{}>", s), vec![a]))
+                }
+                ImmBorrowGlobal(idx) | MutBorrowGlobal(idx) => {
+                    let is_mut = matches!(&code[offset as usize], MutBorrowGlobal(_));
+                    let s = self.struct_string(*idx, None);
+                    let a = pop!();
+                    stack.push(borrow_global(is_mut, s, a))
+                }
+                ImmBorrowGlobalGeneric(idx) | MutBorrowGlobalGeneric(idx) => {
+                    let is_mut = matches!(&code[offset as usize], MutBorrowGlobalGeneric(_));
+                    let inst = module.struct_instantiation_at(*idx);
+                    let s = self.struct_string(inst.def, Some(inst.type_parameters));
+                    let a = pop!();
+                    stack.push(borrow_global(is_mut, s, a))
+                }
+                MoveFrom(idx) => {
+                    let s = self.struct_string(*idx, None);
+                    let a = pop!();
+                    stack.push(Exp::Call(format!("move_from<{}>", s), vec![a]))
+                }
+                MoveFromGeneric(idx) => {
+                    let inst = module.struct_instantiation_at(*idx);
+                    let s = self.struct_string(inst.def, Some(inst.type_parameters));
+                    let a = pop!();
+                    stack.push(Exp::Call(format!("move_from<{}>", s), vec![a]))
+                }
+                MoveTo(idx) => {
+                    let s = self.struct_string(*idx, None);
+                    let v = pop!();
+                    let signer = pop!();
+                    self.spill(&mut stack, &mut stmts);
+                    stmts.push(Stmt::Exp(Exp::Call(
+                        format!("move_to<{}>", s),
+                        vec![signer, v],
+                    )))
+                }
+                MoveToGeneric(idx) => {
+                    let inst = module.struct_instantiation_at(*idx);
+                    let s = self.struct_string(inst.def, Some(inst.type_parameters));
+                    let v = pop!();
+                    let signer = pop!();
+                    self.spill(&mut stack, &mut stmts);
+                    stmts.push(Stmt::Exp(Exp::Call(
+                        format!("move_to<{}>", s),
+                        vec![signer, v],
+                    )))
+                }
+
+                Add => binary!("+"),
+                Sub => binary!("-"),
+                Mul => binary!("*"),
+                Mod => binary!("%"),
+                Div => binary!("/"),
+                BitOr => binary!("|"),
+                BitAnd => binary!("&"),
+                Xor => binary!("^"),
+                Shl => binary!("<<"),
+                Shr => binary!(">>"),
+                Or => binary!("||"),
+                And => binary!("&&"),
+                Eq => binary!("=="),
+                Neq => binary!("!="),
+                Lt => binary!("<"),
+                Gt => binary!(">"),
+                Le => binary!("<="),
+                Ge => binary!(">="),
+                Not => unary!("!"),
+
+                // Vector instructions are rendered as calls of the functions in the standard
+                // library `vector` module they are the bytecode counterparts of.
+                VecPack(sig, count) => {
+                    let at = stack.len().saturating_sub(*count as usize);
+                    let elems = stack.split_off(at);
+                    let prefix = format!("vector{}", self.type_arguments(*sig));
+                    stack.push(Exp::Vector(prefix, elems))
+                }
+                VecLen(sig) => {
+                    let v = pop!();
+                    stack.push(self.vector_call("length", *sig, vec![v]))
+                }
+                VecImmBorrow(sig) | VecMutBorrow(sig) => {
+                    let name = if matches!(&code[offset as usize], VecMutBorrow(_)) {
+                        "borrow_mut"
+                    } else {
+                        "borrow"
+                    };
+                    let i = pop!();
+                    let v = pop!();
+                    stack.push(self.vector_call(name, *sig, vec![v, i]))
+                }
+                VecPushBack(sig) => {
+                    let e = pop!();
+                    let v = pop!();
+                    self.spill(&mut stack, &mut stmts);
+                    stmts.push(Stmt::Exp(self.vector_call("push_back", *sig, vec![v, e])))
+                }
+                VecPopBack(sig) => {
+                    let v = pop!();
+                    stack.push(self.vector_call("pop_back", *sig, vec![v]))
+                }
+                VecSwap(sig) => {
+                    let j = pop!();
+                    let i = pop!();
+                    let v = pop!();
+                    self.spill(&mut stack, &mut stmts);
+                    stmts.push(Stmt::Exp(self.vector_call("swap", *sig, vec![v, i, j])))
+                }
+                VecUnpack(sig, count) => {
+                    let v = pop!();
+                    self.spill(&mut stack, &mut stmts);
+                    let vector = self.fresh_temp();
+                    stmts.push(Stmt::Let(Bind::Var(vector.clone()), v));
+                    let borrow = Exp::Borrow(true, Box::new(Exp::Var(vector.clone())));
+                    let mut elems = vec![];
+                    for _ in 0..*count {
+                        let elem = self.fresh_temp();
+                        let pop_back = self.vector_call("pop_back", *sig, vec![borrow.clone()]);
+                        stmts.push(Stmt::Let(Bind::Var(elem.clone()), pop_back));
+                        elems.push(Exp::Var(elem));
+                    }
+                    stmts.push(Stmt::Exp(self.vector_call(
+                        "destroy_empty",
+                        *sig,
+                        vec![Exp::Var(vector)],
+                    )));
+                    elems.reverse();
+                    stack.extend(elems);
+                }
+            }
+        }
+        let term = match term {
+            Some(term) => term,
+            None => Terminator::Jump(cfg.block_end(block) + 1),
+        };
+        if !stack.is_empty() {
+            bail!("stack not empty at the end of block {}", block)
+        }
+        collapse_assignments(&mut stmts, &self.temps);
+        Ok(BlockCode { stmts, term })
+    }
+
+    /// Bind all expressions pending on the stack to temporaries, unless they are literals or
+    /// temporaries already, whose values cannot be affected by the statement about to be emitted.
+    fn spill(&mut self, stack: &mut [Exp], stmts: &mut Vec<Stmt>) {
+        for e in stack.iter_mut() {
+            let stable = match e {
+                Exp::Value(_) => true,
+                Exp::Var(name) => self.temps.contains(name),
+                _ => false,
+            };
+            if !stable {
+                let temp = self.fresh_temp();
+                let value = std::mem::replace(e, Exp::Var(temp.clone()));
+                stmts.push(Stmt::Let(Bind::Var(temp), value));
+            }
+        }
+    }
+
+    fn call(
+        &mut self,
+        handle: FunctionHandleIndex,
+        callee: String,
+        stack: &mut Vec<Exp>,
+        stmts: &mut Vec<Stmt>,
+    ) -> Result<()> {
+        let module = self.decompiler.module();
+        let handle = module.function_handle_at(handle);
+        let arg_count = module.signature_at(handle.parameters).len();
+        let return_count = module.signature_at(handle.return_).len();
+        if stack.len() < arg_count {
+            bail!("stack underflow in call of {}", callee)
+        }
+        let args = stack.split_off(stack.len() - arg_count);
+        let call = Exp::Call(callee, args);
+        match return_count {
+            0 => {
+                self.spill(stack, stmts);
+                stmts.push(Stmt::Exp(call))
+            }
+            1 => stack.push(call),
+            _ => {
+                self.spill(stack, stmts);
+                let temps = (0..return_count)
+                    .map(|_| self.fresh_temp())
+                    .collect::<Vec<_>>();
+                stmts.push(Stmt::Let(Bind::Tuple(temps.clone()), call));
+                stack.extend(temps.into_iter().map(Exp::Var));
+            }
+        }
+        Ok(())
+    }
+
+    fn pack(
+        &mut self,
+        idx: StructDefinitionIndex,
+        name: String,
+        stack: &mut Vec<Exp>,
+    ) -> Result<()> {
+        let fields = self.field_names(idx);
+        if stack.len() < fields.len() {
+            bail!("stack underflow in pack of {}", name)
+        }
+        let values = stack.split_off(stack.len() - fields.len());
+        stack.push(Exp::Pack(name, fields.into_iter().zip(values).collect()));
+        Ok(())
+    }
+
+    fn unpack(
+        &mut self,
+        idx: StructDefinitionIndex,
+        name: String,
+        e: Exp,
+        stack: &mut Vec<Exp>,
+        stmts: &mut Vec<Stmt>,
+    ) {
+        self.spill(stack, stmts);
+        let fields = self
+            .field_names(idx)
+            .into_iter()
+            .map(|field| (field, self.fresh_temp()))
+            .collect::<Vec<_>>();
+        stack.extend(fields.iter().map(|(_, temp)| Exp::Var(temp.clone())));
+        stmts.push(Stmt::Let(Bind::Unpack(name, fields), e));
+    }
+
+    fn vector_call(&self, name: &str, sig: SignatureIndex, args: Vec<Exp>) -> Exp {
+        Exp::Call(
+            format!("0x1::vector::{}{}", name, self.type_arguments(sig)),
+            args,
+        )
+    }
+
+    fn type_arguments(&self, sig: SignatureIndex) -> String {
+        let tys = &self.decompiler.module().signature_at(sig).0;
+        self.decompiler
+            .type_arguments_string(tys, &self.type_parameters)
+    }
+
+    fn struct_string(&self, idx: StructDefinitionIndex, inst: Option<SignatureIndex>) -> String {
+        let def = self.decompiler.module().struct_def_at(idx);
+        let name = self.decompiler.struct_handle_string(def.struct_handle);
+        match inst {
+            Some(sig) => format!("{}{}", name, self.type_arguments(sig)),
+            None => name,
+        }
+    }
+
+    fn field_names(&self, idx: StructDefinitionIndex) -> Vec<String> {
+        let module = self.decompiler.module();
+        match &module.struct_def_at(idx).field_information {
+            StructFieldInformation::Native => vec![],
+            StructFieldInformation::Declared(fields) => fields
+                .iter()
+                .map(|field| module.identifier_at(field.name).to_string())
+                .collect(),
+        }
+    }
+
+    fn field_name(&self, idx: FieldHandleIndex) -> String {
+        let module = self.decompiler.module();
+        let handle = module.field_handle_at(idx);
+        self.field_names(handle.owner)[handle.field as usize].clone()
+    }
+}
+
+/// The place a reference points to: `x` for `&x`, otherwise the reference itself, which is
+/// dereferenced automatically by field accesses.
+fn place(r: Exp) -> Exp {
+    match r {
+        Exp::Borrow(_, p) => *p,
+        r => r,
+    }
+}
+
+fn borrow_global(is_mut: bool, s: String, a: Exp) -> Exp {
+    let name = if is_mut {
+        "borrow_global_mut"
+    } else {
+        "borrow_global"
+    };
+    Exp::Call(format!("{}<{}>", name, s), vec![a])
+}
+
+/// Replace the binding of the temporary `name` by `_`.
+fn ignore_temp(stmts: &mut [Stmt], name: &str) {
+    for stmt in stmts.iter_mut().rev() {
+        let names = match stmt {
+            Stmt::Let(Bind::Var(bound), _) => vec![bound],
+            Stmt::Let(Bind::Tuple(bound), _) => bound.iter_mut().collect(),
+            Stmt::Let(Bind::Unpack(_, fields), _) => {
+                fields.iter_mut().map(|(_, bound)| bound).collect()
+            }
+            _ => continue,
+        };
+        for bound in names {
+            if bound == name {
+                *bound = "_".to_string();
+                return;
+            }
+        }
+    }
+}
+
+/// Turn `let (t1, .., tn) = e; x1 = t1; ..; xn = tn;` into `(x1, .., xn) = e;`, and likewise for
+/// unpacks, when the temporaries are not used otherwise.
+fn collapse_assignments(stmts: &mut Vec<Stmt>, temps: &BTreeSet<String>) {
+    let mut i = 0;
+    while i < stmts.len() {
+        let bound = match &stmts[i] {
+            Stmt::Let(Bind::Tuple(names), _) => names.clone(),
+            Stmt::Let(Bind::Unpack(_, fields), _) => {
+                fields.iter().map(|(_, name)| name.clone()).collect()
+            }
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        let following = &stmts[i + 1..];
+        let mut targets = bound
+            .iter()
+            .map(|name| (name == "_").then(|| name.clone()))
+            .collect::<Vec<_>>();
+        let assignments = targets.iter().filter(|target| target.is_none()).count();
+        let mut assigned = BTreeSet::new();
+        for stmt in following.iter().take(assignments) {
+            match stmt {
+                Stmt::Assign(Bind::Var(x), Exp::Var(t))
+                    if temps.contains(t) && !temps.contains(x) && assigned.insert(x.clone()) =>
+                {
+                    if let Some(pos) = bound.iter().position(|b| b == t) {
+                        targets[pos] = Some(x.clone());
+                    }
+                }
+                _ => break,
+            }
+        }
+        if targets.iter().all(Option::is_some) && assignments > 0 {
+            let targets = targets.into_iter().map(Option::unwrap).collect::<Vec<_>>();
+            let (bind, e) = match stmts.remove(i) {
+                Stmt::Let(Bind::Tuple(_), e) => (Bind::Tuple(targets), e),
+                Stmt::Let(Bind::Unpack(name, fields), e) => (
+                    Bind::Unpack(
+                        name,
+                        fields
+                            .into_iter()
+                            .map(|(field, _)| field)
+                            .zip(targets)
+                            .collect(),
+                    ),
+                    e,
+                ),
+                _ => unreachable!(),
+            };
+            stmts.splice(i..i + assignments, [Stmt::Assign(bind, e)]);
+        }
+        i += 1;
+    }
+}
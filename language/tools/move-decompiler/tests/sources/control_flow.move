@@ -0,0 +1,64 @@
+module 0x42::control_flow {
+    const EINVALID: u64 = 7;
+
+    public fun max(a: u64, b: u64): u64 {
+        if (a > b) a else b
+    }
+
+    public fun sum(n: u64): u64 {
+        let i = 0;
+        let sum = 0;
+        while (i < n) {
+            i = i + 1;
+            if (i % 3 == 0) continue;
+            sum = sum + i;
+        };
+        sum
+    }
+
+    public fun find(v: &vector<u64>, x: u64): (bool, u64) {
+        let i = 0;
+        let len = std::vector::length(v);
+        while (i < len) {
+            if (*std::vector::borrow(v, i) == x) return (true, i);
+            i = i + 1;
+        };
+        (false, 0)
+    }
+
+    public fun first_multiple(n: u64, m: u64): u64 {
+        assert!(m > 0, EINVALID);
+        let i = 1;
+        loop {
+            if (i * m >= n) break;
+            i = i + 1;
+        };
+        i * m
+    }
+
+    public fun nested(n: u64): u64 {
+        let count = 0;
+        let i = 0;
+        while (i < n) {
+            let j = 0;
+            while (j < i) {
+                if (i > 10 && j > 10) abort EINVALID;
+                count = count + 1;
+                j = j + 1;
+            };
+            i = i + 1;
+        };
+        count
+    }
+
+    public fun classify(x: u64): u8 {
+        let result = if (x == 0) {
+            0
+        } else if (x < 10 || x > 100) {
+            1
+        } else {
+            2
+        };
+        result
+    }
+}
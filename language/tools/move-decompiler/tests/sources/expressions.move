@@ -0,0 +1,52 @@
+module 0x42::expressions {
+    use std::vector;
+
+    public fun arith(a: u64, b: u64): u64 {
+        (a + b) * (a - b) / 2 % 7
+    }
+
+    public fun bits(a: u8, b: u8): u8 {
+        ((a & b) | (a ^ b)) << 1 >> 2
+    }
+
+    public fun casts(a: u8): u128 {
+        ((a as u64) as u128) + 1u128
+    }
+
+    public fun logic(a: bool, b: bool): bool {
+        !a && (b || !b)
+    }
+
+    public fun vectors(): vector<u64> {
+        let v = vector[1, 2, 3];
+        vector::push_back(&mut v, 4);
+        let last = vector::pop_back(&mut v);
+        vector::push_back(&mut v, last + 1);
+        v
+    }
+
+    public fun multiple(): (u64, bool, vector<u8>) {
+        (1, true, vector::empty())
+    }
+
+    public fun use_multiple(): u64 {
+        let (a, b, _) = multiple();
+        if (b) a else 0
+    }
+
+    public fun refs(v: &mut vector<u64>, i: u64): u64 {
+        let r = vector::borrow_mut(v, i);
+        *r = *r + 1;
+        let x = *r;
+        let f = freeze(r);
+        x + *f
+    }
+
+    public fun generic<T: copy + drop>(x: T): vector<T> {
+        vector[x, x]
+    }
+
+    public fun call_generic(): vector<bool> {
+        generic(true)
+    }
+}
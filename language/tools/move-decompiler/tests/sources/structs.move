@@ -0,0 +1,85 @@
+module 0x42::coin {
+    friend 0x42::bank;
+
+    struct Coin<phantom T> has store {
+        value: u64,
+    }
+
+    struct Balance<phantom T> has key {
+        coin: Coin<T>,
+        frozen: bool,
+    }
+
+    struct Pair<A: copy + drop, B> has copy, drop {
+        first: A,
+        second: B,
+    }
+
+    const MAX: u128 = 340282366920938463463374607431768211455;
+    const OWNER: address = @0x42;
+    const NAME: vector<u8> = b"coin \"v1\"";
+    const BYTES: vector<u8> = x"00ff";
+
+    public fun mint<T>(value: u64): Coin<T> {
+        Coin { value }
+    }
+
+    public fun value<T>(coin: &Coin<T>): u64 {
+        coin.value
+    }
+
+    public(friend) fun burn<T>(coin: Coin<T>): u64 {
+        let Coin { value } = coin;
+        value
+    }
+
+    public fun join<T>(coin: &mut Coin<T>, other: Coin<T>) {
+        let Coin { value } = other;
+        coin.value = coin.value + value;
+    }
+
+    public fun publish<T>(account: &signer) {
+        move_to(account, Balance<T> { coin: Coin { value: 0 }, frozen: false })
+    }
+
+    public fun deposit<T>(addr: address, coin: Coin<T>) acquires Balance {
+        assert!(exists<Balance<T>>(addr), 1);
+        let balance = borrow_global_mut<Balance<T>>(addr);
+        assert!(!balance.frozen, 2);
+        join(&mut balance.coin, coin);
+    }
+
+    public fun balance<T>(addr: address): u64 acquires Balance {
+        borrow_global<Balance<T>>(addr).coin.value
+    }
+
+    public fun destroy<T>(addr: address): u64 acquires Balance {
+        let Balance { coin, frozen: _ } = move_from<Balance<T>>(addr);
+        burn(coin)
+    }
+
+    public fun swap<A: copy + drop, B: drop>(p: Pair<A, B>): Pair<A, A> {
+        let Pair { first, second: _ } = p;
+        Pair { first, second: first }
+    }
+
+    public fun constants(): (u128, address, vector<u8>, vector<u8>) {
+        (MAX, OWNER, NAME, BYTES)
+    }
+
+    public fun set(x: &mut u64, y: u64) {
+        *x = y
+    }
+}
+
+module 0x42::bank {
+    use 0x42::coin::{Self, Coin};
+
+    struct Bank has key {
+        reserve: u64,
+    }
+
+    public fun burn(c: Coin<Bank>): u64 {
+        coin::burn(c)
+    }
+}
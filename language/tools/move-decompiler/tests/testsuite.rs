@@ -0,0 +1,68 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use move_command_line_common::testing::EXP_EXT;
+use move_compiler::{
+    compiled_unit::{AnnotatedCompiledUnit, CompiledUnit, NamedCompiledModule},
+    diagnostics::report_diagnostics_to_buffer,
+    Compiler,
+};
+use move_decompiler::decompiler::Decompiler;
+use move_prover_test_utils::baseline_test::verify_or_update_baseline;
+use std::path::Path;
+
+fn compile(path: &str) -> anyhow::Result<Vec<AnnotatedCompiledUnit>> {
+    let (files, units) = Compiler::from_files(
+        vec![path.to_string()],
+        move_stdlib::move_stdlib_files(),
+        move_stdlib::move_stdlib_named_addresses(),
+    )
+    .build()?;
+    match units {
+        Ok((units, _warnings)) => Ok(units),
+        Err(diags) => anyhow::bail!(
+            "{}",
+            String::from_utf8_lossy(&report_diagnostics_to_buffer(&files, diags))
+        ),
+    }
+}
+
+/// Decompile all modules in `units`, with or without their source maps.
+fn decompile(units: &[AnnotatedCompiledUnit], with_source_map: bool) -> anyhow::Result<String> {
+    let mut out = String::new();
+    for unit in units {
+        if let CompiledUnit::Module(NamedCompiledModule {
+            module, source_map, ..
+        }) = &unit.clone().into_compiled_unit()
+        {
+            let source_map = if with_source_map {
+                Some(source_map)
+            } else {
+                None
+            };
+            out.push_str(&Decompiler::new(module, source_map).decompile()?);
+        }
+    }
+    Ok(out)
+}
+
+fn test_runner(path: &Path) -> datatest_stable::Result<()> {
+    let units = compile(path.to_str().unwrap())?;
+    let decompiled = decompile(&units, true)?;
+    verify_or_update_baseline(path.with_extension(EXP_EXT).as_path(), &decompiled)?;
+
+    // The decompiled code must compile again, with and without names from the source map.
+    let dir = tempfile::tempdir()?;
+    for (name, source) in [
+        ("decompiled.move", decompiled),
+        ("nameless.move", decompile(&units, false)?),
+    ] {
+        let decompiled_path = dir.path().join(name);
+        std::fs::write(&decompiled_path, &source)?;
+        compile(decompiled_path.to_str().unwrap())
+            .map_err(|e| anyhow::anyhow!("recompiling\n{}\nfailed:\n{}", source, e))?;
+    }
+    Ok(())
+}
+
+datatest_stable::harness!(test_runner, "tests/sources", r".*\.move$");
@@ -0,0 +1,488 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! A lossless textual form of Move bytecode, which `move-assembler` turns back into the binary it
+//! was printed from.
+//!
+//! Unlike the output of the `Disassembler`, which resolves indices into names, the assembly shows
+//! every table of a `CompiledModule` or `CompiledScript` explicitly, in the order the tables
+//! appear in the Rust representation, and refers to entries by their index. Every entry is
+//! prefixed by that index, so the text can be edited by hand (for example to craft a malformed
+//! module for a test) without losing track of what refers to what. Anything after `//` on a line
+//! is a comment; the comments printed here only describe the entries they follow.
+//!
+//! Types are written as in Move source, except that `S#i` is the struct with handle `i` and `T#i`
+//! the type parameter `i`. Constants, metadata, and the bytes of identifiers are printed in their
+//! serialized form, which is what makes the format lossless.
+
+use move_binary_format::file_format::{
+    AbilitySet, Bytecode, CodeUnit, CompiledModule, CompiledScript, Constant, FunctionDefinition,
+    FunctionHandle, FunctionInstantiation, ModuleHandle, ModuleHandleIndex, Signature,
+    SignatureToken, StructDefinition, StructFieldInformation, StructHandle, Visibility,
+};
+use move_core_types::{
+    account_address::AccountAddress, identifier::Identifier, metadata::Metadata,
+};
+use std::fmt::Write;
+
+/// The names a module or script uses, for the comments in the assembly. Lookups go through
+/// `get` because the binary has not necessarily been verified.
+struct Names<'a> {
+    module_handles: &'a [ModuleHandle],
+    identifiers: &'a [Identifier],
+    address_identifiers: &'a [AccountAddress],
+}
+
+impl<'a> Names<'a> {
+    fn identifier(&self, idx: u16) -> Option<&'a str> {
+        self.identifiers.get(idx as usize).map(|id| id.as_str())
+    }
+
+    fn module(&self, idx: ModuleHandleIndex) -> Option<String> {
+        let handle = self.module_handles.get(idx.0 as usize)?;
+        let address = self.address_identifiers.get(handle.address.0 as usize)?;
+        Some(format!(
+            "{}::{}",
+            address.to_hex_literal(),
+            self.identifier(handle.name.0)?
+        ))
+    }
+
+    fn member(&self, module: ModuleHandleIndex, name: u16) -> Option<String> {
+        Some(format!(
+            "{}::{}",
+            self.module(module)?,
+            self.identifier(name)?
+        ))
+    }
+}
+
+/// Print `module` as assembly.
+pub fn module_assembly(module: &CompiledModule) -> String {
+    let names = Names {
+        module_handles: &module.module_handles,
+        identifiers: &module.identifiers,
+        address_identifiers: &module.address_identifiers,
+    };
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "module version {} self_module_handle {}",
+        module.version, module.self_module_handle_idx
+    );
+    print_common_tables(
+        &mut out,
+        &names,
+        Tables {
+            module_handles: &module.module_handles,
+            struct_handles: &module.struct_handles,
+            function_handles: &module.function_handles,
+            function_instantiations: &module.function_instantiations,
+            signatures: &module.signatures,
+            identifiers: &module.identifiers,
+            address_identifiers: &module.address_identifiers,
+            constant_pool: &module.constant_pool,
+            metadata: &module.metadata,
+        },
+        Some(module),
+    );
+    print_table(
+        &mut out,
+        "struct_defs",
+        &module.struct_defs,
+        |out, _, def| print_struct_def(out, &names, module, def),
+    );
+    print_table(
+        &mut out,
+        "function_defs",
+        &module.function_defs,
+        |out, _, def| print_function_def(out, &names, module, def),
+    );
+    out
+}
+
+/// Print `script` as assembly.
+pub fn script_assembly(script: &CompiledScript) -> String {
+    let names = Names {
+        module_handles: &script.module_handles,
+        identifiers: &script.identifiers,
+        address_identifiers: &script.address_identifiers,
+    };
+    let mut out = String::new();
+    let _ = writeln!(out, "script version {}", script.version);
+    print_common_tables(
+        &mut out,
+        &names,
+        Tables {
+            module_handles: &script.module_handles,
+            struct_handles: &script.struct_handles,
+            function_handles: &script.function_handles,
+            function_instantiations: &script.function_instantiations,
+            signatures: &script.signatures,
+            identifiers: &script.identifiers,
+            address_identifiers: &script.address_identifiers,
+            constant_pool: &script.constant_pool,
+            metadata: &script.metadata,
+        },
+        None,
+    );
+    let _ = write!(
+        out,
+        "\nmain type_parameters {} parameters {} ",
+        abilities_list(&script.type_parameters),
+        script.parameters
+    );
+    print_code(&mut out, &script.code, 1);
+    out.push('\n');
+    out
+}
+
+/// The tables modules and scripts have in common.
+struct Tables<'a> {
+    module_handles: &'a [ModuleHandle],
+    struct_handles: &'a [StructHandle],
+    function_handles: &'a [FunctionHandle],
+    function_instantiations: &'a [FunctionInstantiation],
+    signatures: &'a [Signature],
+    identifiers: &'a [Identifier],
+    address_identifiers: &'a [AccountAddress],
+    constant_pool: &'a [Constant],
+    metadata: &'a [Metadata],
+}
+
+/// Print the tables in `tables`, and when printing a module, its other tables, in the order they
+/// are declared in `CompiledModule`.
+fn print_common_tables(
+    out: &mut String,
+    names: &Names,
+    tables: Tables,
+    module: Option<&CompiledModule>,
+) {
+    print_table(
+        out,
+        "module_handles",
+        tables.module_handles,
+        |out, idx, h| {
+            let _ = write!(out, "address {} name {}", h.address, h.name);
+            comment(out, names.module(ModuleHandleIndex(idx as u16)));
+        },
+    );
+    print_table(out, "struct_handles", tables.struct_handles, |out, _, h| {
+        let params = h
+            .type_parameters
+            .iter()
+            .map(|p| {
+                let abilities = abilities(p.constraints);
+                if p.is_phantom {
+                    format!("phantom {}", abilities)
+                } else {
+                    abilities
+                }
+            })
+            .collect::<Vec<_>>();
+        let _ = write!(
+            out,
+            "module {} name {} abilities {} type_parameters [{}]",
+            h.module,
+            h.name,
+            abilities(h.abilities),
+            params.join(", ")
+        );
+        comment(out, names.member(h.module, h.name.0));
+    });
+    print_table(
+        out,
+        "function_handles",
+        tables.function_handles,
+        |out, _, h| {
+            let _ = write!(
+                out,
+                "module {} name {} parameters {} return {} type_parameters {}",
+                h.module,
+                h.name,
+                h.parameters,
+                h.return_,
+                abilities_list(&h.type_parameters)
+            );
+            comment(out, names.member(h.module, h.name.0));
+        },
+    );
+    if let Some(module) = module {
+        print_table(out, "field_handles", &module.field_handles, |out, _, h| {
+            let _ = write!(out, "owner {} field {}", h.owner, h.field);
+        });
+        print_table(out, "friend_decls", &module.friend_decls, |out, _, h| {
+            let _ = write!(out, "address {} name {}", h.address, h.name);
+        });
+        print_table(
+            out,
+            "struct_def_instantiations",
+            &module.struct_def_instantiations,
+            |out, _, i| {
+                let _ = write!(out, "def {} type_parameters {}", i.def, i.type_parameters);
+            },
+        );
+    }
+    print_table(
+        out,
+        "function_instantiations",
+        tables.function_instantiations,
+        |out, _, i| {
+            let _ = write!(
+                out,
+                "handle {} type_parameters {}",
+                i.handle, i.type_parameters
+            );
+        },
+    );
+    if let Some(module) = module {
+        print_table(
+            out,
+            "field_instantiations",
+            &module.field_instantiations,
+            |out, _, i| {
+                let _ = write!(
+                    out,
+                    "handle {} type_parameters {}",
+                    i.handle, i.type_parameters
+                );
+            },
+        );
+    }
+    print_table(out, "signatures", tables.signatures, |out, _, s| {
+        out.push_str(&types(&s.0));
+    });
+    print_table(out, "identifiers", tables.identifiers, |out, _, id| {
+        let _ = write!(out, "\"{}\"", id);
+    });
+    print_table(
+        out,
+        "address_identifiers",
+        tables.address_identifiers,
+        |out, _, a| {
+            out.push_str(&a.to_hex_literal());
+        },
+    );
+    print_table(out, "constant_pool", tables.constant_pool, |out, _, c| {
+        let _ = write!(out, "{} {}", type_(&c.type_), bytes(&c.data));
+        comment(out, c.deserialize_constant().map(|v| v.to_string()));
+    });
+    print_table(out, "metadata", tables.metadata, |out, _, m| {
+        let _ = write!(out, "key {} value {}", bytes(&m.key), bytes(&m.value));
+    });
+}
+
+fn print_struct_def(
+    out: &mut String,
+    names: &Names,
+    module: &CompiledModule,
+    def: &StructDefinition,
+) {
+    let _ = write!(out, "handle {} ", def.struct_handle);
+    match &def.field_information {
+        StructFieldInformation::Native => out.push_str("native"),
+        StructFieldInformation::Declared(fields) => {
+            let fields = fields
+                .iter()
+                .map(|f| format!("{}: {}", f.name, type_(&f.signature.0)))
+                .collect::<Vec<_>>();
+            let _ = write!(out, "fields [{}]", fields.join(", "));
+        }
+    }
+    comment(
+        out,
+        module
+            .struct_handles
+            .get(def.struct_handle.0 as usize)
+            .and_then(|h| names.identifier(h.name.0).map(str::to_string)),
+    );
+}
+
+fn print_function_def(
+    out: &mut String,
+    names: &Names,
+    module: &CompiledModule,
+    def: &FunctionDefinition,
+) {
+    let visibility = match def.visibility {
+        Visibility::Private => "private",
+        Visibility::Public => "public",
+        Visibility::Friend => "friend",
+    };
+    let acquires = def
+        .acquires_global_resources
+        .iter()
+        .map(|idx| idx.to_string())
+        .collect::<Vec<_>>();
+    let _ = write!(
+        out,
+        "handle {} {}{} acquires [{}] ",
+        def.function,
+        visibility,
+        if def.is_entry { " entry" } else { "" },
+        acquires.join(", ")
+    );
+    let name = module
+        .function_handles
+        .get(def.function.0 as usize)
+        .and_then(|h| names.identifier(h.name.0).map(str::to_string));
+    match &def.code {
+        None => {
+            out.push_str("native");
+            comment(out, name);
+        }
+        Some(code) => {
+            // The comment goes on the first line, before the instructions.
+            let mut body = String::new();
+            print_code(&mut body, code, 2);
+            let (first, rest) = body.split_once('\n').unwrap_or((&body, ""));
+            out.push_str(first);
+            comment(out, name);
+            if !rest.is_empty() {
+                out.push('\n');
+                out.push_str(rest);
+            }
+        }
+    }
+}
+
+/// Print `code`, whose instructions are indented by `indent` levels.
+fn print_code(out: &mut String, code: &CodeUnit, indent: usize) {
+    let _ = write!(out, "locals {} code [", code.locals);
+    if code.code.is_empty() {
+        out.push(']');
+        return;
+    }
+    for (offset, instr) in code.code.iter().enumerate() {
+        let _ = write!(
+            out,
+            "\n{}{}: {}",
+            "    ".repeat(indent),
+            offset,
+            instruction(instr)
+        );
+    }
+    let _ = write!(out, "\n{}]", "    ".repeat(indent - 1));
+}
+
+/// Print a table of entries, one per line, each prefixed by its index.
+fn print_table<T>(
+    out: &mut String,
+    name: &str,
+    entries: &[T],
+    mut print_entry: impl FnMut(&mut String, usize, &T),
+) {
+    let _ = write!(out, "\n{} [", name);
+    if entries.is_empty() {
+        out.push_str("]\n");
+        return;
+    }
+    for (idx, entry) in entries.iter().enumerate() {
+        let _ = write!(out, "\n    {}: ", idx);
+        print_entry(out, idx, entry);
+    }
+    out.push_str("\n]\n");
+}
+
+fn comment(out: &mut String, text: Option<String>) {
+    if let Some(text) = text {
+        let _ = write!(out, " // {}", text.replace('\n', " "));
+    }
+}
+
+fn bytes(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+fn abilities(set: AbilitySet) -> String {
+    let abilities = set
+        .into_iter()
+        .map(|a| format!("{:?}", a).to_lowercase())
+        .collect::<Vec<_>>();
+    format!("[{}]", abilities.join(", "))
+}
+
+fn abilities_list(sets: &[AbilitySet]) -> String {
+    let sets = sets.iter().map(|s| abilities(*s)).collect::<Vec<_>>();
+    format!("[{}]", sets.join(", "))
+}
+
+fn types(tys: &[SignatureToken]) -> String {
+    format!("[{}]", tys.iter().map(type_).collect::<Vec<_>>().join(", "))
+}
+
+fn type_(ty: &SignatureToken) -> String {
+    use SignatureToken::*;
+    match ty {
+        Bool => "bool".to_string(),
+        U8 => "u8".to_string(),
+        U16 => "u16".to_string(),
+        U32 => "u32".to_string(),
+        U64 => "u64".to_string(),
+        U128 => "u128".to_string(),
+        U256 => "u256".to_string(),
+        Address => "address".to_string(),
+        Signer => "signer".to_string(),
+        Vector(elem) => format!("vector<{}>", type_(elem)),
+        Struct(idx) => format!("S#{}", idx),
+        StructInstantiation(idx, args) => {
+            let args = args.iter().map(type_).collect::<Vec<_>>();
+            format!("S#{}<{}>", idx, args.join(", "))
+        }
+        Reference(ty) => format!("&{}", type_(ty)),
+        MutableReference(ty) => format!("&mut {}", type_(ty)),
+        TypeParameter(idx) => format!("T#{}", idx),
+    }
+}
+
+/// An instruction is its opcode followed by its operands, which are all numbers.
+fn instruction(instr: &Bytecode) -> String {
+    use Bytecode::*;
+    match instr {
+        BrTrue(a) => format!("BrTrue {}", a),
+        BrFalse(a) => format!("BrFalse {}", a),
+        Branch(a) => format!("Branch {}", a),
+        LdU8(a) => format!("LdU8 {}", a),
+        LdU16(a) => format!("LdU16 {}", a),
+        LdU32(a) => format!("LdU32 {}", a),
+        LdU64(a) => format!("LdU64 {}", a),
+        LdU128(a) => format!("LdU128 {}", a),
+        LdU256(a) => format!("LdU256 {}", a),
+        LdConst(a) => format!("LdConst {}", a),
+        CopyLoc(a) => format!("CopyLoc {}", a),
+        MoveLoc(a) => format!("MoveLoc {}", a),
+        StLoc(a) => format!("StLoc {}", a),
+        MutBorrowLoc(a) => format!("MutBorrowLoc {}", a),
+        ImmBorrowLoc(a) => format!("ImmBorrowLoc {}", a),
+        Call(a) => format!("Call {}", a),
+        CallGeneric(a) => format!("CallGeneric {}", a),
+        Pack(a) => format!("Pack {}", a),
+        PackGeneric(a) => format!("PackGeneric {}", a),
+        Unpack(a) => format!("Unpack {}", a),
+        UnpackGeneric(a) => format!("UnpackGeneric {}", a),
+        MutBorrowField(a) => format!("MutBorrowField {}", a),
+        MutBorrowFieldGeneric(a) => format!("MutBorrowFieldGeneric {}", a),
+        ImmBorrowField(a) => format!("ImmBorrowField {}", a),
+        ImmBorrowFieldGeneric(a) => format!("ImmBorrowFieldGeneric {}", a),
+        MutBorrowGlobal(a) => format!("MutBorrowGlobal {}", a),
+        MutBorrowGlobalGeneric(a) => format!("MutBorrowGlobalGeneric {}", a),
+        ImmBorrowGlobal(a) => format!("ImmBorrowGlobal {}", a),
+        ImmBorrowGlobalGeneric(a) => format!("ImmBorrowGlobalGeneric {}", a),
+        Exists(a) => format!("Exists {}", a),
+        ExistsGeneric(a) => format!("ExistsGeneric {}", a),
+        MoveFrom(a) => format!("MoveFrom {}", a),
+        MoveFromGeneric(a) => format!("MoveFromGeneric {}", a),
+        MoveTo(a) => format!("MoveTo {}", a),
+        MoveToGeneric(a) => format!("MoveToGeneric {}", a),
+        VecPack(a, n) => format!("VecPack {} {}", a, n),
+        VecLen(a) => format!("VecLen {}", a),
+        VecImmBorrow(a) => format!("VecImmBorrow {}", a),
+        VecMutBorrow(a) => format!("VecMutBorrow {}", a),
+        VecPushBack(a) => format!("VecPushBack {}", a),
+        VecPopBack(a) => format!("VecPopBack {}", a),
+        VecUnpack(a, n) => format!("VecUnpack {} {}", a, n),
+        VecSwap(a) => format!("VecSwap {}", a),
+        // The remaining instructions have no operands, and their `Debug` form is their opcode.
+        _ => format!("{:?}", instr),
+    }
+}
@@ -14,7 +14,10 @@ use move_command_line_common::files::{
     MOVE_COMPILED_EXTENSION, MOVE_EXTENSION, SOURCE_MAP_EXTENSION,
 };
 use move_coverage::coverage_map::CoverageMap;
-use move_disassembler::disassembler::{Disassembler, DisassemblerOptions};
+use move_disassembler::{
+    assembly::{module_assembly, script_assembly},
+    disassembler::{Disassembler, DisassemblerOptions},
+};
 use move_ir_types::location::Spanned;
 use std::{fs, path::Path};
 
@@ -37,6 +40,11 @@ struct Args {
     #[clap(long = "skip-basic-blocks")]
     pub skip_basic_blocks: bool,
 
+    /// Print the lossless assembly form of the bytecode, which `move-assembler` accepts, instead
+    /// of the human-readable disassembly.
+    #[clap(short = 'a', long = "assembly")]
+    pub assembly: bool,
+
     /// Treat input file as a script (default is to treat file as a module)
     #[clap(short = 's', long = "script")]
     pub is_script: bool,
@@ -74,6 +82,20 @@ fn main() {
 
     let bytecode_bytes = fs::read(&args.bytecode_file_path).expect("Unable to read bytecode file");
 
+    if args.assembly {
+        let assembly = if args.is_script {
+            let script = CompiledScript::deserialize(&bytecode_bytes)
+                .expect("Script blob can't be deserialized");
+            script_assembly(&script)
+        } else {
+            let module = CompiledModule::deserialize(&bytecode_bytes)
+                .expect("Module blob can't be deserialized");
+            module_assembly(&module)
+        };
+        print!("{}", assembly);
+        return;
+    }
+
     let source_path = Path::new(&args.bytecode_file_path).with_extension(move_extension);
     let source = fs::read_to_string(&source_path).ok();
     let source_map = source_map_from_file(
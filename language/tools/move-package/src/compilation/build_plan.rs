@@ -3,17 +3,27 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    compilation::compiled_package::CompiledPackage, resolution::resolution_graph::ResolvedGraph,
+    compilation::{
+        compiled_package::CompiledPackage,
+        module_cache::{ModuleCache, TypedAstCache},
+    },
+    resolution::resolution_graph::ResolvedGraph,
     source_package::parsed_manifest::PackageName,
 };
 use anyhow::Result;
 use move_compiler::{
     compiled_unit::AnnotatedCompiledUnit,
     diagnostics::{report_diagnostics_to_color_buffer, report_warnings, FilesSourceText},
+    shared::unique_map::UniqueMap,
+    typing::ast as T,
     Compiler,
 };
 use petgraph::algo::toposort;
-use std::{collections::BTreeSet, io::Write, path::Path};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Write,
+    path::{Path, PathBuf},
+};
 
 use super::package_layout::CompiledPackageLayout;
 
@@ -142,16 +152,76 @@ impl BuildPlan {
         &self,
         writer: &mut W,
         bytecode_version: Option<u32>,
+        compiler_driver: impl FnMut(
+            Compiler,
+        )
+            -> anyhow::Result<(FilesSourceText, Vec<AnnotatedCompiledUnit>)>,
+    ) -> Result<CompiledPackage> {
+        self.compile_with_driver_impl(writer, bytecode_version, None, compiler_driver)
+    }
+
+    /// Same as `compile_with_driver`, but the driver also returns the typed AST of the files it
+    /// compiled, and the typed AST of the whole package is returned with the compiled package.
+    ///
+    /// In incremental builds, a file is only reused if its typed AST is in `typed_ast_cache`,
+    /// which is updated with the files compiled by the build.
+    pub fn compile_with_typed_ast_driver<W: Write>(
+        &self,
+        writer: &mut W,
+        bytecode_version: Option<u32>,
+        typed_ast_cache: &mut TypedAstCache,
+        mut compiler_driver: impl FnMut(
+            Compiler,
+        ) -> anyhow::Result<(
+            FilesSourceText,
+            Vec<AnnotatedCompiledUnit>,
+            T::Program,
+        )>,
+    ) -> Result<(CompiledPackage, T::Program)> {
+        let mut typed_ast = None;
+        let compiled = self.compile_with_driver_impl(
+            writer,
+            bytecode_version,
+            Some(typed_ast_cache),
+            |compiler| {
+                let (files, units, program) = compiler_driver(compiler)?;
+                // An incremental build may invoke the driver more than once, the last time on all
+                // the files which are not reused.
+                typed_ast = Some((files.clone(), program));
+                Ok((files, units))
+            },
+        )?;
+        let (files, program) = typed_ast.unwrap_or_else(|| {
+            let program = T::Program {
+                modules: UniqueMap::new(),
+                scripts: BTreeMap::new(),
+            };
+            (FilesSourceText::new(), program)
+        });
+        if !CompiledPackage::is_incremental(&self.resolution_graph) {
+            *typed_ast_cache = TypedAstCache::new();
+            return Ok((compiled, program));
+        }
+        let module_cache = ModuleCache::load(
+            &self.build_root().join(self.root.as_str()),
+            &self.resolution_graph.build_options,
+        );
+        let program = typed_ast_cache.update(&module_cache, &files, program);
+        Ok((compiled, program))
+    }
+
+    fn compile_with_driver_impl<W: Write>(
+        &self,
+        writer: &mut W,
+        bytecode_version: Option<u32>,
+        typed_ast_cache: Option<&TypedAstCache>,
         mut compiler_driver: impl FnMut(
             Compiler,
         )
             -> anyhow::Result<(FilesSourceText, Vec<AnnotatedCompiledUnit>)>,
     ) -> Result<CompiledPackage> {
         let root_package = &self.resolution_graph.package_table[&self.root];
-        let project_root = match &self.resolution_graph.build_options.install_dir {
-            Some(under_path) => under_path.clone(),
-            None => self.resolution_graph.root_package_path.clone(),
-        };
+        let project_root = self.project_root();
         let immediate_dependencies_names =
             root_package.immediate_dependencies(&self.resolution_graph);
         let transitive_dependencies = root_package
@@ -189,16 +259,28 @@ impl BuildPlan {
             transitive_dependencies,
             bytecode_version,
             &self.resolution_graph,
+            typed_ast_cache,
             &mut compiler_driver,
         )?;
 
         Self::clean(
-            &project_root.join(CompiledPackageLayout::Root.path()),
+            &self.build_root(),
             self.sorted_deps.iter().copied().collect(),
         )?;
         Ok(compiled)
     }
 
+    fn project_root(&self) -> PathBuf {
+        match &self.resolution_graph.build_options.install_dir {
+            Some(under_path) => under_path.clone(),
+            None => self.resolution_graph.root_package_path.clone(),
+        }
+    }
+
+    fn build_root(&self) -> PathBuf {
+        self.project_root().join(CompiledPackageLayout::Root.path())
+    }
+
     #[cfg(feature = "evm-backend")]
     pub fn compile_evm<W: Write>(&self, writer: &mut W) -> Result<()> {
         let root_package = &self.resolution_graph.package_table[&self.root];
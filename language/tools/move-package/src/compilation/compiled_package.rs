@@ -3,7 +3,10 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    compilation::package_layout::CompiledPackageLayout,
+    compilation::{
+        module_cache::{interface_digest, ModuleCache, SourceInput, TypedAstCache},
+        package_layout::CompiledPackageLayout,
+    },
     resolution::resolution_graph::{Renaming, ResolvedGraph, ResolvedPackage, ResolvedTable},
     source_package::{
         layout::{SourcePackageLayout, REFERENCE_TEMPLATE_FILENAME},
@@ -16,6 +19,7 @@ use colored::Colorize;
 use itertools::{Either, Itertools};
 use move_abigen::{Abigen, AbigenOptions};
 use move_binary_format::file_format::{CompiledModule, CompiledScript};
+use move_bytecode_optimizer::optimize_module;
 use move_bytecode_source_map::utils::source_map_from_file;
 use move_bytecode_utils::Modules;
 use move_command_line_common::{
@@ -204,7 +208,7 @@ impl OnDiskCompiledPackage {
         })
     }
 
-    fn decode_unit(
+    pub(crate) fn decode_unit(
         &self,
         package_name: Symbol,
         bytecode_path_str: &str,
@@ -541,6 +545,7 @@ impl CompiledPackage {
         )>,
         bytecode_version: Option<u32>,
         resolution_graph: &ResolvedGraph,
+        typed_ast_cache: Option<&TypedAstCache>,
         mut compiler_driver: impl FnMut(
             Compiler,
         )
@@ -600,20 +605,32 @@ impl CompiledPackage {
         let mut paths = src_deps;
         paths.push(sources_package_paths.clone());
 
-        let compiler = Compiler::from_package_paths(paths, bytecode_deps).set_flags(flags);
-        let (file_map, all_compiled_units) = compiler_driver(compiler)?;
+        let build_options = &resolution_graph.build_options;
+        let (all_compiled_units, module_cache) = if Self::is_incremental(resolution_graph) {
+            let (units, cache) = Self::build_incrementally(
+                w,
+                &project_root.join(CompiledPackageLayout::Root.path()),
+                root_package_name,
+                paths,
+                bytecode_deps,
+                flags,
+                build_options,
+                typed_ast_cache,
+                &mut compiler_driver,
+            )?;
+            (units, Some(cache))
+        } else {
+            let compiler = Compiler::from_package_paths(paths, bytecode_deps).set_flags(flags);
+            let (file_map, units) = compiler_driver(compiler)?;
+            let mut units = with_source_paths(&file_map, units);
+            if build_options.optimize {
+                Self::optimize_units(w, root_package_name, &mut units)?;
+            }
+            (units, None)
+        };
         let mut root_compiled_units = vec![];
         let mut deps_compiled_units = vec![];
-        for annot_unit in all_compiled_units {
-            let source_path = PathBuf::from(file_map[&annot_unit.loc().file_hash()].0.as_str());
-            let package_name = match &annot_unit {
-                compiled_unit::CompiledUnitEnum::Module(m) => m.named_module.package_name.unwrap(),
-                compiled_unit::CompiledUnitEnum::Script(s) => s.named_script.package_name.unwrap(),
-            };
-            let unit = CompiledUnitWithSource {
-                unit: annot_unit.into_compiled_unit(),
-                source_path,
-            };
+        for (package_name, unit) in all_compiled_units {
             if package_name == root_package_name {
                 root_compiled_units.push(unit)
             } else {
@@ -665,14 +682,205 @@ impl CompiledPackage {
             compiled_abis,
         };
 
-        compiled_package.save_to_disk(
+        let on_disk_package = compiled_package.save_to_disk(
             project_root.join(CompiledPackageLayout::Root.path()),
             bytecode_version,
         )?;
+        if let Some(module_cache) = module_cache {
+            module_cache.save(&on_disk_package.root_path)?;
+        }
 
         Ok(compiled_package)
     }
 
+    /// Whether the package is built with the module-granular cache. Builds with renamed addresses
+    /// are not, as the cache does not record renamings.
+    pub(crate) fn is_incremental(resolution_graph: &ResolvedGraph) -> bool {
+        let build_options = &resolution_graph.build_options;
+        build_options.incremental
+            && !build_options.force_recompilation
+            && resolution_graph.contains_renaming().is_none()
+    }
+
+    /// Compile the source files in `packages`, reusing the units of every file whose inputs did
+    /// not change since the last incremental build under `build_root`. Changed files are compiled
+    /// against the reused modules, which are passed to the compiler as bytecode dependencies. If
+    /// this changes the interface of a module, the files depending on it are recompiled as well.
+    /// With a `typed_ast_cache`, files whose typed AST is not cached are recompiled too.
+    fn build_incrementally<W: Write>(
+        w: &mut W,
+        build_root: &Path,
+        root_package_name: PackageName,
+        packages: Vec<PackagePaths>,
+        bytecode_deps: Vec<PackagePaths>,
+        flags: Flags,
+        build_options: &BuildConfig,
+        typed_ast_cache: Option<&TypedAstCache>,
+        compiler_driver: &mut impl FnMut(
+            Compiler,
+        )
+            -> Result<(FilesSourceText, Vec<AnnotatedCompiledUnit>)>,
+    ) -> Result<(Vec<(PackageName, CompiledUnitWithSource)>, ModuleCache)> {
+        let root_path = build_root.join(root_package_name.as_str());
+        let previous_build = OnDiskCompiledPackage::from_path(&root_path).ok();
+        let mut cache = ModuleCache::load(&root_path, build_options);
+        if previous_build.is_none() {
+            cache.sources.clear();
+        }
+
+        let inputs = SourceInput::from_package_paths(&packages)?;
+        let mut dirty = cache.changed_sources(&inputs);
+        if let Some(typed_ast_cache) = typed_ast_cache {
+            let untyped = inputs
+                .iter()
+                .filter(|input| {
+                    !dirty.contains(&input.path)
+                        && !typed_ast_cache.covers(&input.path, &cache.sources[&input.path])
+                })
+                .map(|input| input.path.clone())
+                .collect::<Vec<_>>();
+            dirty.extend(untyped);
+        }
+
+        let mut bytecode_interfaces = BTreeMap::new();
+        for path in bytecode_deps
+            .iter()
+            .flat_map(|package| package.paths.iter())
+        {
+            if let Ok(module) = CompiledModule::deserialize(&std::fs::read(path.as_str())?) {
+                bytecode_interfaces.insert(module.self_id().to_string(), interface_digest(&module));
+            }
+        }
+
+        let (mut compiled_units, interfaces) = loop {
+            let clean = inputs
+                .iter()
+                .map(|input| &input.path)
+                .filter(|path| !dirty.contains(*path))
+                .collect::<Vec<_>>();
+            let mut interfaces = bytecode_interfaces.clone();
+            interfaces.extend(cache.interfaces_of(clean.iter().copied()));
+
+            let compiled_units = if dirty.is_empty() {
+                vec![]
+            } else {
+                let targets = packages
+                    .iter()
+                    .map(|package| PackagePaths {
+                        name: package.name,
+                        paths: package
+                            .paths
+                            .iter()
+                            .filter(|path| dirty.contains(Path::new(path.as_str())))
+                            .copied()
+                            .collect(),
+                        named_address_map: package.named_address_map.clone(),
+                    })
+                    .filter(|package| !package.paths.is_empty())
+                    .collect();
+                let mut deps = bytecode_deps.clone();
+                if let Some(previous_build) = &previous_build {
+                    deps.extend(packages.iter().map(|package| {
+                        PackagePaths {
+                            name: package.name,
+                            paths: package
+                                .paths
+                                .iter()
+                                .filter(|path| !dirty.contains(Path::new(path.as_str())))
+                                .flat_map(|path| {
+                                    cache.bytecode_paths(previous_build, Path::new(path.as_str()))
+                                })
+                                .map(|path| Symbol::from(path.to_string_lossy().as_ref()))
+                                .collect(),
+                            named_address_map: package.named_address_map.clone(),
+                        }
+                    }))
+                }
+                let compiler = Compiler::from_package_paths(targets, deps)
+                    .set_flags(flags.clone())
+                    .set_interface_files_dir(build_root.to_string_lossy().to_string());
+                let (file_map, units) = compiler_driver(compiler)?;
+                with_source_paths(&file_map, units)
+            };
+
+            for (_, unit) in &compiled_units {
+                if let CompiledUnit::Module(m) = &unit.unit {
+                    interfaces.insert(m.module.self_id().to_string(), interface_digest(&m.module));
+                }
+            }
+            let stale = cache.stale_dependents(clean.into_iter().cloned(), &interfaces);
+            if stale.is_empty() {
+                break (compiled_units, interfaces);
+            }
+            dirty.extend(stale);
+        };
+        // Reused units were optimized when they were compiled, as the cache is only reused with
+        // the same build flags.
+        if build_options.optimize {
+            Self::optimize_units(w, root_package_name, &mut compiled_units)?;
+        }
+
+        writeln!(
+            w,
+            "{} {} of {} source files",
+            "REUSING".bold().green(),
+            inputs.len() - dirty.len(),
+            inputs.len()
+        )?;
+
+        let mut units = vec![];
+        let mut new_cache = ModuleCache {
+            build_flags: build_options.clone(),
+            sources: BTreeMap::new(),
+        };
+        for input in &inputs {
+            if dirty.contains(&input.path) {
+                let from_input = compiled_units
+                    .iter()
+                    .filter(|(_, unit)| unit.source_path == input.path)
+                    .map(|(_, unit)| unit)
+                    .collect::<Vec<_>>();
+                new_cache.insert(input, &from_input, &interfaces);
+            } else {
+                let previous_build = previous_build
+                    .as_ref()
+                    .expect("clean sources imply a previous build");
+                for unit in cache.load_units(previous_build, &input.path)? {
+                    units.push((input.package_name, unit));
+                }
+                new_cache
+                    .sources
+                    .insert(input.path.clone(), cache.sources[&input.path].clone());
+            }
+        }
+        units.extend(compiled_units);
+        Ok((units, new_cache))
+    }
+
+    /// Optimize the modules of the root package among `units`, and report the functions whose
+    /// code changed.
+    fn optimize_units<W: Write>(
+        w: &mut W,
+        root_package_name: PackageName,
+        units: &mut [(PackageName, CompiledUnitWithSource)],
+    ) -> Result<()> {
+        writeln!(w, "{} {}", "OPTIMIZING".bold().green(), root_package_name)?;
+        for (package_name, unit) in units.iter_mut() {
+            if *package_name != root_package_name {
+                continue;
+            }
+            if let CompiledUnit::Module(NamedCompiledModule {
+                module, source_map, ..
+            }) = &mut unit.unit
+            {
+                for report in optimize_module(module, source_map) {
+                    writeln!(w, "  {}", report)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
     // We take the (restrictive) view that all filesystems are case insensitive to maximize
     // portability of packages.
     fn check_filepaths_ok(&self) -> Result<()> {
@@ -879,6 +1087,28 @@ impl CompiledPackage {
     }
 }
 
+/// Pairs each compiled unit with the package it belongs to and the path of its source file.
+fn with_source_paths(
+    file_map: &FilesSourceText,
+    units: Vec<AnnotatedCompiledUnit>,
+) -> Vec<(PackageName, CompiledUnitWithSource)> {
+    units
+        .into_iter()
+        .map(|annot_unit| {
+            let source_path = PathBuf::from(file_map[&annot_unit.loc().file_hash()].0.as_str());
+            let package_name = match &annot_unit {
+                compiled_unit::CompiledUnitEnum::Module(m) => m.named_module.package_name.unwrap(),
+                compiled_unit::CompiledUnitEnum::Script(s) => s.named_script.package_name.unwrap(),
+            };
+            let unit = CompiledUnitWithSource {
+                unit: annot_unit.into_compiled_unit(),
+                source_path,
+            };
+            (package_name, unit)
+        })
+        .collect()
+}
+
 pub(crate) fn named_address_mapping_for_compiler(
     resolution_table: &ResolvedTable,
 ) -> BTreeMap<Symbol, NumericalAddress> {
@@ -5,4 +5,5 @@
 pub mod build_plan;
 pub mod compiled_package;
 pub mod model_builder;
+pub mod module_cache;
 pub mod package_layout;
@@ -0,0 +1,418 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! A module-granular compilation cache.
+//!
+//! Every source file that took part in the last incremental build is recorded together with the
+//! digest of its contents, the digest of the named address mapping it was compiled with, the
+//! units it produced, and the interface digests of every module those units depend on. On the
+//! next build a file is reused as-is when all of these inputs are unchanged; otherwise it is
+//! recompiled against the reused modules, which are handed to the compiler as bytecode
+//! dependencies.
+//!
+//! The cached bytecode and source maps are the artifacts that a build already leaves in the
+//! `build/<package>` directory, so the cache itself only adds a small index next to them.
+//!
+//! Typed ASTs cannot be saved with the bytecode. Long-running processes which need them, such as
+//! the analyzer, keep a `TypedAstCache` in memory across builds instead. A file is then only
+//! reused if its typed AST is cached as well.
+
+use crate::{
+    compilation::{
+        compiled_package::{CompiledUnitWithSource, OnDiskCompiledPackage},
+        package_layout::CompiledPackageLayout,
+    },
+    source_package::parsed_manifest::PackageName,
+    BuildConfig,
+};
+use anyhow::Result;
+use move_binary_format::{
+    access::{ModuleAccess, ScriptAccess},
+    file_format::CompiledModule,
+};
+use move_command_line_common::files::{FileHash, MOVE_COMPILED_EXTENSION};
+use move_compiler::{
+    compiled_unit::CompiledUnit,
+    diagnostics::FilesSourceText,
+    expansion::ast::ModuleIdent,
+    interface_generator::write_module_to_string,
+    shared::{unique_map::UniqueMap, NumericalAddress, PackagePaths},
+    typing::ast as T,
+};
+use move_core_types::language_storage::ModuleId;
+use move_symbol_pool::Symbol;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+/// The index of the module-granular compilation cache, saved as `ModuleCache.yaml` in the build
+/// directory of the root package.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModuleCache {
+    /// The flags that the cached units were built with. A cache built with different flags is
+    /// never reused.
+    pub build_flags: BuildConfig,
+    /// Cache entries keyed by the path of the source file that produced them.
+    pub sources: BTreeMap<PathBuf, CachedSource>,
+}
+
+/// The units produced by a single source file, along with everything their compilation depended
+/// on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachedSource {
+    pub package_name: PackageName,
+    /// Digest of the source file contents.
+    pub source_digest: String,
+    /// Digest of the named address mapping the file was compiled with.
+    pub address_digest: String,
+    pub units: Vec<CachedUnit>,
+    /// Interface digests of every module outside of this file that the units depend on.
+    pub dependencies: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachedUnit {
+    pub name: Symbol,
+    pub is_module: bool,
+    /// The module's id and interface digest; `None` for scripts.
+    pub interface: Option<(String, String)>,
+}
+
+/// The typed ASTs of the modules and scripts of every source file in the last incremental build,
+/// kept in memory across builds. Entries are only reused along with the bytecode of the same
+/// file, i.e. while the file's entry in the `ModuleCache` is unchanged.
+#[derive(Debug, Clone, Default)]
+pub struct TypedAstCache {
+    sources: BTreeMap<PathBuf, (CachedSource, CachedTypedAst)>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct CachedTypedAst {
+    modules: Vec<(ModuleIdent, T::ModuleDefinition)>,
+    scripts: Vec<(Symbol, T::Script)>,
+}
+
+/// A source file that takes part in an incremental build.
+#[derive(Debug, Clone)]
+pub(crate) struct SourceInput {
+    pub package_name: PackageName,
+    pub path: PathBuf,
+    pub source_digest: String,
+    pub address_digest: String,
+}
+
+impl ModuleCache {
+    /// Load the cache index from the build directory of `root_path`. A missing or unreadable index,
+    /// or one built with different flags, results in an empty cache.
+    pub fn load(root_path: &Path, build_flags: &BuildConfig) -> Self {
+        let index = root_path.join(CompiledPackageLayout::ModuleCache.path());
+        let cache = std::fs::read(index)
+            .ok()
+            .and_then(|bytes| serde_yaml::from_slice::<ModuleCache>(&bytes).ok());
+        match cache {
+            Some(cache) if &cache.build_flags == build_flags => cache,
+            _ => Self {
+                build_flags: build_flags.clone(),
+                sources: BTreeMap::new(),
+            },
+        }
+    }
+
+    pub fn save(&self, root_path: &Path) -> Result<()> {
+        std::fs::write(
+            root_path.join(CompiledPackageLayout::ModuleCache.path()),
+            serde_yaml::to_string(self)?,
+        )?;
+        Ok(())
+    }
+
+    /// Returns the set of inputs whose cache entry cannot be reused because the file itself or the
+    /// addresses it was compiled with changed, or because it has never been compiled.
+    pub(crate) fn changed_sources(&self, inputs: &[SourceInput]) -> BTreeSet<PathBuf> {
+        inputs
+            .iter()
+            .filter(|input| match self.sources.get(&input.path) {
+                Some(entry) => {
+                    entry.package_name != input.package_name
+                        || entry.source_digest != input.source_digest
+                        || entry.address_digest != input.address_digest
+                }
+                None => true,
+            })
+            .map(|input| input.path.clone())
+            .collect()
+    }
+
+    /// Returns the clean sources that depend on a module whose interface differs from the one
+    /// they were compiled against. `interfaces` maps module ids to their current interface digest.
+    pub(crate) fn stale_dependents(
+        &self,
+        clean: impl IntoIterator<Item = PathBuf>,
+        interfaces: &BTreeMap<String, String>,
+    ) -> BTreeSet<PathBuf> {
+        clean
+            .into_iter()
+            .filter(|path| {
+                self.sources[path]
+                    .dependencies
+                    .iter()
+                    .any(|(id, digest)| interfaces.get(id) != Some(digest))
+            })
+            .collect()
+    }
+
+    /// The interface digests of all modules recorded for `paths`.
+    pub(crate) fn interfaces_of<'a>(
+        &'a self,
+        paths: impl IntoIterator<Item = &'a PathBuf> + 'a,
+    ) -> impl Iterator<Item = (String, String)> + 'a {
+        paths.into_iter().flat_map(move |path| {
+            self.sources[path]
+                .units
+                .iter()
+                .filter_map(|unit| unit.interface.clone())
+        })
+    }
+
+    /// Paths to the bytecode of the modules recorded for `path` in the previous build `on_disk`.
+    pub(crate) fn bytecode_paths(
+        &self,
+        on_disk: &OnDiskCompiledPackage,
+        path: &Path,
+    ) -> Vec<PathBuf> {
+        let entry = &self.sources[path];
+        entry
+            .units
+            .iter()
+            .filter(|unit| unit.is_module)
+            .map(|unit| unit_path(on_disk, entry.package_name, unit))
+            .collect()
+    }
+
+    /// Decode the units recorded for `path` from the previous build `on_disk`.
+    pub(crate) fn load_units(
+        &self,
+        on_disk: &OnDiskCompiledPackage,
+        path: &Path,
+    ) -> Result<Vec<CompiledUnitWithSource>> {
+        let entry = &self.sources[path];
+        entry
+            .units
+            .iter()
+            .map(|unit| {
+                let bytecode_path = unit_path(on_disk, entry.package_name, unit);
+                let mut decoded = on_disk
+                    .decode_unit(entry.package_name, bytecode_path.to_string_lossy().as_ref())?;
+                decoded.source_path = path.to_path_buf();
+                Ok(decoded)
+            })
+            .collect()
+    }
+
+    /// Record the units compiled from `input` in the cache.
+    pub(crate) fn insert(
+        &mut self,
+        input: &SourceInput,
+        units: &[&CompiledUnitWithSource],
+        interfaces: &BTreeMap<String, String>,
+    ) {
+        let own_ids = units
+            .iter()
+            .filter_map(|unit| match &unit.unit {
+                CompiledUnit::Module(m) => Some(m.module.self_id()),
+                CompiledUnit::Script(_) => None,
+            })
+            .collect::<BTreeSet<_>>();
+        let mut dependencies = BTreeMap::new();
+        for unit in units {
+            let deps = match &unit.unit {
+                CompiledUnit::Module(m) => m.module.immediate_dependencies(),
+                CompiledUnit::Script(s) => s.script.immediate_dependencies(),
+            };
+            for id in deps.into_iter().filter(|id| !own_ids.contains(id)) {
+                let id = id.to_string();
+                // A module without a known interface is recorded with an empty digest, which
+                // never matches and thus forces recompilation next time.
+                let digest = interfaces.get(&id).cloned().unwrap_or_default();
+                dependencies.insert(id, digest);
+            }
+        }
+        let units = units
+            .iter()
+            .map(|unit| match &unit.unit {
+                CompiledUnit::Module(m) => CachedUnit {
+                    name: m.name,
+                    is_module: true,
+                    interface: Some((m.module.self_id().to_string(), interface_digest(&m.module))),
+                },
+                CompiledUnit::Script(s) => CachedUnit {
+                    name: s.name,
+                    is_module: false,
+                    interface: None,
+                },
+            })
+            .collect();
+        self.sources.insert(
+            input.path.clone(),
+            CachedSource {
+                package_name: input.package_name,
+                source_digest: input.source_digest.clone(),
+                address_digest: input.address_digest.clone(),
+                units,
+                dependencies,
+            },
+        );
+    }
+}
+
+impl TypedAstCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the typed AST of `path` is cached for the units recorded for it in `entry`.
+    pub(crate) fn covers(&self, path: &Path, entry: &CachedSource) -> bool {
+        matches!(self.sources.get(path), Some((cached, _)) if cached == entry)
+    }
+
+    /// Record the typed ASTs of the files compiled in the last build, and return the typed AST
+    /// of the whole build. `module_cache` is the cache index saved by the build, `files` and
+    /// `program` are what the compiler produced for the files which were not reused.
+    ///
+    /// Modules in `program` which did not come from a recompiled file, e.g. those read from
+    /// interface files for reused modules, are replaced by the cached typed AST of the file
+    /// they were defined in. The `dependency_order` of cached modules is the one of the build
+    /// which compiled them.
+    pub(crate) fn update(
+        &mut self,
+        module_cache: &ModuleCache,
+        files: &FilesSourceText,
+        program: T::Program,
+    ) -> T::Program {
+        let mut recompiled = files
+            .values()
+            .map(|(name, _)| PathBuf::from(name.as_str()))
+            .filter(|path| module_cache.sources.contains_key(path))
+            .map(|path| (path, CachedTypedAst::default()))
+            .collect::<BTreeMap<_, _>>();
+        let path_of = |file_hash: FileHash| {
+            files
+                .get(&file_hash)
+                .map(|(name, _)| PathBuf::from(name.as_str()))
+        };
+
+        let mut other_modules = vec![];
+        for (ident, module) in program.modules {
+            match path_of(ident.loc.file_hash()).and_then(|path| recompiled.get_mut(&path)) {
+                Some(ast) => ast.modules.push((ident, module)),
+                None => other_modules.push((ident, module)),
+            }
+        }
+        let mut other_scripts = vec![];
+        for (name, script) in program.scripts {
+            match path_of(script.loc.file_hash()).and_then(|path| recompiled.get_mut(&path)) {
+                Some(ast) => ast.scripts.push((name, script)),
+                None => other_scripts.push((name, script)),
+            }
+        }
+
+        self.sources
+            .retain(|path, _| module_cache.sources.contains_key(path));
+        for (path, ast) in recompiled {
+            let entry = module_cache.sources[&path].clone();
+            self.sources.insert(path, (entry, ast));
+        }
+
+        let mut modules = UniqueMap::new();
+        let mut scripts = BTreeMap::new();
+        for (_, ast) in self.sources.values() {
+            for (ident, module) in &ast.modules {
+                modules
+                    .add(*ident, module.clone())
+                    .expect("modules are defined in a single file");
+            }
+            for (name, script) in &ast.scripts {
+                scripts.insert(*name, script.clone());
+            }
+        }
+        for (ident, module) in other_modules {
+            if !modules.contains_key(&ident) {
+                modules
+                    .add(ident, module)
+                    .expect("modules are defined in a single file");
+            }
+        }
+        for (name, script) in other_scripts {
+            scripts.entry(name).or_insert(script);
+        }
+        T::Program { modules, scripts }
+    }
+}
+
+impl SourceInput {
+    /// Collect the source files of `package_paths`, computing the digests that key the cache.
+    pub(crate) fn from_package_paths(package_paths: &[PackagePaths]) -> Result<Vec<Self>> {
+        let mut inputs = vec![];
+        for package in package_paths {
+            let package_name = package
+                .name
+                .expect("packages built by the package system are always named");
+            let address_digest = address_digest(&package.named_address_map);
+            for path in &package.paths {
+                let path = PathBuf::from(path.as_str());
+                let source_digest = format!("{:X}", Sha256::digest(&std::fs::read(&path)?));
+                inputs.push(Self {
+                    package_name,
+                    path,
+                    source_digest,
+                    address_digest: address_digest.clone(),
+                })
+            }
+        }
+        Ok(inputs)
+    }
+}
+
+/// The digest of the publicly visible interface of `module`. Dependents of a module only need to
+/// be recompiled when this changes.
+pub fn interface_digest(module: &CompiledModule) -> String {
+    let (_, interface) = write_module_to_string(&BTreeMap::<ModuleId, String>::new(), module)
+        .expect("interface generation cannot fail for a compiled module");
+    format!("{:X}", Sha256::digest(interface.as_bytes()))
+}
+
+fn address_digest(named_address_map: &BTreeMap<Symbol, NumericalAddress>) -> String {
+    let mut hasher = Sha256::new();
+    for (name, addr) in named_address_map {
+        hasher.update(format!("{}={};", name, addr).as_bytes());
+    }
+    format!("{:X}", hasher.finalize())
+}
+
+fn unit_path(
+    on_disk: &OnDiskCompiledPackage,
+    package_name: PackageName,
+    unit: &CachedUnit,
+) -> PathBuf {
+    let category = if unit.is_module {
+        CompiledPackageLayout::CompiledModules
+    } else {
+        CompiledPackageLayout::CompiledScripts
+    };
+    let package_dir = if on_disk.package.compiled_package_info.package_name == package_name {
+        PathBuf::new()
+    } else {
+        CompiledPackageLayout::Dependencies
+            .path()
+            .join(package_name.as_str())
+    };
+    on_disk
+        .root_path
+        .join(category.path())
+        .join(package_dir)
+        .join(unit.name.as_str())
+        .with_extension(MOVE_COMPILED_EXTENSION)
+}
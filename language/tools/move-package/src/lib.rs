@@ -121,6 +121,16 @@ pub struct BuildConfig {
     #[clap(name = "force-recompilation", long = "force", global = true)]
     pub force_recompilation: bool,
 
+    /// Reuse the compiled units of source files whose contents, and the interfaces of the modules
+    /// they depend on, did not change since the last incremental build
+    #[clap(name = "incremental", long = "incremental", global = true)]
+    pub incremental: bool,
+
+    /// Optimize the bytecode of the package's modules for static gas cost, then code size, and
+    /// report how the optimized functions changed
+    #[clap(name = "optimize", long = "optimize", global = true)]
+    pub optimize: bool,
+
     /// Optional location to save the lock file to, if package resolution succeeds.
     #[clap(skip)]
     pub lock_file: Option<PathBuf>,
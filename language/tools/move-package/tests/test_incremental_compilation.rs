@@ -0,0 +1,205 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use move_compiler::{shared::Identifier, typing::ast as T, PASS_TYPING};
+use move_package::{
+    compilation::{
+        build_plan::BuildPlan, compiled_package::CompiledPackage, module_cache::TypedAstCache,
+        package_layout::CompiledPackageLayout,
+    },
+    BuildConfig,
+};
+use std::{fs, path::Path};
+use tempfile::tempdir;
+
+const MANIFEST: &str = r#"
+[package]
+name = "Incremental"
+version = "0.0.0"
+
+[addresses]
+Incremental = "0x42"
+"#;
+
+const A: &str = r#"
+module Incremental::A {
+    public fun value(): u64 { helper() }
+    fun helper(): u64 { 1 }
+}
+"#;
+
+const B: &str = r#"
+module Incremental::B {
+    use Incremental::A;
+    public fun value(): u64 { A::value() + 1 }
+}
+"#;
+
+const C: &str = r#"
+module Incremental::C {
+    public fun value(): u64 { 3 }
+}
+"#;
+
+fn build(path: &Path) -> (CompiledPackage, String) {
+    let mut output = Vec::new();
+    let package = BuildConfig {
+        incremental: true,
+        ..Default::default()
+    }
+    .compile_package(path, &mut output)
+    .unwrap();
+    (package, String::from_utf8(output).unwrap())
+}
+
+/// Builds the package through a driver producing typed ASTs, and returns the names of the modules
+/// the driver compiled and those in the typed AST of the package.
+fn build_typed(path: &Path, typed_ast_cache: &mut TypedAstCache) -> (Vec<String>, Vec<String>) {
+    let resolution_graph = BuildConfig {
+        incremental: true,
+        ..Default::default()
+    }
+    .resolution_graph_for_package(path, &mut Vec::new())
+    .unwrap();
+    let mut compiled = vec![];
+    let (_, program) = BuildPlan::create(resolution_graph)
+        .unwrap()
+        .compile_with_typed_ast_driver(&mut Vec::new(), None, typed_ast_cache, |compiler| {
+            let (files, res) = compiler.run::<PASS_TYPING>()?;
+            let (_, compiler) = res.map_err(|_| anyhow::anyhow!("typing failed"))?;
+            let (compiler, program) = compiler.into_ast();
+            compiled = source_modules(&program);
+            let (units, _) = compiler
+                .at_typing(program.clone())
+                .build()
+                .map_err(|_| anyhow::anyhow!("compilation failed"))?;
+            Ok((files, units, program))
+        })
+        .unwrap();
+    (compiled, source_modules(&program))
+}
+
+fn source_modules(program: &T::Program) -> Vec<String> {
+    program
+        .modules
+        .key_cloned_iter()
+        .filter(|(_, module)| module.is_source_module)
+        .map(|(ident, _)| ident.value.module.value().to_string())
+        .collect()
+}
+
+fn module_bytes(package: &CompiledPackage, name: &str) -> Vec<u8> {
+    package
+        .get_module_by_name_from_root(name)
+        .unwrap()
+        .unit
+        .serialize(None)
+}
+
+#[test]
+fn reuses_unchanged_modules() {
+    let dir = tempdir().unwrap();
+    let path = dir.path();
+    fs::create_dir_all(path.join("sources")).unwrap();
+    fs::write(path.join("Move.toml"), MANIFEST).unwrap();
+    fs::write(path.join("sources").join("A.move"), A).unwrap();
+    fs::write(path.join("sources").join("B.move"), B).unwrap();
+    fs::write(path.join("sources").join("C.move"), C).unwrap();
+
+    let (first, output) = build(path);
+    assert!(output.contains("REUSING 0 of 3 source files"), "{}", output);
+    assert!(path
+        .join(CompiledPackageLayout::Root.path())
+        .join("Incremental")
+        .join(CompiledPackageLayout::ModuleCache.path())
+        .is_file());
+
+    // Nothing changed, so everything is reused.
+    let (second, output) = build(path);
+    assert!(output.contains("REUSING 3 of 3 source files"), "{}", output);
+    for name in ["A", "B", "C"] {
+        assert_eq!(module_bytes(&first, name), module_bytes(&second, name));
+    }
+
+    // A change to a private function of `A` does not affect the interface `B` is compiled against.
+    fs::write(
+        path.join("sources").join("A.move"),
+        A.replace("{ 1 }", "{ 2 }"),
+    )
+    .unwrap();
+    let (third, output) = build(path);
+    assert!(output.contains("REUSING 2 of 3 source files"), "{}", output);
+    assert_ne!(module_bytes(&second, "A"), module_bytes(&third, "A"));
+    assert_eq!(module_bytes(&second, "B"), module_bytes(&third, "B"));
+
+    // Changing the public interface of `A` forces `B` to be recompiled too.
+    fs::write(
+        path.join("sources").join("A.move"),
+        A.replace(
+            "public fun value(): u64",
+            "public fun value(): u64 { 0 }\n    public fun other(): u64",
+        ),
+    )
+    .unwrap();
+    let (_, output) = build(path);
+    assert!(output.contains("REUSING 1 of 3 source files"), "{}", output);
+
+    // A failing change is reported and does not poison the cache.
+    fs::write(
+        path.join("sources").join("C.move"),
+        "module Incremental::C {",
+    )
+    .unwrap();
+    assert!(BuildConfig {
+        incremental: true,
+        ..Default::default()
+    }
+    .compile_package_no_exit(path, &mut Vec::new())
+    .is_err());
+    fs::write(path.join("sources").join("C.move"), C).unwrap();
+    let (_, output) = build(path);
+    assert!(output.contains("REUSING 3 of 3 source files"), "{}", output);
+}
+
+#[test]
+fn reuses_typed_asts() {
+    let dir = tempdir().unwrap();
+    let path = dir.path();
+    fs::create_dir_all(path.join("sources")).unwrap();
+    fs::write(path.join("Move.toml"), MANIFEST).unwrap();
+    fs::write(path.join("sources").join("A.move"), A).unwrap();
+    fs::write(path.join("sources").join("B.move"), B).unwrap();
+    fs::write(path.join("sources").join("C.move"), C).unwrap();
+    let all = vec!["A".to_string(), "B".to_string(), "C".to_string()];
+
+    let mut typed_ast_cache = TypedAstCache::new();
+    assert_eq!(
+        build_typed(path, &mut typed_ast_cache),
+        (all.clone(), all.clone())
+    );
+
+    // Nothing changed, so the typed ASTs of all modules come from the cache.
+    assert_eq!(
+        build_typed(path, &mut typed_ast_cache),
+        (vec![], all.clone())
+    );
+
+    // Bytecode cached on disk is not reused without the typed AST of the same file.
+    assert_eq!(
+        build_typed(path, &mut TypedAstCache::new()),
+        (all.clone(), all.clone())
+    );
+
+    fs::write(
+        path.join("sources").join("C.move"),
+        C.replace("{ 3 }", "{ 4 }"),
+    )
+    .unwrap();
+    let mut fresh_cache = TypedAstCache::new();
+    build_typed(path, &mut fresh_cache);
+    fs::write(path.join("sources").join("C.move"), C).unwrap();
+    assert_eq!(
+        build_typed(path, &mut fresh_cache),
+        (vec!["C".to_string()], all)
+    );
+}
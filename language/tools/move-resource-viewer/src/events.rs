@@ -0,0 +1,300 @@
+// Copyright (c) The Move Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! An index of the events emitted by a sequence of transactions, which can be queried by event
+//! handle (GUID), struct type, sending transaction and field values, and decoded with a
+//! `MoveValueAnnotator`.
+
+use crate::{AnnotatedMoveValue, MoveValueAnnotator};
+use anyhow::{anyhow, bail, Result};
+use move_core_types::{
+    account_address::AccountAddress,
+    effects::Event,
+    identifier::Identifier,
+    language_storage::{StructTag, TypeTag},
+    resolver::MoveResolver,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+/// An event, along with the transaction which emitted it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexedEvent {
+    /// The index of the transaction which emitted the event, in the order transactions were
+    /// added to the index.
+    pub txn: u64,
+    pub guid: Vec<u8>,
+    pub seq_num: u64,
+    pub type_: TypeTag,
+    pub data: Vec<u8>,
+}
+
+/// The serialized form of an `EventIndex`, from which the lookup tables are rebuilt.
+#[derive(Serialize, Deserialize)]
+struct EventLog {
+    num_txns: u64,
+    events: Vec<IndexedEvent>,
+}
+
+/// Identifies a struct type regardless of its type arguments, so that a filter on a generic
+/// event type matches all its instantiations.
+type StructName = (AccountAddress, Identifier, Identifier);
+
+fn struct_name(tag: &StructTag) -> StructName {
+    (tag.address, tag.module.clone(), tag.name.clone())
+}
+
+/// The events emitted by a sequence of transactions, indexed by GUID, struct type and
+/// transaction.
+#[derive(Clone, Debug, Default)]
+pub struct EventIndex {
+    num_txns: u64,
+    events: Vec<IndexedEvent>,
+    by_guid: BTreeMap<Vec<u8>, Vec<usize>>,
+    by_struct: BTreeMap<StructName, Vec<usize>>,
+    by_txn: BTreeMap<u64, Vec<usize>>,
+}
+
+impl EventIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Deserializes an index serialized by `to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let EventLog { num_txns, events } = bcs::from_bytes(bytes)?;
+        let mut index = Self {
+            num_txns,
+            ..Self::default()
+        };
+        for event in events {
+            index.insert(event);
+        }
+        Ok(index)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(bcs::to_bytes(&EventLog {
+            num_txns: self.num_txns,
+            events: self.events.clone(),
+        })?)
+    }
+
+    /// Returns the number of transactions added, including those which emitted no event.
+    pub fn num_txns(&self) -> u64 {
+        self.num_txns
+    }
+
+    /// Returns all events, in the order they were emitted.
+    pub fn events(&self) -> &[IndexedEvent] {
+        &self.events
+    }
+
+    /// Returns the GUIDs of the event handles which emitted events.
+    pub fn guids(&self) -> impl Iterator<Item = &[u8]> {
+        self.by_guid.keys().map(|guid| guid.as_slice())
+    }
+
+    /// Adds the events emitted by the next transaction, and returns the index of the
+    /// transaction.
+    pub fn add_transaction(&mut self, events: impl IntoIterator<Item = Event>) -> u64 {
+        let txn = self.num_txns;
+        self.num_txns += 1;
+        for (guid, seq_num, type_, data) in events {
+            self.insert(IndexedEvent {
+                txn,
+                guid,
+                seq_num,
+                type_,
+                data,
+            });
+        }
+        txn
+    }
+
+    fn insert(&mut self, event: IndexedEvent) {
+        let idx = self.events.len();
+        self.by_guid
+            .entry(event.guid.clone())
+            .or_default()
+            .push(idx);
+        if let TypeTag::Struct(tag) = &event.type_ {
+            self.by_struct
+                .entry(struct_name(tag))
+                .or_default()
+                .push(idx);
+        }
+        self.by_txn.entry(event.txn).or_default().push(idx);
+        self.events.push(event);
+    }
+
+    /// Returns the events matching `filter`, in the order they were emitted, decoded with
+    /// `annotator`.
+    pub fn query<T: MoveResolver + ?Sized>(
+        &self,
+        filter: &EventFilter,
+        annotator: &MoveValueAnnotator<T>,
+    ) -> Result<Vec<DecodedEvent>> {
+        // Start from the smallest of the lookup tables the filter can use.
+        let mut candidates: Option<&[usize]> = None;
+        let mut narrow = |indices: Option<&Vec<usize>>| {
+            let indices = indices.map_or(&[][..], |indices| indices.as_slice());
+            if candidates.map_or(true, |c| indices.len() < c.len()) {
+                candidates = Some(indices);
+            }
+        };
+        if let Some(guid) = &filter.guid {
+            narrow(self.by_guid.get(guid));
+        }
+        if let Some(TypeTag::Struct(tag)) = &filter.type_ {
+            narrow(self.by_struct.get(&struct_name(tag)));
+        }
+        if let Some(txn) = filter.txn {
+            narrow(self.by_txn.get(&txn));
+        }
+        let candidates: Box<dyn Iterator<Item = &IndexedEvent>> = match candidates {
+            Some(indices) => Box::new(indices.iter().map(|idx| &self.events[*idx])),
+            None => Box::new(self.events.iter()),
+        };
+
+        let mut decoded = vec![];
+        for event in candidates {
+            if !filter.matches_event(event) {
+                continue;
+            }
+            let value = annotator.view_value(&event.type_, &event.data)?;
+            if filter.fields.iter().all(|field| field.matches(&value)) {
+                decoded.push(DecodedEvent { event, value });
+            }
+        }
+        Ok(decoded)
+    }
+}
+
+/// Selects events. Events match if they match all the conditions set.
+#[derive(Clone, Debug, Default)]
+pub struct EventFilter {
+    /// The GUID of the event handle which emitted the event.
+    pub guid: Option<Vec<u8>>,
+    /// The type of the event. A struct type without type arguments matches all instantiations of
+    /// the struct.
+    pub type_: Option<TypeTag>,
+    /// The index of the transaction which emitted the event.
+    pub txn: Option<u64>,
+    /// Conditions on the fields of the event.
+    pub fields: Vec<FieldFilter>,
+}
+
+impl EventFilter {
+    fn matches_event(&self, event: &IndexedEvent) -> bool {
+        let type_matches = match (&self.type_, &event.type_) {
+            (None, _) => true,
+            (Some(TypeTag::Struct(expected)), TypeTag::Struct(actual))
+                if expected.type_params.is_empty() =>
+            {
+                struct_name(expected) == struct_name(actual)
+            }
+            (Some(expected), actual) => expected == actual,
+        };
+        type_matches
+            && self.guid.as_ref().map_or(true, |guid| guid == &event.guid)
+            && self.txn.map_or(true, |txn| txn == event.txn)
+    }
+}
+
+/// A condition on the value of a field, written `field=value`, where nested fields are
+/// separated by dots. Integers match with or without a type suffix, addresses as hex literals,
+/// and byte vectors as hex literals or strings. Other values match their textual form.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldFilter {
+    pub path: Vec<Identifier>,
+    pub value: String,
+}
+
+impl FieldFilter {
+    fn matches(&self, value: &AnnotatedMoveValue) -> bool {
+        let mut value = value;
+        for name in &self.path {
+            value = match value {
+                AnnotatedMoveValue::Struct(s) => {
+                    match s.value.iter().find(|(field, _)| field == name) {
+                        Some((_, field_value)) => field_value,
+                        None => return false,
+                    }
+                }
+                _ => return false,
+            }
+        }
+        let expected = self.value.as_str();
+        let matches = match value {
+            AnnotatedMoveValue::U8(n) => expected == n.to_string(),
+            AnnotatedMoveValue::U16(n) => expected == n.to_string(),
+            AnnotatedMoveValue::U32(n) => expected == n.to_string(),
+            AnnotatedMoveValue::U64(n) => expected == n.to_string(),
+            AnnotatedMoveValue::U128(n) => expected == n.to_string(),
+            AnnotatedMoveValue::U256(n) => expected == n.to_string(),
+            AnnotatedMoveValue::Address(a) => {
+                AccountAddress::from_hex_literal(expected).map_or(false, |expected| &expected == a)
+            }
+            AnnotatedMoveValue::Bytes(bytes) => match expected.strip_prefix("0x") {
+                Some(hex) => hex::decode(hex).map_or(false, |expected| &expected == bytes),
+                None => expected.as_bytes() == bytes.as_slice(),
+            },
+            _ => false,
+        };
+        // values also match the form they are printed in
+        matches || expected == value.to_string()
+    }
+}
+
+impl FromStr for FieldFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (path, value) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Expected a field filter of the form `field=value`: {}", s))?;
+        let path = path
+            .trim()
+            .split('.')
+            .map(Identifier::new)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            path,
+            value: value.trim().to_string(),
+        })
+    }
+}
+
+/// Parses a GUID written as a hex string, with or without a `0x` prefix.
+pub fn parse_guid(s: &str) -> Result<Vec<u8>> {
+    let hex = s.strip_prefix("0x").unwrap_or(s);
+    match hex::decode(hex) {
+        Ok(guid) if !guid.is_empty() => Ok(guid),
+        _ => bail!("Expected a GUID written in hex: {}", s),
+    }
+}
+
+/// An event matching a query, with its decoded value.
+#[derive(Clone, Debug)]
+pub struct DecodedEvent<'a> {
+    pub event: &'a IndexedEvent,
+    pub value: AnnotatedMoveValue,
+}
+
+impl<'a> Display for DecodedEvent<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "txn {} guid 0x{} seq {}:",
+            self.event.txn,
+            hex::encode(&self.event.guid),
+            self.event.seq_num
+        )?;
+        write!(f, "{}", self.value)
+    }
+}
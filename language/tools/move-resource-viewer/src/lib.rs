@@ -29,6 +29,7 @@ use std::{
     rc::Rc,
 };
 
+pub mod events;
 mod fat_type;
 mod module_cache;
 mod resolver;
@@ -80,6 +80,11 @@ pub struct UnitTestingConfig {
     )]
     pub report_stacktrace_on_abort: bool,
 
+    /// Show the stack trace of a failing test along with the values of the arguments and locals
+    /// of each frame
+    #[clap(name = "report_stacktrace_locals", long = "stacktrace_locals")]
+    pub report_stacktrace_locals: bool,
+
     /// Ignore compiler's warning, and continue run tests
     #[clap(name = "ignore_compile_warnings", long = "ignore_compile_warnings")]
     pub ignore_compile_warnings: bool,
@@ -107,6 +112,11 @@ pub struct UnitTestingConfig {
     #[clap(long = "stackless")]
     pub check_stackless_vm: bool,
 
+    /// Also run the tests with the stackless bytecode interpreter, checking the specifications
+    /// of the functions called against the concrete executions.
+    #[clap(long = "check-specs")]
+    pub check_specs: bool,
+
     /// Verbose mode
     #[clap(short = 'v', long = "verbose")]
     pub verbose: bool,
@@ -140,10 +150,12 @@ impl UnitTestingConfig {
             report_statistics: false,
             report_storage_on_error: false,
             report_stacktrace_on_abort: false,
+            report_stacktrace_locals: false,
             ignore_compile_warnings: false,
             source_files: vec![],
             dep_files: vec![],
             check_stackless_vm: false,
+            check_specs: false,
             verbose: false,
             list: false,
             named_address_values: vec![],
@@ -244,9 +256,11 @@ impl UnitTestingConfig {
             self.gas_limit.unwrap_or(DEFAULT_EXECUTION_BOUND),
             self.num_threads,
             self.check_stackless_vm,
+            self.check_specs,
             self.verbose,
             self.report_storage_on_error,
             self.report_stacktrace_on_abort,
+            self.report_stacktrace_locals,
             test_plan,
             native_function_table,
             cost_table,
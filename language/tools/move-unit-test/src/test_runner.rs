@@ -38,13 +38,22 @@ use move_stackless_bytecode_interpreter::{
     shared::bridge::{adapt_move_vm_change_set, adapt_move_vm_result},
     StacklessBytecodeInterpreter,
 };
-use move_vm_runtime::{move_vm::MoveVM, native_functions::NativeFunctionTable};
+use move_vm_runtime::{
+    config::VMConfig, move_vm::MoveVM, native_functions::NativeFunctionTable,
+    verified_module_cache::InMemoryVerifiedModuleCache,
+};
 use move_vm_test_utils::{
     gas_schedule::{zero_cost_schedule, CostTable, Gas, GasCost, GasStatus},
     InMemoryStorage,
 };
 use rayon::prelude::*;
-use std::{collections::BTreeMap, io::Write, marker::Send, sync::Mutex, time::Instant};
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    marker::Send,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
 
 use move_vm_runtime::native_extensions::NativeContextExtensions;
 #[cfg(feature = "evm-backend")]
@@ -61,13 +70,17 @@ use {
 pub struct SharedTestingConfig {
     save_storage_state_on_failure: bool,
     report_stacktrace_on_abort: bool,
+    report_stacktrace_locals: bool,
     execution_bound: u64,
     cost_table: CostTable,
     native_function_table: NativeFunctionTable,
     starting_storage_state: InMemoryStorage,
+    // Each test runs in a fresh VM, which would otherwise verify the modules it loads again.
+    verified_module_cache: Arc<InMemoryVerifiedModuleCache>,
     source_files: Vec<String>,
     named_address_values: BTreeMap<String, NumericalAddress>,
     check_stackless_vm: bool,
+    check_specs: bool,
     verbose: bool,
     record_writeset: bool,
 
@@ -143,9 +156,11 @@ impl TestRunner {
         execution_bound: u64,
         num_threads: usize,
         check_stackless_vm: bool,
+        check_specs: bool,
         verbose: bool,
         save_storage_state_on_failure: bool,
         report_stacktrace_on_abort: bool,
+        report_stacktrace_locals: bool,
         tests: TestPlan,
         // TODO: maybe we should require the clients to always pass in a list of native functions so
         // we don't have to make assumptions about their gas parameters.
@@ -172,7 +187,9 @@ impl TestRunner {
             testing_config: SharedTestingConfig {
                 save_storage_state_on_failure,
                 report_stacktrace_on_abort,
+                report_stacktrace_locals,
                 starting_storage_state,
+                verified_module_cache: Arc::new(InMemoryVerifiedModuleCache::new()),
                 execution_bound,
                 native_function_table,
                 // TODO: our current implementation uses a unit cost table to prevent programs from
@@ -183,6 +200,7 @@ impl TestRunner {
                 cost_table: cost_table.unwrap_or_else(unit_cost_table),
                 source_files,
                 check_stackless_vm,
+                check_specs,
                 verbose,
                 named_address_values,
                 record_writeset,
@@ -283,7 +301,15 @@ impl SharedTestingConfig {
         VMResult<Vec<Vec<u8>>>,
         TestRunInfo,
     ) {
-        let move_vm = MoveVM::new(self.native_function_table.clone()).unwrap();
+        let move_vm = MoveVM::new_with_config(
+            self.native_function_table.clone(),
+            VMConfig {
+                verified_module_cache: Some(self.verified_module_cache.clone()),
+                stack_trace_locals: self.report_stacktrace_locals,
+                ..Default::default()
+            },
+        )
+        .unwrap();
         let extensions = extensions::new_extensions();
         let mut session =
             move_vm.new_session_with_extensions(&self.starting_storage_state, extensions);
@@ -304,7 +330,7 @@ impl SharedTestingConfig {
                 .map(|(bytes, _layout)| bytes)
                 .collect()
         });
-        if !self.report_stacktrace_on_abort {
+        if !self.report_stacktrace_on_abort && !self.report_stacktrace_locals {
             if let Err(err) = &mut return_result {
                 err.remove_exec_state();
             }
@@ -389,7 +415,7 @@ impl SharedTestingConfig {
             .cloned()
             .collect::<Vec<_>>();
 
-        let stackless_model = if self.check_stackless_vm {
+        let stackless_model = if self.check_stackless_vm || self.check_specs {
             let model = run_model_builder_with_options_and_compilation_flags(
                 vec![PackagePaths {
                     name: None,
@@ -427,7 +453,7 @@ impl SharedTestingConfig {
                 );
             }
 
-            if self.check_stackless_vm {
+            if self.check_stackless_vm || self.check_specs {
                 let (stackless_vm_change_set, stackless_vm_result, _, prop_check_result) = self
                     .execute_via_stackless_vm(
                         stackless_model.as_ref().unwrap(),
@@ -438,8 +464,11 @@ impl SharedTestingConfig {
                 let move_vm_result = adapt_move_vm_result(exec_result.clone());
                 let move_vm_change_set =
                     adapt_move_vm_change_set(cs_result.clone(), &self.starting_storage_state);
-                if stackless_vm_result != move_vm_result
-                    || stackless_vm_change_set != move_vm_change_set
+                // When only checking specs, differences between the two executions are not
+                // reported; the Move VM result remains the one the test is judged by.
+                if self.check_stackless_vm
+                    && (stackless_vm_result != move_vm_result
+                        || stackless_vm_change_set != move_vm_change_set)
                 {
                     output.fail(function_name);
                     stats.test_failure(